@@ -0,0 +1,287 @@
+//! Test network faucet (`POST /faucet`).
+//!
+//! Each request crafts a transfer from the configured faucet account,
+//! signs it with the faucet key and hands it to the node's transaction
+//! submission path, the same one every other transaction takes. Nonces
+//! start from the faucet account's nonce in chain state.
+//! An address, and the client asking for it, must wait out a cooldown
+//! between grants, and a global per-minute cap bounds how fast the faucet
+//! can be drained. Verifiers run before anything is sent; CAPTCHA checks or
+//! calls to an external webhook plug in there. The faucet refuses to run on
+//! mainnet unless explicitly allowed.
+
+use crate::{ApiResponse, HandlerError, Result};
+use cc_core::crypto::{CCKeypair, CCPublicKey};
+use cc_core::transaction::Transaction;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Faucet settings
+#[derive(Debug, Clone)]
+pub struct FaucetConfig {
+    pub chain_id: String,
+    /// Address (hex public key) grants are sent from; must be the faucet key
+    pub faucet_address: String,
+    /// Amount granted per request
+    pub amount: u64,
+    pub fee: u64,
+    /// Wait between grants to the same address or client
+    pub cooldown: Duration,
+    /// Grants across all addresses per minute
+    pub max_grants_per_minute: usize,
+    /// Run even if `chain_id` names a mainnet
+    pub allow_mainnet: bool,
+}
+
+impl Default for FaucetConfig {
+    fn default() -> Self {
+        Self {
+            chain_id: "cc-chain-testnet".to_string(),
+            faucet_address: String::new(),
+            amount: 10_000,
+            fee: 200,
+            cooldown: Duration::from_secs(24 * 60 * 60),
+            max_grants_per_minute: 30,
+            allow_mainnet: false,
+        }
+    }
+}
+
+/// `POST /faucet` request body
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FaucetRequest {
+    pub address: String,
+    #[serde(default)]
+    pub captcha_token: Option<String>,
+}
+
+/// A granted faucet request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FaucetResponse {
+    pub transaction_hash: String,
+    pub amount: u64,
+    /// When the address may ask again, in seconds since the epoch
+    pub next_request_at: u64,
+}
+
+/// The node's account state and transaction submission path
+pub trait TransactionSubmitter: Send + Sync {
+    /// Next nonce the chain expects from `account`
+    fn account_nonce(&self, account: &CCPublicKey) -> Result<u64>;
+
+    /// Validate a signed transaction and add it to the mempool
+    fn submit(&self, transaction: Transaction) -> Result<()>;
+}
+
+/// Check run on every request before funds are sent; an error is the
+/// reason the request is refused
+pub trait FaucetVerifier: Send + Sync {
+    fn verify(&self, request: &FaucetRequest, client: &str) -> std::result::Result<(), String>;
+}
+
+impl<F> FaucetVerifier for F
+where
+    F: Fn(&FaucetRequest, &str) -> std::result::Result<(), String> + Send + Sync,
+{
+    fn verify(&self, request: &FaucetRequest, client: &str) -> std::result::Result<(), String> {
+        self(request, client)
+    }
+}
+
+/// Requires a CAPTCHA token and has `check` validate it with the provider
+pub struct CaptchaVerifier<F> {
+    check: F,
+}
+
+impl<F: Fn(&str, &str) -> bool + Send + Sync> CaptchaVerifier<F> {
+    /// `check(token, client)` returns whether the provider accepted the token
+    pub fn new(check: F) -> Self {
+        Self { check }
+    }
+}
+
+impl<F: Fn(&str, &str) -> bool + Send + Sync> FaucetVerifier for CaptchaVerifier<F> {
+    fn verify(&self, request: &FaucetRequest, client: &str) -> std::result::Result<(), String> {
+        match request.captcha_token.as_deref() {
+            None | Some("") => Err("CAPTCHA token is required".to_string()),
+            Some(token) if (self.check)(token, client) => Ok(()),
+            Some(_) => Err("CAPTCHA verification failed".to_string()),
+        }
+    }
+}
+
+/// Faucet handler
+pub struct FaucetHandler {
+    config: FaucetConfig,
+    keypair: CCKeypair,
+    submitter: Arc<dyn TransactionSubmitter>,
+    verifiers: Vec<Box<dyn FaucetVerifier>>,
+    /// Last grant to each address and client
+    last_grants: HashMap<String, u64>,
+    /// Times of grants in the last minute
+    recent_grants: VecDeque<u64>,
+    /// Nonce after our last submission, ahead of chain state while it is pending
+    next_nonce: u64,
+}
+
+impl FaucetHandler {
+    /// Faucet sending from `keypair` through `submitter`; fails if
+    /// `faucet_address` is not the keypair's public key
+    pub fn new(config: FaucetConfig, keypair: CCKeypair, submitter: Arc<dyn TransactionSubmitter>) -> Result<Self> {
+        if parse_address(&config.faucet_address) != Some(keypair.public_key()) {
+            return Err(HandlerError::InvalidParameter {
+                param: "faucet_address".to_string(),
+                reason: "Must be the public key of the faucet keypair".to_string(),
+            });
+        }
+        Ok(Self {
+            config,
+            keypair,
+            submitter,
+            verifiers: Vec::new(),
+            last_grants: HashMap::new(),
+            recent_grants: VecDeque::new(),
+            next_nonce: 0,
+        })
+    }
+
+    pub fn with_verifier(mut self, verifier: impl FaucetVerifier + 'static) -> Self {
+        self.verifiers.push(Box::new(verifier));
+        self
+    }
+
+    /// Whether the faucet serves requests on this chain
+    pub fn is_enabled(&self) -> bool {
+        self.config.allow_mainnet || !self.config.chain_id.to_ascii_lowercase().contains("mainnet")
+    }
+
+    /// `POST /faucet` from `client`, the caller's IP address
+    pub fn request_funds(&mut self, request: FaucetRequest, client: &str) -> Result<ApiResponse<FaucetResponse>> {
+        if !self.is_enabled() {
+            return Err(HandlerError::ServiceUnavailable(format!(
+                "Faucet is disabled on {}",
+                self.config.chain_id
+            )));
+        }
+
+        let recipient = parse_address(&request.address).ok_or_else(|| HandlerError::InvalidParameter {
+            param: "address".to_string(),
+            reason: "Expected a hex-encoded 32-byte public key".to_string(),
+        })?;
+        let address = hex::encode(recipient.0);
+
+        let now = unix_now();
+        let cooldown = self.config.cooldown.as_secs();
+        let client_key = format!("client:{}", client);
+        for key in [&address, &client_key] {
+            if let Some(last) = self.last_grants.get(key) {
+                let retry_after = (last + cooldown).saturating_sub(now);
+                if retry_after > 0 {
+                    return Err(HandlerError::RateLimited { retry_after });
+                }
+            }
+        }
+        while self.recent_grants.front().is_some_and(|at| *at + 60 <= now) {
+            self.recent_grants.pop_front();
+        }
+        if self.recent_grants.len() >= self.config.max_grants_per_minute {
+            let retry_after = self.recent_grants.front().map_or(60, |at| (at + 60).saturating_sub(now));
+            return Err(HandlerError::RateLimited {
+                retry_after: retry_after.max(1),
+            });
+        }
+
+        for verifier in &self.verifiers {
+            verifier.verify(&request, client).map_err(HandlerError::Forbidden)?;
+        }
+
+        let sender = self.keypair.public_key();
+        let nonce = self.submitter.account_nonce(&sender)?.max(self.next_nonce);
+        let mut transaction = Transaction::new(sender, recipient, self.config.amount, self.config.fee, nonce, Vec::new());
+        transaction.sign(&self.keypair);
+        let transaction_hash = format!("0x{}", hex::encode(transaction.hash()));
+        self.submitter.submit(transaction)?;
+        self.next_nonce = nonce + 1;
+
+        self.last_grants.insert(address, now);
+        self.last_grants.insert(client_key, now);
+        self.recent_grants.push_back(now);
+        Ok(ApiResponse::success(FaucetResponse {
+            transaction_hash,
+            amount: self.config.amount,
+            next_request_at: now + cooldown,
+        }))
+    }
+
+    /// OpenAPI path item describing `POST /faucet`
+    pub fn openapi() -> Value {
+        let error = |description: &str| json!({ "description": description, "content": { "application/json": { "schema": { "$ref": "#/components/schemas/ApiResponse" } } } });
+        json!({
+            "/faucet": {
+                "post": {
+                    "summary": "Request test network funds",
+                    "description": "Sends a fixed grant from the faucet account to an address. Each address and client may ask once per cooldown, and the faucet is disabled on mainnet.",
+                    "operationId": "requestFaucetFunds",
+                    "tags": ["faucet"],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "type": "object",
+                                    "required": ["address"],
+                                    "properties": {
+                                        "address": { "type": "string", "pattern": "^(0x)?[0-9a-fA-F]{64}$", "description": "Recipient public key" },
+                                        "captcha_token": { "type": "string", "description": "Required when CAPTCHA verification is enabled" }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "Funds sent",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "object",
+                                        "properties": {
+                                            "transaction_hash": { "type": "string" },
+                                            "amount": { "type": "integer", "format": "uint64" },
+                                            "next_request_at": { "type": "integer", "format": "uint64", "description": "Seconds since the epoch" }
+                                        }
+                                    }
+                                }
+                            }
+                        },
+                        "400": error("Malformed address"),
+                        "403": error("Verification failed"),
+                        "429": {
+                            "description": "Address or client is cooling down, or the faucet is at its rate limit",
+                            "headers": { "Retry-After": { "schema": { "type": "integer" } } }
+                        },
+                        "503": error("Faucet disabled on this network")
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Public key from hex, with or without a `0x` prefix
+fn parse_address(address: &str) -> Option<CCPublicKey> {
+    let address = address.trim();
+    let hex = address.strip_prefix("0x").unwrap_or(address);
+    let bytes: [u8; 32] = hex::decode(hex).ok()?.try_into().ok()?;
+    Some(CCPublicKey(bytes))
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
@@ -0,0 +1,183 @@
+//! Governance handlers: proposals, their tallies and the votes cast on
+//! them.
+
+use crate::{paginate, ApiResponse, HandlerError, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Proposal status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProposalStatus {
+    Deposit,
+    Voting,
+    Passed,
+    Rejected,
+}
+
+/// Vote option
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VoteOption {
+    Yes,
+    No,
+    Abstain,
+    Veto,
+}
+
+/// Voting power behind each option
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TallyResult {
+    pub yes: u64,
+    pub no: u64,
+    pub abstain: u64,
+    pub veto: u64,
+}
+
+impl TallyResult {
+    fn add(&mut self, option: VoteOption, power: u64) {
+        match option {
+            VoteOption::Yes => self.yes += power,
+            VoteOption::No => self.no += power,
+            VoteOption::Abstain => self.abstain += power,
+            VoteOption::Veto => self.veto += power,
+        }
+    }
+}
+
+/// Governance proposal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Proposal {
+    pub id: u64,
+    pub title: String,
+    pub description: String,
+    pub proposer: String,
+    pub status: ProposalStatus,
+    pub total_deposit: u64,
+    pub submitted_at: u64,
+    pub voting_start: Option<u64>,
+    pub voting_end: Option<u64>,
+    pub tally: TallyResult,
+}
+
+/// A vote cast on a proposal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Vote {
+    pub proposal_id: u64,
+    pub voter: String,
+    pub option: VoteOption,
+    pub voting_power: u64,
+    pub transaction_hash: String,
+    pub height: u64,
+    pub timestamp: u64,
+}
+
+/// Governance handler
+pub struct GovernanceHandler {
+    proposals: HashMap<u64, Proposal>,
+    votes: HashMap<u64, Vec<Vote>>,
+}
+
+impl GovernanceHandler {
+    pub fn new() -> Self {
+        let mut handler = Self {
+            proposals: HashMap::new(),
+            votes: HashMap::new(),
+        };
+
+        handler.add_sample_data();
+        handler
+    }
+
+    /// Get proposal by id
+    pub fn get_proposal(&self, id: u64) -> Result<ApiResponse<Proposal>> {
+        match self.proposals.get(&id) {
+            Some(proposal) => Ok(ApiResponse::success(proposal.clone())),
+            None => Err(HandlerError::NotFound {
+                resource: format!("Proposal {}", id),
+            }),
+        }
+    }
+
+    /// List proposals, with `status` if given, newest first
+    pub fn list_proposals(&self, status: Option<ProposalStatus>, page: u32, per_page: u32) -> Result<ApiResponse<Vec<Proposal>>> {
+        let mut proposals: Vec<Proposal> = self
+            .proposals
+            .values()
+            .filter(|proposal| status.is_none_or(|status| proposal.status == status))
+            .cloned()
+            .collect();
+        proposals.sort_by_key(|proposal| Reverse(proposal.id));
+
+        let (page_proposals, pagination) = paginate(proposals, page, per_page)?;
+        Ok(ApiResponse::success_with_pagination(page_proposals, pagination))
+    }
+
+    /// Votes cast on a proposal, newest first
+    pub fn get_proposal_votes(&self, id: u64, page: u32, per_page: u32) -> Result<ApiResponse<Vec<Vote>>> {
+        if !self.proposals.contains_key(&id) {
+            return Err(HandlerError::NotFound {
+                resource: format!("Proposal {}", id),
+            });
+        }
+
+        let mut votes = self.votes.get(&id).cloned().unwrap_or_default();
+        votes.sort_by(|a, b| b.height.cmp(&a.height).then_with(|| a.voter.cmp(&b.voter)));
+
+        let (page_votes, pagination) = paginate(votes, page, per_page)?;
+        Ok(ApiResponse::success_with_pagination(page_votes, pagination))
+    }
+
+    fn add_sample_data(&mut self) {
+        let statuses = [ProposalStatus::Passed, ProposalStatus::Rejected, ProposalStatus::Voting, ProposalStatus::Deposit];
+        for (i, status) in (1..=4u64).zip(statuses) {
+            let submitted_at = 1640995200 + (i * 86400);
+            let voting = status != ProposalStatus::Deposit;
+            let mut proposal = Proposal {
+                id: i,
+                title: format!("Proposal {}", i),
+                description: format!("Parameter change proposal number {}", i),
+                proposer: format!("0x{:040x}", i * 10),
+                status,
+                total_deposit: 10_000 * i,
+                submitted_at,
+                voting_start: voting.then_some(submitted_at + 86400),
+                voting_end: voting.then_some(submitted_at + 8 * 86400),
+                tally: TallyResult::default(),
+            };
+
+            let mut votes = Vec::new();
+            if voting {
+                for j in 1..=3u64 {
+                    let option = match (status, j) {
+                        (ProposalStatus::Rejected, 1) => VoteOption::Veto,
+                        (ProposalStatus::Rejected, _) => VoteOption::No,
+                        (_, 3) => VoteOption::Abstain,
+                        _ => VoteOption::Yes,
+                    };
+                    let vote = Vote {
+                        proposal_id: i,
+                        voter: format!("validator_{}", j),
+                        option,
+                        voting_power: 1_000_000 * (4 - j),
+                        transaction_hash: format!("0x{:064x}", i * 3000 + j),
+                        height: i * 10 + j,
+                        timestamp: submitted_at + 86400 + (j * 60),
+                    };
+                    proposal.tally.add(vote.option, vote.voting_power);
+                    votes.push(vote);
+                }
+            }
+
+            self.votes.insert(i, votes);
+            self.proposals.insert(i, proposal);
+        }
+    }
+}
+
+impl Default for GovernanceHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
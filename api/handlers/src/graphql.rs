@@ -0,0 +1,1165 @@
+//! GraphQL handler (`POST /graphql`) over blocks, transactions, accounts and
+//! validators.
+//!
+//! Supports the query subset explorer frontends use: named and anonymous
+//! queries, variables with defaults, arguments, aliases, named and inline
+//! fragments and `__typename`. Objects resolve nested fields on demand, so
+//! a block can be fetched with its transactions and their receipts in one
+//! round trip. Lists are cursor-paginated connections. Before anything is
+//! resolved, the query's depth and complexity are checked against limits:
+//! every field costs one, times the page sizes of the connections it sits
+//! in, so a handful of nested `first: 100` cannot fan out to millions of
+//! objects.
+
+use crate::validators::{Validator, ValidatorHandler, ValidatorStatus};
+use crate::{
+    Account, AccountHandler, ApiResponse, Block, BlockHandler, Transaction, TransactionHandler,
+    TransactionStatus,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// A GraphQL request body
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphQLRequest {
+    pub query: String,
+    pub operation_name: Option<String>,
+    #[serde(default)]
+    pub variables: Map<String, Value>,
+}
+
+/// A GraphQL error, with the response path of the field it belongs to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphQLError {
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path: Vec<Value>,
+}
+
+impl GraphQLError {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            path: Vec::new(),
+        }
+    }
+}
+
+/// A GraphQL response body
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphQLResponse {
+    pub data: Option<Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<GraphQLError>,
+}
+
+impl GraphQLResponse {
+    fn failed(error: GraphQLError) -> Self {
+        Self {
+            data: None,
+            errors: vec![error],
+        }
+    }
+}
+
+/// Limits on what a single query may ask for
+#[derive(Debug, Clone)]
+pub struct GraphQLLimits {
+    pub max_depth: usize,
+    pub max_complexity: u64,
+    pub default_page_size: u32,
+    pub max_page_size: u32,
+}
+
+impl Default for GraphQLLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 10,
+            max_complexity: 5_000,
+            default_page_size: 10,
+            max_page_size: 100,
+        }
+    }
+}
+
+// Query documents
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Punct(char),
+    Spread,
+    Name(String),
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum InputValue {
+    Null,
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Boolean(bool),
+    Enum(String),
+    List(Vec<InputValue>),
+    Object(Vec<(String, InputValue)>),
+    Variable(String),
+}
+
+#[derive(Debug, Clone)]
+struct Field {
+    alias: Option<String>,
+    name: String,
+    arguments: Vec<(String, InputValue)>,
+    selection: Vec<Selection>,
+}
+
+impl Field {
+    fn response_key(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Selection {
+    Field(Field),
+    FragmentSpread(String),
+    InlineFragment {
+        type_condition: Option<String>,
+        selection: Vec<Selection>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Fragment {
+    type_condition: String,
+    selection: Vec<Selection>,
+}
+
+#[derive(Debug, Clone)]
+struct Operation {
+    name: Option<String>,
+    /// Variable names with their defaults
+    variables: Vec<(String, Option<InputValue>)>,
+    selection: Vec<Selection>,
+}
+
+#[derive(Debug, Default)]
+struct Document {
+    operations: Vec<Operation>,
+    fragments: HashMap<String, Fragment>,
+}
+
+type ParseResult<T> = std::result::Result<T, GraphQLError>;
+
+fn tokenize(source: &str) -> ParseResult<Vec<Token>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            // Commas are insignificant, like whitespace
+            c if c.is_whitespace() || c == ',' || c == '\u{feff}' => i += 1,
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '{' | '}' | '(' | ')' | '[' | ']' | ':' | '!' | '$' | '=' | '@' => {
+                tokens.push(Token::Punct(c));
+                i += 1;
+            }
+            '.' if chars[i..].starts_with(&['.', '.', '.']) => {
+                tokens.push(Token::Spread);
+                i += 3;
+            }
+            '"' => {
+                if chars[i..].starts_with(&['"', '"', '"']) {
+                    return Err(GraphQLError::new("Block strings are not supported"));
+                }
+                i += 1;
+                let mut value = String::new();
+                loop {
+                    match chars.get(i) {
+                        None | Some('\n') => return Err(GraphQLError::new("Unterminated string")),
+                        Some('"') => break,
+                        Some('\\') => {
+                            let escaped = match chars.get(i + 1) {
+                                Some('"') => '"',
+                                Some('\\') => '\\',
+                                Some('/') => '/',
+                                Some('b') => '\u{8}',
+                                Some('f') => '\u{c}',
+                                Some('n') => '\n',
+                                Some('r') => '\r',
+                                Some('t') => '\t',
+                                Some('u') => {
+                                    let hex: String = chars.get(i + 2..i + 6).unwrap_or_default().iter().collect();
+                                    let code = u32::from_str_radix(&hex, 16)
+                                        .ok()
+                                        .and_then(char::from_u32)
+                                        .ok_or_else(|| GraphQLError::new("Invalid unicode escape"))?;
+                                    i += 4;
+                                    code
+                                }
+                                _ => return Err(GraphQLError::new("Invalid escape sequence")),
+                            };
+                            value.push(escaped);
+                            i += 2;
+                        }
+                        Some(&c) => {
+                            value.push(c);
+                            i += 1;
+                        }
+                    }
+                }
+                tokens.push(Token::Str(value));
+                i += 1;
+            }
+            c if c == '-' || c.is_ascii_digit() => {
+                let start = i;
+                i += 1;
+                let mut float = false;
+                while let Some(&c) = chars.get(i) {
+                    match c {
+                        '0'..='9' => {}
+                        '.' | 'e' | 'E' => float = true,
+                        '+' | '-' if matches!(chars[i - 1], 'e' | 'E') => {}
+                        _ => break,
+                    }
+                    i += 1;
+                }
+                let number: String = chars[start..i].iter().collect();
+                let invalid = || GraphQLError::new(format!("Invalid number {}", number));
+                tokens.push(if float {
+                    Token::Float(number.parse().map_err(|_| invalid())?)
+                } else {
+                    Token::Int(number.parse().map_err(|_| invalid())?)
+                });
+            }
+            c if c == '_' || c.is_ascii_alphabetic() => {
+                let start = i;
+                while chars.get(i).is_some_and(|c| *c == '_' || c.is_ascii_alphanumeric()) {
+                    i += 1;
+                }
+                tokens.push(Token::Name(chars[start..i].iter().collect()));
+            }
+            c => return Err(GraphQLError::new(format!("Unexpected character '{}'", c))),
+        }
+    }
+    Ok(tokens)
+}
+
+/// Nesting allowed past `max_depth` for argument values and variable types
+const VALUE_NESTING_ALLOWANCE: usize = 8;
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// Open `{` and `[` being parsed, bounded so deeply nested queries fail
+    /// before they exhaust the stack
+    nesting: usize,
+    max_nesting: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> ParseResult<Token> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or_else(|| GraphQLError::new("Unexpected end of query"))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn eat(&mut self, punct: char) -> bool {
+        if self.peek() == Some(&Token::Punct(punct)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, punct: char) -> ParseResult<()> {
+        match self.next()? {
+            Token::Punct(c) if c == punct => Ok(()),
+            token => Err(GraphQLError::new(format!("Expected '{}', found {:?}", punct, token))),
+        }
+    }
+
+    fn enter(&mut self) -> ParseResult<()> {
+        self.nesting += 1;
+        if self.nesting > self.max_nesting {
+            return Err(GraphQLError::new(format!(
+                "Query is nested deeper than the limit of {}",
+                self.max_nesting
+            )));
+        }
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.nesting -= 1;
+    }
+
+    fn name(&mut self) -> ParseResult<String> {
+        match self.next()? {
+            Token::Name(name) => Ok(name),
+            token => Err(GraphQLError::new(format!("Expected a name, found {:?}", token))),
+        }
+    }
+
+    fn document(mut self) -> ParseResult<Document> {
+        let mut document = Document::default();
+        while let Some(token) = self.peek().cloned() {
+            match token {
+                Token::Punct('{') => document.operations.push(Operation {
+                    name: None,
+                    variables: Vec::new(),
+                    selection: self.selection_set()?,
+                }),
+                Token::Name(keyword) if keyword == "query" => {
+                    self.position += 1;
+                    document.operations.push(self.operation()?);
+                }
+                Token::Name(keyword) if keyword == "fragment" => {
+                    self.position += 1;
+                    let name = self.name()?;
+                    if self.name()? != "on" {
+                        return Err(GraphQLError::new(format!("Fragment {} needs a type condition", name)));
+                    }
+                    let type_condition = self.name()?;
+                    self.directives()?;
+                    let selection = self.selection_set()?;
+                    if document
+                        .fragments
+                        .insert(name.clone(), Fragment { type_condition, selection })
+                        .is_some()
+                    {
+                        return Err(GraphQLError::new(format!("Fragment {} is defined twice", name)));
+                    }
+                }
+                Token::Name(keyword) if keyword == "mutation" || keyword == "subscription" => {
+                    return Err(GraphQLError::new(format!("{} operations are not supported", keyword)));
+                }
+                token => return Err(GraphQLError::new(format!("Unexpected {:?}", token))),
+            }
+        }
+        if document.operations.is_empty() {
+            return Err(GraphQLError::new("Query has no operations"));
+        }
+        Ok(document)
+    }
+
+    fn operation(&mut self) -> ParseResult<Operation> {
+        let name = match self.peek() {
+            Some(Token::Name(_)) => Some(self.name()?),
+            _ => None,
+        };
+        let mut variables = Vec::new();
+        if self.eat('(') {
+            while !self.eat(')') {
+                self.expect('$')?;
+                let variable = self.name()?;
+                self.expect(':')?;
+                self.skip_type()?;
+                let default = if self.eat('=') { Some(self.value(true)?) } else { None };
+                variables.push((variable, default));
+            }
+        }
+        self.directives()?;
+        Ok(Operation {
+            name,
+            variables,
+            selection: self.selection_set()?,
+        })
+    }
+
+    /// Variable types are not checked; arguments are checked where used
+    fn skip_type(&mut self) -> ParseResult<()> {
+        if self.eat('[') {
+            self.enter()?;
+            self.skip_type()?;
+            self.expect(']')?;
+            self.leave();
+        } else {
+            self.name()?;
+        }
+        self.eat('!');
+        Ok(())
+    }
+
+    fn directives(&mut self) -> ParseResult<()> {
+        if self.peek() == Some(&Token::Punct('@')) {
+            return Err(GraphQLError::new("Directives are not supported"));
+        }
+        Ok(())
+    }
+
+    fn selection_set(&mut self) -> ParseResult<Vec<Selection>> {
+        self.expect('{')?;
+        self.enter()?;
+        let mut selection = Vec::new();
+        while !self.eat('}') {
+            if self.peek() == Some(&Token::Spread) {
+                self.position += 1;
+                match self.peek() {
+                    Some(Token::Name(on)) if on == "on" => {
+                        self.position += 1;
+                        let type_condition = Some(self.name()?);
+                        self.directives()?;
+                        selection.push(Selection::InlineFragment {
+                            type_condition,
+                            selection: self.selection_set()?,
+                        });
+                    }
+                    Some(Token::Name(_)) => {
+                        selection.push(Selection::FragmentSpread(self.name()?));
+                        self.directives()?;
+                    }
+                    _ => {
+                        self.directives()?;
+                        selection.push(Selection::InlineFragment {
+                            type_condition: None,
+                            selection: self.selection_set()?,
+                        });
+                    }
+                }
+                continue;
+            }
+
+            let mut name = self.name()?;
+            let mut alias = None;
+            if self.eat(':') {
+                alias = Some(name);
+                name = self.name()?;
+            }
+            let mut arguments = Vec::new();
+            if self.eat('(') {
+                while !self.eat(')') {
+                    let argument = self.name()?;
+                    self.expect(':')?;
+                    arguments.push((argument, self.value(false)?));
+                }
+            }
+            self.directives()?;
+            let selection_set = if self.peek() == Some(&Token::Punct('{')) {
+                self.selection_set()?
+            } else {
+                Vec::new()
+            };
+            selection.push(Selection::Field(Field {
+                alias,
+                name,
+                arguments,
+                selection: selection_set,
+            }));
+        }
+        self.leave();
+        Ok(selection)
+    }
+
+    fn value(&mut self, constant: bool) -> ParseResult<InputValue> {
+        Ok(match self.next()? {
+            Token::Punct('$') if !constant => InputValue::Variable(self.name()?),
+            Token::Int(value) => InputValue::Int(value),
+            Token::Float(value) => InputValue::Float(value),
+            Token::Str(value) => InputValue::Str(value),
+            Token::Name(name) => match name.as_str() {
+                "true" => InputValue::Boolean(true),
+                "false" => InputValue::Boolean(false),
+                "null" => InputValue::Null,
+                _ => InputValue::Enum(name),
+            },
+            Token::Punct('[') => {
+                self.enter()?;
+                let mut items = Vec::new();
+                while !self.eat(']') {
+                    items.push(self.value(constant)?);
+                }
+                self.leave();
+                InputValue::List(items)
+            }
+            Token::Punct('{') => {
+                self.enter()?;
+                let mut fields = Vec::new();
+                while !self.eat('}') {
+                    let name = self.name()?;
+                    self.expect(':')?;
+                    fields.push((name, self.value(constant)?));
+                }
+                self.leave();
+                InputValue::Object(fields)
+            }
+            token => return Err(GraphQLError::new(format!("Expected a value, found {:?}", token))),
+        })
+    }
+}
+
+fn parse(query: &str, max_nesting: usize) -> ParseResult<Document> {
+    Parser {
+        tokens: tokenize(query)?,
+        position: 0,
+        nesting: 0,
+        max_nesting,
+    }
+    .document()
+}
+
+// Execution
+
+/// Objects fields are resolved on
+#[derive(Debug, Clone)]
+enum Node {
+    Query,
+    Block(Block),
+    Transaction(Transaction),
+    Receipt(Transaction),
+    Account(Account),
+    Validator(Validator),
+    Connection {
+        type_name: &'static str,
+        edges: Vec<(String, Node)>,
+        has_next_page: bool,
+        total_count: usize,
+    },
+    Edge {
+        type_name: &'static str,
+        cursor: String,
+        node: Box<Node>,
+    },
+    PageInfo {
+        has_next_page: bool,
+        end_cursor: Option<String>,
+    },
+}
+
+impl Node {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Node::Query => "Query",
+            Node::Block(_) => "Block",
+            Node::Transaction(_) => "Transaction",
+            Node::Receipt(_) => "Receipt",
+            Node::Account(_) => "Account",
+            Node::Validator(_) => "Validator",
+            Node::Connection { type_name, .. } => type_name,
+            Node::Edge { type_name, .. } => type_name,
+            Node::PageInfo { .. } => "PageInfo",
+        }
+    }
+}
+
+/// What a field resolved to
+enum Resolved {
+    Leaf(Value),
+    Object(Node),
+    List(Vec<Node>),
+}
+
+/// Fields that take `first`, by the type they are on
+const CONNECTION_FIELDS: &[(&str, &str)] = &[
+    ("Query", "blocks"),
+    ("Query", "transactions"),
+    ("Query", "validators"),
+    ("Account", "transactions"),
+];
+
+/// Items assumed per block transaction list when computing complexity
+const LIST_COMPLEXITY_FACTOR: u64 = 10;
+
+/// Arguments of one field, with variables substituted
+struct Arguments(Map<String, Value>);
+
+impl Arguments {
+    fn string(&self, name: &str) -> Result<Option<String>, String> {
+        match self.0.get(name) {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::String(value)) => Ok(Some(value.clone())),
+            Some(value) => Err(format!("Argument {} must be a string, got {}", name, value)),
+        }
+    }
+
+    fn required_string(&self, name: &str) -> Result<String, String> {
+        self.string(name)?.ok_or_else(|| format!("Argument {} is required", name))
+    }
+
+    fn int(&self, name: &str) -> Result<Option<i64>, String> {
+        match self.0.get(name) {
+            None | Some(Value::Null) => Ok(None),
+            Some(value) => value
+                .as_i64()
+                .map(Some)
+                .ok_or_else(|| format!("Argument {} must be an integer, got {}", name, value)),
+        }
+    }
+}
+
+/// GraphQL handler
+pub struct GraphQLHandler {
+    blocks: BlockHandler,
+    transactions: TransactionHandler,
+    accounts: AccountHandler,
+    validators: ValidatorHandler,
+    limits: GraphQLLimits,
+}
+
+/// Fragments being expanded during a complexity walk, and the cost of
+/// those already walked, by fragment, type, multiplier and depth
+#[derive(Default)]
+struct FragmentCosts {
+    active: Vec<String>,
+    known: HashMap<(String, String, u64, usize), u64>,
+}
+
+struct Execution<'a> {
+    document: &'a Document,
+    variables: Map<String, Value>,
+    errors: Vec<GraphQLError>,
+}
+
+impl GraphQLHandler {
+    pub fn new(limits: GraphQLLimits) -> Self {
+        Self {
+            blocks: BlockHandler::new(),
+            transactions: TransactionHandler::new(),
+            accounts: AccountHandler::new(),
+            validators: ValidatorHandler::new(),
+            limits,
+        }
+    }
+
+    /// `POST /graphql`
+    pub fn execute(&self, request: GraphQLRequest) -> GraphQLResponse {
+        let document = match parse(&request.query, self.limits.max_depth + VALUE_NESTING_ALLOWANCE) {
+            Ok(document) => document,
+            Err(error) => return GraphQLResponse::failed(error),
+        };
+        let operation = match (&request.operation_name, document.operations.as_slice()) {
+            (None, [operation]) => operation,
+            (None, _) => {
+                return GraphQLResponse::failed(GraphQLError::new(
+                    "operationName is required when the query has several operations",
+                ))
+            }
+            (Some(name), operations) => match operations.iter().find(|op| op.name.as_ref() == Some(name)) {
+                Some(operation) => operation,
+                None => return GraphQLResponse::failed(GraphQLError::new(format!("Unknown operation {}", name))),
+            },
+        };
+
+        let mut variables = Map::new();
+        for (name, default) in &operation.variables {
+            let value = match (request.variables.get(name), default) {
+                (Some(value), _) => value.clone(),
+                (None, Some(default)) => to_json(default, &Map::new()),
+                (None, None) => Value::Null,
+            };
+            variables.insert(name.clone(), value);
+        }
+
+        let mut execution = Execution {
+            document: &document,
+            variables,
+            errors: Vec::new(),
+        };
+        if let Err(error) = self.check_limits(&execution, &operation.selection) {
+            return GraphQLResponse::failed(error);
+        }
+        let data = self.complete(&mut execution, &Node::Query, &operation.selection, &mut Vec::new());
+        GraphQLResponse {
+            data: Some(data),
+            errors: execution.errors,
+        }
+    }
+
+    /// Reject queries nested deeper or costing more than the limits allow
+    fn check_limits(&self, execution: &Execution, selection: &[Selection]) -> ParseResult<()> {
+        let complexity = self.complexity(execution, "Query", selection, 1, 1, &mut FragmentCosts::default())?;
+        if complexity > self.limits.max_complexity {
+            return Err(GraphQLError::new(format!(
+                "Query complexity {} exceeds the limit of {}",
+                complexity, self.limits.max_complexity
+            )));
+        }
+        Ok(())
+    }
+
+    /// Cost of `selection`, returned early once it exceeds `max_complexity`
+    fn complexity(
+        &self,
+        execution: &Execution,
+        type_name: &str,
+        selection: &[Selection],
+        multiplier: u64,
+        depth: usize,
+        fragments: &mut FragmentCosts,
+    ) -> ParseResult<u64> {
+        if depth > self.limits.max_depth {
+            return Err(GraphQLError::new(format!(
+                "Query is nested deeper than the limit of {}",
+                self.limits.max_depth
+            )));
+        }
+        let mut total: u64 = 0;
+        for item in selection {
+            let cost = match item {
+                Selection::Field(field) => {
+                    // Everything selected under a list is paid for per item
+                    let factor = if CONNECTION_FIELDS.contains(&(type_name, field.name.as_str())) {
+                        let arguments = arguments(field, &execution.variables);
+                        self.page_size(&arguments).map_err(GraphQLError::new)? as u64
+                    } else if (type_name, field.name.as_str()) == ("Block", "transactions") {
+                        LIST_COMPLEXITY_FACTOR
+                    } else {
+                        1
+                    };
+                    multiplier.saturating_add(self.complexity(
+                        execution,
+                        child_type(type_name, &field.name),
+                        &field.selection,
+                        multiplier.saturating_mul(factor),
+                        depth + 1,
+                        fragments,
+                    )?)
+                }
+                Selection::FragmentSpread(name) => {
+                    if fragments.active.contains(name) {
+                        return Err(GraphQLError::new(format!("Fragment {} spreads itself", name)));
+                    }
+                    // Fragments spread repeatedly are walked once per place they
+                    // can cost differently, not once per spread
+                    let key = (name.clone(), type_name.to_string(), multiplier, depth);
+                    if let Some(cost) = fragments.known.get(&key) {
+                        *cost
+                    } else {
+                        let fragment = execution
+                            .document
+                            .fragments
+                            .get(name)
+                            .ok_or_else(|| GraphQLError::new(format!("Unknown fragment {}", name)))?;
+                        fragments.active.push(name.clone());
+                        let cost = self.complexity(execution, type_name, &fragment.selection, multiplier, depth, fragments)?;
+                        fragments.active.pop();
+                        fragments.known.insert(key, cost);
+                        cost
+                    }
+                }
+                Selection::InlineFragment { selection, .. } => {
+                    self.complexity(execution, type_name, selection, multiplier, depth, fragments)?
+                }
+            };
+            total = total.saturating_add(cost);
+            if total > self.limits.max_complexity {
+                break;
+            }
+        }
+        Ok(total)
+    }
+
+    fn page_size(&self, arguments: &Arguments) -> Result<u32, String> {
+        match arguments.int("first")? {
+            None => Ok(self.limits.default_page_size),
+            Some(first) if first < 0 => Err("Argument first must not be negative".to_string()),
+            Some(first) if first > self.limits.max_page_size as i64 => Err(format!(
+                "Argument first must be at most {}",
+                self.limits.max_page_size
+            )),
+            Some(first) => Ok(first as u32),
+        }
+    }
+
+    /// Resolve the fields of `selection` on `node`
+    fn complete(&self, execution: &mut Execution, node: &Node, selection: &[Selection], path: &mut Vec<Value>) -> Value {
+        let mut fields = Vec::new();
+        collect_fields(execution.document, node.type_name(), selection, &mut fields);
+
+        let mut object = Map::new();
+        for field in fields {
+            let key = field.response_key().to_string();
+            if object.contains_key(&key) {
+                continue;
+            }
+            path.push(Value::String(key.clone()));
+            let value = match self.resolve_field(execution, node, field) {
+                Ok(resolved) => self.complete_value(execution, resolved, field, path),
+                Err(message) => {
+                    execution.errors.push(GraphQLError {
+                        message,
+                        path: path.clone(),
+                    });
+                    Value::Null
+                }
+            };
+            path.pop();
+            object.insert(key, value);
+        }
+        Value::Object(object)
+    }
+
+    fn complete_value(&self, execution: &mut Execution, resolved: Resolved, field: &Field, path: &mut Vec<Value>) -> Value {
+        let needs_selection = !matches!(resolved, Resolved::Leaf(_));
+        if needs_selection == field.selection.is_empty() {
+            execution.errors.push(GraphQLError {
+                message: if needs_selection {
+                    format!("Field {} needs a selection of subfields", field.name)
+                } else {
+                    format!("Field {} has no subfields", field.name)
+                },
+                path: path.clone(),
+            });
+            return Value::Null;
+        }
+        match resolved {
+            Resolved::Leaf(value) => value,
+            Resolved::Object(node) => self.complete(execution, &node, &field.selection, path),
+            Resolved::List(nodes) => Value::Array(
+                nodes
+                    .iter()
+                    .enumerate()
+                    .map(|(index, node)| {
+                        path.push(json!(index));
+                        let value = self.complete(execution, node, &field.selection, path);
+                        path.pop();
+                        value
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    fn resolve_field(&self, execution: &Execution, node: &Node, field: &Field) -> Result<Resolved, String> {
+        if field.name == "__typename" {
+            return Ok(Resolved::Leaf(json!(node.type_name())));
+        }
+        let arguments = arguments(field, &execution.variables);
+        let unknown = || format!("Unknown field {} on {}", field.name, node.type_name());
+        let leaf = |value: Value| Ok(Resolved::Leaf(value));
+        let object = |node: Option<Node>| Ok(node.map_or(Resolved::Leaf(Value::Null), Resolved::Object));
+
+        match node {
+            Node::Query => match field.name.as_str() {
+                "block" => {
+                    let block = match (arguments.int("height")?, arguments.string("hash")?) {
+                        (Some(height), None) => self.block_at(height as u64),
+                        (None, Some(hash)) => data(self.blocks.get_block_by_hash(&hash)),
+                        _ => return Err("Argument height or hash is required".to_string()),
+                    };
+                    object(block.map(Node::Block))
+                }
+                "latestBlock" => object(data(self.blocks.get_latest_block()).map(Node::Block)),
+                "blocks" => {
+                    let blocks = all(self.blocks.list_blocks(1, u32::MAX));
+                    self.connection("BlockConnection", blocks.into_iter().map(Node::Block).collect(), &arguments)
+                }
+                "transaction" => {
+                    let hash = arguments.required_string("hash")?;
+                    object(data(self.transactions.get_transaction(&hash)).map(Node::Transaction))
+                }
+                "transactions" => {
+                    let address = arguments.string("address")?;
+                    let transactions = self.transactions_of(address.as_deref());
+                    self.connection("TransactionConnection", transactions, &arguments)
+                }
+                "account" => {
+                    let address = arguments.required_string("address")?;
+                    object(data(self.accounts.get_account(&address)).map(Node::Account))
+                }
+                "validator" => {
+                    let address = arguments.required_string("address")?;
+                    object(data(self.validators.get_validator(&address)).map(Node::Validator))
+                }
+                "validators" => {
+                    let status = match arguments.string("status")? {
+                        Some(status) => Some(
+                            serde_json::from_value::<ValidatorStatus>(json!(status.to_ascii_lowercase()))
+                                .map_err(|_| format!("Unknown validator status {}", status))?,
+                        ),
+                        None => None,
+                    };
+                    let validators = all(self.validators.list_validators(status, 1, u32::MAX));
+                    self.connection("ValidatorConnection", validators.into_iter().map(Node::Validator).collect(), &arguments)
+                }
+                _ => Err(unknown()),
+            },
+            Node::Block(block) => match field.name.as_str() {
+                "hash" => leaf(json!(block.hash)),
+                "height" => leaf(json!(block.height)),
+                "parentHash" => leaf(json!(block.parent_hash)),
+                "timestamp" => leaf(json!(block.timestamp)),
+                "proposer" => leaf(json!(block.proposer)),
+                "transactionCount" => leaf(json!(block.transaction_count)),
+                "gasUsed" => leaf(json!(block.gas_used)),
+                "gasLimit" => leaf(json!(block.gas_limit)),
+                "size" => leaf(json!(block.size)),
+                "transactions" => Ok(Resolved::List(
+                    block
+                        .transactions
+                        .iter()
+                        .filter_map(|hash| data(self.transactions.get_transaction(hash)))
+                        .map(Node::Transaction)
+                        .collect(),
+                )),
+                "proposerValidator" => object(data(self.validators.get_validator(&block.proposer)).map(Node::Validator)),
+                _ => Err(unknown()),
+            },
+            Node::Transaction(transaction) => match field.name.as_str() {
+                "hash" => leaf(json!(transaction.hash)),
+                "blockHash" => leaf(json!(transaction.block_hash)),
+                "blockHeight" => leaf(json!(transaction.block_height)),
+                "transactionIndex" => leaf(json!(transaction.transaction_index)),
+                "from" => leaf(json!(transaction.from)),
+                "to" => leaf(json!(transaction.to)),
+                "amount" => leaf(json!(transaction.amount)),
+                "fee" => leaf(json!(transaction.fee)),
+                "gasLimit" => leaf(json!(transaction.gas_limit)),
+                "gasUsed" => leaf(json!(transaction.gas_used)),
+                "status" => leaf(json!(status_name(&transaction.status))),
+                "timestamp" => leaf(json!(transaction.timestamp)),
+                "data" => leaf(json!(transaction.data)),
+                "block" => object(transaction.block_height.and_then(|height| self.block_at(height)).map(Node::Block)),
+                // Pending transactions have no receipt yet
+                "receipt" => object(
+                    transaction
+                        .block_height
+                        .is_some()
+                        .then(|| Node::Receipt(transaction.clone())),
+                ),
+                _ => Err(unknown()),
+            },
+            Node::Receipt(transaction) => match field.name.as_str() {
+                "transactionHash" => leaf(json!(transaction.hash)),
+                "blockHash" => leaf(json!(transaction.block_hash)),
+                "blockHeight" => leaf(json!(transaction.block_height)),
+                "success" => leaf(json!(matches!(transaction.status, TransactionStatus::Confirmed))),
+                "gasUsed" => leaf(json!(transaction.gas_used.unwrap_or_default())),
+                "fee" => leaf(json!(transaction.fee)),
+                _ => Err(unknown()),
+            },
+            Node::Account(account) => match field.name.as_str() {
+                "address" => leaf(json!(account.address)),
+                "balance" => leaf(json!(account.balance)),
+                "nonce" => leaf(json!(account.nonce)),
+                "transactionCount" => leaf(json!(account.transaction_count)),
+                "lastActivity" => leaf(json!(account.last_activity)),
+                "transactions" => {
+                    let transactions = self.transactions_of(Some(&account.address));
+                    self.connection("TransactionConnection", transactions, &arguments)
+                }
+                _ => Err(unknown()),
+            },
+            Node::Validator(validator) => match field.name.as_str() {
+                "address" => leaf(json!(validator.address)),
+                "moniker" => leaf(json!(validator.moniker)),
+                "status" => leaf(json!(format!("{:?}", validator.status).to_ascii_uppercase())),
+                "votingPower" => leaf(json!(validator.voting_power)),
+                "selfStake" => leaf(json!(validator.self_stake)),
+                "delegatedStake" => leaf(json!(validator.delegated_stake)),
+                "commissionRate" => leaf(json!(validator.commission_rate)),
+                "blocksProposed" => leaf(json!(validator.blocks_proposed)),
+                "uptime" => leaf(json!(validator.uptime)),
+                "joinedAt" => leaf(json!(validator.joined_at)),
+                _ => Err(unknown()),
+            },
+            Node::Connection {
+                type_name,
+                edges,
+                has_next_page,
+                total_count,
+            } => match field.name.as_str() {
+                "edges" => Ok(Resolved::List(
+                    edges
+                        .iter()
+                        .map(|(cursor, node)| Node::Edge {
+                            type_name: edge_type(type_name),
+                            cursor: cursor.clone(),
+                            node: Box::new(node.clone()),
+                        })
+                        .collect(),
+                )),
+                "nodes" => Ok(Resolved::List(edges.iter().map(|(_, node)| node.clone()).collect())),
+                "pageInfo" => object(Some(Node::PageInfo {
+                    has_next_page: *has_next_page,
+                    end_cursor: edges.last().map(|(cursor, _)| cursor.clone()),
+                })),
+                "totalCount" => leaf(json!(total_count)),
+                _ => Err(unknown()),
+            },
+            Node::Edge { cursor, node, .. } => match field.name.as_str() {
+                "cursor" => leaf(json!(cursor)),
+                "node" => object(Some((**node).clone())),
+                _ => Err(unknown()),
+            },
+            Node::PageInfo {
+                has_next_page,
+                end_cursor,
+            } => match field.name.as_str() {
+                "hasNextPage" => leaf(json!(has_next_page)),
+                "endCursor" => leaf(json!(end_cursor)),
+                _ => Err(unknown()),
+            },
+        }
+    }
+
+    /// A page of `items` after the `after` cursor
+    fn connection(&self, type_name: &'static str, items: Vec<Node>, arguments: &Arguments) -> Result<Resolved, String> {
+        let first = self.page_size(arguments)? as usize;
+        let offset = match arguments.string("after")? {
+            Some(cursor) => decode_cursor(&cursor).ok_or_else(|| format!("Invalid cursor {}", cursor))? + 1,
+            None => 0,
+        };
+        let total_count = items.len();
+        let edges = items
+            .into_iter()
+            .enumerate()
+            .skip(offset)
+            .take(first)
+            .map(|(index, node)| (encode_cursor(index), node))
+            .collect();
+        Ok(Resolved::Object(Node::Connection {
+            type_name,
+            edges,
+            has_next_page: offset.saturating_add(first) < total_count,
+            total_count,
+        }))
+    }
+
+    fn block_at(&self, height: u64) -> Option<Block> {
+        data(self.blocks.get_block_by_height(height))
+    }
+
+    /// Transactions, from or to `address` if given, newest first
+    fn transactions_of(&self, address: Option<&str>) -> Vec<Node> {
+        all(self.transactions.list_transactions(1, u32::MAX))
+            .into_iter()
+            .filter(|transaction| {
+                address.is_none_or(|address| {
+                    transaction.from.eq_ignore_ascii_case(address) || transaction.to.eq_ignore_ascii_case(address)
+                })
+            })
+            .map(Node::Transaction)
+            .collect()
+    }
+}
+
+impl Default for GraphQLHandler {
+    fn default() -> Self {
+        Self::new(GraphQLLimits::default())
+    }
+}
+
+/// Fields of `selection` that apply to `type_name`, fragments expanded
+fn collect_fields<'a>(document: &'a Document, type_name: &str, selection: &'a [Selection], fields: &mut Vec<&'a Field>) {
+    for item in selection {
+        match item {
+            Selection::Field(field) => fields.push(field),
+            Selection::FragmentSpread(name) => {
+                // Limits were checked, so the fragment exists and does not recurse
+                if let Some(fragment) = document.fragments.get(name) {
+                    if fragment.type_condition == type_name {
+                        collect_fields(document, type_name, &fragment.selection, fields);
+                    }
+                }
+            }
+            Selection::InlineFragment {
+                type_condition,
+                selection,
+            } => {
+                if type_condition.as_deref().is_none_or(|condition| condition == type_name) {
+                    collect_fields(document, type_name, selection, fields);
+                }
+            }
+        }
+    }
+}
+
+/// Type of the object a field returns, for complexity checks
+fn child_type(type_name: &str, field: &str) -> &'static str {
+    match (type_name, field) {
+        ("Query", "block" | "latestBlock") | ("Transaction", "block") => "Block",
+        ("Query", "transaction") | ("Block", "transactions") => "Transaction",
+        ("Query", "account") => "Account",
+        ("Query", "validator") | ("Block", "proposerValidator") => "Validator",
+        ("Transaction", "receipt") => "Receipt",
+        ("Query", "blocks") => "BlockConnection",
+        ("Query", "transactions") | ("Account", "transactions") => "TransactionConnection",
+        ("Query", "validators") => "ValidatorConnection",
+        ("BlockConnection", "nodes") | ("BlockEdge", "node") => "Block",
+        ("TransactionConnection", "nodes") | ("TransactionEdge", "node") => "Transaction",
+        ("ValidatorConnection", "nodes") | ("ValidatorEdge", "node") => "Validator",
+        (connection, "edges") => edge_type(connection),
+        (_, "pageInfo") => "PageInfo",
+        _ => "Leaf",
+    }
+}
+
+fn edge_type(connection: &str) -> &'static str {
+    match connection {
+        "BlockConnection" => "BlockEdge",
+        "TransactionConnection" => "TransactionEdge",
+        "ValidatorConnection" => "ValidatorEdge",
+        _ => "Leaf",
+    }
+}
+
+fn arguments(field: &Field, variables: &Map<String, Value>) -> Arguments {
+    Arguments(
+        field
+            .arguments
+            .iter()
+            .map(|(name, value)| (name.clone(), to_json(value, variables)))
+            .collect(),
+    )
+}
+
+fn to_json(value: &InputValue, variables: &Map<String, Value>) -> Value {
+    match value {
+        InputValue::Null => Value::Null,
+        InputValue::Int(value) => json!(value),
+        InputValue::Float(value) => json!(value),
+        InputValue::Str(value) | InputValue::Enum(value) => json!(value),
+        InputValue::Boolean(value) => json!(value),
+        InputValue::List(items) => Value::Array(items.iter().map(|item| to_json(item, variables)).collect()),
+        InputValue::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(name, value)| (name.clone(), to_json(value, variables)))
+                .collect(),
+        ),
+        InputValue::Variable(name) => variables.get(name).cloned().unwrap_or(Value::Null),
+    }
+}
+
+fn status_name(status: &TransactionStatus) -> &'static str {
+    match status {
+        TransactionStatus::Pending => "PENDING",
+        TransactionStatus::Confirmed => "CONFIRMED",
+        TransactionStatus::Failed => "FAILED",
+    }
+}
+
+fn encode_cursor(index: usize) -> String {
+    format!("cursor:{}", index)
+}
+
+fn decode_cursor(cursor: &str) -> Option<usize> {
+    cursor.strip_prefix("cursor:")?.parse().ok()
+}
+
+fn data<T>(response: crate::Result<ApiResponse<T>>) -> Option<T> {
+    response.ok().and_then(|response| response.data)
+}
+
+fn all<T>(response: crate::Result<ApiResponse<Vec<T>>>) -> Vec<T> {
+    data(response).unwrap_or_default()
+}
@@ -1,7 +1,25 @@
 //! CC Chain API Handlers
 //!
 //! This module provides comprehensive request handlers for the CC Chain API,
-//! including handlers for blocks, transactions, accounts, and network information.
+//! including handlers for blocks, transactions, accounts, network information,
+//! validators, staking and governance, and event streams of new blocks and
+//! transactions.
+
+pub mod faucet;
+pub mod governance;
+pub mod graphql;
+pub mod search;
+pub mod staking;
+pub mod streaming;
+pub mod validators;
+
+pub use faucet::*;
+pub use governance::*;
+pub use graphql::*;
+pub use search::*;
+pub use staking::*;
+pub use streaming::*;
+pub use validators::*;
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -20,6 +38,10 @@ pub enum HandlerError {
     BadRequest(String),
     #[error("Service unavailable: {0}")]
     ServiceUnavailable(String),
+    #[error("Too many requests, retry after {retry_after}s")]
+    RateLimited { retry_after: u64 },
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
 }
 
 pub type Result<T> = std::result::Result<T, HandlerError>;
@@ -97,6 +119,27 @@ impl PaginationInfo {
     }
 }
 
+/// One page of `items`, with its pagination information
+pub(crate) fn paginate<T>(items: Vec<T>, page: u32, per_page: u32) -> Result<(Vec<T>, PaginationInfo)> {
+    if page == 0 {
+        return Err(HandlerError::InvalidParameter {
+            param: "page".to_string(),
+            reason: "Pages start at 1".to_string(),
+        });
+    }
+    if per_page == 0 {
+        return Err(HandlerError::InvalidParameter {
+            param: "per_page".to_string(),
+            reason: "Page size must be greater than 0".to_string(),
+        });
+    }
+
+    let pagination = PaginationInfo::new(page, per_page, items.len() as u64);
+    let offset = (page as usize - 1).saturating_mul(per_page as usize);
+    let page_items = items.into_iter().skip(offset).take(per_page as usize).collect();
+    Ok((page_items, pagination))
+}
+
 /// Block data structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Block {
@@ -220,21 +263,10 @@ impl BlockHandler {
 
     /// List blocks with pagination
     pub fn list_blocks(&self, page: u32, per_page: u32) -> Result<ApiResponse<Vec<Block>>> {
-        let total_items = self.blocks.len() as u64;
-        let pagination = PaginationInfo::new(page, per_page, total_items);
-
-        let offset = ((page - 1) * per_page) as usize;
-        let limit = per_page as usize;
-
         let mut blocks: Vec<Block> = self.blocks.values().cloned().collect();
         blocks.sort_by(|a, b| b.height.cmp(&a.height)); // Sort by height descending
 
-        let page_blocks = blocks
-            .into_iter()
-            .skip(offset)
-            .take(limit)
-            .collect();
-
+        let (page_blocks, pagination) = paginate(blocks, page, per_page)?;
         Ok(ApiResponse::success_with_pagination(page_blocks, pagination))
     }
 
@@ -333,21 +365,10 @@ impl TransactionHandler {
 
     /// List transactions with pagination
     pub fn list_transactions(&self, page: u32, per_page: u32) -> Result<ApiResponse<Vec<Transaction>>> {
-        let total_items = self.transactions.len() as u64;
-        let pagination = PaginationInfo::new(page, per_page, total_items);
-
-        let offset = ((page - 1) * per_page) as usize;
-        let limit = per_page as usize;
-
         let mut transactions: Vec<Transaction> = self.transactions.values().cloned().collect();
         transactions.sort_by(|a, b| b.timestamp.cmp(&a.timestamp)); // Sort by timestamp descending
 
-        let page_transactions = transactions
-            .into_iter()
-            .skip(offset)
-            .take(limit)
-            .collect();
-
+        let (page_transactions, pagination) = paginate(transactions, page, per_page)?;
         Ok(ApiResponse::success_with_pagination(page_transactions, pagination))
     }
 
@@ -712,6 +733,343 @@ mod tests {
         assert_eq!(network_info.chain_id, "cc-chain-mainnet");
     }
 
+    #[test]
+    fn test_validator_handler() {
+        let handler = ValidatorHandler::new();
+        let response = handler.list_validators(Some(ValidatorStatus::Active), 1, 10).unwrap();
+        let validators = response.data.unwrap();
+        assert_eq!(validators.len(), 2);
+        assert!(validators[0].voting_power >= validators[1].voting_power);
+
+        let validator = handler.get_validator("validator_3").unwrap().data.unwrap();
+        assert_eq!(validator.status, ValidatorStatus::Jailed);
+        let history = handler.get_validator_history("validator_3", 1, 2).unwrap();
+        assert!(matches!(history.data.unwrap()[0].event, ValidatorEventKind::Jailed { .. }));
+        assert!(history.pagination.unwrap().has_next);
+
+        assert!(matches!(handler.get_validator("validator_9"), Err(HandlerError::NotFound { .. })));
+        assert!(matches!(
+            handler.list_validators(None, 0, 10),
+            Err(HandlerError::InvalidParameter { .. })
+        ));
+    }
+
+    #[test]
+    fn test_staking_handler() {
+        let handler = StakingHandler::new();
+        let filter = DelegationFilter {
+            validator: Some("validator_1".to_string()),
+            ..DelegationFilter::default()
+        };
+        let delegations = handler.list_delegations(&filter, 1, 10).unwrap().data.unwrap();
+        assert!(!delegations.is_empty());
+        assert!(delegations.iter().all(|delegation| delegation.validator == "validator_1"));
+
+        let delegation = handler.get_delegation(&delegations[0].id).unwrap().data.unwrap();
+        assert_eq!(delegation.id, delegations[0].id);
+
+        let delegator = format!("0x{:040x}", 20);
+        let history = handler.get_delegation_history(&delegator, 1, 10).unwrap().data.unwrap();
+        assert!(matches!(history[0].action, DelegationAction::Undelegate { .. }));
+        assert!(matches!(history[1].action, DelegationAction::Delegate { .. }));
+    }
+
+    #[test]
+    fn test_governance_handler() {
+        let handler = GovernanceHandler::new();
+        let proposals = handler.list_proposals(None, 1, 2).unwrap();
+        assert_eq!(proposals.data.unwrap()[0].id, 4);
+        assert_eq!(proposals.pagination.unwrap().total_pages, 2);
+
+        let proposal = handler.get_proposal(2).unwrap().data.unwrap();
+        assert_eq!(proposal.status, ProposalStatus::Rejected);
+        assert!(proposal.tally.veto > 0);
+
+        let votes = handler.get_proposal_votes(1, 1, 10).unwrap().data.unwrap();
+        let yes: u64 = votes.iter().filter(|vote| vote.option == VoteOption::Yes).map(|vote| vote.voting_power).sum();
+        assert_eq!(yes, handler.get_proposal(1).unwrap().data.unwrap().tally.yes);
+        assert!(handler.get_proposal_votes(4, 1, 10).unwrap().data.unwrap().is_empty());
+        assert!(matches!(handler.get_proposal_votes(99, 1, 10), Err(HandlerError::NotFound { .. })));
+    }
+
+    #[test]
+    fn test_block_stream() {
+        let handler = StreamHandler::new(StreamConfig {
+            heartbeat_interval: std::time::Duration::from_millis(10),
+            ..StreamConfig::default()
+        });
+        let mut stream = handler.stream_blocks();
+        assert_eq!(stream.next_frame().unwrap(), "retry: 3000\n\n");
+        assert_eq!(stream.next_frame().unwrap(), ": heartbeat\n\n");
+
+        let block = BlockHandler::new().get_block_by_height(3).unwrap().data.unwrap();
+        handler.blocks().publish(&block);
+        let frame = stream.next_frame().unwrap();
+        assert!(frame.starts_with("id: 3\nevent: block\ndata: {"));
+        assert!(frame.ends_with("}\n\n"));
+    }
+
+    #[test]
+    fn test_transaction_stream_filter_and_slow_consumers() {
+        let handler = StreamHandler::new(StreamConfig {
+            heartbeat_interval: std::time::Duration::from_millis(10),
+            buffer_size: 2,
+            ..StreamConfig::default()
+        });
+        let transactions = TransactionHandler::new();
+        let sent = transactions.get_transaction(&format!("0x{:064x}", 1000)).unwrap().data.unwrap();
+        let other = transactions.get_transaction(&format!("0x{:064x}", 2000)).unwrap().data.unwrap();
+
+        let query = HashMap::from([("address".to_string(), sent.from.to_uppercase().replace("0X", "0x"))]);
+        let mut filtered = handler.stream_transactions(&query).unwrap();
+        let mut slow = handler.stream_transactions(&HashMap::new()).unwrap();
+        filtered.next_frame();
+        handler.transactions().publish(&other);
+        handler.transactions().publish(&sent);
+        let frame = filtered.next_frame().unwrap();
+        assert!(frame.starts_with(&format!("id: {}\nevent: transaction\n", sent.hash)));
+
+        // The unread stream falls a full buffer behind and is cut off
+        handler.transactions().publish(&other);
+        assert_eq!(handler.transactions().subscriber_count(), 1);
+        let frames: Vec<String> = slow.by_ref().take(4).collect();
+        assert_eq!(frames.len(), 3);
+
+        let empty = HashMap::from([("address".to_string(), " ".to_string())]);
+        assert!(matches!(
+            handler.stream_transactions(&empty),
+            Err(HandlerError::InvalidParameter { .. })
+        ));
+    }
+
+    #[test]
+    fn test_graphql_nested_resolution_and_pagination() {
+        let handler = GraphQLHandler::default();
+        let response = handler.execute(GraphQLRequest {
+            query: r#"
+                query Explorer($height: Int!, $after: String) {
+                    block(height: $height) {
+                        height
+                        txs: transactions { ...Tx receipt { success gasUsed } }
+                    }
+                    blocks(first: 2, after: $after) {
+                        totalCount
+                        edges { cursor node { __typename height } }
+                        pageInfo { hasNextPage endCursor }
+                    }
+                }
+                fragment Tx on Transaction { hash status block { height } }
+            "#
+            .to_string(),
+            operation_name: None,
+            variables: serde_json::json!({ "height": 1, "after": "cursor:0" }).as_object().unwrap().clone(),
+        });
+
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.unwrap();
+        let transaction = &data["block"]["txs"][0];
+        assert_eq!(transaction["hash"], format!("0x{:064x}", 1000));
+        assert_eq!(transaction["status"], "CONFIRMED");
+        assert_eq!(transaction["block"]["height"], 1);
+        assert_eq!(transaction["receipt"]["success"], true);
+
+        let blocks = &data["blocks"];
+        assert_eq!(blocks["edges"].as_array().unwrap().len(), 2);
+        assert_eq!(blocks["edges"][0]["cursor"], "cursor:1");
+        assert_eq!(blocks["edges"][0]["node"]["__typename"], "Block");
+        assert_eq!(blocks["pageInfo"]["endCursor"], "cursor:2");
+        assert_eq!(blocks["pageInfo"]["hasNextPage"], true);
+
+        // Resolution errors null the field and report its path
+        let response = handler.execute(GraphQLRequest {
+            query: "{ block(height: 999) { height } validator(address: \"validator_1\") { moniker bogus } }".to_string(),
+            ..GraphQLRequest::default()
+        });
+        let data = response.data.unwrap();
+        assert!(data["block"].is_null());
+        assert_eq!(data["validator"]["moniker"], "CC Validator 1");
+        assert_eq!(response.errors.len(), 2);
+        assert_eq!(response.errors[1].path, vec![serde_json::json!("validator"), serde_json::json!("bogus")]);
+    }
+
+    #[test]
+    fn test_graphql_rejects_costly_queries() {
+        let handler = GraphQLHandler::new(GraphQLLimits {
+            max_depth: 6,
+            max_complexity: 500,
+            ..GraphQLLimits::default()
+        });
+        let execute = |query: &str| {
+            handler.execute(GraphQLRequest {
+                query: query.to_string(),
+                ..GraphQLRequest::default()
+            })
+        };
+
+        let response = execute("{ blocks(first: 50) { nodes { transactions { hash from to amount } } } }");
+        assert!(response.data.is_none());
+        assert!(response.errors[0].message.contains("complexity"));
+
+        let response = execute("{ transactions { nodes { block { transactions { block { transactions { hash } } } } } } }");
+        assert!(response.errors[0].message.contains("deeper"));
+
+        // Nesting is bounded while parsing, before it can exhaust the stack
+        let response = execute(&"{a".repeat(200_000));
+        assert!(response.errors[0].message.contains("deeper"));
+        let response = execute(&format!("{{ block(height: {}) {{ height }} }}", "[".repeat(200_000)));
+        assert!(response.errors[0].message.contains("deeper"));
+
+        let response = execute("{ blocks(first: 1000) { totalCount } }");
+        assert!(response.errors[0].message.contains("at most"));
+
+        let response = execute("{ latestBlock { ...A } } fragment A on Block { height ...A }");
+        assert!(response.errors[0].message.contains("spreads itself"));
+
+        // Fragments that each spread the next one twice would take 2^n walks
+        let mut query = "{ ...F0 }".to_string();
+        for i in 0..64 {
+            query.push_str(&format!(" fragment F{} on Query {{ ...F{} ...F{} }}", i, i + 1, i + 1));
+        }
+        query.push_str(" fragment F64 on Query { latestBlock { height } }");
+        let response = execute(&query);
+        assert!(response.errors[0].message.contains("complexity"));
+
+        let response = execute("mutation { block(height: 1) { height } }");
+        assert!(response.errors[0].message.contains("not supported"));
+
+        assert!(execute("{ blocks(first: 5) { nodes { height transactions { hash } } } }").errors.is_empty());
+    }
+
+    #[test]
+    fn test_search_resolves_query_kinds() {
+        let handler = SearchHandler::new();
+        let search = |q: &str| handler.search(&HashMap::from([("q".to_string(), q.to_string())]));
+
+        let result = search(" 7 ").unwrap().data.unwrap();
+        assert_eq!(result.resource_type, SearchResourceType::Block);
+        assert_eq!(result.link, "/api/v1/blocks/7");
+
+        let result = search(&format!("0X{:064X}", 3)).unwrap().data.unwrap();
+        assert_eq!(result.resource_type, SearchResourceType::Block);
+        assert_eq!(result.id, "3");
+
+        let result = search(&format!("0x{:064x}", 2000)).unwrap().data.unwrap();
+        assert_eq!(result.resource_type, SearchResourceType::Transaction);
+        assert_eq!(result.link, format!("/api/v1/transactions/0x{:064x}", 2000));
+
+        let result = search(&format!("{:040x}", 10)).unwrap().data.unwrap();
+        assert_eq!(result.resource_type, SearchResourceType::Address);
+        assert_eq!(result.id, format!("0x{:040x}", 10));
+
+        assert!(matches!(search("999"), Err(HandlerError::NotFound { .. })));
+        assert!(matches!(search(&format!("0x{:064x}", 9)).map(|r| r.data), Ok(Some(_))));
+        assert!(matches!(search(&format!("0x{:064x}", 7777)), Err(HandlerError::NotFound { .. })));
+        assert!(matches!(search("validator"), Err(HandlerError::InvalidParameter { .. })));
+        assert!(matches!(search(""), Err(HandlerError::InvalidParameter { .. })));
+    }
+
+    /// Chain state and mempool stand-in for the faucet
+    struct RecordingSubmitter {
+        chain_nonce: u64,
+        submitted: std::sync::Mutex<Vec<cc_core::transaction::Transaction>>,
+    }
+
+    impl TransactionSubmitter for RecordingSubmitter {
+        fn account_nonce(&self, _account: &cc_core::crypto::CCPublicKey) -> Result<u64> {
+            Ok(self.chain_nonce)
+        }
+
+        fn submit(&self, transaction: cc_core::transaction::Transaction) -> Result<()> {
+            transaction
+                .validate()
+                .map_err(|e| HandlerError::BadRequest(e.to_string()))?;
+            self.submitted.lock().unwrap().push(transaction);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_faucet_cooldowns_and_verification() {
+        let keypair = cc_core::crypto::CCKeypair::generate();
+        let config = FaucetConfig {
+            faucet_address: hex::encode(keypair.public_key().0),
+            max_grants_per_minute: 2,
+            ..FaucetConfig::default()
+        };
+        let submitter = std::sync::Arc::new(RecordingSubmitter {
+            chain_nonce: 7,
+            submitted: std::sync::Mutex::new(Vec::new()),
+        });
+        let mut faucet = FaucetHandler::new(config.clone(), keypair.clone(), submitter.clone())
+            .unwrap()
+            .with_verifier(CaptchaVerifier::new(|token: &str, _: &str| token == "solved"));
+        let request = |n: u64, token: &str| FaucetRequest {
+            address: format!("0x{:064X}", n),
+            captcha_token: Some(token.to_string()),
+        };
+
+        let granted = faucet.request_funds(request(1, "solved"), "203.0.113.1").unwrap().data.unwrap();
+        assert_eq!(granted.amount, config.amount);
+        {
+            let submitted = submitter.submitted.lock().unwrap();
+            assert_eq!(submitted[0].from, keypair.public_key());
+            assert_eq!(submitted[0].nonce, 7);
+            assert_eq!(granted.transaction_hash, format!("0x{}", hex::encode(submitted[0].hash())));
+        }
+        assert!(matches!(
+            faucet.request_funds(request(1, "solved"), "203.0.113.2"),
+            Err(HandlerError::RateLimited { .. })
+        ));
+        assert!(matches!(
+            faucet.request_funds(request(2, "solved"), "203.0.113.1"),
+            Err(HandlerError::RateLimited { .. })
+        ));
+        assert!(matches!(
+            faucet.request_funds(request(2, "wrong"), "203.0.113.2"),
+            Err(HandlerError::Forbidden(_))
+        ));
+        faucet.request_funds(request(2, "solved"), "203.0.113.2").unwrap();
+        // Pending grants are not in chain state yet, so nonces keep counting up
+        assert_eq!(submitter.submitted.lock().unwrap()[1].nonce, 8);
+        // The per-minute cap holds even for fresh addresses and clients
+        assert!(matches!(
+            faucet.request_funds(request(3, "solved"), "203.0.113.3"),
+            Err(HandlerError::RateLimited { .. })
+        ));
+        assert!(matches!(
+            faucet.request_funds(
+                FaucetRequest {
+                    address: "0x1234".to_string(),
+                    captcha_token: Some("solved".to_string()),
+                },
+                "203.0.113.4"
+            ),
+            Err(HandlerError::InvalidParameter { .. })
+        ));
+
+        // The faucet address must be the key it signs with
+        assert!(matches!(
+            FaucetHandler::new(config.clone(), cc_core::crypto::CCKeypair::generate(), submitter.clone()),
+            Err(HandlerError::InvalidParameter { .. })
+        ));
+
+        let mut mainnet = FaucetHandler::new(
+            FaucetConfig {
+                chain_id: "cc-chain-mainnet".to_string(),
+                ..config
+            },
+            keypair,
+            submitter,
+        )
+        .unwrap();
+        assert!(!mainnet.is_enabled());
+        assert!(matches!(
+            mainnet.request_funds(request(5, "solved"), "203.0.113.5"),
+            Err(HandlerError::ServiceUnavailable(_))
+        ));
+        assert!(FaucetHandler::openapi()["/faucet"]["post"]["responses"]["429"].is_object());
+    }
+
     #[test]
     fn test_pagination_info() {
         let pagination = PaginationInfo::new(2, 10, 25);
@@ -0,0 +1,121 @@
+//! Search handler (`/search?q=`) behind the explorer search box.
+//!
+//! The query's shape decides what it can be: digits are a block height,
+//! 32-byte hex a block or transaction hash, and 20-byte hex an address.
+//! Hashes are tried as blocks first, then transactions. The result names
+//! the resource found and links to its canonical REST path.
+
+use crate::{AccountHandler, ApiResponse, BlockHandler, HandlerError, Result, TransactionHandler};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Kind of resource a search resolved to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchResourceType {
+    Block,
+    Transaction,
+    Address,
+}
+
+/// Resolved search query
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub resource_type: SearchResourceType,
+    /// Canonical identifier: height for blocks, hash for transactions
+    pub id: String,
+    pub link: String,
+}
+
+/// Search handler
+pub struct SearchHandler {
+    blocks: BlockHandler,
+    transactions: TransactionHandler,
+    accounts: AccountHandler,
+}
+
+impl SearchHandler {
+    pub fn new() -> Self {
+        Self {
+            blocks: BlockHandler::new(),
+            transactions: TransactionHandler::new(),
+            accounts: AccountHandler::new(),
+        }
+    }
+
+    /// `GET /search?q=`
+    pub fn search(&self, query: &HashMap<String, String>) -> Result<ApiResponse<SearchResult>> {
+        let q = query.get("q").map(|q| q.trim()).unwrap_or_default();
+        if q.is_empty() {
+            return Err(HandlerError::InvalidParameter {
+                param: "q".to_string(),
+                reason: "Search query cannot be empty".to_string(),
+            });
+        }
+
+        if q.bytes().all(|b| b.is_ascii_digit()) {
+            let height = q.parse::<u64>().map_err(|_| HandlerError::InvalidParameter {
+                param: "q".to_string(),
+                reason: "Block height is out of range".to_string(),
+            })?;
+            self.blocks.get_block_by_height(height)?;
+            return Ok(ApiResponse::success(block_result(height)));
+        }
+
+        let hex = q.strip_prefix("0x").or_else(|| q.strip_prefix("0X")).unwrap_or(q);
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(not_searchable());
+        }
+        let normalized = format!("0x{}", hex.to_ascii_lowercase());
+        match hex.len() {
+            64 => {
+                if let Ok(response) = self.blocks.get_block_by_hash(&normalized) {
+                    if let Some(block) = response.data {
+                        return Ok(ApiResponse::success(block_result(block.height)));
+                    }
+                }
+                match self.transactions.get_transaction(&normalized) {
+                    Ok(_) => Ok(ApiResponse::success(SearchResult {
+                        resource_type: SearchResourceType::Transaction,
+                        link: format!("/api/v1/transactions/{}", normalized),
+                        id: normalized,
+                    })),
+                    Err(_) => Err(HandlerError::NotFound {
+                        resource: format!("Block or transaction with hash {}", normalized),
+                    }),
+                }
+            }
+            40 => {
+                // Every well-formed address exists, if only with a zero balance
+                self.accounts.get_account(&normalized)?;
+                Ok(ApiResponse::success(SearchResult {
+                    resource_type: SearchResourceType::Address,
+                    link: format!("/api/v1/accounts/{}", normalized),
+                    id: normalized,
+                }))
+            }
+            _ => Err(not_searchable()),
+        }
+    }
+}
+
+impl Default for SearchHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn block_result(height: u64) -> SearchResult {
+    SearchResult {
+        resource_type: SearchResourceType::Block,
+        id: height.to_string(),
+        link: format!("/api/v1/blocks/{}", height),
+    }
+}
+
+fn not_searchable() -> HandlerError {
+    HandlerError::InvalidParameter {
+        param: "q".to_string(),
+        reason: "Expected a block height, block or transaction hash, or address".to_string(),
+    }
+}
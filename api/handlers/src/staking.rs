@@ -0,0 +1,162 @@
+//! Staking handlers: delegations and the history of delegation actions.
+
+use crate::{paginate, ApiResponse, HandlerError, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// A delegator's stake with one validator
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Delegation {
+    /// `<delegator>/<validator>`
+    pub id: String,
+    pub delegator: String,
+    pub validator: String,
+    pub amount: u64,
+    pub pending_rewards: u64,
+    /// Stake on its way out, released at `unbonding_completes_at`
+    pub unbonding: u64,
+    pub unbonding_completes_at: Option<u64>,
+    pub created_at: u64,
+}
+
+/// Kind of delegation action
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DelegationAction {
+    Delegate { amount: u64 },
+    Undelegate { amount: u64 },
+    Redelegate { to_validator: String, amount: u64 },
+    ClaimRewards { amount: u64 },
+}
+
+/// A delegation action taken by a delegator
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DelegationEvent {
+    pub delegator: String,
+    pub validator: String,
+    pub transaction_hash: String,
+    pub height: u64,
+    pub timestamp: u64,
+    pub action: DelegationAction,
+}
+
+/// Filters for listing delegations
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DelegationFilter {
+    pub delegator: Option<String>,
+    pub validator: Option<String>,
+}
+
+/// Staking handler
+pub struct StakingHandler {
+    delegations: HashMap<String, Delegation>,
+    history: Vec<DelegationEvent>,
+}
+
+impl StakingHandler {
+    pub fn new() -> Self {
+        let mut handler = Self {
+            delegations: HashMap::new(),
+            history: Vec::new(),
+        };
+
+        handler.add_sample_data();
+        handler
+    }
+
+    /// Get delegation by id
+    pub fn get_delegation(&self, id: &str) -> Result<ApiResponse<Delegation>> {
+        match self.delegations.get(id) {
+            Some(delegation) => Ok(ApiResponse::success(delegation.clone())),
+            None => Err(HandlerError::NotFound {
+                resource: format!("Delegation {}", id),
+            }),
+        }
+    }
+
+    /// List delegations matching `filter`, largest first
+    pub fn list_delegations(&self, filter: &DelegationFilter, page: u32, per_page: u32) -> Result<ApiResponse<Vec<Delegation>>> {
+        let mut delegations: Vec<Delegation> = self
+            .delegations
+            .values()
+            .filter(|delegation| filter.delegator.as_ref().is_none_or(|d| &delegation.delegator == d))
+            .filter(|delegation| filter.validator.as_ref().is_none_or(|v| &delegation.validator == v))
+            .cloned()
+            .collect();
+        delegations.sort_by(|a, b| b.amount.cmp(&a.amount).then_with(|| a.id.cmp(&b.id)));
+
+        let (page_delegations, pagination) = paginate(delegations, page, per_page)?;
+        Ok(ApiResponse::success_with_pagination(page_delegations, pagination))
+    }
+
+    /// Delegation actions of a delegator, newest first
+    pub fn get_delegation_history(&self, delegator: &str, page: u32, per_page: u32) -> Result<ApiResponse<Vec<DelegationEvent>>> {
+        if delegator.is_empty() {
+            return Err(HandlerError::InvalidParameter {
+                param: "delegator".to_string(),
+                reason: "Delegator address cannot be empty".to_string(),
+            });
+        }
+
+        let mut events: Vec<DelegationEvent> = self
+            .history
+            .iter()
+            .filter(|event| event.delegator == delegator)
+            .cloned()
+            .collect();
+        events.sort_by_key(|event| Reverse(event.height));
+
+        let (page_events, pagination) = paginate(events, page, per_page)?;
+        Ok(ApiResponse::success_with_pagination(page_events, pagination))
+    }
+
+    fn add_sample_data(&mut self) {
+        for i in 1..=5u64 {
+            let delegator = format!("0x{:040x}", i * 10);
+            let validator = format!("validator_{}", i % 3 + 1);
+            let amount = i * 50_000;
+            let delegation = Delegation {
+                id: format!("{}/{}", delegator, validator),
+                delegator: delegator.clone(),
+                validator: validator.clone(),
+                amount,
+                pending_rewards: i * 120,
+                unbonding: if i == 2 { 10_000 } else { 0 },
+                unbonding_completes_at: if i == 2 { Some(1640995200 + 21 * 86400) } else { None },
+                created_at: 1640995200 + (i * 60),
+            };
+
+            self.history.push(DelegationEvent {
+                delegator: delegator.clone(),
+                validator: validator.clone(),
+                transaction_hash: format!("0x{:064x}", i * 2000),
+                height: i,
+                timestamp: 1640995200 + (i * 60),
+                action: DelegationAction::Delegate {
+                    amount: amount + delegation.unbonding,
+                },
+            });
+            if delegation.unbonding > 0 {
+                self.history.push(DelegationEvent {
+                    delegator,
+                    validator,
+                    transaction_hash: format!("0x{:064x}", i * 2000 + 1),
+                    height: i + 5,
+                    timestamp: 1640995200 + ((i + 5) * 60),
+                    action: DelegationAction::Undelegate {
+                        amount: delegation.unbonding,
+                    },
+                });
+            }
+
+            self.delegations.insert(delegation.id.clone(), delegation);
+        }
+    }
+}
+
+impl Default for StakingHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
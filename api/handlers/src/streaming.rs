@@ -0,0 +1,232 @@
+//! Server-Sent Events streaming handlers for new blocks (`/stream/blocks`)
+//! and transactions (`/stream/txs?address=...`).
+//!
+//! Blocks and transactions are published to event buses, which hand every
+//! subscribed connection its own bounded queue. Publishing never blocks: a
+//! connection that falls a full queue behind is dropped from the bus and
+//! its stream ends, so the client reconnects instead of memory growing.
+//! While nothing arrives, streams send a heartbeat comment every interval
+//! so proxies keep the connection open.
+
+use crate::{Block, HandlerError, Result, Transaction};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Content type of event streams
+pub const EVENT_STREAM_CONTENT_TYPE: &str = "text/event-stream";
+
+/// Fans published events out to subscribers
+pub struct EventBus<T> {
+    subscribers: Mutex<Vec<SyncSender<T>>>,
+}
+
+impl<T: Clone> EventBus<T> {
+    pub fn new() -> Self {
+        Self {
+            subscribers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Receive events published from now on, up to `capacity` unread
+    pub fn subscribe(&self, capacity: usize) -> Receiver<T> {
+        let (sender, receiver) = mpsc::sync_channel(capacity.max(1));
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    /// Hand an event to every subscriber, dropping those that are full or
+    /// gone
+    pub fn publish(&self, event: &T) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.try_send(event.clone()).is_ok());
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.lock().unwrap().len()
+    }
+}
+
+impl<T: Clone> Default for EventBus<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Streaming settings
+#[derive(Debug, Clone)]
+pub struct StreamConfig {
+    pub heartbeat_interval: Duration,
+    /// Events a connection may fall behind before it is dropped
+    pub buffer_size: usize,
+    /// Reconnection delay suggested to clients
+    pub retry: Duration,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            heartbeat_interval: Duration::from_secs(15),
+            buffer_size: 256,
+            retry: Duration::from_secs(3),
+        }
+    }
+}
+
+/// Which transactions a connection receives
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransactionStreamFilter {
+    /// Only transactions from or to this address
+    pub address: Option<String>,
+}
+
+impl TransactionStreamFilter {
+    /// Filter from the stream's query parameters
+    pub fn from_query(query: &HashMap<String, String>) -> Result<Self> {
+        let address = match query.get("address") {
+            Some(address) if address.trim().is_empty() => {
+                return Err(HandlerError::InvalidParameter {
+                    param: "address".to_string(),
+                    reason: "Address cannot be empty".to_string(),
+                })
+            }
+            Some(address) => Some(address.trim().to_ascii_lowercase()),
+            None => None,
+        };
+        Ok(Self { address })
+    }
+
+    pub fn matches(&self, transaction: &Transaction) -> bool {
+        self.address.as_ref().is_none_or(|address| {
+            transaction.from.eq_ignore_ascii_case(address) || transaction.to.eq_ignore_ascii_case(address)
+        })
+    }
+}
+
+/// A stream of SSE frames for one connection
+pub struct SseStream<T> {
+    receiver: Receiver<T>,
+    event: &'static str,
+    filter: Box<dyn Fn(&T) -> bool + Send>,
+    id: fn(&T) -> String,
+    heartbeat_interval: Duration,
+    /// `retry` field sent before the first event
+    preamble: Option<String>,
+}
+
+impl<T: Serialize> SseStream<T> {
+    /// The next frame to write, waiting at most a heartbeat interval;
+    /// `None` once the stream has ended
+    pub fn next_frame(&mut self) -> Option<String> {
+        if let Some(preamble) = self.preamble.take() {
+            return Some(preamble);
+        }
+        loop {
+            match self.receiver.recv_timeout(self.heartbeat_interval) {
+                Ok(item) if (self.filter)(&item) => {
+                    let data = serde_json::to_string(&item).ok()?;
+                    return Some(format_event(&(self.id)(&item), self.event, &data));
+                }
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout) => return Some(": heartbeat\n\n".to_string()),
+                Err(RecvTimeoutError::Disconnected) => return None,
+            }
+        }
+    }
+}
+
+impl<T: Serialize> Iterator for SseStream<T> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        self.next_frame()
+    }
+}
+
+/// One SSE event; multi-line data is split over `data:` lines
+fn format_event(id: &str, event: &str, data: &str) -> String {
+    let mut frame = format!("id: {}\nevent: {}\n", id, event);
+    for line in data.lines() {
+        frame.push_str("data: ");
+        frame.push_str(line);
+        frame.push('\n');
+    }
+    frame.push('\n');
+    frame
+}
+
+/// Stream handler
+pub struct StreamHandler {
+    config: StreamConfig,
+    blocks: EventBus<Block>,
+    transactions: EventBus<Transaction>,
+}
+
+impl StreamHandler {
+    pub fn new(config: StreamConfig) -> Self {
+        Self {
+            config,
+            blocks: EventBus::new(),
+            transactions: EventBus::new(),
+        }
+    }
+
+    /// Bus consensus publishes committed blocks to
+    pub fn blocks(&self) -> &EventBus<Block> {
+        &self.blocks
+    }
+
+    /// Bus the mempool publishes accepted transactions to
+    pub fn transactions(&self) -> &EventBus<Transaction> {
+        &self.transactions
+    }
+
+    /// `GET /stream/blocks`
+    pub fn stream_blocks(&self) -> SseStream<Block> {
+        SseStream {
+            receiver: self.blocks.subscribe(self.config.buffer_size),
+            event: "block",
+            filter: Box::new(|_| true),
+            id: |block| block.height.to_string(),
+            heartbeat_interval: self.config.heartbeat_interval,
+            preamble: Some(self.preamble()),
+        }
+    }
+
+    /// `GET /stream/txs`, optionally filtered with `?address=`
+    pub fn stream_transactions(&self, query: &HashMap<String, String>) -> Result<SseStream<Transaction>> {
+        let filter = TransactionStreamFilter::from_query(query)?;
+        Ok(SseStream {
+            receiver: self.transactions.subscribe(self.config.buffer_size),
+            event: "transaction",
+            filter: Box::new(move |transaction| filter.matches(transaction)),
+            id: |transaction| transaction.hash.clone(),
+            heartbeat_interval: self.config.heartbeat_interval,
+            preamble: Some(self.preamble()),
+        })
+    }
+
+    /// Response headers of an event stream
+    pub fn response_headers(&self) -> Vec<(String, String)> {
+        vec![
+            ("Content-Type".to_string(), EVENT_STREAM_CONTENT_TYPE.to_string()),
+            ("Cache-Control".to_string(), "no-cache".to_string()),
+            // Stop nginx from buffering the stream
+            ("X-Accel-Buffering".to_string(), "no".to_string()),
+        ]
+    }
+
+    fn preamble(&self) -> String {
+        format!("retry: {}\n\n", self.config.retry.as_millis())
+    }
+}
+
+impl Default for StreamHandler {
+    fn default() -> Self {
+        Self::new(StreamConfig::default())
+    }
+}
@@ -0,0 +1,165 @@
+//! Validator handlers: the validator set, single validators and the
+//! history of changes to each.
+
+use crate::{paginate, ApiResponse, HandlerError, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Validator status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidatorStatus {
+    Active,
+    Inactive,
+    Jailed,
+}
+
+/// Validator information
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Validator {
+    pub address: String,
+    pub moniker: String,
+    pub status: ValidatorStatus,
+    pub voting_power: u64,
+    pub self_stake: u64,
+    pub delegated_stake: u64,
+    /// Commission in basis points
+    pub commission_rate: u32,
+    pub blocks_proposed: u64,
+    /// Share of recent blocks signed, in basis points
+    pub uptime: u32,
+    pub joined_at: u64,
+}
+
+/// Kind of change in a validator's history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ValidatorEventKind {
+    Joined,
+    CommissionChanged { from: u32, to: u32 },
+    Jailed { reason: String },
+    Unjailed,
+    Slashed { amount: u64 },
+}
+
+/// A change in a validator's history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidatorEvent {
+    pub validator: String,
+    pub height: u64,
+    pub timestamp: u64,
+    pub event: ValidatorEventKind,
+}
+
+/// Validator handler
+pub struct ValidatorHandler {
+    validators: HashMap<String, Validator>,
+    history: HashMap<String, Vec<ValidatorEvent>>,
+}
+
+impl ValidatorHandler {
+    pub fn new() -> Self {
+        let mut handler = Self {
+            validators: HashMap::new(),
+            history: HashMap::new(),
+        };
+
+        handler.add_sample_data();
+        handler
+    }
+
+    /// Get validator by address
+    pub fn get_validator(&self, address: &str) -> Result<ApiResponse<Validator>> {
+        match self.validators.get(address) {
+            Some(validator) => Ok(ApiResponse::success(validator.clone())),
+            None => Err(HandlerError::NotFound {
+                resource: format!("Validator {}", address),
+            }),
+        }
+    }
+
+    /// List validators, with `status` if given, by voting power
+    pub fn list_validators(&self, status: Option<ValidatorStatus>, page: u32, per_page: u32) -> Result<ApiResponse<Vec<Validator>>> {
+        let mut validators: Vec<Validator> = self
+            .validators
+            .values()
+            .filter(|validator| status.is_none_or(|status| validator.status == status))
+            .cloned()
+            .collect();
+        validators.sort_by(|a, b| b.voting_power.cmp(&a.voting_power).then_with(|| a.address.cmp(&b.address)));
+
+        let (page_validators, pagination) = paginate(validators, page, per_page)?;
+        Ok(ApiResponse::success_with_pagination(page_validators, pagination))
+    }
+
+    /// History of a validator, newest first
+    pub fn get_validator_history(&self, address: &str, page: u32, per_page: u32) -> Result<ApiResponse<Vec<ValidatorEvent>>> {
+        if !self.validators.contains_key(address) {
+            return Err(HandlerError::NotFound {
+                resource: format!("Validator {}", address),
+            });
+        }
+
+        let mut events = self.history.get(address).cloned().unwrap_or_default();
+        events.sort_by_key(|event| Reverse(event.height));
+
+        let (page_events, pagination) = paginate(events, page, per_page)?;
+        Ok(ApiResponse::success_with_pagination(page_events, pagination))
+    }
+
+    fn add_sample_data(&mut self) {
+        for i in 1..=3u64 {
+            let address = format!("validator_{}", i);
+            let validator = Validator {
+                address: address.clone(),
+                moniker: format!("CC Validator {}", i),
+                status: if i == 3 { ValidatorStatus::Jailed } else { ValidatorStatus::Active },
+                voting_power: 1_000_000 * (4 - i),
+                self_stake: 100_000 * i,
+                delegated_stake: 1_000_000 * (4 - i) - 100_000 * i,
+                commission_rate: 500 + (i as u32 * 100),
+                blocks_proposed: 10 * i,
+                uptime: 10_000 - (i as u32 * 50),
+                joined_at: 1640995200,
+            };
+
+            let mut events = vec![
+                ValidatorEvent {
+                    validator: address.clone(),
+                    height: 1,
+                    timestamp: 1640995200,
+                    event: ValidatorEventKind::Joined,
+                },
+                ValidatorEvent {
+                    validator: address.clone(),
+                    height: 4,
+                    timestamp: 1640995200 + 240,
+                    event: ValidatorEventKind::CommissionChanged {
+                        from: 500,
+                        to: validator.commission_rate,
+                    },
+                },
+            ];
+            if validator.status == ValidatorStatus::Jailed {
+                events.push(ValidatorEvent {
+                    validator: address.clone(),
+                    height: 8,
+                    timestamp: 1640995200 + 480,
+                    event: ValidatorEventKind::Jailed {
+                        reason: "Missed too many blocks".to_string(),
+                    },
+                });
+            }
+
+            self.history.insert(address.clone(), events);
+            self.validators.insert(address, validator);
+        }
+    }
+}
+
+impl Default for ValidatorHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,288 @@
+//! Network access control
+//!
+//! Requests are filtered by source address before anything else runs, so
+//! blocked sources cost a lookup rather than a signature check. Deny rules
+//! win over allow rules, a non-empty allowlist blocks every source outside
+//! it, and with a GeoIP lookup configured, sources in blocked countries are
+//! rejected too. Routes can replace the default rules; the most specific
+//! matching route applies.
+
+use crate::rbac::path_matches;
+use crate::{MiddlewareError, RequestContext, Result};
+use std::collections::HashSet;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// An address block such as `10.0.0.0/8`; a bare address is a block of one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, canonical(ip)) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                prefix_eq(&network.octets(), &ip.octets(), self.prefix)
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                prefix_eq(&network.octets(), &ip.octets(), self.prefix)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = MiddlewareError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || MiddlewareError::Validation {
+            reason: format!("Invalid CIDR block: {}", s),
+        };
+        let (address, prefix) = match s.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (s, None),
+        };
+        let network = canonical(address.trim().parse().map_err(|_| invalid())?);
+        let max: u8 = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix.trim().parse().map_err(|_| invalid())?,
+            None => max,
+        };
+        if prefix > max {
+            return Err(invalid());
+        }
+        Ok(Self { network, prefix })
+    }
+}
+
+/// IPv4-mapped IPv6 addresses as the IPv4 addresses they are
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        ip => ip,
+    }
+}
+
+fn prefix_eq(a: &[u8], b: &[u8], prefix: u8) -> bool {
+    let bytes = prefix as usize / 8;
+    let bits = prefix % 8;
+    if a[..bytes] != b[..bytes] {
+        return false;
+    }
+    bits == 0 || (a[bytes] ^ b[bytes]) >> (8 - bits) == 0
+}
+
+/// Country of an address
+pub trait GeoIpLookup: Send + Sync {
+    /// ISO 3166-1 alpha-2 code, if the address is known
+    fn country(&self, ip: IpAddr) -> Option<String>;
+}
+
+/// Countries by address block, as exported from GeoIP databases
+#[derive(Debug, Clone, Default)]
+pub struct CidrCountryTable {
+    blocks: Vec<(Cidr, String)>,
+}
+
+impl CidrCountryTable {
+    /// Parse `network,country` lines; blank lines and `#` comments are
+    /// skipped
+    pub fn parse_csv(csv: &str) -> Result<Self> {
+        let mut blocks: Vec<(Cidr, String)> = Vec::new();
+        for line in csv.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (network, country) =
+                line.split_once(',')
+                    .ok_or_else(|| MiddlewareError::Validation {
+                        reason: format!("Invalid GeoIP line: {}", line),
+                    })?;
+            blocks.push((network.parse()?, country.trim().to_ascii_uppercase()));
+        }
+        // Most specific blocks first
+        blocks.sort_by_key(|(block, _)| std::cmp::Reverse(block.prefix));
+        Ok(Self { blocks })
+    }
+}
+
+impl GeoIpLookup for CidrCountryTable {
+    fn country(&self, ip: IpAddr) -> Option<String> {
+        self.blocks
+            .iter()
+            .find(|(block, _)| block.contains(ip))
+            .map(|(_, country)| country.clone())
+    }
+}
+
+/// Allow and deny rules
+#[derive(Debug, Clone, Default)]
+pub struct AclRules {
+    /// When not empty, only these sources are let in
+    pub allow: Vec<Cidr>,
+    pub deny: Vec<Cidr>,
+    /// Country codes rejected when a GeoIP lookup is configured
+    pub blocked_countries: HashSet<String>,
+}
+
+impl AclRules {
+    /// Rules from CIDR strings
+    pub fn from_lists(allow: &[&str], deny: &[&str]) -> Result<Self> {
+        let parse = |blocks: &[&str]| -> Result<Vec<Cidr>> {
+            blocks.iter().map(|block| block.parse()).collect()
+        };
+        Ok(Self {
+            allow: parse(allow)?,
+            deny: parse(deny)?,
+            blocked_countries: HashSet::new(),
+        })
+    }
+
+    pub fn block_countries(mut self, countries: &[&str]) -> Self {
+        self.blocked_countries
+            .extend(countries.iter().map(|country| country.to_ascii_uppercase()));
+        self
+    }
+}
+
+/// Network ACL middleware
+#[derive(Default)]
+pub struct NetworkAclMiddleware {
+    rules: AclRules,
+    /// Path patterns, as in access rules, with the rules replacing the
+    /// defaults there
+    routes: Vec<(String, AclRules)>,
+    geoip: Option<Arc<dyn GeoIpLookup>>,
+}
+
+impl NetworkAclMiddleware {
+    pub fn new(rules: AclRules) -> Self {
+        Self {
+            rules,
+            routes: Vec::new(),
+            geoip: None,
+        }
+    }
+
+    /// Apply `rules` instead of the defaults to paths matching `path`
+    pub fn with_route(mut self, path: &str, rules: AclRules) -> Self {
+        self.routes.push((path.to_string(), rules));
+        self
+    }
+
+    pub fn with_geoip(mut self, geoip: Arc<dyn GeoIpLookup>) -> Self {
+        self.geoip = Some(geoip);
+        self
+    }
+
+    /// Reject requests from blocked sources
+    pub fn process(&self, context: &RequestContext) -> Result<()> {
+        let rules = self
+            .routes
+            .iter()
+            .filter(|(pattern, _)| path_matches(pattern, &context.path))
+            .max_by_key(|(pattern, _)| pattern.trim_end_matches('*').len())
+            .map(|(_, rules)| rules)
+            .unwrap_or(&self.rules);
+        let source = context.remote_addr.as_deref().and_then(parse_source);
+        let denied = |reason: String| Err(MiddlewareError::AccessDenied { reason });
+
+        let Some(ip) = source else {
+            if rules.allow.is_empty() {
+                return Ok(());
+            }
+            return denied("Unknown source address".to_string());
+        };
+        if rules.deny.iter().any(|block| block.contains(ip)) {
+            return denied(format!("{} is denied", ip));
+        }
+        if !rules.allow.is_empty() && !rules.allow.iter().any(|block| block.contains(ip)) {
+            return denied(format!("{} is not allowed", ip));
+        }
+        if !rules.blocked_countries.is_empty() {
+            let country = self.geoip.as_ref().and_then(|geoip| geoip.country(ip));
+            if let Some(country) = country.filter(|c| rules.blocked_countries.contains(c)) {
+                return denied(format!("Requests from {} are blocked", country));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An address with or without a port
+fn parse_source(remote_addr: &str) -> Option<IpAddr> {
+    remote_addr
+        .parse::<SocketAddr>()
+        .map(|socket| socket.ip())
+        .or_else(|_| remote_addr.parse())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from(path: &str, remote_addr: &str) -> RequestContext {
+        let mut context = RequestContext::new("GET".to_string(), path.to_string());
+        context.remote_addr = Some(remote_addr.to_string());
+        context
+    }
+
+    #[test]
+    fn test_cidr() {
+        let block: Cidr = "10.1.0.0/16".parse().unwrap();
+        assert!(block.contains("10.1.200.3".parse().unwrap()));
+        assert!(!block.contains("10.2.0.1".parse().unwrap()));
+        assert!(block.contains("::ffff:10.1.0.9".parse().unwrap()));
+        let v6: Cidr = "2001:db8::/33".parse().unwrap();
+        assert!(v6.contains("2001:db8:7fff::1".parse().unwrap()));
+        assert!(!v6.contains("2001:db8:8000::1".parse().unwrap()));
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("0.0.0.0/0"
+            .parse::<Cidr>()
+            .unwrap()
+            .contains("8.8.8.8".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_rules_routes_and_countries() {
+        let geoip =
+            CidrCountryTable::parse_csv("# test\n203.0.0.0/8,au\n203.0.113.0/24,XX\n").unwrap();
+        let acl = NetworkAclMiddleware::new(
+            AclRules::from_lists(&[], &["192.0.2.0/24"])
+                .unwrap()
+                .block_countries(&["xx"]),
+        )
+        .with_route(
+            "/admin/*",
+            AclRules::from_lists(&["10.0.0.0/8"], &["10.9.0.0/16"]).unwrap(),
+        )
+        .with_geoip(Arc::new(geoip));
+
+        assert!(acl
+            .process(&from("/api/v1/blocks", "198.51.100.7:4000"))
+            .is_ok());
+        assert!(acl
+            .process(&from("/api/v1/blocks", "192.0.2.1:4000"))
+            .is_err());
+        assert!(acl.process(&from("/api/v1/blocks", "203.0.113.5")).is_err());
+        assert!(acl.process(&from("/api/v1/blocks", "203.0.114.5")).is_ok());
+
+        // The admin route only admits the allowlist, minus its denied block
+        assert!(acl.process(&from("/admin/keys", "10.1.2.3:80")).is_ok());
+        assert!(acl.process(&from("/admin/keys", "10.9.2.3:80")).is_err());
+        assert!(acl
+            .process(&from("/admin/keys", "198.51.100.7:80"))
+            .is_err());
+        assert!(acl
+            .process(&RequestContext::new(
+                "GET".to_string(),
+                "/admin".to_string()
+            ))
+            .is_err());
+    }
+}
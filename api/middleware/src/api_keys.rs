@@ -0,0 +1,428 @@
+//! API keys
+//!
+//! Keys are issued as `cc_<key id>_<secret>`. Only a SHA-256 hash of the
+//! full key is stored, under its key id, so a leaked store does not leak
+//! usable keys; a presented key is looked up by its id and accepted when
+//! its hash matches and it is neither revoked nor expired. The scopes of a
+//! key are the permissions it grants. Rotating a key issues a new one for
+//! the same user and scopes and revokes the old one.
+
+use crate::{AuthResult, MiddlewareError, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use cc_core_storage::Storage;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Prefix of every issued key
+pub const API_KEY_PREFIX: &str = "cc";
+
+/// Permission needed to use the admin API
+pub const ADMIN_PERMISSION: &str = "admin";
+
+/// A stored API key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiKeyRecord {
+    pub key_id: String,
+    pub user_id: String,
+    /// Hex SHA-256 of the full key
+    pub key_hash: String,
+    /// Permissions the key grants
+    pub scopes: Vec<String>,
+    /// Unix seconds
+    pub created_at: u64,
+    pub expires_at: Option<u64>,
+    pub revoked_at: Option<u64>,
+}
+
+impl ApiKeyRecord {
+    /// Whether the key is accepted at unix second `now`
+    pub fn is_active(&self, now: u64) -> bool {
+        self.revoked_at.is_none() && self.expires_at.is_none_or(|expires| now < expires)
+    }
+}
+
+/// Where API key records are kept
+pub trait ApiKeyStore: Send + Sync {
+    fn get(&self, key_id: &str) -> Result<Option<ApiKeyRecord>>;
+    /// Insert or replace the record with the same key id
+    fn put(&self, record: ApiKeyRecord) -> Result<()>;
+    fn list(&self) -> Result<Vec<ApiKeyRecord>>;
+}
+
+/// Keeps records in memory only
+#[derive(Default)]
+pub struct InMemoryApiKeyStore {
+    records: RwLock<HashMap<String, ApiKeyRecord>>,
+}
+
+impl InMemoryApiKeyStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ApiKeyStore for InMemoryApiKeyStore {
+    fn get(&self, key_id: &str) -> Result<Option<ApiKeyRecord>> {
+        Ok(self.records.read().unwrap().get(key_id).cloned())
+    }
+
+    fn put(&self, record: ApiKeyRecord) -> Result<()> {
+        self.records
+            .write()
+            .unwrap()
+            .insert(record.key_id.clone(), record);
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<ApiKeyRecord>> {
+        Ok(self.records.read().unwrap().values().cloned().collect())
+    }
+}
+
+/// Keeps records in a storage backend, one entry per key id, so they
+/// survive restarts and replicas sharing the backend share them
+pub struct StorageApiKeyStore {
+    storage: Arc<dyn Storage>,
+}
+
+impl StorageApiKeyStore {
+    /// Keep records in `storage`, which should be reserved for API keys,
+    /// such as a namespace of its own
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self { storage }
+    }
+}
+
+impl ApiKeyStore for StorageApiKeyStore {
+    fn get(&self, key_id: &str) -> Result<Option<ApiKeyRecord>> {
+        self.storage
+            .get(key_id.as_bytes())
+            .map_err(storage_error)?
+            .map(|value| decode_record(&value))
+            .transpose()
+    }
+
+    fn put(&self, record: ApiKeyRecord) -> Result<()> {
+        let value = serde_json::to_vec(&record).map_err(|e| MiddlewareError::Generic(e.to_string()))?;
+        self.storage
+            .put(record.key_id.as_bytes(), &value)
+            .map_err(storage_error)
+    }
+
+    fn list(&self) -> Result<Vec<ApiKeyRecord>> {
+        self.storage
+            .scan_prefix(&[])
+            .map_err(storage_error)?
+            .map(|entry| decode_record(&entry.map_err(storage_error)?.1))
+            .collect()
+    }
+}
+
+fn decode_record(value: &[u8]) -> Result<ApiKeyRecord> {
+    serde_json::from_slice(value)
+        .map_err(|e| MiddlewareError::Generic(format!("Invalid API key record: {}", e)))
+}
+
+fn storage_error(error: cc_core_storage::StorageError) -> MiddlewareError {
+    MiddlewareError::Generic(format!("API key store failed: {}", error))
+}
+
+/// A newly issued key; `api_key` is shown once and cannot be recovered
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssuedApiKey {
+    pub api_key: String,
+    pub record: ApiKeyRecord,
+}
+
+/// Admin API requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ApiKeyAdminRequest {
+    Issue {
+        user_id: String,
+        scopes: Vec<String>,
+        ttl_secs: Option<u64>,
+    },
+    Rotate {
+        key_id: String,
+    },
+    Revoke {
+        key_id: String,
+    },
+    List {
+        user_id: Option<String>,
+    },
+}
+
+/// Admin API responses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum ApiKeyAdminResponse {
+    Issued(IssuedApiKey),
+    Revoked { key_id: String },
+    Keys { keys: Vec<ApiKeyRecord> },
+}
+
+/// Issues, checks and revokes API keys
+pub struct ApiKeyManager {
+    store: Arc<dyn ApiKeyStore>,
+}
+
+impl ApiKeyManager {
+    pub fn new(store: Arc<dyn ApiKeyStore>) -> Self {
+        Self { store }
+    }
+
+    /// Issue a key for `user_id`, expiring after `ttl` if given
+    pub fn issue(
+        &self,
+        user_id: &str,
+        scopes: Vec<String>,
+        ttl: Option<Duration>,
+    ) -> Result<IssuedApiKey> {
+        self.issue_at(user_id, scopes, ttl, unix_now())
+    }
+
+    /// Replace a key with a new one for the same user and scopes, valid as
+    /// long as the old one was
+    pub fn rotate(&self, key_id: &str) -> Result<IssuedApiKey> {
+        let now = unix_now();
+        let old = self.active_record(key_id, now)?;
+        let ttl = old
+            .expires_at
+            .map(|expires| Duration::from_secs(expires.saturating_sub(old.created_at)));
+        let issued = self.issue_at(&old.user_id, old.scopes.clone(), ttl, now)?;
+        self.store.put(ApiKeyRecord {
+            revoked_at: Some(now),
+            ..old
+        })?;
+        Ok(issued)
+    }
+
+    pub fn revoke(&self, key_id: &str) -> Result<()> {
+        let now = unix_now();
+        let record = self.active_record(key_id, now)?;
+        self.store.put(ApiKeyRecord {
+            revoked_at: Some(now),
+            ..record
+        })
+    }
+
+    /// Keys, of `user_id` if given, oldest first
+    pub fn list(&self, user_id: Option<&str>) -> Result<Vec<ApiKeyRecord>> {
+        let mut keys: Vec<ApiKeyRecord> = self
+            .store
+            .list()?
+            .into_iter()
+            .filter(|record| user_id.is_none_or(|user| record.user_id == user))
+            .collect();
+        keys.sort_by(|a, b| (a.created_at, &a.key_id).cmp(&(b.created_at, &b.key_id)));
+        Ok(keys)
+    }
+
+    /// The record of a presented key, if the key is valid
+    pub fn authenticate(&self, api_key: &str) -> Result<ApiKeyRecord> {
+        self.authenticate_at(api_key, unix_now())
+    }
+
+    /// Serve an admin API request for a caller with the admin permission
+    pub fn handle_admin(
+        &self,
+        caller: &AuthResult,
+        request: ApiKeyAdminRequest,
+    ) -> Result<ApiKeyAdminResponse> {
+        if !caller.has_permission(ADMIN_PERMISSION) {
+            return Err(MiddlewareError::Authorization {
+                reason: "Managing API keys requires the admin permission".to_string(),
+            });
+        }
+        Ok(match request {
+            ApiKeyAdminRequest::Issue {
+                user_id,
+                scopes,
+                ttl_secs,
+            } => ApiKeyAdminResponse::Issued(self.issue(
+                &user_id,
+                scopes,
+                ttl_secs.map(Duration::from_secs),
+            )?),
+            ApiKeyAdminRequest::Rotate { key_id } => {
+                ApiKeyAdminResponse::Issued(self.rotate(&key_id)?)
+            }
+            ApiKeyAdminRequest::Revoke { key_id } => {
+                self.revoke(&key_id)?;
+                ApiKeyAdminResponse::Revoked { key_id }
+            }
+            ApiKeyAdminRequest::List { user_id } => ApiKeyAdminResponse::Keys {
+                keys: self.list(user_id.as_deref())?,
+            },
+        })
+    }
+
+    fn issue_at(
+        &self,
+        user_id: &str,
+        scopes: Vec<String>,
+        ttl: Option<Duration>,
+        now: u64,
+    ) -> Result<IssuedApiKey> {
+        let mut id = [0u8; 8];
+        let mut secret = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut id);
+        rand::thread_rng().fill_bytes(&mut secret);
+        let key_id = hex::encode(id);
+        let api_key = format!(
+            "{}_{}_{}",
+            API_KEY_PREFIX,
+            key_id,
+            URL_SAFE_NO_PAD.encode(secret)
+        );
+        let record = ApiKeyRecord {
+            key_id,
+            user_id: user_id.to_string(),
+            key_hash: hash_key(&api_key),
+            scopes,
+            created_at: now,
+            expires_at: ttl.map(|ttl| now + ttl.as_secs()),
+            revoked_at: None,
+        };
+        self.store.put(record.clone())?;
+        Ok(IssuedApiKey { api_key, record })
+    }
+
+    fn active_record(&self, key_id: &str, now: u64) -> Result<ApiKeyRecord> {
+        match self.store.get(key_id)? {
+            Some(record) if record.is_active(now) => Ok(record),
+            Some(_) => Err(MiddlewareError::Validation {
+                reason: format!("API key {} is revoked or expired", key_id),
+            }),
+            None => Err(MiddlewareError::Validation {
+                reason: format!("Unknown API key {}", key_id),
+            }),
+        }
+    }
+
+    fn authenticate_at(&self, api_key: &str, now: u64) -> Result<ApiKeyRecord> {
+        let invalid = |reason: &str| MiddlewareError::Authentication {
+            reason: reason.to_string(),
+        };
+        let key_id = api_key
+            .strip_prefix(API_KEY_PREFIX)
+            .and_then(|rest| rest.strip_prefix('_'))
+            .and_then(|rest| rest.split_once('_'))
+            .map(|(key_id, _)| key_id)
+            .ok_or_else(|| invalid("Invalid API key"))?;
+        let record = self
+            .store
+            .get(key_id)?
+            .filter(|record| {
+                crate::constant_time_eq(record.key_hash.as_bytes(), hash_key(api_key).as_bytes())
+            })
+            .ok_or_else(|| invalid("Invalid API key"))?;
+        if record.revoked_at.is_some() {
+            return Err(invalid("API key has been revoked"));
+        }
+        if !record.is_active(now) {
+            return Err(invalid("API key has expired"));
+        }
+        Ok(record)
+    }
+}
+
+fn hash_key(api_key: &str) -> String {
+    hex::encode(Sha256::digest(api_key.as_bytes()))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue_authenticate_rotate_revoke() {
+        let manager = ApiKeyManager::new(Arc::new(InMemoryApiKeyStore::new()));
+        let issued = manager
+            .issue("alice", vec!["read".to_string()], None)
+            .unwrap();
+        assert!(issued.api_key.starts_with("cc_"));
+        assert_ne!(issued.record.key_hash, issued.api_key);
+        assert_eq!(
+            manager.authenticate(&issued.api_key).unwrap().user_id,
+            "alice"
+        );
+
+        // A forged secret for a real key id is rejected
+        let forged = format!("cc_{}_forged", issued.record.key_id);
+        assert!(manager.authenticate(&forged).is_err());
+        assert!(manager.authenticate("garbage").is_err());
+
+        let rotated = manager.rotate(&issued.record.key_id).unwrap();
+        assert_eq!(rotated.record.scopes, vec!["read"]);
+        assert!(manager.authenticate(&issued.api_key).is_err());
+        assert!(manager.authenticate(&rotated.api_key).is_ok());
+
+        manager.revoke(&rotated.record.key_id).unwrap();
+        assert!(manager.authenticate(&rotated.api_key).is_err());
+        assert!(manager.revoke(&rotated.record.key_id).is_err());
+        assert_eq!(manager.list(Some("alice")).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_expiration_and_admin_permission() {
+        let manager = ApiKeyManager::new(Arc::new(InMemoryApiKeyStore::new()));
+        let admin = AuthResult::ApiKey {
+            key_id: "root".to_string(),
+            user_id: "root".to_string(),
+            permissions: vec![ADMIN_PERMISSION.to_string()],
+        };
+        let request = ApiKeyAdminRequest::Issue {
+            user_id: "bob".to_string(),
+            scopes: vec!["read".to_string()],
+            ttl_secs: Some(60),
+        };
+        assert!(matches!(
+            manager.handle_admin(&AuthResult::Anonymous, request.clone()),
+            Err(MiddlewareError::Authorization { .. })
+        ));
+        let ApiKeyAdminResponse::Issued(issued) = manager.handle_admin(&admin, request).unwrap()
+        else {
+            panic!("Expected an issued key");
+        };
+        let created = issued.record.created_at;
+        assert!(manager
+            .authenticate_at(&issued.api_key, created + 59)
+            .is_ok());
+        assert!(manager
+            .authenticate_at(&issued.api_key, created + 60)
+            .is_err());
+    }
+
+    #[test]
+    fn test_storage_store_persists() {
+        let storage: Arc<dyn Storage> = Arc::new(cc_core_storage::InMemoryStorage::new());
+        let issued = ApiKeyManager::new(Arc::new(StorageApiKeyStore::new(storage.clone())))
+            .issue("carol", vec!["write".to_string()], None)
+            .unwrap();
+
+        // Another store over the same storage sees the key
+        let reopened = ApiKeyManager::new(Arc::new(StorageApiKeyStore::new(storage.clone())));
+        assert_eq!(
+            reopened.authenticate(&issued.api_key).unwrap(),
+            issued.record
+        );
+        assert_eq!(reopened.list(Some("carol")).unwrap(), vec![issued.record.clone()]);
+        let stored = storage.get(issued.record.key_id.as_bytes()).unwrap().unwrap();
+        assert!(!String::from_utf8(stored).unwrap().contains(&issued.api_key));
+    }
+}
@@ -0,0 +1,400 @@
+//! Audit logging
+//!
+//! `LoggingMiddleware` records each request, the decision taken on it and
+//! the response as structured JSON events, correlated by request id, and
+//! hands them to its sinks: stdout, a file, syslog or an OTLP collector.
+//!
+//! Files are hash-chained: each line carries the hash of the line before
+//! it and a hash over its own sequence number, event and that previous
+//! hash, so editing, dropping or reordering lines breaks the chain from
+//! that point on, which `verify_audit_log` reports. A reopened file
+//! continues its chain.
+
+use crate::{http, MiddlewareError, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::net::UdpSocket;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Hash the first line of a file chains from
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// What an event records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEventKind {
+    Request,
+    Decision,
+    Response,
+}
+
+/// The outcome of the middleware for a request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditDecision {
+    pub allowed: bool,
+    /// Why the request was refused
+    pub reason: Option<String>,
+}
+
+/// A structured audit event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub kind: AuditEventKind,
+    /// Unix millis
+    pub timestamp_ms: u64,
+    pub request_id: String,
+    pub method: String,
+    pub path: String,
+    pub remote_addr: Option<String>,
+    pub user_agent: Option<String>,
+    pub user_id: Option<String>,
+    /// Request headers, with sensitive values masked
+    pub headers: Option<BTreeMap<String, String>>,
+    pub decision: Option<AuditDecision>,
+    pub status: Option<u16>,
+    pub duration_ms: Option<u64>,
+    pub size: Option<usize>,
+}
+
+impl AuditEvent {
+    pub(crate) fn new(kind: AuditEventKind, context: &crate::RequestContext) -> Self {
+        Self {
+            kind,
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            request_id: context.request_id.clone(),
+            method: context.method.clone(),
+            path: context.path.clone(),
+            remote_addr: context.remote_addr.clone(),
+            user_agent: context.user_agent.clone(),
+            user_id: None,
+            headers: None,
+            decision: None,
+            status: None,
+            duration_ms: None,
+            size: None,
+        }
+    }
+}
+
+/// Where audit events go
+pub trait AuditSink: Send + Sync {
+    fn write(&self, event: &AuditEvent) -> Result<()>;
+}
+
+/// One JSON event per line on stdout
+pub struct StdoutAuditSink;
+
+impl AuditSink for StdoutAuditSink {
+    fn write(&self, event: &AuditEvent) -> Result<()> {
+        println!("{}", to_json(event)?);
+        Ok(())
+    }
+}
+
+/// A line of a hash-chained audit file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub sequence: u64,
+    pub prev_hash: String,
+    pub hash: String,
+    pub event: AuditEvent,
+}
+
+fn chain_hash(sequence: u64, prev_hash: &str, event: &AuditEvent) -> Result<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(sequence.to_be_bytes());
+    hasher.update(prev_hash.as_bytes());
+    hasher.update(to_json(event)?.as_bytes());
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Appends hash-chained events to a file
+pub struct FileAuditSink {
+    path: PathBuf,
+    state: Mutex<(File, u64, String)>,
+}
+
+impl FileAuditSink {
+    /// Open `path` for appending, continuing the chain of its last line
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let error = |e: std::io::Error| file_error(&path, e);
+        let (mut next, mut prev_hash) = (0, GENESIS_HASH.to_string());
+        if path.exists() {
+            let file = File::open(&path).map_err(error)?;
+            if let Some(line) = BufReader::new(file).lines().last() {
+                let record = parse_record(&path, &line.map_err(error)?)?;
+                next = record.sequence + 1;
+                prev_hash = record.hash;
+            }
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(error)?;
+        Ok(Self {
+            path,
+            state: Mutex::new((file, next, prev_hash)),
+        })
+    }
+}
+
+impl AuditSink for FileAuditSink {
+    fn write(&self, event: &AuditEvent) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let (file, next, prev_hash) = &mut *state;
+        let record = AuditRecord {
+            sequence: *next,
+            prev_hash: prev_hash.clone(),
+            hash: chain_hash(*next, prev_hash, event)?,
+            event: event.clone(),
+        };
+        let line = format!("{}\n", to_json(&record)?);
+        file.write_all(line.as_bytes())
+            .and_then(|_| file.flush())
+            .map_err(|e| file_error(&self.path, e))?;
+        *next += 1;
+        *prev_hash = record.hash;
+        Ok(())
+    }
+}
+
+/// Check the chain of an audit file, returning how many records it holds
+pub fn verify_audit_log(path: &Path) -> Result<u64> {
+    let file = File::open(path).map_err(|e| file_error(path, e))?;
+    let mut prev_hash = GENESIS_HASH.to_string();
+    let mut count = 0;
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let record = parse_record(path, &line.map_err(|e| file_error(path, e))?)?;
+        let intact = record.sequence == count
+            && record.prev_hash == prev_hash
+            && record.hash == chain_hash(record.sequence, &record.prev_hash, &record.event)?;
+        if !intact {
+            return Err(MiddlewareError::Generic(format!(
+                "Audit log {} is broken at line {}",
+                path.display(),
+                index + 1
+            )));
+        }
+        prev_hash = record.hash;
+        count += 1;
+    }
+    Ok(count)
+}
+
+fn parse_record(path: &Path, line: &str) -> Result<AuditRecord> {
+    serde_json::from_str(line).map_err(|e| {
+        MiddlewareError::Generic(format!("Invalid audit log {}: {}", path.display(), e))
+    })
+}
+
+fn file_error(path: &Path, error: std::io::Error) -> MiddlewareError {
+    MiddlewareError::Generic(format!("Audit log {} failed: {}", path.display(), error))
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String> {
+    serde_json::to_string(value).map_err(|e| MiddlewareError::Generic(e.to_string()))
+}
+
+/// Sends events to a syslog server over UDP, as RFC 5424 messages with
+/// the event as JSON
+pub struct SyslogAuditSink {
+    socket: UdpSocket,
+    server: String,
+    app_name: String,
+    hostname: String,
+}
+
+impl SyslogAuditSink {
+    pub fn new(server: String, app_name: String) -> Result<Self> {
+        let socket = UdpSocket::bind("0.0.0.0:0")
+            .map_err(|e| MiddlewareError::Generic(format!("Syslog socket failed: {}", e)))?;
+        let hostname = std::env::var("HOSTNAME").unwrap_or_else(|_| "-".to_string());
+        Ok(Self {
+            socket,
+            server,
+            app_name,
+            hostname,
+        })
+    }
+
+    fn message(&self, event: &AuditEvent) -> Result<String> {
+        // Facility 13 (log audit); refusals are warnings, the rest notices
+        let severity = match &event.decision {
+            Some(decision) if !decision.allowed => 4,
+            _ => 5,
+        };
+        Ok(format!(
+            "<{}>1 - {} {} - {} - {}",
+            13 * 8 + severity,
+            self.hostname,
+            self.app_name,
+            match event.kind {
+                AuditEventKind::Request => "request",
+                AuditEventKind::Decision => "decision",
+                AuditEventKind::Response => "response",
+            },
+            to_json(event)?
+        ))
+    }
+}
+
+impl AuditSink for SyslogAuditSink {
+    fn write(&self, event: &AuditEvent) -> Result<()> {
+        self.socket
+            .send_to(self.message(event)?.as_bytes(), &self.server)
+            .map_err(|e| MiddlewareError::Generic(format!("Syslog send failed: {}", e)))?;
+        Ok(())
+    }
+}
+
+/// Exports events as OTLP/HTTP JSON log records
+pub struct OtlpAuditSink {
+    endpoint: String,
+    service_name: String,
+    timeout: Duration,
+}
+
+impl OtlpAuditSink {
+    /// Export to a collector's logs endpoint, such as
+    /// `http://collector:4318/v1/logs`
+    pub fn new(endpoint: String, service_name: String) -> Self {
+        Self {
+            endpoint,
+            service_name,
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    fn payload(&self, event: &AuditEvent) -> Result<serde_json::Value> {
+        let attribute = |key: &str, value: &str| serde_json::json!({"key": key, "value": {"stringValue": value}});
+        let mut attributes = vec![
+            attribute("request.id", &event.request_id),
+            attribute("http.method", &event.method),
+            attribute("url.path", &event.path),
+        ];
+        if let Some(user_id) = &event.user_id {
+            attributes.push(attribute("enduser.id", user_id));
+        }
+        if let Some(status) = event.status {
+            attributes.push(serde_json::json!({
+                "key": "http.status_code",
+                "value": {"intValue": status.to_string()}
+            }));
+        }
+        let denied = event.decision.as_ref().is_some_and(|d| !d.allowed);
+        Ok(serde_json::json!({
+            "resourceLogs": [{
+                "resource": {"attributes": [attribute("service.name", &self.service_name)]},
+                "scopeLogs": [{
+                    "scope": {"name": "cc-chain-audit"},
+                    "logRecords": [{
+                        "timeUnixNano": (event.timestamp_ms as u128 * 1_000_000).to_string(),
+                        "severityNumber": if denied { 13 } else { 9 },
+                        "severityText": if denied { "WARN" } else { "INFO" },
+                        "body": {"stringValue": to_json(event)?},
+                        "attributes": attributes,
+                    }]
+                }]
+            }]
+        }))
+    }
+}
+
+impl AuditSink for OtlpAuditSink {
+    fn write(&self, event: &AuditEvent) -> Result<()> {
+        let body = to_json(&self.payload(event)?)?;
+        let error = |reason: &dyn std::fmt::Display| {
+            MiddlewareError::Generic(format!(
+                "OTLP export to {} failed: {}",
+                self.endpoint, reason
+            ))
+        };
+        let response = http::request(
+            "POST",
+            &self.endpoint,
+            Some(("application/json", body.as_bytes())),
+            self.timeout,
+        )
+        .map_err(|e| error(&e))?;
+        if !(200..300).contains(&response.status) {
+            return Err(error(&format!("collector returned {}", response.status)));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RequestContext;
+
+    fn event(kind: AuditEventKind) -> AuditEvent {
+        let context = RequestContext::new("GET".to_string(), "/api/v1/blocks".to_string());
+        AuditEvent::new(kind, &context)
+    }
+
+    #[test]
+    fn test_hash_chain_detects_tampering() {
+        let path = std::env::temp_dir().join(format!("cc-audit-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let sink = FileAuditSink::open(&path).unwrap();
+        sink.write(&event(AuditEventKind::Request)).unwrap();
+        sink.write(&event(AuditEventKind::Decision)).unwrap();
+        drop(sink);
+        // A reopened file continues the chain
+        FileAuditSink::open(&path)
+            .unwrap()
+            .write(&event(AuditEventKind::Response))
+            .unwrap();
+        assert_eq!(verify_audit_log(&path).unwrap(), 3);
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, log.replace("/api/v1/blocks", "/api/v1/peers")).unwrap();
+        assert!(verify_audit_log(&path).is_err());
+        let lines: Vec<&str> = log.lines().collect();
+        std::fs::write(&path, format!("{}\n{}\n", lines[0], lines[2])).unwrap();
+        assert!(verify_audit_log(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_syslog_and_otlp_formats() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sink = SyslogAuditSink::new(
+            server.local_addr().unwrap().to_string(),
+            "cc-api".to_string(),
+        )
+        .unwrap();
+        let mut denied = event(AuditEventKind::Decision);
+        denied.decision = Some(AuditDecision {
+            allowed: false,
+            reason: Some("no".to_string()),
+        });
+        sink.write(&denied).unwrap();
+        let mut buffer = [0u8; 2048];
+        let read = server.recv(&mut buffer).unwrap();
+        let message = String::from_utf8_lossy(&buffer[..read]);
+        assert!(message.starts_with("<108>1 - "));
+        assert!(message.contains(" cc-api - decision - {"));
+
+        let payload = OtlpAuditSink::new(String::new(), "cc-api".to_string())
+            .payload(&denied)
+            .unwrap();
+        let record = &payload["resourceLogs"][0]["scopeLogs"][0]["logRecords"][0];
+        assert_eq!(record["severityText"], "WARN");
+        assert_eq!(
+            record["attributes"][0]["value"]["stringValue"],
+            denied.request_id
+        );
+    }
+}
@@ -0,0 +1,347 @@
+//! JSON Web Key Sets
+//!
+//! Identity providers publish the keys their tokens are signed with as a
+//! JWKS document and rotate them from time to time. `JwksCache` fetches
+//! the set, keeps it for a refresh interval and fetches it again early
+//! when a token names a key it has not seen, at most once per cooldown, so
+//! tokens signed with a new key are accepted as soon as it is published
+//! while bogus key ids cannot make it hammer the provider. When a refresh
+//! fails, the keys already fetched stay in use.
+
+use crate::http;
+use crate::jwt::JwtAlgorithm;
+use crate::{MiddlewareError, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::hmac;
+use ring::signature::{
+    RsaPublicKeyComponents, UnparsedPublicKey, ECDSA_P256_SHA256_FIXED, RSA_PKCS1_2048_8192_SHA256,
+};
+use serde::Deserialize;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// Key material a signature is checked against
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerifyingKey {
+    /// Shared secret for HS256
+    Hmac(Vec<u8>),
+    /// Big-endian modulus and exponent for RS256
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+    /// Big-endian P-256 public point for ES256
+    EcP256 { x: Vec<u8>, y: Vec<u8> },
+}
+
+impl VerifyingKey {
+    /// The one algorithm the key may be used with, so a public key can
+    /// never be taken for an HMAC secret
+    pub fn algorithm(&self) -> JwtAlgorithm {
+        match self {
+            VerifyingKey::Hmac(_) => JwtAlgorithm::HS256,
+            VerifyingKey::Rsa { .. } => JwtAlgorithm::RS256,
+            VerifyingKey::EcP256 { .. } => JwtAlgorithm::ES256,
+        }
+    }
+
+    /// Whether `signature` over `message` was made with this key
+    pub(crate) fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        match self {
+            VerifyingKey::Hmac(secret) => {
+                hmac::verify(&hmac::Key::new(hmac::HMAC_SHA256, secret), message, signature).is_ok()
+            }
+            VerifyingKey::Rsa { n, e } => {
+                // ring wants the minimal big-endian encoding some providers pad
+                let n = &n[n.iter().take_while(|byte| **byte == 0).count()..];
+                RsaPublicKeyComponents { n, e: e.as_slice() }
+                    .verify(&RSA_PKCS1_2048_8192_SHA256, message, signature)
+                    .is_ok()
+            }
+            VerifyingKey::EcP256 { x, y } => {
+                if x.len() != 32 || y.len() != 32 {
+                    return false;
+                }
+                let point = [&[0x04], x.as_slice(), y.as_slice()].concat();
+                UnparsedPublicKey::new(&ECDSA_P256_SHA256_FIXED, point)
+                    .verify(message, signature)
+                    .is_ok()
+            }
+        }
+    }
+}
+
+/// A signing key, as listed in a JWKS
+#[derive(Debug, Clone, PartialEq)]
+pub struct Jwk {
+    pub kid: Option<String>,
+    pub key: VerifyingKey,
+}
+
+impl Jwk {
+    pub fn new(kid: Option<String>, key: VerifyingKey) -> Self {
+        Self { kid, key }
+    }
+
+    /// Signature keys of a JWKS document; keys of other types, curves or
+    /// uses are skipped
+    pub fn parse_set(json: &str) -> Result<Vec<Jwk>> {
+        let set: RawKeySet = serde_json::from_str(json)
+            .map_err(|e| MiddlewareError::Generic(format!("Invalid JWKS: {}", e)))?;
+        Ok(set.keys.into_iter().filter_map(RawKey::into_jwk).collect())
+    }
+}
+
+#[derive(Deserialize)]
+struct RawKeySet {
+    keys: Vec<RawKey>,
+}
+
+#[derive(Deserialize)]
+struct RawKey {
+    kty: String,
+    kid: Option<String>,
+    #[serde(rename = "use")]
+    usage: Option<String>,
+    alg: Option<String>,
+    crv: Option<String>,
+    n: Option<String>,
+    e: Option<String>,
+    x: Option<String>,
+    y: Option<String>,
+    k: Option<String>,
+}
+
+impl RawKey {
+    fn into_jwk(self) -> Option<Jwk> {
+        if self.usage.as_deref().is_some_and(|usage| usage != "sig") {
+            return None;
+        }
+        let decode = |value: Option<String>| URL_SAFE_NO_PAD.decode(value?).ok();
+        let key = match (self.kty.as_str(), self.crv.as_deref()) {
+            ("RSA", _) => VerifyingKey::Rsa {
+                n: decode(self.n)?,
+                e: decode(self.e)?,
+            },
+            ("EC", Some("P-256")) => VerifyingKey::EcP256 {
+                x: decode(self.x)?,
+                y: decode(self.y)?,
+            },
+            ("oct", _) => VerifyingKey::Hmac(decode(self.k)?),
+            _ => return None,
+        };
+        // A key published for another algorithm is not used for this one
+        if self.alg.is_some_and(|alg| alg != key.algorithm().as_str()) {
+            return None;
+        }
+        Some(Jwk::new(self.kid, key))
+    }
+}
+
+/// Fetches a JWKS document
+pub trait JwksFetcher: Send + Sync {
+    fn fetch(&self) -> Result<String>;
+}
+
+/// Fetches a JWKS over plain HTTP/1.1; `https://` endpoints need a
+/// TLS-terminating proxy or a custom `JwksFetcher`
+pub struct HttpJwksFetcher {
+    url: String,
+    timeout: Duration,
+}
+
+impl HttpJwksFetcher {
+    pub fn new(url: String, timeout: Duration) -> Self {
+        Self { url, timeout }
+    }
+}
+
+impl JwksFetcher for HttpJwksFetcher {
+    fn fetch(&self) -> Result<String> {
+        let error = |reason: &dyn std::fmt::Display| {
+            MiddlewareError::Generic(format!(
+                "Failed to fetch JWKS from {}: {}",
+                self.url, reason
+            ))
+        };
+        let response = http::request("GET", &self.url, None, self.timeout).map_err(|e| error(&e))?;
+        if !(200..300).contains(&response.status) {
+            return Err(error(&format!("server returned {}", response.status)));
+        }
+        Ok(response.body)
+    }
+}
+
+/// JWKS cache settings
+#[derive(Debug, Clone)]
+pub struct JwksConfig {
+    /// How long a fetched set is used before fetching it again
+    pub refresh_interval: Duration,
+    /// Shortest wait between fetches prompted by unknown key ids or errors
+    pub refetch_cooldown: Duration,
+}
+
+impl Default for JwksConfig {
+    fn default() -> Self {
+        Self {
+            refresh_interval: Duration::from_secs(3600), // 1 hour
+            refetch_cooldown: Duration::from_secs(60),
+        }
+    }
+}
+
+struct CachedKeys {
+    keys: Vec<Jwk>,
+    fetched_at: Option<Instant>,
+    last_attempt: Option<Instant>,
+}
+
+/// Keys of a JWKS, refreshed as they rotate
+pub struct JwksCache {
+    fetcher: Arc<dyn JwksFetcher>,
+    config: JwksConfig,
+    cached: RwLock<CachedKeys>,
+}
+
+impl JwksCache {
+    pub fn new(fetcher: Arc<dyn JwksFetcher>, config: JwksConfig) -> Self {
+        Self {
+            fetcher,
+            config,
+            cached: RwLock::new(CachedKeys {
+                keys: Vec::new(),
+                fetched_at: None,
+                last_attempt: None,
+            }),
+        }
+    }
+
+    /// Fetch the JWKS at an `http://` URL
+    pub fn from_url(url: String, config: JwksConfig) -> Self {
+        Self::new(
+            Arc::new(HttpJwksFetcher::new(url, Duration::from_secs(10))),
+            config,
+        )
+    }
+
+    /// Keys that may have signed a token with key id `kid`, refreshing the
+    /// set when it is stale or does not have the key
+    pub fn keys(&self, kid: Option<&str>) -> Result<Vec<Jwk>> {
+        let now = Instant::now();
+        let (stale, found) = {
+            let cached = self.cached.read().unwrap();
+            let stale = cached
+                .fetched_at
+                .is_none_or(|fetched| now.duration_since(fetched) >= self.config.refresh_interval);
+            (stale, matching(&cached.keys, kid))
+        };
+        if !stale && !found.is_empty() {
+            return Ok(found);
+        }
+
+        let refreshed = self.refresh(now);
+        let found = matching(&self.cached.read().unwrap().keys, kid);
+        match refreshed {
+            Err(e) if found.is_empty() => Err(e),
+            _ => Ok(found),
+        }
+    }
+
+    /// Fetch the set again unless a fetch was attempted within the cooldown
+    /// or, for a fresh set, the refresh interval
+    fn refresh(&self, now: Instant) -> Result<()> {
+        let mut cached = self.cached.write().unwrap();
+        if let Some(last) = cached.last_attempt {
+            if now.duration_since(last) < self.config.refetch_cooldown {
+                return Ok(());
+            }
+        }
+        cached.last_attempt = Some(now);
+        let keys = Jwk::parse_set(&self.fetcher.fetch()?)?;
+        cached.keys = keys;
+        cached.fetched_at = Some(now);
+        Ok(())
+    }
+}
+
+fn matching(keys: &[Jwk], kid: Option<&str>) -> Vec<Jwk> {
+    keys.iter()
+        .filter(|key| kid.is_none() || key.kid.as_deref() == kid)
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    /// Serves whichever set is current, counting fetches
+    struct StaticFetcher {
+        json: Mutex<String>,
+        fetches: AtomicUsize,
+    }
+
+    impl JwksFetcher for StaticFetcher {
+        fn fetch(&self) -> Result<String> {
+            self.fetches.fetch_add(1, Ordering::SeqCst);
+            Ok(self.json.lock().unwrap().clone())
+        }
+    }
+
+    fn oct(kid: &str, secret: &str) -> String {
+        format!(
+            r#"{{"kty":"oct","kid":"{}","k":"{}"}}"#,
+            kid,
+            URL_SAFE_NO_PAD.encode(secret)
+        )
+    }
+
+    #[test]
+    fn test_parse_set() {
+        let json = format!(
+            r#"{{"keys":[{},{{"kty":"oct","use":"enc","k":"AA"}},{{"kty":"EC","crv":"P-384","x":"AA","y":"AA"}},{{"kty":"oct","alg":"RS256","k":"AA"}}]}}"#,
+            oct("a", "secret")
+        );
+        let keys = Jwk::parse_set(&json).unwrap();
+        assert_eq!(
+            keys,
+            vec![Jwk::new(
+                Some("a".to_string()),
+                VerifyingKey::Hmac(b"secret".to_vec())
+            )]
+        );
+        assert!(Jwk::parse_set("{}").is_err());
+    }
+
+    #[test]
+    fn test_rotation_and_cooldown() {
+        let fetcher = Arc::new(StaticFetcher {
+            json: Mutex::new(format!(r#"{{"keys":[{}]}}"#, oct("old", "one"))),
+            fetches: AtomicUsize::new(0),
+        });
+        let cache = JwksCache::new(
+            fetcher.clone(),
+            JwksConfig {
+                refresh_interval: Duration::from_secs(3600),
+                refetch_cooldown: Duration::ZERO,
+            },
+        );
+        assert_eq!(cache.keys(Some("old")).unwrap().len(), 1);
+        assert_eq!(cache.keys(Some("old")).unwrap().len(), 1);
+        assert_eq!(fetcher.fetches.load(Ordering::SeqCst), 1);
+
+        // The provider rotates to a new key
+        *fetcher.json.lock().unwrap() = format!(r#"{{"keys":[{}]}}"#, oct("new", "two"));
+        assert_eq!(
+            cache.keys(Some("new")).unwrap()[0].key,
+            VerifyingKey::Hmac(b"two".to_vec())
+        );
+        assert_eq!(fetcher.fetches.load(Ordering::SeqCst), 2);
+
+        // Unknown key ids only refetch once the cooldown has passed
+        let cache = JwksCache::new(fetcher.clone(), JwksConfig::default());
+        assert!(cache.keys(Some("bogus")).unwrap().is_empty());
+        assert!(cache.keys(Some("bogus")).unwrap().is_empty());
+        assert_eq!(fetcher.fetches.load(Ordering::SeqCst), 3);
+    }
+}
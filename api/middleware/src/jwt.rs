@@ -0,0 +1,414 @@
+//! JWT validation
+//!
+//! Bearer tokens are accepted once their signature checks out against a
+//! configured key and their claims hold: not expired, already valid, and
+//! issued by and for whom the configuration expects. Only HS256, RS256 and
+//! ES256 are supported, `none` never is, and a key only verifies the
+//! algorithm of its own type, so a published RSA key cannot be passed off
+//! as an HMAC secret. Permissions come from claims such as OAuth's `scope`
+//! and from roles mapped to permissions.
+
+use crate::jwks::{Jwk, JwksCache, VerifyingKey};
+use crate::{MiddlewareError, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Signature algorithms accepted in the `alg` header
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JwtAlgorithm {
+    HS256,
+    RS256,
+    ES256,
+}
+
+impl JwtAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JwtAlgorithm::HS256 => "HS256",
+            JwtAlgorithm::RS256 => "RS256",
+            JwtAlgorithm::ES256 => "ES256",
+        }
+    }
+
+    fn parse(alg: &str) -> Option<Self> {
+        [
+            JwtAlgorithm::HS256,
+            JwtAlgorithm::RS256,
+            JwtAlgorithm::ES256,
+        ]
+        .into_iter()
+        .find(|algorithm| algorithm.as_str() == alg)
+    }
+}
+
+/// Where a token's permissions come from
+#[derive(Debug, Clone)]
+pub struct PermissionMapping {
+    /// Claims holding permissions, as an array or a space-separated
+    /// string such as OAuth's `scope`
+    pub permission_claims: Vec<String>,
+    /// Claim holding the user's roles
+    pub role_claim: String,
+    /// Permissions each role grants
+    pub role_permissions: HashMap<String, Vec<String>>,
+}
+
+impl Default for PermissionMapping {
+    fn default() -> Self {
+        Self {
+            permission_claims: vec!["permissions".to_string(), "scope".to_string()],
+            role_claim: "roles".to_string(),
+            role_permissions: HashMap::new(),
+        }
+    }
+}
+
+/// JWT validation configuration
+#[derive(Debug, Clone)]
+pub struct JwtConfig {
+    /// Algorithms tokens may be signed with
+    pub algorithms: Vec<JwtAlgorithm>,
+    /// Required `iss` claim
+    pub issuer: Option<String>,
+    /// Required entry of the `aud` claim
+    pub audience: Option<String>,
+    /// Clock skew allowed when checking `exp` and `nbf`
+    pub leeway: Duration,
+    pub permissions: PermissionMapping,
+}
+
+impl Default for JwtConfig {
+    fn default() -> Self {
+        Self {
+            algorithms: vec![
+                JwtAlgorithm::HS256,
+                JwtAlgorithm::RS256,
+                JwtAlgorithm::ES256,
+            ],
+            issuer: None,
+            audience: None,
+            leeway: Duration::from_secs(60),
+            permissions: PermissionMapping::default(),
+        }
+    }
+}
+
+/// Claims of a validated token
+#[derive(Debug, Clone)]
+pub struct JwtClaims {
+    /// The `sub` claim
+    pub subject: String,
+    pub permissions: Vec<String>,
+    pub expires_at: SystemTime,
+    /// Every claim, as in the token
+    pub claims: serde_json::Map<String, Value>,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    alg: String,
+    kid: Option<String>,
+}
+
+enum Keys {
+    Static(Vec<Jwk>),
+    Jwks(JwksCache),
+}
+
+/// Validates bearer tokens against configured keys or a JWKS
+pub struct JwtValidator {
+    config: JwtConfig,
+    keys: Keys,
+}
+
+impl JwtValidator {
+    /// Validate HS256 tokens signed with `secret`
+    pub fn with_secret(config: JwtConfig, secret: &[u8]) -> Self {
+        Self::with_keys(
+            config,
+            vec![Jwk::new(None, VerifyingKey::Hmac(secret.to_vec()))],
+        )
+    }
+
+    /// Validate tokens signed with any of `keys`
+    pub fn with_keys(config: JwtConfig, keys: Vec<Jwk>) -> Self {
+        Self {
+            config,
+            keys: Keys::Static(keys),
+        }
+    }
+
+    /// Validate tokens signed with the keys of a JWKS
+    pub fn with_jwks(config: JwtConfig, jwks: JwksCache) -> Self {
+        Self {
+            config,
+            keys: Keys::Jwks(jwks),
+        }
+    }
+
+    /// Check `token`'s signature and claims
+    pub fn validate(&self, token: &str) -> Result<JwtClaims> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        self.validate_at(token, now)
+    }
+
+    /// Check `token` as of `now`, in seconds since the epoch
+    pub(crate) fn validate_at(&self, token: &str, now: u64) -> Result<JwtClaims> {
+        let mut parts = token.split('.');
+        let (Some(header), Some(payload), Some(encoded_signature), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid("malformed token"));
+        };
+        let header: Header = decode_json(header)?;
+        let algorithm = JwtAlgorithm::parse(&header.alg)
+            .filter(|algorithm| self.config.algorithms.contains(algorithm))
+            .ok_or_else(|| invalid(&format!("algorithm {} is not accepted", header.alg)))?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(encoded_signature)
+            .map_err(|_| invalid("malformed signature"))?;
+
+        let keys = match &self.keys {
+            Keys::Static(keys) => keys
+                .iter()
+                .filter(|key| header.kid.is_none() || key.kid.is_none() || key.kid == header.kid)
+                .cloned()
+                .collect(),
+            Keys::Jwks(jwks) => jwks
+                .keys(header.kid.as_deref())
+                .map_err(|e| invalid(&e.to_string()))?,
+        };
+        let signed = &token[..token.len() - encoded_signature.len() - 1];
+        let verified = keys
+            .iter()
+            .filter(|key| key.key.algorithm() == algorithm)
+            .any(|key| key.key.verify(signed.as_bytes(), &signature));
+        if !verified {
+            return Err(invalid("signature does not match any key"));
+        }
+
+        let claims: serde_json::Map<String, Value> = decode_json(payload)?;
+        self.check_claims(claims, now)
+    }
+
+    fn check_claims(&self, claims: serde_json::Map<String, Value>, now: u64) -> Result<JwtClaims> {
+        let leeway = self.config.leeway.as_secs();
+        let exp = claims
+            .get("exp")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| invalid("missing exp claim"))?;
+        if now > exp.saturating_add(leeway) {
+            return Err(invalid("token has expired"));
+        }
+        if let Some(nbf) = claims.get("nbf") {
+            let nbf = nbf.as_u64().ok_or_else(|| invalid("invalid nbf claim"))?;
+            if now.saturating_add(leeway) < nbf {
+                return Err(invalid("token is not valid yet"));
+            }
+        }
+        if let Some(issuer) = &self.config.issuer {
+            if claims.get("iss").and_then(Value::as_str) != Some(issuer.as_str()) {
+                return Err(invalid("unexpected issuer"));
+            }
+        }
+        if let Some(audience) = &self.config.audience {
+            let accepted = match claims.get("aud") {
+                Some(Value::String(aud)) => aud == audience,
+                aud => strings(aud).contains(audience),
+            };
+            if !accepted {
+                return Err(invalid("unexpected audience"));
+            }
+        }
+        let subject = claims
+            .get("sub")
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid("missing sub claim"))?
+            .to_string();
+
+        let mapping = &self.config.permissions;
+        let mut permissions: Vec<String> = mapping
+            .permission_claims
+            .iter()
+            .flat_map(|claim| strings(claims.get(claim)))
+            .collect();
+        for role in strings(claims.get(&mapping.role_claim)) {
+            permissions.extend(
+                mapping
+                    .role_permissions
+                    .get(&role)
+                    .into_iter()
+                    .flatten()
+                    .cloned(),
+            );
+        }
+        permissions.sort();
+        permissions.dedup();
+
+        Ok(JwtClaims {
+            subject,
+            permissions,
+            expires_at: UNIX_EPOCH + Duration::from_secs(exp),
+            claims,
+        })
+    }
+}
+
+pub(crate) fn decode_json<T: serde::de::DeserializeOwned>(part: &str) -> Result<T> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(part)
+        .map_err(|_| invalid("malformed token"))?;
+    serde_json::from_slice(&bytes).map_err(|_| invalid("malformed token"))
+}
+
+/// A claim holding a string, an array of strings or space-separated words
+fn strings(claim: Option<&Value>) -> Vec<String> {
+    match claim {
+        Some(Value::String(words)) => words.split_whitespace().map(str::to_string).collect(),
+        Some(Value::Array(values)) => values
+            .iter()
+            .filter_map(|value| value.as_str().map(str::to_string))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn invalid(reason: &str) -> MiddlewareError {
+    MiddlewareError::Authentication {
+        reason: format!("Invalid token: {}", reason),
+    }
+}
+
+/// An HS256 token for `claims`, signed with `secret`
+pub(crate) fn sign_hs256(claims: &Value, secret: &[u8]) -> String {
+    let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"HS256","typ":"JWT"}"#);
+    let payload = URL_SAFE_NO_PAD.encode(claims.to_string());
+    let signed = format!("{}.{}", header, payload);
+    let signature = URL_SAFE_NO_PAD.encode(hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, secret), signed.as_bytes()));
+    format!("{}.{}", signed, signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jwks::{JwksConfig, JwksFetcher};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    const NOW: u64 = 1_750_000_000;
+
+    /// JWKS and tokens made with another implementation, for `alice` with
+    /// `scope` "read write" and the `operator` role
+    const JWKS: &str = include_str!("../testdata/jwks.json");
+    const RS256_TOKEN: &str = include_str!("../testdata/rs256.jwt");
+    const ES256_TOKEN: &str = include_str!("../testdata/es256.jwt");
+
+    struct Fixed;
+
+    impl JwksFetcher for Fixed {
+        fn fetch(&self) -> Result<String> {
+            Ok(JWKS.to_string())
+        }
+    }
+
+    fn config() -> JwtConfig {
+        JwtConfig {
+            issuer: Some("https://auth.example".to_string()),
+            audience: Some("cc-chain-api".to_string()),
+            ..JwtConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_hs256_claims() {
+        let validator = JwtValidator::with_secret(config(), b"secret");
+        let claims = json!({
+            "sub": "bob",
+            "iss": "https://auth.example",
+            "aud": ["other", "cc-chain-api"],
+            "exp": NOW + 60,
+            "permissions": ["write", "read"],
+        });
+        let token = sign_hs256(&claims, b"secret");
+        let validated = validator.validate_at(&token, NOW).unwrap();
+        assert_eq!(validated.subject, "bob");
+        assert_eq!(validated.permissions, vec!["read", "write"]);
+
+        let rejected = |claims: Value, now: u64| {
+            validator
+                .validate_at(&sign_hs256(&claims, b"secret"), now)
+                .is_err()
+        };
+        assert!(validator
+            .validate_at(&sign_hs256(&claims, b"wrong"), NOW)
+            .is_err());
+        assert!(!rejected(claims.clone(), NOW + 60 + 59));
+        assert!(rejected(claims.clone(), NOW + 60 + 61));
+        let mut early = claims.clone();
+        early["nbf"] = json!(NOW + 120);
+        assert!(rejected(early, NOW));
+        let mut foreign = claims.clone();
+        foreign["aud"] = json!("other");
+        assert!(rejected(foreign, NOW));
+        let mut forged = claims.clone();
+        forged["iss"] = json!("https://evil.example");
+        assert!(rejected(forged, NOW));
+        let mut endless = claims.clone();
+        endless.as_object_mut().unwrap().remove("exp");
+        assert!(rejected(endless, NOW));
+
+        // Unsigned tokens are never accepted
+        let unsigned = format!(
+            "{}.{}.",
+            URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        assert!(validator.validate_at(&unsigned, NOW).is_err());
+    }
+
+    #[test]
+    fn test_asymmetric_tokens_from_jwks() {
+        let mut config = config();
+        config
+            .permissions
+            .role_permissions
+            .insert("operator".to_string(), vec!["admin".to_string()]);
+        let validator = JwtValidator::with_jwks(
+            config,
+            JwksCache::new(Arc::new(Fixed), JwksConfig::default()),
+        );
+        for token in [RS256_TOKEN, ES256_TOKEN] {
+            let claims = validator.validate_at(token.trim(), NOW).unwrap();
+            assert_eq!(claims.subject, "alice");
+            assert_eq!(claims.permissions, vec!["admin", "read", "write"]);
+        }
+
+        // A flipped bit in the payload breaks both signatures
+        for token in [RS256_TOKEN, ES256_TOKEN] {
+            let token = token.trim();
+            let dot = token.find('.').unwrap() + 5;
+            let mut tampered = token.as_bytes().to_vec();
+            tampered[dot] ^= 1;
+            assert!(validator
+                .validate_at(std::str::from_utf8(&tampered).unwrap(), NOW)
+                .is_err());
+        }
+
+        // An HS256 token signed with the RSA modulus as its secret
+        let keys = Jwk::parse_set(JWKS).unwrap();
+        let VerifyingKey::Rsa { n, .. } = &keys[0].key else {
+            panic!("expected the RSA key first");
+        };
+        let confused = sign_hs256(&json!({"sub": "mallory", "exp": NOW + 60}), n);
+        assert!(validator.validate_at(&confused, NOW).is_err());
+    }
+}
@@ -3,8 +3,37 @@
 //! This module provides comprehensive middleware functionality for the CC Chain API,
 //! including authentication, logging, CORS, rate limiting, and request/response processing.
 
+pub mod acl;
+pub mod api_keys;
+pub mod audit;
+mod http;
+pub mod jwks;
+pub mod jwt;
+pub mod pipeline;
+pub mod quotas;
+pub mod rate_limit_store;
+pub mod rbac;
+pub mod resilience;
+pub mod response;
+pub mod routes;
+pub mod sessions;
+pub mod signing;
+
+use acl::NetworkAclMiddleware;
+use api_keys::ApiKeyManager;
+use audit::{AuditDecision, AuditEvent, AuditEventKind, AuditSink, StdoutAuditSink};
+use jwt::JwtValidator;
+use pipeline::{Middleware, Next, PipelineBuilder, PipelineContext};
+use quotas::QuotaManager;
+use rate_limit_store::{InMemoryRateLimitStore, RateLimitStore};
+use rbac::RbacEngine;
+use routes::RouteTable;
+use sessions::SessionManager;
+use signing::SignatureVerifier;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use thiserror::Error;
 
@@ -14,16 +43,48 @@ pub enum MiddlewareError {
     Authentication { reason: String },
     #[error("Authorization failed: {reason}")]
     Authorization { reason: String },
+    #[error("Access denied: {reason}")]
+    AccessDenied { reason: String },
     #[error("Rate limit exceeded: {message}")]
     RateLimit { message: String },
     #[error("CORS validation failed: {reason}")]
     Cors { reason: String },
     #[error("Request validation failed: {reason}")]
     Validation { reason: String },
+    #[error("Circuit open for {handler}, retry after {retry_after:?}")]
+    CircuitOpen { handler: String, retry_after: Duration },
+    #[error("Overloaded: {reason}")]
+    Overloaded { reason: String },
     #[error("Middleware error: {0}")]
     Generic(String),
 }
 
+impl MiddlewareError {
+    /// HTTP status to respond with
+    pub fn status_code(&self) -> u16 {
+        match self {
+            MiddlewareError::Authentication { .. } => 401,
+            MiddlewareError::Authorization { .. }
+            | MiddlewareError::AccessDenied { .. }
+            | MiddlewareError::Cors { .. } => 403,
+            MiddlewareError::RateLimit { .. } => 429,
+            MiddlewareError::Validation { .. } => 400,
+            MiddlewareError::CircuitOpen { .. } | MiddlewareError::Overloaded { .. } => 503,
+            MiddlewareError::Generic(_) => 500,
+        }
+    }
+
+    /// How long the client should wait before retrying, for the
+    /// `Retry-After` header
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            MiddlewareError::CircuitOpen { retry_after, .. } => Some(*retry_after),
+            MiddlewareError::Overloaded { .. } => Some(Duration::from_secs(1)),
+            _ => None,
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, MiddlewareError>;
 
 /// HTTP request context
@@ -37,6 +98,8 @@ pub struct RequestContext {
     pub remote_addr: Option<String>,
     pub user_agent: Option<String>,
     pub content_type: Option<String>,
+    /// Raw request body, covered by request signatures
+    pub body: Vec<u8>,
     pub start_time: Instant,
 }
 
@@ -51,10 +114,16 @@ impl RequestContext {
             remote_addr: None,
             user_agent: None,
             content_type: None,
+            body: Vec::new(),
             start_time: Instant::now(),
         }
     }
 
+    pub fn with_body(mut self, body: Vec<u8>) -> Self {
+        self.body = body;
+        self
+    }
+
     pub fn duration(&self) -> Duration {
         self.start_time.elapsed()
     }
@@ -66,6 +135,16 @@ pub struct AuthMiddleware {
     pub allow_anonymous: bool,
     pub api_key_header: String,
     pub token_header: String,
+    /// Checks API keys; without one they are rejected
+    api_keys: Option<Arc<ApiKeyManager>>,
+    /// Validates bearer tokens; without one they are rejected
+    jwt: Option<JwtValidator>,
+    /// Verifies signed requests; without one they are rejected
+    signatures: Option<SignatureVerifier>,
+    /// Accepts session access tokens as bearer tokens
+    sessions: Option<Arc<SessionManager>>,
+    /// Per-route requirements, overriding `allow_anonymous`
+    routes: Option<Arc<RouteTable>>,
 }
 
 impl AuthMiddleware {
@@ -75,6 +154,11 @@ impl AuthMiddleware {
             allow_anonymous: false,
             api_key_header: "X-API-Key".to_string(),
             token_header: "Authorization".to_string(),
+            api_keys: None,
+            jwt: None,
+            signatures: None,
+            sessions: None,
+            routes: None,
         }
     }
 
@@ -88,8 +172,54 @@ impl AuthMiddleware {
         self
     }
 
+    /// Accept API keys that `manager` issued
+    pub fn with_api_keys(mut self, manager: Arc<ApiKeyManager>) -> Self {
+        self.api_keys = Some(manager);
+        self
+    }
+
+    /// Accept bearer tokens that `validator` accepts
+    pub fn with_jwt(mut self, validator: JwtValidator) -> Self {
+        self.jwt = Some(validator);
+        self
+    }
+
+    /// Accept access tokens of sessions `manager` issued
+    pub fn with_sessions(mut self, manager: Arc<SessionManager>) -> Self {
+        self.sessions = Some(manager);
+        self
+    }
+
+    /// Accept requests signed with a key `verifier` knows
+    pub fn with_signatures(mut self, verifier: SignatureVerifier) -> Self {
+        self.signatures = Some(verifier);
+        self
+    }
+
+    /// Let public routes through without credentials and hold callers of
+    /// protected ones to the route's permissions
+    pub fn with_routes(mut self, routes: Arc<RouteTable>) -> Self {
+        self.routes = Some(routes);
+        self
+    }
+
     /// Process authentication for request
     pub fn process(&self, context: &RequestContext) -> Result<AuthResult> {
+        let route = self.routes.as_ref().and_then(|routes| routes.resolve(context));
+        let allow_anonymous = route.as_ref().map_or(self.allow_anonymous, |route| !route.auth_required);
+        let auth_result = self.authenticate(context, allow_anonymous)?;
+
+        if let Some(route) = route.filter(|route| route.auth_required) {
+            if let Some(missing) = route.required_permissions.iter().find(|p| !auth_result.has_permission(p)) {
+                return Err(MiddlewareError::Authorization {
+                    reason: format!("{} {} requires the {} permission", context.method, context.path, missing),
+                });
+            }
+        }
+        Ok(auth_result)
+    }
+
+    fn authenticate(&self, context: &RequestContext, allow_anonymous: bool) -> Result<AuthResult> {
         // Check for API key
         if let Some(api_key) = context.headers.get(&self.api_key_header) {
             return self.validate_api_key(api_key);
@@ -101,10 +231,18 @@ impl AuthMiddleware {
                 let token = &auth_header[7..];
                 return self.validate_jwt_token(token);
             }
+
+            // Check for request signature
+            if let Some(params) = auth_header.strip_prefix(signing::SIGNATURE_SCHEME).and_then(|rest| rest.strip_prefix(' ')) {
+                let verifier = self.signatures.as_ref().ok_or_else(|| MiddlewareError::Authentication {
+                    reason: "Signed requests are not accepted".to_string(),
+                })?;
+                return verifier.verify(context, params);
+            }
         }
 
         // No authentication provided
-        if self.allow_anonymous {
+        if allow_anonymous {
             Ok(AuthResult::Anonymous)
         } else {
             Err(MiddlewareError::Authentication {
@@ -113,21 +251,34 @@ impl AuthMiddleware {
         }
     }
 
-    fn validate_api_key(&self, _api_key: &str) -> Result<AuthResult> {
-        // In a real implementation, this would validate against a key store
+    fn validate_api_key(&self, api_key: &str) -> Result<AuthResult> {
+        let manager = self.api_keys.as_ref().ok_or_else(|| MiddlewareError::Authentication {
+            reason: "API keys are not accepted".to_string(),
+        })?;
+        let record = manager.authenticate(api_key)?;
         Ok(AuthResult::ApiKey {
-            key_id: "test_key".to_string(),
-            user_id: "test_user".to_string(),
-            permissions: vec!["read".to_string(), "write".to_string()],
+            key_id: record.key_id,
+            user_id: record.user_id,
+            permissions: record.scopes,
         })
     }
 
-    fn validate_jwt_token(&self, _token: &str) -> Result<AuthResult> {
-        // In a real implementation, this would validate JWT signature and expiration
+    fn validate_jwt_token(&self, token: &str) -> Result<AuthResult> {
+        // Session tokens first; anything else may still be a provider's token
+        let session = self.sessions.as_ref().map(|sessions| sessions.authenticate(token));
+        let claims = match (session, &self.jwt) {
+            (Some(Ok(claims)), _) => claims,
+            (Some(Err(error)), None) => return Err(error),
+            (Some(Err(error)), Some(_)) if sessions::is_session_token(token) => return Err(error),
+            (_, Some(validator)) => validator.validate(token)?,
+            (None, None) => return Err(MiddlewareError::Authentication {
+                reason: "Bearer tokens are not accepted".to_string(),
+            }),
+        };
         Ok(AuthResult::JwtToken {
-            user_id: "jwt_user".to_string(),
-            permissions: vec!["read".to_string(), "write".to_string()],
-            expires_at: std::time::SystemTime::now() + Duration::from_secs(3600),
+            user_id: claims.subject,
+            permissions: claims.permissions,
+            expires_at: claims.expires_at,
         })
     }
 }
@@ -152,6 +303,11 @@ pub enum AuthResult {
         permissions: Vec<String>,
         expires_at: std::time::SystemTime,
     },
+    Signature {
+        key_id: String,
+        user_id: String,
+        permissions: Vec<String>,
+    },
 }
 
 impl AuthResult {
@@ -160,6 +316,7 @@ impl AuthResult {
             AuthResult::Anonymous => permission == "read", // Anonymous users can only read
             AuthResult::ApiKey { permissions, .. } => permissions.contains(&permission.to_string()),
             AuthResult::JwtToken { permissions, .. } => permissions.contains(&permission.to_string()),
+            AuthResult::Signature { permissions, .. } => permissions.contains(&permission.to_string()),
         }
     }
 
@@ -168,6 +325,17 @@ impl AuthResult {
             AuthResult::Anonymous => None,
             AuthResult::ApiKey { user_id, .. } => Some(user_id),
             AuthResult::JwtToken { user_id, .. } => Some(user_id),
+            AuthResult::Signature { user_id, .. } => Some(user_id),
+        }
+    }
+
+    /// Permissions granted by authentication
+    pub fn permissions(&self) -> &[String] {
+        match self {
+            AuthResult::Anonymous => &[],
+            AuthResult::ApiKey { permissions, .. } => permissions,
+            AuthResult::JwtToken { permissions, .. } => permissions,
+            AuthResult::Signature { permissions, .. } => permissions,
         }
     }
 }
@@ -277,6 +445,12 @@ pub enum CorsResponse {
 pub struct RateLimitMiddleware {
     limits: HashMap<String, RateLimit>,
     global_limit: Option<RateLimit>,
+    /// Where requests are counted, shared by replicas using the same store
+    store: Arc<dyn RateLimitStore>,
+    /// Counts requests in this process while `store` is failing
+    fallback: InMemoryRateLimitStore,
+    /// Whether `store` failed on its last use
+    degraded: AtomicBool,
 }
 
 #[derive(Debug, Clone)]
@@ -331,9 +505,18 @@ impl RateLimitMiddleware {
         Self {
             limits: HashMap::new(),
             global_limit: Some(RateLimit::new(1000, Duration::from_secs(60))), // 1000 req/min
+            store: Arc::new(InMemoryRateLimitStore::new()),
+            fallback: InMemoryRateLimitStore::new(),
+            degraded: AtomicBool::new(false),
         }
     }
 
+    /// Count requests in `store` instead of in this process
+    pub fn with_store(mut self, store: Arc<dyn RateLimitStore>) -> Self {
+        self.store = store;
+        self
+    }
+
     pub fn with_global_limit(mut self, requests_per_minute: u32) -> Self {
         self.global_limit = Some(RateLimit::new(requests_per_minute, Duration::from_secs(60)));
         self
@@ -345,46 +528,54 @@ impl RateLimitMiddleware {
     }
 
     /// Process rate limiting for request
-    pub fn process(&mut self, _context: &RequestContext, auth_result: &AuthResult) -> Result<RateLimitInfo> {
+    pub fn process(&self, _context: &RequestContext, auth_result: &AuthResult) -> Result<RateLimitInfo> {
         // Check global limit first
-        if let Some(ref mut global_limit) = self.global_limit {
-            if !global_limit.check_and_increment() {
+        let mut info = None;
+        if let Some(ref global_limit) = self.global_limit {
+            let counted = self.count("global", global_limit.window_duration)?;
+            if counted.count > global_limit.requests_per_window {
                 return Err(MiddlewareError::RateLimit {
                     message: "Global rate limit exceeded".to_string(),
                 });
             }
+            info = Some(RateLimitInfo::new(global_limit, counted));
         }
 
         // Check user-specific limit
         if let Some(user_id) = auth_result.user_id() {
-            if let Some(user_limit) = self.limits.get_mut(user_id) {
-                if !user_limit.check_and_increment() {
+            if let Some(user_limit) = self.limits.get(user_id) {
+                let counted = self.count(&format!("user:{}", user_id), user_limit.window_duration)?;
+                if counted.count > user_limit.requests_per_window {
                     return Err(MiddlewareError::RateLimit {
                         message: format!("User rate limit exceeded for {}", user_id),
                     });
                 }
 
-                return Ok(RateLimitInfo {
-                    remaining: user_limit.remaining(),
-                    reset_time: user_limit.reset_time(),
-                    limit: user_limit.requests_per_window,
-                });
+                return Ok(RateLimitInfo::new(user_limit, counted));
             }
         }
 
         // Return global limit info if no user-specific limit
-        if let Some(ref global_limit) = self.global_limit {
-            Ok(RateLimitInfo {
-                remaining: global_limit.remaining(),
-                reset_time: global_limit.reset_time(),
-                limit: global_limit.requests_per_window,
-            })
-        } else {
-            Ok(RateLimitInfo {
-                remaining: u32::MAX,
-                reset_time: Duration::from_secs(0),
-                limit: u32::MAX,
-            })
+        Ok(info.unwrap_or_else(RateLimitInfo::unlimited))
+    }
+
+    /// Count a request in the shared store, or in this process while the
+    /// store is unreachable, so an outage neither rejects every request nor
+    /// lifts the limits
+    fn count(&self, key: &str, window: Duration) -> Result<rate_limit_store::WindowCount> {
+        match self.store.increment(key, window) {
+            Ok(counted) => {
+                if self.degraded.swap(false, Ordering::Relaxed) {
+                    tracing::info!("Rate limit store recovered, counting in the shared store again");
+                }
+                Ok(counted)
+            }
+            Err(err) => {
+                if !self.degraded.swap(true, Ordering::Relaxed) {
+                    tracing::warn!("Rate limit store failed, counting in this process: {}", err);
+                }
+                self.fallback.increment(key, window)
+            }
         }
     }
 }
@@ -403,12 +594,54 @@ pub struct RateLimitInfo {
     pub limit: u32,
 }
 
-/// Logging middleware
+impl RateLimitInfo {
+    /// Info for requests no limit applies to
+    pub fn unlimited() -> Self {
+        Self {
+            remaining: u32::MAX,
+            reset_time: Duration::from_secs(0),
+            limit: u32::MAX,
+        }
+    }
+
+    fn new(limit: &RateLimit, counted: rate_limit_store::WindowCount) -> Self {
+        Self {
+            remaining: limit.requests_per_window.saturating_sub(counted.count),
+            reset_time: counted.reset_after,
+            limit: limit.requests_per_window,
+        }
+    }
+
+    /// `X-RateLimit-Limit`, `X-RateLimit-Remaining` and `X-RateLimit-Reset`
+    /// (seconds until the window resets) response headers
+    pub fn headers(&self) -> Vec<(String, String)> {
+        vec![
+            ("X-RateLimit-Limit".to_string(), self.limit.to_string()),
+            ("X-RateLimit-Remaining".to_string(), self.remaining.to_string()),
+            ("X-RateLimit-Reset".to_string(), reset_secs(self.reset_time).to_string()),
+        ]
+    }
+
+    /// Headers for a request rejected as over the limit, with `Retry-After`
+    pub fn exceeded_headers(&self) -> Vec<(String, String)> {
+        let mut headers = self.headers();
+        headers.push(("Retry-After".to_string(), reset_secs(self.reset_time).to_string()));
+        headers
+    }
+}
+
+/// Whole seconds, rounded up so clients never retry too early
+fn reset_secs(duration: Duration) -> u64 {
+    duration.as_millis().div_ceil(1000) as u64
+}
+
+/// Logging middleware, writing structured audit events to its sinks
 pub struct LoggingMiddleware {
     pub log_requests: bool,
     pub log_responses: bool,
     pub log_body: bool,
     pub sensitive_headers: Vec<String>,
+    sinks: Vec<Arc<dyn AuditSink>>,
 }
 
 impl LoggingMiddleware {
@@ -422,25 +655,37 @@ impl LoggingMiddleware {
                 "X-API-Key".to_string(),
                 "Cookie".to_string(),
             ],
+            sinks: vec![Arc::new(StdoutAuditSink)],
         }
     }
 
+    /// Write events to `sinks` instead of stdout
+    pub fn with_sinks(mut self, sinks: Vec<Arc<dyn AuditSink>>) -> Self {
+        self.sinks = sinks;
+        self
+    }
+
     /// Log incoming request
     pub fn log_request(&self, context: &RequestContext) {
         if !self.log_requests {
             return;
         }
 
-        let headers = self.filter_sensitive_headers(&context.headers);
-        
-        println!(
-            "[REQUEST] {} {} {} - Headers: {:?} - User-Agent: {:?}",
-            context.request_id,
-            context.method,
-            context.path,
-            headers,
-            context.user_agent
-        );
+        let mut event = AuditEvent::new(AuditEventKind::Request, context);
+        event.headers = Some(self.filter_sensitive_headers(&context.headers));
+        self.emit(&event);
+    }
+
+    /// Log whether the middleware let a request through, and for whom
+    pub fn log_decision(&self, context: &RequestContext, user_id: Option<&str>, outcome: std::result::Result<(), &MiddlewareError>) {
+        let mut event = AuditEvent::new(AuditEventKind::Decision, context);
+        event.user_id = user_id.map(str::to_string);
+        event.decision = Some(AuditDecision {
+            allowed: outcome.is_ok(),
+            reason: outcome.err().map(|e| e.to_string()),
+        });
+        event.status = outcome.err().map(|e| e.status_code());
+        self.emit(&event);
     }
 
     /// Log outgoing response
@@ -449,20 +694,26 @@ impl LoggingMiddleware {
             return;
         }
 
-        println!(
-            "[RESPONSE] {} - Status: {} - Duration: {:?} - Size: {:?}",
-            context.request_id,
-            status,
-            context.duration(),
-            size
-        );
+        let mut event = AuditEvent::new(AuditEventKind::Response, context);
+        event.status = Some(status);
+        event.duration_ms = Some(context.duration().as_millis() as u64);
+        event.size = size;
+        self.emit(&event);
+    }
+
+    fn emit(&self, event: &AuditEvent) {
+        for sink in &self.sinks {
+            if let Err(e) = sink.write(event) {
+                tracing::warn!("Failed to write audit event {}: {}", event.request_id, e);
+            }
+        }
     }
 
-    fn filter_sensitive_headers(&self, headers: &HashMap<String, String>) -> HashMap<String, String> {
+    fn filter_sensitive_headers(&self, headers: &HashMap<String, String>) -> BTreeMap<String, String> {
         headers
             .iter()
             .map(|(key, value)| {
-                if self.sensitive_headers.contains(key) {
+                if self.sensitive_headers.iter().any(|sensitive| sensitive.eq_ignore_ascii_case(key)) {
                     (key.clone(), "***".to_string())
                 } else {
                     (key.clone(), value.clone())
@@ -480,54 +731,64 @@ impl Default for LoggingMiddleware {
 
 /// Middleware chain for processing requests
 pub struct MiddlewareChain {
+    /// Source address filtering, evaluated before everything else
+    pub network_acl: NetworkAclMiddleware,
     pub auth: AuthMiddleware,
     pub cors: CorsMiddleware,
     pub rate_limit: RateLimitMiddleware,
     pub logging: LoggingMiddleware,
+    /// Access control enforced after authentication
+    pub rbac: Arc<RbacEngine>,
+    /// Usage accounting, charged once a request passes rate limiting
+    pub quotas: Option<Arc<QuotaManager>>,
 }
 
 impl MiddlewareChain {
     pub fn new() -> Self {
         Self {
+            network_acl: NetworkAclMiddleware::default(),
             auth: AuthMiddleware::new(),
             cors: CorsMiddleware::new(CorsConfig::default()),
             rate_limit: RateLimitMiddleware::new(),
             logging: LoggingMiddleware::new(),
+            rbac: Arc::new(RbacEngine::default()),
+            quotas: None,
         }
     }
 
     /// Process request through all middleware
-    pub fn process_request(&mut self, context: &RequestContext) -> Result<MiddlewareResult> {
-        // Log request
-        self.logging.log_request(context);
-
-        // Process CORS
-        let cors_response = self.cors.process(context)?;
-
-        // Skip auth for preflight requests
-        if let CorsResponse::Preflight { .. } = cors_response {
-            return Ok(MiddlewareResult {
-                auth_result: AuthResult::Anonymous,
-                cors_response,
-                rate_limit_info: RateLimitInfo {
-                    remaining: u32::MAX,
-                    reset_time: Duration::from_secs(0),
-                    limit: u32::MAX,
-                },
-            });
+    pub fn process_request(&self, context: &RequestContext) -> Result<MiddlewareResult> {
+        let mut stages: Vec<&dyn Middleware> = vec![
+            &self.logging,
+            &self.network_acl,
+            &self.cors,
+            &self.auth,
+            self.rbac.as_ref(),
+            &self.rate_limit,
+        ];
+        if let Some(quotas) = &self.quotas {
+            stages.push(quotas.as_ref());
         }
+        let mut pipeline_context = PipelineContext::new(context);
+        Next::new(&stages).run(&mut pipeline_context)?;
+        Ok(pipeline_context.into_result())
+    }
 
-        // Process authentication
-        let auth_result = self.auth.process(context)?;
-
-        // Process rate limiting
-        let rate_limit_info = self.rate_limit.process(context, &auth_result)?;
-
-        Ok(MiddlewareResult {
-            auth_result,
-            cors_response,
-            rate_limit_info,
-        })
+    /// The chain's stages as a pipeline builder, to add custom stages to:
+    /// `logging`, `network_acl`, `cors`, `auth`, `rbac`, `rate_limit` and,
+    /// when quotas are set, `quotas`
+    pub fn into_builder(self) -> PipelineBuilder {
+        let builder = PipelineBuilder::new()
+            .stage("logging", self.logging)
+            .stage("network_acl", self.network_acl)
+            .stage("cors", self.cors)
+            .stage("auth", self.auth)
+            .shared_stage("rbac", self.rbac)
+            .stage("rate_limit", self.rate_limit);
+        match self.quotas {
+            Some(quotas) => builder.shared_stage("quotas", quotas),
+            None => builder,
+        }
     }
 
     /// Log response
@@ -560,6 +821,12 @@ fn generate_request_id() -> String {
     format!("req_{}", timestamp % 1_000_000)
 }
 
+/// Compare without exiting at the first difference, so timing does not
+/// leak how much of a secret was right
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -588,34 +855,54 @@ mod tests {
 
     #[test]
     fn test_auth_middleware_api_key() {
-        let auth = AuthMiddleware::new();
+        let manager = Arc::new(ApiKeyManager::new(Arc::new(api_keys::InMemoryApiKeyStore::new())));
+        let issued = manager.issue("test_user", vec!["read".to_string()], None).unwrap();
+        let auth = AuthMiddleware::new().with_api_keys(manager.clone());
         let mut context = create_test_context();
-        context.headers.insert("X-API-Key".to_string(), "test-key".to_string());
+        context.headers.insert("X-API-Key".to_string(), issued.api_key.clone());
         
         let result = auth.process(&context);
         assert!(result.is_ok());
         
-        if let Ok(AuthResult::ApiKey { key_id, .. }) = result {
-            assert_eq!(key_id, "test_key");
+        if let Ok(AuthResult::ApiKey { key_id, user_id, permissions }) = result {
+            assert_eq!(key_id, issued.record.key_id);
+            assert_eq!(user_id, "test_user");
+            assert_eq!(permissions, vec!["read"]);
         } else {
             panic!("Expected API key auth result");
         }
+
+        // Unknown and revoked keys are rejected
+        context.headers.insert("X-API-Key".to_string(), "test-key".to_string());
+        assert!(auth.process(&context).is_err());
+        manager.revoke(&issued.record.key_id).unwrap();
+        context.headers.insert("X-API-Key".to_string(), issued.api_key);
+        assert!(auth.process(&context).is_err());
     }
 
     #[test]
     fn test_auth_middleware_jwt_token() {
-        let auth = AuthMiddleware::new();
+        let auth = AuthMiddleware::new().with_jwt(jwt::JwtValidator::with_secret(jwt::JwtConfig::default(), b"secret"));
+        let expires = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() + 3600;
+        let token = jwt::sign_hs256(&serde_json::json!({"sub": "jwt_user", "exp": expires, "scope": "read write"}), b"secret");
         let mut context = create_test_context();
-        context.headers.insert("Authorization".to_string(), "Bearer test-token".to_string());
+        context.headers.insert("Authorization".to_string(), format!("Bearer {}", token));
         
         let result = auth.process(&context);
         assert!(result.is_ok());
         
-        if let Ok(AuthResult::JwtToken { user_id, .. }) = result {
+        if let Ok(AuthResult::JwtToken { user_id, permissions, .. }) = result {
             assert_eq!(user_id, "jwt_user");
+            assert_eq!(permissions, vec!["read", "write"]);
         } else {
             panic!("Expected JWT token auth result");
         }
+
+        // Forged or unverifiable tokens are rejected
+        context.headers.insert("Authorization".to_string(), "Bearer test-token".to_string());
+        assert!(auth.process(&context).is_err());
+        context.headers.insert("Authorization".to_string(), format!("Bearer {}", token));
+        assert!(AuthMiddleware::new().process(&context).is_err());
     }
 
     #[test]
@@ -666,7 +953,7 @@ mod tests {
 
     #[test]
     fn test_rate_limit_middleware() {
-        let mut middleware = RateLimitMiddleware::new().with_global_limit(2);
+        let middleware = RateLimitMiddleware::new().with_global_limit(2);
         let context = create_test_context();
         let auth_result = AuthResult::Anonymous;
         
@@ -683,6 +970,49 @@ mod tests {
         assert!(result3.is_err());
     }
 
+    #[test]
+    fn test_rate_limit_shared_store() {
+        let store: Arc<dyn RateLimitStore> = Arc::new(InMemoryRateLimitStore::new());
+        let replica_a = RateLimitMiddleware::new().with_global_limit(3).with_store(store.clone());
+        let replica_b = RateLimitMiddleware::new().with_global_limit(3).with_store(store);
+        let context = create_test_context();
+
+        assert!(replica_a.process(&context, &AuthResult::Anonymous).is_ok());
+        let info = replica_b.process(&context, &AuthResult::Anonymous).unwrap();
+        assert_eq!(info.remaining, 1);
+        let headers = info.headers();
+        assert_eq!(headers[0], ("X-RateLimit-Limit".to_string(), "3".to_string()));
+        assert_eq!(headers[1], ("X-RateLimit-Remaining".to_string(), "1".to_string()));
+        assert_eq!(headers[2], ("X-RateLimit-Reset".to_string(), "60".to_string()));
+        assert_eq!(info.exceeded_headers()[3].0, "Retry-After");
+
+        assert!(replica_a.process(&context, &AuthResult::Anonymous).is_ok());
+        assert!(replica_b.process(&context, &AuthResult::Anonymous).is_err());
+    }
+
+    #[test]
+    fn test_rate_limit_survives_store_outage() {
+        struct UnreachableStore;
+        impl RateLimitStore for UnreachableStore {
+            fn increment(&self, _key: &str, _window: Duration) -> Result<rate_limit_store::WindowCount> {
+                Err(MiddlewareError::Generic("connection refused".to_string()))
+            }
+        }
+
+        let middleware = RateLimitMiddleware::new()
+            .with_global_limit(2)
+            .with_store(Arc::new(UnreachableStore));
+        let context = create_test_context();
+
+        // Requests are counted locally instead of failing or going unlimited
+        assert!(middleware.process(&context, &AuthResult::Anonymous).is_ok());
+        assert!(middleware.process(&context, &AuthResult::Anonymous).is_ok());
+        assert!(matches!(
+            middleware.process(&context, &AuthResult::Anonymous),
+            Err(MiddlewareError::RateLimit { .. })
+        ));
+    }
+
     #[test]
     fn test_logging_middleware() {
         let logging = LoggingMiddleware::new();
@@ -692,6 +1022,29 @@ mod tests {
         // This should not panic and should filter sensitive headers
         logging.log_request(&context);
         logging.log_response(&context, 200, Some(1024));
+
+        struct Collect(std::sync::Mutex<Vec<AuditEvent>>);
+        impl AuditSink for Collect {
+            fn write(&self, event: &AuditEvent) -> Result<()> {
+                self.0.lock().unwrap().push(event.clone());
+                Ok(())
+            }
+        }
+        let sink = Arc::new(Collect(std::sync::Mutex::new(Vec::new())));
+        let mut chain = MiddlewareChain::new();
+        chain.logging = LoggingMiddleware::new().with_sinks(vec![sink.clone()]);
+        chain.auth = AuthMiddleware::new();
+        assert!(chain.process_request(&context).is_err());
+        chain.log_response(&context, 401, None);
+
+        let events = sink.0.lock().unwrap();
+        let kinds: Vec<AuditEventKind> = events.iter().map(|event| event.kind).collect();
+        assert_eq!(kinds, vec![AuditEventKind::Request, AuditEventKind::Decision, AuditEventKind::Response]);
+        assert!(events.iter().all(|event| event.request_id == context.request_id));
+        assert_eq!(events[0].headers.as_ref().unwrap()["Authorization"], "***");
+        assert!(!events[1].decision.as_ref().unwrap().allowed);
+        assert_eq!(events[1].status, Some(401));
+        assert_eq!(events[2].status, Some(401));
     }
 
     #[test]
@@ -706,6 +1059,36 @@ mod tests {
         
         let middleware_result = result.unwrap();
         assert!(matches!(middleware_result.auth_result, AuthResult::Anonymous));
+
+        // The admin namespace is closed by default
+        let admin = RequestContext::new("GET".to_string(), "/admin/keys".to_string());
+        assert!(matches!(chain.process_request(&admin), Err(MiddlewareError::Authorization { .. })));
+
+        // Blocked sources are turned away before authentication
+        chain.network_acl = NetworkAclMiddleware::new(acl::AclRules::from_lists(&[], &["192.0.2.0/24"]).unwrap());
+        let mut blocked = create_test_context();
+        blocked.remote_addr = Some("192.0.2.10:5000".to_string());
+        assert!(matches!(chain.process_request(&blocked), Err(MiddlewareError::AccessDenied { .. })));
+    }
+
+    #[test]
+    fn test_route_metadata_drives_auth() {
+        let manager = Arc::new(ApiKeyManager::new(Arc::new(api_keys::InMemoryApiKeyStore::new())));
+        let reader = manager.issue("reader", vec!["read".to_string()], None).unwrap();
+        let routes = routes::RouteTable::new()
+            .with_route(routes::RouteMetadata::public(&["GET"], "/api/v1/blocks/*"))
+            .with_route(routes::RouteMetadata::protected(&["POST"], "/api/v1/transactions", &["write"]));
+        let mut chain = MiddlewareChain::new();
+        chain.auth = AuthMiddleware::new().with_api_keys(manager).with_routes(Arc::new(routes));
+
+        // Public routes need no credentials even though the chain does
+        let block = RequestContext::new("GET".to_string(), "/api/v1/blocks/7".to_string());
+        assert!(matches!(chain.process_request(&block).unwrap().auth_result, AuthResult::Anonymous));
+
+        let mut submit = RequestContext::new("POST".to_string(), "/api/v1/transactions".to_string());
+        assert!(matches!(chain.process_request(&submit), Err(MiddlewareError::Authentication { .. })));
+        submit.headers.insert("X-API-Key".to_string(), reader.api_key);
+        assert!(matches!(chain.process_request(&submit), Err(MiddlewareError::Authorization { .. })));
     }
 
     #[test]
@@ -0,0 +1,311 @@
+//! Middleware pipeline
+//!
+//! A pipeline runs a request through an ordered list of stages. Each stage
+//! gets the request's pipeline context and a `Next` handle: it can look at
+//! or fill in the context, pass the request on with `next.run`, act on
+//! what the later stages did, or stop the request by returning without
+//! calling `next`. Stages are named so custom ones can be slotted in
+//! around the built-in ones; `MiddlewareChain::into_builder` starts from
+//! the standard order.
+
+use crate::acl::NetworkAclMiddleware;
+use crate::quotas::QuotaManager;
+use crate::rbac::RbacEngine;
+use crate::{
+    AuthMiddleware, AuthResult, CorsMiddleware, CorsResponse, LoggingMiddleware, MiddlewareError,
+    MiddlewareResult, RateLimitInfo, RateLimitMiddleware, RequestContext, Result,
+};
+use std::sync::Arc;
+
+/// What the stages have learned about a request so far
+#[derive(Debug, Clone)]
+pub struct PipelineContext<'a> {
+    pub request: &'a RequestContext,
+    /// Anonymous until an authentication stage says otherwise
+    pub auth_result: AuthResult,
+    pub cors_response: Option<CorsResponse>,
+    pub rate_limit_info: Option<RateLimitInfo>,
+}
+
+impl<'a> PipelineContext<'a> {
+    pub fn new(request: &'a RequestContext) -> Self {
+        Self {
+            request,
+            auth_result: AuthResult::Anonymous,
+            cors_response: None,
+            rate_limit_info: None,
+        }
+    }
+
+    /// The outcome of a request that made it through, with defaults for
+    /// stages the pipeline did not have
+    pub fn into_result(self) -> MiddlewareResult {
+        MiddlewareResult {
+            auth_result: self.auth_result,
+            cors_response: self.cors_response.unwrap_or(CorsResponse::Regular {
+                allowed_origin: None,
+                exposed_headers: Vec::new(),
+            }),
+            rate_limit_info: self.rate_limit_info.unwrap_or_else(RateLimitInfo::unlimited),
+        }
+    }
+}
+
+/// The stages after the current one
+#[derive(Clone, Copy)]
+pub struct Next<'a> {
+    stages: &'a [&'a dyn Middleware],
+}
+
+impl<'a> Next<'a> {
+    pub fn new(stages: &'a [&'a dyn Middleware]) -> Self {
+        Self { stages }
+    }
+
+    /// Pass the request to the next stage; past the last stage it is let
+    /// through
+    pub fn run(self, context: &mut PipelineContext<'_>) -> Result<()> {
+        match self.stages.split_first() {
+            Some((stage, rest)) => stage.handle(context, Next::new(rest)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A pipeline stage
+pub trait Middleware: Send + Sync {
+    fn handle(&self, context: &mut PipelineContext<'_>, next: Next<'_>) -> Result<()>;
+}
+
+impl<F> Middleware for F
+where
+    F: Fn(&mut PipelineContext<'_>, Next<'_>) -> Result<()> + Send + Sync,
+{
+    fn handle(&self, context: &mut PipelineContext<'_>, next: Next<'_>) -> Result<()> {
+        self(context, next)
+    }
+}
+
+impl Middleware for LoggingMiddleware {
+    fn handle(&self, context: &mut PipelineContext<'_>, next: Next<'_>) -> Result<()> {
+        self.log_request(context.request);
+        let result = next.run(context);
+        self.log_decision(context.request, context.auth_result.user_id(), result.as_ref().map(|_| ()));
+        result
+    }
+}
+
+impl Middleware for NetworkAclMiddleware {
+    fn handle(&self, context: &mut PipelineContext<'_>, next: Next<'_>) -> Result<()> {
+        self.process(context.request)?;
+        next.run(context)
+    }
+}
+
+impl Middleware for CorsMiddleware {
+    fn handle(&self, context: &mut PipelineContext<'_>, next: Next<'_>) -> Result<()> {
+        let response = self.process(context.request)?;
+        let preflight = matches!(response, CorsResponse::Preflight { .. });
+        context.cors_response = Some(response);
+        // Preflight requests are answered here, without auth
+        if preflight {
+            return Ok(());
+        }
+        next.run(context)
+    }
+}
+
+impl Middleware for AuthMiddleware {
+    fn handle(&self, context: &mut PipelineContext<'_>, next: Next<'_>) -> Result<()> {
+        context.auth_result = self.process(context.request)?;
+        next.run(context)
+    }
+}
+
+impl Middleware for RbacEngine {
+    fn handle(&self, context: &mut PipelineContext<'_>, next: Next<'_>) -> Result<()> {
+        self.authorize(&context.auth_result, context.request)?;
+        next.run(context)
+    }
+}
+
+impl Middleware for RateLimitMiddleware {
+    fn handle(&self, context: &mut PipelineContext<'_>, next: Next<'_>) -> Result<()> {
+        context.rate_limit_info = Some(self.process(context.request, &context.auth_result)?);
+        next.run(context)
+    }
+}
+
+impl Middleware for QuotaManager {
+    fn handle(&self, context: &mut PipelineContext<'_>, next: Next<'_>) -> Result<()> {
+        self.process(context.request, &context.auth_result)?;
+        next.run(context)
+    }
+}
+
+/// Named stages in the order they run
+#[derive(Default)]
+pub struct Pipeline {
+    stages: Vec<(String, Arc<dyn Middleware>)>,
+}
+
+impl Pipeline {
+    pub fn builder() -> PipelineBuilder {
+        PipelineBuilder::default()
+    }
+
+    /// Stage names, in order
+    pub fn stage_names(&self) -> Vec<&str> {
+        self.stages.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    /// Run a request through every stage
+    pub fn process_request(&self, request: &RequestContext) -> Result<MiddlewareResult> {
+        let stages: Vec<&dyn Middleware> = self.stages.iter().map(|(_, stage)| stage.as_ref()).collect();
+        let mut context = PipelineContext::new(request);
+        Next::new(&stages).run(&mut context)?;
+        Ok(context.into_result())
+    }
+}
+
+/// Assembles a `Pipeline`
+#[derive(Default)]
+pub struct PipelineBuilder {
+    stages: Vec<(String, Arc<dyn Middleware>)>,
+}
+
+impl PipelineBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `stage` after the stages added so far
+    pub fn stage(self, name: &str, stage: impl Middleware + 'static) -> Self {
+        self.shared_stage(name, Arc::new(stage))
+    }
+
+    /// Run a stage that is also used elsewhere after the stages added so far
+    pub fn shared_stage(mut self, name: &str, stage: Arc<dyn Middleware>) -> Self {
+        self.stages.push((name.to_string(), stage));
+        self
+    }
+
+    /// Run `stage` right before the stage named `existing`
+    pub fn before(self, existing: &str, name: &str, stage: impl Middleware + 'static) -> Result<Self> {
+        self.insert(existing, 0, name, stage)
+    }
+
+    /// Run `stage` right after the stage named `existing`
+    pub fn after(self, existing: &str, name: &str, stage: impl Middleware + 'static) -> Result<Self> {
+        self.insert(existing, 1, name, stage)
+    }
+
+    /// Drop the stage named `name`
+    pub fn without(mut self, name: &str) -> Result<Self> {
+        let index = self.position(name)?;
+        self.stages.remove(index);
+        Ok(self)
+    }
+
+    pub fn build(self) -> Pipeline {
+        Pipeline { stages: self.stages }
+    }
+
+    fn insert(mut self, existing: &str, offset: usize, name: &str, stage: impl Middleware + 'static) -> Result<Self> {
+        let index = self.position(existing)? + offset;
+        self.stages.insert(index, (name.to_string(), Arc::new(stage)));
+        Ok(self)
+    }
+
+    fn position(&self, name: &str) -> Result<usize> {
+        self.stages
+            .iter()
+            .position(|(stage, _)| stage == name)
+            .ok_or_else(|| MiddlewareError::Generic(format!("No middleware stage named {}", name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MiddlewareChain;
+    use std::sync::Mutex;
+
+    fn request() -> RequestContext {
+        RequestContext::new("GET".to_string(), "/api/v1/blocks".to_string())
+    }
+
+    #[test]
+    fn test_stages_run_in_order_and_can_stop() {
+        let trace = Arc::new(Mutex::new(Vec::new()));
+        let stage = |label: &'static str| {
+            let trace = trace.clone();
+            move |context: &mut PipelineContext<'_>, next: Next<'_>| {
+                trace.lock().unwrap().push(label);
+                let result = next.run(context);
+                trace.lock().unwrap().push(label);
+                result
+            }
+        };
+        let pipeline = Pipeline::builder()
+            .stage("outer", stage("outer"))
+            .stage("inner", stage("inner"))
+            .before("inner", "middle", stage("middle"))
+            .unwrap()
+            .build();
+        assert_eq!(pipeline.stage_names(), vec!["outer", "middle", "inner"]);
+        pipeline.process_request(&request()).unwrap();
+        assert_eq!(
+            *trace.lock().unwrap(),
+            vec!["outer", "middle", "inner", "inner", "middle", "outer"]
+        );
+
+        let deny = |_: &mut PipelineContext<'_>, _: Next<'_>| -> Result<()> {
+            Err(MiddlewareError::Validation {
+                reason: "nope".to_string(),
+            })
+        };
+        trace.lock().unwrap().clear();
+        let pipeline = Pipeline::builder()
+            .stage("outer", stage("outer"))
+            .stage("deny", deny)
+            .stage("inner", stage("inner"))
+            .build();
+        assert!(pipeline.process_request(&request()).is_err());
+        assert_eq!(*trace.lock().unwrap(), vec!["outer", "outer"]);
+        assert!(Pipeline::builder().after("missing", "x", deny).is_err());
+    }
+
+    #[test]
+    fn test_custom_stage_in_standard_chain() {
+        let mut chain = MiddlewareChain::new();
+        chain.auth = AuthMiddleware::new();
+        // Trusted internal callers get in without credentials
+        let internal = |context: &mut PipelineContext<'_>, next: Next<'_>| -> Result<()> {
+            if context.request.headers.contains_key("X-Internal") {
+                context.auth_result = AuthResult::ApiKey {
+                    key_id: "internal".to_string(),
+                    user_id: "internal".to_string(),
+                    permissions: vec!["read".to_string()],
+                };
+                return Ok(());
+            }
+            next.run(context)
+        };
+        let pipeline = chain.into_builder().before("auth", "internal", internal).unwrap().build();
+        assert_eq!(
+            pipeline.stage_names(),
+            vec!["logging", "network_acl", "cors", "internal", "auth", "rbac", "rate_limit"]
+        );
+
+        assert!(matches!(
+            pipeline.process_request(&request()),
+            Err(MiddlewareError::Authentication { .. })
+        ));
+        let mut internal_request = request();
+        internal_request.headers.insert("X-Internal".to_string(), "1".to_string());
+        let result = pipeline.process_request(&internal_request).unwrap();
+        assert_eq!(result.auth_result.user_id(), Some("internal"));
+        assert_eq!(result.rate_limit_info.limit, u32::MAX);
+    }
+}
@@ -0,0 +1,496 @@
+//! Usage quotas
+//!
+//! Every authenticated request is charged to its account: one request,
+//! the compute units of the route it hit, and the bytes it moved. Usage is
+//! counted per quota period and kept in a storage backend, so it survives
+//! restarts and replicas sharing the backend share the count. Once any of
+//! an account's limits is used up its requests are turned away with 429
+//! until the next period starts, and the quota hooks hear about it, once
+//! per limit and period, so billing can upsell or cut the account off.
+
+use crate::api_keys::ADMIN_PERMISSION;
+use crate::rbac::path_matches;
+use crate::{http, AuthResult, MiddlewareError, RequestContext, Result};
+use cc_core_storage::Storage;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Route compute units are booked under when no cost rule matches
+pub const DEFAULT_ROUTE: &str = "*";
+
+/// What an account may use per period; `None` is unlimited
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotaLimits {
+    pub max_requests: Option<u64>,
+    pub max_compute_units: Option<u64>,
+    pub max_bytes: Option<u64>,
+}
+
+/// A limit that can run out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaKind {
+    Requests,
+    ComputeUnits,
+    Bytes,
+}
+
+/// An account's usage in one period
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub account_id: String,
+    /// Unix seconds
+    pub period_start: u64,
+    pub requests: u64,
+    pub compute_units: u64,
+    /// Compute units per cost rule path, `*` for unmatched routes
+    pub compute_units_by_route: BTreeMap<String, u64>,
+    pub bytes: u64,
+    /// Limits the hooks were told ran out this period
+    #[serde(default)]
+    pub exhausted: Vec<QuotaKind>,
+}
+
+impl UsageRecord {
+    fn new(account_id: &str, period_start: u64) -> Self {
+        Self {
+            account_id: account_id.to_string(),
+            period_start,
+            ..Self::default()
+        }
+    }
+
+    /// Limits of `limits` this usage has used up
+    pub fn exhausted_by(&self, limits: &QuotaLimits) -> Vec<QuotaKind> {
+        [
+            (QuotaKind::Requests, self.requests, limits.max_requests),
+            (QuotaKind::ComputeUnits, self.compute_units, limits.max_compute_units),
+            (QuotaKind::Bytes, self.bytes, limits.max_bytes),
+        ]
+        .into_iter()
+        .filter(|(_, used, limit)| limit.is_some_and(|limit| *used >= limit))
+        .map(|(kind, _, _)| kind)
+        .collect()
+    }
+}
+
+/// Told when an account uses up one of its limits
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuotaExhausted {
+    pub account_id: String,
+    pub kind: QuotaKind,
+    pub limits: QuotaLimits,
+    pub usage: UsageRecord,
+    /// Unix seconds the limit is lifted at
+    pub resets_at: u64,
+}
+
+/// Receives quota exhaustion events
+pub trait QuotaHook: Send + Sync {
+    fn quota_exhausted(&self, event: &QuotaExhausted) -> Result<()>;
+}
+
+/// Posts exhaustion events as JSON to a billing webhook
+pub struct WebhookQuotaHook {
+    url: String,
+    timeout: Duration,
+}
+
+impl WebhookQuotaHook {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl QuotaHook for WebhookQuotaHook {
+    fn quota_exhausted(&self, event: &QuotaExhausted) -> Result<()> {
+        let body = serde_json::to_vec(event).map_err(|e| MiddlewareError::Generic(e.to_string()))?;
+        let error = |reason: &dyn std::fmt::Display| {
+            MiddlewareError::Generic(format!("Quota webhook {} failed: {}", self.url, reason))
+        };
+        let response = http::request("POST", &self.url, Some(("application/json", &body)), self.timeout)
+            .map_err(|e| error(&e))?;
+        if !(200..300).contains(&response.status) {
+            return Err(error(&format!("webhook returned {}", response.status)));
+        }
+        Ok(())
+    }
+}
+
+/// Admin API requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum QuotaRequest {
+    /// Usage of `account_id`, the caller's own when not given
+    Usage { account_id: Option<String> },
+    SetLimits {
+        account_id: String,
+        limits: QuotaLimits,
+    },
+}
+
+/// Current usage of an account against its limits
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageReport {
+    pub usage: UsageRecord,
+    pub limits: QuotaLimits,
+    /// Unix seconds the current period ends at
+    pub period_end: u64,
+}
+
+/// Admin API responses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum QuotaResponse {
+    Usage(UsageReport),
+    LimitsSet { account_id: String },
+}
+
+/// Counts usage per account and enforces quotas
+pub struct QuotaManager {
+    storage: Arc<dyn Storage>,
+    period: u64,
+    default_limits: QuotaLimits,
+    account_limits: RwLock<HashMap<String, QuotaLimits>>,
+    /// Path patterns, as in access rules, with their compute units
+    costs: Vec<(String, u64)>,
+    default_cost: u64,
+    hooks: Vec<Arc<dyn QuotaHook>>,
+    /// Serializes read-modify-write cycles on usage records
+    lock: Mutex<()>,
+}
+
+impl QuotaManager {
+    /// Count usage per `period` in `storage`, which should be reserved for
+    /// usage records, such as a namespace of its own
+    pub fn new(storage: Arc<dyn Storage>, period: Duration) -> Result<Self> {
+        if period.as_secs() == 0 {
+            return Err(MiddlewareError::Generic(
+                "Quota period must be at least a second".to_string(),
+            ));
+        }
+        Ok(Self {
+            storage,
+            period: period.as_secs(),
+            default_limits: QuotaLimits::default(),
+            account_limits: RwLock::new(HashMap::new()),
+            costs: Vec::new(),
+            default_cost: 1,
+            hooks: Vec::new(),
+            lock: Mutex::new(()),
+        })
+    }
+
+    /// Limits of accounts without limits of their own
+    pub fn with_default_limits(mut self, limits: QuotaLimits) -> Self {
+        self.default_limits = limits;
+        self
+    }
+
+    /// Charge `units` compute units for requests to paths matching `path`
+    pub fn with_cost(mut self, path: &str, units: u64) -> Self {
+        self.costs.push((path.to_string(), units));
+        self
+    }
+
+    /// Charge `units` compute units for routes no cost rule matches
+    pub fn with_default_cost(mut self, units: u64) -> Self {
+        self.default_cost = units;
+        self
+    }
+
+    pub fn with_hook(mut self, hook: Arc<dyn QuotaHook>) -> Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    pub fn set_limits(&self, account_id: &str, limits: QuotaLimits) {
+        self.account_limits
+            .write()
+            .unwrap()
+            .insert(account_id.to_string(), limits);
+    }
+
+    pub fn limits(&self, account_id: &str) -> QuotaLimits {
+        self.account_limits
+            .read()
+            .unwrap()
+            .get(account_id)
+            .cloned()
+            .unwrap_or_else(|| self.default_limits.clone())
+    }
+
+    /// Charge a request to the caller's account, rejecting it when the
+    /// account has used up a limit; anonymous requests are not counted
+    pub fn process(&self, context: &RequestContext, auth_result: &AuthResult) -> Result<()> {
+        let Some(account_id) = auth_result.user_id() else {
+            return Ok(());
+        };
+        let (route, units) = self.cost(&context.path);
+        self.charge_at(account_id, route, units, context.body.len() as u64, unix_now())
+            .map(|_| ())
+    }
+
+    /// Add the bytes of a response already let through to an account
+    pub fn record_bytes(&self, account_id: &str, bytes: u64) -> Result<UsageRecord> {
+        self.update(account_id, unix_now(), |usage| usage.bytes += bytes)
+    }
+
+    /// Usage of an account in the current period
+    pub fn usage(&self, account_id: &str) -> Result<UsageReport> {
+        self.report_at(account_id, unix_now())
+    }
+
+    /// Serve a quota API request; callers may see their own usage, and
+    /// need the admin permission for anything else
+    pub fn handle(&self, caller: &AuthResult, request: QuotaRequest) -> Result<QuotaResponse> {
+        let Some(caller_id) = caller.user_id() else {
+            return Err(MiddlewareError::Authentication {
+                reason: "Usage is only tracked for authenticated callers".to_string(),
+            });
+        };
+        let own = matches!(&request, QuotaRequest::Usage { account_id } if account_id.as_deref().is_none_or(|id| id == caller_id));
+        if !own && !caller.has_permission(ADMIN_PERMISSION) {
+            return Err(MiddlewareError::Authorization {
+                reason: "Managing other accounts' quotas requires the admin permission".to_string(),
+            });
+        }
+        Ok(match request {
+            QuotaRequest::Usage { account_id } => {
+                QuotaResponse::Usage(self.usage(account_id.as_deref().unwrap_or(caller_id))?)
+            }
+            QuotaRequest::SetLimits { account_id, limits } => {
+                self.set_limits(&account_id, limits);
+                QuotaResponse::LimitsSet { account_id }
+            }
+        })
+    }
+
+    /// Cost rule path matching `path`, most specific first, and its units
+    fn cost(&self, path: &str) -> (&str, u64) {
+        self.costs
+            .iter()
+            .filter(|(pattern, _)| path_matches(pattern, path))
+            .max_by_key(|(pattern, _)| (pattern.trim_end_matches('*').len(), !pattern.ends_with('*')))
+            .map(|(pattern, units)| (pattern.as_str(), *units))
+            .unwrap_or((DEFAULT_ROUTE, self.default_cost))
+    }
+
+    fn charge_at(&self, account_id: &str, route: &str, units: u64, bytes: u64, now: u64) -> Result<UsageRecord> {
+        let limits = self.limits(account_id);
+        let current = self.report_at(account_id, now)?;
+        if let Some(kind) = current.usage.exhausted_by(&limits).first() {
+            return Err(MiddlewareError::RateLimit {
+                message: format!(
+                    "{:?} quota of {} exhausted until {}",
+                    kind, account_id, current.period_end
+                ),
+            });
+        }
+        self.update(account_id, now, |usage| {
+            usage.requests += 1;
+            usage.compute_units += units;
+            *usage.compute_units_by_route.entry(route.to_string()).or_default() += units;
+            usage.bytes += bytes;
+        })
+    }
+
+    /// Apply `change` to the current period's usage and tell the hooks
+    /// about limits it used up
+    fn update(&self, account_id: &str, now: u64, change: impl FnOnce(&mut UsageRecord)) -> Result<UsageRecord> {
+        let limits = self.limits(account_id);
+        let (usage, newly_exhausted) = {
+            let _guard = self.lock.lock().unwrap();
+            let mut usage = self.load(account_id, now)?;
+            change(&mut usage);
+            let newly_exhausted: Vec<QuotaKind> = usage
+                .exhausted_by(&limits)
+                .into_iter()
+                .filter(|kind| !usage.exhausted.contains(kind))
+                .collect();
+            usage.exhausted.extend(&newly_exhausted);
+            self.save(&usage)?;
+            (usage, newly_exhausted)
+        };
+
+        // Hooks may be slow; call them without holding up other accounts
+        for kind in newly_exhausted {
+            let event = QuotaExhausted {
+                account_id: account_id.to_string(),
+                kind,
+                limits: limits.clone(),
+                usage: usage.clone(),
+                resets_at: usage.period_start + self.period,
+            };
+            for hook in &self.hooks {
+                if let Err(e) = hook.quota_exhausted(&event) {
+                    tracing::warn!("Failed to report exhausted quota of {}: {}", account_id, e);
+                }
+            }
+        }
+        Ok(usage)
+    }
+
+    fn report_at(&self, account_id: &str, now: u64) -> Result<UsageReport> {
+        let usage = self.load(account_id, now)?;
+        Ok(UsageReport {
+            period_end: usage.period_start + self.period,
+            limits: self.limits(account_id),
+            usage,
+        })
+    }
+
+    /// The account's record for the period containing `now`, fresh when
+    /// the stored one is from an earlier period
+    fn load(&self, account_id: &str, now: u64) -> Result<UsageRecord> {
+        let period_start = now - now % self.period;
+        let stored = self
+            .storage
+            .get(account_id.as_bytes())
+            .map_err(storage_error)?
+            .map(|value| serde_json::from_slice::<UsageRecord>(&value))
+            .transpose()
+            .map_err(|e| MiddlewareError::Generic(format!("Invalid usage record of {}: {}", account_id, e)))?;
+        Ok(stored
+            .filter(|usage| usage.period_start == period_start)
+            .unwrap_or_else(|| UsageRecord::new(account_id, period_start)))
+    }
+
+    fn save(&self, usage: &UsageRecord) -> Result<()> {
+        let value = serde_json::to_vec(usage).map_err(|e| MiddlewareError::Generic(e.to_string()))?;
+        self.storage
+            .put(usage.account_id.as_bytes(), &value)
+            .map_err(storage_error)
+    }
+}
+
+fn storage_error(error: cc_core_storage::StorageError) -> MiddlewareError {
+    MiddlewareError::Generic(format!("Usage store failed: {}", error))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cc_core_storage::InMemoryStorage;
+
+    struct Collect(Mutex<Vec<QuotaExhausted>>);
+
+    impl QuotaHook for Collect {
+        fn quota_exhausted(&self, event: &QuotaExhausted) -> Result<()> {
+            self.0.lock().unwrap().push(event.clone());
+            Ok(())
+        }
+    }
+
+    fn user(user_id: &str, permissions: &[&str]) -> AuthResult {
+        AuthResult::ApiKey {
+            key_id: user_id.to_string(),
+            user_id: user_id.to_string(),
+            permissions: permissions.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_charges_compute_units_per_route_and_persists() {
+        let storage: Arc<dyn Storage> = Arc::new(InMemoryStorage::new());
+        let quotas = QuotaManager::new(storage.clone(), Duration::from_secs(3600))
+            .unwrap()
+            .with_cost("/api/v1/blocks/*", 5)
+            .with_cost("/api/v1/blocks/latest", 2);
+        quotas.charge_at("alice", quotas.cost("/api/v1/blocks/7").0, 5, 10, 7_200).unwrap();
+        let (route, units) = quotas.cost("/api/v1/blocks/latest");
+        assert_eq!((route, units), ("/api/v1/blocks/latest", 2));
+        quotas.charge_at("alice", route, units, 0, 7_300).unwrap();
+        assert_eq!(quotas.cost("/api/v1/peers"), (DEFAULT_ROUTE, 1));
+
+        // Another manager over the same storage sees the same usage
+        let reopened = QuotaManager::new(storage, Duration::from_secs(3600)).unwrap();
+        let report = reopened.report_at("alice", 7_400).unwrap();
+        assert_eq!(report.usage.requests, 2);
+        assert_eq!(report.usage.compute_units, 7);
+        assert_eq!(report.usage.compute_units_by_route["/api/v1/blocks/*"], 5);
+        assert_eq!(report.usage.bytes, 10);
+        assert_eq!(report.period_end, 10_800);
+
+        // A new period starts from zero
+        assert_eq!(reopened.report_at("alice", 10_800).unwrap().usage.requests, 0);
+    }
+
+    #[test]
+    fn test_exhausted_quota_rejects_and_notifies_once() {
+        let hook = Arc::new(Collect(Mutex::new(Vec::new())));
+        let quotas = QuotaManager::new(Arc::new(InMemoryStorage::new()), Duration::from_secs(60))
+            .unwrap()
+            .with_default_limits(QuotaLimits {
+                max_requests: Some(2),
+                ..QuotaLimits::default()
+            })
+            .with_hook(hook.clone());
+
+        assert!(quotas.charge_at("bob", DEFAULT_ROUTE, 1, 0, 120).is_ok());
+        assert!(quotas.charge_at("bob", DEFAULT_ROUTE, 1, 0, 121).is_ok());
+        let rejected = quotas.charge_at("bob", DEFAULT_ROUTE, 1, 0, 122);
+        assert!(matches!(rejected, Err(MiddlewareError::RateLimit { .. })));
+        assert!(quotas.charge_at("bob", DEFAULT_ROUTE, 1, 0, 123).is_err());
+        {
+            let events = hook.0.lock().unwrap();
+            assert_eq!(events.len(), 1);
+            assert_eq!(events[0].kind, QuotaKind::Requests);
+            assert_eq!(events[0].resets_at, 180);
+        }
+
+        // Lifted when the period rolls over, and raised limits apply at once
+        assert!(quotas.charge_at("bob", DEFAULT_ROUTE, 1, 0, 180).is_ok());
+        quotas.set_limits("carol", QuotaLimits::default());
+        for now in 120..130 {
+            assert!(quotas.charge_at("carol", DEFAULT_ROUTE, 1, 0, now).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_usage_query_permissions() {
+        let quotas = QuotaManager::new(Arc::new(InMemoryStorage::new()), Duration::from_secs(60)).unwrap();
+        let context = RequestContext::new("POST".to_string(), "/api/v1/transactions".to_string())
+            .with_body(vec![0; 32]);
+        quotas.process(&context, &user("alice", &[])).unwrap();
+        quotas.process(&context, &AuthResult::Anonymous).unwrap();
+
+        let QuotaResponse::Usage(report) = quotas
+            .handle(&user("alice", &[]), QuotaRequest::Usage { account_id: None })
+            .unwrap()
+        else {
+            panic!("Expected usage");
+        };
+        assert_eq!(report.usage.requests, 1);
+        assert_eq!(report.usage.bytes, 32);
+
+        let others = QuotaRequest::Usage {
+            account_id: Some("alice".to_string()),
+        };
+        assert!(matches!(
+            quotas.handle(&user("mallory", &[]), others.clone()),
+            Err(MiddlewareError::Authorization { .. })
+        ));
+        assert!(quotas.handle(&user("root", &[ADMIN_PERMISSION]), others).is_ok());
+        assert!(quotas
+            .handle(&AuthResult::Anonymous, QuotaRequest::Usage { account_id: None })
+            .is_err());
+    }
+}
@@ -0,0 +1,302 @@
+//! Rate limit counters
+//!
+//! `RateLimitMiddleware` counts requests in fixed windows through a
+//! `RateLimitStore`. The in-memory store suits a single process; with the
+//! Redis store, every API replica counts against the same keys, and the
+//! counts survive restarts. Redis does the counting in one script, so
+//! replicas racing on a key never lose an increment or leave a key without
+//! its expiry. Commands run on a small pool of connections, so concurrent
+//! requests do not queue behind one socket.
+
+use crate::{MiddlewareError, Result};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Requests counted in the current window of a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowCount {
+    /// Requests so far, including the one just counted
+    pub count: u32,
+    /// Time until the window ends
+    pub reset_after: Duration,
+}
+
+/// Shared request counters
+pub trait RateLimitStore: Send + Sync {
+    /// Count a request against `key`, starting a window of `window` when
+    /// none is open
+    fn increment(&self, key: &str, window: Duration) -> Result<WindowCount>;
+}
+
+/// Counters local to the process
+#[derive(Default)]
+pub struct InMemoryRateLimitStore {
+    windows: Mutex<HashMap<String, (u32, Instant)>>,
+}
+
+impl InMemoryRateLimitStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl RateLimitStore for InMemoryRateLimitStore {
+    fn increment(&self, key: &str, window: Duration) -> Result<WindowCount> {
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap();
+        // Drop closed windows now and then so idle keys do not pile up
+        if windows.len() >= 10_000 {
+            windows.retain(|_, (_, ends)| *ends > now);
+        }
+        let (count, ends) = windows.entry(key.to_string()).or_insert((0, now + window));
+        if *ends <= now {
+            *count = 0;
+            *ends = now + window;
+        }
+        *count = count.saturating_add(1);
+        Ok(WindowCount {
+            count: *count,
+            reset_after: ends.saturating_duration_since(now),
+        })
+    }
+}
+
+/// Increments a key, setting its expiry when the increment created it, and
+/// returns the count with the milliseconds left
+const INCREMENT_SCRIPT: &str = "local count = redis.call('INCR', KEYS[1]) \
+if count == 1 then redis.call('PEXPIRE', KEYS[1], ARGV[1]) end \
+local ttl = redis.call('PTTL', KEYS[1]) \
+if ttl < 0 then redis.call('PEXPIRE', KEYS[1], ARGV[1]) ttl = tonumber(ARGV[1]) end \
+return {count, ttl}";
+
+/// Counters in Redis, or any server speaking its protocol, shared by every
+/// replica pointed at it
+pub struct RedisRateLimitStore {
+    address: String,
+    key_prefix: String,
+    timeout: Duration,
+    /// Idle connections kept open between commands
+    max_idle: usize,
+    idle: Mutex<Vec<BufReader<TcpStream>>>,
+}
+
+impl RedisRateLimitStore {
+    /// Use the server at `address` (`host:port`)
+    pub fn new(address: String) -> Self {
+        Self {
+            address,
+            key_prefix: "cc:ratelimit:".to_string(),
+            timeout: Duration::from_secs(1),
+            max_idle: 8,
+            idle: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn with_key_prefix(mut self, key_prefix: String) -> Self {
+        self.key_prefix = key_prefix;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Keep up to `max_idle` connections open for reuse; commands beyond
+    /// that run on connections opened for them
+    pub fn with_max_idle(mut self, max_idle: usize) -> Self {
+        self.max_idle = max_idle;
+        self
+    }
+
+    fn error(&self, reason: impl std::fmt::Display) -> MiddlewareError {
+        MiddlewareError::Generic(format!("Rate limit store {}: {}", self.address, reason))
+    }
+
+    fn connect(&self) -> Result<BufReader<TcpStream>> {
+        let stream = TcpStream::connect(&self.address).map_err(|e| self.error(e))?;
+        stream
+            .set_read_timeout(Some(self.timeout))
+            .and_then(|_| stream.set_write_timeout(Some(self.timeout)))
+            .and_then(|_| stream.set_nodelay(true))
+            .map_err(|e| self.error(e))?;
+        Ok(BufReader::new(stream))
+    }
+
+    /// Send a command on an idle connection, or a new one if none is idle;
+    /// a failed connection is dropped rather than returned to the pool
+    fn command(&self, args: &[&str]) -> Result<Resp> {
+        let idle = self.idle.lock().unwrap().pop();
+        let mut stream = match idle {
+            Some(stream) => stream,
+            None => self.connect()?,
+        };
+        let result = stream
+            .get_mut()
+            .write_all(&encode_command(args))
+            .map_err(|e| self.error(e))
+            .and_then(|_| read_resp(&mut stream).map_err(|e| self.error(e)));
+        if result.is_ok() {
+            let mut idle = self.idle.lock().unwrap();
+            if idle.len() < self.max_idle {
+                idle.push(stream);
+            }
+        }
+        match result? {
+            Resp::Error(message) => Err(self.error(message)),
+            reply => Ok(reply),
+        }
+    }
+}
+
+impl RateLimitStore for RedisRateLimitStore {
+    fn increment(&self, key: &str, window: Duration) -> Result<WindowCount> {
+        let key = format!("{}{}", self.key_prefix, key);
+        let window_ms = window.as_millis().max(1).to_string();
+        let reply = self.command(&["EVAL", INCREMENT_SCRIPT, "1", &key, &window_ms])?;
+        match reply {
+            Resp::Array(items) => match items.as_slice() {
+                [Resp::Integer(count), Resp::Integer(ttl)] => Ok(WindowCount {
+                    count: (*count).clamp(0, u32::MAX as i64) as u32,
+                    reset_after: Duration::from_millis((*ttl).max(0) as u64),
+                }),
+                _ => Err(self.error("unexpected reply")),
+            },
+            _ => Err(self.error("unexpected reply")),
+        }
+    }
+}
+
+/// A RESP reply
+#[derive(Debug, PartialEq)]
+enum Resp {
+    Simple(String),
+    Error(String),
+    Integer(i64),
+    Bulk(Option<Vec<u8>>),
+    Array(Vec<Resp>),
+}
+
+fn encode_command(args: &[&str]) -> Vec<u8> {
+    let mut encoded = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        encoded.extend(format!("${}\r\n", arg.len()).into_bytes());
+        encoded.extend(arg.as_bytes());
+        encoded.extend(b"\r\n");
+    }
+    encoded
+}
+
+fn read_resp(reader: &mut impl BufRead) -> std::io::Result<Resp> {
+    let invalid =
+        |reason: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, reason.to_string());
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    let line = line.trim_end_matches("\r\n");
+    let (kind, rest) = line.split_at(line.len().min(1));
+    let number = || rest.parse::<i64>().map_err(|_| invalid("invalid length"));
+    Ok(match kind {
+        "+" => Resp::Simple(rest.to_string()),
+        "-" => Resp::Error(rest.to_string()),
+        ":" => Resp::Integer(number()?),
+        "$" => match number()? {
+            -1 => Resp::Bulk(None),
+            len if len >= 0 => {
+                let mut data = vec![0; len as usize + 2];
+                reader.read_exact(&mut data)?;
+                data.truncate(len as usize);
+                Resp::Bulk(Some(data))
+            }
+            _ => return Err(invalid("invalid length")),
+        },
+        "*" => {
+            let len = number()?;
+            let mut items = Vec::new();
+            for _ in 0..len.max(0) {
+                items.push(read_resp(reader)?);
+            }
+            Resp::Array(items)
+        }
+        _ => return Err(invalid("unknown reply type")),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_in_memory_windows() {
+        let store = InMemoryRateLimitStore::new();
+        let window = Duration::from_millis(50);
+        assert_eq!(store.increment("a", window).unwrap().count, 1);
+        assert_eq!(store.increment("a", window).unwrap().count, 2);
+        assert_eq!(store.increment("b", window).unwrap().count, 1);
+        std::thread::sleep(window);
+        assert_eq!(store.increment("a", window).unwrap().count, 1);
+    }
+
+    #[test]
+    fn test_redis_store() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = std::thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            let mut received = Vec::new();
+            let mut buffer = [0u8; 1024];
+            while !received.ends_with(b"60000\r\n") {
+                let read = socket.read(&mut buffer).unwrap();
+                received.extend_from_slice(&buffer[..read]);
+            }
+            socket.write_all(b"*2\r\n:3\r\n:59000\r\n").unwrap();
+            received
+        });
+
+        let store = RedisRateLimitStore::new(address);
+        let count = store
+            .increment("user:alice", Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(
+            count,
+            WindowCount {
+                count: 3,
+                reset_after: Duration::from_millis(59_000),
+            }
+        );
+        let received = String::from_utf8(server.join().unwrap()).unwrap();
+        assert!(received.starts_with("*5\r\n$4\r\nEVAL\r\n"));
+        assert!(received.contains("$23\r\ncc:ratelimit:user:alice\r\n"));
+
+        assert_eq!(store.idle.lock().unwrap().len(), 1);
+
+        // The server is gone; the error surfaces and the connection is dropped
+        assert!(store
+            .increment("user:alice", Duration::from_secs(60))
+            .is_err());
+        assert!(store.idle.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_read_resp() {
+        let mut reply: &[u8] = b"*3\r\n+OK\r\n$5\r\nhello\r\n$-1\r\n-ERR nope\r\n";
+        assert_eq!(
+            read_resp(&mut reply).unwrap(),
+            Resp::Array(vec![
+                Resp::Simple("OK".to_string()),
+                Resp::Bulk(Some(b"hello".to_vec())),
+                Resp::Bulk(None),
+            ])
+        );
+        assert_eq!(
+            read_resp(&mut reply).unwrap(),
+            Resp::Error("ERR nope".to_string())
+        );
+    }
+}
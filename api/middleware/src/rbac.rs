@@ -0,0 +1,334 @@
+//! Role-based access control
+//!
+//! A policy maps roles to permissions and request paths to the permission
+//! they need. A caller holds the permissions authentication granted it,
+//! those of any role among them, and those of the roles assigned to its
+//! user id. The most specific rule matching a request decides; requests no
+//! rule matches are allowed, except under the admin namespace, which is
+//! denied unless a rule grants access. Policies load from a JSON file and
+//! can be changed at runtime through the admin API.
+
+use crate::api_keys::ADMIN_PERMISSION;
+use crate::{AuthResult, MiddlewareError, RequestContext, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::RwLock;
+
+/// The permission requests to matching paths need
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccessRule {
+    /// HTTP methods the rule covers; `*` covers all
+    pub methods: Vec<String>,
+    /// Exact path, or a prefix ending in `/*`; `*` matches every path
+    pub path: String,
+    /// Permission a caller needs
+    pub permission: String,
+}
+
+impl AccessRule {
+    pub fn new(methods: &[&str], path: &str, permission: &str) -> Self {
+        Self {
+            methods: methods.iter().map(|method| method.to_string()).collect(),
+            path: path.to_string(),
+            permission: permission.to_string(),
+        }
+    }
+
+    fn matches(&self, method: &str, path: &str) -> bool {
+        let method_matches = self
+            .methods
+            .iter()
+            .any(|allowed| allowed == "*" || allowed.eq_ignore_ascii_case(method));
+        method_matches && path_matches(&self.path, path)
+    }
+
+    /// Longer patterns are more specific; exact paths beat prefixes
+    fn specificity(&self) -> (usize, bool) {
+        (
+            self.path.trim_end_matches('*').len(),
+            !self.path.ends_with('*'),
+        )
+    }
+}
+
+pub(crate) fn path_matches(pattern: &str, path: &str) -> bool {
+    if pattern == "*" {
+        return true;
+    }
+    match pattern.strip_suffix("/*") {
+        Some(prefix) => path == prefix || path.starts_with(&format!("{}/", prefix)),
+        None => pattern == path,
+    }
+}
+
+/// Roles, user role assignments and access rules
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RbacPolicy {
+    /// Permissions of each role
+    pub roles: HashMap<String, Vec<String>>,
+    /// Roles assigned to user ids
+    pub user_roles: HashMap<String, Vec<String>>,
+    pub rules: Vec<AccessRule>,
+    /// Paths under this prefix are denied unless a rule matches
+    pub admin_prefix: String,
+}
+
+impl Default for RbacPolicy {
+    fn default() -> Self {
+        Self {
+            roles: HashMap::new(),
+            user_roles: HashMap::new(),
+            rules: Vec::new(),
+            admin_prefix: "/admin".to_string(),
+        }
+    }
+}
+
+/// Admin API requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RbacAdminRequest {
+    SetRole {
+        role: String,
+        permissions: Vec<String>,
+    },
+    RemoveRole {
+        role: String,
+    },
+    AssignRoles {
+        user_id: String,
+        roles: Vec<String>,
+    },
+    AddRule {
+        rule: AccessRule,
+    },
+    RemoveRule {
+        path: String,
+    },
+    GetPolicy,
+}
+
+/// Enforces an `RbacPolicy`
+#[derive(Debug, Default)]
+pub struct RbacEngine {
+    policy: RwLock<RbacPolicy>,
+}
+
+impl RbacEngine {
+    pub fn new(policy: RbacPolicy) -> Self {
+        Self {
+            policy: RwLock::new(policy),
+        }
+    }
+
+    /// Load a JSON policy
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path).map_err(|e| {
+            MiddlewareError::Generic(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        let policy = serde_json::from_str(&json).map_err(|e| {
+            MiddlewareError::Generic(format!("Invalid policy {}: {}", path.display(), e))
+        })?;
+        Ok(Self::new(policy))
+    }
+
+    pub fn policy(&self) -> RbacPolicy {
+        self.policy.read().unwrap().clone()
+    }
+
+    /// Permissions `auth` holds, with roles expanded
+    pub fn permissions(&self, auth: &AuthResult) -> HashSet<String> {
+        let policy = self.policy.read().unwrap();
+        let mut held: Vec<&String> = auth.permissions().iter().collect();
+        if let Some(roles) = auth.user_id().and_then(|user| policy.user_roles.get(user)) {
+            held.extend(roles);
+        }
+        let mut permissions = HashSet::new();
+        for name in held {
+            if let Some(granted) = policy.roles.get(name) {
+                permissions.extend(granted.iter().cloned());
+            }
+            permissions.insert(name.clone());
+        }
+        permissions
+    }
+
+    /// Whether `auth` may make the request
+    pub fn authorize(&self, auth: &AuthResult, context: &RequestContext) -> Result<()> {
+        let required = {
+            let policy = self.policy.read().unwrap();
+            let rule = policy
+                .rules
+                .iter()
+                .filter(|rule| rule.matches(&context.method, &context.path))
+                .max_by_key(|rule| rule.specificity());
+            match rule {
+                Some(rule) => Some(rule.permission.clone()),
+                None if path_matches(&format!("{}/*", policy.admin_prefix), &context.path) => {
+                    return Err(MiddlewareError::Authorization {
+                        reason: format!("No rule grants access to {}", context.path),
+                    });
+                }
+                None => None,
+            }
+        };
+        match required {
+            Some(permission)
+                if !auth.has_permission(&permission)
+                    && !self.permissions(auth).contains(&permission) =>
+            {
+                Err(MiddlewareError::Authorization {
+                    reason: format!(
+                        "{} {} requires the {} permission",
+                        context.method, context.path, permission
+                    ),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Serve an admin API request for a caller with the admin permission,
+    /// returning the policy as it stands afterwards
+    pub fn handle_admin(
+        &self,
+        caller: &AuthResult,
+        request: RbacAdminRequest,
+    ) -> Result<RbacPolicy> {
+        if !caller.has_permission(ADMIN_PERMISSION)
+            && !self.permissions(caller).contains(ADMIN_PERMISSION)
+        {
+            return Err(MiddlewareError::Authorization {
+                reason: "Managing access control requires the admin permission".to_string(),
+            });
+        }
+        let mut policy = self.policy.write().unwrap();
+        match request {
+            RbacAdminRequest::SetRole { role, permissions } => {
+                policy.roles.insert(role, permissions);
+            }
+            RbacAdminRequest::RemoveRole { role } => {
+                policy.roles.remove(&role);
+            }
+            RbacAdminRequest::AssignRoles { user_id, roles } => {
+                if roles.is_empty() {
+                    policy.user_roles.remove(&user_id);
+                } else {
+                    policy.user_roles.insert(user_id, roles);
+                }
+            }
+            RbacAdminRequest::AddRule { rule } => {
+                policy.rules.retain(|existing| {
+                    existing.path != rule.path || existing.methods != rule.methods
+                });
+                policy.rules.push(rule);
+            }
+            RbacAdminRequest::RemoveRule { path } => {
+                policy.rules.retain(|rule| rule.path != path);
+            }
+            RbacAdminRequest::GetPolicy => {}
+        }
+        Ok(policy.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn caller(user_id: &str, permissions: &[&str]) -> AuthResult {
+        AuthResult::ApiKey {
+            key_id: user_id.to_string(),
+            user_id: user_id.to_string(),
+            permissions: permissions.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_rules_and_roles() {
+        let engine = RbacEngine::new(RbacPolicy {
+            roles: HashMap::from([(
+                "operator".to_string(),
+                vec!["write".to_string(), "peers".to_string()],
+            )]),
+            rules: vec![
+                AccessRule::new(&["POST"], "/api/v1/*", "write"),
+                AccessRule::new(&["*"], "/api/v1/peers/*", "peers"),
+                AccessRule::new(&["*"], "/admin/keys/*", "admin"),
+            ],
+            ..RbacPolicy::default()
+        });
+        let post = RequestContext::new("POST".to_string(), "/api/v1/transactions".to_string());
+        let peers = RequestContext::new("GET".to_string(), "/api/v1/peers/1".to_string());
+        let blocks = RequestContext::new("GET".to_string(), "/api/v1/blocks".to_string());
+
+        assert!(engine.authorize(&AuthResult::Anonymous, &blocks).is_ok());
+        assert!(engine.authorize(&caller("bob", &["read"]), &post).is_err());
+        assert!(engine.authorize(&caller("bob", &["write"]), &post).is_ok());
+        // The more specific peers rule decides over the POST rule
+        assert!(engine
+            .authorize(&caller("bob", &["write"]), &peers)
+            .is_err());
+        assert!(engine
+            .authorize(&caller("bob", &["operator"]), &peers)
+            .is_ok());
+
+        // The admin namespace is denied unless a rule grants it
+        let keys = RequestContext::new("POST".to_string(), "/admin/keys/issue".to_string());
+        let other = RequestContext::new("GET".to_string(), "/admin/config".to_string());
+        let admin = caller("root", &["admin"]);
+        assert!(engine.authorize(&admin, &keys).is_ok());
+        assert!(engine
+            .authorize(&caller("bob", &["operator"]), &keys)
+            .is_err());
+        assert!(engine.authorize(&admin, &other).is_err());
+    }
+
+    #[test]
+    fn test_admin_api() {
+        let engine = RbacEngine::default();
+        let admin = caller("root", &["admin"]);
+        let context = RequestContext::new("GET".to_string(), "/admin/status".to_string());
+        assert!(engine
+            .handle_admin(&caller("bob", &[]), RbacAdminRequest::GetPolicy)
+            .is_err());
+
+        engine
+            .handle_admin(
+                &admin,
+                RbacAdminRequest::SetRole {
+                    role: "auditor".to_string(),
+                    permissions: vec!["status".to_string()],
+                },
+            )
+            .unwrap();
+        engine
+            .handle_admin(
+                &admin,
+                RbacAdminRequest::AddRule {
+                    rule: AccessRule::new(&["GET"], "/admin/status", "status"),
+                },
+            )
+            .unwrap();
+        assert!(engine.authorize(&caller("carol", &[]), &context).is_err());
+
+        let policy = engine
+            .handle_admin(
+                &admin,
+                RbacAdminRequest::AssignRoles {
+                    user_id: "carol".to_string(),
+                    roles: vec!["auditor".to_string()],
+                },
+            )
+            .unwrap();
+        assert!(engine.authorize(&caller("carol", &[]), &context).is_ok());
+
+        // Policies round-trip through the file format
+        let path = std::env::temp_dir().join(format!("cc-rbac-{}.json", std::process::id()));
+        std::fs::write(&path, serde_json::to_string(&policy).unwrap()).unwrap();
+        assert_eq!(RbacEngine::from_file(&path).unwrap().policy(), policy);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,353 @@
+//! Circuit breakers and bulkheads
+//!
+//! Each handler has a circuit breaker fed with the outcomes of its calls.
+//! Once enough of the recent calls failed, the breaker opens and calls fail
+//! fast with a `Retry-After` instead of piling onto a struggling
+//! dependency. When the open period ends it lets a few probe calls through:
+//! if they succeed it closes, if one fails it opens again. Bulkheads cap
+//! the calls in flight per route, so one slow route cannot take every
+//! worker.
+//!
+//! Wrap handler dispatch in `ResilienceMiddleware::enter` and report the
+//! outcome on the returned guard.
+
+use crate::rbac::path_matches;
+use crate::{MiddlewareError, RequestContext, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Circuit breaker settings
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerConfig {
+    /// Recent calls the failure rate is computed over
+    pub window_size: usize,
+    /// Calls needed in the window before the breaker can open
+    pub min_requests: usize,
+    /// Failure rate, in percent, at which the breaker opens
+    pub failure_rate_threshold: f64,
+    /// How long the breaker stays open before probing
+    pub open_duration: Duration,
+    /// Successful probes needed to close again
+    pub half_open_probes: usize,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            window_size: 50,
+            min_requests: 10,
+            failure_rate_threshold: 50.0,
+            open_duration: Duration::from_secs(30),
+            half_open_probes: 3,
+        }
+    }
+}
+
+/// State of a handler's breaker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Debug)]
+enum Breaker {
+    Closed { outcomes: VecDeque<bool> },
+    Open { until: Instant },
+    HalfOpen { in_flight: usize, successes: usize },
+}
+
+impl Default for Breaker {
+    fn default() -> Self {
+        Breaker::Closed {
+            outcomes: VecDeque::new(),
+        }
+    }
+}
+
+/// Caps the calls in flight
+#[derive(Debug)]
+pub struct Bulkhead {
+    max_concurrent: usize,
+    in_flight: AtomicUsize,
+}
+
+impl Bulkhead {
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            max_concurrent,
+            in_flight: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Acquire)
+    }
+
+    /// A slot, released when the permit drops, or `None` when all are taken
+    pub fn try_acquire(self: &Arc<Self>) -> Option<BulkheadPermit> {
+        self.in_flight
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |in_flight| {
+                (in_flight < self.max_concurrent).then_some(in_flight + 1)
+            })
+            .ok()?;
+        Some(BulkheadPermit(self.clone()))
+    }
+}
+
+/// A call's slot in a bulkhead
+#[derive(Debug)]
+pub struct BulkheadPermit(Arc<Bulkhead>);
+
+impl Drop for BulkheadPermit {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Circuit breakers per handler and bulkheads per route
+#[derive(Default)]
+pub struct ResilienceMiddleware {
+    config: CircuitBreakerConfig,
+    breakers: Mutex<HashMap<String, Breaker>>,
+    /// Path patterns, as in access rules, with their bulkheads
+    bulkheads: Vec<(String, Arc<Bulkhead>)>,
+}
+
+impl ResilienceMiddleware {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            breakers: Mutex::new(HashMap::new()),
+            bulkheads: Vec::new(),
+        }
+    }
+
+    /// Allow at most `max_concurrent` calls at once to paths matching `path`
+    pub fn with_bulkhead(mut self, path: &str, max_concurrent: usize) -> Self {
+        self.bulkheads
+            .push((path.to_string(), Arc::new(Bulkhead::new(max_concurrent))));
+        self
+    }
+
+    pub fn state(&self, handler: &str) -> CircuitState {
+        match self.breakers.lock().unwrap().get(handler) {
+            Some(Breaker::Open { .. }) => CircuitState::Open,
+            Some(Breaker::HalfOpen { .. }) => CircuitState::HalfOpen,
+            _ => CircuitState::Closed,
+        }
+    }
+
+    /// Admit a call to `handler`, or fail fast when its breaker is open or
+    /// the route's bulkhead is full
+    pub fn enter(&self, handler: &str, context: &RequestContext) -> Result<CallGuard<'_>> {
+        let bulkhead = self
+            .bulkheads
+            .iter()
+            .filter(|(pattern, _)| path_matches(pattern, &context.path))
+            .max_by_key(|(pattern, _)| pattern.trim_end_matches('*').len());
+        let permit = match bulkhead {
+            Some((pattern, bulkhead)) => {
+                Some(
+                    bulkhead
+                        .try_acquire()
+                        .ok_or_else(|| MiddlewareError::Overloaded {
+                            reason: format!("Too many concurrent requests to {}", pattern),
+                        })?,
+                )
+            }
+            None => None,
+        };
+
+        let now = Instant::now();
+        let mut breakers = self.breakers.lock().unwrap();
+        let breaker = breakers.entry(handler.to_string()).or_default();
+        if let Breaker::Open { until } = *breaker {
+            if now < until {
+                return Err(MiddlewareError::CircuitOpen {
+                    handler: handler.to_string(),
+                    retry_after: until - now,
+                });
+            }
+            *breaker = Breaker::HalfOpen {
+                in_flight: 0,
+                successes: 0,
+            };
+        }
+        let probe = match breaker {
+            Breaker::HalfOpen { in_flight, .. } => {
+                if *in_flight >= self.config.half_open_probes.max(1) {
+                    return Err(MiddlewareError::CircuitOpen {
+                        handler: handler.to_string(),
+                        retry_after: Duration::from_secs(1),
+                    });
+                }
+                *in_flight += 1;
+                true
+            }
+            _ => false,
+        };
+        Ok(CallGuard {
+            middleware: self,
+            handler: handler.to_string(),
+            probe,
+            reported: false,
+            _permit: permit,
+        })
+    }
+
+    fn record(&self, handler: &str, probe: bool, success: bool) {
+        let mut breakers = self.breakers.lock().unwrap();
+        let Some(breaker) = breakers.get_mut(handler) else {
+            return;
+        };
+        let open = Breaker::Open {
+            until: Instant::now() + self.config.open_duration,
+        };
+        match breaker {
+            Breaker::Closed { outcomes } if !probe => {
+                outcomes.push_back(success);
+                while outcomes.len() > self.config.window_size.max(1) {
+                    outcomes.pop_front();
+                }
+                let failures = outcomes.iter().filter(|ok| !**ok).count();
+                if outcomes.len() >= self.config.min_requests
+                    && failures as f64 * 100.0 / outcomes.len() as f64
+                        >= self.config.failure_rate_threshold
+                {
+                    *breaker = open;
+                }
+            }
+            Breaker::HalfOpen {
+                in_flight,
+                successes,
+            } if probe => {
+                *in_flight = in_flight.saturating_sub(1);
+                if !success {
+                    *breaker = open;
+                } else {
+                    *successes += 1;
+                    if *successes >= self.config.half_open_probes.max(1) {
+                        *breaker = Breaker::default();
+                    }
+                }
+            }
+            // Calls admitted before the breaker last changed state
+            _ => {}
+        }
+    }
+
+    /// Free a probe slot whose outcome was never reported
+    fn abandon(&self, handler: &str) {
+        if let Some(Breaker::HalfOpen { in_flight, .. }) =
+            self.breakers.lock().unwrap().get_mut(handler)
+        {
+            *in_flight = in_flight.saturating_sub(1);
+        }
+    }
+}
+
+/// An admitted call; report how it went with `success` or `failure`
+pub struct CallGuard<'a> {
+    middleware: &'a ResilienceMiddleware,
+    handler: String,
+    probe: bool,
+    reported: bool,
+    _permit: Option<BulkheadPermit>,
+}
+
+impl CallGuard<'_> {
+    pub fn success(mut self) {
+        self.report(true);
+    }
+
+    pub fn failure(mut self) {
+        self.report(false);
+    }
+
+    /// Report by response status; server errors count as failures
+    pub fn finish(mut self, status: u16) {
+        self.report(status < 500);
+    }
+
+    fn report(&mut self, success: bool) {
+        self.reported = true;
+        self.middleware.record(&self.handler, self.probe, success);
+    }
+}
+
+impl Drop for CallGuard<'_> {
+    fn drop(&mut self) {
+        if self.probe && !self.reported {
+            self.middleware.abandon(&self.handler);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> RequestContext {
+        RequestContext::new("GET".to_string(), "/api/v1/blocks/7".to_string())
+    }
+
+    #[test]
+    fn test_breaker_opens_and_recovers() {
+        let middleware = ResilienceMiddleware::new(CircuitBreakerConfig {
+            window_size: 10,
+            min_requests: 4,
+            failure_rate_threshold: 50.0,
+            open_duration: Duration::from_millis(50),
+            half_open_probes: 2,
+        });
+        for success in [true, false, true, false] {
+            middleware
+                .enter("get_block", &context())
+                .unwrap()
+                .finish(if success { 200 } else { 503 });
+        }
+        assert_eq!(middleware.state("get_block"), CircuitState::Open);
+        let Err(error) = middleware.enter("get_block", &context()) else {
+            panic!("Expected the breaker to fail fast");
+        };
+        assert_eq!(error.status_code(), 503);
+        assert!(error.retry_after().unwrap() <= Duration::from_millis(50));
+        // Other handlers are unaffected
+        assert!(middleware.enter("get_peers", &context()).is_ok());
+
+        // After the open period, probes decide
+        std::thread::sleep(Duration::from_millis(60));
+        let first = middleware.enter("get_block", &context()).unwrap();
+        let second = middleware.enter("get_block", &context()).unwrap();
+        assert!(middleware.enter("get_block", &context()).is_err());
+        assert_eq!(middleware.state("get_block"), CircuitState::HalfOpen);
+        first.success();
+        second.failure();
+        assert_eq!(middleware.state("get_block"), CircuitState::Open);
+
+        std::thread::sleep(Duration::from_millis(60));
+        middleware.enter("get_block", &context()).unwrap().success();
+        middleware.enter("get_block", &context()).unwrap().success();
+        assert_eq!(middleware.state("get_block"), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_bulkhead() {
+        let middleware = ResilienceMiddleware::default().with_bulkhead("/api/v1/blocks/*", 2);
+        let first = middleware.enter("get_block", &context()).unwrap();
+        let _second = middleware.enter("get_block", &context()).unwrap();
+        let full = middleware.enter("get_block", &context());
+        assert!(matches!(full, Err(MiddlewareError::Overloaded { .. })));
+        // Unrelated routes have no bulkhead
+        let other = RequestContext::new("GET".to_string(), "/api/v1/peers".to_string());
+        assert!(middleware.enter("get_peers", &other).is_ok());
+
+        first.success();
+        assert!(middleware.enter("get_block", &context()).is_ok());
+    }
+}
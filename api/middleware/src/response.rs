@@ -0,0 +1,284 @@
+//! Response compression and conditional requests
+//!
+//! Successful `GET` and `HEAD` responses get a weak ETag over their body,
+//! and a request whose `If-None-Match` lists it is answered `304 Not
+//! Modified` with no body, so explorers polling unchanged blocks or
+//! accounts only pay for the headers. Bodies large enough to be worth it
+//! and of a compressible type are then encoded with the best coding the
+//! client accepts. The tag is weak because it identifies the content, not
+//! the encoded bytes, so it stays the same whichever coding is sent.
+
+use crate::{MiddlewareError, RequestContext, Result};
+use rpc_serialization::compression::ContentEncoding;
+use sha2::{Digest, Sha256};
+
+/// A response on its way out
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn new(status: u16, content_type: &str, body: Vec<u8>) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type".to_string(), content_type.to_string())],
+            body,
+        }
+    }
+
+    /// A header's value, by case-insensitive name
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Replace a header
+    pub fn set_header(&mut self, name: &str, value: String) {
+        self.headers
+            .retain(|(key, _)| !key.eq_ignore_ascii_case(name));
+        self.headers.push((name.to_string(), value));
+    }
+}
+
+/// Compression and ETag middleware
+#[derive(Debug, Clone)]
+pub struct ResponseMiddleware {
+    /// Smallest body worth compressing, in bytes
+    pub min_compress_size: usize,
+    /// Codings offered, in order of preference for `Accept-Encoding: *`
+    pub encodings: Vec<ContentEncoding>,
+    /// Content type prefixes that compress well
+    pub compressible_types: Vec<String>,
+    pub etags: bool,
+}
+
+impl ResponseMiddleware {
+    pub fn new() -> Self {
+        Self {
+            min_compress_size: 1024,
+            encodings: vec![
+                ContentEncoding::Zstd,
+                ContentEncoding::Gzip,
+                ContentEncoding::Deflate,
+            ],
+            compressible_types: vec![
+                "application/json".to_string(),
+                "application/javascript".to_string(),
+                "application/xml".to_string(),
+                "text/".to_string(),
+                "image/svg+xml".to_string(),
+            ],
+            etags: true,
+        }
+    }
+
+    /// Tag, revalidate and compress a response to `context`
+    pub fn process(
+        &self,
+        context: &RequestContext,
+        mut response: HttpResponse,
+    ) -> Result<HttpResponse> {
+        let cacheable = matches!(context.method.to_ascii_uppercase().as_str(), "GET" | "HEAD")
+            && response.status == 200;
+        if self.etags && cacheable {
+            let etag = match response.header("ETag") {
+                Some(etag) => etag.to_string(),
+                None => {
+                    let etag = etag_for(&response.body);
+                    response.set_header("ETag", etag.clone());
+                    etag
+                }
+            };
+            if request_header(context, "If-None-Match")
+                .is_some_and(|tags| matches_etag(tags, &etag))
+            {
+                return Ok(not_modified(response));
+            }
+        }
+
+        if self.compressible(&response) {
+            // Caches must key this response on the client's codings
+            add_vary(&mut response, "Accept-Encoding");
+            let encoding = request_header(context, "Accept-Encoding")
+                .and_then(|accept| ContentEncoding::negotiate(accept, &self.encodings))
+                .filter(|encoding| *encoding != ContentEncoding::Identity);
+            if let Some(encoding) = encoding {
+                response.body = encoding.compress(&response.body).map_err(|e| {
+                    MiddlewareError::Generic(format!("Response compression failed: {}", e))
+                })?;
+                response.set_header("Content-Encoding", encoding.name().to_string());
+            }
+        }
+        if !response
+            .headers
+            .iter()
+            .any(|(key, _)| key.eq_ignore_ascii_case("Content-Length"))
+        {
+            response.set_header("Content-Length", response.body.len().to_string());
+        }
+        Ok(response)
+    }
+
+    /// Whether the body is big enough and of a type worth compressing
+    fn compressible(&self, response: &HttpResponse) -> bool {
+        if response.body.len() < self.min_compress_size
+            || response.header("Content-Encoding").is_some()
+            || matches!(response.status, 204 | 206 | 304)
+        {
+            return false;
+        }
+        let content_type = response
+            .header("Content-Type")
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        self.compressible_types
+            .iter()
+            .any(|compressible| content_type.starts_with(compressible.as_str()))
+    }
+}
+
+impl Default for ResponseMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Weak ETag identifying `body`
+pub fn etag_for(body: &[u8]) -> String {
+    format!("W/\"{}\"", &hex::encode(Sha256::digest(body))[..32])
+}
+
+/// Whether an `If-None-Match` value lists `etag`, comparing weakly
+fn matches_etag(if_none_match: &str, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    if_none_match.trim() == "*"
+        || if_none_match
+            .split(',')
+            .any(|candidate| opaque(candidate) == opaque(etag))
+}
+
+/// The headers of `response` a 304 keeps, without its body
+fn not_modified(response: HttpResponse) -> HttpResponse {
+    const KEPT: [&str; 5] = ["ETag", "Cache-Control", "Expires", "Vary", "Last-Modified"];
+    HttpResponse {
+        status: 304,
+        headers: response
+            .headers
+            .into_iter()
+            .filter(|(key, _)| KEPT.iter().any(|kept| kept.eq_ignore_ascii_case(key)))
+            .collect(),
+        body: Vec::new(),
+    }
+}
+
+fn add_vary(response: &mut HttpResponse, header: &str) {
+    let vary = match response.header("Vary") {
+        Some(vary)
+            if vary
+                .split(',')
+                .any(|value| value.trim().eq_ignore_ascii_case(header) || value.trim() == "*") =>
+        {
+            return;
+        }
+        Some(vary) => format!("{}, {}", vary, header),
+        None => header.to_string(),
+    };
+    response.set_header("Vary", vary);
+}
+
+fn request_header<'a>(context: &'a RequestContext, name: &str) -> Option<&'a str> {
+    context
+        .headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get(headers: &[(&str, &str)]) -> RequestContext {
+        let mut context = RequestContext::new("GET".to_string(), "/api/v1/blocks/7".to_string());
+        for (name, value) in headers {
+            context.headers.insert(name.to_string(), value.to_string());
+        }
+        context
+    }
+
+    fn block() -> HttpResponse {
+        let body =
+            serde_json::to_vec(&vec![serde_json::json!({"height": 7, "txs": []}); 100]).unwrap();
+        HttpResponse::new(200, "application/json", body)
+    }
+
+    #[test]
+    fn test_compression_negotiation() {
+        let middleware = ResponseMiddleware::new();
+        let plain = block();
+
+        let response = middleware
+            .process(&get(&[("accept-encoding", "gzip, br")]), block())
+            .unwrap();
+        assert_eq!(response.header("Content-Encoding"), Some("gzip"));
+        assert_eq!(response.header("Vary"), Some("Accept-Encoding"));
+        assert!(response.body.len() < plain.body.len() / 4);
+        assert_eq!(
+            ContentEncoding::Gzip
+                .decompress(&response.body, plain.body.len())
+                .unwrap(),
+            plain.body
+        );
+
+        // No Accept-Encoding, small bodies and binary types stay as they are
+        let response = middleware.process(&get(&[]), block()).unwrap();
+        assert_eq!(response.header("Content-Encoding"), None);
+        let small = HttpResponse::new(200, "application/json", b"{}".to_vec());
+        let response = middleware
+            .process(&get(&[("Accept-Encoding", "gzip")]), small)
+            .unwrap();
+        assert_eq!(response.header("Content-Encoding"), None);
+        let binary = HttpResponse::new(200, "application/octet-stream", plain.body.clone());
+        let response = middleware
+            .process(&get(&[("Accept-Encoding", "gzip")]), binary)
+            .unwrap();
+        assert_eq!(response.header("Content-Encoding"), None);
+    }
+
+    #[test]
+    fn test_etag_and_not_modified() {
+        let middleware = ResponseMiddleware::new();
+        let first = middleware
+            .process(&get(&[("Accept-Encoding", "zstd")]), block())
+            .unwrap();
+        let etag = first.header("ETag").unwrap().to_string();
+        assert!(etag.starts_with("W/\""));
+        // The tag does not depend on the coding
+        let identity = middleware.process(&get(&[]), block()).unwrap();
+        assert_eq!(identity.header("ETag"), Some(etag.as_str()));
+
+        let revalidate = get(&[("If-None-Match", &format!("\"other\", {}", etag))]);
+        let response = middleware.process(&revalidate, block()).unwrap();
+        assert_eq!(response.status, 304);
+        assert!(response.body.is_empty());
+        assert_eq!(response.header("ETag"), Some(etag.as_str()));
+
+        // Changed content and non-GET requests get full responses
+        let mut changed = block();
+        changed.body.push(b' ');
+        assert_eq!(
+            middleware.process(&revalidate, changed).unwrap().status,
+            200
+        );
+        let mut post = revalidate.clone();
+        post.method = "POST".to_string();
+        let response = middleware.process(&post, block()).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.header("ETag"), None);
+    }
+}
@@ -0,0 +1,210 @@
+//! Route metadata
+//!
+//! Routes declare whether they need an authenticated caller and which
+//! permissions the caller must hold. REST routes are matched on method and
+//! path pattern, the most specific match winning as with access rules;
+//! JSON-RPC calls posted to the RPC path are matched on the method named
+//! in the body, with the metadata the protocol registry has for it, and a
+//! batch needs everything any of its calls needs. `AuthMiddleware` lets
+//! requests to public routes through without credentials and holds
+//! callers of protected routes to the route's permissions.
+
+use crate::rbac::path_matches;
+use crate::RequestContext;
+use rpc_protocol::{MethodMetadata, RpcProtocol};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Authentication requirements of a REST route
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouteMetadata {
+    /// HTTP methods the route covers; `*` covers all
+    pub methods: Vec<String>,
+    /// Exact path, or a prefix ending in `/*`; `*` matches every path
+    pub path: String,
+    pub auth_required: bool,
+    /// Permissions callers need, on top of being authenticated
+    #[serde(default)]
+    pub required_permissions: Vec<String>,
+}
+
+impl RouteMetadata {
+    /// A route anyone may call
+    pub fn public(methods: &[&str], path: &str) -> Self {
+        Self {
+            methods: methods.iter().map(|method| method.to_string()).collect(),
+            path: path.to_string(),
+            auth_required: false,
+            required_permissions: Vec::new(),
+        }
+    }
+
+    /// A route only callers holding `permissions` may call
+    pub fn protected(methods: &[&str], path: &str, permissions: &[&str]) -> Self {
+        Self {
+            auth_required: true,
+            required_permissions: permissions.iter().map(|p| p.to_string()).collect(),
+            ..Self::public(methods, path)
+        }
+    }
+
+    fn matches(&self, method: &str, path: &str) -> bool {
+        let method_matches = self
+            .methods
+            .iter()
+            .any(|allowed| allowed == "*" || allowed.eq_ignore_ascii_case(method));
+        method_matches && path_matches(&self.path, path)
+    }
+
+    fn specificity(&self) -> (usize, bool) {
+        (
+            self.path.trim_end_matches('*').len(),
+            !self.path.ends_with('*'),
+        )
+    }
+}
+
+/// What a request needs to get through authentication
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RouteAuth {
+    pub auth_required: bool,
+    pub required_permissions: Vec<String>,
+}
+
+impl From<&MethodMetadata> for RouteAuth {
+    fn from(metadata: &MethodMetadata) -> Self {
+        Self {
+            auth_required: metadata.auth_required,
+            required_permissions: metadata.required_permissions.clone(),
+        }
+    }
+}
+
+impl RouteAuth {
+    /// Needs of a batch containing both
+    fn merge(mut self, other: RouteAuth) -> Self {
+        self.auth_required |= other.auth_required;
+        for permission in other.required_permissions {
+            if !self.required_permissions.contains(&permission) {
+                self.required_permissions.push(permission);
+            }
+        }
+        self
+    }
+}
+
+/// REST routes and JSON-RPC methods with their requirements
+#[derive(Debug, Clone, Default)]
+pub struct RouteTable {
+    routes: Vec<RouteMetadata>,
+    /// Path JSON-RPC calls are posted to
+    rpc_path: Option<String>,
+    rpc_methods: HashMap<String, RouteAuth>,
+}
+
+impl RouteTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_route(mut self, route: RouteMetadata) -> Self {
+        self.routes.push(route);
+        self
+    }
+
+    /// Match JSON-RPC calls posted to `path` against the methods
+    /// registered with `protocol`
+    pub fn with_rpc_methods(mut self, path: &str, protocol: &RpcProtocol) -> Self {
+        self.rpc_path = Some(path.to_string());
+        for name in protocol.get_supported_methods() {
+            if let Some(metadata) = protocol.get_method(&name) {
+                self.rpc_methods.insert(name, RouteAuth::from(metadata));
+            }
+        }
+        self
+    }
+
+    /// Requirements of the route a request is for, or `None` when no route
+    /// or registered method matches it
+    pub fn resolve(&self, context: &RequestContext) -> Option<RouteAuth> {
+        if self.rpc_path.as_deref() == Some(context.path.as_str()) {
+            if let Some(auth) = self.resolve_rpc(&context.body) {
+                return Some(auth);
+            }
+        }
+        self.routes
+            .iter()
+            .filter(|route| route.matches(&context.method, &context.path))
+            .max_by_key(|route| route.specificity())
+            .map(|route| RouteAuth {
+                auth_required: route.auth_required,
+                required_permissions: route.required_permissions.clone(),
+            })
+    }
+
+    /// Requirements of the calls in a JSON-RPC body; `None` unless every
+    /// call names a registered method
+    fn resolve_rpc(&self, body: &[u8]) -> Option<RouteAuth> {
+        let calls = match serde_json::from_slice(body).ok()? {
+            Value::Array(calls) if !calls.is_empty() => calls,
+            call @ Value::Object(_) => vec![call],
+            _ => return None,
+        };
+        calls.iter().try_fold(RouteAuth::default(), |auth, call| {
+            let method = call.get("method")?.as_str()?;
+            Some(auth.merge(self.rpc_methods.get(method)?.clone()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_most_specific_rest_route_wins() {
+        let table = RouteTable::new()
+            .with_route(RouteMetadata::protected(&["*"], "/api/v1/*", &["read"]))
+            .with_route(RouteMetadata::public(&["GET"], "/api/v1/blocks/*"))
+            .with_route(RouteMetadata::protected(&["POST"], "/api/v1/transactions", &["write"]));
+
+        let blocks = RequestContext::new("GET".to_string(), "/api/v1/blocks/7".to_string());
+        assert!(!table.resolve(&blocks).unwrap().auth_required);
+        let submit = RequestContext::new("POST".to_string(), "/api/v1/transactions".to_string());
+        assert_eq!(table.resolve(&submit).unwrap().required_permissions, vec!["write"]);
+        let peers = RequestContext::new("GET".to_string(), "/api/v1/network/peers".to_string());
+        assert_eq!(table.resolve(&peers).unwrap().required_permissions, vec!["read"]);
+        let health = RequestContext::new("GET".to_string(), "/health".to_string());
+        assert_eq!(table.resolve(&health), None);
+    }
+
+    #[test]
+    fn test_rpc_methods_from_protocol() {
+        let mut protocol = RpcProtocol::new();
+        let mut method = protocol.get_method("cc_getBlockByHeight").unwrap().clone();
+        method.name = "admin_rotateKeys".to_string();
+        method.auth_required = true;
+        method.required_permissions = vec!["admin".to_string()];
+        protocol.register_method(method);
+        let table = RouteTable::new().with_rpc_methods("/rpc", &protocol);
+
+        let call = |body: &str| {
+            RequestContext::new("POST".to_string(), "/rpc".to_string()).with_body(body.as_bytes().to_vec())
+        };
+        let public = call(r#"{"jsonrpc":"2.0","method":"cc_getBlockByHeight","id":1}"#);
+        assert_eq!(table.resolve(&public), Some(RouteAuth::default()));
+
+        // A batch is as protected as its most protected call
+        let batch = call(
+            r#"[{"jsonrpc":"2.0","method":"cc_getBlockByHeight","id":1},
+                {"jsonrpc":"2.0","method":"admin_rotateKeys","id":2}]"#,
+        );
+        let auth = table.resolve(&batch).unwrap();
+        assert!(auth.auth_required);
+        assert_eq!(auth.required_permissions, vec!["admin"]);
+
+        assert_eq!(table.resolve(&call(r#"{"method":"cc_unknown"}"#)), None);
+        assert_eq!(table.resolve(&call("not json")), None);
+    }
+}
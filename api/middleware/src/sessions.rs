@@ -0,0 +1,556 @@
+//! Sessions
+//!
+//! Interactive users sign in once and get a session: a short-lived access
+//! token, an HS256 JWT sent as a bearer token, and a refresh token that
+//! trades for a new pair when the access token runs out. Each refresh
+//! slides the session's expiry forward by the idle timeout, up to its
+//! maximum lifetime, so a dashboard left open keeps working while an
+//! abandoned one lapses. Refresh tokens are single use and, like API keys,
+//! only stored hashed; presenting a refresh token that was already traded
+//! in means it leaked, and revokes the whole session. Access tokens stop
+//! working as soon as their session is revoked or lapses.
+
+use crate::api_keys::ADMIN_PERMISSION;
+use crate::jwt::{decode_json, sign_hs256, JwtAlgorithm, JwtClaims, JwtConfig, JwtValidator};
+use crate::{AuthResult, MiddlewareError, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Prefix of every refresh token
+pub const REFRESH_TOKEN_PREFIX: &str = "ccr";
+
+/// `iss` claim of access tokens
+pub const SESSION_ISSUER: &str = "cc-chain-sessions";
+
+/// Session lifetimes
+#[derive(Debug, Clone)]
+pub struct SessionConfig {
+    pub access_token_ttl: Duration,
+    /// How long a session lives without being refreshed
+    pub idle_timeout: Duration,
+    /// How long a session lives however often it is refreshed
+    pub max_lifetime: Duration,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            access_token_ttl: Duration::from_secs(15 * 60),
+            idle_timeout: Duration::from_secs(12 * 60 * 60),
+            max_lifetime: Duration::from_secs(30 * 24 * 60 * 60),
+        }
+    }
+}
+
+/// A stored session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub session_id: String,
+    pub user_id: String,
+    pub permissions: Vec<String>,
+    /// Hex SHA-256 of the current refresh token
+    pub refresh_hash: String,
+    /// Hex SHA-256 of the refresh token traded in last
+    pub previous_refresh_hash: Option<String>,
+    /// Unix seconds
+    pub created_at: u64,
+    pub refreshed_at: u64,
+    pub expires_at: u64,
+    pub revoked_at: Option<u64>,
+}
+
+impl SessionRecord {
+    /// Whether the session is usable at unix second `now`
+    pub fn is_active(&self, now: u64) -> bool {
+        self.revoked_at.is_none() && now < self.expires_at
+    }
+}
+
+/// Where sessions are kept
+pub trait SessionStore: Send + Sync {
+    fn get(&self, session_id: &str) -> Result<Option<SessionRecord>>;
+    /// Insert or replace the session with the same id
+    fn put(&self, record: SessionRecord) -> Result<()>;
+    fn list(&self) -> Result<Vec<SessionRecord>>;
+}
+
+/// Keeps sessions in memory only
+#[derive(Default)]
+pub struct InMemorySessionStore {
+    records: RwLock<HashMap<String, SessionRecord>>,
+}
+
+impl InMemorySessionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SessionStore for InMemorySessionStore {
+    fn get(&self, session_id: &str) -> Result<Option<SessionRecord>> {
+        Ok(self.records.read().unwrap().get(session_id).cloned())
+    }
+
+    fn put(&self, record: SessionRecord) -> Result<()> {
+        self.records
+            .write()
+            .unwrap()
+            .insert(record.session_id.clone(), record);
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<SessionRecord>> {
+        Ok(self.records.read().unwrap().values().cloned().collect())
+    }
+}
+
+/// Tokens handed to a client; the refresh token cannot be recovered
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionTokens {
+    pub session_id: String,
+    pub access_token: String,
+    pub refresh_token: String,
+    /// Unix seconds
+    pub access_expires_at: u64,
+    pub session_expires_at: u64,
+}
+
+/// Session API requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum SessionRequest {
+    Refresh {
+        refresh_token: String,
+    },
+    Logout {
+        refresh_token: String,
+    },
+    Revoke {
+        session_id: String,
+    },
+    /// The caller's active sessions
+    List,
+}
+
+/// Session API responses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum SessionResponse {
+    Tokens(SessionTokens),
+    Revoked { session_id: String },
+    Sessions { sessions: Vec<SessionRecord> },
+}
+
+/// Creates, refreshes and revokes sessions
+pub struct SessionManager {
+    store: Arc<dyn SessionStore>,
+    config: SessionConfig,
+    secret: Vec<u8>,
+    validator: JwtValidator,
+    /// Serializes refreshes, so a refresh token is only ever traded once
+    refresh_lock: Mutex<()>,
+}
+
+impl SessionManager {
+    /// Sign access tokens with `secret`
+    pub fn new(store: Arc<dyn SessionStore>, config: SessionConfig, secret: &[u8]) -> Self {
+        let validator = JwtValidator::with_secret(
+            JwtConfig {
+                algorithms: vec![JwtAlgorithm::HS256],
+                issuer: Some(SESSION_ISSUER.to_string()),
+                ..JwtConfig::default()
+            },
+            secret,
+        );
+        Self {
+            store,
+            config,
+            secret: secret.to_vec(),
+            validator,
+            refresh_lock: Mutex::new(()),
+        }
+    }
+
+    /// Start a session for a user who has just signed in
+    pub fn create(&self, user_id: &str, permissions: Vec<String>) -> Result<SessionTokens> {
+        self.create_at(user_id, permissions, unix_now())
+    }
+
+    /// Trade a refresh token for new tokens, extending the session
+    pub fn refresh(&self, refresh_token: &str) -> Result<SessionTokens> {
+        self.refresh_at(refresh_token, unix_now())
+    }
+
+    /// End the session a refresh token belongs to
+    pub fn logout(&self, refresh_token: &str) -> Result<String> {
+        let now = unix_now();
+        let record = self.find(refresh_token)?;
+        if !crate::constant_time_eq(
+            record.refresh_hash.as_bytes(),
+            hash_token(refresh_token).as_bytes(),
+        ) {
+            return Err(invalid("Invalid refresh token"));
+        }
+        self.revoke_record(record, now)
+    }
+
+    pub fn revoke(&self, session_id: &str) -> Result<()> {
+        let record = self
+            .store
+            .get(session_id)?
+            .ok_or_else(|| MiddlewareError::Validation {
+                reason: format!("Unknown session {}", session_id),
+            })?;
+        self.revoke_record(record, unix_now()).map(|_| ())
+    }
+
+    /// Active sessions, of `user_id` if given, oldest first
+    pub fn list(&self, user_id: Option<&str>) -> Result<Vec<SessionRecord>> {
+        let now = unix_now();
+        let mut sessions: Vec<SessionRecord> = self
+            .store
+            .list()?
+            .into_iter()
+            .filter(|record| record.is_active(now))
+            .filter(|record| user_id.is_none_or(|user| record.user_id == user))
+            .collect();
+        sessions.sort_by(|a, b| (a.created_at, &a.session_id).cmp(&(b.created_at, &b.session_id)));
+        Ok(sessions)
+    }
+
+    /// Claims of an access token whose session is still active
+    pub fn authenticate(&self, access_token: &str) -> Result<JwtClaims> {
+        self.authenticate_at(access_token, unix_now())
+    }
+
+    /// Serve a session API request. Refresh and logout are authorized by
+    /// the refresh token itself; a session can be revoked by its user or
+    /// an admin.
+    pub fn handle(&self, caller: &AuthResult, request: SessionRequest) -> Result<SessionResponse> {
+        Ok(match request {
+            SessionRequest::Refresh { refresh_token } => {
+                SessionResponse::Tokens(self.refresh(&refresh_token)?)
+            }
+            SessionRequest::Logout { refresh_token } => SessionResponse::Revoked {
+                session_id: self.logout(&refresh_token)?,
+            },
+            SessionRequest::Revoke { session_id } => {
+                let owner = self.store.get(&session_id)?.map(|record| record.user_id);
+                if !caller.has_permission(ADMIN_PERMISSION)
+                    && (caller.user_id().is_none() || caller.user_id() != owner.as_deref())
+                {
+                    return Err(MiddlewareError::Authorization {
+                        reason: "Only its user or an admin can revoke a session".to_string(),
+                    });
+                }
+                self.revoke(&session_id)?;
+                SessionResponse::Revoked { session_id }
+            }
+            SessionRequest::List => {
+                let user_id = caller
+                    .user_id()
+                    .ok_or_else(|| MiddlewareError::Authentication {
+                        reason: "Listing sessions requires authentication".to_string(),
+                    })?;
+                SessionResponse::Sessions {
+                    sessions: self.list(Some(user_id))?,
+                }
+            }
+        })
+    }
+
+    fn create_at(
+        &self,
+        user_id: &str,
+        permissions: Vec<String>,
+        now: u64,
+    ) -> Result<SessionTokens> {
+        let mut id = [0u8; 12];
+        rand::thread_rng().fill_bytes(&mut id);
+        let session_id = hex::encode(id);
+        let refresh_token = new_refresh_token(&session_id);
+        let record = SessionRecord {
+            session_id,
+            user_id: user_id.to_string(),
+            permissions,
+            refresh_hash: hash_token(&refresh_token),
+            previous_refresh_hash: None,
+            created_at: now,
+            refreshed_at: now,
+            expires_at: now + self.config.idle_timeout.as_secs().min(self.max_lifetime()),
+            revoked_at: None,
+        };
+        self.store.put(record.clone())?;
+        Ok(self.tokens(&record, refresh_token, now))
+    }
+
+    fn refresh_at(&self, refresh_token: &str, now: u64) -> Result<SessionTokens> {
+        let _guard = self.refresh_lock.lock().unwrap();
+        let record = self.find(refresh_token)?;
+        let hash = hash_token(refresh_token);
+        if !crate::constant_time_eq(record.refresh_hash.as_bytes(), hash.as_bytes()) {
+            if record.previous_refresh_hash.as_deref() == Some(hash.as_str()) {
+                self.revoke_record(record, now)?;
+                return Err(invalid(
+                    "Refresh token was already used; the session has been revoked",
+                ));
+            }
+            return Err(invalid("Invalid refresh token"));
+        }
+        if record.revoked_at.is_some() {
+            return Err(invalid("Session has been revoked"));
+        }
+        if !record.is_active(now) {
+            return Err(invalid("Session has expired"));
+        }
+
+        let refresh_token = new_refresh_token(&record.session_id);
+        let record = SessionRecord {
+            previous_refresh_hash: Some(hash),
+            refresh_hash: hash_token(&refresh_token),
+            refreshed_at: now,
+            expires_at: (now + self.config.idle_timeout.as_secs())
+                .min(record.created_at + self.max_lifetime()),
+            ..record
+        };
+        self.store.put(record.clone())?;
+        Ok(self.tokens(&record, refresh_token, now))
+    }
+
+    fn authenticate_at(&self, access_token: &str, now: u64) -> Result<JwtClaims> {
+        let claims = self.validator.validate_at(access_token, now)?;
+        let session_id = claims
+            .claims
+            .get("sid")
+            .and_then(serde_json::Value::as_str)
+            .ok_or_else(|| invalid("Access token has no session"))?;
+        match self.store.get(session_id)? {
+            Some(record) if record.is_active(now) => Ok(claims),
+            _ => Err(invalid("Session has ended")),
+        }
+    }
+
+    fn tokens(&self, record: &SessionRecord, refresh_token: String, now: u64) -> SessionTokens {
+        let access_expires_at =
+            (now + self.config.access_token_ttl.as_secs()).min(record.expires_at);
+        let claims = serde_json::json!({
+            "iss": SESSION_ISSUER,
+            "sub": record.user_id,
+            "sid": record.session_id,
+            "permissions": record.permissions,
+            "iat": now,
+            "exp": access_expires_at,
+        });
+        SessionTokens {
+            session_id: record.session_id.clone(),
+            access_token: sign_hs256(&claims, &self.secret),
+            refresh_token,
+            access_expires_at,
+            session_expires_at: record.expires_at,
+        }
+    }
+
+    /// The session a refresh token names, before checking its secret
+    fn find(&self, refresh_token: &str) -> Result<SessionRecord> {
+        refresh_token
+            .strip_prefix(REFRESH_TOKEN_PREFIX)
+            .and_then(|rest| rest.strip_prefix('_'))
+            .and_then(|rest| rest.split_once('_'))
+            .map(|(session_id, _)| session_id)
+            .map(|session_id| self.store.get(session_id))
+            .transpose()?
+            .flatten()
+            .ok_or_else(|| invalid("Invalid refresh token"))
+    }
+
+    fn revoke_record(&self, record: SessionRecord, now: u64) -> Result<String> {
+        let session_id = record.session_id.clone();
+        if record.revoked_at.is_none() {
+            self.store.put(SessionRecord {
+                revoked_at: Some(now),
+                ..record
+            })?;
+        }
+        Ok(session_id)
+    }
+
+    fn max_lifetime(&self) -> u64 {
+        self.config.max_lifetime.as_secs()
+    }
+}
+
+fn new_refresh_token(session_id: &str) -> String {
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+    format!(
+        "{}_{}_{}",
+        REFRESH_TOKEN_PREFIX,
+        session_id,
+        URL_SAFE_NO_PAD.encode(secret)
+    )
+}
+
+/// Whether a token's claims, unverified, mark it as a session access token
+///
+/// Only decides how a token is checked, never whether it is accepted: a
+/// session token that fails its session check must not be retried against
+/// a provider validator that may share the session secret.
+pub(crate) fn is_session_token(token: &str) -> bool {
+    token
+        .split('.')
+        .nth(1)
+        .and_then(|payload| decode_json::<serde_json::Map<String, serde_json::Value>>(payload).ok())
+        .is_some_and(|claims| {
+            claims.get("iss").and_then(serde_json::Value::as_str) == Some(SESSION_ISSUER)
+                || claims.contains_key("sid")
+        })
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn invalid(reason: &str) -> MiddlewareError {
+    MiddlewareError::Authentication {
+        reason: reason.to_string(),
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AuthMiddleware, RequestContext};
+
+    fn manager() -> Arc<SessionManager> {
+        Arc::new(SessionManager::new(
+            Arc::new(InMemorySessionStore::new()),
+            SessionConfig {
+                access_token_ttl: Duration::from_secs(60),
+                idle_timeout: Duration::from_secs(600),
+                max_lifetime: Duration::from_secs(1000),
+            },
+            b"session secret",
+        ))
+    }
+
+    #[test]
+    fn test_refresh_rotates_and_detects_reuse() {
+        let sessions = manager();
+        let first = sessions.create("alice", vec!["read".to_string()]).unwrap();
+        let auth = AuthMiddleware::new().with_sessions(sessions.clone());
+        let mut context = RequestContext::new("GET".to_string(), "/api/v1/blocks".to_string());
+        context.headers.insert(
+            "Authorization".to_string(),
+            format!("Bearer {}", first.access_token),
+        );
+        let result = auth.process(&context).unwrap();
+        assert_eq!(result.user_id(), Some("alice"));
+        assert!(result.has_permission("read"));
+
+        let second = sessions.refresh(&first.refresh_token).unwrap();
+        assert_eq!(second.session_id, first.session_id);
+        assert_ne!(second.refresh_token, first.refresh_token);
+        assert!(sessions.authenticate(&second.access_token).is_ok());
+
+        // Replaying the traded-in token ends the session for everyone
+        assert!(sessions.refresh(&first.refresh_token).is_err());
+        assert!(sessions.refresh(&second.refresh_token).is_err());
+        assert!(sessions.authenticate(&second.access_token).is_err());
+        assert!(auth.process(&context).is_err());
+    }
+
+    #[test]
+    fn test_sliding_expiration() {
+        let sessions = manager();
+        let tokens = sessions.create_at("bob", Vec::new(), 1_000).unwrap();
+        assert_eq!(tokens.access_expires_at, 1_060);
+        assert_eq!(tokens.session_expires_at, 1_600);
+        assert!(sessions
+            .authenticate_at(&tokens.access_token, 1_500)
+            .is_err());
+
+        // Each refresh pushes the idle deadline out, up to the lifetime cap
+        let tokens = sessions.refresh_at(&tokens.refresh_token, 1_500).unwrap();
+        assert_eq!(tokens.session_expires_at, 2_000);
+        let tokens = sessions.refresh_at(&tokens.refresh_token, 1_900).unwrap();
+        assert_eq!(tokens.session_expires_at, 2_000);
+        assert_eq!(tokens.access_expires_at, 1_960);
+        assert!(sessions.refresh_at(&tokens.refresh_token, 2_000).is_err());
+
+        let idle = sessions.create_at("bob", Vec::new(), 1_000).unwrap();
+        assert!(sessions.refresh_at(&idle.refresh_token, 1_600).is_err());
+    }
+
+    #[test]
+    fn test_logout_and_revoke() {
+        let sessions = manager();
+        let alice = sessions.create("alice", Vec::new()).unwrap();
+        let other = sessions.create("alice", Vec::new()).unwrap();
+        let caller = AuthResult::JwtToken {
+            user_id: "alice".to_string(),
+            permissions: Vec::new(),
+            expires_at: SystemTime::now(),
+        };
+        let SessionResponse::Sessions { sessions: listed } =
+            sessions.handle(&caller, SessionRequest::List).unwrap()
+        else {
+            panic!("Expected sessions");
+        };
+        assert_eq!(listed.len(), 2);
+
+        let logout = SessionRequest::Logout {
+            refresh_token: alice.refresh_token.clone(),
+        };
+        assert!(sessions.handle(&AuthResult::Anonymous, logout).is_ok());
+        assert!(sessions.refresh(&alice.refresh_token).is_err());
+        assert!(sessions.authenticate(&alice.access_token).is_err());
+        assert!(sessions.authenticate(&other.access_token).is_ok());
+
+        let revoke = SessionRequest::Revoke {
+            session_id: other.session_id.clone(),
+        };
+        let mallory = AuthResult::JwtToken {
+            user_id: "mallory".to_string(),
+            permissions: Vec::new(),
+            expires_at: SystemTime::now(),
+        };
+        assert!(matches!(
+            sessions.handle(&mallory, revoke.clone()),
+            Err(MiddlewareError::Authorization { .. })
+        ));
+        assert!(sessions.handle(&caller, revoke).is_ok());
+        assert!(sessions.authenticate(&other.access_token).is_err());
+    }
+
+    #[test]
+    fn test_revoked_session_is_not_retried_as_provider_token() {
+        let sessions = manager();
+        let tokens = sessions.create("alice", Vec::new()).unwrap();
+        // A provider validator sharing the session secret
+        let auth = AuthMiddleware::new()
+            .with_sessions(sessions.clone())
+            .with_jwt(JwtValidator::with_secret(JwtConfig::default(), b"session secret"));
+        let mut context = RequestContext::new("GET".to_string(), "/api/v1/blocks".to_string());
+        context.headers.insert(
+            "Authorization".to_string(),
+            format!("Bearer {}", tokens.access_token),
+        );
+        assert!(auth.process(&context).is_ok());
+
+        sessions.revoke(&tokens.session_id).unwrap();
+        assert!(auth.process(&context).is_err());
+    }
+}
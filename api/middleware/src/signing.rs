@@ -0,0 +1,339 @@
+//! Request signatures
+//!
+//! Clients sign each request with a shared secret (HMAC-SHA256) or a
+//! private key (RS256 or ES256) and send the result as
+//!
+//! ```text
+//! Authorization: Signature keyId="k1",timestamp="1750000000",nonce="5f2c",signature="<base64url>"
+//! ```
+//!
+//! The signature covers the canonical request from `canonical_request`:
+//! method, path, query, timestamp, nonce and the SHA-256 of the body, one
+//! per line. Query keys and values are percent-encoded (everything but
+//! RFC 3986 unreserved characters, as `%XX` in upper case), then the
+//! `key=value` pairs are sorted and joined with `&`.
+//!
+//! Requests whose timestamp is further from the server clock than the
+//! allowed skew are rejected, and a nonce is accepted once per key while
+//! its timestamp is within that skew, so a captured request cannot be
+//! replayed.
+
+use crate::jwks::VerifyingKey;
+use crate::{AuthResult, MiddlewareError, RequestContext, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Scheme name in the `Authorization` header
+pub const SIGNATURE_SCHEME: &str = "Signature";
+
+/// Seconds between two sweeps of expired nonces
+const NONCE_PRUNE_INTERVAL: u64 = 60;
+/// Stored nonces that trigger a sweep before the interval is up
+const NONCE_PRUNE_SIZE: usize = 100_000;
+
+/// A key clients sign requests with, and whom it authenticates
+#[derive(Debug, Clone)]
+pub struct SigningCredential {
+    pub key_id: String,
+    pub user_id: String,
+    pub permissions: Vec<String>,
+    pub key: VerifyingKey,
+}
+
+/// Request signature settings
+#[derive(Debug, Clone)]
+pub struct SignatureConfig {
+    /// Largest accepted difference between a request's timestamp and the
+    /// server clock, either way
+    pub max_clock_skew: Duration,
+}
+
+impl Default for SignatureConfig {
+    fn default() -> Self {
+        Self {
+            max_clock_skew: Duration::from_secs(300), // 5 minutes
+        }
+    }
+}
+
+/// The string a request's signature covers
+pub fn canonical_request(context: &RequestContext, timestamp: u64, nonce: &str) -> String {
+    let mut query: Vec<String> = context
+        .query_params
+        .iter()
+        .map(|(key, value)| format!("{}={}", percent_encode(key), percent_encode(value)))
+        .collect();
+    query.sort();
+    format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        context.method.to_ascii_uppercase(),
+        context.path,
+        query.join("&"),
+        timestamp,
+        nonce,
+        hex::encode(Sha256::digest(&context.body))
+    )
+}
+
+/// Verifies signed requests
+pub struct SignatureVerifier {
+    config: SignatureConfig,
+    credentials: HashMap<String, SigningCredential>,
+    nonces: Mutex<NonceCache>,
+}
+
+/// Nonces seen per key id, with the unix second they can be forgotten
+///
+/// Expired nonces are swept every `NONCE_PRUNE_INTERVAL` seconds, or sooner
+/// once the cache doubles in size since the last sweep, rather than on every
+/// verification.
+#[derive(Default)]
+struct NonceCache {
+    seen: HashMap<(String, String), u64>,
+    next_prune: u64,
+    prune_size: usize,
+}
+
+impl NonceCache {
+    /// Record a nonce, returning false if it is already in use
+    fn insert(&mut self, key_id: &str, nonce: &str, forget_at: u64, now: u64) -> bool {
+        if now >= self.next_prune || self.seen.len() >= self.prune_size {
+            self.seen.retain(|_, forget_at| *forget_at > now);
+            self.next_prune = now + NONCE_PRUNE_INTERVAL;
+            self.prune_size = (self.seen.len() * 2).max(NONCE_PRUNE_SIZE);
+        }
+        match self.seen.entry((key_id.to_string(), nonce.to_string())) {
+            Entry::Occupied(entry) if *entry.get() > now => false,
+            Entry::Occupied(mut entry) => {
+                entry.insert(forget_at);
+                true
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(forget_at);
+                true
+            }
+        }
+    }
+}
+
+impl SignatureVerifier {
+    pub fn new(config: SignatureConfig) -> Self {
+        Self {
+            config,
+            credentials: HashMap::new(),
+            nonces: Mutex::new(NonceCache::default()),
+        }
+    }
+
+    pub fn with_credential(mut self, credential: SigningCredential) -> Self {
+        self.credentials
+            .insert(credential.key_id.clone(), credential);
+        self
+    }
+
+    /// Authenticate a request from the parameters of its `Signature`
+    /// authorization header
+    pub fn verify(&self, context: &RequestContext, params: &str) -> Result<AuthResult> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        self.verify_at(context, params, now)
+    }
+
+    fn verify_at(&self, context: &RequestContext, params: &str, now: u64) -> Result<AuthResult> {
+        let params = parse_params(params);
+        let param = |name: &str| {
+            params
+                .get(name)
+                .copied()
+                .ok_or_else(|| invalid(&format!("missing {}", name)))
+        };
+        let key_id = param("keyId")?;
+        let nonce = param("nonce")?;
+        let timestamp: u64 = param("timestamp")?
+            .parse()
+            .map_err(|_| invalid("malformed timestamp"))?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(param("signature")?)
+            .map_err(|_| invalid("malformed signature"))?;
+        if nonce.is_empty() {
+            return Err(invalid("empty nonce"));
+        }
+
+        let skew = self.config.max_clock_skew.as_secs();
+        if timestamp.abs_diff(now) > skew {
+            return Err(invalid("timestamp outside the allowed clock skew"));
+        }
+        let credential = self
+            .credentials
+            .get(key_id)
+            .ok_or_else(|| invalid("unknown key"))?;
+        let canonical = canonical_request(context, timestamp, nonce);
+        if !credential.key.verify(canonical.as_bytes(), &signature) {
+            return Err(invalid("signature does not match"));
+        }
+
+        // Only verified requests record nonces, so forgeries cannot use them up
+        let fresh = self
+            .nonces
+            .lock()
+            .unwrap()
+            .insert(key_id, nonce, timestamp + skew + 1, now);
+        if !fresh {
+            return Err(invalid("nonce already used"));
+        }
+
+        Ok(AuthResult::Signature {
+            key_id: credential.key_id.clone(),
+            user_id: credential.user_id.clone(),
+            permissions: credential.permissions.clone(),
+        })
+    }
+}
+
+/// Percent-encode everything but RFC 3986 unreserved characters, so `&`
+/// and `=` inside a key or value cannot be confused with the separators
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// `name="value"` pairs, separated by commas
+fn parse_params(params: &str) -> HashMap<&str, &str> {
+    params
+        .split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(name, value)| (name.trim(), value.trim().trim_matches('"')))
+        .collect()
+}
+
+fn invalid(reason: &str) -> MiddlewareError {
+    MiddlewareError::Authentication {
+        reason: format!("Invalid request signature: {}", reason),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_750_000_000;
+
+    fn request() -> RequestContext {
+        let mut context =
+            RequestContext::new("POST".to_string(), "/api/v1/transactions".to_string())
+                .with_body(br#"{"amount":5}"#.to_vec());
+        context
+            .query_params
+            .insert("fee".to_string(), "low".to_string());
+        context
+    }
+
+    fn hmac_params(context: &RequestContext, timestamp: u64, nonce: &str) -> String {
+        let canonical = canonical_request(context, timestamp, nonce);
+        let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, b"secret");
+        let signature = ring::hmac::sign(&key, canonical.as_bytes());
+        format!(
+            r#"keyId="hmac-1",timestamp="{}",nonce="{}",signature="{}""#,
+            timestamp,
+            nonce,
+            URL_SAFE_NO_PAD.encode(signature)
+        )
+    }
+
+    fn verifier() -> SignatureVerifier {
+        SignatureVerifier::new(SignatureConfig::default()).with_credential(SigningCredential {
+            key_id: "hmac-1".to_string(),
+            user_id: "bot".to_string(),
+            permissions: vec!["write".to_string()],
+            key: VerifyingKey::Hmac(b"secret".to_vec()),
+        })
+    }
+
+    #[test]
+    fn test_hmac_signature_skew_and_replay() {
+        let verifier = verifier();
+        let context = request();
+        let params = hmac_params(&context, NOW, "n-1");
+        let auth = verifier.verify_at(&context, &params, NOW + 10).unwrap();
+        assert_eq!(auth.user_id(), Some("bot"));
+        assert!(auth.has_permission("write"));
+
+        // Replays are rejected, fresh nonces are not
+        assert!(verifier.verify_at(&context, &params, NOW + 20).is_err());
+        let fresh = hmac_params(&context, NOW, "n-2");
+        assert!(verifier.verify_at(&context, &fresh, NOW + 20).is_ok());
+
+        // Stale or future timestamps are rejected
+        let stale = hmac_params(&context, NOW - 301, "n-3");
+        assert!(verifier.verify_at(&context, &stale, NOW).is_err());
+        let future = hmac_params(&context, NOW + 301, "n-4");
+        assert!(verifier.verify_at(&context, &future, NOW).is_err());
+
+        // Any change to the request breaks the signature
+        let tampered = request().with_body(br#"{"amount":500}"#.to_vec());
+        let params = hmac_params(&context, NOW, "n-5");
+        assert!(verifier.verify_at(&tampered, &params, NOW).is_err());
+    }
+
+    #[test]
+    fn test_expired_nonces_are_swept_periodically() {
+        let mut nonces = NonceCache::default();
+        assert!(nonces.insert("k", "n-1", NOW + 10, NOW));
+        assert!(!nonces.insert("k", "n-1", NOW + 10, NOW + 5));
+        assert!(nonces.insert("k", "n-2", NOW + 20, NOW + 5));
+        assert_eq!(nonces.seen.len(), 2, "no sweep within the interval");
+
+        // An expired nonce may be reused even before it is swept
+        assert!(nonces.insert("k", "n-1", NOW + 30, NOW + 15));
+        assert!(nonces.insert("k", "n-3", NOW + 100, NOW + NONCE_PRUNE_INTERVAL + 25));
+        assert_eq!(nonces.seen.len(), 1);
+    }
+
+    #[test]
+    fn test_canonical_query_is_unambiguous() {
+        let mut split = RequestContext::new("GET".to_string(), "/api/v1/blocks".to_string());
+        split.query_params.insert("a".to_string(), "1".to_string());
+        split.query_params.insert("b".to_string(), "2".to_string());
+        let mut joined = RequestContext::new("GET".to_string(), "/api/v1/blocks".to_string());
+        joined.query_params.insert("a".to_string(), "1&b=2".to_string());
+
+        let canonical = canonical_request(&joined, NOW, "n-1");
+        assert_ne!(canonical_request(&split, NOW, "n-1"), canonical);
+        assert!(canonical.contains("\na=1%26b%3D2\n"));
+    }
+
+    #[test]
+    fn test_es256_signature() {
+        let decode = |value: &str| URL_SAFE_NO_PAD.decode(value).unwrap();
+        let verifier =
+            SignatureVerifier::new(SignatureConfig::default()).with_credential(SigningCredential {
+                key_id: "ec-1".to_string(),
+                user_id: "partner".to_string(),
+                permissions: vec!["read".to_string()],
+                key: VerifyingKey::EcP256 {
+                    x: decode("aelKxWKM6GHcngB6Tn5RyRJuh3SUb3gje4T4Af3lghY"),
+                    y: decode("KRlyxWIS2POJ_GzK3QFBFiUHnTeCgW-K5BPREoyb034"),
+                },
+            });
+        let params = r#"keyId="ec-1", timestamp="1750000000", nonce="n-1", signature="xf64r_VXuC-OWQ97J3CsZh8zVOipx0zjETUPRwc9_Q8j3V1PzxHs1BP68DPp4rUJEuHlliPyZXGZHa4gEJHhlA""#;
+        let auth = verifier.verify_at(&request(), params, NOW).unwrap();
+        assert_eq!(auth.user_id(), Some("partner"));
+        assert!(verifier
+            .verify_at(&request(), &params.replace("ec-1", "hmac-1"), NOW)
+            .is_err());
+    }
+}
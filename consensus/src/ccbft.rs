@@ -40,6 +40,8 @@ pub struct CcBftConsensus {
     message_queues: MessageQueues,
     /// Performance metrics
     metrics: Arc<RwLock<ConsensusMetrics>>,
+    /// Where outgoing messages go once a network is attached
+    network_sender: RwLock<Option<tokio::sync::mpsc::UnboundedSender<CcBftNetworkMessage>>>,
 }
 
 /// Validator identity and cryptographic keys
@@ -187,7 +189,7 @@ pub struct ViewChangeManager {
 }
 
 /// New view proposal for leader transition
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NewViewProposal {
     pub new_view: u64,
     pub proposer: CCPublicKey,
@@ -219,7 +221,7 @@ pub struct VoteSet {
 }
 
 /// Individual vote with enhanced metadata
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Vote {
     pub voter: CCPublicKey,
     pub block_hash: Hash,
@@ -227,6 +229,8 @@ pub struct Vote {
     pub round: u64,
     pub vote_type: VoteType,
     pub signature: CCSignature,
+    /// Local receipt time; not sent over the wire
+    #[serde(skip, default = "Instant::now")]
     pub timestamp: Instant,
     pub justification: Option<VoteJustification>,
 }
@@ -247,7 +251,7 @@ pub enum VoteType {
 }
 
 /// Vote justification for enhanced security
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VoteJustification {
     pub reason: JustificationReason,
     pub supporting_evidence: Vec<Hash>,
@@ -255,7 +259,7 @@ pub struct VoteJustification {
 }
 
 /// Reasons for vote justification
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum JustificationReason {
     ValidBlock,
     InvalidBlock,
@@ -273,19 +277,21 @@ pub struct AggregateSignature {
 }
 
 /// Block proposal with enhanced metadata
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlockProposal {
     pub block: Block,
     pub proposer: CCPublicKey,
     pub view: u64,
     pub round: u64,
+    /// Local receipt time; not sent over the wire
+    #[serde(skip, default = "Instant::now")]
     pub proposal_time: Instant,
     pub signature: CCSignature,
     pub justification: ProposalJustification,
 }
 
 /// Proposal justification
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProposalJustification {
     pub previous_block_hash: Hash,
     pub transaction_root: Hash,
@@ -294,7 +300,7 @@ pub struct ProposalJustification {
 }
 
 /// Validator set change information
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ValidatorChange {
     pub change_type: ChangeType,
     pub validator: CCPublicKey,
@@ -302,7 +308,7 @@ pub struct ValidatorChange {
 }
 
 /// Types of validator changes
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ChangeType {
     Add,
     Remove,
@@ -342,7 +348,7 @@ pub struct MessageQueues {
 }
 
 /// View change message
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ViewChangeMessage {
     pub from_view: u64,
     pub to_view: u64,
@@ -504,6 +510,7 @@ impl CcBftConsensus {
                 pipeline_efficiency: 1.0,
                 fault_recoveries: 0,
             })),
+            network_sender: RwLock::new(None),
         }
     }
 
@@ -1383,11 +1390,20 @@ pub enum PerformanceUpdate {
 
 /// Network interface for ccBFT consensus
 impl CcBftConsensus {
-    /// Send message to network (placeholder - would integrate with actual networking layer)
+    /// Attach the network layer; outgoing messages are handed to `sender`
+    pub fn set_network_sender(&self, sender: tokio::sync::mpsc::UnboundedSender<CcBftNetworkMessage>) {
+        *self.network_sender.write() = Some(sender);
+    }
+
+    /// Send message to network, or drop it if no network is attached
     pub fn send_to_network(&self, message: CcBftNetworkMessage) -> Result<()> {
-        // In a real implementation, this would send the message to the network layer
         tracing::debug!("Sending ccBFT message: {:?}", message);
-        Ok(())
+        match self.network_sender.read().as_ref() {
+            Some(sender) => sender
+                .send(message)
+                .map_err(|_| CCError::Network("Network layer has shut down".to_string())),
+            None => Ok(()),
+        }
     }
 
     /// Receive message from network and queue for processing
@@ -1441,7 +1457,7 @@ impl CcBftConsensus {
 }
 
 /// Network message types for ccBFT
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum CcBftNetworkMessage {
     Proposal(BlockProposal),
     Vote(Vote),
@@ -14,4 +14,4 @@ pub use consensus_types::*;
 
 // Re-export key types
 pub use ccbft::{CcBftConsensus, CcBftConfig};
-pub use safety::{SafetySystem, SafetyConfig};
\ No newline at end of file
+pub use safety::{QuarantineList, SafetySystem, SafetyConfig};
\ No newline at end of file
@@ -6,9 +6,11 @@
 //! - Validator behavior monitoring
 //! - Automatic recovery procedures
 //! - Performance degradation detection
+//! - Quarantine of misbehaving validators and peers
 
 use cc_core::{Result, CCPublicKey, Hash};
 use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use parking_lot::RwLock;
 
@@ -22,6 +24,8 @@ pub struct SafetySystem {
     fault_detector: RwLock<FaultDetector>,
     /// Recovery mechanisms
     recovery_engine: RwLock<RecoveryEngine>,
+    /// Validators and peers excluded from participation
+    quarantine: Arc<QuarantineList>,
     /// Safety configuration
     config: SafetyConfig,
 }
@@ -104,6 +108,8 @@ pub struct SafetyConfig {
     pub max_byzantine_fraction: f64,
     /// Network timeout thresholds
     pub network_timeouts: NetworkTimeouts,
+    /// How long a validator raising a critical alert stays quarantined
+    pub quarantine_duration: Duration,
 }
 
 /// Behavior alert for suspicious validator activity
@@ -264,6 +270,7 @@ impl Default for SafetyConfig {
                 heartbeat_interval: Duration::from_secs(30),
                 partition_detection_timeout: Duration::from_secs(60),
             },
+            quarantine_duration: Duration::from_secs(3600),
         }
     }
 }
@@ -276,6 +283,7 @@ impl SafetySystem {
             network_monitor: RwLock::new(NetworkMonitor::new()),
             fault_detector: RwLock::new(FaultDetector::new()),
             recovery_engine: RwLock::new(RecoveryEngine::new()),
+            quarantine: Arc::new(QuarantineList::new()),
             config,
         }
     }
@@ -334,6 +342,11 @@ impl SafetySystem {
         match alert.severity {
             AlertSeverity::Critical => {
                 // Immediate action required
+                self.quarantine.quarantine(
+                    hex::encode(alert.validator.0),
+                    format!("{:?}: {}", alert.alert_type, alert.details),
+                    self.config.quarantine_duration,
+                );
                 self.trigger_recovery(FaultType::Byzantine)?;
             }
             AlertSeverity::High => {
@@ -373,6 +386,11 @@ impl SafetySystem {
         Ok(())
     }
 
+    /// Quarantine list, shared with the networking layer
+    pub fn quarantine(&self) -> &Arc<QuarantineList> {
+        &self.quarantine
+    }
+
     /// Get safety system status
     pub fn get_safety_status(&self) -> SafetyStatus {
         let validator_monitor = self.validator_monitor.read();
@@ -385,6 +403,7 @@ impl SafetySystem {
             active_alerts: validator_monitor.behavior_alerts.len(),
             active_faults: fault_detector.active_faults.len(),
             active_recoveries: recovery_engine.active_recoveries.len(),
+            quarantined: self.quarantine.len(),
             byzantine_tolerance: self.calculate_byzantine_tolerance(&validator_monitor),
         }
     }
@@ -413,9 +432,90 @@ pub struct SafetyStatus {
     pub active_alerts: usize,
     pub active_faults: usize,
     pub active_recoveries: usize,
+    pub quarantined: usize,
     pub byzantine_tolerance: f64,
 }
 
+/// Quarantined validators and peers, keyed by identifier (hex-encoded
+/// validator key, peer node id or peer address)
+#[derive(Debug, Default)]
+pub struct QuarantineList {
+    entries: RwLock<HashMap<String, QuarantineEntry>>,
+}
+
+/// Why and until when an identifier is quarantined
+#[derive(Debug, Clone)]
+pub struct QuarantineEntry {
+    pub reason: String,
+    pub since: Instant,
+    pub until: Instant,
+}
+
+impl QuarantineList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Quarantine `id` for `duration`, extending any existing quarantine
+    pub fn quarantine(&self, id: impl Into<String>, reason: impl Into<String>, duration: Duration) {
+        let now = Instant::now();
+        let until = now + duration;
+        let mut entries = self.entries.write();
+        let entry = entries.entry(id.into()).or_insert_with(|| QuarantineEntry {
+            reason: String::new(),
+            since: now,
+            until,
+        });
+        entry.reason = reason.into();
+        entry.until = entry.until.max(until);
+    }
+
+    /// Lift the quarantine on `id`, returning whether it was quarantined
+    pub fn release(&self, id: &str) -> bool {
+        self.entries.write().remove(id).is_some()
+    }
+
+    pub fn is_quarantined(&self, id: &str) -> bool {
+        self.entries
+            .read()
+            .get(id)
+            .is_some_and(|entry| entry.until > Instant::now())
+    }
+
+    pub fn get(&self, id: &str) -> Option<QuarantineEntry> {
+        self.entries
+            .read()
+            .get(id)
+            .filter(|entry| entry.until > Instant::now())
+            .cloned()
+    }
+
+    /// Identifiers currently quarantined
+    pub fn entries(&self) -> Vec<(String, QuarantineEntry)> {
+        self.prune();
+        self.entries
+            .read()
+            .iter()
+            .map(|(id, entry)| (id.clone(), entry.clone()))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.prune();
+        self.entries.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget expired quarantines
+    pub fn prune(&self) {
+        let now = Instant::now();
+        self.entries.write().retain(|_, entry| entry.until > now);
+    }
+}
+
 /// Validator action types for monitoring
 #[derive(Debug, Clone)]
 pub enum ValidatorAction {
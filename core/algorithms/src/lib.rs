@@ -305,6 +305,40 @@ impl<K: Clone + std::hash::Hash + Eq, V: Clone> LRUCache<K, V> {
         }
     }
 
+    /// Remove a key from the cache, returning its value if present
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let index = self.map.get(key).copied()?;
+        let (_, value) = self.entries.remove(index)?;
+        self.update_indices();
+        Some(value)
+    }
+
+    /// Check whether a key is cached without updating its recency
+    pub fn contains(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Number of cached entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Maximum number of entries the cache holds
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Remove all entries from the cache
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.entries.clear();
+    }
+
     fn update_indices(&mut self) {
         self.map.clear();
         for (index, (key, _)) in self.entries.iter().enumerate() {
@@ -635,6 +669,23 @@ mod tests {
         assert_eq!(cache.get(&"c"), Some(3));
     }
 
+    #[test]
+    fn test_lru_cache_remove() {
+        let mut cache = LRUCache::new(3);
+
+        cache.put("a", 1);
+        cache.put("b", 2);
+        assert_eq!(cache.len(), 2);
+
+        assert_eq!(cache.remove(&"a"), Some(1));
+        assert_eq!(cache.remove(&"a"), None);
+        assert!(!cache.contains(&"a"));
+        assert_eq!(cache.get(&"b"), Some(2));
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
     #[test]
     fn test_consistent_hash() {
         let mut hash_ring = ConsistentHash::new(3);
@@ -0,0 +1,81 @@
+use cc_core_algorithms::LRUCache;
+use cc_core_data_structures::ConcurrentLruCache;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::thread;
+
+const THREADS: u32 = 8;
+const OPS_PER_THREAD: u32 = 2_000;
+const KEY_SPACE: u32 = 4_096;
+
+/// Common interface so both caches run the same workload
+trait SharedCache: Send + Sync {
+    fn get(&self, key: u32) -> Option<u64>;
+    fn put(&self, key: u32, value: u64);
+}
+
+impl SharedCache for Mutex<LRUCache<u32, u64>> {
+    fn get(&self, key: u32) -> Option<u64> {
+        self.lock().get(&key)
+    }
+
+    fn put(&self, key: u32, value: u64) {
+        self.lock().put(key, value)
+    }
+}
+
+impl SharedCache for ConcurrentLruCache<u32, u64> {
+    fn get(&self, key: u32) -> Option<u64> {
+        ConcurrentLruCache::get(self, &key)
+    }
+
+    fn put(&self, key: u32, value: u64) {
+        ConcurrentLruCache::put(self, key, value)
+    }
+}
+
+/// Run `THREADS` threads doing reads with a miss-driven insert
+fn run_workload(cache: Arc<dyn SharedCache>) {
+    let handles: Vec<_> = (0..THREADS)
+        .map(|thread_id| {
+            let cache = cache.clone();
+            thread::spawn(move || {
+                for i in 0..OPS_PER_THREAD {
+                    let key = (thread_id * 7919 + i * 31) % KEY_SPACE;
+                    if black_box(cache.get(key)).is_none() {
+                        cache.put(key, key as u64);
+                    }
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
+
+fn benchmark_concurrent_lru(c: &mut Criterion) {
+    let mut group = c.benchmark_group("concurrent_lru");
+
+    for capacity in [256usize, 1024] {
+        let locked: Arc<dyn SharedCache> = Arc::new(Mutex::new(LRUCache::new(capacity)));
+        group.bench_with_input(
+            BenchmarkId::new("mutex_lru", capacity),
+            &capacity,
+            |b, _| b.iter(|| run_workload(locked.clone())),
+        );
+
+        let sharded: Arc<dyn SharedCache> = Arc::new(ConcurrentLruCache::new(capacity));
+        group.bench_with_input(
+            BenchmarkId::new("sharded_lru", capacity),
+            &capacity,
+            |b, _| b.iter(|| run_workload(sharded.clone())),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_concurrent_lru);
+criterion_main!(benches);
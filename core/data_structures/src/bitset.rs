@@ -0,0 +1,282 @@
+use crate::{DataStructureError, Result};
+use serde::{Deserialize, Serialize};
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// Fixed-width set of small indices, one bit each
+///
+/// Sized for a validator set, it records which validators took part in a
+/// round (e.g. voted) with O(1) membership checks and word-at-a-time set
+/// operations. Serializes as its width plus 64-bit words; deserialization
+/// rejects a word count that does not match the width or bits set past it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "BitSetData")]
+pub struct BitSet {
+    capacity: usize,
+    words: Vec<u64>,
+}
+
+/// Unvalidated wire form of a `BitSet`
+#[derive(Deserialize)]
+struct BitSetData {
+    capacity: usize,
+    words: Vec<u64>,
+}
+
+impl TryFrom<BitSetData> for BitSet {
+    type Error = DataStructureError;
+
+    fn try_from(data: BitSetData) -> Result<Self> {
+        let expected = data.capacity.div_ceil(WORD_BITS);
+        if data.words.len() != expected {
+            return Err(DataStructureError::Invalid(format!(
+                "Bitset of {} bits needs {} words, got {}",
+                data.capacity,
+                expected,
+                data.words.len()
+            )));
+        }
+        let set = Self {
+            capacity: data.capacity,
+            words: data.words,
+        };
+        if set.words.last().copied().unwrap_or(0) & !set.last_word_mask() != 0 {
+            return Err(DataStructureError::Invalid(
+                "Bitset has bits set beyond its capacity".to_string(),
+            ));
+        }
+        Ok(set)
+    }
+}
+
+impl BitSet {
+    /// Create an empty set for indices `0..capacity`
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            words: vec![0; capacity.div_ceil(WORD_BITS)],
+        }
+    }
+
+    /// Create a set for indices `0..capacity` with the given indices set
+    pub fn from_indices(capacity: usize, indices: impl IntoIterator<Item = usize>) -> Result<Self> {
+        let mut set = Self::new(capacity);
+        for index in indices {
+            set.insert(index)?;
+        }
+        Ok(set)
+    }
+
+    /// Number of indices the set can hold
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Set an index, returning whether it was newly set
+    pub fn insert(&mut self, index: usize) -> Result<bool> {
+        self.check_index(index)?;
+        let (word, mask) = Self::locate(index);
+        let newly = self.words[word] & mask == 0;
+        self.words[word] |= mask;
+        Ok(newly)
+    }
+
+    /// Clear an index, returning whether it was set
+    pub fn remove(&mut self, index: usize) -> Result<bool> {
+        self.check_index(index)?;
+        let (word, mask) = Self::locate(index);
+        let was_set = self.words[word] & mask != 0;
+        self.words[word] &= !mask;
+        Ok(was_set)
+    }
+
+    /// Whether an index is set; indices past the capacity never are
+    pub fn contains(&self, index: usize) -> bool {
+        let (word, mask) = Self::locate(index);
+        index < self.capacity && self.words[word] & mask != 0
+    }
+
+    /// Number of indices set
+    pub fn count(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Whether no index is set
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&word| word == 0)
+    }
+
+    /// Whether every index is set
+    pub fn is_full(&self) -> bool {
+        self.count() == self.capacity
+    }
+
+    /// Clear every index
+    pub fn clear(&mut self) {
+        self.words.fill(0);
+    }
+
+    /// Set indices in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            let mut remaining = word;
+            std::iter::from_fn(move || {
+                if remaining == 0 {
+                    return None;
+                }
+                let bit = remaining.trailing_zeros() as usize;
+                remaining &= remaining - 1;
+                Some(i * WORD_BITS + bit)
+            })
+        })
+    }
+
+    /// Indices set in either set; as wide as the wider of the two
+    pub fn union(&self, other: &BitSet) -> BitSet {
+        let (wide, narrow) = if self.capacity >= other.capacity {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let mut result = wide.clone();
+        for (word, &theirs) in result.words.iter_mut().zip(&narrow.words) {
+            *word |= theirs;
+        }
+        result
+    }
+
+    /// Indices set in both sets; as wide as the narrower of the two
+    pub fn intersection(&self, other: &BitSet) -> BitSet {
+        let capacity = self.capacity.min(other.capacity);
+        let mut result = BitSet::new(capacity);
+        for (word, (&ours, &theirs)) in result
+            .words
+            .iter_mut()
+            .zip(self.words.iter().zip(&other.words))
+        {
+            *word = ours & theirs;
+        }
+        result.mask_tail();
+        result
+    }
+
+    /// Indices set in this set but not in `other`
+    pub fn difference(&self, other: &BitSet) -> BitSet {
+        let mut result = self.clone();
+        for (word, &theirs) in result.words.iter_mut().zip(&other.words) {
+            *word &= !theirs;
+        }
+        result
+    }
+
+    /// Number of indices set in both sets, without allocating
+    pub fn intersection_count(&self, other: &BitSet) -> usize {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .map(|(&ours, &theirs)| (ours & theirs).count_ones() as usize)
+            .sum()
+    }
+
+    fn locate(index: usize) -> (usize, u64) {
+        (index / WORD_BITS, 1 << (index % WORD_BITS))
+    }
+
+    fn check_index(&self, index: usize) -> Result<()> {
+        if index >= self.capacity {
+            return Err(DataStructureError::IndexOutOfBounds {
+                index,
+                len: self.capacity,
+            });
+        }
+        Ok(())
+    }
+
+    /// Bits of the last word that lie within the capacity
+    fn last_word_mask(&self) -> u64 {
+        match self.capacity % WORD_BITS {
+            0 => u64::MAX,
+            bits => (1 << bits) - 1,
+        }
+    }
+
+    /// Clear any bits past the capacity
+    fn mask_tail(&mut self) {
+        let mask = self.last_word_mask();
+        if let Some(last) = self.words.last_mut() {
+            *last &= mask;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_membership_and_bounds() {
+        let mut votes = BitSet::new(100);
+        assert!(votes.insert(0).unwrap());
+        assert!(votes.insert(64).unwrap());
+        assert!(votes.insert(99).unwrap());
+        assert!(!votes.insert(64).unwrap());
+        assert_eq!(
+            votes.insert(100),
+            Err(DataStructureError::IndexOutOfBounds {
+                index: 100,
+                len: 100
+            })
+        );
+        assert!(votes.contains(99));
+        assert!(!votes.contains(98));
+        assert!(!votes.contains(1_000));
+        assert_eq!(votes.count(), 3);
+        assert_eq!(votes.iter().collect::<Vec<_>>(), vec![0, 64, 99]);
+
+        assert!(votes.remove(64).unwrap());
+        assert!(!votes.remove(64).unwrap());
+        votes.clear();
+        assert!(votes.is_empty());
+
+        let all = BitSet::from_indices(3, 0..3).unwrap();
+        assert!(all.is_full());
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let prevotes = BitSet::from_indices(130, [1, 5, 64, 128]).unwrap();
+        let precommits = BitSet::from_indices(130, [5, 64, 129]).unwrap();
+
+        let both = prevotes.intersection(&precommits);
+        assert_eq!(both.iter().collect::<Vec<_>>(), vec![5, 64]);
+        assert_eq!(prevotes.intersection_count(&precommits), 2);
+        assert_eq!(prevotes.union(&precommits).count(), 5);
+        assert_eq!(
+            prevotes.difference(&precommits).iter().collect::<Vec<_>>(),
+            vec![1, 128]
+        );
+
+        // Mixed widths, e.g. across a validator set change
+        let smaller = BitSet::from_indices(10, [1, 9]).unwrap();
+        assert_eq!(smaller.union(&prevotes).capacity(), 130);
+        let narrow = prevotes.intersection(&smaller);
+        assert_eq!(narrow.capacity(), 10);
+        assert_eq!(narrow.iter().collect::<Vec<_>>(), vec![1]);
+    }
+
+    #[test]
+    fn test_serde_validates_width() {
+        let set = BitSet::from_indices(70, [3, 69]).unwrap();
+        let json = serde_json::to_string(&set).unwrap();
+        assert_eq!(serde_json::from_str::<BitSet>(&json).unwrap(), set);
+
+        let invalid = r#"{"capacity":70,"words":[0]}"#;
+        assert!(serde_json::from_str::<BitSet>(invalid).is_err());
+        // Bit 70 lies past the capacity
+        let invalid = r#"{"capacity":70,"words":[0,64]}"#;
+        assert!(serde_json::from_str::<BitSet>(invalid).is_err());
+    }
+}
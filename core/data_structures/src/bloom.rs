@@ -0,0 +1,184 @@
+use crate::DataStructureError;
+use serde::{Deserialize, Serialize};
+
+/// Upper bound on hash functions accepted from a peer
+const MAX_HASH_FUNCTIONS: u32 = 32;
+
+/// Bloom filter with per-slot counters, so items can be removed
+///
+/// Used for mempool deduplication, where transactions leave the pool again.
+/// Slot positions come from Blake3, so two nodes with the same parameters
+/// agree on them and filters can be exchanged between peers for mempool
+/// reconciliation; deserialization validates the parameters a peer sent.
+///
+/// Counters saturate at 255 and are never decremented from there, trading a
+/// slightly higher false positive rate for never producing false negatives.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "FilterData")]
+pub struct CountingBloomFilter {
+    counters: Vec<u8>,
+    hash_functions: u32,
+    item_count: usize,
+}
+
+/// Unvalidated wire form of a `CountingBloomFilter`
+#[derive(Deserialize)]
+struct FilterData {
+    counters: Vec<u8>,
+    hash_functions: u32,
+    item_count: usize,
+}
+
+impl TryFrom<FilterData> for CountingBloomFilter {
+    type Error = DataStructureError;
+
+    fn try_from(data: FilterData) -> Result<Self, Self::Error> {
+        if data.counters.is_empty() {
+            return Err(DataStructureError::Invalid(
+                "Bloom filter has no counters".to_string(),
+            ));
+        }
+        if data.hash_functions == 0 || data.hash_functions > MAX_HASH_FUNCTIONS {
+            return Err(DataStructureError::Invalid(format!(
+                "Bloom filter uses {} hash functions, expected 1 to {}",
+                data.hash_functions, MAX_HASH_FUNCTIONS
+            )));
+        }
+        Ok(Self {
+            counters: data.counters,
+            hash_functions: data.hash_functions,
+            item_count: data.item_count,
+        })
+    }
+}
+
+impl CountingBloomFilter {
+    /// Create a filter sized for `capacity` items at the given false positive rate
+    pub fn new(capacity: usize, false_positive_rate: f64) -> Self {
+        let capacity = capacity.max(1) as f64;
+        let rate = false_positive_rate.clamp(f64::MIN_POSITIVE, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let size = (-(capacity * rate.ln()) / (ln2 * ln2)).ceil().max(1.0);
+        let hash_functions = ((size / capacity) * ln2).round() as u32;
+
+        Self {
+            counters: vec![0; size as usize],
+            hash_functions: hash_functions.clamp(1, MAX_HASH_FUNCTIONS),
+            item_count: 0,
+        }
+    }
+
+    /// Add an item
+    pub fn insert(&mut self, item: &[u8]) {
+        for index in self.indices(item) {
+            let counter = &mut self.counters[index];
+            *counter = counter.saturating_add(1);
+        }
+        self.item_count += 1;
+    }
+
+    /// Remove an item, returning `false` (and changing nothing) if it is
+    /// definitely not in the filter
+    ///
+    /// Only remove items that were inserted: removing a false positive
+    /// decrements other items' counters and can cause false negatives.
+    pub fn remove(&mut self, item: &[u8]) -> bool {
+        if !self.contains(item) {
+            return false;
+        }
+        for index in self.indices(item) {
+            let counter = &mut self.counters[index];
+            if *counter != u8::MAX {
+                *counter -= 1;
+            }
+        }
+        self.item_count = self.item_count.saturating_sub(1);
+        true
+    }
+
+    /// Check whether an item might be in the filter
+    pub fn contains(&self, item: &[u8]) -> bool {
+        self.indices(item).all(|index| self.counters[index] > 0)
+    }
+
+    /// Number of items currently counted in the filter
+    pub fn len(&self) -> usize {
+        self.item_count
+    }
+
+    /// Whether the filter is empty
+    pub fn is_empty(&self) -> bool {
+        self.item_count == 0
+    }
+
+    /// Remove all items
+    pub fn clear(&mut self) {
+        self.counters.fill(0);
+        self.item_count = 0;
+    }
+
+    /// Current false positive probability
+    pub fn false_positive_probability(&self) -> f64 {
+        let occupied = self.counters.iter().filter(|&&counter| counter > 0).count();
+        (occupied as f64 / self.counters.len() as f64).powi(self.hash_functions as i32)
+    }
+
+    /// Counter slots for an item, by double hashing a Blake3 digest
+    fn indices(&self, item: &[u8]) -> impl Iterator<Item = usize> {
+        let digest = blake3::hash(item);
+        let bytes = digest.as_bytes();
+        let h1 = u64::from_le_bytes(bytes[..8].try_into().expect("8-byte slice"));
+        let h2 = u64::from_le_bytes(bytes[8..16].try_into().expect("8-byte slice")) | 1;
+        let size = self.counters.len() as u64;
+        (0..self.hash_functions as u64)
+            .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % size) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tx(i: u32) -> Vec<u8> {
+        format!("tx-{}", i).into_bytes()
+    }
+
+    #[test]
+    fn test_insert_remove() {
+        let mut filter = CountingBloomFilter::new(1000, 0.01);
+        for i in 0..500 {
+            filter.insert(&tx(i));
+        }
+        assert!((0..500).all(|i| filter.contains(&tx(i))));
+        assert_eq!(filter.len(), 500);
+
+        for i in 0..250 {
+            assert!(filter.remove(&tx(i)));
+        }
+        assert!((250..500).all(|i| filter.contains(&tx(i))));
+        let false_positives = (0..250).filter(|&i| filter.contains(&tx(i))).count();
+        assert!(false_positives < 10);
+        assert_eq!(filter.len(), 250);
+
+        filter.clear();
+        assert!(!filter.remove(&tx(300)));
+        assert!(filter.is_empty());
+    }
+
+    #[test]
+    fn test_exchange_between_peers() {
+        let mut filter = CountingBloomFilter::new(100, 0.01);
+        filter.insert(b"tx-a");
+        filter.insert(b"tx-b");
+
+        let wire = serde_json::to_string(&filter).unwrap();
+        let received: CountingBloomFilter = serde_json::from_str(&wire).unwrap();
+        assert_eq!(received, filter);
+        assert!(received.contains(b"tx-a"));
+
+        let invalid = r#"{"counters":[],"hash_functions":3,"item_count":0}"#;
+        assert!(serde_json::from_str::<CountingBloomFilter>(invalid).is_err());
+        let invalid = r#"{"counters":[0,0],"hash_functions":0,"item_count":0}"#;
+        assert!(serde_json::from_str::<CountingBloomFilter>(invalid).is_err());
+    }
+}
@@ -0,0 +1,187 @@
+use parking_lot::Mutex;
+use std::borrow::Borrow;
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+/// Thread-safe set answering "was this key seen in the last `window`?"
+///
+/// Used for replay protection and for dropping duplicate consensus and
+/// gossip messages. Keys expire `window` after they were first seen; a
+/// repeat sighting does not extend that. Expired keys are dropped lazily on
+/// every call, and at most `capacity` keys are kept, evicting the oldest
+/// early when full, so memory stays bounded under floods of unique keys.
+pub struct DedupWindow<K> {
+    window: Duration,
+    capacity: usize,
+    inner: Mutex<Inner<K>>,
+}
+
+struct Inner<K> {
+    first_seen: HashMap<K, Instant>,
+    /// Keys in the order they were first seen
+    order: VecDeque<(Instant, K)>,
+}
+
+impl<K: Hash + Eq + Clone> Inner<K> {
+    /// Drop keys first seen before `cutoff`
+    fn expire(&mut self, cutoff: Option<Instant>) {
+        let Some(cutoff) = cutoff else {
+            return;
+        };
+        while self.order.front().is_some_and(|(seen, _)| *seen <= cutoff) {
+            self.pop_oldest();
+        }
+    }
+
+    fn pop_oldest(&mut self) {
+        if let Some((_, key)) = self.order.pop_front() {
+            self.first_seen.remove(&key);
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone> DedupWindow<K> {
+    /// Create a window remembering keys for `window`, holding at most `capacity` keys
+    pub fn new(window: Duration, capacity: usize) -> Self {
+        Self {
+            window,
+            capacity: capacity.max(1),
+            inner: Mutex::new(Inner {
+                first_seen: HashMap::new(),
+                order: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Record a key, returning `true` if it was not seen within the window
+    pub fn insert(&self, key: K) -> bool {
+        self.insert_at(key, Instant::now())
+    }
+
+    /// Record a key as seen at `now`, returning `true` if it is new
+    pub fn insert_at(&self, key: K, now: Instant) -> bool {
+        let mut inner = self.inner.lock();
+        inner.expire(now.checked_sub(self.window));
+        if inner.first_seen.contains_key(&key) {
+            return false;
+        }
+        if inner.order.len() >= self.capacity {
+            inner.pop_oldest();
+        }
+        inner.first_seen.insert(key.clone(), now);
+        inner.order.push_back((now, key));
+        true
+    }
+
+    /// Check whether a key was seen within the window, without recording it
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.contains_at(key, Instant::now())
+    }
+
+    /// Check whether a key was seen within the window ending at `now`
+    pub fn contains_at<Q>(&self, key: &Q, now: Instant) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut inner = self.inner.lock();
+        inner.expire(now.checked_sub(self.window));
+        inner.first_seen.contains_key(key)
+    }
+
+    /// Drop expired keys now rather than on the next call
+    pub fn purge_expired(&self) {
+        let cutoff = Instant::now().checked_sub(self.window);
+        self.inner.lock().expire(cutoff);
+    }
+
+    /// Number of keys remembered, including any not yet purged
+    pub fn len(&self) -> usize {
+        self.inner.lock().order.len()
+    }
+
+    /// Whether no keys are remembered
+    pub fn is_empty(&self) -> bool {
+        self.inner.lock().order.is_empty()
+    }
+
+    /// How long keys are remembered
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Maximum number of keys remembered
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Forget all keys
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock();
+        inner.first_seen.clear();
+        inner.order.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_keys_expire_after_window() {
+        let dedup = DedupWindow::new(Duration::from_secs(10), 100);
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert!(dedup.insert_at("tx-1", at(0)));
+        assert!(!dedup.insert_at("tx-1", at(5)));
+        assert!(dedup.insert_at("tx-2", at(6)));
+        assert!(dedup.contains_at("tx-1", at(9)));
+
+        // A repeat at 5s did not extend tx-1's window
+        assert!(!dedup.contains_at("tx-1", at(10)));
+        assert!(dedup.contains_at("tx-2", at(10)));
+        assert_eq!(dedup.len(), 1);
+        assert!(dedup.insert_at("tx-1", at(11)));
+
+        dedup.clear();
+        assert!(dedup.is_empty());
+    }
+
+    #[test]
+    fn test_capacity_evicts_oldest() {
+        let dedup = DedupWindow::new(Duration::from_secs(60), 3);
+        let now = Instant::now();
+        for i in 0..5u32 {
+            assert!(dedup.insert_at(i, now));
+        }
+        assert_eq!(dedup.len(), 3);
+        assert!(!dedup.contains_at(&0, now));
+        assert!(!dedup.contains_at(&1, now));
+        assert!(dedup.contains_at(&4, now));
+        assert!(dedup.insert(0));
+        assert!(!dedup.contains(&2));
+    }
+
+    #[test]
+    fn test_one_thread_wins_each_key() {
+        let dedup = Arc::new(DedupWindow::new(Duration::from_secs(60), 10_000));
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let dedup = dedup.clone();
+                thread::spawn(move || (0..1000u32).filter(|&i| dedup.insert(i)).count())
+            })
+            .collect();
+        let wins: usize = handles.into_iter().map(|h| h.join().unwrap()).sum();
+        assert_eq!(wins, 1000);
+        dedup.purge_expired();
+        assert_eq!(dedup.len(), 1000);
+    }
+}
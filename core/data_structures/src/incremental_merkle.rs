@@ -0,0 +1,228 @@
+use crate::{internal_hash, leaf_hash, DataStructureError, Hash, Result, EMPTY_HASH};
+use serde::{Deserialize, Serialize};
+
+/// Merkle tree over an append-only list of leaves
+///
+/// Every level of the tree is kept, so appending or updating a leaf only
+/// rehashes the O(log n) nodes on its path to the root. A node without a
+/// right sibling is paired with the all-zero hash.
+#[derive(Debug, Clone, Default)]
+pub struct IncrementalMerkleTree {
+    /// `levels[0]` holds the leaf nodes, the last level holds the root
+    levels: Vec<Vec<Hash>>,
+}
+
+/// Inclusion proof for a leaf of an `IncrementalMerkleTree`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    /// Index of the proven leaf
+    pub index: usize,
+    /// Sibling hashes from the leaf level up to just below the root
+    pub siblings: Vec<Hash>,
+}
+
+impl MerkleProof {
+    /// Check that `leaf` is at `self.index` in the tree with the given root
+    pub fn verify(&self, root: &Hash, leaf: &Hash) -> bool {
+        if self.siblings.len() < usize::BITS as usize && self.index >> self.siblings.len() != 0 {
+            return false;
+        }
+        let mut current = leaf_hash(&[leaf]);
+        let mut index = self.index;
+        for sibling in &self.siblings {
+            current = if index.is_multiple_of(2) {
+                internal_hash(&current, sibling)
+            } else {
+                internal_hash(sibling, &current)
+            };
+            index /= 2;
+        }
+        current == *root
+    }
+}
+
+impl IncrementalMerkleTree {
+    /// Create an empty tree
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a tree from a list of leaves
+    pub fn from_leaves(leaves: &[Hash]) -> Self {
+        let mut tree = Self::new();
+        for leaf in leaves {
+            tree.append(*leaf);
+        }
+        tree
+    }
+
+    /// Number of leaves
+    pub fn len(&self) -> usize {
+        self.levels.first().map_or(0, Vec::len)
+    }
+
+    /// Whether the tree has no leaves
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Root hash; all zeros for an empty tree
+    pub fn root(&self) -> Hash {
+        self.levels
+            .last()
+            .and_then(|level| level.first())
+            .copied()
+            .unwrap_or(EMPTY_HASH)
+    }
+
+    /// Append a leaf, returning its index
+    pub fn append(&mut self, leaf: Hash) -> usize {
+        if self.levels.is_empty() {
+            self.levels.push(Vec::new());
+        }
+        let index = self.levels[0].len();
+        self.levels[0].push(leaf_hash(&[&leaf]));
+        self.rehash_path(index);
+        index
+    }
+
+    /// Replace the leaf at `index`
+    pub fn update(&mut self, index: usize, leaf: Hash) -> Result<()> {
+        self.check_index(index)?;
+        self.levels[0][index] = leaf_hash(&[&leaf]);
+        self.rehash_path(index);
+        Ok(())
+    }
+
+    /// Inclusion proof for the leaf at `index`
+    pub fn proof(&self, index: usize) -> Result<MerkleProof> {
+        self.check_index(index)?;
+        let siblings = self.levels[..self.levels.len() - 1]
+            .iter()
+            .enumerate()
+            .map(|(height, level)| {
+                level
+                    .get((index >> height) ^ 1)
+                    .copied()
+                    .unwrap_or(EMPTY_HASH)
+            })
+            .collect();
+        Ok(MerkleProof { index, siblings })
+    }
+
+    fn check_index(&self, index: usize) -> Result<()> {
+        let len = self.len();
+        if index >= len {
+            return Err(DataStructureError::IndexOutOfBounds { index, len });
+        }
+        Ok(())
+    }
+
+    /// Recompute the nodes above leaf `index`, growing the tree if needed
+    fn rehash_path(&mut self, mut index: usize) {
+        let mut height = 0;
+        while self.levels[height].len() > 1 {
+            let parent = index / 2;
+            let level = &self.levels[height];
+            let left = level[2 * parent];
+            let right = level.get(2 * parent + 1).copied().unwrap_or(EMPTY_HASH);
+            let hash = internal_hash(&left, &right);
+
+            if height + 1 == self.levels.len() {
+                self.levels.push(Vec::new());
+            }
+            let next = &mut self.levels[height + 1];
+            if parent == next.len() {
+                next.push(hash);
+            } else {
+                next[parent] = hash;
+            }
+            index = parent;
+            height += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(i: usize) -> Hash {
+        blake3::hash(&i.to_le_bytes()).into()
+    }
+
+    /// Root computed level by level from scratch, for comparison
+    fn rebuilt_root(leaves: &[Hash]) -> Hash {
+        if leaves.is_empty() {
+            return EMPTY_HASH;
+        }
+        let mut level: Vec<Hash> = leaves.iter().map(|leaf| leaf_hash(&[leaf])).collect();
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| internal_hash(&pair[0], pair.get(1).unwrap_or(&EMPTY_HASH)))
+                .collect();
+        }
+        level[0]
+    }
+
+    #[test]
+    fn test_append_and_update_match_rebuild() {
+        let mut tree = IncrementalMerkleTree::new();
+        let mut leaves = Vec::new();
+        assert_eq!(tree.root(), EMPTY_HASH);
+
+        for i in 0..37 {
+            assert_eq!(tree.append(leaf(i)), i);
+            leaves.push(leaf(i));
+            assert_eq!(tree.root(), rebuilt_root(&leaves));
+        }
+
+        for i in [0, 17, 36] {
+            tree.update(i, leaf(100 + i)).unwrap();
+            leaves[i] = leaf(100 + i);
+            assert_eq!(tree.root(), rebuilt_root(&leaves));
+        }
+        assert_eq!(tree.len(), 37);
+        assert_eq!(
+            tree.update(37, leaf(0)),
+            Err(DataStructureError::IndexOutOfBounds { index: 37, len: 37 })
+        );
+        assert_eq!(
+            IncrementalMerkleTree::from_leaves(&leaves).root(),
+            tree.root()
+        );
+    }
+
+    #[test]
+    fn test_proofs_for_every_leaf() {
+        for size in [1, 2, 3, 8, 13] {
+            let leaves: Vec<Hash> = (0..size).map(leaf).collect();
+            let tree = IncrementalMerkleTree::from_leaves(&leaves);
+            let root = tree.root();
+
+            for (index, value) in leaves.iter().enumerate() {
+                let proof = tree.proof(index).unwrap();
+                assert!(proof.verify(&root, value));
+                assert!(!proof.verify(&root, &leaf(1000)));
+
+                let moved = MerkleProof {
+                    index: index ^ 1,
+                    ..proof.clone()
+                };
+                assert!(!moved.verify(&root, value));
+            }
+            assert!(tree.proof(size).is_err());
+        }
+    }
+
+    #[test]
+    fn test_update_invalidates_old_proofs() {
+        let mut tree = IncrementalMerkleTree::from_leaves(&[leaf(0), leaf(1), leaf(2)]);
+        let proof = tree.proof(1).unwrap();
+
+        tree.update(1, leaf(9)).unwrap();
+        assert!(!proof.verify(&tree.root(), &leaf(1)));
+        assert!(tree.proof(1).unwrap().verify(&tree.root(), &leaf(9)));
+    }
+}
@@ -1,2 +1,89 @@
 //! Core data_structures functionality
+//!
+//! Authenticated and general-purpose data structures shared by CC Chain
+//! components:
+//! - Sparse Merkle tree with inclusion and non-inclusion proofs
+//! - Incremental (append/update) Merkle tree with cached internal nodes
+//! - Counting Bloom filter with removal, exchangeable between peers
+//! - Sharded concurrent LRU cache
+//! - Indexed priority queue with removal and re-prioritisation by key
+//! - Concurrent skip list keyed by `(address, nonce)` for nonce sequences
+//! - Ring buffer and timestamped time series that persist across restarts,
+//!   with windowed aggregation and resampling
+//! - Bitset for tracking validator participation per round
+//! - Online (weighted) statistics accumulator with EWMA
+//! - Expiring deduplication window for replay and duplicate-message checks
+//! - Persistent (copy-on-write) hash map with structural sharing
+//! - Experimental verkle tree with vector commitment proofs (`verkle` feature)
 
+pub mod bitset;
+pub mod bloom;
+pub mod dedup;
+pub mod incremental_merkle;
+pub mod lru;
+pub mod persistent_map;
+pub mod priority_queue;
+pub mod ring_buffer;
+pub mod skip_list;
+pub mod sparse_merkle;
+pub mod stats;
+pub mod time_series;
+#[cfg(feature = "verkle")]
+pub mod verkle;
+
+pub use bitset::BitSet;
+pub use bloom::CountingBloomFilter;
+pub use dedup::DedupWindow;
+pub use incremental_merkle::{IncrementalMerkleTree, MerkleProof};
+pub use lru::ConcurrentLruCache;
+pub use persistent_map::PersistentMap;
+pub use priority_queue::IndexedPriorityQueue;
+pub use ring_buffer::RingBuffer;
+pub use skip_list::NonceSkipMap;
+pub use sparse_merkle::{SparseMerkleProof, SparseMerkleTree};
+pub use stats::OnlineStats;
+pub use time_series::{Aggregation, Sample, TimeSeries};
+#[cfg(feature = "verkle")]
+pub use verkle::{VerkleProof, VerkleTree};
+
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum DataStructureError {
+    #[error("Index {index} out of bounds for length {len}")]
+    IndexOutOfBounds { index: usize, len: usize },
+
+    #[error("Invalid data: {0}")]
+    Invalid(String),
+}
+
+pub type Result<T> = std::result::Result<T, DataStructureError>;
+
+/// 32-byte Blake3 digest used by the authenticated structures
+pub type Hash = [u8; 32];
+
+/// Hash standing in for an empty subtree or a missing node
+pub(crate) const EMPTY_HASH: Hash = [0u8; 32];
+
+/// Domain separation prefixes, so leaves and internal nodes never collide
+const LEAF_PREFIX: u8 = 0x00;
+const INTERNAL_PREFIX: u8 = 0x01;
+
+/// Hash of a leaf node over the given parts
+pub fn leaf_hash(parts: &[&[u8]]) -> Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[LEAF_PREFIX]);
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+/// Hash of an internal node over its children
+pub fn internal_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[INTERNAL_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
@@ -0,0 +1,316 @@
+use parking_lot::Mutex;
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+
+/// Default number of shards
+pub const DEFAULT_LRU_SHARDS: usize = 16;
+/// Slot index marking the end of a shard's recency list
+const NIL: usize = usize::MAX;
+
+/// Thread-safe LRU cache split into independently locked shards
+///
+/// Each shard is a hash map into a slab-allocated doubly linked recency
+/// list, so every operation is O(1) and only locks the shard owning the key.
+/// Eviction is per shard: the least recently used entry of the key's shard
+/// is evicted, which approximates global LRU order when keys hash evenly.
+///
+/// The API mirrors `cc_core_algorithms::LRUCache`, taking `&self` instead
+/// of `&mut self`.
+pub struct ConcurrentLruCache<K, V> {
+    shards: Vec<Mutex<Shard<K, V>>>,
+    hasher: RandomState,
+}
+
+struct Entry<K, V> {
+    key: K,
+    value: V,
+    prev: usize,
+    next: usize,
+}
+
+/// One shard: key index plus a recency list, most recent at `head`
+struct Shard<K, V> {
+    map: HashMap<K, usize>,
+    slots: Vec<Option<Entry<K, V>>>,
+    free: Vec<usize>,
+    head: usize,
+    tail: usize,
+    capacity: usize,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Shard<K, V> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            map: HashMap::with_capacity(capacity),
+            slots: Vec::with_capacity(capacity),
+            free: Vec::new(),
+            head: NIL,
+            tail: NIL,
+            capacity,
+        }
+    }
+
+    fn entry(&mut self, slot: usize) -> &mut Entry<K, V> {
+        self.slots[slot].as_mut().expect("linked slot is occupied")
+    }
+
+    fn unlink(&mut self, slot: usize) {
+        let (prev, next) = {
+            let entry = self.entry(slot);
+            (entry.prev, entry.next)
+        };
+        match prev {
+            NIL => self.head = next,
+            prev => self.entry(prev).next = next,
+        }
+        match next {
+            NIL => self.tail = prev,
+            next => self.entry(next).prev = prev,
+        }
+    }
+
+    fn push_front(&mut self, slot: usize) {
+        let head = self.head;
+        {
+            let entry = self.entry(slot);
+            entry.prev = NIL;
+            entry.next = head;
+        }
+        match head {
+            NIL => self.tail = slot,
+            head => self.entry(head).prev = slot,
+        }
+        self.head = slot;
+    }
+
+    fn get<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let slot = *self.map.get(key)?;
+        self.unlink(slot);
+        self.push_front(slot);
+        Some(self.entry(slot).value.clone())
+    }
+
+    fn put(&mut self, key: K, value: V) {
+        if let Some(&slot) = self.map.get(&key) {
+            self.entry(slot).value = value;
+            self.unlink(slot);
+            self.push_front(slot);
+            return;
+        }
+        if self.capacity == 0 {
+            return;
+        }
+        if self.map.len() >= self.capacity {
+            let tail = self.tail;
+            self.release(tail);
+        }
+
+        let entry = Entry {
+            key: key.clone(),
+            value,
+            prev: NIL,
+            next: NIL,
+        };
+        let slot = match self.free.pop() {
+            Some(slot) => {
+                self.slots[slot] = Some(entry);
+                slot
+            }
+            None => {
+                self.slots.push(Some(entry));
+                self.slots.len() - 1
+            }
+        };
+        self.push_front(slot);
+        self.map.insert(key, slot);
+    }
+
+    fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let slot = *self.map.get(key)?;
+        Some(self.release(slot))
+    }
+
+    /// Unlink and free a slot, returning its value
+    fn release(&mut self, slot: usize) -> V {
+        self.unlink(slot);
+        let entry = self.slots[slot].take().expect("linked slot is occupied");
+        self.map.remove(&entry.key);
+        self.free.push(slot);
+        entry.value
+    }
+
+    fn clear(&mut self) {
+        self.map.clear();
+        self.slots.clear();
+        self.free.clear();
+        self.head = NIL;
+        self.tail = NIL;
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> ConcurrentLruCache<K, V> {
+    /// Create a cache holding up to `capacity` entries in the default number of shards
+    pub fn new(capacity: usize) -> Self {
+        Self::with_shards(capacity, DEFAULT_LRU_SHARDS)
+    }
+
+    /// Create a cache holding up to `capacity` entries in `shard_count` shards
+    ///
+    /// The capacity is split evenly; small caches use fewer shards so each
+    /// shard holds at least one entry.
+    pub fn with_shards(capacity: usize, shard_count: usize) -> Self {
+        let shard_count = shard_count.min(capacity).max(1);
+        let shards = (0..shard_count)
+            .map(|i| {
+                // Spread the remainder over the first shards
+                let share = capacity / shard_count + usize::from(i < capacity % shard_count);
+                Mutex::new(Shard::new(share))
+            })
+            .collect();
+        Self {
+            shards,
+            hasher: RandomState::new(),
+        }
+    }
+
+    fn shard<Q: Hash + ?Sized>(&self, key: &Q) -> &Mutex<Shard<K, V>> {
+        let index = self.hasher.hash_one(key) % self.shards.len() as u64;
+        &self.shards[index as usize]
+    }
+
+    /// Get a value, marking it as most recently used
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shard(key).lock().get(key)
+    }
+
+    /// Insert or replace a value, evicting the shard's least recently used entry if full
+    pub fn put(&self, key: K, value: V) {
+        self.shard(&key).lock().put(key, value)
+    }
+
+    /// Remove a key, returning its value if present
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shard(key).lock().remove(key)
+    }
+
+    /// Check whether a key is cached without updating its recency
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shard(key).lock().map.contains_key(key)
+    }
+
+    /// Number of cached entries
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.lock().map.len()).sum()
+    }
+
+    /// Whether the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| shard.lock().map.is_empty())
+    }
+
+    /// Maximum number of entries the cache holds
+    pub fn capacity(&self) -> usize {
+        self.shards.iter().map(|shard| shard.lock().capacity).sum()
+    }
+
+    /// Remove all entries from the cache
+    pub fn clear(&self) {
+        for shard in &self.shards {
+            shard.lock().clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_lru_eviction_within_shard() {
+        let cache = ConcurrentLruCache::with_shards(3, 1);
+        cache.put("a", 1);
+        cache.put("b", 2);
+        cache.put("c", 3);
+        assert_eq!(cache.get("a"), Some(1));
+
+        // "b" is now the least recently used
+        cache.put("d", 4);
+        assert!(!cache.contains("b"));
+        assert_eq!(cache.len(), 3);
+
+        cache.put("a", 10);
+        assert_eq!(cache.remove("a"), Some(10));
+        assert_eq!(cache.remove("a"), None);
+        cache.put("e", 5);
+        cache.put("f", 6);
+        assert_eq!(cache.len(), 3);
+        assert!(!cache.contains("c"));
+
+        cache.clear();
+        assert!(cache.is_empty());
+        assert_eq!(cache.capacity(), 3);
+    }
+
+    #[test]
+    fn test_capacity_split_across_shards() {
+        let cache: ConcurrentLruCache<u32, u32> = ConcurrentLruCache::with_shards(10, 4);
+        assert_eq!(cache.capacity(), 10);
+        for i in 0..1000 {
+            cache.put(i, i);
+        }
+        assert!(cache.len() <= 10);
+
+        let tiny: ConcurrentLruCache<u32, u32> = ConcurrentLruCache::new(2);
+        assert_eq!(tiny.capacity(), 2);
+        let empty: ConcurrentLruCache<u32, u32> = ConcurrentLruCache::new(0);
+        empty.put(1, 1);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_access() {
+        let cache = Arc::new(ConcurrentLruCache::new(256));
+        let handles: Vec<_> = (0..8u32)
+            .map(|thread_id| {
+                let cache = cache.clone();
+                thread::spawn(move || {
+                    for i in 0..1000u32 {
+                        let key = (thread_id * 1000 + i) % 512;
+                        cache.put(key.to_be_bytes().to_vec(), key);
+                        if let Some(value) = cache.get(&key.to_be_bytes()[..]) {
+                            assert_eq!(value, key);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert!(cache.len() <= 256);
+    }
+}
@@ -0,0 +1,401 @@
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use std::sync::Arc;
+
+/// Hash bits consumed per level of the trie
+const BITS_PER_LEVEL: u32 = 5;
+const LEVEL_MASK: u64 = (1 << BITS_PER_LEVEL) - 1;
+
+/// Persistent hash map (hash array mapped trie) with structural sharing
+///
+/// Cloning is O(1): the clone shares every node with the original. Updates
+/// copy only the O(log32 n) nodes on the changed key's path, and nodes not
+/// shared with any other clone are updated in place. This makes speculative
+/// state overlays and snapshots cost O(changes) instead of O(entries):
+/// clone the map, apply changes to the clone, and either keep or drop it.
+///
+/// Keys are routed by 64-bit hash, 5 bits per level; keys with identical
+/// full hashes share a collision leaf. Values are cloned when a shared leaf
+/// is copied, so large values are best stored behind an `Arc`.
+pub struct PersistentMap<K, V> {
+    root: Arc<Node<K, V>>,
+    len: usize,
+    hasher: RandomState,
+}
+
+#[derive(Clone)]
+enum Node<K, V> {
+    /// Children for the set bits of `bitmap`, in bit order
+    Branch {
+        bitmap: u32,
+        children: Vec<Arc<Node<K, V>>>,
+    },
+    /// Entries whose keys all have the full hash `hash`
+    Leaf { hash: u64, entries: Vec<(K, V)> },
+}
+
+/// Bit selecting a hash's child at `shift`, and that child's position
+fn slot(bitmap: u32, hash: u64, shift: u32) -> (u32, usize) {
+    let bit = 1 << ((hash >> shift) & LEVEL_MASK);
+    (bit, (bitmap & (bit - 1)).count_ones() as usize)
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Node<K, V> {
+    fn empty() -> Self {
+        Node::Branch {
+            bitmap: 0,
+            children: Vec::new(),
+        }
+    }
+
+    fn get<Q>(&self, hash: u64, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let mut node = self;
+        let mut shift = 0;
+        loop {
+            match node {
+                Node::Branch { bitmap, children } => {
+                    let (bit, position) = slot(*bitmap, hash, shift);
+                    if bitmap & bit == 0 {
+                        return None;
+                    }
+                    node = &children[position];
+                    shift += BITS_PER_LEVEL;
+                }
+                Node::Leaf {
+                    hash: leaf_hash,
+                    entries,
+                } => {
+                    if *leaf_hash != hash {
+                        return None;
+                    }
+                    return entries
+                        .iter()
+                        .find(|(k, _)| k.borrow() == key)
+                        .map(|(_, v)| v);
+                }
+            }
+        }
+    }
+
+    fn insert(node: &mut Arc<Self>, hash: u64, shift: u32, key: K, value: V) -> Option<V> {
+        match Arc::make_mut(node) {
+            Node::Branch { bitmap, children } => {
+                let (bit, position) = slot(*bitmap, hash, shift);
+                if *bitmap & bit == 0 {
+                    let leaf = Node::Leaf {
+                        hash,
+                        entries: vec![(key, value)],
+                    };
+                    children.insert(position, Arc::new(leaf));
+                    *bitmap |= bit;
+                    return None;
+                }
+                Self::insert(
+                    &mut children[position],
+                    hash,
+                    shift + BITS_PER_LEVEL,
+                    key,
+                    value,
+                )
+            }
+            Node::Leaf {
+                hash: leaf_hash,
+                entries,
+            } if *leaf_hash == hash => {
+                if let Some((_, existing)) = entries.iter_mut().find(|(k, _)| *k == key) {
+                    return Some(std::mem::replace(existing, value));
+                }
+                entries.push((key, value));
+                None
+            }
+            leaf => {
+                // A different hash shares this prefix: push the leaf one
+                // level down. Distinct hashes diverge before bit 64.
+                let leaf_hash = match leaf {
+                    Node::Leaf { hash, .. } => *hash,
+                    Node::Branch { .. } => unreachable!("branches are matched above"),
+                };
+                let existing = std::mem::replace(leaf, Self::empty());
+                let (bit, _) = slot(0, leaf_hash, shift);
+                *leaf = Node::Branch {
+                    bitmap: bit,
+                    children: vec![Arc::new(existing)],
+                };
+                Self::insert(node, hash, shift, key, value)
+            }
+        }
+    }
+
+    fn remove<Q>(node: &mut Arc<Self>, hash: u64, shift: u32, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        match Arc::make_mut(node) {
+            Node::Branch { bitmap, children } => {
+                let (bit, position) = slot(*bitmap, hash, shift);
+                if *bitmap & bit == 0 {
+                    return None;
+                }
+                let child = &mut children[position];
+                let removed = Self::remove(child, hash, shift + BITS_PER_LEVEL, key)?;
+
+                // Keep the trie compact: drop empty children and lift a
+                // lone leaf into its parent
+                match &**child {
+                    Node::Leaf { entries, .. } if entries.is_empty() => {
+                        children.remove(position);
+                        *bitmap &= !bit;
+                    }
+                    Node::Branch {
+                        children: grandchildren,
+                        ..
+                    } if grandchildren.len() == 1
+                        && matches!(*grandchildren[0], Node::Leaf { .. }) =>
+                    {
+                        *child = grandchildren[0].clone();
+                    }
+                    _ => {}
+                }
+                Some(removed)
+            }
+            Node::Leaf {
+                hash: leaf_hash,
+                entries,
+            } => {
+                if *leaf_hash != hash {
+                    return None;
+                }
+                let index = entries.iter().position(|(k, _)| k.borrow() == key)?;
+                Some(entries.swap_remove(index).1)
+            }
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> PersistentMap<K, V> {
+    /// Create an empty map
+    pub fn new() -> Self {
+        Self {
+            root: Arc::new(Node::empty()),
+            len: 0,
+            hasher: RandomState::new(),
+        }
+    }
+
+    /// Value stored under a key
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.root.get(self.hasher.hash_one(key), key)
+    }
+
+    /// Check whether a key is present
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Insert or replace a value, returning the previous value
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let hash = self.hasher.hash_one(&key);
+        let previous = Node::insert(&mut self.root, hash, 0, key, value);
+        if previous.is_none() {
+            self.len += 1;
+        }
+        previous
+    }
+
+    /// Remove a key, returning its value
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = self.hasher.hash_one(key);
+        // Avoid copying a shared path for a key that is not there
+        self.root.get(hash, key)?;
+        let removed = Node::remove(&mut self.root, hash, 0, key);
+        if removed.is_some() {
+            self.len -= 1;
+        }
+        removed
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether two maps are clones with no changes between them, in O(1)
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.root, &other.root)
+    }
+
+    /// Entries in hash order
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        let mut stack = vec![&*self.root];
+        let mut leaf: std::slice::Iter<'_, (K, V)> = [].iter();
+        std::iter::from_fn(move || loop {
+            if let Some((key, value)) = leaf.next() {
+                return Some((key, value));
+            }
+            match stack.pop()? {
+                Node::Branch { children, .. } => {
+                    stack.extend(children.iter().rev().map(|child| &**child))
+                }
+                Node::Leaf { entries, .. } => leaf = entries.iter(),
+            }
+        })
+    }
+}
+
+impl<K, V> Clone for PersistentMap<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            len: self.len,
+            hasher: self.hasher.clone(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Default for PersistentMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> FromIterator<(K, V)> for PersistentMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        for (key, value) in iter {
+            map.insert(key, value);
+        }
+        map
+    }
+}
+
+impl<K, V> std::fmt::Debug for PersistentMap<K, V>
+where
+    K: Hash + Eq + Clone + std::fmt::Debug,
+    V: Clone + std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_overlay_leaves_base_untouched() {
+        let base: PersistentMap<u32, u64> = (0..1000).map(|i| (i, u64::from(i))).collect();
+        let mut overlay = base.clone();
+        assert!(overlay.ptr_eq(&base));
+
+        overlay.insert(5, 500);
+        overlay.insert(2000, 1);
+        assert_eq!(overlay.remove(&7), Some(7));
+        assert_eq!(overlay.remove(&7), None);
+        assert!(!overlay.ptr_eq(&base));
+
+        assert_eq!(base.get(&5), Some(&5));
+        assert_eq!(base.get(&7), Some(&7));
+        assert!(!base.contains_key(&2000));
+        assert_eq!(base.len(), 1000);
+
+        assert_eq!(overlay.get(&5), Some(&500));
+        assert!(!overlay.contains_key(&7));
+        assert_eq!(overlay.len(), 1000);
+        assert_eq!(overlay.iter().count(), 1000);
+
+        // Removing an absent key must not copy the shared path
+        let mut untouched = base.clone();
+        assert_eq!(untouched.remove(&5000), None);
+        assert!(untouched.ptr_eq(&base));
+    }
+
+    #[test]
+    fn test_matches_hash_map_under_churn() {
+        let mut map = PersistentMap::new();
+        let mut expected = HashMap::new();
+        let mut snapshots = Vec::new();
+        let mut seed = 0x853c_49e6_748f_ea9bu64;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+
+        for step in 0..5000 {
+            let key = next() % 512;
+            if next() % 3 == 0 {
+                assert_eq!(map.remove(&key), expected.remove(&key));
+            } else {
+                let value = next();
+                assert_eq!(map.insert(key, value), expected.insert(key, value));
+            }
+            if step % 1000 == 0 {
+                snapshots.push((map.clone(), expected.clone()));
+            }
+        }
+
+        assert_eq!(map.len(), expected.len());
+        let collected: HashMap<u64, u64> = map.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(collected, expected);
+        for (snapshot, contents) in snapshots {
+            assert_eq!(snapshot.len(), contents.len());
+            assert!(contents.iter().all(|(k, v)| snapshot.get(k) == Some(v)));
+        }
+    }
+
+    /// Key whose hash ignores its value, forcing full collisions
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Colliding(u32);
+
+    impl Hash for Colliding {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+            state.write_u32(0);
+        }
+    }
+
+    #[test]
+    fn test_full_hash_collisions() {
+        let mut map = PersistentMap::new();
+        for i in 0..10 {
+            map.insert(Colliding(i), i);
+        }
+        let snapshot = map.clone();
+        assert_eq!(map.insert(Colliding(3), 30), Some(3));
+        assert_eq!(map.remove(&Colliding(4)), Some(4));
+        assert_eq!(map.get(&Colliding(3)), Some(&30));
+        assert_eq!(snapshot.get(&Colliding(3)), Some(&3));
+        assert_eq!(snapshot.get(&Colliding(4)), Some(&4));
+        assert_eq!(map.len(), 9);
+        for i in 0..10 {
+            map.remove(&Colliding(i));
+        }
+        assert!(map.is_empty());
+        assert_eq!(map.iter().count(), 0);
+    }
+}
@@ -0,0 +1,337 @@
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Max-priority queue addressable by key
+///
+/// A binary heap paired with a key-to-position index, so besides `push` and
+/// `pop` an entry can be removed or re-prioritised by key in O(log n)
+/// without rebuilding the heap. Each key is queued at most once.
+///
+/// Highest priority pops first; wrap priorities in `std::cmp::Reverse` for
+/// earliest-deadline-first ordering, e.g. for timers.
+///
+/// Serializes as a list of `(key, priority)` pairs; deserialization rebuilds
+/// the heap and index and rejects duplicate keys.
+#[derive(Debug, Clone)]
+pub struct IndexedPriorityQueue<K, P> {
+    heap: Vec<(K, P)>,
+    positions: HashMap<K, usize>,
+}
+
+impl<K: Hash + Eq + Clone, P: Ord> Default for IndexedPriorityQueue<K, P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq + Clone, P: Ord> IndexedPriorityQueue<K, P> {
+    /// Create an empty queue
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Create an empty queue with room for `capacity` entries
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            heap: Vec::with_capacity(capacity),
+            positions: HashMap::with_capacity(capacity),
+        }
+    }
+
+    /// Queue a key, returning its previous priority if it was already queued
+    pub fn push(&mut self, key: K, priority: P) -> Option<P> {
+        if let Some(&position) = self.positions.get(&key) {
+            return Some(self.reprioritise(position, priority));
+        }
+        let position = self.heap.len();
+        self.positions.insert(key.clone(), position);
+        self.heap.push((key, priority));
+        self.sift_up(position);
+        None
+    }
+
+    /// Remove and return the highest priority entry
+    pub fn pop(&mut self) -> Option<(K, P)> {
+        if self.heap.is_empty() {
+            return None;
+        }
+        Some(self.remove_at(0))
+    }
+
+    /// Highest priority entry without removing it
+    pub fn peek(&self) -> Option<(&K, &P)> {
+        self.heap.first().map(|(key, priority)| (key, priority))
+    }
+
+    /// Remove a key, returning its priority if it was queued
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<P>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let position = *self.positions.get(key)?;
+        Some(self.remove_at(position).1)
+    }
+
+    /// Change the priority of a queued key, returning the old priority
+    ///
+    /// Returns `None` and queues nothing if the key is not present.
+    pub fn change_priority<Q>(&mut self, key: &Q, priority: P) -> Option<P>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let position = *self.positions.get(key)?;
+        Some(self.reprioritise(position, priority))
+    }
+
+    /// Current priority of a key
+    pub fn priority<Q>(&self, key: &Q) -> Option<&P>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.positions
+            .get(key)
+            .map(|&position| &self.heap[position].1)
+    }
+
+    /// Check whether a key is queued
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.positions.contains_key(key)
+    }
+
+    /// Number of queued entries
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    /// Whether the queue is empty
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// Remove all entries
+    pub fn clear(&mut self) {
+        self.heap.clear();
+        self.positions.clear();
+    }
+
+    /// Entries in heap order, not sorted by priority
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &P)> {
+        self.heap.iter().map(|(key, priority)| (key, priority))
+    }
+
+    /// Replace the priority at `position` and restore heap order
+    fn reprioritise(&mut self, position: usize, priority: P) -> P {
+        let old = std::mem::replace(&mut self.heap[position].1, priority);
+        if self.heap[position].1 > old {
+            self.sift_up(position);
+        } else {
+            self.sift_down(position);
+        }
+        old
+    }
+
+    /// Remove the entry at `position` by swapping in the last entry
+    fn remove_at(&mut self, position: usize) -> (K, P) {
+        let last = self.heap.len() - 1;
+        self.swap(position, last);
+        let (key, priority) = self.heap.pop().expect("heap is non-empty");
+        self.positions.remove(&key);
+        if position < self.heap.len() {
+            // The moved entry may belong above or below its new position
+            self.sift_up(position);
+            self.sift_down(position);
+        }
+        (key, priority)
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        self.heap.swap(a, b);
+        *self
+            .positions
+            .get_mut(&self.heap[a].0)
+            .expect("queued key is indexed") = a;
+        *self
+            .positions
+            .get_mut(&self.heap[b].0)
+            .expect("queued key is indexed") = b;
+    }
+
+    fn sift_up(&mut self, mut position: usize) {
+        while position > 0 {
+            let parent = (position - 1) / 2;
+            if self.heap[position].1 <= self.heap[parent].1 {
+                break;
+            }
+            self.swap(position, parent);
+            position = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut position: usize) {
+        loop {
+            let left = 2 * position + 1;
+            let right = left + 1;
+            let mut largest = position;
+            if left < self.heap.len() && self.heap[left].1 > self.heap[largest].1 {
+                largest = left;
+            }
+            if right < self.heap.len() && self.heap[right].1 > self.heap[largest].1 {
+                largest = right;
+            }
+            if largest == position {
+                break;
+            }
+            self.swap(position, largest);
+            position = largest;
+        }
+    }
+}
+
+impl<K: Serialize, P: Serialize> Serialize for IndexedPriorityQueue<K, P> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(&self.heap)
+    }
+}
+
+impl<'de, K, P> Deserialize<'de> for IndexedPriorityQueue<K, P>
+where
+    K: Hash + Eq + Clone + Deserialize<'de>,
+    P: Ord + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let entries = Vec::<(K, P)>::deserialize(deserializer)?;
+        let mut queue = Self::with_capacity(entries.len());
+        for (key, priority) in entries {
+            if queue.push(key, priority).is_some() {
+                return Err(de::Error::custom("duplicate key in priority queue"));
+            }
+        }
+        Ok(queue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cmp::Reverse;
+
+    /// Drain a queue, checking priorities come out in non-increasing order
+    fn drain_sorted(queue: &mut IndexedPriorityQueue<u32, u64>) -> Vec<(u32, u64)> {
+        let mut out = Vec::new();
+        while let Some(entry) = queue.pop() {
+            if let Some(&(_, previous)) = out.last() {
+                assert!(entry.1 <= previous);
+            }
+            out.push(entry);
+        }
+        out
+    }
+
+    #[test]
+    fn test_remove_and_change_priority() {
+        let mut queue = IndexedPriorityQueue::new();
+        for (key, fee) in [(1u32, 10u64), (2, 50), (3, 30), (4, 20), (5, 40)] {
+            assert_eq!(queue.push(key, fee), None);
+        }
+        assert_eq!(queue.peek(), Some((&2, &50)));
+
+        // Replacement by fee bump re-queues the key in place
+        assert_eq!(queue.push(1, 60), Some(10));
+        assert_eq!(queue.peek(), Some((&1, &60)));
+        assert_eq!(queue.change_priority(&1, 5), Some(60));
+        assert_eq!(queue.change_priority(&9, 5), None);
+        assert!(!queue.contains(&9));
+
+        assert_eq!(queue.remove(&5), Some(40));
+        assert_eq!(queue.remove(&5), None);
+        assert_eq!(queue.priority(&3), Some(&30));
+        assert_eq!(queue.len(), 4);
+
+        let order: Vec<u32> = drain_sorted(&mut queue)
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(order, vec![2, 3, 4, 1]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let mut queue = IndexedPriorityQueue::new();
+        for key in 0..20u32 {
+            queue.push(key, u64::from(key * 7 % 11));
+        }
+        let json = serde_json::to_string(&queue).unwrap();
+        let mut restored: IndexedPriorityQueue<u32, u64> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.priority(&13), Some(&3));
+        assert_eq!(drain_sorted(&mut restored), drain_sorted(&mut queue));
+
+        let duplicate = "[[1,5],[1,6]]";
+        assert!(serde_json::from_str::<IndexedPriorityQueue<u32, u64>>(duplicate).is_err());
+    }
+
+    #[test]
+    fn test_reverse_orders_timers_by_deadline() {
+        let mut timers = IndexedPriorityQueue::new();
+        timers.push("propose", Reverse(300u64));
+        timers.push("prevote", Reverse(100));
+        timers.push("precommit", Reverse(200));
+
+        timers.change_priority("propose", Reverse(50));
+        timers.remove("precommit");
+        assert_eq!(timers.pop(), Some(("propose", Reverse(50))));
+        assert_eq!(timers.pop(), Some(("prevote", Reverse(100))));
+        assert_eq!(timers.pop(), None);
+    }
+
+    #[test]
+    fn test_matches_sorted_order_under_churn() {
+        let mut queue = IndexedPriorityQueue::with_capacity(200);
+        let mut expected = HashMap::new();
+        // Deterministic pseudo-random stream
+        let mut seed = 0x9e37_79b9_7f4a_7c15u64;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+
+        for _ in 0..2000 {
+            let key = (next() % 200) as u32;
+            let priority = next() % 1000;
+            match next() % 3 {
+                0 => {
+                    assert_eq!(queue.remove(&key), expected.remove(&key));
+                }
+                1 => {
+                    let old = queue.change_priority(&key, priority);
+                    let was = expected
+                        .get_mut(&key)
+                        .map(|p| std::mem::replace(p, priority));
+                    assert_eq!(old, was);
+                }
+                _ => {
+                    assert_eq!(queue.push(key, priority), expected.insert(key, priority));
+                }
+            }
+        }
+
+        assert_eq!(queue.len(), expected.len());
+        let drained = drain_sorted(&mut queue);
+        assert_eq!(drained.len(), expected.len());
+        for (key, priority) in drained {
+            assert_eq!(expected.get(&key), Some(&priority));
+        }
+    }
+}
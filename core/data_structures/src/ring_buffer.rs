@@ -0,0 +1,157 @@
+use crate::DataStructureError;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Fixed-capacity buffer that overwrites its oldest item when full
+///
+/// Serializes as its capacity plus items oldest first; deserialization
+/// rejects a zero capacity or more items than the capacity allows.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RingBufferData<T>")]
+#[serde(bound(deserialize = "T: Deserialize<'de>"))]
+pub struct RingBuffer<T> {
+    capacity: usize,
+    items: VecDeque<T>,
+}
+
+/// Unvalidated wire form of a `RingBuffer`
+#[derive(Deserialize)]
+struct RingBufferData<T> {
+    capacity: usize,
+    items: VecDeque<T>,
+}
+
+impl<T> TryFrom<RingBufferData<T>> for RingBuffer<T> {
+    type Error = DataStructureError;
+
+    fn try_from(data: RingBufferData<T>) -> Result<Self, Self::Error> {
+        if data.capacity == 0 {
+            return Err(DataStructureError::Invalid(
+                "Ring buffer has zero capacity".to_string(),
+            ));
+        }
+        if data.items.len() > data.capacity {
+            return Err(DataStructureError::Invalid(format!(
+                "Ring buffer holds {} items, capacity is {}",
+                data.items.len(),
+                data.capacity
+            )));
+        }
+        Ok(Self {
+            capacity: data.capacity,
+            items: data.items,
+        })
+    }
+}
+
+impl<T> RingBuffer<T> {
+    /// Create a buffer holding up to `capacity` items (at least one)
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            items: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Append an item, returning the oldest item if it was overwritten
+    pub fn push(&mut self, item: T) -> Option<T> {
+        let evicted = if self.is_full() {
+            self.items.pop_front()
+        } else {
+            None
+        };
+        self.items.push_back(item);
+        evicted
+    }
+
+    /// Remove and return the oldest item
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.items.pop_front()
+    }
+
+    /// Oldest item
+    pub fn front(&self) -> Option<&T> {
+        self.items.front()
+    }
+
+    /// Newest item
+    pub fn back(&self) -> Option<&T> {
+        self.items.back()
+    }
+
+    /// Item at `index`, counting from the oldest
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.items.get(index)
+    }
+
+    /// Items from oldest to newest
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> + ExactSizeIterator {
+        self.items.iter()
+    }
+
+    /// Number of items held
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Whether the buffer is empty
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Whether the next push overwrites the oldest item
+    pub fn is_full(&self) -> bool {
+        self.items.len() == self.capacity
+    }
+
+    /// Maximum number of items held
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Remove all items
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_overwrites_oldest() {
+        let mut buffer = RingBuffer::new(3);
+        assert_eq!(buffer.push(1), None);
+        assert_eq!(buffer.push(2), None);
+        assert_eq!(buffer.push(3), None);
+        assert!(buffer.is_full());
+        assert_eq!(buffer.push(4), Some(1));
+        assert_eq!(buffer.iter().copied().collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert_eq!((buffer.front(), buffer.back()), (Some(&2), Some(&4)));
+        assert_eq!(buffer.pop_front(), Some(2));
+        assert_eq!(buffer.get(1), Some(&4));
+
+        buffer.clear();
+        assert!(buffer.is_empty());
+        assert_eq!(RingBuffer::<u8>::new(0).capacity(), 1);
+    }
+
+    #[test]
+    fn test_serde_round_trip_validates() {
+        let mut buffer = RingBuffer::new(4);
+        for i in 0..6u32 {
+            buffer.push(i);
+        }
+        let json = serde_json::to_string(&buffer).unwrap();
+        assert_eq!(json, r#"{"capacity":4,"items":[2,3,4,5]}"#);
+        let restored: RingBuffer<u32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, buffer);
+
+        let invalid = r#"{"capacity":0,"items":[]}"#;
+        assert!(serde_json::from_str::<RingBuffer<u32>>(invalid).is_err());
+        let invalid = r#"{"capacity":1,"items":[1,2]}"#;
+        assert!(serde_json::from_str::<RingBuffer<u32>>(invalid).is_err());
+    }
+}
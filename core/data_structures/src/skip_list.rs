@@ -0,0 +1,476 @@
+use parking_lot::RwLock;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+
+/// Default number of shards
+pub const DEFAULT_SKIP_LIST_SHARDS: usize = 16;
+/// Maximum tower height; 4^16 entries per shard before search degrades
+const MAX_LEVEL: usize = 16;
+/// Slot index marking the end of a level
+const NIL: usize = usize::MAX;
+
+/// Concurrent ordered map keyed by `(address, nonce)`
+///
+/// Entries are kept in skip lists sorted by address then nonce, so the
+/// queued nonces of one sender are adjacent and "next runnable nonce"
+/// queries walk only that sender's run. Senders are spread over
+/// independently locked shards, so writers for different senders do not
+/// contend and reads only take a shared lock.
+pub struct NonceSkipMap<A, V> {
+    shards: Vec<RwLock<SkipList<A, V>>>,
+    hasher: RandomState,
+}
+
+struct Node<A, V> {
+    address: A,
+    nonce: u64,
+    value: V,
+    /// Forward links, one per level of this node's tower
+    next: Vec<usize>,
+}
+
+/// Single-threaded skip list over slab-allocated nodes
+struct SkipList<A, V> {
+    nodes: Vec<Option<Node<A, V>>>,
+    free: Vec<usize>,
+    head: [usize; MAX_LEVEL],
+    level: usize,
+    len: usize,
+    rng: u64,
+}
+
+impl<A: Ord, V> SkipList<A, V> {
+    fn new(seed: u64) -> Self {
+        Self {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            head: [NIL; MAX_LEVEL],
+            level: 1,
+            len: 0,
+            // Xorshift state must be non-zero
+            rng: seed | 1,
+        }
+    }
+
+    fn node(&self, slot: usize) -> &Node<A, V> {
+        self.nodes[slot].as_ref().expect("linked slot is occupied")
+    }
+
+    fn node_mut(&mut self, slot: usize) -> &mut Node<A, V> {
+        self.nodes[slot].as_mut().expect("linked slot is occupied")
+    }
+
+    /// Successor of `slot` at `level`, where `NIL` stands for the head
+    fn next_of(&self, slot: usize, level: usize) -> usize {
+        match slot {
+            NIL => self.head[level],
+            slot => self.node(slot).next[level],
+        }
+    }
+
+    fn set_next(&mut self, slot: usize, level: usize, next: usize) {
+        match slot {
+            NIL => self.head[level] = next,
+            slot => self.node_mut(slot).next[level] = next,
+        }
+    }
+
+    fn is_before(&self, slot: usize, address: &A, nonce: u64) -> bool {
+        let node = self.node(slot);
+        (&node.address, node.nonce) < (address, nonce)
+    }
+
+    fn is_at(&self, slot: usize, address: &A, nonce: u64) -> bool {
+        slot != NIL && {
+            let node = self.node(slot);
+            node.address == *address && node.nonce == nonce
+        }
+    }
+
+    /// Last node before the key on every level
+    fn predecessors(&self, address: &A, nonce: u64) -> [usize; MAX_LEVEL] {
+        let mut update = [NIL; MAX_LEVEL];
+        let mut current = NIL;
+        for level in (0..self.level).rev() {
+            loop {
+                let next = self.next_of(current, level);
+                if next == NIL || !self.is_before(next, address, nonce) {
+                    break;
+                }
+                current = next;
+            }
+            update[level] = current;
+        }
+        update
+    }
+
+    /// First node at or after the key
+    fn seek(&self, address: &A, nonce: u64) -> usize {
+        let mut current = NIL;
+        for level in (0..self.level).rev() {
+            loop {
+                let next = self.next_of(current, level);
+                if next == NIL || !self.is_before(next, address, nonce) {
+                    break;
+                }
+                current = next;
+            }
+        }
+        self.next_of(current, 0)
+    }
+
+    /// Tower height with a 1/4 chance of growing each level
+    fn random_level(&mut self) -> usize {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        let mut bits = self.rng;
+        let mut level = 1;
+        while level < MAX_LEVEL && bits & 3 == 0 {
+            level += 1;
+            bits >>= 2;
+        }
+        level
+    }
+
+    fn get(&self, address: &A, nonce: u64) -> Option<&V> {
+        let slot = self.seek(address, nonce);
+        self.is_at(slot, address, nonce)
+            .then(|| &self.node(slot).value)
+    }
+
+    fn insert(&mut self, address: A, nonce: u64, value: V) -> Option<V> {
+        let update = self.predecessors(&address, nonce);
+        let found = self.next_of(update[0], 0);
+        if self.is_at(found, &address, nonce) {
+            return Some(std::mem::replace(&mut self.node_mut(found).value, value));
+        }
+
+        let height = self.random_level();
+        if height > self.level {
+            // New levels start at the head, which `update` already holds as NIL
+            self.level = height;
+        }
+        let next = (0..height)
+            .map(|level| self.next_of(update[level], level))
+            .collect();
+        let node = Node {
+            address,
+            nonce,
+            value,
+            next,
+        };
+        let slot = match self.free.pop() {
+            Some(slot) => {
+                self.nodes[slot] = Some(node);
+                slot
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        };
+        for (level, &pred) in update.iter().enumerate().take(height) {
+            self.set_next(pred, level, slot);
+        }
+        self.len += 1;
+        None
+    }
+
+    fn remove(&mut self, address: &A, nonce: u64) -> Option<V> {
+        let update = self.predecessors(address, nonce);
+        let found = self.next_of(update[0], 0);
+        if !self.is_at(found, address, nonce) {
+            return None;
+        }
+        let node = self.nodes[found].take().expect("linked slot is occupied");
+        for (level, &next) in node.next.iter().enumerate() {
+            self.set_next(update[level], level, next);
+        }
+        while self.level > 1 && self.head[self.level - 1] == NIL {
+            self.level -= 1;
+        }
+        self.free.push(found);
+        self.len -= 1;
+        Some(node.value)
+    }
+
+    /// Nodes of `address` from `nonce` onwards, in nonce order
+    fn run_from<'a>(
+        &'a self,
+        address: &'a A,
+        nonce: u64,
+    ) -> impl Iterator<Item = &'a Node<A, V>> + 'a {
+        let mut slot = self.seek(address, nonce);
+        std::iter::from_fn(move || {
+            if slot == NIL {
+                return None;
+            }
+            let node = self.node(slot);
+            if node.address != *address {
+                return None;
+            }
+            slot = node.next[0];
+            Some(node)
+        })
+    }
+
+    fn clear(&mut self) {
+        self.nodes.clear();
+        self.free.clear();
+        self.head = [NIL; MAX_LEVEL];
+        self.level = 1;
+        self.len = 0;
+    }
+}
+
+impl<A: Ord + Hash + Clone, V: Clone> NonceSkipMap<A, V> {
+    /// Create an empty map with the default number of shards
+    pub fn new() -> Self {
+        Self::with_shards(DEFAULT_SKIP_LIST_SHARDS)
+    }
+
+    /// Create an empty map with `shard_count` shards
+    pub fn with_shards(shard_count: usize) -> Self {
+        let hasher = RandomState::new();
+        let shards = (0..shard_count.max(1))
+            .map(|i| RwLock::new(SkipList::new(hasher.hash_one(i))))
+            .collect();
+        Self { shards, hasher }
+    }
+
+    fn shard(&self, address: &A) -> &RwLock<SkipList<A, V>> {
+        let index = self.hasher.hash_one(address) % self.shards.len() as u64;
+        &self.shards[index as usize]
+    }
+
+    /// Insert or replace the entry for `(address, nonce)`, returning the old value
+    pub fn insert(&self, address: A, nonce: u64, value: V) -> Option<V> {
+        self.shard(&address).write().insert(address, nonce, value)
+    }
+
+    /// Value stored for `(address, nonce)`
+    pub fn get(&self, address: &A, nonce: u64) -> Option<V> {
+        self.shard(address).read().get(address, nonce).cloned()
+    }
+
+    /// Remove the entry for `(address, nonce)`, returning its value
+    pub fn remove(&self, address: &A, nonce: u64) -> Option<V> {
+        self.shard(address).write().remove(address, nonce)
+    }
+
+    /// Check whether `(address, nonce)` is present
+    pub fn contains(&self, address: &A, nonce: u64) -> bool {
+        self.shard(address).read().get(address, nonce).is_some()
+    }
+
+    /// Lowest queued nonce of `address` and its value
+    pub fn first(&self, address: &A) -> Option<(u64, V)> {
+        self.shard(address)
+            .read()
+            .run_from(address, 0)
+            .next()
+            .map(|node| (node.nonce, node.value.clone()))
+    }
+
+    /// All entries of `address` in nonce order
+    pub fn entries(&self, address: &A) -> Vec<(u64, V)> {
+        self.shard(address)
+            .read()
+            .run_from(address, 0)
+            .map(|node| (node.nonce, node.value.clone()))
+            .collect()
+    }
+
+    /// Entries of `address` that can execute in sequence from `account_nonce`
+    ///
+    /// Stops at the first missing nonce, so entries behind a gap are not
+    /// returned.
+    pub fn runnable(&self, address: &A, account_nonce: u64) -> Vec<(u64, V)> {
+        let shard = self.shard(address).read();
+        // `None` once the run has reached `u64::MAX`, which nothing follows
+        let mut expected = Some(account_nonce);
+        shard
+            .run_from(address, account_nonce)
+            .take_while(|node| {
+                let in_sequence = expected == Some(node.nonce);
+                expected = node.nonce.checked_add(1);
+                in_sequence
+            })
+            .map(|node| (node.nonce, node.value.clone()))
+            .collect()
+    }
+
+    /// First nonce at or after `account_nonce` with no queued entry
+    ///
+    /// This is the next nonce the sender needs to fill, either to extend the
+    /// runnable sequence or to close the first gap. Returns `None` if the
+    /// sequence runs up to `u64::MAX`.
+    pub fn next_nonce(&self, address: &A, account_nonce: u64) -> Option<u64> {
+        let shard = self.shard(address).read();
+        let mut expected = account_nonce;
+        for node in shard.run_from(address, account_nonce) {
+            if node.nonce != expected {
+                break;
+            }
+            expected = expected.checked_add(1)?;
+        }
+        Some(expected)
+    }
+
+    /// Remove every entry of `address` below `nonce`, e.g. once they are
+    /// included in a block, returning the removed entries in nonce order
+    pub fn remove_below(&self, address: &A, nonce: u64) -> Vec<(u64, V)> {
+        let mut shard = self.shard(address).write();
+        let stale: Vec<u64> = shard
+            .run_from(address, 0)
+            .map(|node| node.nonce)
+            .take_while(|&queued| queued < nonce)
+            .collect();
+        stale
+            .into_iter()
+            .filter_map(|queued| {
+                let value = shard.remove(address, queued)?;
+                Some((queued, value))
+            })
+            .collect()
+    }
+
+    /// Number of entries across all addresses
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.read().len).sum()
+    }
+
+    /// Whether the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| shard.read().len == 0)
+    }
+
+    /// Remove all entries
+    pub fn clear(&self) {
+        for shard in &self.shards {
+            shard.write().clear();
+        }
+    }
+}
+
+impl<A: Ord + Hash + Clone, V: Clone> Default for NonceSkipMap<A, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_runnable_stops_at_gap() {
+        let map = NonceSkipMap::new();
+        for nonce in [5u64, 3, 4, 7, 8] {
+            assert_eq!(map.insert("alice", nonce, nonce * 10), None);
+        }
+        map.insert("bob", 4, 1);
+        assert_eq!(map.insert("alice", 3, 31), Some(30));
+
+        assert_eq!(map.runnable(&"alice", 3), vec![(3, 31), (4, 40), (5, 50)]);
+        assert_eq!(map.next_nonce(&"alice", 3), Some(6));
+        assert!(map.runnable(&"alice", 2).is_empty());
+        assert_eq!(map.next_nonce(&"alice", 2), Some(2));
+        assert_eq!(map.next_nonce(&"carol", 0), Some(0));
+        assert_eq!(map.first(&"alice"), Some((3, 31)));
+
+        // Filling the gap makes the tail runnable
+        map.insert("alice", 6, 60);
+        assert_eq!(map.next_nonce(&"alice", 3), Some(9));
+        assert_eq!(map.runnable(&"bob", 4), vec![(4, 1)]);
+
+        map.insert("max", u64::MAX, 0);
+        assert_eq!(map.next_nonce(&"max", u64::MAX), None);
+        assert_eq!(map.runnable(&"max", u64::MAX), vec![(u64::MAX, 0)]);
+        map.insert("max", u64::MAX - 1, 1);
+        assert_eq!(map.runnable(&"max", u64::MAX - 1), vec![(u64::MAX - 1, 1), (u64::MAX, 0)]);
+    }
+
+    #[test]
+    fn test_remove_below_prunes_included_nonces() {
+        let map = NonceSkipMap::with_shards(1);
+        for nonce in 0..10u64 {
+            map.insert(1u32, nonce, nonce);
+            map.insert(2u32, nonce, nonce);
+        }
+        let removed = map.remove_below(&1, 4);
+        assert_eq!(removed, (0..4).map(|n| (n, n)).collect::<Vec<_>>());
+        assert_eq!(map.first(&1), Some((4, 4)));
+        assert_eq!(map.entries(&2).len(), 10);
+        assert_eq!(map.len(), 16);
+
+        assert_eq!(map.remove(&1, 9), Some(9));
+        assert_eq!(map.remove(&1, 9), None);
+        assert!(!map.contains(&1, 9));
+        map.clear();
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_matches_btree_map_under_churn() {
+        let map = NonceSkipMap::with_shards(2);
+        let mut expected = BTreeMap::new();
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+
+        for _ in 0..5000 {
+            let address = (next() % 8) as u8;
+            let nonce = next() % 64;
+            if next() % 3 == 0 {
+                assert_eq!(
+                    map.remove(&address, nonce),
+                    expected.remove(&(address, nonce))
+                );
+            } else {
+                let value = next();
+                assert_eq!(
+                    map.insert(address, nonce, value),
+                    expected.insert((address, nonce), value)
+                );
+            }
+        }
+
+        assert_eq!(map.len(), expected.len());
+        for address in 0..8u8 {
+            let want: Vec<(u64, u64)> = expected
+                .range((address, 0)..=(address, u64::MAX))
+                .map(|(&(_, nonce), &value)| (nonce, value))
+                .collect();
+            assert_eq!(map.entries(&address), want);
+        }
+    }
+
+    #[test]
+    fn test_concurrent_senders() {
+        let map = Arc::new(NonceSkipMap::new());
+        let handles: Vec<_> = (0..8u32)
+            .map(|sender| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    for nonce in (0..500u64).rev() {
+                        map.insert(sender, nonce, u64::from(sender));
+                    }
+                    assert_eq!(map.next_nonce(&sender, 0), Some(500));
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(map.len(), 4000);
+    }
+}
@@ -0,0 +1,407 @@
+use crate::{internal_hash, Hash, EMPTY_HASH};
+use serde::{Deserialize, Serialize};
+
+/// Depth of the tree: one level per bit of the key path
+const MAX_DEPTH: usize = 256;
+
+/// Sparse Merkle tree over a 256-bit keyspace
+///
+/// Keys are placed at the path `blake3(key)`. Subtrees holding a single leaf
+/// are collapsed into that leaf and empty subtrees hash to zero, so updates
+/// and proofs cost O(log n) hashes instead of one per level of the 256-bit
+/// path. Every set of entries has exactly one root, independent of insertion
+/// order, which lets light clients check both inclusion and non-inclusion
+/// against a state root.
+#[derive(Debug, Clone, Default)]
+pub struct SparseMerkleTree {
+    root: Node,
+    len: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+enum Node {
+    #[default]
+    Empty,
+    Leaf {
+        path: Hash,
+        value: Vec<u8>,
+        hash: Hash,
+    },
+    Internal {
+        left: Box<Node>,
+        right: Box<Node>,
+        hash: Hash,
+    },
+}
+
+impl Node {
+    fn leaf(path: Hash, value: Vec<u8>) -> Self {
+        let hash = leaf_hash(&path, &blake3::hash(&value).into());
+        Node::Leaf { path, value, hash }
+    }
+
+    fn internal(left: Node, right: Node) -> Self {
+        let hash = internal_hash(&left.hash(), &right.hash());
+        Node::Internal {
+            left: Box::new(left),
+            right: Box::new(right),
+            hash,
+        }
+    }
+
+    fn hash(&self) -> Hash {
+        match self {
+            Node::Empty => EMPTY_HASH,
+            Node::Leaf { hash, .. } | Node::Internal { hash, .. } => *hash,
+        }
+    }
+
+    /// Insert below `depth`, returning the new subtree and the previous value
+    fn insert(self, path: Hash, value: Vec<u8>, depth: usize) -> (Node, Option<Vec<u8>>) {
+        match self {
+            Node::Empty => (Node::leaf(path, value), None),
+            Node::Leaf {
+                path: existing,
+                value: previous,
+                hash,
+            } => {
+                if existing == path {
+                    return (Node::leaf(path, value), Some(previous));
+                }
+                // Push the existing leaf one level down and retry; the paths
+                // differ, so this ends at their first differing bit
+                let leaf = Node::Leaf {
+                    path: existing,
+                    value: previous,
+                    hash,
+                };
+                let split = if bit(&existing, depth) {
+                    Node::internal(Node::Empty, leaf)
+                } else {
+                    Node::internal(leaf, Node::Empty)
+                };
+                split.insert(path, value, depth)
+            }
+            Node::Internal { left, right, .. } => {
+                if bit(&path, depth) {
+                    let (right, previous) = right.insert(path, value, depth + 1);
+                    (Node::internal(*left, right), previous)
+                } else {
+                    let (left, previous) = left.insert(path, value, depth + 1);
+                    (Node::internal(left, *right), previous)
+                }
+            }
+        }
+    }
+
+    /// Remove below `depth`, returning the new subtree and the removed value
+    fn remove(self, path: &Hash, depth: usize) -> (Node, Option<Vec<u8>>) {
+        match self {
+            Node::Leaf {
+                path: existing,
+                value,
+                ..
+            } if existing == *path => (Node::Empty, Some(value)),
+            Node::Internal { left, right, hash } => {
+                let (left, right, removed) = if bit(path, depth) {
+                    let (right, removed) = right.remove(path, depth + 1);
+                    (*left, right, removed)
+                } else {
+                    let (left, removed) = left.remove(path, depth + 1);
+                    (left, *right, removed)
+                };
+                let node = match (left, right, removed.is_some()) {
+                    (left, right, false) => Node::Internal {
+                        left: Box::new(left),
+                        right: Box::new(right),
+                        hash,
+                    },
+                    // A single remaining leaf moves up to keep the tree canonical
+                    (Node::Empty, leaf @ Node::Leaf { .. }, true)
+                    | (leaf @ Node::Leaf { .. }, Node::Empty, true) => leaf,
+                    (left, right, true) => Node::internal(left, right),
+                };
+                (node, removed)
+            }
+            node => (node, None),
+        }
+    }
+}
+
+/// Merkle proof for the presence or absence of a key
+///
+/// `siblings` holds the sibling hashes along the key's path, from the root
+/// down to the subtree where the path ends. That subtree is either empty or
+/// a single leaf, described by `leaf` as `(path, value hash)`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SparseMerkleProof {
+    pub siblings: Vec<Hash>,
+    pub leaf: Option<(Hash, Hash)>,
+}
+
+impl SparseMerkleProof {
+    /// Check that `key` maps to `value` in the tree with the given root
+    pub fn verify_inclusion(&self, root: &Hash, key: &[u8], value: &[u8]) -> bool {
+        let path = key_path(key);
+        match self.leaf {
+            Some((leaf_path, value_hash)) => {
+                leaf_path == path
+                    && value_hash == Hash::from(blake3::hash(value))
+                    && self.compute_root(&path) == Some(*root)
+            }
+            None => false,
+        }
+    }
+
+    /// Check that `key` is absent from the tree with the given root
+    pub fn verify_exclusion(&self, root: &Hash, key: &[u8]) -> bool {
+        let path = key_path(key);
+        if let Some((leaf_path, _)) = self.leaf {
+            // The path must end at a different key's leaf sharing the walked prefix
+            if leaf_path == path || common_prefix_bits(&leaf_path, &path) < self.siblings.len() {
+                return false;
+            }
+        }
+        self.compute_root(&path) == Some(*root)
+    }
+
+    fn compute_root(&self, path: &Hash) -> Option<Hash> {
+        if self.siblings.len() > MAX_DEPTH {
+            return None;
+        }
+        let mut current = match &self.leaf {
+            Some((leaf_path, value_hash)) => leaf_hash(leaf_path, value_hash),
+            None => EMPTY_HASH,
+        };
+        for (depth, sibling) in self.siblings.iter().enumerate().rev() {
+            current = if bit(path, depth) {
+                internal_hash(sibling, &current)
+            } else {
+                internal_hash(&current, sibling)
+            };
+        }
+        Some(current)
+    }
+}
+
+impl SparseMerkleTree {
+    /// Create an empty tree
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Root hash; all zeros for an empty tree
+    pub fn root(&self) -> Hash {
+        self.root.hash()
+    }
+
+    /// Number of keys in the tree
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the tree is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the value stored for a key
+    pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
+        let path = key_path(key);
+        let mut node = &self.root;
+        let mut depth = 0;
+        loop {
+            match node {
+                Node::Empty => return None,
+                Node::Leaf {
+                    path: leaf_path,
+                    value,
+                    ..
+                } => return (*leaf_path == path).then_some(value.as_slice()),
+                Node::Internal { left, right, .. } => {
+                    node = if bit(&path, depth) { right } else { left };
+                    depth += 1;
+                }
+            }
+        }
+    }
+
+    /// Insert or replace a value, returning the previous one
+    pub fn insert(&mut self, key: &[u8], value: Vec<u8>) -> Option<Vec<u8>> {
+        let root = std::mem::take(&mut self.root);
+        let (root, previous) = root.insert(key_path(key), value, 0);
+        self.root = root;
+        if previous.is_none() {
+            self.len += 1;
+        }
+        previous
+    }
+
+    /// Remove a key, returning its value
+    pub fn remove(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        let root = std::mem::take(&mut self.root);
+        let (root, removed) = root.remove(&key_path(key), 0);
+        self.root = root;
+        if removed.is_some() {
+            self.len -= 1;
+        }
+        removed
+    }
+
+    /// Inclusion proof for a key; `None` if the key is absent
+    pub fn prove(&self, key: &[u8]) -> Option<SparseMerkleProof> {
+        let path = key_path(key);
+        let proof = self.proof(&path);
+        matches!(proof.leaf, Some((leaf_path, _)) if leaf_path == path).then_some(proof)
+    }
+
+    /// Non-inclusion proof for a key; `None` if the key is present
+    pub fn prove_absent(&self, key: &[u8]) -> Option<SparseMerkleProof> {
+        let path = key_path(key);
+        let proof = self.proof(&path);
+        match proof.leaf {
+            Some((leaf_path, _)) if leaf_path == path => None,
+            _ => Some(proof),
+        }
+    }
+
+    fn proof(&self, path: &Hash) -> SparseMerkleProof {
+        let mut siblings = Vec::new();
+        let mut node = &self.root;
+        loop {
+            match node {
+                Node::Empty => {
+                    return SparseMerkleProof {
+                        siblings,
+                        leaf: None,
+                    }
+                }
+                Node::Leaf {
+                    path: leaf_path,
+                    value,
+                    ..
+                } => {
+                    let leaf = Some((*leaf_path, blake3::hash(value).into()));
+                    return SparseMerkleProof { siblings, leaf };
+                }
+                Node::Internal { left, right, .. } => {
+                    let depth = siblings.len();
+                    let (next, sibling) = if bit(path, depth) {
+                        (right, left)
+                    } else {
+                        (left, right)
+                    };
+                    siblings.push(sibling.hash());
+                    node = next;
+                }
+            }
+        }
+    }
+}
+
+fn key_path(key: &[u8]) -> Hash {
+    blake3::hash(key).into()
+}
+
+/// Bit `depth` of a path, most significant bit first; set means "go right"
+fn bit(path: &Hash, depth: usize) -> bool {
+    path[depth / 8] & (0x80 >> (depth % 8)) != 0
+}
+
+fn common_prefix_bits(a: &Hash, b: &Hash) -> usize {
+    (0..MAX_DEPTH)
+        .find(|&depth| bit(a, depth) != bit(b, depth))
+        .unwrap_or(MAX_DEPTH)
+}
+
+fn leaf_hash(path: &Hash, value_hash: &Hash) -> Hash {
+    crate::leaf_hash(&[path, value_hash])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(i: u32) -> Vec<u8> {
+        format!("account-{}", i).into_bytes()
+    }
+
+    #[test]
+    fn test_root_is_independent_of_insertion_order() {
+        let mut forward = SparseMerkleTree::new();
+        let mut backward = SparseMerkleTree::new();
+        assert_eq!(forward.root(), EMPTY_HASH);
+
+        for i in 0..100 {
+            forward.insert(&key(i), vec![i as u8]);
+        }
+        for i in (0..100).rev() {
+            backward.insert(&key(i), vec![i as u8]);
+        }
+        assert_eq!(forward.len(), 100);
+        assert_eq!(forward.root(), backward.root());
+        assert_eq!(forward.get(&key(42)), Some(&[42u8][..]));
+
+        // Removing keys restores the root of the smaller set
+        let mut small = SparseMerkleTree::new();
+        for i in 0..50 {
+            small.insert(&key(i), vec![i as u8]);
+        }
+        for i in 50..100 {
+            assert_eq!(forward.remove(&key(i)), Some(vec![i as u8]));
+        }
+        assert_eq!(forward.root(), small.root());
+        assert_eq!(forward.remove(&key(99)), None);
+        assert_eq!(forward.len(), 50);
+    }
+
+    #[test]
+    fn test_inclusion_proofs() {
+        let mut tree = SparseMerkleTree::new();
+        for i in 0..64 {
+            tree.insert(&key(i), format!("balance-{}", i).into_bytes());
+        }
+        let root = tree.root();
+
+        for i in 0..64 {
+            let proof = tree.prove(&key(i)).unwrap();
+            let value = format!("balance-{}", i).into_bytes();
+            assert!(proof.verify_inclusion(&root, &key(i), &value));
+            assert!(!proof.verify_inclusion(&root, &key(i), b"forged"));
+            assert!(!proof.verify_inclusion(&root, &key(i + 1), &value));
+            assert!(!proof.verify_exclusion(&root, &key(i)));
+        }
+        assert!(tree.prove(&key(1000)).is_none());
+
+        let mut single = SparseMerkleTree::new();
+        single.insert(b"only", b"one".to_vec());
+        let proof = single.prove(b"only").unwrap();
+        assert!(proof.siblings.is_empty());
+        assert!(proof.verify_inclusion(&single.root(), b"only", b"one"));
+    }
+
+    #[test]
+    fn test_non_inclusion_proofs() {
+        let mut tree = SparseMerkleTree::new();
+        assert!(tree
+            .prove_absent(b"anything")
+            .unwrap()
+            .verify_exclusion(&EMPTY_HASH, b"anything"));
+
+        for i in 0..64 {
+            tree.insert(&key(i), vec![1]);
+        }
+        let root = tree.root();
+        for i in 64..128 {
+            let proof = tree.prove_absent(&key(i)).unwrap();
+            assert!(proof.verify_exclusion(&root, &key(i)));
+            // The proof does not cover a different absent key's path
+            assert!(!proof.verify_exclusion(&[1u8; 32], &key(i)));
+        }
+        assert!(tree.prove_absent(&key(3)).is_none());
+
+        // An inclusion proof cannot be passed off as proof of absence
+        let inclusion = tree.prove(&key(3)).unwrap();
+        assert!(!inclusion.verify_exclusion(&root, &key(3)));
+    }
+}
@@ -0,0 +1,275 @@
+use serde::{Deserialize, Serialize};
+
+/// Default smoothing factor for the exponentially weighted moving average
+pub const DEFAULT_EWMA_ALPHA: f64 = 0.1;
+
+/// Running statistics over a stream of samples in O(1) memory
+///
+/// Tracks count, (weighted) mean and variance with West's incremental
+/// algorithm, min/max, and an exponentially weighted moving average, so
+/// metrics can be summarised without storing their samples. Accumulators
+/// built separately (e.g. per thread) can be combined with `merge`.
+///
+/// Weights act as frequencies: a sample of weight 2 counts like two equal
+/// samples for the mean and variance. The EWMA and min/max ignore weights.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OnlineStats {
+    count: u64,
+    weight: f64,
+    mean: f64,
+    /// Weighted sum of squared deviations from the mean
+    m2: f64,
+    /// `None` until the first sample, so empty accumulators serialize cleanly
+    min: Option<f64>,
+    max: Option<f64>,
+    ewma: Option<f64>,
+    alpha: f64,
+}
+
+impl Default for OnlineStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OnlineStats {
+    /// Create an empty accumulator using `DEFAULT_EWMA_ALPHA`
+    pub fn new() -> Self {
+        Self {
+            count: 0,
+            weight: 0.0,
+            mean: 0.0,
+            m2: 0.0,
+            min: None,
+            max: None,
+            ewma: None,
+            alpha: DEFAULT_EWMA_ALPHA,
+        }
+    }
+
+    /// Use `alpha` (clamped to `0.0..=1.0`) as the EWMA smoothing factor;
+    /// higher values follow recent samples more closely
+    pub fn with_alpha(mut self, alpha: f64) -> Self {
+        self.alpha = alpha.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Add a sample with weight 1
+    pub fn push(&mut self, value: f64) {
+        self.push_weighted(value, 1.0);
+    }
+
+    /// Add a sample with the given weight; non-finite values and
+    /// non-positive or non-finite weights are ignored
+    pub fn push_weighted(&mut self, value: f64, weight: f64) {
+        if !value.is_finite() || !weight.is_finite() || weight <= 0.0 {
+            return;
+        }
+        self.count += 1;
+        self.weight += weight;
+        let delta = value - self.mean;
+        self.mean += delta * weight / self.weight;
+        self.m2 += weight * delta * (value - self.mean);
+        self.min = Some(self.min.map_or(value, |min| min.min(value)));
+        self.max = Some(self.max.map_or(value, |max| max.max(value)));
+        self.ewma = Some(match self.ewma {
+            Some(ewma) => ewma + self.alpha * (value - ewma),
+            None => value,
+        });
+    }
+
+    /// Fold another accumulator's samples into this one
+    ///
+    /// Count, mean, variance and min/max become those of the combined
+    /// samples. The EWMA treats `other` as the more recent stream, moving
+    /// towards its average as if its samples were pushed after ours.
+    pub fn merge(&mut self, other: &OnlineStats) {
+        if other.count == 0 {
+            return;
+        }
+        if self.count == 0 {
+            let alpha = self.alpha;
+            *self = other.clone();
+            self.alpha = alpha;
+            return;
+        }
+        let weight = self.weight + other.weight;
+        let delta = other.mean - self.mean;
+        self.mean += delta * other.weight / weight;
+        self.m2 += other.m2 + delta * delta * self.weight * other.weight / weight;
+        self.weight = weight;
+        self.count += other.count;
+        self.min = self.min.zip(other.min).map(|(ours, theirs)| ours.min(theirs));
+        self.max = self.max.zip(other.max).map(|(ours, theirs)| ours.max(theirs));
+        if let (Some(ours), Some(theirs)) = (self.ewma, other.ewma) {
+            let decay = (1.0 - self.alpha).powf(other.count as f64);
+            self.ewma = Some(theirs + decay * (ours - theirs));
+        }
+    }
+
+    /// Number of samples
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Whether no samples were added
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Total weight of the samples
+    pub fn total_weight(&self) -> f64 {
+        self.weight
+    }
+
+    /// Weighted mean
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.mean)
+    }
+
+    /// Weighted population variance
+    pub fn variance(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.m2 / self.weight)
+    }
+
+    /// Unbiased sample variance, treating weights as frequencies
+    pub fn sample_variance(&self) -> Option<f64> {
+        (self.weight > 1.0).then(|| self.m2 / (self.weight - 1.0))
+    }
+
+    /// Population standard deviation
+    pub fn std_dev(&self) -> Option<f64> {
+        self.variance().map(f64::sqrt)
+    }
+
+    /// Smallest sample
+    pub fn min(&self) -> Option<f64> {
+        self.min
+    }
+
+    /// Largest sample
+    pub fn max(&self) -> Option<f64> {
+        self.max
+    }
+
+    /// Exponentially weighted moving average, seeded with the first sample
+    pub fn ewma(&self) -> Option<f64> {
+        self.ewma
+    }
+
+    /// Forget all samples, keeping the smoothing factor
+    pub fn reset(&mut self) {
+        *self = Self::new().with_alpha(self.alpha);
+    }
+}
+
+impl Extend<f64> for OnlineStats {
+    fn extend<I: IntoIterator<Item = f64>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl FromIterator<f64> for OnlineStats {
+    fn from_iter<I: IntoIterator<Item = f64>>(iter: I) -> Self {
+        let mut stats = Self::new();
+        stats.extend(iter);
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Option<f64>, b: f64) -> bool {
+        a.is_some_and(|a| (a - b).abs() < 1e-9)
+    }
+
+    #[test]
+    fn test_matches_direct_computation() {
+        let samples = [12.0, 7.5, 3.25, 20.0, 9.0, 11.5];
+        let stats: OnlineStats = samples.iter().copied().collect();
+
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let squares: f64 = samples.iter().map(|x| (x - mean).powi(2)).sum();
+        assert_eq!(stats.count(), 6);
+        assert!(close(stats.mean(), mean));
+        assert!(close(stats.variance(), squares / n));
+        assert!(close(stats.sample_variance(), squares / (n - 1.0)));
+        assert!(close(stats.std_dev(), (squares / n).sqrt()));
+        assert_eq!((stats.min(), stats.max()), (Some(3.25), Some(20.0)));
+
+        let empty = OnlineStats::new();
+        assert!(empty.is_empty());
+        assert_eq!(
+            (empty.mean(), empty.variance(), empty.min()),
+            (None, None, None)
+        );
+    }
+
+    #[test]
+    fn test_weights_act_as_frequencies() {
+        let mut weighted = OnlineStats::new();
+        weighted.push_weighted(10.0, 3.0);
+        weighted.push_weighted(20.0, 1.0);
+        weighted.push_weighted(99.0, 0.0);
+        weighted.push(f64::NAN);
+
+        let repeated: OnlineStats = [10.0, 10.0, 10.0, 20.0].into_iter().collect();
+        assert_eq!(weighted.count(), 2);
+        assert_eq!(weighted.total_weight(), 4.0);
+        assert!(close(weighted.mean(), repeated.mean().unwrap()));
+        assert!(close(weighted.variance(), repeated.variance().unwrap()));
+        assert!(close(
+            weighted.sample_variance(),
+            repeated.sample_variance().unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_merge_and_ewma() {
+        let first: Vec<f64> = (0..50).map(|i| (i * 7 % 13) as f64).collect();
+        let second: Vec<f64> = (0..30).map(|i| (i * 5 % 11) as f64 + 100.0).collect();
+
+        let mut merged: OnlineStats = OnlineStats::new().with_alpha(0.2);
+        merged.extend(first.iter().copied());
+        let mut other = OnlineStats::new().with_alpha(0.2);
+        other.extend(second.iter().copied());
+        merged.merge(&other);
+
+        let mut sequential = OnlineStats::new().with_alpha(0.2);
+        sequential.extend(first.iter().chain(&second).copied());
+        assert_eq!(merged.count(), sequential.count());
+        assert!(close(merged.mean(), sequential.mean().unwrap()));
+        assert!(close(merged.variance(), sequential.variance().unwrap()));
+        assert_eq!(merged.max(), sequential.max());
+        // Both EWMAs have moved most of the way to the second stream
+        assert!(merged.ewma().unwrap() > 95.0);
+        assert!(sequential.ewma().unwrap() > 95.0);
+
+        let mut ewma = OnlineStats::new().with_alpha(0.5);
+        ewma.extend([10.0, 20.0, 20.0]);
+        assert_eq!(ewma.ewma(), Some(17.5));
+        ewma.reset();
+        assert!(ewma.is_empty());
+        assert_eq!(ewma.ewma(), None);
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        // JSON has no infinities, so an empty accumulator must not need them
+        let empty = OnlineStats::new();
+        let json = serde_json::to_string(&empty).unwrap();
+        let decoded: OnlineStats = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, empty);
+        assert_eq!((decoded.min(), decoded.max()), (None, None));
+
+        let stats: OnlineStats = [4.0, -2.5, 9.0].into_iter().collect();
+        let decoded: OnlineStats = serde_json::from_str(&serde_json::to_string(&stats).unwrap()).unwrap();
+        assert_eq!(decoded, stats);
+        assert_eq!((decoded.min(), decoded.max()), (Some(-2.5), Some(9.0)));
+    }
+}
@@ -0,0 +1,416 @@
+use crate::{DataStructureError, Result, RingBuffer};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How to combine the values in a window or resampling bucket
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Aggregation {
+    Mean,
+    Min,
+    Max,
+    Sum,
+    /// Percentile in `0.0..=100.0`, interpolated between the closest ranks
+    Percentile(f64),
+}
+
+impl Aggregation {
+    /// Apply to a set of values; `None` if there are none
+    fn apply(self, mut values: Vec<f64>) -> Option<f64> {
+        if values.is_empty() {
+            return None;
+        }
+        let result = match self {
+            Aggregation::Mean => values.iter().sum::<f64>() / values.len() as f64,
+            Aggregation::Min => values.iter().copied().fold(f64::INFINITY, f64::min),
+            Aggregation::Max => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            Aggregation::Sum => values.iter().sum(),
+            Aggregation::Percentile(percentile) => {
+                values.sort_by(f64::total_cmp);
+                let rank = percentile.clamp(0.0, 100.0) / 100.0 * (values.len() - 1) as f64;
+                let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+                values[lower] + (values[upper] - values[lower]) * (rank - lower as f64)
+            }
+        };
+        Some(result)
+    }
+}
+
+/// Timestamped value in a `TimeSeries`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sample<T> {
+    /// Wall-clock time of the sample, serialized as Unix epoch milliseconds
+    #[serde(with = "epoch_millis")]
+    pub timestamp: SystemTime,
+    pub value: T,
+}
+
+/// Bounded series of samples in timestamp order
+///
+/// Keeps at most `capacity` samples, dropping the oldest first, and
+/// optionally drops samples older than a retention period. Timestamps are
+/// wall-clock `SystemTime`s rather than `Instant`s so a series can be
+/// persisted and restored across restarts; they are stored on the wire as
+/// epoch milliseconds, truncating any sub-millisecond part.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "TimeSeriesData<T>")]
+#[serde(bound(deserialize = "T: Deserialize<'de>"))]
+pub struct TimeSeries<T> {
+    samples: RingBuffer<Sample<T>>,
+    retention: Option<Duration>,
+}
+
+/// Unvalidated wire form of a `TimeSeries`
+#[derive(Deserialize)]
+struct TimeSeriesData<T> {
+    samples: RingBuffer<Sample<T>>,
+    retention: Option<Duration>,
+}
+
+impl<T> TryFrom<TimeSeriesData<T>> for TimeSeries<T> {
+    type Error = DataStructureError;
+
+    fn try_from(data: TimeSeriesData<T>) -> std::result::Result<Self, Self::Error> {
+        let ordered = data
+            .samples
+            .iter()
+            .zip(data.samples.iter().skip(1))
+            .all(|(earlier, later)| earlier.timestamp <= later.timestamp);
+        if !ordered {
+            return Err(DataStructureError::Invalid(
+                "Time series samples are out of order".to_string(),
+            ));
+        }
+        Ok(Self {
+            samples: data.samples,
+            retention: data.retention,
+        })
+    }
+}
+
+impl<T> TimeSeries<T> {
+    /// Create a series holding up to `capacity` samples
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: RingBuffer::new(capacity),
+            retention: None,
+        }
+    }
+
+    /// Also drop samples older than `retention` relative to the newest sample
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = Some(retention);
+        self
+    }
+
+    /// Record a value at the current time
+    ///
+    /// If the wall clock stepped backwards the sample takes the latest
+    /// timestamp instead, keeping the series ordered.
+    pub fn push(&mut self, value: T) {
+        let now = SystemTime::now();
+        let timestamp = match self.latest() {
+            Some(latest) if latest.timestamp > now => latest.timestamp,
+            _ => now,
+        };
+        self.insert(timestamp, value);
+    }
+
+    /// Record a value at `timestamp`, which must not precede the latest sample
+    pub fn push_at(&mut self, timestamp: SystemTime, value: T) -> Result<()> {
+        if let Some(latest) = self.latest() {
+            if timestamp < latest.timestamp {
+                return Err(DataStructureError::Invalid(
+                    "Sample is older than the latest sample".to_string(),
+                ));
+            }
+        }
+        self.insert(timestamp, value);
+        Ok(())
+    }
+
+    fn insert(&mut self, timestamp: SystemTime, value: T) {
+        self.samples.push(Sample { timestamp, value });
+        if let Some(cutoff) = self.retention.and_then(|r| timestamp.checked_sub(r)) {
+            self.prune_before(cutoff);
+        }
+    }
+
+    /// Drop samples taken before `cutoff`
+    pub fn prune_before(&mut self, cutoff: SystemTime) {
+        while self
+            .samples
+            .front()
+            .is_some_and(|sample| sample.timestamp < cutoff)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Newest sample
+    pub fn latest(&self) -> Option<&Sample<T>> {
+        self.samples.back()
+    }
+
+    /// Samples from oldest to newest
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Sample<T>> + ExactSizeIterator {
+        self.samples.iter()
+    }
+
+    /// Samples taken at or after `start`
+    pub fn since(&self, start: SystemTime) -> impl Iterator<Item = &Sample<T>> {
+        self.samples
+            .iter()
+            .skip_while(move |sample| sample.timestamp < start)
+    }
+
+    /// Number of samples held
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Whether the series is empty
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Maximum number of samples held
+    pub fn capacity(&self) -> usize {
+        self.samples.capacity()
+    }
+
+    /// Retention period, if any
+    pub fn retention(&self) -> Option<Duration> {
+        self.retention
+    }
+
+    /// Remove all samples
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
+
+impl<T: Clone + Into<f64>> TimeSeries<T> {
+    /// Values of the samples within `window` of the newest sample
+    ///
+    /// Windows end at the newest sample rather than the current time, so a
+    /// restored or idle series still aggregates its last recorded window.
+    fn window_values(&self, window: Duration) -> Vec<f64> {
+        let Some(latest) = self.latest() else {
+            return Vec::new();
+        };
+        let start = latest.timestamp.checked_sub(window).unwrap_or(UNIX_EPOCH);
+        self.since(start)
+            .map(|sample| sample.value.clone().into())
+            .collect()
+    }
+
+    /// Aggregate the samples within `window` of the newest sample
+    pub fn aggregate(&self, window: Duration, aggregation: Aggregation) -> Option<f64> {
+        aggregation.apply(self.window_values(window))
+    }
+
+    /// Mean over the window
+    pub fn mean(&self, window: Duration) -> Option<f64> {
+        self.aggregate(window, Aggregation::Mean)
+    }
+
+    /// Smallest value in the window
+    pub fn min(&self, window: Duration) -> Option<f64> {
+        self.aggregate(window, Aggregation::Min)
+    }
+
+    /// Largest value in the window
+    pub fn max(&self, window: Duration) -> Option<f64> {
+        self.aggregate(window, Aggregation::Max)
+    }
+
+    /// Sum over the window; zero if it is empty
+    pub fn sum(&self, window: Duration) -> f64 {
+        self.aggregate(window, Aggregation::Sum).unwrap_or(0.0)
+    }
+
+    /// Percentile (`0.0..=100.0`) over the window
+    pub fn percentile(&self, window: Duration, percentile: f64) -> Option<f64> {
+        self.aggregate(window, Aggregation::Percentile(percentile))
+    }
+
+    /// Downsample into one sample per `interval`
+    ///
+    /// Buckets are aligned to the Unix epoch and each output sample is
+    /// stamped with its bucket's start; empty buckets produce no sample.
+    /// The result keeps this series' retention.
+    pub fn resample(
+        &self,
+        interval: Duration,
+        aggregation: Aggregation,
+    ) -> Result<TimeSeries<f64>> {
+        if interval.is_zero() {
+            return Err(DataStructureError::Invalid(
+                "Resampling interval must be non-zero".to_string(),
+            ));
+        }
+        let bucket_of = |timestamp: SystemTime| {
+            let since_epoch = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+            since_epoch.as_nanos() / interval.as_nanos()
+        };
+
+        let mut buckets: Vec<(u128, Vec<f64>)> = Vec::new();
+        for sample in self.iter() {
+            let bucket = bucket_of(sample.timestamp);
+            let value = sample.value.clone().into();
+            match buckets.last_mut() {
+                Some((current, values)) if *current == bucket => values.push(value),
+                _ => buckets.push((bucket, vec![value])),
+            }
+        }
+
+        let mut resampled = TimeSeries::new(buckets.len());
+        resampled.retention = self.retention;
+        for (bucket, values) in buckets {
+            let offset = interval.as_nanos() * bucket;
+            let start = u64::try_from(offset)
+                .ok()
+                .and_then(|nanos| UNIX_EPOCH.checked_add(Duration::from_nanos(nanos)))
+                .ok_or_else(|| {
+                    DataStructureError::Invalid("Bucket start is out of range".to_string())
+                })?;
+            if let Some(value) = aggregation.apply(values) {
+                resampled.insert(start, value);
+            }
+        }
+        Ok(resampled)
+    }
+}
+
+/// `SystemTime` as milliseconds since the Unix epoch
+mod epoch_millis {
+    use serde::{de, ser, Deserialize, Deserializer, Serializer};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    pub fn serialize<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+        let since_epoch = time
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| ser::Error::custom("timestamp is before the Unix epoch"))?;
+        let millis = u64::try_from(since_epoch.as_millis())
+            .map_err(|_| ser::Error::custom("timestamp is out of range"))?;
+        serializer.serialize_u64(millis)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SystemTime, D::Error> {
+        let millis = u64::deserialize(deserializer)?;
+        UNIX_EPOCH
+            .checked_add(Duration::from_millis(millis))
+            .ok_or_else(|| de::Error::custom("timestamp is out of range"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(millis: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn test_ordering_capacity_and_retention() {
+        let mut series = TimeSeries::new(3).with_retention(Duration::from_secs(10));
+        series.push_at(at(1_000), 1).unwrap();
+        series.push_at(at(2_000), 2).unwrap();
+        assert!(series.push_at(at(1_500), 9).is_err());
+
+        series.push_at(at(3_000), 3).unwrap();
+        series.push_at(at(4_000), 4).unwrap();
+        let values: Vec<i32> = series.iter().map(|s| s.value).collect();
+        assert_eq!(values, vec![2, 3, 4]);
+        assert_eq!(series.since(at(3_000)).count(), 2);
+
+        // Only the new sample is within 10s of itself
+        series.push_at(at(20_000), 5).unwrap();
+        assert_eq!(series.len(), 1);
+        assert_eq!(series.latest().map(|s| s.value), Some(5));
+
+        series.push(6);
+        assert_eq!(series.len(), 1);
+        series.clear();
+        assert!(series.is_empty());
+    }
+
+    #[test]
+    fn test_serde_uses_epoch_millis() {
+        let mut series = TimeSeries::new(8);
+        series.push_at(at(1_700_000_000_123), 0.5f64).unwrap();
+        series.push_at(at(1_700_000_001_000), 0.75).unwrap();
+
+        let json = serde_json::to_value(&series).unwrap();
+        assert_eq!(
+            json["samples"]["items"][0]["timestamp"],
+            serde_json::json!(1_700_000_000_123u64)
+        );
+        let restored: TimeSeries<f64> = serde_json::from_value(json).unwrap();
+        assert_eq!(restored, series);
+
+        let invalid = serde_json::json!({
+            "samples": {
+                "capacity": 2,
+                "items": [{"timestamp": 2, "value": 1.0}, {"timestamp": 1, "value": 2.0}]
+            },
+            "retention": null
+        });
+        assert!(serde_json::from_value::<TimeSeries<f64>>(invalid).is_err());
+    }
+
+    #[test]
+    fn test_windowed_aggregations() {
+        let mut series = TimeSeries::new(16);
+        assert_eq!(series.mean(Duration::from_secs(1)), None);
+        assert_eq!(series.sum(Duration::from_secs(1)), 0.0);
+
+        for (second, latency) in [(0u64, 40u32), (1, 10), (2, 30), (3, 20), (4, 50)] {
+            series.push_at(at(second * 1_000), latency).unwrap();
+        }
+        // Window of 2s before the newest sample covers seconds 2..=4
+        let window = Duration::from_secs(2);
+        assert_eq!(series.mean(window), Some(100.0 / 3.0));
+        assert_eq!(series.min(window), Some(20.0));
+        assert_eq!(series.max(window), Some(50.0));
+        assert_eq!(series.sum(window), 100.0);
+
+        let all = Duration::from_secs(60);
+        assert_eq!(series.percentile(all, 50.0), Some(30.0));
+        assert_eq!(series.percentile(all, 100.0), Some(50.0));
+        assert_eq!(series.percentile(all, 0.0), Some(10.0));
+        assert_eq!(series.percentile(all, 25.0), Some(20.0));
+        assert_eq!(series.percentile(all, 90.0), Some(46.0));
+    }
+
+    #[test]
+    fn test_resample_buckets_by_interval() {
+        let mut series = TimeSeries::new(16).with_retention(Duration::from_secs(3600));
+        for millis in [0u64, 400, 900, 1_200, 3_100, 3_900] {
+            series.push_at(at(millis), (millis / 100) as f32).unwrap();
+        }
+
+        let resampled = series
+            .resample(Duration::from_secs(1), Aggregation::Sum)
+            .unwrap();
+        let buckets: Vec<(SystemTime, f64)> = resampled
+            .iter()
+            .map(|sample| (sample.timestamp, sample.value))
+            .collect();
+        assert_eq!(
+            buckets,
+            vec![(at(0), 13.0), (at(1_000), 12.0), (at(3_000), 70.0)]
+        );
+        assert_eq!(resampled.retention(), series.retention());
+
+        let max = series
+            .resample(Duration::from_secs(2), Aggregation::Max)
+            .unwrap();
+        assert_eq!(
+            max.iter().map(|s| s.value).collect::<Vec<_>>(),
+            vec![12.0, 39.0]
+        );
+        assert!(series.resample(Duration::ZERO, Aggregation::Mean).is_err());
+    }
+}
@@ -0,0 +1,518 @@
+use crate::Hash;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::{Identity, VartimeMultiscalarMul};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// Children per internal node; one byte of the key path picks the child
+pub const VERKLE_WIDTH: usize = 256;
+/// Halving rounds of an opening proof, log2 of the width
+const IPA_ROUNDS: usize = 8;
+
+const GENERATOR_CONTEXT: &str = "cc-chain verkle 2024-01 generators";
+const LEAF_CONTEXT: &str = "cc-chain verkle 2024-01 leaf";
+const COMMITMENT_CONTEXT: &str = "cc-chain verkle 2024-01 commitment";
+const TRANSCRIPT_CONTEXT: &str = "cc-chain verkle 2024-01 opening";
+
+/// Experimental verkle tree over a 256-bit keyspace
+///
+/// Each internal node commits to its 256 children with a Pedersen vector
+/// commitment over Ristretto, `C = sum(s_i * G_i)`, where `s_i` is a scalar
+/// derived from child `i` (zero if empty). Keys are placed at the path
+/// `blake3(key)` and single-leaf subtrees are collapsed, as in
+/// `SparseMerkleTree`; the root node is always an internal node so the root
+/// is its compressed commitment, all zeros when empty.
+///
+/// Updating a child only adds `(new - old) * G_i` to its parent, so inserts
+/// cost one scalar multiplication per level. Proofs carry one inner product
+/// argument per level of 2 * 8 points plus a scalar, independent of width;
+/// openings are not aggregated across levels. This is a research prototype
+/// for comparing proof sizes against the Merkle structures: it supports
+/// insertion, lookup and inclusion proofs only.
+pub struct VerkleTree {
+    root: Internal,
+    len: usize,
+}
+
+enum Node {
+    Empty,
+    Leaf { path: Hash, value: Vec<u8> },
+    Internal(Box<Internal>),
+}
+
+struct Internal {
+    children: Vec<Node>,
+    /// Committed scalar of every child, kept for incremental updates and openings
+    scalars: Vec<Scalar>,
+    commitment: RistrettoPoint,
+}
+
+/// Commitment key: one generator per child slot plus one for opened values
+struct Generators {
+    slots: Vec<RistrettoPoint>,
+    value: RistrettoPoint,
+}
+
+/// Generators derived by hashing to the curve, so nobody knows their discrete logs
+fn generators() -> &'static Generators {
+    static GENERATORS: OnceLock<Generators> = OnceLock::new();
+    GENERATORS.get_or_init(|| {
+        let point = |index: u64| {
+            let mut bytes = [0u8; 64];
+            let mut hasher = blake3::Hasher::new_derive_key(GENERATOR_CONTEXT);
+            hasher.update(&index.to_le_bytes());
+            hasher.finalize_xof().fill(&mut bytes);
+            RistrettoPoint::from_uniform_bytes(&bytes)
+        };
+        Generators {
+            slots: (0..VERKLE_WIDTH as u64).map(point).collect(),
+            value: point(VERKLE_WIDTH as u64),
+        }
+    })
+}
+
+fn hash_to_scalar(context: &str, parts: &[&[u8]]) -> Scalar {
+    let mut hasher = blake3::Hasher::new_derive_key(context);
+    for part in parts {
+        hasher.update(part);
+    }
+    let mut bytes = [0u8; 64];
+    hasher.finalize_xof().fill(&mut bytes);
+    Scalar::from_bytes_mod_order_wide(&bytes)
+}
+
+fn leaf_scalar(path: &Hash, value: &[u8]) -> Scalar {
+    hash_to_scalar(LEAF_CONTEXT, &[path, value])
+}
+
+fn commitment_scalar(commitment: &Hash) -> Scalar {
+    hash_to_scalar(COMMITMENT_CONTEXT, &[commitment])
+}
+
+impl Node {
+    /// Scalar this node contributes to its parent's commitment
+    fn scalar(&self) -> Scalar {
+        match self {
+            Node::Empty => Scalar::ZERO,
+            Node::Leaf { path, value } => leaf_scalar(path, value),
+            Node::Internal(internal) => commitment_scalar(&internal.commitment_bytes()),
+        }
+    }
+}
+
+impl Internal {
+    fn new() -> Self {
+        Self {
+            children: (0..VERKLE_WIDTH).map(|_| Node::Empty).collect(),
+            scalars: vec![Scalar::ZERO; VERKLE_WIDTH],
+            commitment: RistrettoPoint::identity(),
+        }
+    }
+
+    fn commitment_bytes(&self) -> Hash {
+        self.commitment.compress().to_bytes()
+    }
+
+    /// Recommit child `index` after it changed
+    fn refresh(&mut self, index: usize) {
+        let scalar = self.children[index].scalar();
+        self.commitment += generators().slots[index] * (scalar - self.scalars[index]);
+        self.scalars[index] = scalar;
+    }
+
+    fn insert(&mut self, depth: usize, path: &Hash, value: Vec<u8>) -> Option<Vec<u8>> {
+        let index = path[depth] as usize;
+        let child = &mut self.children[index];
+        let previous = match child {
+            Node::Empty => {
+                *child = Node::Leaf { path: *path, value };
+                None
+            }
+            Node::Leaf {
+                path: existing,
+                value: old,
+            } if existing == path => Some(std::mem::replace(old, value)),
+            Node::Leaf { .. } => {
+                // Push the existing leaf one level down; distinct paths
+                // always diverge before the last byte
+                let existing = std::mem::replace(child, Node::Empty);
+                let mut split = Internal::new();
+                if let Node::Leaf { path: other, .. } = &existing {
+                    let slot = other[depth + 1] as usize;
+                    split.children[slot] = existing;
+                    split.refresh(slot);
+                }
+                let previous = split.insert(depth + 1, path, value);
+                *child = Node::Internal(Box::new(split));
+                previous
+            }
+            Node::Internal(internal) => internal.insert(depth + 1, path, value),
+        };
+        self.refresh(index);
+        previous
+    }
+}
+
+impl Default for VerkleTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VerkleTree {
+    /// Create an empty tree
+    pub fn new() -> Self {
+        Self {
+            root: Internal::new(),
+            len: 0,
+        }
+    }
+
+    /// Compressed root commitment; all zeros for an empty tree
+    pub fn root(&self) -> Hash {
+        self.root.commitment_bytes()
+    }
+
+    /// Number of keys
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the tree has no keys
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Value stored under a key
+    pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
+        let path = key_path(key);
+        let mut internal = &self.root;
+        for &byte in &path {
+            match &internal.children[byte as usize] {
+                Node::Internal(child) => internal = child,
+                Node::Leaf { path: leaf, value } if *leaf == path => return Some(value),
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    /// Insert or replace a value, returning the previous value
+    pub fn insert(&mut self, key: &[u8], value: Vec<u8>) -> Option<Vec<u8>> {
+        let previous = self.root.insert(0, &key_path(key), value);
+        if previous.is_none() {
+            self.len += 1;
+        }
+        previous
+    }
+
+    /// Inclusion proof for a key, or `None` if it is absent
+    pub fn prove(&self, key: &[u8]) -> Option<VerkleProof> {
+        let path = key_path(key);
+        let mut commitments = Vec::new();
+        let mut openings = Vec::new();
+        let mut internal = &self.root;
+        for &byte in &path {
+            let index = byte as usize;
+            openings.push(SlotOpening::create(
+                &internal.scalars,
+                &internal.commitment_bytes(),
+                index,
+            ));
+            match &internal.children[index] {
+                Node::Internal(child) => {
+                    commitments.push(child.commitment_bytes());
+                    internal = child;
+                }
+                Node::Leaf { path: leaf, .. } if *leaf == path => {
+                    return Some(VerkleProof {
+                        commitments,
+                        openings,
+                    });
+                }
+                _ => return None,
+            }
+        }
+        None
+    }
+}
+
+fn key_path(key: &[u8]) -> Hash {
+    blake3::hash(key).into()
+}
+
+/// Verkle proof that a key holds a value
+///
+/// `commitments` are the compressed commitments of the internal nodes on
+/// the key's path below the root. `openings[i]` opens the i-th commitment
+/// on the path (the root first) at the slot the key selects, to the scalar
+/// of the next commitment or, for the last one, of the leaf.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerkleProof {
+    commitments: Vec<Hash>,
+    openings: Vec<SlotOpening>,
+}
+
+impl VerkleProof {
+    /// Check that `key` holds `value` in the tree with the given root
+    pub fn verify_inclusion(&self, root: &Hash, key: &[u8], value: &[u8]) -> bool {
+        let path = key_path(key);
+        if self.openings.len() != self.commitments.len() + 1 || self.openings.len() > path.len() {
+            return false;
+        }
+        let mut commitment = *root;
+        for (depth, opening) in self.openings.iter().enumerate() {
+            let (opened, next) = match self.commitments.get(depth) {
+                Some(child) => (commitment_scalar(child), *child),
+                None => (leaf_scalar(&path, value), commitment),
+            };
+            if !opening.verify(&commitment, path[depth] as usize, &opened) {
+                return false;
+            }
+            commitment = next;
+        }
+        true
+    }
+
+    /// Encoded size: 32 bytes per commitment, point and scalar
+    pub fn size_in_bytes(&self) -> usize {
+        let opening = (2 * IPA_ROUNDS + 1) * 32;
+        self.commitments.len() * 32 + self.openings.len() * opening
+    }
+}
+
+/// Inner product argument opening one slot of a vector commitment
+///
+/// Proves that commitment `C = <a, G>` has `a_i = v` by running the
+/// argument for `<a, e_i> = v`, halving the vectors each round and sending
+/// the two cross terms. No blinding is used, as committed values are public.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SlotOpening {
+    left: Vec<Hash>,
+    right: Vec<Hash>,
+    scalar: [u8; 32],
+}
+
+/// Fiat-Shamir transcript for one opening
+struct Transcript(blake3::Hasher);
+
+impl Transcript {
+    fn new(commitment: &Hash, index: usize, value: &Scalar) -> Self {
+        let mut hasher = blake3::Hasher::new_derive_key(TRANSCRIPT_CONTEXT);
+        hasher.update(commitment);
+        hasher.update(&(index as u64).to_le_bytes());
+        hasher.update(value.as_bytes());
+        Self(hasher)
+    }
+
+    fn append(&mut self, point: &Hash) {
+        self.0.update(point);
+    }
+
+    fn challenge(&mut self) -> Scalar {
+        let mut bytes = [0u8; 64];
+        self.0.finalize_xof().fill(&mut bytes);
+        // Later challenges depend on earlier ones
+        self.0.update(&bytes);
+        Scalar::from_bytes_mod_order_wide(&bytes)
+    }
+}
+
+fn inner_product(a: &[Scalar], b: &[Scalar]) -> Scalar {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+impl SlotOpening {
+    fn create(values: &[Scalar], commitment: &Hash, index: usize) -> Self {
+        let generators = generators();
+        let mut transcript = Transcript::new(commitment, index, &values[index]);
+        let q = generators.value * transcript.challenge();
+
+        let mut a = values.to_vec();
+        let mut b: Vec<Scalar> = (0..VERKLE_WIDTH)
+            .map(|i| {
+                if i == index {
+                    Scalar::ONE
+                } else {
+                    Scalar::ZERO
+                }
+            })
+            .collect();
+        let mut g = generators.slots.clone();
+        let mut left = Vec::with_capacity(IPA_ROUNDS);
+        let mut right = Vec::with_capacity(IPA_ROUNDS);
+
+        while a.len() > 1 {
+            let half = a.len() / 2;
+            let (a_lo, a_hi) = a.split_at(half);
+            let (b_lo, b_hi) = b.split_at(half);
+            let (g_lo, g_hi) = g.split_at(half);
+
+            let l = RistrettoPoint::vartime_multiscalar_mul(
+                a_lo.iter().chain([&inner_product(a_lo, b_hi)]),
+                g_hi.iter().chain([&q]),
+            )
+            .compress()
+            .to_bytes();
+            let r = RistrettoPoint::vartime_multiscalar_mul(
+                a_hi.iter().chain([&inner_product(a_hi, b_lo)]),
+                g_lo.iter().chain([&q]),
+            )
+            .compress()
+            .to_bytes();
+            transcript.append(&l);
+            transcript.append(&r);
+            let x = transcript.challenge();
+            let x_inv = x.invert();
+
+            a = (0..half).map(|i| a_lo[i] + x * a_hi[i]).collect();
+            b = (0..half).map(|i| b_lo[i] + x_inv * b_hi[i]).collect();
+            g = (0..half).map(|i| g_lo[i] + g_hi[i] * x_inv).collect();
+            left.push(l);
+            right.push(r);
+        }
+
+        Self {
+            left,
+            right,
+            scalar: a[0].to_bytes(),
+        }
+    }
+
+    fn verify(&self, commitment: &Hash, index: usize, value: &Scalar) -> bool {
+        if self.left.len() != IPA_ROUNDS || self.right.len() != IPA_ROUNDS {
+            return false;
+        }
+        let Some(point) = CompressedRistretto(*commitment).decompress() else {
+            return false;
+        };
+        let Some(a) = Option::<Scalar>::from(Scalar::from_canonical_bytes(self.scalar)) else {
+            return false;
+        };
+        let generators = generators();
+        let mut transcript = Transcript::new(commitment, index, value);
+        let q = generators.value * transcript.challenge();
+
+        // P = C + v * Q, folded by P' = P + x^-1 * L + x * R each round
+        let mut p = point + q * value;
+        let mut inverses = Vec::with_capacity(IPA_ROUNDS);
+        for (l, r) in self.left.iter().zip(&self.right) {
+            let (Some(l_point), Some(r_point)) = (
+                CompressedRistretto(*l).decompress(),
+                CompressedRistretto(*r).decompress(),
+            ) else {
+                return false;
+            };
+            transcript.append(l);
+            transcript.append(r);
+            let x = transcript.challenge();
+            let x_inv = x.invert();
+            p += l_point * x_inv + r_point * x;
+            inverses.push(x_inv);
+        }
+
+        // The folded generator and unit vector weight slot i by x_j^-1 for
+        // every round j that put i in the upper half
+        let weights: Vec<Scalar> = (0..VERKLE_WIDTH)
+            .map(|i| {
+                inverses
+                    .iter()
+                    .enumerate()
+                    .filter(|(round, _)| (i >> (IPA_ROUNDS - 1 - round)) & 1 == 1)
+                    .map(|(_, x_inv)| x_inv)
+                    .product()
+            })
+            .collect();
+        let g = RistrettoPoint::vartime_multiscalar_mul(&weights, &generators.slots);
+        p == g * a + q * (a * weights[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(i: u32) -> Vec<u8> {
+        format!("account-{}", i).into_bytes()
+    }
+
+    #[test]
+    fn test_root_is_canonical() {
+        let mut forward = VerkleTree::new();
+        assert_eq!(forward.root(), [0u8; 32]);
+        for i in 0..300 {
+            assert_eq!(forward.insert(&key(i), i.to_le_bytes().to_vec()), None);
+        }
+        let mut backward = VerkleTree::new();
+        for i in (0..300).rev() {
+            backward.insert(&key(i), i.to_le_bytes().to_vec());
+        }
+        assert_eq!(forward.root(), backward.root());
+        assert_eq!(forward.len(), 300);
+        assert_eq!(forward.get(&key(42)), Some(&42u32.to_le_bytes()[..]));
+        assert_eq!(forward.get(&key(300)), None);
+
+        let before = forward.root();
+        assert_eq!(
+            forward.insert(&key(7), vec![1]),
+            Some(7u32.to_le_bytes().to_vec())
+        );
+        assert_ne!(forward.root(), before);
+        forward.insert(&key(7), 7u32.to_le_bytes().to_vec());
+        assert_eq!(forward.root(), before);
+    }
+
+    #[test]
+    fn test_inclusion_proofs() {
+        let mut tree = VerkleTree::new();
+        for i in 0..300 {
+            tree.insert(&key(i), vec![i as u8]);
+        }
+        let root = tree.root();
+
+        for i in [0, 123, 299] {
+            let proof = tree.prove(&key(i)).unwrap();
+            assert!(proof.verify_inclusion(&root, &key(i), &[i as u8]));
+            assert!(!proof.verify_inclusion(&root, &key(i), &[i as u8 + 1]));
+            assert!(!proof.verify_inclusion(&root, &key(i + 1), &[i as u8]));
+            assert_eq!(
+                proof.size_in_bytes(),
+                proof.commitments.len() * 32 + proof.openings.len() * 544
+            );
+        }
+        assert!(tree.prove(&key(300)).is_none());
+
+        let proof = tree.prove(&key(5)).unwrap();
+        tree.insert(&key(5), vec![0]);
+        assert!(!proof.verify_inclusion(&tree.root(), &key(5), &[5]));
+    }
+
+    #[test]
+    fn test_tampered_proofs_fail() {
+        let mut tree = VerkleTree::new();
+        tree.insert(b"alice", b"100".to_vec());
+        tree.insert(b"bob", b"50".to_vec());
+        let root = tree.root();
+        let proof = tree.prove(b"alice").unwrap();
+
+        let wire = serde_json::to_string(&proof).unwrap();
+        let received: VerkleProof = serde_json::from_str(&wire).unwrap();
+        assert!(received.verify_inclusion(&root, b"alice", b"100"));
+
+        let mut swapped = proof.clone();
+        let opening = &mut swapped.openings[0];
+        std::mem::swap(&mut opening.left, &mut opening.right);
+        assert!(!swapped.verify_inclusion(&root, b"alice", b"100"));
+
+        let mut scaled = proof.clone();
+        scaled.openings[0].scalar =
+            (Scalar::from_canonical_bytes(proof.openings[0].scalar).unwrap() + Scalar::ONE)
+                .to_bytes();
+        assert!(!scaled.verify_inclusion(&root, b"alice", b"100"));
+
+        let mut truncated = proof;
+        truncated.openings[0].left.pop();
+        assert!(!truncated.verify_inclusion(&root, b"alice", b"100"));
+    }
+}
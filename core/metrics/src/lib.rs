@@ -1,2 +1,107 @@
 //! Core metrics functionality
+//!
+//! Lightweight metric primitives shared between core components and the
+//! monitoring/export layers. Components expose their counters by implementing
+//! [`MetricsSource`]; exporters (such as the RPC monitor) collect samples from
+//! registered sources without depending on the component crates directly.
 
+use serde::{Deserialize, Serialize};
+
+/// Kind of a metric sample, mirroring the Prometheus metric types
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MetricKind {
+    /// Monotonically increasing value
+    Counter,
+    /// Value that can go up and down
+    Gauge,
+}
+
+impl MetricKind {
+    /// Prometheus type name for this kind
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetricKind::Counter => "counter",
+            MetricKind::Gauge => "gauge",
+        }
+    }
+}
+
+/// A single metric observation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricSample {
+    pub name: String,
+    pub help: String,
+    pub kind: MetricKind,
+    pub value: f64,
+    pub labels: Vec<(String, String)>,
+}
+
+impl MetricSample {
+    /// Create a counter sample
+    pub fn counter(name: impl Into<String>, help: impl Into<String>, value: f64) -> Self {
+        Self {
+            name: name.into(),
+            help: help.into(),
+            kind: MetricKind::Counter,
+            value,
+            labels: Vec::new(),
+        }
+    }
+
+    /// Create a gauge sample
+    pub fn gauge(name: impl Into<String>, help: impl Into<String>, value: f64) -> Self {
+        Self {
+            name: name.into(),
+            help: help.into(),
+            kind: MetricKind::Gauge,
+            value,
+            labels: Vec::new(),
+        }
+    }
+
+    /// Attach a label to the sample
+    pub fn with_label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.labels.push((key.into(), value.into()));
+        self
+    }
+}
+
+/// A component that can report its current metrics
+pub trait MetricsSource: Send + Sync {
+    /// Name of the component reporting the metrics
+    fn source_name(&self) -> &str;
+
+    /// Collect a snapshot of the component's metrics
+    fn collect(&self) -> Vec<MetricSample>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedSource;
+
+    impl MetricsSource for FixedSource {
+        fn source_name(&self) -> &str {
+            "fixed"
+        }
+
+        fn collect(&self) -> Vec<MetricSample> {
+            vec![
+                MetricSample::counter("fixed_total", "Fixed counter", 3.0),
+                MetricSample::gauge("fixed_ratio", "Fixed gauge", 0.5).with_label("shard", "0"),
+            ]
+        }
+    }
+
+    #[test]
+    fn test_metrics_source_collect() {
+        let source = FixedSource;
+        let samples = source.collect();
+
+        assert_eq!(source.source_name(), "fixed");
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].kind.as_str(), "counter");
+        assert_eq!(samples[1].labels, vec![("shard".to_string(), "0".to_string())]);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::error::Result;
 use blake3::Hasher;
+use cc_core_data_structures::{internal_hash, leaf_hash};
 use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -101,85 +102,171 @@ pub fn hash_multiple(data_pieces: &[&[u8]]) -> Hash {
 }
 
 /// Merkle tree implementation for efficient batch verification
+///
+/// Every level is kept after construction, so audit paths can be extracted
+/// for any leaf. How nodes are hashed is set by the tree's `MerkleFormat`;
+/// `build` uses `MerkleFormat::V1`, which block, transaction and state roots
+/// are computed with.
 pub struct MerkleTree {
-    nodes: Vec<Hash>,
+    format: MerkleFormat,
+    /// `levels[0]` holds the (hashed, for V2) leaves, the last level holds the root
+    levels: Vec<Vec<Hash>>,
     leaf_count: usize,
 }
 
+/// How a `MerkleTree` hashes its nodes
+///
+/// The format is part of what a root commits to: the same leaves give
+/// different roots under different formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MerkleFormat {
+    /// Leaves are used as-is, nodes are `hash(left || right)` and a node
+    /// without a right sibling is paired with itself
+    #[default]
+    V1,
+    /// Leaves and internal nodes are hashed with the distinct domain
+    /// prefixes of `cc-core-data_structures`, so an internal node cannot
+    /// pass as a leaf, and a node without a right sibling is paired with
+    /// the all-zero hash
+    V2,
+}
+
+impl MerkleFormat {
+    fn leaf(self, leaf: &Hash) -> Hash {
+        match self {
+            MerkleFormat::V1 => *leaf,
+            MerkleFormat::V2 => leaf_hash(&[leaf]),
+        }
+    }
+
+    fn node(self, left: &Hash, right: &Hash) -> Hash {
+        match self {
+            MerkleFormat::V1 => hash_multiple(&[left, right]),
+            MerkleFormat::V2 => internal_hash(left, right),
+        }
+    }
+
+    /// Right sibling of the last node of an odd level
+    fn padding(self, node: &Hash) -> Hash {
+        match self {
+            MerkleFormat::V1 => *node,
+            MerkleFormat::V2 => [0u8; 32],
+        }
+    }
+
+    /// Verify an audit path from `MerkleTree::proof` against a root
+    pub fn verify(self, root: &Hash, leaf: &Hash, proof: &[(Hash, Side)]) -> bool {
+        let computed = proof.iter().fold(self.leaf(leaf), |current, (sibling, side)| match side {
+            Side::Left => self.node(sibling, &current),
+            Side::Right => self.node(&current, sibling),
+        });
+        computed == *root
+    }
+}
+
+/// Side of the path a sibling hash sits on in a merkle audit path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Side {
+    /// Sibling is the left input: `node(sibling, current)`
+    Left,
+    /// Sibling is the right input: `node(current, sibling)`
+    Right,
+}
+
 impl MerkleTree {
-    /// Build a merkle tree from leaves
+    /// Build a merkle tree from leaves in the `V1` format
     pub fn build(leaves: &[Hash]) -> Self {
+        Self::build_with_format(leaves, MerkleFormat::V1)
+    }
+
+    /// Build a merkle tree from leaves in the given format
+    pub fn build_with_format(leaves: &[Hash], format: MerkleFormat) -> Self {
         if leaves.is_empty() {
             return Self {
-                nodes: vec![[0u8; 32]],
+                format,
+                levels: vec![vec![[0u8; 32]]],
                 leaf_count: 0,
             };
         }
 
-        let leaf_count = leaves.len();
-        let mut nodes = Vec::with_capacity(2 * leaf_count);
-
-        // Add leaves
-        nodes.extend_from_slice(leaves);
-
-        // Build internal nodes
-        let mut level_size = leaf_count;
-        while level_size > 1 {
-            let next_level_size = (level_size + 1) / 2;
-            let level_start = nodes.len() - level_size;
-
-            for i in 0..next_level_size {
-                let left_idx = level_start + 2 * i;
-                let right_idx = std::cmp::min(left_idx + 1, level_start + level_size - 1);
-
-                let combined = hash_multiple(&[&nodes[left_idx], &nodes[right_idx]]);
-                nodes.push(combined);
-            }
-
-            level_size = next_level_size;
+        let mut levels = vec![leaves.iter().map(|leaf| format.leaf(leaf)).collect::<Vec<_>>()];
+        while levels[levels.len() - 1].len() > 1 {
+            let next_level = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| {
+                    let right = pair.get(1).copied().unwrap_or_else(|| format.padding(&pair[0]));
+                    format.node(&pair[0], &right)
+                })
+                .collect();
+            levels.push(next_level);
         }
 
-        Self { nodes, leaf_count }
+        Self {
+            format,
+            levels,
+            leaf_count: leaves.len(),
+        }
     }
 
     /// Get the merkle root
     pub fn root(&self) -> Hash {
-        if self.nodes.is_empty() {
-            [0u8; 32]
-        } else {
-            *self.nodes.last().unwrap()
-        }
+        self.levels
+            .last()
+            .and_then(|level| level.first())
+            .copied()
+            .unwrap_or([0u8; 32])
+    }
+
+    /// Format the tree was built in
+    pub fn format(&self) -> MerkleFormat {
+        self.format
     }
 
-    /// Generate merkle proof for a leaf
-    pub fn proof(&self, leaf_index: usize) -> Option<Vec<Hash>> {
+    /// Number of leaves
+    pub fn leaf_count(&self) -> usize {
+        self.leaf_count
+    }
+
+    /// Audit path for a leaf: sibling hashes from the leaf level up, each
+    /// with the side it is hashed on
+    pub fn proof(&self, leaf_index: usize) -> Option<Vec<(Hash, Side)>> {
         if leaf_index >= self.leaf_count {
             return None;
         }
 
-        let mut proof = Vec::new();
-        let mut current_idx = leaf_index;
-        let mut level_start = 0;
-        let mut level_size = self.leaf_count;
-
-        while level_size > 1 {
-            let sibling_idx = if current_idx % 2 == 0 {
-                std::cmp::min(current_idx + 1, level_size - 1)
+        let mut index = leaf_index;
+        let mut proof = Vec::with_capacity(self.levels.len() - 1);
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = if index.is_multiple_of(2) {
+                let right = level.get(index + 1).copied();
+                (right.unwrap_or_else(|| self.format.padding(&level[index])), Side::Right)
             } else {
-                current_idx - 1
+                (level[index - 1], Side::Left)
             };
-
-            proof.push(self.nodes[level_start + sibling_idx]);
-
-            current_idx /= 2;
-            level_start += level_size;
-            level_size = (level_size + 1) / 2;
+            proof.push(sibling);
+            index /= 2;
         }
 
         Some(proof)
     }
 
-    /// Verify a merkle proof
+    /// Self-contained proof for a leaf, carrying the root and format
+    pub fn merkle_proof(&self, leaf_index: usize) -> Option<MerkleProof> {
+        Some(MerkleProof {
+            format: self.format,
+            leaf_index,
+            proof: self.proof(leaf_index)?,
+            root: self.root(),
+        })
+    }
+
+    /// Verify a `V1` audit path from `proof` against a root
+    pub fn verify(root: &Hash, leaf: &Hash, proof: &[(Hash, Side)]) -> bool {
+        MerkleFormat::V1.verify(root, leaf, proof)
+    }
+
+    /// Verify a `V1` proof given as bare sibling hashes, taking sides from
+    /// the bits of `leaf_index`
     pub fn verify_proof(root: &Hash, leaf: &Hash, proof: &[Hash], leaf_index: usize) -> bool {
         let mut current_hash = *leaf;
         let mut index = leaf_index;
@@ -198,13 +285,23 @@ impl MerkleTree {
 }
 
 /// Merkle proof for efficient verification
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MerkleProof {
+    #[serde(default)]
+    pub format: MerkleFormat,
     pub leaf_index: usize,
-    pub proof: Vec<Hash>,
+    /// Audit path from `MerkleTree::proof`
+    pub proof: Vec<(Hash, Side)>,
     pub root: Hash,
 }
 
+impl MerkleProof {
+    /// Check that `leaf` is in the tree this proof was taken from
+    pub fn verify(&self, leaf: &Hash) -> bool {
+        self.format.verify(&self.root, leaf, &self.proof)
+    }
+}
+
 /// Advanced signature aggregation for batch verification
 pub struct SignatureAggregator {
     signatures: Vec<CCSignature>,
@@ -16,7 +16,7 @@ pub mod utils;
 
 // Re-export commonly used types
 pub use block::{Block, BlockHeader, Blockchain};
-pub use crypto::{CCKeypair, CCPublicKey, CCSignature, Hash, MerkleTree, MerkleProof, 
+pub use crypto::{CCKeypair, CCPublicKey, CCSignature, Hash, MerkleTree, MerkleFormat, MerkleProof, Side,
                  SignatureAggregator, QuantumResistantSignature, HashCache, 
                  parallel_hash_multiple, multi_hash, MultiHash};
 pub use error::{CCError, Result};
@@ -0,0 +1,65 @@
+use cc_core_storage::{InMemoryStorage, ShardedMemoryStorage, Storage};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::sync::Arc;
+use std::thread;
+
+const THREADS: usize = 8;
+const OPS_PER_THREAD: u32 = 2_000;
+const PRELOADED_KEYS: u32 = 10_000;
+
+fn preload(storage: &dyn Storage) {
+    for i in 0..PRELOADED_KEYS {
+        storage.put(&i.to_be_bytes(), &[0u8; 64]).unwrap();
+    }
+}
+
+/// Run `THREADS` threads that each do `OPS_PER_THREAD` operations, one write
+/// in every `write_every` operations and reads otherwise
+fn run_mixed_workload(storage: Arc<dyn Storage>, write_every: u32) {
+    let handles: Vec<_> = (0..THREADS as u32)
+        .map(|thread_id| {
+            let storage = storage.clone();
+            thread::spawn(move || {
+                for i in 0..OPS_PER_THREAD {
+                    let key = ((thread_id * OPS_PER_THREAD + i) % PRELOADED_KEYS).to_be_bytes();
+                    if i % write_every == 0 {
+                        storage.put(&key, &[1u8; 64]).unwrap();
+                    } else {
+                        black_box(storage.get(&key).unwrap());
+                    }
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
+
+fn benchmark_concurrent_access(c: &mut Criterion) {
+    let mut group = c.benchmark_group("concurrent_storage");
+
+    // Read-heavy (1 write in 10) and write-heavy (1 write in 2) workloads
+    for write_every in [10, 2] {
+        let single: Arc<dyn Storage> = Arc::new(InMemoryStorage::new());
+        preload(single.as_ref());
+        group.bench_with_input(
+            BenchmarkId::new("in_memory", write_every),
+            &write_every,
+            |b, &write_every| b.iter(|| run_mixed_workload(single.clone(), write_every)),
+        );
+
+        let sharded: Arc<dyn Storage> = Arc::new(ShardedMemoryStorage::new());
+        preload(sharded.as_ref());
+        group.bench_with_input(
+            BenchmarkId::new("sharded", write_every),
+            &write_every,
+            |b, &write_every| b.iter(|| run_mixed_workload(sharded.clone(), write_every)),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_concurrent_access);
+criterion_main!(benches);
@@ -0,0 +1,659 @@
+use crate::periodic::PeriodicTask;
+use crate::{
+    BatchOperation, BatchStorage, MetadataStorage, Result, Storage, StorageIterator,
+    StorageMetadata, StorageSnapshot, StorageStats,
+};
+use cc_core_algorithms::LRUCache;
+use cc_core_data_structures::ConcurrentLruCache;
+use cc_core_metrics::{MetricSample, MetricsSource};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Hot-entry cache statistics
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    pub capacity: usize,
+    /// Writes queued in write-behind mode that the backend has not seen yet
+    pub dirty_entries: usize,
+}
+
+impl CacheStats {
+    /// Fraction of lookups served from the cache
+    pub fn hit_rate(&self) -> f64 {
+        let requests = self.hits + self.misses;
+        if requests == 0 {
+            0.0
+        } else {
+            self.hits as f64 / requests as f64
+        }
+    }
+}
+
+/// Entry cache used by `CachedStorage`
+///
+/// Implemented for a mutex-guarded `LRUCache` (the default) and for the
+/// sharded `ConcurrentLruCache`, which scales better under many threads.
+pub trait EntryCache: Send + Sync {
+    /// Get a cached value, marking it as recently used
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>>;
+
+    /// Insert or replace a value, evicting as needed
+    fn put(&self, key: Vec<u8>, value: Vec<u8>);
+
+    /// Drop a key from the cache
+    fn remove(&self, key: &[u8]);
+
+    /// Check whether a key is cached without updating its recency
+    fn contains(&self, key: &[u8]) -> bool;
+
+    /// Number of cached entries
+    fn len(&self) -> usize;
+
+    /// Whether the cache is empty
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Maximum number of entries
+    fn capacity(&self) -> usize;
+
+    /// Drop all entries
+    fn clear(&self);
+}
+
+impl EntryCache for Mutex<LRUCache<Vec<u8>, Vec<u8>>> {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.lock().get(&key.to_vec())
+    }
+
+    fn put(&self, key: Vec<u8>, value: Vec<u8>) {
+        self.lock().put(key, value)
+    }
+
+    fn remove(&self, key: &[u8]) {
+        self.lock().remove(&key.to_vec());
+    }
+
+    fn contains(&self, key: &[u8]) -> bool {
+        self.lock().contains(&key.to_vec())
+    }
+
+    fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    fn capacity(&self) -> usize {
+        self.lock().capacity()
+    }
+
+    fn clear(&self) {
+        self.lock().clear()
+    }
+}
+
+impl EntryCache for ConcurrentLruCache<Vec<u8>, Vec<u8>> {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        ConcurrentLruCache::get(self, key)
+    }
+
+    fn put(&self, key: Vec<u8>, value: Vec<u8>) {
+        ConcurrentLruCache::put(self, key, value)
+    }
+
+    fn remove(&self, key: &[u8]) {
+        ConcurrentLruCache::remove(self, key);
+    }
+
+    fn contains(&self, key: &[u8]) -> bool {
+        ConcurrentLruCache::contains(self, key)
+    }
+
+    fn len(&self) -> usize {
+        ConcurrentLruCache::len(self)
+    }
+
+    fn capacity(&self) -> usize {
+        ConcurrentLruCache::capacity(self)
+    }
+
+    fn clear(&self) {
+        ConcurrentLruCache::clear(self)
+    }
+}
+
+/// LRU cache in front of a storage backend
+///
+/// Reads are served from the cache when possible and populate it on a miss.
+/// By default the cache is write-through: writes and deletes go to the
+/// backend first and then update the cache, so the cache never holds a value
+/// the backend has not accepted.
+///
+/// # Write-behind mode
+///
+/// With `with_write_behind`, writes and deletes are acknowledged once they
+/// are queued in a bounded dirty queue and reach the backend later, when the
+/// queue is flushed (by `flush`/`sync`, a `CacheFlusher`, a full queue, or
+/// dropping the cache). Reads, scans and batches always see queued writes.
+///
+/// Crash consistency: queued writes live only in memory, so a crash loses
+/// every write acknowledged since the last flush. Flushing writes the queue
+/// in key order rather than write order, one operation at a time, so after a
+/// crash in the middle of a flush the backend may hold any subset of the
+/// queued writes. Only use write-behind for data that can be rebuilt (e.g.
+/// indexes or caches of chain state); call `sync` before taking a backup or
+/// checkpoint, and note that `sync` only hands the writes to the backend -
+/// whether they survive a crash from there is up to the backend.
+pub struct CachedStorage<S: Storage> {
+    inner: S,
+    name: String,
+    cache: Box<dyn EntryCache>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    /// Dirty queue capacity; `None` in write-through mode
+    write_behind: Option<usize>,
+    /// Queued writes by key, `None` marking a pending delete
+    dirty: Mutex<BTreeMap<Vec<u8>, Option<Vec<u8>>>>,
+    /// Held while flushing so flushes cannot reorder writes to the backend
+    flushing: Mutex<()>,
+    /// Bumped on every cache update by a write; a read that raced with one
+    /// does not populate the cache, as its value may be stale
+    generation: Mutex<u64>,
+}
+
+impl<S: Storage> CachedStorage<S> {
+    /// Wrap a backend with a cache holding up to `capacity` entries
+    pub fn new(inner: S, capacity: usize) -> Self {
+        Self::with_cache(inner, Mutex::new(LRUCache::new(capacity)))
+    }
+
+    /// Wrap a backend with a sharded cache for highly concurrent workloads
+    pub fn concurrent(inner: S, capacity: usize) -> Self {
+        Self::with_cache(inner, ConcurrentLruCache::new(capacity))
+    }
+
+    /// Wrap a backend with the given entry cache
+    pub fn with_cache(inner: S, cache: impl EntryCache + 'static) -> Self {
+        Self {
+            inner,
+            name: "default".to_string(),
+            cache: Box::new(cache),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            write_behind: None,
+            dirty: Mutex::new(BTreeMap::new()),
+            flushing: Mutex::new(()),
+            generation: Mutex::new(0),
+        }
+    }
+
+    /// Set the cache name used to label exported metrics
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Switch to write-behind mode, queueing up to `max_dirty` writes
+    ///
+    /// A write that would grow the queue past `max_dirty` first flushes it.
+    pub fn with_write_behind(mut self, max_dirty: usize) -> Self {
+        self.write_behind = Some(max_dirty.max(1));
+        self
+    }
+
+    /// Get the wrapped backend
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Get cache statistics
+    pub fn cache_stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.cache.len(),
+            capacity: self.cache.capacity(),
+            dirty_entries: self.dirty.lock().len(),
+        }
+    }
+
+    /// Write queued writes to the backend, returning how many were written
+    ///
+    /// Returns `Ok(0)` without waiting if another flush is in progress; use
+    /// `sync` to wait for it. On error, the writes that did not reach the
+    /// backend stay queued.
+    pub fn flush(&self) -> Result<usize> {
+        match self.flushing.try_lock() {
+            Some(_flushing) => self.write_dirty(),
+            None => Ok(0),
+        }
+    }
+
+    /// Write queued writes to the backend, waiting for any flush in progress
+    ///
+    /// When this returns, every write acknowledged before the call has been
+    /// handed to the backend.
+    pub fn sync(&self) -> Result<()> {
+        let _flushing = self.flushing.lock();
+        self.write_dirty().map(|_| ())
+    }
+
+    /// Caller must hold `flushing`
+    fn write_dirty(&self) -> Result<usize> {
+        let pending: Vec<(Vec<u8>, Option<Vec<u8>>)> = self
+            .dirty
+            .lock()
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+
+        for (key, value) in &pending {
+            match value {
+                Some(value) => self.inner.put(key, value)?,
+                None => self.inner.delete(key)?,
+            }
+            // Keep the entry queued if it was overwritten while we wrote it
+            let mut dirty = self.dirty.lock();
+            if dirty.get(key) == Some(value) {
+                dirty.remove(key);
+            }
+        }
+        Ok(pending.len())
+    }
+
+    /// Queue a write (or delete, for `None`) in write-behind mode
+    fn queue(&self, key: &[u8], value: Option<Vec<u8>>, max_dirty: usize) -> Result<()> {
+        loop {
+            {
+                // Check for room and queue under one lock, so racing writers
+                // cannot all see room and overfill the queue
+                let mut dirty = self.dirty.lock();
+                if dirty.len() < max_dirty || dirty.contains_key(key) {
+                    self.update_cache(key, value.clone());
+                    dirty.insert(key.to_vec(), value);
+                    return Ok(());
+                }
+            }
+            self.sync()?;
+        }
+    }
+
+    /// Cache a written value (or drop a deleted key), invalidating concurrent reads
+    fn update_cache(&self, key: &[u8], value: Option<Vec<u8>>) {
+        let mut generation = self.generation.lock();
+        *generation += 1;
+        match value {
+            Some(value) => self.cache.put(key.to_vec(), value),
+            None => self.cache.remove(key),
+        }
+    }
+
+    /// Drop all cached entries and reset statistics
+    pub fn clear_cache(&self) {
+        self.cache.clear();
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+    }
+}
+
+impl<S: Storage> Storage for CachedStorage<S> {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        if let Some(pending) = self.dirty.lock().get(key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(pending.clone());
+        }
+        if let Some(value) = self.cache.get(key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(Some(value));
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let read_generation = *self.generation.lock();
+        let value = self.inner.get(key)?;
+        if let Some(value) = &value {
+            let generation = self.generation.lock();
+            if *generation == read_generation {
+                self.cache.put(key.to_vec(), value.clone());
+            }
+        }
+        Ok(value)
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        if let Some(max_dirty) = self.write_behind {
+            return self.queue(key, Some(value.to_vec()), max_dirty);
+        }
+        self.inner.put(key, value)?;
+        self.update_cache(key, Some(value.to_vec()));
+        Ok(())
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        if let Some(max_dirty) = self.write_behind {
+            return self.queue(key, None, max_dirty);
+        }
+        self.inner.delete(key)?;
+        self.update_cache(key, None);
+        Ok(())
+    }
+
+    fn contains(&self, key: &[u8]) -> Result<bool> {
+        if let Some(pending) = self.dirty.lock().get(key) {
+            return Ok(pending.is_some());
+        }
+        if self.cache.contains(key) {
+            return Ok(true);
+        }
+        self.inner.contains(key)
+    }
+
+    // Scans and snapshots are served by the backend, so queued writes are
+    // synced first
+
+    fn keys(&self) -> Result<Vec<Vec<u8>>> {
+        self.sync()?;
+        self.inner.keys()
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<StorageIterator<'_>> {
+        self.sync()?;
+        self.inner.scan_prefix(prefix)
+    }
+
+    fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<StorageIterator<'_>> {
+        self.sync()?;
+        self.inner.scan_range(start, end)
+    }
+
+    fn snapshot(&self) -> Result<Box<dyn StorageSnapshot + '_>> {
+        self.sync()?;
+        self.inner.snapshot()
+    }
+
+    /// Backend statistics; writes still queued in write-behind mode are not
+    /// counted until flushed
+    fn stats(&self) -> StorageStats {
+        self.inner.stats()
+    }
+}
+
+impl<S: BatchStorage> BatchStorage for CachedStorage<S> {
+    fn batch(&self, operations: Vec<BatchOperation>) -> Result<()> {
+        let keys: Vec<Vec<u8>> = operations.iter().map(|op| op.key().to_vec()).collect();
+        // Batches bypass the dirty queue, so earlier queued writes go first
+        self.sync()?;
+        self.inner.batch(operations)?;
+
+        for key in &keys {
+            self.update_cache(key, None);
+        }
+        Ok(())
+    }
+}
+
+impl<S: MetadataStorage> MetadataStorage for CachedStorage<S> {
+    fn get_metadata(&self, key: &[u8]) -> Result<Option<StorageMetadata>> {
+        self.sync()?;
+        self.inner.get_metadata(key)
+    }
+}
+
+impl<S: Storage> Drop for CachedStorage<S> {
+    fn drop(&mut self) {
+        // Best effort: nothing can report the error from here
+        let _ = self.sync();
+    }
+}
+
+/// Background thread that periodically flushes a write-behind cache
+///
+/// The thread stops when the flusher is dropped or `stop` is called.
+pub struct CacheFlusher {
+    task: PeriodicTask,
+}
+
+impl CacheFlusher {
+    /// Flush `storage` every `interval`
+    pub fn start<S>(storage: Arc<CachedStorage<S>>, interval: Duration) -> Self
+    where
+        S: Storage + 'static,
+    {
+        // Failed writes stay queued; retry next round
+        let task = PeriodicTask::start(interval, move || {
+            let _ = storage.flush();
+        });
+        Self { task }
+    }
+
+    /// Stop the flusher and wait for the thread to exit
+    pub fn stop(&mut self) {
+        self.task.stop();
+    }
+}
+
+impl<S: Storage> MetricsSource for CachedStorage<S> {
+    fn source_name(&self) -> &str {
+        &self.name
+    }
+
+    fn collect(&self) -> Vec<MetricSample> {
+        let stats = self.cache_stats();
+        vec![
+            MetricSample::counter(
+                "cc_storage_cache_hits_total",
+                "Storage cache lookups served from the cache",
+                stats.hits as f64,
+            ),
+            MetricSample::counter(
+                "cc_storage_cache_misses_total",
+                "Storage cache lookups that fell through to the backend",
+                stats.misses as f64,
+            ),
+            MetricSample::gauge(
+                "cc_storage_cache_entries",
+                "Entries currently held in the storage cache",
+                stats.entries as f64,
+            ),
+            MetricSample::gauge(
+                "cc_storage_cache_dirty_entries",
+                "Writes queued in the storage cache but not yet flushed",
+                stats.dirty_entries as f64,
+            ),
+            MetricSample::gauge(
+                "cc_storage_cache_hit_rate",
+                "Fraction of storage cache lookups that hit",
+                stats.hit_rate(),
+            ),
+        ]
+        .into_iter()
+        .map(|sample| sample.with_label("cache", self.name.clone()))
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InMemoryStorage, NamespacedStorage};
+    use std::thread;
+
+    #[test]
+    fn test_hits_and_misses() {
+        let backend = InMemoryStorage::new();
+        backend.put(b"k", b"v").unwrap();
+        let cached = CachedStorage::new(backend, 16);
+
+        assert_eq!(cached.get(b"k").unwrap(), Some(b"v".to_vec()));
+        assert_eq!(cached.get(b"k").unwrap(), Some(b"v".to_vec()));
+        assert_eq!(cached.get(b"missing").unwrap(), None);
+
+        let stats = cached.cache_stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 2);
+        assert_eq!(stats.entries, 1);
+        // Only the first lookup of "k" and the missing key reached the backend
+        assert_eq!(cached.inner().stats().reads, 2);
+    }
+
+    #[test]
+    fn test_concurrent_cache() {
+        let cached = Arc::new(CachedStorage::concurrent(InMemoryStorage::new(), 64));
+        let handles: Vec<_> = (0..4u8)
+            .map(|thread_id| {
+                let cached = cached.clone();
+                thread::spawn(move || {
+                    for i in 0..100u8 {
+                        cached.put(&[thread_id, i], &[i]).unwrap();
+                        assert_eq!(cached.get(&[thread_id, i]).unwrap(), Some(vec![i]));
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let stats = cached.cache_stats();
+        assert_eq!(stats.capacity, 64);
+        assert!(stats.entries <= 64);
+        // Other threads can evict a key between its put and get, so only the total is fixed
+        assert_eq!(stats.hits + stats.misses, 400);
+        assert_eq!(cached.inner().len(), 400);
+    }
+
+    #[test]
+    fn test_write_through_and_eviction() {
+        let cached = CachedStorage::new(InMemoryStorage::new(), 2);
+
+        cached.put(b"a", b"1").unwrap();
+        cached.put(b"b", b"2").unwrap();
+        cached.put(b"c", b"3").unwrap();
+
+        assert_eq!(cached.cache_stats().entries, 2);
+        assert_eq!(cached.inner().len(), 3);
+        assert_eq!(cached.get(b"a").unwrap(), Some(b"1".to_vec()));
+
+        cached.delete(b"a").unwrap();
+        assert_eq!(cached.get(b"a").unwrap(), None);
+        assert!(!cached.inner().contains(b"a").unwrap());
+    }
+
+    #[test]
+    fn test_metrics_source() {
+        let cached = CachedStorage::new(InMemoryStorage::new(), 4).with_name("state");
+        cached.put(b"k", b"v").unwrap();
+        cached.get(b"k").unwrap();
+
+        let samples = cached.collect();
+        assert_eq!(cached.source_name(), "state");
+        assert!(samples
+            .iter()
+            .all(|s| s.labels == vec![("cache".to_string(), "state".to_string())]));
+        let hits = samples
+            .iter()
+            .find(|s| s.name == "cc_storage_cache_hits_total")
+            .unwrap();
+        assert_eq!(hits.value, 1.0);
+    }
+
+    #[test]
+    fn test_batch_invalidates_cached_entries() {
+        let cached = CachedStorage::new(InMemoryStorage::new(), 8);
+        cached.put(b"a", b"1").unwrap();
+        cached.get(b"a").unwrap();
+
+        cached
+            .batch(vec![BatchOperation::Put { key: b"a".to_vec(), value: b"2".to_vec() }])
+            .unwrap();
+
+        assert_eq!(cached.get(b"a").unwrap(), Some(b"2".to_vec()));
+        assert_eq!(cached.get_metadata(b"a").unwrap().unwrap().version, 2);
+    }
+
+    #[test]
+    fn test_write_behind_defers_writes_until_flush() {
+        let backend = InMemoryStorage::new();
+        backend.put(b"old", b"x").unwrap();
+        let cached = CachedStorage::new(backend, 2).with_write_behind(16);
+
+        for key in [b"a", b"b", b"c"] {
+            cached.put(key, b"1").unwrap();
+        }
+        cached.delete(b"old").unwrap();
+        assert_eq!(cached.inner().len(), 1);
+        assert_eq!(cached.cache_stats().dirty_entries, 4);
+
+        // Queued writes are visible even after the LRU evicted them
+        assert_eq!(cached.get(b"a").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(cached.get(b"old").unwrap(), None);
+        assert!(!cached.contains(b"old").unwrap());
+
+        assert_eq!(cached.flush().unwrap(), 4);
+        assert_eq!(cached.cache_stats().dirty_entries, 0);
+        assert_eq!(cached.inner().len(), 3);
+        assert!(!cached.inner().contains(b"old").unwrap());
+    }
+
+    #[test]
+    fn test_write_behind_queue_is_bounded() {
+        let cached = CachedStorage::new(InMemoryStorage::new(), 8).with_write_behind(2);
+        cached.put(b"a", b"1").unwrap();
+        cached.put(b"b", b"1").unwrap();
+        // Rewriting a queued key does not grow the queue
+        cached.put(b"a", b"2").unwrap();
+        assert!(cached.inner().is_empty());
+
+        cached.put(b"c", b"1").unwrap();
+        assert_eq!(cached.inner().len(), 2);
+        assert_eq!(cached.inner().get(b"a").unwrap(), Some(b"2".to_vec()));
+        assert_eq!(cached.cache_stats().dirty_entries, 1);
+
+        // Scans see queued writes
+        assert_eq!(cached.scan_prefix(b"").unwrap().count(), 3);
+
+        // Racing writers cannot overfill the queue
+        let cached = Arc::new(CachedStorage::new(InMemoryStorage::new(), 64).with_write_behind(4));
+        let handles: Vec<_> = (0..8u8)
+            .map(|thread_id| {
+                let cached = cached.clone();
+                thread::spawn(move || {
+                    for i in 0..50u8 {
+                        cached.put(&[thread_id, i], &[i]).unwrap();
+                        assert!(cached.cache_stats().dirty_entries <= 4);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        cached.sync().unwrap();
+        assert_eq!(cached.inner().len(), 400);
+    }
+
+    #[test]
+    fn test_write_behind_flusher_and_drop() {
+        let cached = Arc::new(CachedStorage::new(InMemoryStorage::new(), 8).with_write_behind(64));
+        cached.put(b"a", b"1").unwrap();
+
+        let mut flusher = CacheFlusher::start(cached.clone(), Duration::from_millis(20));
+        thread::sleep(Duration::from_millis(200));
+        flusher.stop();
+        assert_eq!(cached.inner().len(), 1);
+
+        // Dropping the cache syncs whatever is still queued
+        let storage = NamespacedStorage::new(InMemoryStorage::new());
+        let backend = storage.open_namespace("index").unwrap();
+        let cached = CachedStorage::new(backend.clone(), 8).with_write_behind(64);
+        cached.put(b"b", b"2").unwrap();
+        assert!(!backend.contains(b"b").unwrap());
+        drop(cached);
+        assert_eq!(backend.get(b"b").unwrap(), Some(b"2".to_vec()));
+    }
+}
@@ -0,0 +1,360 @@
+use crate::{
+    BatchOperation, BatchStorage, MaterializedSnapshot, MetadataStorage, Result, Storage,
+    StorageError, StorageIterator, StorageMetadata, StorageSnapshot, StorageStats,
+};
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Compression codec applied to stored values
+///
+/// The codec tag is written as the first byte of every stored value, so
+/// values written with different codecs can be read back side by side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CompressionCodec {
+    /// Value stored as-is
+    None,
+    /// LZ4 block format: fast, moderate ratio
+    Lz4,
+    /// Zstandard at the given level: slower, better ratio
+    Zstd(i32),
+}
+
+impl CompressionCodec {
+    const TAG_NONE: u8 = 0;
+    const TAG_LZ4: u8 = 1;
+    const TAG_ZSTD: u8 = 2;
+
+    fn tag(&self) -> u8 {
+        match self {
+            CompressionCodec::None => Self::TAG_NONE,
+            CompressionCodec::Lz4 => Self::TAG_LZ4,
+            CompressionCodec::Zstd(_) => Self::TAG_ZSTD,
+        }
+    }
+
+    fn compress(&self, value: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            CompressionCodec::None => Ok(value.to_vec()),
+            CompressionCodec::Lz4 => Ok(lz4_flex::compress_prepend_size(value)),
+            CompressionCodec::Zstd(level) => zstd::bulk::compress(value, *level)
+                .map_err(|e| StorageError::Compression(e.to_string())),
+        }
+    }
+}
+
+/// Compression settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompressionConfig {
+    pub codec: CompressionCodec,
+    /// Values smaller than this many bytes are stored uncompressed
+    pub min_size: usize,
+    /// Largest value accepted by a write, and the most a stored value may
+    /// decompress to, so a corrupt or hostile record cannot exhaust memory
+    pub max_value_size: usize,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            codec: CompressionCodec::Lz4,
+            min_size: 256,
+            max_value_size: 64 * 1024 * 1024, // 64MB
+        }
+    }
+}
+
+/// Encode a value with its codec header
+///
+/// Falls back to storing the value uncompressed when it is below the size
+/// threshold or compression would not make it smaller.
+fn encode(config: &CompressionConfig, value: &[u8]) -> Result<Vec<u8>> {
+    if value.len() > config.max_value_size {
+        return Err(StorageError::Compression(format!(
+            "Value of {} bytes exceeds the {} byte limit",
+            value.len(),
+            config.max_value_size
+        )));
+    }
+    if value.len() >= config.min_size && config.codec != CompressionCodec::None {
+        let compressed = config.codec.compress(value)?;
+        if compressed.len() < value.len() {
+            let mut encoded = Vec::with_capacity(compressed.len() + 1);
+            encoded.push(config.codec.tag());
+            encoded.extend_from_slice(&compressed);
+            return Ok(encoded);
+        }
+    }
+
+    let mut encoded = Vec::with_capacity(value.len() + 1);
+    encoded.push(CompressionCodec::TAG_NONE);
+    encoded.extend_from_slice(value);
+    Ok(encoded)
+}
+
+/// Decode a value written by `encode`, refusing to inflate it past `max_size` bytes
+fn decode(encoded: &[u8], max_size: usize) -> Result<Vec<u8>> {
+    let (tag, payload) = encoded
+        .split_first()
+        .ok_or_else(|| StorageError::Compression("Missing codec header".to_string()))?;
+    let too_large = || StorageError::Compression(format!("Value exceeds the {} byte limit", max_size));
+
+    match *tag {
+        CompressionCodec::TAG_NONE => Ok(payload.to_vec()),
+        CompressionCodec::TAG_LZ4 => {
+            // The size prefix decides the allocation, so check it first
+            let size = payload
+                .get(..4)
+                .map(|prefix| u32::from_le_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]))
+                .ok_or_else(|| StorageError::Compression("Truncated LZ4 value".to_string()))?;
+            if size as usize > max_size {
+                return Err(too_large());
+            }
+            lz4_flex::decompress_size_prepended(payload)
+                .map_err(|e| StorageError::Compression(e.to_string()))
+        }
+        CompressionCodec::TAG_ZSTD => {
+            let mut out = Vec::new();
+            zstd::stream::read::Decoder::new(payload)
+                .and_then(|decoder| {
+                    decoder
+                        .take((max_size as u64).saturating_add(1))
+                        .read_to_end(&mut out)
+                })
+                .map_err(|e| StorageError::Compression(e.to_string()))?;
+            if out.len() > max_size {
+                return Err(too_large());
+            }
+            Ok(out)
+        }
+        other => Err(StorageError::Compression(format!(
+            "Unknown codec tag: {}",
+            other
+        ))),
+    }
+}
+
+/// Storage wrapper that transparently compresses values
+///
+/// Keys are left untouched so ordering and scans behave exactly as on the
+/// wrapped backend. Metadata sizes reported by the backend refer to the
+/// stored (encoded) value.
+pub struct CompressedStorage<S: Storage> {
+    inner: S,
+    config: CompressionConfig,
+    /// Bytes handed to `put` before compression
+    uncompressed_bytes: AtomicU64,
+    /// Bytes actually written to the backend, headers included
+    compressed_bytes: AtomicU64,
+}
+
+impl<S: Storage> CompressedStorage<S> {
+    /// Wrap a backend with the default compression settings
+    pub fn new(inner: S) -> Self {
+        Self::with_config(inner, CompressionConfig::default())
+    }
+
+    /// Wrap a backend with custom compression settings
+    pub fn with_config(inner: S, config: CompressionConfig) -> Self {
+        Self {
+            inner,
+            config,
+            uncompressed_bytes: AtomicU64::new(0),
+            compressed_bytes: AtomicU64::new(0),
+        }
+    }
+
+    /// Get the wrapped backend
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Get the compression settings
+    pub fn config(&self) -> &CompressionConfig {
+        &self.config
+    }
+
+    fn decode_entry(&self, entry: Result<(Vec<u8>, Vec<u8>)>) -> Result<(Vec<u8>, Vec<u8>)> {
+        let (key, value) = entry?;
+        Ok((key, decode(&value, self.config.max_value_size)?))
+    }
+
+    fn record_write(&self, raw: u64, stored: u64) {
+        self.uncompressed_bytes.fetch_add(raw, Ordering::Relaxed);
+        self.compressed_bytes.fetch_add(stored, Ordering::Relaxed);
+    }
+}
+
+impl<S: Storage> Storage for CompressedStorage<S> {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.inner
+            .get(key)?
+            .map(|value| decode(&value, self.config.max_value_size))
+            .transpose()
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        let encoded = encode(&self.config, value)?;
+        self.inner.put(key, &encoded)?;
+        self.record_write(value.len() as u64, encoded.len() as u64);
+        Ok(())
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        self.inner.delete(key)
+    }
+
+    fn contains(&self, key: &[u8]) -> Result<bool> {
+        self.inner.contains(key)
+    }
+
+    fn keys(&self) -> Result<Vec<Vec<u8>>> {
+        self.inner.keys()
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<StorageIterator<'_>> {
+        let entries = self.inner.scan_prefix(prefix)?;
+        Ok(Box::new(entries.map(move |entry| self.decode_entry(entry))))
+    }
+
+    fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<StorageIterator<'_>> {
+        let entries = self.inner.scan_range(start, end)?;
+        Ok(Box::new(entries.map(move |entry| self.decode_entry(entry))))
+    }
+
+    fn snapshot(&self) -> Result<Box<dyn StorageSnapshot + '_>> {
+        let inner = self.inner.snapshot()?;
+        let entries = inner.scan_prefix(&[])?.map(|entry| self.decode_entry(entry));
+        Ok(Box::new(MaterializedSnapshot::collect(Box::new(entries))?))
+    }
+
+    fn stats(&self) -> StorageStats {
+        StorageStats {
+            uncompressed_bytes: self.uncompressed_bytes.load(Ordering::Relaxed),
+            compressed_bytes: self.compressed_bytes.load(Ordering::Relaxed),
+            ..self.inner.stats()
+        }
+    }
+}
+
+impl<S: BatchStorage> BatchStorage for CompressedStorage<S> {
+    fn batch(&self, operations: Vec<BatchOperation>) -> Result<()> {
+        let (mut raw, mut stored) = (0u64, 0u64);
+        let mut encoded = Vec::with_capacity(operations.len());
+        for operation in operations {
+            encoded.push(match operation {
+                BatchOperation::Put { key, value } => {
+                    let value_encoded = encode(&self.config, &value)?;
+                    raw += value.len() as u64;
+                    stored += value_encoded.len() as u64;
+                    BatchOperation::Put { key, value: value_encoded }
+                }
+                delete => delete,
+            });
+        }
+
+        self.inner.batch(encoded)?;
+        self.record_write(raw, stored);
+        Ok(())
+    }
+}
+
+impl<S: MetadataStorage> MetadataStorage for CompressedStorage<S> {
+    fn get_metadata(&self, key: &[u8]) -> Result<Option<StorageMetadata>> {
+        self.inner.get_metadata(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryStorage;
+
+    fn compressible(len: usize) -> Vec<u8> {
+        b"cc-chain block payload ".iter().copied().cycle().take(len).collect()
+    }
+
+    #[test]
+    fn test_round_trip_all_codecs() {
+        for codec in [CompressionCodec::None, CompressionCodec::Lz4, CompressionCodec::Zstd(3)] {
+            let storage = CompressedStorage::with_config(
+                InMemoryStorage::new(),
+                CompressionConfig { codec, min_size: 64, ..CompressionConfig::default() },
+            );
+            let value = compressible(4096);
+
+            storage.put(b"big", &value).unwrap();
+            storage.put(b"small", b"tiny").unwrap();
+
+            assert_eq!(storage.get(b"big").unwrap(), Some(value.clone()));
+            assert_eq!(storage.get(b"small").unwrap(), Some(b"tiny".to_vec()));
+            // Small values are stored raw behind a one-byte header
+            assert_eq!(storage.inner().get(b"small").unwrap().unwrap().len(), 5);
+        }
+    }
+
+    #[test]
+    fn test_compression_ratio_stats() {
+        let storage = CompressedStorage::new(InMemoryStorage::new());
+        storage.put(b"block", &compressible(8192)).unwrap();
+
+        let stats = storage.stats();
+        assert_eq!(stats.uncompressed_bytes, 8192);
+        assert!(stats.compressed_bytes < 8192);
+        assert!(stats.compression_ratio() > 1.0);
+        assert_eq!(stats.total_keys, 1);
+    }
+
+    #[test]
+    fn test_batch_and_scan_decode_values() {
+        let storage = CompressedStorage::with_config(
+            InMemoryStorage::new(),
+            CompressionConfig {
+                codec: CompressionCodec::Zstd(1),
+                min_size: 0,
+                ..CompressionConfig::default()
+            },
+        );
+        storage
+            .batch(vec![
+                BatchOperation::Put { key: b"tx:1".to_vec(), value: compressible(1000) },
+                BatchOperation::Put { key: b"tx:2".to_vec(), value: compressible(2000) },
+            ])
+            .unwrap();
+
+        let values: Vec<Vec<u8>> = storage
+            .scan_prefix(b"tx:")
+            .unwrap()
+            .map(|entry| entry.unwrap().1)
+            .collect();
+        assert_eq!(values, vec![compressible(1000), compressible(2000)]);
+        assert_eq!(storage.stats().uncompressed_bytes, 3000);
+    }
+
+    #[test]
+    fn test_unknown_codec_tag_rejected() {
+        let storage = CompressedStorage::new(InMemoryStorage::new());
+        storage.inner().put(b"k", &[9, 1, 2, 3]).unwrap();
+
+        assert!(matches!(storage.get(b"k"), Err(StorageError::Compression(_))));
+    }
+
+    #[test]
+    fn test_decompressed_size_is_bounded() {
+        for codec in [CompressionCodec::Lz4, CompressionCodec::Zstd(3)] {
+            let config = CompressionConfig { codec, max_value_size: 1024, ..CompressionConfig::default() };
+            let storage = CompressedStorage::with_config(InMemoryStorage::new(), config.clone());
+            assert!(storage.put(b"big", &compressible(2048)).is_err());
+
+            // A small record that inflates past the limit is refused on read
+            let bomb = encode(&CompressionConfig { codec, ..CompressionConfig::default() }, &compressible(1 << 20))
+                .unwrap();
+            assert!(bomb.len() < 1 << 16);
+            storage.inner().put(b"bomb", &bomb).unwrap();
+            assert!(matches!(storage.get(b"bomb"), Err(StorageError::Compression(_))));
+
+            storage.put(b"ok", &compressible(1024)).unwrap();
+            assert_eq!(storage.get(b"ok").unwrap(), Some(compressible(1024)));
+        }
+    }
+}
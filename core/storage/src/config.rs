@@ -0,0 +1,112 @@
+use crate::{BatchStorage, InMemoryStorage, MetadataStorage, Result, StorageError};
+#[cfg(feature = "rocksdb")]
+use crate::{RocksDbConfig, RocksDbStorage};
+#[cfg(feature = "sled")]
+use crate::{SledConfig, SledStorage};
+use std::path::PathBuf;
+
+/// A complete storage backend usable behind a trait object
+pub trait StorageBackend: BatchStorage + MetadataStorage {}
+
+impl<T: BatchStorage + MetadataStorage> StorageBackend for T {}
+
+/// Storage backend selection
+#[derive(Debug, Clone, Default)]
+pub enum StorageConfig {
+    /// Volatile in-memory storage
+    #[default]
+    InMemory,
+    /// RocksDB on-disk storage
+    #[cfg(feature = "rocksdb")]
+    RocksDb(RocksDbConfig),
+    /// sled on-disk storage
+    #[cfg(feature = "sled")]
+    Sled(SledConfig),
+}
+
+impl StorageConfig {
+    /// Build a configuration from a backend name (`memory`, `rocksdb`, `sled`)
+    ///
+    /// Fails if the name is unknown or the backend was not compiled in.
+    pub fn from_backend_name(name: &str, path: impl Into<PathBuf>) -> Result<Self> {
+        let _path = path.into();
+        match name.to_ascii_lowercase().as_str() {
+            "memory" | "in-memory" | "inmemory" => Ok(StorageConfig::InMemory),
+            #[cfg(feature = "rocksdb")]
+            "rocksdb" => Ok(StorageConfig::RocksDb(RocksDbConfig::new(_path))),
+            #[cfg(feature = "sled")]
+            "sled" => Ok(StorageConfig::Sled(SledConfig::new(_path))),
+            #[cfg(not(feature = "rocksdb"))]
+            "rocksdb" => Err(backend_disabled(name)),
+            #[cfg(not(feature = "sled"))]
+            "sled" => Err(backend_disabled(name)),
+            other => Err(StorageError::Config(format!(
+                "Unknown storage backend: {}",
+                other
+            ))),
+        }
+    }
+
+    /// Name of the selected backend
+    pub fn backend_name(&self) -> &'static str {
+        match self {
+            StorageConfig::InMemory => "memory",
+            #[cfg(feature = "rocksdb")]
+            StorageConfig::RocksDb(_) => "rocksdb",
+            #[cfg(feature = "sled")]
+            StorageConfig::Sled(_) => "sled",
+        }
+    }
+
+    /// Open the configured backend
+    pub fn open(&self) -> Result<Box<dyn StorageBackend>> {
+        match self {
+            StorageConfig::InMemory => Ok(Box::new(InMemoryStorage::new())),
+            #[cfg(feature = "rocksdb")]
+            StorageConfig::RocksDb(config) => Ok(Box::new(RocksDbStorage::open(config.clone())?)),
+            #[cfg(feature = "sled")]
+            StorageConfig::Sled(config) => Ok(Box::new(SledStorage::open(config.clone())?)),
+        }
+    }
+}
+
+#[cfg(not(all(feature = "rocksdb", feature = "sled")))]
+fn backend_disabled(name: &str) -> StorageError {
+    StorageError::Config(format!(
+        "Storage backend '{}' is not enabled in this build",
+        name
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_in_memory_backend() {
+        let config = StorageConfig::from_backend_name("memory", "unused").unwrap();
+        assert_eq!(config.backend_name(), "memory");
+
+        let storage = config.open().unwrap();
+        storage.put(b"key", b"value").unwrap();
+        assert_eq!(storage.get(b"key").unwrap(), Some(b"value".to_vec()));
+        assert_eq!(storage.get_metadata(b"key").unwrap().unwrap().version, 1);
+    }
+
+    #[test]
+    fn test_unknown_backend_rejected() {
+        let result = StorageConfig::from_backend_name("leveldb", "data");
+        assert!(matches!(result, Err(StorageError::Config(_))));
+    }
+
+    #[cfg(feature = "sled")]
+    #[test]
+    fn test_select_sled_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = StorageConfig::from_backend_name("sled", dir.path()).unwrap();
+
+        let storage = config.open().unwrap();
+        storage.put(b"key", b"value").unwrap();
+        assert_eq!(storage.get(b"key").unwrap(), Some(b"value".to_vec()));
+    }
+}
@@ -0,0 +1,395 @@
+use crate::{
+    BatchOperation, BatchStorage, MaterializedSnapshot, MetadataStorage, Result, Storage,
+    StorageError, StorageIterator, StorageMetadata, StorageSnapshot, StorageStats,
+};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use parking_lot::RwLock;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Envelope format version written as the first byte of every value
+const ENVELOPE_VERSION: u8 = 1;
+/// Envelope header: version byte, key id and nonce
+const HEADER_LEN: usize = 1 + 4 + NONCE_LEN;
+const NONCE_LEN: usize = 12;
+/// Values re-encrypted per batch by `reencrypt_all`
+const REENCRYPT_CHUNK: usize = 256;
+
+/// 256-bit data encryption key
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncryptionKey([u8; 32]);
+
+impl EncryptionKey {
+    /// Create a key from raw bytes
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// Generate a random key from the OS RNG
+    pub fn generate() -> Self {
+        Self(ChaCha20Poly1305::generate_key(&mut OsRng).into())
+    }
+
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(Key::from_slice(&self.0))
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptionKey(<redacted>)")
+    }
+}
+
+/// Source of data encryption keys
+///
+/// Every encrypted value records the id of the key it was sealed with, so
+/// providers must keep retired keys available until all values written
+/// under them have been re-encrypted.
+pub trait KeyProvider: Send + Sync {
+    /// Key used for new writes, with its id
+    fn current_key(&self) -> Result<(u32, EncryptionKey)>;
+
+    /// Look up a key by id
+    fn key(&self, id: u32) -> Result<Option<EncryptionKey>>;
+}
+
+/// Key provider holding its keys in memory
+pub struct InMemoryKeyProvider {
+    keys: RwLock<BTreeMap<u32, EncryptionKey>>,
+}
+
+impl InMemoryKeyProvider {
+    /// Create a provider whose first key (id 0) is `key`
+    pub fn new(key: EncryptionKey) -> Self {
+        let mut keys = BTreeMap::new();
+        keys.insert(0, key);
+        Self {
+            keys: RwLock::new(keys),
+        }
+    }
+
+    /// Make `key` the current key, returning its id
+    ///
+    /// Previous keys remain available for decryption.
+    pub fn rotate(&self, key: EncryptionKey) -> u32 {
+        let mut keys = self.keys.write();
+        let id = keys.keys().next_back().map_or(0, |id| id + 1);
+        keys.insert(id, key);
+        id
+    }
+
+    /// Drop a retired key; the current key cannot be removed
+    pub fn retire(&self, id: u32) -> bool {
+        let mut keys = self.keys.write();
+        if keys.keys().next_back() == Some(&id) {
+            return false;
+        }
+        keys.remove(&id).is_some()
+    }
+}
+
+impl KeyProvider for InMemoryKeyProvider {
+    fn current_key(&self) -> Result<(u32, EncryptionKey)> {
+        self.keys
+            .read()
+            .iter()
+            .next_back()
+            .map(|(id, key)| (*id, key.clone()))
+            .ok_or_else(|| StorageError::Encryption("No encryption key available".to_string()))
+    }
+
+    fn key(&self, id: u32) -> Result<Option<EncryptionKey>> {
+        Ok(self.keys.read().get(&id).cloned())
+    }
+}
+
+/// Storage wrapper that encrypts values at rest with ChaCha20-Poly1305
+///
+/// Each value is sealed with a fresh random nonce and the storage key as
+/// associated data, so ciphertexts cannot be swapped between keys without
+/// detection. Keys (and therefore ordering and scans) are stored in clear.
+pub struct EncryptedStorage<S: Storage> {
+    inner: S,
+    provider: Arc<dyn KeyProvider>,
+    /// Shared by writes, held exclusively by `reencrypt_all` while it
+    /// rewrites a chunk, so no write lands between its read and its rewrite
+    write_gate: RwLock<()>,
+}
+
+impl<S: Storage> EncryptedStorage<S> {
+    /// Wrap a backend, taking keys from `provider`
+    pub fn new(inner: S, provider: Arc<dyn KeyProvider>) -> Self {
+        Self {
+            inner,
+            provider,
+            write_gate: RwLock::new(()),
+        }
+    }
+
+    /// Get the wrapped backend
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Id of the key a stored value was encrypted with
+    pub fn key_id(&self, key: &[u8]) -> Result<Option<u32>> {
+        self.inner
+            .get(key)?
+            .map(|envelope| parse_header(&envelope).map(|(id, _, _)| id))
+            .transpose()
+    }
+
+    fn encrypt(&self, key: &[u8], value: &[u8]) -> Result<Vec<u8>> {
+        let (key_id, data_key) = self.provider.current_key()?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = data_key
+            .cipher()
+            .encrypt(&nonce, Payload { msg: value, aad: key })
+            .map_err(|_| StorageError::Encryption("Encryption failed".to_string()))?;
+
+        let mut envelope = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+        envelope.push(ENVELOPE_VERSION);
+        envelope.extend_from_slice(&key_id.to_be_bytes());
+        envelope.extend_from_slice(&nonce);
+        envelope.extend_from_slice(&ciphertext);
+        Ok(envelope)
+    }
+
+    fn decrypt(&self, key: &[u8], envelope: &[u8]) -> Result<Vec<u8>> {
+        let (key_id, nonce, ciphertext) = parse_header(envelope)?;
+        let data_key = self.provider.key(key_id)?.ok_or_else(|| {
+            StorageError::Encryption(format!("Unknown encryption key id: {}", key_id))
+        })?;
+
+        data_key
+            .cipher()
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: key })
+            .map_err(|_| StorageError::Encryption("Value failed authentication".to_string()))
+    }
+
+    fn decrypt_entry(&self, entry: Result<(Vec<u8>, Vec<u8>)>) -> Result<(Vec<u8>, Vec<u8>)> {
+        let (key, envelope) = entry?;
+        let value = self.decrypt(&key, &envelope)?;
+        Ok((key, value))
+    }
+}
+
+impl<S: BatchStorage> EncryptedStorage<S> {
+    /// Re-encrypt every value not sealed with the current key
+    ///
+    /// Run after rotating keys so retired keys can be dropped from the
+    /// provider. Values are rewritten in batches of bounded size, each under
+    /// a lock that holds off writes through this wrapper, so a value written
+    /// concurrently is never overwritten with its older contents; keys
+    /// deleted meanwhile are skipped. Returns the number of values rewritten.
+    pub fn reencrypt_all(&self) -> Result<usize> {
+        let (current_id, _) = self.provider.current_key()?;
+        let mut rewritten = 0;
+
+        for keys in self.inner.keys()?.chunks(REENCRYPT_CHUNK) {
+            let _gate = self.write_gate.write();
+            let mut operations = Vec::new();
+            for key in keys {
+                let Some(envelope) = self.inner.get(key)? else {
+                    continue;
+                };
+                if parse_header(&envelope)?.0 == current_id {
+                    continue;
+                }
+                let value = self.decrypt(key, &envelope)?;
+                let value = self.encrypt(key, &value)?;
+                operations.push(BatchOperation::Put { key: key.clone(), value });
+            }
+
+            rewritten += operations.len();
+            if !operations.is_empty() {
+                self.inner.batch(operations)?;
+            }
+        }
+        Ok(rewritten)
+    }
+}
+
+/// Split an envelope into key id, nonce and ciphertext
+fn parse_header(envelope: &[u8]) -> Result<(u32, &[u8], &[u8])> {
+    if envelope.len() < HEADER_LEN {
+        return Err(StorageError::Encryption("Truncated encrypted value".to_string()));
+    }
+    if envelope[0] != ENVELOPE_VERSION {
+        return Err(StorageError::Encryption(format!(
+            "Unsupported envelope version: {}",
+            envelope[0]
+        )));
+    }
+
+    let key_id = u32::from_be_bytes([envelope[1], envelope[2], envelope[3], envelope[4]]);
+    Ok((key_id, &envelope[5..HEADER_LEN], &envelope[HEADER_LEN..]))
+}
+
+impl<S: Storage> Storage for EncryptedStorage<S> {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.inner
+            .get(key)?
+            .map(|envelope| self.decrypt(key, &envelope))
+            .transpose()
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        let envelope = self.encrypt(key, value)?;
+        let _gate = self.write_gate.read();
+        self.inner.put(key, &envelope)
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        let _gate = self.write_gate.read();
+        self.inner.delete(key)
+    }
+
+    fn contains(&self, key: &[u8]) -> Result<bool> {
+        self.inner.contains(key)
+    }
+
+    fn keys(&self) -> Result<Vec<Vec<u8>>> {
+        self.inner.keys()
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<StorageIterator<'_>> {
+        let entries = self.inner.scan_prefix(prefix)?;
+        Ok(Box::new(entries.map(move |entry| self.decrypt_entry(entry))))
+    }
+
+    fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<StorageIterator<'_>> {
+        let entries = self.inner.scan_range(start, end)?;
+        Ok(Box::new(entries.map(move |entry| self.decrypt_entry(entry))))
+    }
+
+    fn snapshot(&self) -> Result<Box<dyn StorageSnapshot + '_>> {
+        let inner = self.inner.snapshot()?;
+        let entries = inner.scan_prefix(&[])?.map(|entry| self.decrypt_entry(entry));
+        Ok(Box::new(MaterializedSnapshot::collect(Box::new(entries))?))
+    }
+
+    fn stats(&self) -> StorageStats {
+        self.inner.stats()
+    }
+}
+
+impl<S: BatchStorage> BatchStorage for EncryptedStorage<S> {
+    fn batch(&self, operations: Vec<BatchOperation>) -> Result<()> {
+        let operations = operations
+            .into_iter()
+            .map(|operation| match operation {
+                BatchOperation::Put { key, value } => {
+                    let value = self.encrypt(&key, &value)?;
+                    Ok(BatchOperation::Put { key, value })
+                }
+                delete => Ok(delete),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let _gate = self.write_gate.read();
+        self.inner.batch(operations)
+    }
+}
+
+impl<S: MetadataStorage> MetadataStorage for EncryptedStorage<S> {
+    fn get_metadata(&self, key: &[u8]) -> Result<Option<StorageMetadata>> {
+        self.inner.get_metadata(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryStorage;
+
+    fn encrypted() -> (Arc<InMemoryKeyProvider>, EncryptedStorage<InMemoryStorage>) {
+        let provider = Arc::new(InMemoryKeyProvider::new(EncryptionKey::generate()));
+        let storage = EncryptedStorage::new(InMemoryStorage::new(), provider.clone());
+        (provider, storage)
+    }
+
+    #[test]
+    fn test_round_trip_hides_plaintext() {
+        let (_provider, storage) = encrypted();
+        storage.put(b"validator:key", b"secret key material").unwrap();
+
+        assert_eq!(
+            storage.get(b"validator:key").unwrap(),
+            Some(b"secret key material".to_vec())
+        );
+        let raw = storage.inner().get(b"validator:key").unwrap().unwrap();
+        assert!(!raw.windows(6).any(|w| w == b"secret"));
+    }
+
+    #[test]
+    fn test_tampering_and_swapping_detected() {
+        let (_provider, storage) = encrypted();
+        storage.put(b"a", b"1").unwrap();
+        storage.put(b"b", b"2").unwrap();
+
+        // Moving a ciphertext to another key fails authentication
+        let envelope = storage.inner().get(b"a").unwrap().unwrap();
+        storage.inner().put(b"b", &envelope).unwrap();
+        assert!(matches!(storage.get(b"b"), Err(StorageError::Encryption(_))));
+
+        let mut tampered = envelope;
+        *tampered.last_mut().unwrap() ^= 1;
+        storage.inner().put(b"a", &tampered).unwrap();
+        assert!(matches!(storage.get(b"a"), Err(StorageError::Encryption(_))));
+    }
+
+    #[test]
+    fn test_key_rotation_and_reencryption() {
+        let (provider, storage) = encrypted();
+        storage.put(b"old", b"v1").unwrap();
+
+        let new_id = provider.rotate(EncryptionKey::generate());
+        storage.put(b"new", b"v2").unwrap();
+        assert_eq!(storage.key_id(b"old").unwrap(), Some(0));
+        assert_eq!(storage.key_id(b"new").unwrap(), Some(new_id));
+        assert_eq!(storage.get(b"old").unwrap(), Some(b"v1".to_vec()));
+
+        assert_eq!(storage.reencrypt_all().unwrap(), 1);
+        assert!(provider.retire(0));
+        assert!(!provider.retire(new_id));
+        assert_eq!(storage.get(b"old").unwrap(), Some(b"v1".to_vec()));
+        assert_eq!(storage.key_id(b"old").unwrap(), Some(new_id));
+    }
+
+    #[test]
+    fn test_reencryption_spans_chunks() {
+        let (provider, storage) = encrypted();
+        let count = REENCRYPT_CHUNK * 2 + 1;
+        for i in 0..count {
+            storage.put(format!("k{}", i).as_bytes(), b"v").unwrap();
+        }
+
+        let new_id = provider.rotate(EncryptionKey::generate());
+        assert_eq!(storage.reencrypt_all().unwrap(), count);
+        assert_eq!(storage.reencrypt_all().unwrap(), 0);
+        assert!(provider.retire(0));
+        assert_eq!(storage.key_id(b"k0").unwrap(), Some(new_id));
+        assert_eq!(storage.get(format!("k{}", count - 1).as_bytes()).unwrap(), Some(b"v".to_vec()));
+    }
+
+    #[test]
+    fn test_scans_decrypt_values() {
+        let (_provider, storage) = encrypted();
+        storage
+            .batch(vec![
+                BatchOperation::Put { key: b"s:1".to_vec(), value: b"x".to_vec() },
+                BatchOperation::Put { key: b"s:2".to_vec(), value: b"y".to_vec() },
+            ])
+            .unwrap();
+
+        let values: Vec<Vec<u8>> = storage
+            .scan_prefix(b"s:")
+            .unwrap()
+            .map(|entry| entry.unwrap().1)
+            .collect();
+        assert_eq!(values, vec![b"x".to_vec(), b"y".to_vec()]);
+    }
+}
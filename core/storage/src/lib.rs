@@ -1,2 +1,307 @@
 //! Core storage functionality
+//!
+//! This crate defines the key-value storage interface used by CC Chain
+//! components together with its backends and wrappers:
+//! - `Storage` trait for byte-oriented key-value stores, with batch and
+//!   per-key metadata extensions
+//! - In-memory backend for tests and ephemeral nodes, plus a sharded variant
+//!   for highly concurrent workloads
+//! - RocksDB backend for persistent nodes (`rocksdb` feature)
+//! - sled backend for pure-Rust deployments (`sled` feature)
+//! - Configuration-driven backend selection
+//! - Namespaces giving subsystems isolated keyspaces on one backend
+//! - Point-in-time snapshots for consistent reads
+//! - Hot/cold tiering with an object-store cold tier
+//! - Metrics export through the monitoring `MetricsSource` interface
+//! - LRU hot-entry cache in front of slower (disk-backed) stores, with an
+//!   optional write-behind mode
+//! - Transparent per-value compression (LZ4/Zstandard)
+//! - Encryption at rest (ChaCha20-Poly1305) with key rotation
+//! - Time-to-live entries with lazy and periodic expiry
+//! - Full and incremental backups with verification and restore
+//! - Write-ahead journaling with configurable fsync policy
+//! - Streaming bulk export/import for migrating between backends
 
+pub mod backup;
+pub mod cache;
+pub mod compression;
+pub mod config;
+pub mod encryption;
+pub mod memory;
+pub mod metrics;
+pub mod namespace;
+mod periodic;
+#[cfg(feature = "rocksdb")]
+pub mod rocksdb;
+pub mod sharded;
+pub mod snapshot;
+pub mod tiered;
+#[cfg(feature = "sled")]
+pub mod sled;
+pub mod ttl;
+pub mod utils;
+pub mod wal;
+
+pub use backup::{BackupInfo, BackupKind, BackupManager, BackupPhase, BackupProgress, VerifyReport};
+pub use cache::{CacheFlusher, CacheStats, CachedStorage, EntryCache};
+pub use compression::{CompressedStorage, CompressionCodec, CompressionConfig};
+pub use config::{StorageBackend, StorageConfig};
+pub use encryption::{EncryptedStorage, EncryptionKey, InMemoryKeyProvider, KeyProvider};
+pub use memory::InMemoryStorage;
+pub use metrics::StorageMetrics;
+pub use namespace::{Namespace, NamespacedStorage};
+#[cfg(feature = "rocksdb")]
+pub use self::rocksdb::{RocksDbConfig, RocksDbStorage};
+pub use sharded::ShardedMemoryStorage;
+pub use snapshot::{MaterializedSnapshot, StorageSnapshot};
+pub use tiered::{
+    FileObjectStore, InMemoryObjectStore, ObjectStore, TieredStorage, TieringPolicy,
+    TieringReport,
+};
+#[cfg(feature = "sled")]
+pub use self::sled::{SledConfig, SledStorage};
+pub use ttl::{TtlStorage, TtlSweeper};
+pub use utils::StorageUtils;
+pub use wal::{JournaledStorage, SyncPolicy, WalConfig};
+
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum StorageError {
+    #[error("Backend error: {0}")]
+    Backend(String),
+
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Configuration error: {0}")]
+    Config(String),
+
+    #[error("Compression error: {0}")]
+    Compression(String),
+
+    #[error("Encryption error: {0}")]
+    Encryption(String),
+
+    #[error("Backup error: {0}")]
+    Backup(String),
+
+    #[error("Corruption detected for key {key}: expected checksum {expected:08x}, found {actual:08x}")]
+    Corruption {
+        key: String,
+        expected: u32,
+        actual: u32,
+    },
+
+    #[error("Storage capacity exceeded: required {required} bytes, limit {limit} bytes")]
+    CapacityExceeded { required: u64, limit: u64 },
+}
+
+pub type Result<T> = std::result::Result<T, StorageError>;
+
+impl From<bincode::Error> for StorageError {
+    fn from(err: bincode::Error) -> Self {
+        StorageError::Serialization(err.to_string())
+    }
+}
+
+/// Operational statistics reported by a storage backend
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StorageStats {
+    pub total_keys: u64,
+    pub total_size_bytes: u64,
+    pub reads: u64,
+    pub writes: u64,
+    pub deletes: u64,
+    /// Value bytes written before compression (0 when not compressing)
+    pub uncompressed_bytes: u64,
+    /// Value bytes written after compression, codec headers included
+    pub compressed_bytes: u64,
+}
+
+impl StorageStats {
+    /// Ratio of uncompressed to stored bytes; 1.0 when nothing was compressed
+    pub fn compression_ratio(&self) -> f64 {
+        if self.compressed_bytes == 0 {
+            1.0
+        } else {
+            self.uncompressed_bytes as f64 / self.compressed_bytes as f64
+        }
+    }
+}
+
+/// Ordered iterator over key-value pairs produced by storage scans
+pub type StorageIterator<'a> = Box<dyn Iterator<Item = Result<(Vec<u8>, Vec<u8>)>> + 'a>;
+
+/// Byte-oriented key-value store
+pub trait Storage: Send + Sync {
+    /// Get the value stored under a key
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>>;
+
+    /// Store a value under a key, replacing any previous value
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()>;
+
+    /// Delete a key; deleting a missing key is not an error
+    fn delete(&self, key: &[u8]) -> Result<()>;
+
+    /// Check whether a key exists
+    fn contains(&self, key: &[u8]) -> Result<bool> {
+        Ok(self.get(key)?.is_some())
+    }
+
+    /// List all keys in the store
+    ///
+    /// Materializes every key; prefer `scan_prefix`/`scan_range` for large stores.
+    fn keys(&self) -> Result<Vec<Vec<u8>>>;
+
+    /// Iterate, in key order, over all entries whose key starts with `prefix`
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<StorageIterator<'_>>;
+
+    /// Iterate, in key order, over all entries with `start <= key < end`
+    fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<StorageIterator<'_>>;
+
+    /// Take a read-only, point-in-time view of the store
+    ///
+    /// The default copies the store through a full scan, which is only
+    /// point-in-time if the backend's scans are; backends override it.
+    fn snapshot(&self) -> Result<Box<dyn StorageSnapshot + '_>> {
+        Ok(Box::new(MaterializedSnapshot::collect(self.scan_prefix(&[])?)?))
+    }
+
+    /// Get backend statistics
+    fn stats(&self) -> StorageStats;
+}
+
+/// Single operation within a storage batch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BatchOperation {
+    Put { key: Vec<u8>, value: Vec<u8> },
+    Delete { key: Vec<u8> },
+}
+
+impl BatchOperation {
+    /// Key the operation applies to
+    pub fn key(&self) -> &[u8] {
+        match self {
+            BatchOperation::Put { key, .. } | BatchOperation::Delete { key } => key,
+        }
+    }
+}
+
+/// Storage that can apply several operations in one call
+pub trait BatchStorage: Storage {
+    /// Apply a batch of put/delete operations in order
+    ///
+    /// Batches are all-or-nothing: if an error is returned, none of the
+    /// operations have taken effect.
+    fn batch(&self, operations: Vec<BatchOperation>) -> Result<()>;
+}
+
+/// Bookkeeping stored alongside each value
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageMetadata {
+    /// Creation time in milliseconds since the Unix epoch
+    pub created_at: u64,
+    /// Last update time in milliseconds since the Unix epoch
+    pub updated_at: u64,
+    /// Size of the stored value in bytes
+    pub size: u64,
+    /// Number of times the value has been written
+    pub version: u64,
+    /// Integrity checksum of the stored value
+    pub checksum: Option<u32>,
+}
+
+impl StorageMetadata {
+    /// Metadata for a write of `value` following `previous`
+    pub fn next(previous: Option<&StorageMetadata>, value: &[u8]) -> Self {
+        let now = current_timestamp();
+        let checksum = Some(checksum(value));
+        match previous {
+            Some(previous) => Self {
+                created_at: previous.created_at,
+                updated_at: now,
+                size: value.len() as u64,
+                version: previous.version + 1,
+                checksum,
+            },
+            None => Self {
+                created_at: now,
+                updated_at: now,
+                size: value.len() as u64,
+                version: 1,
+                checksum,
+            },
+        }
+    }
+
+    /// Check that `value`, read back for `key`, matches the recorded checksum
+    ///
+    /// Entries written before checksums were recorded are accepted as-is.
+    pub fn verify(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        let Some(expected) = self.checksum else {
+            return Ok(());
+        };
+        let actual = checksum(value);
+        if actual != expected {
+            return Err(StorageError::Corruption {
+                key: hex::encode(key),
+                expected,
+                actual,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// CRC32 checksum of a stored value
+pub fn checksum(value: &[u8]) -> u32 {
+    crc32fast::hash(value)
+}
+
+/// Storage that tracks metadata for each key
+pub trait MetadataStorage: Storage {
+    /// Get the metadata recorded for a key
+    fn get_metadata(&self, key: &[u8]) -> Result<Option<StorageMetadata>>;
+}
+
+/// Smallest key that sorts after every key starting with `prefix`
+///
+/// Returns `None` when no such key exists (empty prefix or all `0xff` bytes),
+/// meaning a prefix scan must run to the end of the keyspace.
+pub fn prefix_end(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < u8::MAX {
+            end.push(last + 1);
+            return Some(end);
+        }
+    }
+    None
+}
+
+/// Current time in milliseconds since the Unix epoch
+pub(crate) fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_end() {
+        assert_eq!(prefix_end(b"abc"), Some(b"abd".to_vec()));
+        assert_eq!(prefix_end(&[0x01, 0xff]), Some(vec![0x02]));
+        assert_eq!(prefix_end(&[0xff, 0xff]), None);
+        assert_eq!(prefix_end(b""), None);
+    }
+}
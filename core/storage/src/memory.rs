@@ -0,0 +1,491 @@
+use crate::{
+    prefix_end, BatchOperation, BatchStorage, MetadataStorage, Result, Storage, StorageError,
+    MaterializedSnapshot, StorageIterator, StorageMetadata, StorageSnapshot, StorageStats,
+};
+use parking_lot::RwLock;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Number of entries copied out of the map per lock acquisition during scans
+const SCAN_CHUNK_SIZE: usize = 256;
+
+/// In-memory storage backend
+///
+/// Keeps all entries in an ordered map. Data is lost when the process exits,
+/// so this backend is intended for tests and ephemeral nodes. An optional
+/// size limit bounds the memory used by keys and values.
+#[derive(Debug, Default)]
+pub struct InMemoryStorage {
+    state: RwLock<MemoryState>,
+    reads: AtomicU64,
+    writes: AtomicU64,
+    deletes: AtomicU64,
+    max_size_bytes: Option<u64>,
+}
+
+/// Entries and their metadata, guarded by a single lock so batches commit atomically
+///
+/// The map sits behind an `Arc` so snapshots can share it. Writes go through
+/// `Arc::make_mut`, which copies the map only while a snapshot still holds it.
+#[derive(Debug, Default)]
+pub(crate) struct MemoryState {
+    pub(crate) data: Arc<BTreeMap<Vec<u8>, Vec<u8>>>,
+    pub(crate) metadata: HashMap<Vec<u8>, StorageMetadata>,
+    pub(crate) size_bytes: u64,
+}
+
+impl MemoryState {
+    /// Total size the store would have after applying `operations`
+    fn projected_size(&self, operations: &[BatchOperation]) -> u64 {
+        let mut overlay: HashMap<&[u8], Option<u64>> = HashMap::new();
+        let mut size = self.size_bytes;
+
+        for operation in operations {
+            let key = operation.key();
+            let current = match overlay.get(key) {
+                Some(current) => *current,
+                None => self.data.get(key).map(|v| (key.len() + v.len()) as u64),
+            };
+            let next = match operation {
+                BatchOperation::Put { value, .. } => Some((key.len() + value.len()) as u64),
+                BatchOperation::Delete { .. } => None,
+            };
+            size = size - current.unwrap_or(0) + next.unwrap_or(0);
+            overlay.insert(key, next);
+        }
+
+        size
+    }
+
+    /// Check a stored value against its recorded checksum
+    fn verify(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        match self.metadata.get(key) {
+            Some(metadata) => metadata.verify(key, value),
+            None => Ok(()),
+        }
+    }
+
+    /// Read a value, checking it against its recorded checksum
+    pub(crate) fn get_verified(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let Some(value) = self.data.get(key) else {
+            return Ok(None);
+        };
+        self.verify(key, value)?;
+        Ok(Some(value.clone()))
+    }
+
+    pub(crate) fn apply(&mut self, operation: BatchOperation) {
+        match operation {
+            BatchOperation::Put { key, value } => {
+                let entry = StorageMetadata::next(self.metadata.get(&key), &value);
+                self.metadata.insert(key.clone(), entry);
+                self.size_bytes += (key.len() + value.len()) as u64;
+                if let Some(old) = Arc::make_mut(&mut self.data).insert(key.clone(), value) {
+                    self.size_bytes -= (key.len() + old.len()) as u64;
+                }
+            }
+            BatchOperation::Delete { key } => {
+                self.metadata.remove(&key);
+                if let Some(old) = Arc::make_mut(&mut self.data).remove(&key) {
+                    self.size_bytes -= (key.len() + old.len()) as u64;
+                }
+            }
+        }
+    }
+}
+
+impl InMemoryStorage {
+    /// Create an empty in-memory store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty store holding at most `max_size_bytes` of keys and values
+    pub fn with_max_size(max_size_bytes: u64) -> Self {
+        Self {
+            max_size_bytes: Some(max_size_bytes),
+            ..Default::default()
+        }
+    }
+
+    /// Number of stored entries
+    pub fn len(&self) -> usize {
+        self.state.read().data.len()
+    }
+
+    /// Whether the store is empty
+    pub fn is_empty(&self) -> bool {
+        self.state.read().data.is_empty()
+    }
+}
+
+impl Storage for InMemoryStorage {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.reads.fetch_add(1, Ordering::Relaxed);
+        self.state.read().get_verified(key)
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.batch(vec![BatchOperation::Put {
+            key: key.to_vec(),
+            value: value.to_vec(),
+        }])
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        self.batch(vec![BatchOperation::Delete { key: key.to_vec() }])
+    }
+
+    fn contains(&self, key: &[u8]) -> Result<bool> {
+        Ok(self.state.read().data.contains_key(key))
+    }
+
+    fn keys(&self) -> Result<Vec<Vec<u8>>> {
+        Ok(self.state.read().data.keys().cloned().collect())
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<StorageIterator<'_>> {
+        Ok(Box::new(MemoryScan::new(&self.state, prefix.to_vec(), prefix_end(prefix))))
+    }
+
+    fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<StorageIterator<'_>> {
+        Ok(Box::new(MemoryScan::new(&self.state, start.to_vec(), Some(end.to_vec()))))
+    }
+
+    fn snapshot(&self) -> Result<Box<dyn StorageSnapshot + '_>> {
+        let data = Arc::clone(&self.state.read().data);
+        Ok(Box::new(MaterializedSnapshot::shared(data)))
+    }
+
+    fn stats(&self) -> StorageStats {
+        let state = self.state.read();
+        StorageStats {
+            total_keys: state.data.len() as u64,
+            total_size_bytes: state.size_bytes,
+            reads: self.reads.load(Ordering::Relaxed),
+            writes: self.writes.load(Ordering::Relaxed),
+            deletes: self.deletes.load(Ordering::Relaxed),
+            ..Default::default()
+        }
+    }
+}
+
+/// Ordered scan over the in-memory map
+///
+/// Entries are copied out in chunks so the read lock is never held between
+/// calls to `next`; writes made during the scan may or may not be observed.
+/// Each entry is checked against its checksum, as in `get`.
+pub(crate) struct MemoryScan<'a> {
+    state: &'a RwLock<MemoryState>,
+    next: Bound<Vec<u8>>,
+    end: Option<Vec<u8>>,
+    buffer: std::vec::IntoIter<Result<(Vec<u8>, Vec<u8>)>>,
+    exhausted: bool,
+}
+
+impl<'a> MemoryScan<'a> {
+    pub(crate) fn new(state: &'a RwLock<MemoryState>, start: Vec<u8>, end: Option<Vec<u8>>) -> Self {
+        Self {
+            state,
+            next: Bound::Included(start),
+            end,
+            buffer: Vec::new().into_iter(),
+            exhausted: false,
+        }
+    }
+
+    fn refill(&mut self) {
+        let past_end = match (&self.next, &self.end) {
+            (Bound::Included(next), Some(end)) => next >= end,
+            (Bound::Excluded(next), Some(end)) => next >= end,
+            _ => false,
+        };
+        if past_end {
+            self.exhausted = true;
+            return;
+        }
+
+        let end = match &self.end {
+            Some(end) => Bound::Excluded(end.clone()),
+            None => Bound::Unbounded,
+        };
+        let state = self.state.read();
+        let mut last = None;
+        let chunk: Vec<Result<(Vec<u8>, Vec<u8>)>> = state
+            .data
+            .range((self.next.clone(), end))
+            .take(SCAN_CHUNK_SIZE)
+            .map(|(key, value)| {
+                last = Some(key);
+                state.verify(key, value).map(|_| (key.clone(), value.clone()))
+            })
+            .collect();
+
+        if chunk.len() < SCAN_CHUNK_SIZE {
+            self.exhausted = true;
+        }
+        if let Some(last) = last {
+            self.next = Bound::Excluded(last.clone());
+        }
+        self.buffer = chunk.into_iter();
+    }
+}
+
+impl Iterator for MemoryScan<'_> {
+    type Item = Result<(Vec<u8>, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(entry) = self.buffer.next() {
+            return Some(entry);
+        }
+        if self.exhausted {
+            return None;
+        }
+        self.refill();
+        self.buffer.next()
+    }
+}
+
+impl BatchStorage for InMemoryStorage {
+    fn batch(&self, operations: Vec<BatchOperation>) -> Result<()> {
+        let mut state = self.state.write();
+
+        // Validate the whole batch before touching any entry
+        if let Some(limit) = self.max_size_bytes {
+            let required = state.projected_size(&operations);
+            if required > limit {
+                return Err(StorageError::CapacityExceeded { required, limit });
+            }
+        }
+
+        let writes = operations
+            .iter()
+            .filter(|op| matches!(op, BatchOperation::Put { .. }))
+            .count() as u64;
+        let deletes = operations.len() as u64 - writes;
+        for operation in operations {
+            state.apply(operation);
+        }
+
+        drop(state);
+        self.writes.fetch_add(writes, Ordering::Relaxed);
+        self.deletes.fetch_add(deletes, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+impl MetadataStorage for InMemoryStorage {
+    fn get_metadata(&self, key: &[u8]) -> Result<Option<StorageMetadata>> {
+        Ok(self.state.read().metadata.get(key).cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_put_get_delete() {
+        let storage = InMemoryStorage::new();
+
+        storage.put(b"key1", b"value1").unwrap();
+        assert_eq!(storage.get(b"key1").unwrap(), Some(b"value1".to_vec()));
+        assert!(storage.contains(b"key1").unwrap());
+
+        storage.delete(b"key1").unwrap();
+        assert_eq!(storage.get(b"key1").unwrap(), None);
+        assert!(storage.is_empty());
+    }
+
+    #[test]
+    fn test_stats_tracking() {
+        let storage = InMemoryStorage::new();
+
+        storage.put(b"a", b"1234").unwrap();
+        storage.put(b"a", b"12").unwrap();
+        storage.put(b"b", b"1").unwrap();
+        storage.get(b"a").unwrap();
+
+        let stats = storage.stats();
+        assert_eq!(stats.total_keys, 2);
+        assert_eq!(stats.total_size_bytes, 5);
+        assert_eq!(stats.writes, 3);
+        assert_eq!(stats.reads, 1);
+
+        storage.delete(b"a").unwrap();
+        assert_eq!(storage.stats().total_size_bytes, 2);
+    }
+
+    #[test]
+    fn test_batch_and_metadata() {
+        let storage = InMemoryStorage::new();
+
+        storage
+            .batch(vec![
+                BatchOperation::Put { key: b"a".to_vec(), value: b"1".to_vec() },
+                BatchOperation::Put { key: b"b".to_vec(), value: b"22".to_vec() },
+                BatchOperation::Put { key: b"a".to_vec(), value: b"333".to_vec() },
+                BatchOperation::Delete { key: b"b".to_vec() },
+            ])
+            .unwrap();
+
+        assert_eq!(storage.get(b"a").unwrap(), Some(b"333".to_vec()));
+        assert_eq!(storage.get(b"b").unwrap(), None);
+
+        let metadata = storage.get_metadata(b"a").unwrap().unwrap();
+        assert_eq!(metadata.version, 2);
+        assert_eq!(metadata.size, 3);
+        assert!(storage.get_metadata(b"b").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_failed_batch_leaves_store_untouched() {
+        let storage = InMemoryStorage::with_max_size(12);
+        storage.put(b"a", b"1").unwrap();
+        let before = storage.stats();
+
+        // The second put pushes the store over its limit mid-batch
+        let result = storage.batch(vec![
+            BatchOperation::Put { key: b"b".to_vec(), value: b"1234".to_vec() },
+            BatchOperation::Put { key: b"c".to_vec(), value: b"123456789".to_vec() },
+            BatchOperation::Delete { key: b"a".to_vec() },
+        ]);
+
+        assert!(matches!(
+            result,
+            Err(StorageError::CapacityExceeded { required: 15, limit: 12 })
+        ));
+        assert_eq!(storage.get(b"a").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(storage.get(b"b").unwrap(), None);
+        assert_eq!(storage.get_metadata(b"a").unwrap().unwrap().version, 1);
+        assert_eq!(storage.stats().total_size_bytes, before.total_size_bytes);
+        assert_eq!(storage.stats().writes, before.writes);
+    }
+
+    #[test]
+    fn test_batch_capacity_uses_net_size() {
+        let storage = InMemoryStorage::with_max_size(4);
+
+        // Temporarily exceeds the limit, but the final state fits
+        storage
+            .batch(vec![
+                BatchOperation::Put { key: b"big".to_vec(), value: b"123456".to_vec() },
+                BatchOperation::Delete { key: b"big".to_vec() },
+                BatchOperation::Put { key: b"k".to_vec(), value: b"12".to_vec() },
+            ])
+            .unwrap();
+
+        assert_eq!(storage.stats().total_size_bytes, 3);
+        assert!(storage.put(b"x", b"12").is_err());
+    }
+
+    #[test]
+    fn test_scan_prefix_and_range() {
+        let storage = InMemoryStorage::new();
+        for key in ["block:1", "block:2", "block:3", "state:1", "tx:1"] {
+            storage.put(key.as_bytes(), key.as_bytes()).unwrap();
+        }
+
+        let blocks: Vec<Vec<u8>> = storage
+            .scan_prefix(b"block:")
+            .unwrap()
+            .map(|entry| entry.unwrap().0)
+            .collect();
+        assert_eq!(blocks, vec![b"block:1".to_vec(), b"block:2".to_vec(), b"block:3".to_vec()]);
+
+        let range: Vec<Vec<u8>> = storage
+            .scan_range(b"block:2", b"state:2")
+            .unwrap()
+            .map(|entry| entry.unwrap().0)
+            .collect();
+        assert_eq!(range, vec![b"block:2".to_vec(), b"block:3".to_vec(), b"state:1".to_vec()]);
+
+        assert_eq!(storage.scan_range(b"z", b"a").unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_scan_spans_multiple_chunks() {
+        let storage = InMemoryStorage::new();
+        let count = SCAN_CHUNK_SIZE * 2 + 7;
+        for i in 0..count {
+            storage.put(format!("k{:05}", i).as_bytes(), b"v").unwrap();
+        }
+        storage.put(b"other", b"v").unwrap();
+
+        let keys: Vec<Vec<u8>> = storage
+            .scan_prefix(b"k")
+            .unwrap()
+            .map(|entry| entry.unwrap().0)
+            .collect();
+        assert_eq!(keys.len(), count);
+        assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_snapshot_unaffected_by_later_writes() {
+        let storage = InMemoryStorage::new();
+        storage.put(b"page:1", b"a").unwrap();
+        storage.put(b"page:2", b"b").unwrap();
+
+        let snapshot = storage.snapshot().unwrap();
+        storage.put(b"page:1", b"changed").unwrap();
+        storage.put(b"page:3", b"c").unwrap();
+        storage.delete(b"page:2").unwrap();
+
+        assert_eq!(snapshot.get(b"page:1").unwrap(), Some(b"a".to_vec()));
+        assert!(snapshot.contains(b"page:2").unwrap());
+        let keys: Vec<Vec<u8>> = snapshot
+            .scan_range(b"page:", b"page:9")
+            .unwrap()
+            .map(|entry| entry.unwrap().0)
+            .collect();
+        assert_eq!(keys, vec![b"page:1".to_vec(), b"page:2".to_vec()]);
+        assert_eq!(storage.len(), 2);
+    }
+
+    #[test]
+    fn test_corrupted_value_detected_on_read() {
+        let storage = InMemoryStorage::new();
+        storage.put(b"key", b"value").unwrap();
+        assert!(storage.get_metadata(b"key").unwrap().unwrap().checksum.is_some());
+
+        // Flip a byte behind the store's back
+        Arc::make_mut(&mut storage.state.write().data).insert(b"key".to_vec(), b"valuf".to_vec());
+
+        match storage.get(b"key") {
+            Err(StorageError::Corruption { key, .. }) => assert_eq!(key, hex::encode(b"key")),
+            other => panic!("expected corruption error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_snapshot_shares_map_until_next_write() {
+        let storage = InMemoryStorage::new();
+        storage.put(b"a", b"1").unwrap();
+
+        let snapshot = storage.snapshot().unwrap();
+        assert_eq!(Arc::strong_count(&storage.state.read().data), 2);
+
+        storage.put(b"b", b"2").unwrap();
+        assert_eq!(Arc::strong_count(&storage.state.read().data), 1);
+        assert!(!snapshot.contains(b"b").unwrap());
+    }
+
+    #[test]
+    fn test_corrupted_value_detected_on_scan() {
+        let storage = InMemoryStorage::new();
+        storage.put(b"a", b"1").unwrap();
+        storage.put(b"b", b"2").unwrap();
+        storage.put(b"c", b"3").unwrap();
+
+        Arc::make_mut(&mut storage.state.write().data).insert(b"b".to_vec(), b"9".to_vec());
+
+        let entries: Vec<Result<(Vec<u8>, Vec<u8>)>> = storage.scan_prefix(b"").unwrap().collect();
+        assert_eq!(entries.len(), 3);
+        assert!(entries[0].is_ok());
+        assert!(matches!(entries[1], Err(StorageError::Corruption { .. })));
+        assert_eq!(entries[2].as_ref().unwrap().0, b"c".to_vec());
+    }
+}
@@ -0,0 +1,196 @@
+use crate::Storage;
+use cc_core_metrics::{MetricSample, MetricsSource};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Exports a store's `StorageStats` as monitoring metrics
+///
+/// Register it with the RPC monitor (or any other `MetricsSource` consumer)
+/// next to the `CachedStorage` in front of the store, which reports the cache
+/// hit rate. A collection costs what the backend's `stats` costs: the
+/// in-memory backends briefly take the read lock of their map (or of each
+/// shard) for key counts and sizes, sled counts the keys of its data tree,
+/// and RocksDB only reads its estimates. Namespace sizes require a scan of
+/// each registered namespace.
+pub struct StorageMetrics {
+    name: String,
+    storage: Arc<dyn Storage>,
+    namespaces: Vec<(String, Arc<dyn Storage>)>,
+    /// Operation total and time of the previous collection, for ops/sec
+    last_collection: Mutex<Option<(Instant, u64)>>,
+}
+
+impl StorageMetrics {
+    /// Export metrics for `storage`, labelled `storage=<name>`
+    pub fn new(name: impl Into<String>, storage: Arc<dyn Storage>) -> Self {
+        Self {
+            name: name.into(),
+            storage,
+            namespaces: Vec::new(),
+            last_collection: Mutex::new(None),
+        }
+    }
+
+    /// Also report the key count and size of a namespace
+    pub fn with_namespace(mut self, name: impl Into<String>, namespace: Arc<dyn Storage>) -> Self {
+        self.namespaces.push((name.into(), namespace));
+        self
+    }
+
+    /// Operations per second since the previous collection (0 on the first)
+    fn ops_per_second(&self, total_ops: u64) -> f64 {
+        let now = Instant::now();
+        let mut last = self.last_collection.lock();
+        let rate = match *last {
+            Some((at, ops)) => {
+                let elapsed = now.duration_since(at).as_secs_f64();
+                if elapsed > 0.0 {
+                    total_ops.saturating_sub(ops) as f64 / elapsed
+                } else {
+                    0.0
+                }
+            }
+            None => 0.0,
+        };
+        *last = Some((now, total_ops));
+        rate
+    }
+}
+
+impl MetricsSource for StorageMetrics {
+    fn source_name(&self) -> &str {
+        &self.name
+    }
+
+    fn collect(&self) -> Vec<MetricSample> {
+        let stats = self.storage.stats();
+        let total_ops = stats.reads + stats.writes + stats.deletes;
+
+        let mut samples = vec![
+            MetricSample::counter(
+                "cc_storage_reads_total",
+                "Storage read operations",
+                stats.reads as f64,
+            ),
+            MetricSample::counter(
+                "cc_storage_writes_total",
+                "Storage write operations",
+                stats.writes as f64,
+            ),
+            MetricSample::counter(
+                "cc_storage_deletes_total",
+                "Storage delete operations",
+                stats.deletes as f64,
+            ),
+            MetricSample::gauge(
+                "cc_storage_ops_per_second",
+                "Storage operations per second since the previous scrape",
+                self.ops_per_second(total_ops),
+            ),
+            MetricSample::gauge(
+                "cc_storage_keys",
+                "Keys in the store",
+                stats.total_keys as f64,
+            ),
+            MetricSample::gauge(
+                "cc_storage_size_bytes",
+                "Bytes used by keys and values",
+                stats.total_size_bytes as f64,
+            ),
+        ];
+        if stats.compressed_bytes > 0 {
+            samples.push(MetricSample::gauge(
+                "cc_storage_compression_ratio",
+                "Ratio of uncompressed to stored value bytes",
+                stats.compression_ratio(),
+            ));
+        }
+        let mut samples: Vec<MetricSample> = samples
+            .into_iter()
+            .map(|sample| sample.with_label("storage", self.name.clone()))
+            .collect();
+
+        for (namespace, storage) in &self.namespaces {
+            let stats = storage.stats();
+            samples.push(
+                MetricSample::gauge(
+                    "cc_storage_namespace_keys",
+                    "Keys in a storage namespace",
+                    stats.total_keys as f64,
+                )
+                .with_label("storage", self.name.clone())
+                .with_label("namespace", namespace.clone()),
+            );
+            samples.push(
+                MetricSample::gauge(
+                    "cc_storage_namespace_size_bytes",
+                    "Bytes used by keys and values in a storage namespace",
+                    stats.total_size_bytes as f64,
+                )
+                .with_label("storage", self.name.clone())
+                .with_label("namespace", namespace.clone()),
+            );
+        }
+        samples
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InMemoryStorage, NamespacedStorage};
+
+    fn value(samples: &[MetricSample], name: &str) -> f64 {
+        samples.iter().find(|s| s.name == name).unwrap().value
+    }
+
+    #[test]
+    fn test_storage_metrics() {
+        let storage = Arc::new(InMemoryStorage::new());
+        storage.put(b"a", b"1").unwrap();
+        storage.get(b"a").unwrap();
+        storage.delete(b"b").unwrap();
+
+        let metrics = StorageMetrics::new("chain", storage.clone());
+        let samples = metrics.collect();
+        assert_eq!(value(&samples, "cc_storage_reads_total"), 1.0);
+        assert_eq!(value(&samples, "cc_storage_writes_total"), 1.0);
+        assert_eq!(value(&samples, "cc_storage_deletes_total"), 1.0);
+        assert_eq!(value(&samples, "cc_storage_keys"), 1.0);
+        assert_eq!(value(&samples, "cc_storage_ops_per_second"), 0.0);
+        assert!(samples
+            .iter()
+            .all(|s| s.labels[0] == ("storage".to_string(), "chain".to_string())));
+
+        storage.put(b"c", b"3").unwrap();
+        let samples = metrics.collect();
+        assert!(value(&samples, "cc_storage_ops_per_second") > 0.0);
+    }
+
+    #[test]
+    fn test_namespace_sizes() {
+        let storage = NamespacedStorage::new(InMemoryStorage::new());
+        let blocks = storage.open_namespace("blocks").unwrap();
+        blocks.put(b"1", b"block").unwrap();
+        storage
+            .open_namespace("state")
+            .unwrap()
+            .put(b"x", b"y")
+            .unwrap();
+
+        let metrics = StorageMetrics::new("chain", Arc::new(blocks.clone()))
+            .with_namespace("blocks", Arc::new(blocks));
+        let samples = metrics.collect();
+
+        let keys = samples
+            .iter()
+            .find(|s| s.name == "cc_storage_namespace_keys")
+            .unwrap();
+        assert_eq!(keys.value, 1.0);
+        assert!(keys
+            .labels
+            .contains(&("namespace".to_string(), "blocks".to_string())));
+        assert_eq!(value(&samples, "cc_storage_namespace_size_bytes"), 6.0);
+    }
+}
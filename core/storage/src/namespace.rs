@@ -0,0 +1,272 @@
+use crate::{
+    BatchOperation, BatchStorage, MaterializedSnapshot, MetadataStorage, Result, Storage,
+    StorageError, StorageIterator, StorageMetadata, StorageSnapshot, StorageStats,
+};
+use std::sync::Arc;
+
+/// Namespaces over a single shared storage backend
+///
+/// Each namespace owns the keys starting with its length-prefixed name, so
+/// `blocks` and `blocks_v2` can never see each other's entries. Handles are
+/// cheap to create and can be handed to subsystems as plain `Storage`.
+pub struct NamespacedStorage<S: Storage> {
+    inner: Arc<S>,
+}
+
+impl<S: Storage> NamespacedStorage<S> {
+    /// Share `inner` between namespaces
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Get the shared backend
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Open a handle on the namespace called `name`
+    pub fn open_namespace(&self, name: &str) -> Result<Namespace<S>> {
+        Ok(Namespace {
+            inner: self.inner.clone(),
+            name: name.to_string(),
+            prefix: namespace_prefix(name)?,
+        })
+    }
+}
+
+impl<S: BatchStorage> NamespacedStorage<S> {
+    /// Apply operations spanning several namespaces as one atomic batch
+    pub fn batch(&self, operations: Vec<(&Namespace<S>, BatchOperation)>) -> Result<()> {
+        let operations = operations
+            .into_iter()
+            .map(|(namespace, operation)| namespace.prefix_operation(operation))
+            .collect();
+        self.inner.batch(operations)
+    }
+}
+
+fn namespace_prefix(name: &str) -> Result<Vec<u8>> {
+    if name.is_empty() || name.len() > u8::MAX as usize {
+        return Err(StorageError::Config(format!(
+            "Namespace name must be 1-255 bytes: {:?}",
+            name
+        )));
+    }
+    let mut prefix = Vec::with_capacity(name.len() + 1);
+    prefix.push(name.len() as u8);
+    prefix.extend_from_slice(name.as_bytes());
+    Ok(prefix)
+}
+
+/// Handle on one namespace of a `NamespacedStorage`
+///
+/// Keys passed to and returned from a namespace never include its prefix.
+pub struct Namespace<S: Storage> {
+    inner: Arc<S>,
+    name: String,
+    prefix: Vec<u8>,
+}
+
+impl<S: Storage> Clone for Namespace<S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            name: self.name.clone(),
+            prefix: self.prefix.clone(),
+        }
+    }
+}
+
+impl<S: Storage> Namespace<S> {
+    /// Namespace name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn full_key(&self, key: &[u8]) -> Vec<u8> {
+        [self.prefix.as_slice(), key].concat()
+    }
+
+    fn prefix_operation(&self, operation: BatchOperation) -> BatchOperation {
+        match operation {
+            BatchOperation::Put { key, value } => BatchOperation::Put {
+                key: self.full_key(&key),
+                value,
+            },
+            BatchOperation::Delete { key } => BatchOperation::Delete {
+                key: self.full_key(&key),
+            },
+        }
+    }
+
+    fn strip_prefix<'a>(&'a self, entries: StorageIterator<'a>) -> StorageIterator<'a> {
+        let prefix_len = self.prefix.len();
+        Box::new(entries.map(move |entry| {
+            entry.map(|(mut key, value)| {
+                key.drain(..prefix_len);
+                (key, value)
+            })
+        }))
+    }
+}
+
+impl<S: Storage> Storage for Namespace<S> {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.inner.get(&self.full_key(key))
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.inner.put(&self.full_key(key), value)
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        self.inner.delete(&self.full_key(key))
+    }
+
+    fn contains(&self, key: &[u8]) -> Result<bool> {
+        self.inner.contains(&self.full_key(key))
+    }
+
+    fn keys(&self) -> Result<Vec<Vec<u8>>> {
+        self.scan_prefix(&[])?
+            .map(|entry| entry.map(|(key, _)| key))
+            .collect()
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<StorageIterator<'_>> {
+        let entries = self.inner.scan_prefix(&self.full_key(prefix))?;
+        Ok(self.strip_prefix(entries))
+    }
+
+    fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<StorageIterator<'_>> {
+        let entries = self
+            .inner
+            .scan_range(&self.full_key(start), &self.full_key(end))?;
+        Ok(self.strip_prefix(entries))
+    }
+
+    fn snapshot(&self) -> Result<Box<dyn StorageSnapshot + '_>> {
+        let inner = self.inner.snapshot()?;
+        let entries = self.strip_prefix(inner.scan_prefix(&self.prefix)?);
+        Ok(Box::new(MaterializedSnapshot::collect(entries)?))
+    }
+
+    /// Key count and size cover this namespace only; this walks the
+    /// namespace, so it costs O(entries). Operation counters are shared.
+    fn stats(&self) -> StorageStats {
+        let (mut total_keys, mut total_size_bytes) = (0, 0);
+        if let Ok(entries) = self.inner.scan_prefix(&self.prefix) {
+            for (key, value) in entries.flatten() {
+                total_keys += 1;
+                total_size_bytes += (key.len() - self.prefix.len() + value.len()) as u64;
+            }
+        }
+        StorageStats {
+            total_keys,
+            total_size_bytes,
+            ..self.inner.stats()
+        }
+    }
+}
+
+impl<S: BatchStorage> BatchStorage for Namespace<S> {
+    fn batch(&self, operations: Vec<BatchOperation>) -> Result<()> {
+        let operations = operations
+            .into_iter()
+            .map(|operation| self.prefix_operation(operation))
+            .collect();
+        self.inner.batch(operations)
+    }
+}
+
+impl<S: MetadataStorage> MetadataStorage for Namespace<S> {
+    fn get_metadata(&self, key: &[u8]) -> Result<Option<StorageMetadata>> {
+        self.inner.get_metadata(&self.full_key(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryStorage;
+
+    #[test]
+    fn test_namespaces_are_isolated() {
+        let storage = NamespacedStorage::new(InMemoryStorage::new());
+        let blocks = storage.open_namespace("blocks").unwrap();
+        let blocks_v2 = storage.open_namespace("blocks_v2").unwrap();
+
+        blocks.put(b"1", b"genesis").unwrap();
+        blocks_v2.put(b"1", b"other").unwrap();
+
+        assert_eq!(blocks.get(b"1").unwrap(), Some(b"genesis".to_vec()));
+        assert_eq!(blocks.keys().unwrap(), vec![b"1".to_vec()]);
+        assert_eq!(blocks_v2.scan_prefix(b"").unwrap().count(), 1);
+        assert_eq!(blocks.stats().total_keys, 1);
+        assert_eq!(storage.inner().len(), 2);
+    }
+
+    #[test]
+    fn test_scans_strip_prefix() {
+        let storage = NamespacedStorage::new(InMemoryStorage::new());
+        let state = storage.open_namespace("state").unwrap();
+        for key in [&b"acct:a"[..], b"acct:b", b"code:a"] {
+            state.put(key, b"v").unwrap();
+        }
+
+        let accounts: Vec<Vec<u8>> = state
+            .scan_prefix(b"acct:")
+            .unwrap()
+            .map(|entry| entry.unwrap().0)
+            .collect();
+        assert_eq!(accounts, vec![b"acct:a".to_vec(), b"acct:b".to_vec()]);
+        assert_eq!(state.scan_range(b"acct:b", b"code:b").unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_cross_namespace_batch() {
+        let storage = NamespacedStorage::new(InMemoryStorage::new());
+        let blocks = storage.open_namespace("blocks").unwrap();
+        let state = storage.open_namespace("state").unwrap();
+
+        storage
+            .batch(vec![
+                (
+                    &blocks,
+                    BatchOperation::Put {
+                        key: b"10".to_vec(),
+                        value: b"block".to_vec(),
+                    },
+                ),
+                (
+                    &state,
+                    BatchOperation::Put {
+                        key: b"root".to_vec(),
+                        value: b"hash".to_vec(),
+                    },
+                ),
+            ])
+            .unwrap();
+
+        assert_eq!(blocks.get(b"10").unwrap(), Some(b"block".to_vec()));
+        assert_eq!(state.get_metadata(b"root").unwrap().unwrap().version, 1);
+        assert!(storage.open_namespace("").is_err());
+    }
+
+    #[test]
+    fn test_snapshot_covers_only_the_namespace() {
+        let storage = NamespacedStorage::new(InMemoryStorage::new());
+        let blocks = storage.open_namespace("blocks").unwrap();
+        let state = storage.open_namespace("state").unwrap();
+        blocks.put(b"1", b"a").unwrap();
+        state.put(b"1", b"b").unwrap();
+
+        let snapshot = blocks.snapshot().unwrap();
+        blocks.put(b"2", b"c").unwrap();
+
+        assert_eq!(snapshot.get(b"1").unwrap(), Some(b"a".to_vec()));
+        assert_eq!(snapshot.scan_prefix(b"").unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,55 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Background thread running a task every interval
+///
+/// Backs the maintenance threads of the storage wrappers (`CacheFlusher`,
+/// `TtlSweeper`). The thread stops when the task is dropped or `stop` is
+/// called.
+pub(crate) struct PeriodicTask {
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl PeriodicTask {
+    /// Run `task` every `interval` on a new thread
+    pub(crate) fn start(interval: Duration, mut task: impl FnMut() + Send + 'static) -> Self {
+        let running = Arc::new(AtomicBool::new(true));
+        let flag = running.clone();
+        let handle = thread::spawn(move || {
+            // Sleep in short steps so stopping does not wait a full interval,
+            // but never zero-length ones, which would spin on a zero interval
+            let step = interval.clamp(Duration::from_millis(1), Duration::from_millis(50));
+            let mut elapsed = Duration::ZERO;
+            while flag.load(Ordering::Relaxed) {
+                thread::sleep(step);
+                elapsed += step;
+                if elapsed >= interval {
+                    elapsed = Duration::ZERO;
+                    task();
+                }
+            }
+        });
+
+        Self {
+            running,
+            handle: Some(handle),
+        }
+    }
+
+    /// Stop the thread and wait for it to exit
+    pub(crate) fn stop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for PeriodicTask {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,458 @@
+use crate::{
+    BatchOperation, BatchStorage, MetadataStorage, Result, Storage, StorageError,
+    StorageIterator, StorageMetadata, StorageSnapshot, StorageStats,
+};
+use ::rocksdb::{
+    ColumnFamily, ColumnFamilyDescriptor, DBIteratorWithThreadMode, Direction, IteratorMode,
+    Options, SnapshotWithThreadMode, WriteBatch, WriteOptions, DB,
+};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Column family holding user values
+const DATA_CF: &str = "data";
+/// Column family holding `StorageMetadata` records, keyed like the data
+const METADATA_CF: &str = "metadata";
+
+/// RocksDB backend configuration
+#[derive(Debug, Clone)]
+pub struct RocksDbConfig {
+    pub path: PathBuf,
+    pub create_if_missing: bool,
+    /// fsync the WAL on every write
+    pub sync_writes: bool,
+    /// Skip the WAL entirely; writes may be lost on crash
+    pub disable_wal: bool,
+    pub max_open_files: i32,
+    pub write_buffer_size: usize,
+}
+
+impl Default for RocksDbConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("data/rocksdb"),
+            create_if_missing: true,
+            sync_writes: false,
+            disable_wal: false,
+            max_open_files: 1024,
+            write_buffer_size: 64 * 1024 * 1024, // 64MB
+        }
+    }
+}
+
+impl RocksDbConfig {
+    /// Default configuration for a database at `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<::rocksdb::Error> for StorageError {
+    fn from(err: ::rocksdb::Error) -> Self {
+        StorageError::Backend(err.to_string())
+    }
+}
+
+/// Persistent storage backend on top of RocksDB
+///
+/// Values and their metadata live in separate column families and are always
+/// written together in a single `WriteBatch`.
+pub struct RocksDbStorage {
+    db: DB,
+    config: RocksDbConfig,
+    /// Serializes writers, so the metadata version read at the start of a
+    /// batch is still current when the batch is written
+    write_lock: Mutex<()>,
+    reads: AtomicU64,
+    writes: AtomicU64,
+    deletes: AtomicU64,
+}
+
+impl RocksDbStorage {
+    /// Open (or create) a database with the given configuration
+    pub fn open(config: RocksDbConfig) -> Result<Self> {
+        let mut options = Options::default();
+        options.create_if_missing(config.create_if_missing);
+        options.create_missing_column_families(true);
+        options.set_max_open_files(config.max_open_files);
+        options.set_write_buffer_size(config.write_buffer_size);
+
+        let descriptors = vec![
+            ColumnFamilyDescriptor::new(DATA_CF, Options::default()),
+            ColumnFamilyDescriptor::new(METADATA_CF, Options::default()),
+        ];
+        let db = DB::open_cf_descriptors(&options, &config.path, descriptors)?;
+
+        Ok(Self {
+            db,
+            config,
+            write_lock: Mutex::new(()),
+            reads: AtomicU64::new(0),
+            writes: AtomicU64::new(0),
+            deletes: AtomicU64::new(0),
+        })
+    }
+
+    /// Get the backend configuration
+    pub fn config(&self) -> &RocksDbConfig {
+        &self.config
+    }
+
+    /// Flush memtables of both column families to disk
+    pub fn flush(&self) -> Result<()> {
+        self.db.flush_cf(self.data_cf()?)?;
+        self.db.flush_cf(self.metadata_cf()?)?;
+        Ok(())
+    }
+
+    fn data_cf(&self) -> Result<&ColumnFamily> {
+        self.column_family(DATA_CF)
+    }
+
+    fn metadata_cf(&self) -> Result<&ColumnFamily> {
+        self.column_family(METADATA_CF)
+    }
+
+    fn column_family(&self, name: &str) -> Result<&ColumnFamily> {
+        self.db
+            .cf_handle(name)
+            .ok_or_else(|| StorageError::Backend(format!("Missing column family: {}", name)))
+    }
+
+    fn write_options(&self) -> WriteOptions {
+        let mut options = WriteOptions::default();
+        options.set_sync(self.config.sync_writes);
+        options.disable_wal(self.config.disable_wal);
+        options
+    }
+
+    fn read_metadata(&self, key: &[u8]) -> Result<Option<StorageMetadata>> {
+        match self.db.get_cf(self.metadata_cf()?, key)? {
+            Some(bytes) => Ok(Some(bincode::deserialize(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn property(&self, name: &str) -> u64 {
+        self.data_cf()
+            .ok()
+            .and_then(|cf| self.db.property_int_value_cf(cf, name).ok().flatten())
+            .unwrap_or(0)
+    }
+}
+
+impl Storage for RocksDbStorage {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.reads.fetch_add(1, Ordering::Relaxed);
+        // Read the value and its checksum from one snapshot so a concurrent
+        // write cannot pair a new value with old metadata
+        let snapshot = self.db.snapshot();
+        let Some(value) = snapshot.get_cf(self.data_cf()?, key)? else {
+            return Ok(None);
+        };
+        if let Some(bytes) = snapshot.get_cf(self.metadata_cf()?, key)? {
+            bincode::deserialize::<StorageMetadata>(&bytes)?.verify(key, &value)?;
+        }
+        Ok(Some(value))
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.batch(vec![BatchOperation::Put {
+            key: key.to_vec(),
+            value: value.to_vec(),
+        }])
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        self.batch(vec![BatchOperation::Delete { key: key.to_vec() }])
+    }
+
+    fn keys(&self) -> Result<Vec<Vec<u8>>> {
+        self.db
+            .iterator_cf(self.data_cf()?, IteratorMode::Start)
+            .map(|item| item.map(|(key, _)| key.to_vec()).map_err(StorageError::from))
+            .collect()
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<StorageIterator<'_>> {
+        let iter = self
+            .db
+            .iterator_cf(self.data_cf()?, IteratorMode::From(prefix, Direction::Forward));
+        Ok(prefix_iter(iter, prefix))
+    }
+
+    fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<StorageIterator<'_>> {
+        let iter = self
+            .db
+            .iterator_cf(self.data_cf()?, IteratorMode::From(start, Direction::Forward));
+        Ok(range_iter(iter, end))
+    }
+
+    fn snapshot(&self) -> Result<Box<dyn StorageSnapshot + '_>> {
+        Ok(Box::new(RocksDbSnapshot {
+            snapshot: self.db.snapshot(),
+            data_cf: self.data_cf()?,
+        }))
+    }
+
+    fn stats(&self) -> StorageStats {
+        StorageStats {
+            total_keys: self.property("rocksdb.estimate-num-keys"),
+            total_size_bytes: self.property("rocksdb.total-sst-files-size")
+                + self.property("rocksdb.cur-size-all-mem-tables"),
+            reads: self.reads.load(Ordering::Relaxed),
+            writes: self.writes.load(Ordering::Relaxed),
+            deletes: self.deletes.load(Ordering::Relaxed),
+            ..Default::default()
+        }
+    }
+}
+
+/// Entries of `iter` while their key starts with `prefix`
+fn prefix_iter<'a>(iter: DBIteratorWithThreadMode<'a, DB>, prefix: &[u8]) -> StorageIterator<'a> {
+    let prefix = prefix.to_vec();
+    Box::new(
+        iter.map(|item| item.map(|(k, v)| (k.to_vec(), v.to_vec())).map_err(StorageError::from))
+            .take_while(move |item| match item {
+                Ok((key, _)) => key.starts_with(&prefix),
+                Err(_) => true,
+            }),
+    )
+}
+
+/// Entries of `iter` while their key is below `end`
+fn range_iter<'a>(iter: DBIteratorWithThreadMode<'a, DB>, end: &[u8]) -> StorageIterator<'a> {
+    let end = end.to_vec();
+    Box::new(
+        iter.map(|item| item.map(|(k, v)| (k.to_vec(), v.to_vec())).map_err(StorageError::from))
+            .take_while(move |item| match item {
+                Ok((key, _)) => *key < end,
+                Err(_) => true,
+            }),
+    )
+}
+
+/// Native RocksDB snapshot of the data column family
+struct RocksDbSnapshot<'a> {
+    snapshot: SnapshotWithThreadMode<'a, DB>,
+    data_cf: &'a ColumnFamily,
+}
+
+impl StorageSnapshot for RocksDbSnapshot<'_> {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        Ok(self.snapshot.get_cf(self.data_cf, key)?)
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<StorageIterator<'_>> {
+        let iter = self
+            .snapshot
+            .iterator_cf(self.data_cf, IteratorMode::From(prefix, Direction::Forward));
+        Ok(prefix_iter(iter, prefix))
+    }
+
+    fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<StorageIterator<'_>> {
+        let iter = self
+            .snapshot
+            .iterator_cf(self.data_cf, IteratorMode::From(start, Direction::Forward));
+        Ok(range_iter(iter, end))
+    }
+}
+
+impl BatchStorage for RocksDbStorage {
+    fn batch(&self, operations: Vec<BatchOperation>) -> Result<()> {
+        let data_cf = self.data_cf()?;
+        let metadata_cf = self.metadata_cf()?;
+        let mut batch = WriteBatch::default();
+        // Metadata staged earlier in this batch, so repeated writes bump the version correctly
+        let mut staged: HashMap<Vec<u8>, Option<StorageMetadata>> = HashMap::new();
+        let (mut writes, mut deletes) = (0, 0);
+        let _guard = self.write_lock.lock();
+
+        for operation in operations {
+            match operation {
+                BatchOperation::Put { key, value } => {
+                    let previous = match staged.get(&key) {
+                        Some(previous) => previous.clone(),
+                        None => self.read_metadata(&key)?,
+                    };
+                    let metadata = StorageMetadata::next(previous.as_ref(), &value);
+                    batch.put_cf(data_cf, &key, &value);
+                    batch.put_cf(metadata_cf, &key, bincode::serialize(&metadata)?);
+                    staged.insert(key, Some(metadata));
+                    writes += 1;
+                }
+                BatchOperation::Delete { key } => {
+                    batch.delete_cf(data_cf, &key);
+                    batch.delete_cf(metadata_cf, &key);
+                    staged.insert(key, None);
+                    deletes += 1;
+                }
+            }
+        }
+
+        self.db.write_opt(batch, &self.write_options())?;
+        self.writes.fetch_add(writes, Ordering::Relaxed);
+        self.deletes.fetch_add(deletes, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+impl MetadataStorage for RocksDbStorage {
+    fn get_metadata(&self, key: &[u8]) -> Result<Option<StorageMetadata>> {
+        self.read_metadata(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_temp() -> (tempfile::TempDir, RocksDbStorage) {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = RocksDbStorage::open(RocksDbConfig::new(dir.path())).unwrap();
+        (dir, storage)
+    }
+
+    #[test]
+    fn test_put_get_delete() {
+        let (_dir, storage) = open_temp();
+
+        storage.put(b"key", b"value").unwrap();
+        assert_eq!(storage.get(b"key").unwrap(), Some(b"value".to_vec()));
+
+        storage.delete(b"key").unwrap();
+        assert_eq!(storage.get(b"key").unwrap(), None);
+        assert!(storage.get_metadata(b"key").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_concurrent_writes_bump_versions() {
+        let (_dir, storage) = open_temp();
+        let storage = std::sync::Arc::new(storage);
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let storage = storage.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..25 {
+                        storage.put(b"key", b"value").unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(storage.get_metadata(b"key").unwrap().unwrap().version, 100);
+    }
+
+    #[test]
+    fn test_batch_metadata_versions() {
+        let (_dir, storage) = open_temp();
+
+        storage
+            .batch(vec![
+                BatchOperation::Put { key: b"a".to_vec(), value: b"1".to_vec() },
+                BatchOperation::Put { key: b"a".to_vec(), value: b"22".to_vec() },
+                BatchOperation::Put { key: b"b".to_vec(), value: b"3".to_vec() },
+            ])
+            .unwrap();
+
+        let metadata = storage.get_metadata(b"a").unwrap().unwrap();
+        assert_eq!(metadata.version, 2);
+        assert_eq!(metadata.size, 2);
+        assert_eq!(storage.keys().unwrap(), vec![b"a".to_vec(), b"b".to_vec()]);
+    }
+
+    #[test]
+    fn test_reopen_persists_data() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let storage = RocksDbStorage::open(RocksDbConfig::new(dir.path())).unwrap();
+            storage.put(b"persisted", b"yes").unwrap();
+            storage.flush().unwrap();
+        }
+
+        let storage = RocksDbStorage::open(RocksDbConfig::new(dir.path())).unwrap();
+        assert_eq!(storage.get(b"persisted").unwrap(), Some(b"yes".to_vec()));
+    }
+
+    #[test]
+    fn test_failed_batch_is_not_written() {
+        let (_dir, storage) = open_temp();
+        storage.put(b"a", b"1").unwrap();
+        // Corrupt the metadata of "c" so staging fails partway through the batch
+        storage
+            .db
+            .put_cf(storage.metadata_cf().unwrap(), b"c", [0xff])
+            .unwrap();
+
+        let result = storage.batch(vec![
+            BatchOperation::Put { key: b"a".to_vec(), value: b"2".to_vec() },
+            BatchOperation::Put { key: b"b".to_vec(), value: b"3".to_vec() },
+            BatchOperation::Put { key: b"c".to_vec(), value: b"4".to_vec() },
+        ]);
+
+        assert!(matches!(result, Err(StorageError::Serialization(_))));
+        assert_eq!(storage.get(b"a").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(storage.get(b"b").unwrap(), None);
+        assert_eq!(storage.get_metadata(b"a").unwrap().unwrap().version, 1);
+    }
+
+    #[test]
+    fn test_scan_prefix_and_range() {
+        let (_dir, storage) = open_temp();
+        for key in ["block:1", "block:2", "state:1", "tx:1"] {
+            storage.put(key.as_bytes(), b"v").unwrap();
+        }
+
+        let blocks: Vec<Vec<u8>> = storage
+            .scan_prefix(b"block:")
+            .unwrap()
+            .map(|entry| entry.unwrap().0)
+            .collect();
+        assert_eq!(blocks, vec![b"block:1".to_vec(), b"block:2".to_vec()]);
+
+        let range: Vec<Vec<u8>> = storage
+            .scan_range(b"block:2", b"tx:1")
+            .unwrap()
+            .map(|entry| entry.unwrap().0)
+            .collect();
+        assert_eq!(range, vec![b"block:2".to_vec(), b"state:1".to_vec()]);
+    }
+
+    #[test]
+    fn test_snapshot_is_stable() {
+        let (_dir, storage) = open_temp();
+        storage.put(b"a", b"1").unwrap();
+
+        let snapshot = storage.snapshot().unwrap();
+        storage.put(b"a", b"2").unwrap();
+        storage.put(b"b", b"3").unwrap();
+
+        assert_eq!(snapshot.get(b"a").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(snapshot.scan_prefix(b"").unwrap().count(), 1);
+        assert_eq!(storage.get(b"a").unwrap(), Some(b"2".to_vec()));
+    }
+
+    #[test]
+    fn test_corrupted_value_detected_on_read() {
+        let (_dir, storage) = open_temp();
+        storage.put(b"key", b"value").unwrap();
+        storage
+            .db
+            .put_cf(storage.data_cf().unwrap(), b"key", b"garbage")
+            .unwrap();
+
+        assert!(matches!(
+            storage.get(b"key"),
+            Err(StorageError::Corruption { .. })
+        ));
+    }
+}
@@ -0,0 +1,277 @@
+use crate::memory::{MemoryScan, MemoryState};
+use crate::{
+    prefix_end, BatchOperation, BatchStorage, MaterializedSnapshot, MetadataStorage, Result,
+    Storage, StorageIterator, StorageMetadata, StorageSnapshot, StorageStats,
+};
+use parking_lot::RwLock;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::iter::Peekable;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Default number of shards
+pub const DEFAULT_SHARD_COUNT: usize = 16;
+
+/// In-memory storage split across independently locked shards
+///
+/// Keys are assigned to shards by hash, so point reads and writes on
+/// different keys rarely contend. Batches lock every shard they touch (in
+/// shard order, to avoid deadlocks) and remain all-or-nothing. Scans merge
+/// the ordered shard maps, so iteration order is the same as for
+/// `InMemoryStorage`.
+pub struct ShardedMemoryStorage {
+    shards: Vec<RwLock<MemoryState>>,
+    hasher: RandomState,
+    reads: AtomicU64,
+    writes: AtomicU64,
+    deletes: AtomicU64,
+}
+
+impl ShardedMemoryStorage {
+    /// Create an empty store with the default number of shards
+    pub fn new() -> Self {
+        Self::with_shards(DEFAULT_SHARD_COUNT)
+    }
+
+    /// Create an empty store with `shard_count` shards (at least one)
+    pub fn with_shards(shard_count: usize) -> Self {
+        Self {
+            shards: (0..shard_count.max(1)).map(|_| RwLock::default()).collect(),
+            hasher: RandomState::new(),
+            reads: AtomicU64::new(0),
+            writes: AtomicU64::new(0),
+            deletes: AtomicU64::new(0),
+        }
+    }
+
+    /// Number of shards
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Number of stored entries
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.read().data.len()).sum()
+    }
+
+    /// Whether the store is empty
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| shard.read().data.is_empty())
+    }
+
+    fn shard_index(&self, key: &[u8]) -> usize {
+        (self.hasher.hash_one(key) % self.shards.len() as u64) as usize
+    }
+
+    fn shard(&self, key: &[u8]) -> &RwLock<MemoryState> {
+        &self.shards[self.shard_index(key)]
+    }
+
+    fn scan(&self, start: Vec<u8>, end: Option<Vec<u8>>) -> ShardedScan<'_> {
+        ShardedScan {
+            scans: self
+                .shards
+                .iter()
+                .map(|shard| MemoryScan::new(shard, start.clone(), end.clone()).peekable())
+                .collect(),
+        }
+    }
+}
+
+impl Default for ShardedMemoryStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Storage for ShardedMemoryStorage {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.reads.fetch_add(1, Ordering::Relaxed);
+        self.shard(key).read().get_verified(key)
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.shard(key).write().apply(BatchOperation::Put {
+            key: key.to_vec(),
+            value: value.to_vec(),
+        });
+        self.writes.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        self.shard(key)
+            .write()
+            .apply(BatchOperation::Delete { key: key.to_vec() });
+        self.deletes.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn contains(&self, key: &[u8]) -> Result<bool> {
+        Ok(self.shard(key).read().data.contains_key(key))
+    }
+
+    fn keys(&self) -> Result<Vec<Vec<u8>>> {
+        self.scan(Vec::new(), None)
+            .map(|entry| entry.map(|(key, _)| key))
+            .collect()
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<StorageIterator<'_>> {
+        Ok(Box::new(self.scan(prefix.to_vec(), prefix_end(prefix))))
+    }
+
+    fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<StorageIterator<'_>> {
+        Ok(Box::new(self.scan(start.to_vec(), Some(end.to_vec()))))
+    }
+
+    fn snapshot(&self) -> Result<Box<dyn StorageSnapshot + '_>> {
+        // Hold every shard's read lock at once so the copy is a single point in time
+        let guards: Vec<_> = self.shards.iter().map(|shard| shard.read()).collect();
+        let data = guards
+            .iter()
+            .flat_map(|shard| shard.data.iter().map(|(k, v)| (k.clone(), v.clone())))
+            .collect();
+        Ok(Box::new(MaterializedSnapshot::new(data)))
+    }
+
+    fn stats(&self) -> StorageStats {
+        let (total_keys, total_size_bytes) = self.shards.iter().fold((0, 0), |(keys, size), shard| {
+            let shard = shard.read();
+            (keys + shard.data.len() as u64, size + shard.size_bytes)
+        });
+        StorageStats {
+            total_keys,
+            total_size_bytes,
+            reads: self.reads.load(Ordering::Relaxed),
+            writes: self.writes.load(Ordering::Relaxed),
+            deletes: self.deletes.load(Ordering::Relaxed),
+            ..Default::default()
+        }
+    }
+}
+
+impl BatchStorage for ShardedMemoryStorage {
+    fn batch(&self, operations: Vec<BatchOperation>) -> Result<()> {
+        let indices: Vec<usize> = operations.iter().map(|op| self.shard_index(op.key())).collect();
+        let mut involved = indices.clone();
+        involved.sort_unstable();
+        involved.dedup();
+
+        // Lock in ascending shard order so concurrent batches cannot deadlock
+        let mut guards: Vec<_> = involved.iter().map(|&i| self.shards[i].write()).collect();
+
+        let writes = operations
+            .iter()
+            .filter(|op| matches!(op, BatchOperation::Put { .. }))
+            .count() as u64;
+        let deletes = operations.len() as u64 - writes;
+        for (operation, index) in operations.into_iter().zip(indices) {
+            let guard = involved.binary_search(&index).expect("shard was locked above");
+            guards[guard].apply(operation);
+        }
+        drop(guards);
+
+        self.writes.fetch_add(writes, Ordering::Relaxed);
+        self.deletes.fetch_add(deletes, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+impl MetadataStorage for ShardedMemoryStorage {
+    fn get_metadata(&self, key: &[u8]) -> Result<Option<StorageMetadata>> {
+        Ok(self.shard(key).read().metadata.get(key).cloned())
+    }
+}
+
+/// Ordered merge of the per-shard scans
+struct ShardedScan<'a> {
+    scans: Vec<Peekable<MemoryScan<'a>>>,
+}
+
+impl Iterator for ShardedScan<'_> {
+    type Item = Result<(Vec<u8>, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut smallest: Option<(usize, &[u8])> = None;
+        for (index, scan) in self.scans.iter_mut().enumerate() {
+            if let Some(Ok((key, _))) = scan.peek() {
+                if smallest.is_none_or(|(_, current)| key.as_slice() < current) {
+                    smallest = Some((index, key.as_slice()));
+                }
+            }
+        }
+        let (index, _) = smallest?;
+        self.scans[index].next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_basic_operations_and_ordered_scans() {
+        let storage = ShardedMemoryStorage::with_shards(4);
+        for i in (0..100u8).rev() {
+            storage.put(&[b'k', i], &[i]).unwrap();
+        }
+        storage.put(b"other", b"x").unwrap();
+        storage.delete(&[b'k', 50]).unwrap();
+
+        assert_eq!(storage.len(), 100);
+        assert_eq!(storage.get(&[b'k', 7]).unwrap(), Some(vec![7]));
+        assert_eq!(storage.get_metadata(&[b'k', 7]).unwrap().unwrap().version, 1);
+
+        let keys: Vec<Vec<u8>> = storage
+            .scan_prefix(b"k")
+            .unwrap()
+            .map(|entry| entry.unwrap().0)
+            .collect();
+        assert_eq!(keys.len(), 99);
+        assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let range = storage.scan_range(&[b'k', 10], &[b'k', 20]).unwrap().count();
+        assert_eq!(range, 10);
+        assert_eq!(storage.keys().unwrap().last().unwrap(), b"other");
+    }
+
+    #[test]
+    fn test_batch_across_shards() {
+        let storage = ShardedMemoryStorage::with_shards(8);
+        let operations = (0..64u8)
+            .map(|i| BatchOperation::Put { key: vec![i], value: vec![i] })
+            .chain([BatchOperation::Delete { key: vec![3] }])
+            .collect();
+        storage.batch(operations).unwrap();
+
+        assert_eq!(storage.len(), 63);
+        assert_eq!(storage.get(&[3]).unwrap(), None);
+        let stats = storage.stats();
+        assert_eq!((stats.writes, stats.deletes, stats.total_keys), (64, 1, 63));
+    }
+
+    #[test]
+    fn test_concurrent_writers() {
+        let storage = Arc::new(ShardedMemoryStorage::new());
+        let handles: Vec<_> = (0..8u8)
+            .map(|thread_id| {
+                let storage = storage.clone();
+                thread::spawn(move || {
+                    for i in 0..500u16 {
+                        let key = [&[thread_id][..], &i.to_be_bytes()[..]].concat();
+                        storage.put(&key, b"v").unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(storage.len(), 4000);
+        assert_eq!(storage.scan_prefix(&[3]).unwrap().count(), 500);
+    }
+}
@@ -0,0 +1,356 @@
+use crate::{
+    BatchOperation, BatchStorage, MetadataStorage, Result, Storage, StorageError,
+    StorageIterator, StorageMetadata, StorageStats,
+};
+use ::sled::transaction::{ConflictableTransactionError, TransactionError};
+use ::sled::{Db, Transactional, Tree};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Tree holding user values
+const DATA_TREE: &str = "data";
+/// Tree holding `StorageMetadata` records, keyed like the data
+const METADATA_TREE: &str = "metadata";
+
+/// sled backend configuration
+#[derive(Debug, Clone)]
+pub struct SledConfig {
+    pub path: PathBuf,
+    /// Page cache size in bytes
+    pub cache_capacity: u64,
+    /// Background flush interval; `None` disables periodic flushing
+    pub flush_every_ms: Option<u64>,
+    /// Flush to disk after every write call
+    pub sync_writes: bool,
+    /// Delete the database when it is dropped (useful for tests)
+    pub temporary: bool,
+}
+
+impl Default for SledConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("data/sled"),
+            cache_capacity: 256 * 1024 * 1024, // 256MB
+            flush_every_ms: Some(500),
+            sync_writes: false,
+            temporary: false,
+        }
+    }
+}
+
+impl SledConfig {
+    /// Default configuration for a database at `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<::sled::Error> for StorageError {
+    fn from(err: ::sled::Error) -> Self {
+        StorageError::Backend(err.to_string())
+    }
+}
+
+impl From<TransactionError<StorageError>> for StorageError {
+    fn from(err: TransactionError<StorageError>) -> Self {
+        match err {
+            TransactionError::Abort(err) => err,
+            TransactionError::Storage(err) => err.into(),
+        }
+    }
+}
+
+/// Embedded pure-Rust storage backend on top of sled
+///
+/// Values and metadata live in separate trees and every write goes through a
+/// transaction spanning both, so a batch is applied entirely or not at all.
+/// sled has no native snapshots, so `snapshot()` copies the data tree.
+pub struct SledStorage {
+    db: Db,
+    data: Tree,
+    metadata: Tree,
+    config: SledConfig,
+    reads: AtomicU64,
+    writes: AtomicU64,
+    deletes: AtomicU64,
+}
+
+impl SledStorage {
+    /// Open (or create) a database with the given configuration
+    pub fn open(config: SledConfig) -> Result<Self> {
+        let db = ::sled::Config::new()
+            .path(&config.path)
+            .cache_capacity(config.cache_capacity)
+            .flush_every_ms(config.flush_every_ms)
+            .temporary(config.temporary)
+            .open()?;
+        let data = db.open_tree(DATA_TREE)?;
+        let metadata = db.open_tree(METADATA_TREE)?;
+
+        Ok(Self {
+            db,
+            data,
+            metadata,
+            config,
+            reads: AtomicU64::new(0),
+            writes: AtomicU64::new(0),
+            deletes: AtomicU64::new(0),
+        })
+    }
+
+    /// Get the backend configuration
+    pub fn config(&self) -> &SledConfig {
+        &self.config
+    }
+
+    /// Flush all dirty pages to disk, returning the number of bytes written
+    pub fn flush(&self) -> Result<usize> {
+        Ok(self.db.flush()?)
+    }
+}
+
+impl Storage for SledStorage {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.reads.fetch_add(1, Ordering::Relaxed);
+        // Read the value and its checksum in one transaction so a concurrent
+        // write cannot pair a new value with old metadata
+        let (value, metadata) = (&self.data, &self.metadata)
+            .transaction(|(data, metadata)| Ok((data.get(key)?, metadata.get(key)?)))
+            .map_err(|err: TransactionError<StorageError>| StorageError::from(err))?;
+        let Some(value) = value else {
+            return Ok(None);
+        };
+        if let Some(bytes) = metadata {
+            bincode::deserialize::<StorageMetadata>(&bytes)?.verify(key, &value)?;
+        }
+        Ok(Some(value.to_vec()))
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.batch(vec![BatchOperation::Put {
+            key: key.to_vec(),
+            value: value.to_vec(),
+        }])
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        self.batch(vec![BatchOperation::Delete { key: key.to_vec() }])
+    }
+
+    fn contains(&self, key: &[u8]) -> Result<bool> {
+        Ok(self.data.contains_key(key)?)
+    }
+
+    fn keys(&self) -> Result<Vec<Vec<u8>>> {
+        self.data
+            .iter()
+            .keys()
+            .map(|key| key.map(|key| key.to_vec()).map_err(StorageError::from))
+            .collect()
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<StorageIterator<'_>> {
+        Ok(Box::new(self.data.scan_prefix(prefix).map(convert_entry)))
+    }
+
+    fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<StorageIterator<'_>> {
+        if start >= end {
+            return Ok(Box::new(std::iter::empty()));
+        }
+        Ok(Box::new(self.data.range(start..end).map(convert_entry)))
+    }
+
+    fn stats(&self) -> StorageStats {
+        StorageStats {
+            total_keys: self.data.len() as u64,
+            total_size_bytes: self.db.size_on_disk().unwrap_or(0),
+            reads: self.reads.load(Ordering::Relaxed),
+            writes: self.writes.load(Ordering::Relaxed),
+            deletes: self.deletes.load(Ordering::Relaxed),
+            ..Default::default()
+        }
+    }
+}
+
+impl BatchStorage for SledStorage {
+    fn batch(&self, operations: Vec<BatchOperation>) -> Result<()> {
+        (&self.data, &self.metadata).transaction(|(data, metadata)| {
+            // The closure may be retried on conflict, so staging state is rebuilt each attempt
+            let mut staged: HashMap<&[u8], Option<StorageMetadata>> = HashMap::new();
+
+            for operation in &operations {
+                match operation {
+                    BatchOperation::Put { key, value } => {
+                        let previous = match staged.get(key.as_slice()) {
+                            Some(previous) => previous.clone(),
+                            None => match metadata.get(key)? {
+                                Some(bytes) => Some(
+                                    bincode::deserialize(&bytes)
+                                        .map_err(|e| ConflictableTransactionError::Abort(e.into()))?,
+                                ),
+                                None => None,
+                            },
+                        };
+                        let entry = StorageMetadata::next(previous.as_ref(), value);
+                        let encoded = bincode::serialize(&entry)
+                            .map_err(|e| ConflictableTransactionError::Abort(e.into()))?;
+
+                        data.insert(key.as_slice(), value.as_slice())?;
+                        metadata.insert(key.as_slice(), encoded)?;
+                        staged.insert(key, Some(entry));
+                    }
+                    BatchOperation::Delete { key } => {
+                        data.remove(key.as_slice())?;
+                        metadata.remove(key.as_slice())?;
+                        staged.insert(key, None);
+                    }
+                }
+            }
+            Ok(())
+        })?;
+
+        if self.config.sync_writes {
+            self.db.flush()?;
+        }
+
+        let writes = operations
+            .iter()
+            .filter(|op| matches!(op, BatchOperation::Put { .. }))
+            .count() as u64;
+        self.writes.fetch_add(writes, Ordering::Relaxed);
+        self.deletes
+            .fetch_add(operations.len() as u64 - writes, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+fn convert_entry(
+    entry: std::result::Result<(::sled::IVec, ::sled::IVec), ::sled::Error>,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let (key, value) = entry?;
+    Ok((key.to_vec(), value.to_vec()))
+}
+
+impl MetadataStorage for SledStorage {
+    fn get_metadata(&self, key: &[u8]) -> Result<Option<StorageMetadata>> {
+        match self.metadata.get(key)? {
+            Some(bytes) => Ok(Some(bincode::deserialize(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_temp() -> (tempfile::TempDir, SledStorage) {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SledStorage::open(SledConfig::new(dir.path())).unwrap();
+        (dir, storage)
+    }
+
+    #[test]
+    fn test_put_get_delete() {
+        let (_dir, storage) = open_temp();
+
+        storage.put(b"key", b"value").unwrap();
+        assert_eq!(storage.get(b"key").unwrap(), Some(b"value".to_vec()));
+        assert_eq!(storage.stats().total_keys, 1);
+
+        storage.delete(b"key").unwrap();
+        assert_eq!(storage.get(b"key").unwrap(), None);
+        assert!(storage.get_metadata(b"key").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_batch_metadata_versions() {
+        let (_dir, storage) = open_temp();
+
+        storage
+            .batch(vec![
+                BatchOperation::Put { key: b"a".to_vec(), value: b"1".to_vec() },
+                BatchOperation::Put { key: b"a".to_vec(), value: b"22".to_vec() },
+                BatchOperation::Put { key: b"b".to_vec(), value: b"3".to_vec() },
+                BatchOperation::Delete { key: b"b".to_vec() },
+            ])
+            .unwrap();
+
+        let metadata = storage.get_metadata(b"a").unwrap().unwrap();
+        assert_eq!(metadata.version, 2);
+        assert_eq!(metadata.size, 2);
+        assert_eq!(storage.keys().unwrap(), vec![b"a".to_vec()]);
+        assert_eq!(storage.stats().deletes, 1);
+    }
+
+    #[test]
+    fn test_flush_and_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let storage = SledStorage::open(SledConfig::new(dir.path())).unwrap();
+            storage.put(b"persisted", b"yes").unwrap();
+            storage.flush().unwrap();
+        }
+
+        let storage = SledStorage::open(SledConfig::new(dir.path())).unwrap();
+        assert_eq!(storage.get(b"persisted").unwrap(), Some(b"yes".to_vec()));
+    }
+
+    #[test]
+    fn test_failed_batch_is_rolled_back() {
+        let (_dir, storage) = open_temp();
+        storage.put(b"a", b"1").unwrap();
+        // Corrupt the metadata of "c" so staging fails partway through the batch
+        storage.metadata.insert(b"c", &[0xff][..]).unwrap();
+
+        let result = storage.batch(vec![
+            BatchOperation::Put { key: b"a".to_vec(), value: b"2".to_vec() },
+            BatchOperation::Put { key: b"b".to_vec(), value: b"3".to_vec() },
+            BatchOperation::Put { key: b"c".to_vec(), value: b"4".to_vec() },
+        ]);
+
+        assert!(matches!(result, Err(StorageError::Serialization(_))));
+        assert_eq!(storage.get(b"a").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(storage.get(b"b").unwrap(), None);
+        assert_eq!(storage.get_metadata(b"a").unwrap().unwrap().version, 1);
+        assert_eq!(storage.stats().writes, 1);
+    }
+
+    #[test]
+    fn test_scan_prefix_and_range() {
+        let (_dir, storage) = open_temp();
+        for key in ["block:1", "block:2", "state:1", "tx:1"] {
+            storage.put(key.as_bytes(), b"v").unwrap();
+        }
+
+        let blocks: Vec<Vec<u8>> = storage
+            .scan_prefix(b"block:")
+            .unwrap()
+            .map(|entry| entry.unwrap().0)
+            .collect();
+        assert_eq!(blocks, vec![b"block:1".to_vec(), b"block:2".to_vec()]);
+
+        let range: Vec<Vec<u8>> = storage
+            .scan_range(b"block:2", b"tx:1")
+            .unwrap()
+            .map(|entry| entry.unwrap().0)
+            .collect();
+        assert_eq!(range, vec![b"block:2".to_vec(), b"state:1".to_vec()]);
+    }
+
+    #[test]
+    fn test_corrupted_value_detected_on_read() {
+        let (_dir, storage) = open_temp();
+        storage.put(b"key", b"value").unwrap();
+        storage.data.insert(b"key", &b"garbage"[..]).unwrap();
+
+        assert!(matches!(
+            storage.get(b"key"),
+            Err(StorageError::Corruption { .. })
+        ));
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{prefix_end, Result, StorageIterator};
+use std::collections::BTreeMap;
+use std::ops::Bound;
+use std::sync::Arc;
+
+/// Read-only, point-in-time view of a store
+///
+/// A snapshot keeps returning the data as it was when it was taken, no
+/// matter what is written to the store afterwards, so long scans (such as
+/// paginated RPC queries) see a consistent state.
+pub trait StorageSnapshot: Send + Sync {
+    /// Get the value a key had when the snapshot was taken
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>>;
+
+    /// Check whether a key existed when the snapshot was taken
+    fn contains(&self, key: &[u8]) -> Result<bool> {
+        Ok(self.get(key)?.is_some())
+    }
+
+    /// Iterate, in key order, over the entries whose key starts with `prefix`
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<StorageIterator<'_>>;
+
+    /// Iterate, in key order, over the entries with `start <= key < end`
+    fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<StorageIterator<'_>>;
+}
+
+/// Snapshot holding its own copy of the data
+///
+/// Used by backends without native snapshots and by wrappers that transform
+/// values; taking one costs a copy of the (visible part of the) store.
+/// Backends that already keep their map behind an `Arc` can share it with
+/// `shared` instead.
+#[derive(Debug, Clone, Default)]
+pub struct MaterializedSnapshot {
+    data: Arc<BTreeMap<Vec<u8>, Vec<u8>>>,
+}
+
+impl MaterializedSnapshot {
+    /// Snapshot of the given entries
+    pub fn new(data: BTreeMap<Vec<u8>, Vec<u8>>) -> Self {
+        Self { data: Arc::new(data) }
+    }
+
+    /// Snapshot sharing a map that the caller will no longer modify in place
+    pub fn shared(data: Arc<BTreeMap<Vec<u8>, Vec<u8>>>) -> Self {
+        Self { data }
+    }
+
+    /// Collect a snapshot from an entry iterator, failing on the first error
+    pub fn collect(entries: StorageIterator<'_>) -> Result<Self> {
+        Ok(Self::new(entries.collect::<Result<_>>()?))
+    }
+
+    /// Number of entries in the snapshot
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Whether the snapshot is empty
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn range(&self, start: &[u8], end: Option<Vec<u8>>) -> StorageIterator<'_> {
+        let end = match end {
+            Some(end) if end.as_slice() <= start => return Box::new(std::iter::empty()),
+            Some(end) => Bound::Excluded(end),
+            None => Bound::Unbounded,
+        };
+        Box::new(
+            self.data
+                .range::<Vec<u8>, _>((Bound::Included(start.to_vec()), end))
+                .map(|(key, value)| Ok((key.clone(), value.clone()))),
+        )
+    }
+}
+
+impl StorageSnapshot for MaterializedSnapshot {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        Ok(self.data.get(key).cloned())
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<StorageIterator<'_>> {
+        Ok(self.range(prefix, prefix_end(prefix)))
+    }
+
+    fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<StorageIterator<'_>> {
+        Ok(self.range(start, Some(end.to_vec())))
+    }
+}
@@ -0,0 +1,492 @@
+use crate::{
+    current_timestamp, BatchOperation, BatchStorage, MaterializedSnapshot, MetadataStorage, Result,
+    Storage, StorageError, StorageIterator, StorageMetadata, StorageSnapshot, StorageStats,
+};
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Tag for values stored inline in the hot backend
+const TAG_INLINE: u8 = 0;
+/// Tag for stubs pointing at an object in the cold tier
+const TAG_OFFLOADED: u8 = 1;
+/// Stubs written per batch during an offload pass
+const OFFLOAD_BATCH_SIZE: usize = 256;
+
+/// Blob store used as the cold tier
+///
+/// Mirrors the subset of the S3 API the tiering layer needs, so an
+/// S3-compatible client can be plugged in behind it.
+pub trait ObjectStore: Send + Sync {
+    /// Upload an object, replacing any existing one
+    fn put_object(&self, name: &str, data: &[u8]) -> Result<()>;
+
+    /// Download an object
+    fn get_object(&self, name: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Delete an object; deleting a missing object is not an error
+    fn delete_object(&self, name: &str) -> Result<()>;
+}
+
+/// Object store kept in memory, for tests
+#[derive(Debug, Default)]
+pub struct InMemoryObjectStore {
+    objects: RwLock<HashMap<String, Vec<u8>>>,
+}
+
+impl InMemoryObjectStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of stored objects
+    pub fn len(&self) -> usize {
+        self.objects.read().len()
+    }
+
+    /// Whether the store holds no objects
+    pub fn is_empty(&self) -> bool {
+        self.objects.read().is_empty()
+    }
+}
+
+impl ObjectStore for InMemoryObjectStore {
+    fn put_object(&self, name: &str, data: &[u8]) -> Result<()> {
+        self.objects.write().insert(name.to_string(), data.to_vec());
+        Ok(())
+    }
+
+    fn get_object(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self.objects.read().get(name).cloned())
+    }
+
+    fn delete_object(&self, name: &str) -> Result<()> {
+        self.objects.write().remove(name);
+        Ok(())
+    }
+}
+
+/// Object store backed by a local directory, one file per object
+#[derive(Debug, Clone)]
+pub struct FileObjectStore {
+    root: PathBuf,
+}
+
+impl FileObjectStore {
+    /// Store objects under `root`, creating it if needed
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(&root)?;
+        Ok(Self { root })
+    }
+}
+
+impl ObjectStore for FileObjectStore {
+    fn put_object(&self, name: &str, data: &[u8]) -> Result<()> {
+        let tmp = self.root.join(format!("{}.tmp", name));
+        fs::write(&tmp, data)?;
+        fs::rename(tmp, self.root.join(name))?;
+        Ok(())
+    }
+
+    fn get_object(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        match fs::read(self.root.join(name)) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn delete_object(&self, name: &str) -> Result<()> {
+        match fs::remove_file(self.root.join(name)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Which entries are moved to the cold tier
+#[derive(Debug, Clone)]
+pub struct TieringPolicy {
+    /// Only entries not written for at least this long are offloaded
+    pub min_age: Duration,
+    /// Only values at least this large are offloaded
+    pub min_size: usize,
+    /// Only keys with one of these prefixes are offloaded; empty means all keys
+    pub key_prefixes: Vec<Vec<u8>>,
+}
+
+impl Default for TieringPolicy {
+    fn default() -> Self {
+        Self {
+            min_age: Duration::from_secs(7 * 24 * 60 * 60), // 1 week
+            min_size: 4096,
+            key_prefixes: Vec::new(),
+        }
+    }
+}
+
+impl TieringPolicy {
+    fn matches(&self, key: &[u8], value_len: usize, updated_at: u64, now: u64) -> bool {
+        let age = now.saturating_sub(updated_at);
+        age >= self.min_age.as_millis() as u64
+            && value_len >= self.min_size
+            && (self.key_prefixes.is_empty()
+                || self
+                    .key_prefixes
+                    .iter()
+                    .any(|prefix| key.starts_with(prefix)))
+    }
+}
+
+/// Outcome of an offload pass
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TieringReport {
+    pub offloaded: u64,
+    pub bytes: u64,
+}
+
+/// Two-tier storage: a local hot backend plus an object-store cold tier
+///
+/// `offload_cold` moves entries matching the tiering policy to the object
+/// store, leaving a small stub in the hot backend. Reads resolve stubs
+/// transparently, and overwriting or deleting an offloaded key removes its
+/// cold object.
+pub struct TieredStorage<S: BatchStorage + MetadataStorage, O: ObjectStore> {
+    hot: S,
+    cold: O,
+    policy: TieringPolicy,
+}
+
+impl<S: BatchStorage + MetadataStorage, O: ObjectStore> TieredStorage<S, O> {
+    /// Combine a hot backend and a cold object store
+    pub fn new(hot: S, cold: O, policy: TieringPolicy) -> Self {
+        Self { hot, cold, policy }
+    }
+
+    /// Get the hot backend
+    pub fn hot(&self) -> &S {
+        &self.hot
+    }
+
+    /// Get the cold object store
+    pub fn cold(&self) -> &O {
+        &self.cold
+    }
+
+    /// Whether a key currently lives in the cold tier
+    pub fn is_offloaded(&self, key: &[u8]) -> Result<bool> {
+        Ok(matches!(self.hot.get(key)?, Some(stored) if stored.first() == Some(&TAG_OFFLOADED)))
+    }
+
+    /// Move every entry matching the policy to the cold tier
+    ///
+    /// Only the keys of matching entries are collected up front; values are
+    /// read back and uploaded one at a time, and their stubs written in
+    /// batches, so a pass holds at most one value in memory. The stub
+    /// replaces the value without a compare-and-swap, so a write to a key
+    /// racing with its offload can be lost; run offload passes when the
+    /// affected (cold) keys are not being written.
+    pub fn offload_cold(&self) -> Result<TieringReport> {
+        let now = current_timestamp();
+        let mut report = TieringReport::default();
+        let mut candidates = Vec::new();
+
+        for entry in self.hot.scan_prefix(&[])? {
+            let (key, stored) = entry?;
+            if self.should_offload(&key, &stored, now)? {
+                candidates.push(key);
+            }
+        }
+
+        for keys in candidates.chunks(OFFLOAD_BATCH_SIZE) {
+            let mut stubs = Vec::with_capacity(keys.len());
+            for key in keys {
+                // Read the value back; it may have changed since the scan
+                let Some(stored) = self.hot.get(key)? else {
+                    continue;
+                };
+                if !self.should_offload(key, &stored, now)? {
+                    continue;
+                }
+                let name = object_name(key, now);
+                // Upload first: if the stub write fails the object is merely orphaned
+                self.cold.put_object(&name, &stored[1..])?;
+                stubs.push(BatchOperation::Put {
+                    key: key.clone(),
+                    value: stub(&name),
+                });
+                report.bytes += (stored.len() - 1) as u64;
+            }
+            if !stubs.is_empty() {
+                report.offloaded += stubs.len() as u64;
+                self.hot.batch(stubs)?;
+            }
+        }
+        Ok(report)
+    }
+
+    /// Whether an inline entry matches the tiering policy
+    fn should_offload(&self, key: &[u8], stored: &[u8], now: u64) -> Result<bool> {
+        if stored.first() != Some(&TAG_INLINE) {
+            return Ok(false);
+        }
+        Ok(self.hot.get_metadata(key)?.is_some_and(|metadata| {
+            self.policy
+                .matches(key, stored.len() - 1, metadata.updated_at, now)
+        }))
+    }
+
+    fn resolve(&self, stored: &[u8]) -> Result<Vec<u8>> {
+        match stored.split_first() {
+            Some((&TAG_INLINE, value)) => Ok(value.to_vec()),
+            Some((&TAG_OFFLOADED, name)) => {
+                let name = std::str::from_utf8(name)
+                    .map_err(|e| StorageError::Serialization(e.to_string()))?;
+                self.cold
+                    .get_object(name)?
+                    .ok_or_else(|| StorageError::Backend(format!("Cold object missing: {}", name)))
+            }
+            _ => Err(StorageError::Serialization(
+                "Invalid tiered storage value".to_string(),
+            )),
+        }
+    }
+
+    fn resolve_entry(&self, entry: Result<(Vec<u8>, Vec<u8>)>) -> Result<(Vec<u8>, Vec<u8>)> {
+        let (key, stored) = entry?;
+        let value = self.resolve(&stored)?;
+        Ok((key, value))
+    }
+
+    /// Cold object currently referenced by `key`, if any
+    fn offloaded_object(&self, key: &[u8]) -> Result<Option<String>> {
+        match self.hot.get(key)? {
+            Some(stored) if stored.first() == Some(&TAG_OFFLOADED) => {
+                Ok(Some(String::from_utf8_lossy(&stored[1..]).into_owned()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Drop the cold objects of keys that have just been overwritten or deleted
+    fn release_objects(&self, objects: Vec<String>) -> Result<()> {
+        for name in objects {
+            self.cold.delete_object(&name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Object name for `key`: a hash rather than the key itself, so names stay
+/// within file name and object key length limits however long the key is
+fn object_name(key: &[u8], now: u64) -> String {
+    format!("{}-{}", blake3::hash(key).to_hex(), now)
+}
+
+fn stub(name: &str) -> Vec<u8> {
+    let mut stub = Vec::with_capacity(name.len() + 1);
+    stub.push(TAG_OFFLOADED);
+    stub.extend_from_slice(name.as_bytes());
+    stub
+}
+
+fn inline(value: &[u8]) -> Vec<u8> {
+    let mut stored = Vec::with_capacity(value.len() + 1);
+    stored.push(TAG_INLINE);
+    stored.extend_from_slice(value);
+    stored
+}
+
+impl<S: BatchStorage + MetadataStorage, O: ObjectStore> Storage for TieredStorage<S, O> {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.hot
+            .get(key)?
+            .map(|stored| self.resolve(&stored))
+            .transpose()
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.batch(vec![BatchOperation::Put {
+            key: key.to_vec(),
+            value: value.to_vec(),
+        }])
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        self.batch(vec![BatchOperation::Delete { key: key.to_vec() }])
+    }
+
+    fn contains(&self, key: &[u8]) -> Result<bool> {
+        self.hot.contains(key)
+    }
+
+    fn keys(&self) -> Result<Vec<Vec<u8>>> {
+        self.hot.keys()
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<StorageIterator<'_>> {
+        let entries = self.hot.scan_prefix(prefix)?;
+        Ok(Box::new(
+            entries.map(move |entry| self.resolve_entry(entry)),
+        ))
+    }
+
+    fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<StorageIterator<'_>> {
+        let entries = self.hot.scan_range(start, end)?;
+        Ok(Box::new(
+            entries.map(move |entry| self.resolve_entry(entry)),
+        ))
+    }
+
+    fn snapshot(&self) -> Result<Box<dyn StorageSnapshot + '_>> {
+        let hot = self.hot.snapshot()?;
+        let entries = hot.scan_prefix(&[])?.map(|entry| self.resolve_entry(entry));
+        Ok(Box::new(MaterializedSnapshot::collect(Box::new(entries))?))
+    }
+
+    fn stats(&self) -> StorageStats {
+        self.hot.stats()
+    }
+}
+
+impl<S: BatchStorage + MetadataStorage, O: ObjectStore> BatchStorage for TieredStorage<S, O> {
+    fn batch(&self, operations: Vec<BatchOperation>) -> Result<()> {
+        let mut released = Vec::new();
+        let mut encoded = Vec::with_capacity(operations.len());
+        for operation in operations {
+            if let Some(name) = self.offloaded_object(operation.key())? {
+                released.push(name);
+            }
+            encoded.push(match operation {
+                BatchOperation::Put { key, value } => BatchOperation::Put {
+                    key,
+                    value: inline(&value),
+                },
+                delete => delete,
+            });
+        }
+
+        self.hot.batch(encoded)?;
+        released.sort();
+        released.dedup();
+        self.release_objects(released)
+    }
+}
+
+impl<S: BatchStorage + MetadataStorage, O: ObjectStore> MetadataStorage for TieredStorage<S, O> {
+    /// Metadata of the hot entry; for offloaded keys this describes the stub
+    fn get_metadata(&self, key: &[u8]) -> Result<Option<StorageMetadata>> {
+        self.hot.get_metadata(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryStorage;
+
+    fn offload_everything() -> TieringPolicy {
+        TieringPolicy {
+            min_age: Duration::ZERO,
+            min_size: 4,
+            key_prefixes: vec![b"block:".to_vec()],
+        }
+    }
+
+    #[test]
+    fn test_offload_and_read_through() {
+        let storage = TieredStorage::new(
+            InMemoryStorage::new(),
+            InMemoryObjectStore::new(),
+            offload_everything(),
+        );
+        storage.put(b"block:1", b"old block body").unwrap();
+        storage.put(b"block:2", b"abc").unwrap();
+        storage.put(b"state:1", b"hot state").unwrap();
+
+        let report = storage.offload_cold().unwrap();
+        assert_eq!(
+            report,
+            TieringReport {
+                offloaded: 1,
+                bytes: 14
+            }
+        );
+        assert!(storage.is_offloaded(b"block:1").unwrap());
+        assert!(!storage.is_offloaded(b"state:1").unwrap());
+        assert_eq!(storage.cold().len(), 1);
+
+        assert_eq!(
+            storage.get(b"block:1").unwrap(),
+            Some(b"old block body".to_vec())
+        );
+        let values: Vec<Vec<u8>> = storage
+            .scan_prefix(b"block:")
+            .unwrap()
+            .map(|entry| entry.unwrap().1)
+            .collect();
+        assert_eq!(values, vec![b"old block body".to_vec(), b"abc".to_vec()]);
+
+        // A second pass finds nothing new to move
+        assert_eq!(storage.offload_cold().unwrap().offloaded, 0);
+    }
+
+    #[test]
+    fn test_overwrite_and_delete_release_cold_objects() {
+        let storage = TieredStorage::new(
+            InMemoryStorage::new(),
+            InMemoryObjectStore::new(),
+            offload_everything(),
+        );
+        storage.put(b"block:1", b"first body").unwrap();
+        storage.put(b"block:2", b"second body").unwrap();
+        storage.offload_cold().unwrap();
+        assert_eq!(storage.cold().len(), 2);
+
+        storage.put(b"block:1", b"rewritten").unwrap();
+        assert!(!storage.is_offloaded(b"block:1").unwrap());
+        storage.delete(b"block:2").unwrap();
+
+        assert!(storage.cold().is_empty());
+        assert_eq!(
+            storage.get(b"block:1").unwrap(),
+            Some(b"rewritten".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_long_keys_get_short_object_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = TieredStorage::new(
+            InMemoryStorage::new(),
+            FileObjectStore::new(dir.path()).unwrap(),
+            offload_everything(),
+        );
+        let key = [b"block:".to_vec(), vec![b'x'; 1024]].concat();
+        storage.put(&key, b"long keyed body").unwrap();
+
+        assert_eq!(storage.offload_cold().unwrap().offloaded, 1);
+        assert_eq!(storage.get(&key).unwrap(), Some(b"long keyed body".to_vec()));
+    }
+
+    #[test]
+    fn test_file_object_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = TieredStorage::new(
+            InMemoryStorage::new(),
+            FileObjectStore::new(dir.path()).unwrap(),
+            offload_everything(),
+        );
+        storage.put(b"block:9", b"snapshot blob").unwrap();
+        storage.offload_cold().unwrap();
+
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+        assert_eq!(
+            storage.get(b"block:9").unwrap(),
+            Some(b"snapshot blob".to_vec())
+        );
+    }
+}
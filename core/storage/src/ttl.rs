@@ -0,0 +1,332 @@
+use crate::periodic::PeriodicTask;
+use crate::{
+    current_timestamp, BatchOperation, BatchStorage, MaterializedSnapshot, MetadataStorage,
+    Result, Storage, StorageError, StorageIterator, StorageMetadata, StorageSnapshot,
+    StorageStats,
+};
+use parking_lot::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Length of the expiry header prepended to every stored value
+const HEADER_LEN: usize = 8;
+/// Header value for entries that never expire
+const NO_EXPIRY: u64 = 0;
+/// Keys deleted per batch during a sweep
+const SWEEP_BATCH_SIZE: usize = 512;
+
+/// Storage wrapper adding per-entry time-to-live
+///
+/// Each value carries its expiry time (milliseconds since the Unix epoch) in
+/// an 8-byte header. Expired entries are hidden and removed lazily when read,
+/// and in bulk by `sweep_expired`, which a `TtlSweeper` can run periodically.
+/// Removal re-checks the entry with writes held off, so a key rewritten after
+/// it was seen expired keeps its new value.
+pub struct TtlStorage<S: Storage> {
+    inner: S,
+    expired: AtomicU64,
+    /// Shared by writes, held exclusively while removing expired entries
+    write_gate: RwLock<()>,
+}
+
+impl<S: Storage> TtlStorage<S> {
+    /// Wrap a backend
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            expired: AtomicU64::new(0),
+            write_gate: RwLock::new(()),
+        }
+    }
+
+    /// Get the wrapped backend
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Store a value that expires after `ttl`
+    pub fn put_with_ttl(&self, key: &[u8], value: &[u8], ttl: Duration) -> Result<()> {
+        let expires_at = current_timestamp().saturating_add(ttl.as_millis() as u64);
+        let _gate = self.write_gate.read();
+        self.inner.put(key, &encode(expires_at, value))
+    }
+
+    /// Remaining lifetime of a key; `Ok(None)` if missing, expired or without TTL
+    pub fn ttl(&self, key: &[u8]) -> Result<Option<Duration>> {
+        let Some(stored) = self.inner.get(key)? else {
+            return Ok(None);
+        };
+        let (expires_at, _) = decode(&stored)?;
+        let now = current_timestamp();
+        if expires_at == NO_EXPIRY || expires_at <= now {
+            return Ok(None);
+        }
+        Ok(Some(Duration::from_millis(expires_at - now)))
+    }
+
+    /// Number of entries removed because they expired
+    pub fn expired_count(&self) -> u64 {
+        self.expired.load(Ordering::Relaxed)
+    }
+
+    fn live_value(&self, key: &[u8], stored: &[u8], now: u64) -> Result<Option<Vec<u8>>> {
+        let (expires_at, value) = decode(stored)?;
+        if !is_expired(expires_at, now) {
+            return Ok(Some(value.to_vec()));
+        }
+
+        let _gate = self.write_gate.write();
+        match self.inner.get(key)? {
+            // Rewritten since we read it
+            Some(current) if !self.is_expired_entry(&current, now)? => {
+                Ok(Some(decode(&current)?.1.to_vec()))
+            }
+            Some(_) => {
+                self.inner.delete(key)?;
+                self.expired.fetch_add(1, Ordering::Relaxed);
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn is_expired_entry(&self, stored: &[u8], now: u64) -> Result<bool> {
+        Ok(is_expired(decode(stored)?.0, now))
+    }
+
+    fn live_entries<'a>(&'a self, entries: StorageIterator<'a>) -> StorageIterator<'a> {
+        let now = current_timestamp();
+        Box::new(entries.filter_map(move |entry| {
+            let (key, stored) = match entry {
+                Ok(entry) => entry,
+                Err(e) => return Some(Err(e)),
+            };
+            match decode(&stored) {
+                Ok((expires_at, _)) if is_expired(expires_at, now) => None,
+                Ok((_, value)) => Some(Ok((key, value.to_vec()))),
+                Err(e) => Some(Err(e)),
+            }
+        }))
+    }
+}
+
+impl<S: BatchStorage> TtlStorage<S> {
+    /// Delete every expired entry, returning how many were removed
+    pub fn sweep_expired(&self) -> Result<usize> {
+        let now = current_timestamp();
+        let mut expired = Vec::new();
+        for entry in self.inner.scan_prefix(&[])? {
+            let (key, stored) = entry?;
+            if is_expired(decode(&stored)?.0, now) {
+                expired.push(key);
+            }
+        }
+
+        let mut removed = 0;
+        for chunk in expired.chunks(SWEEP_BATCH_SIZE) {
+            let _gate = self.write_gate.write();
+            let mut operations = Vec::new();
+            for key in chunk {
+                // Skip keys rewritten or deleted since the scan
+                if let Some(stored) = self.inner.get(key)? {
+                    if self.is_expired_entry(&stored, now)? {
+                        operations.push(BatchOperation::Delete { key: key.clone() });
+                    }
+                }
+            }
+            removed += operations.len();
+            if !operations.is_empty() {
+                self.inner.batch(operations)?;
+            }
+        }
+        self.expired.fetch_add(removed as u64, Ordering::Relaxed);
+        Ok(removed)
+    }
+}
+
+fn is_expired(expires_at: u64, now: u64) -> bool {
+    expires_at != NO_EXPIRY && expires_at <= now
+}
+
+fn encode(expires_at: u64, value: &[u8]) -> Vec<u8> {
+    let mut stored = Vec::with_capacity(HEADER_LEN + value.len());
+    stored.extend_from_slice(&expires_at.to_be_bytes());
+    stored.extend_from_slice(value);
+    stored
+}
+
+fn decode(stored: &[u8]) -> Result<(u64, &[u8])> {
+    if stored.len() < HEADER_LEN {
+        return Err(StorageError::Serialization(
+            "Value is missing its expiry header".to_string(),
+        ));
+    }
+    let (header, value) = stored.split_at(HEADER_LEN);
+    let mut expires_at = [0u8; HEADER_LEN];
+    expires_at.copy_from_slice(header);
+    Ok((u64::from_be_bytes(expires_at), value))
+}
+
+impl<S: Storage> Storage for TtlStorage<S> {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        match self.inner.get(key)? {
+            Some(stored) => self.live_value(key, &stored, current_timestamp()),
+            None => Ok(None),
+        }
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        let _gate = self.write_gate.read();
+        self.inner.put(key, &encode(NO_EXPIRY, value))
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        let _gate = self.write_gate.read();
+        self.inner.delete(key)
+    }
+
+    fn keys(&self) -> Result<Vec<Vec<u8>>> {
+        self.live_entries(self.inner.scan_prefix(&[])?)
+            .map(|entry| entry.map(|(key, _)| key))
+            .collect()
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<StorageIterator<'_>> {
+        Ok(self.live_entries(self.inner.scan_prefix(prefix)?))
+    }
+
+    fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<StorageIterator<'_>> {
+        Ok(self.live_entries(self.inner.scan_range(start, end)?))
+    }
+
+    /// Entries count as expired relative to the time the snapshot is taken
+    fn snapshot(&self) -> Result<Box<dyn StorageSnapshot + '_>> {
+        let inner = self.inner.snapshot()?;
+        let entries = self.live_entries(inner.scan_prefix(&[])?);
+        Ok(Box::new(MaterializedSnapshot::collect(entries)?))
+    }
+
+    fn stats(&self) -> StorageStats {
+        self.inner.stats()
+    }
+}
+
+impl<S: BatchStorage> BatchStorage for TtlStorage<S> {
+    fn batch(&self, operations: Vec<BatchOperation>) -> Result<()> {
+        let operations = operations
+            .into_iter()
+            .map(|operation| match operation {
+                BatchOperation::Put { key, value } => BatchOperation::Put {
+                    key,
+                    value: encode(NO_EXPIRY, &value),
+                },
+                delete => delete,
+            })
+            .collect();
+        let _gate = self.write_gate.read();
+        self.inner.batch(operations)
+    }
+}
+
+impl<S: MetadataStorage> MetadataStorage for TtlStorage<S> {
+    fn get_metadata(&self, key: &[u8]) -> Result<Option<StorageMetadata>> {
+        if self.get(key)?.is_none() {
+            return Ok(None);
+        }
+        self.inner.get_metadata(key)
+    }
+}
+
+/// Background thread that periodically sweeps expired entries
+///
+/// The thread stops when the sweeper is dropped or `stop` is called.
+pub struct TtlSweeper {
+    task: PeriodicTask,
+}
+
+impl TtlSweeper {
+    /// Sweep `storage` every `interval`
+    pub fn start<S>(storage: Arc<TtlStorage<S>>, interval: Duration) -> Self
+    where
+        S: BatchStorage + 'static,
+    {
+        // Errors are transient from the sweeper's point of view; retry next round
+        let task = PeriodicTask::start(interval, move || {
+            let _ = storage.sweep_expired();
+        });
+        Self { task }
+    }
+
+    /// Stop the sweeper and wait for the thread to exit
+    pub fn stop(&mut self) {
+        self.task.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryStorage;
+    use std::thread;
+
+    #[test]
+    fn test_expired_entries_hidden_and_removed_on_read() {
+        let storage = TtlStorage::new(InMemoryStorage::new());
+        storage.put(b"permanent", b"1").unwrap();
+        storage.put_with_ttl(b"session", b"2", Duration::from_secs(60)).unwrap();
+        storage.put_with_ttl(b"gossip", b"3", Duration::ZERO).unwrap();
+
+        assert_eq!(storage.get(b"permanent").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(storage.get(b"session").unwrap(), Some(b"2".to_vec()));
+        assert_eq!(storage.get(b"gossip").unwrap(), None);
+        assert!(!storage.inner().contains(b"gossip").unwrap());
+        assert_eq!(storage.expired_count(), 1);
+
+        assert!(storage.ttl(b"session").unwrap().unwrap() > Duration::from_secs(59));
+        assert_eq!(storage.ttl(b"permanent").unwrap(), None);
+    }
+
+    #[test]
+    fn test_lazy_expiry_keeps_rewritten_value() {
+        let storage = TtlStorage::new(InMemoryStorage::new());
+        storage.put_with_ttl(b"key", b"old", Duration::ZERO).unwrap();
+        let stale = storage.inner().get(b"key").unwrap().unwrap();
+
+        // A writer replaces the entry after a reader saw it expired
+        storage.put(b"key", b"new").unwrap();
+        let now = current_timestamp();
+        assert_eq!(storage.live_value(b"key", &stale, now).unwrap(), Some(b"new".to_vec()));
+        assert_eq!(storage.get(b"key").unwrap(), Some(b"new".to_vec()));
+        assert_eq!(storage.expired_count(), 0);
+    }
+
+    #[test]
+    fn test_sweep_and_scans_skip_expired() {
+        let storage = TtlStorage::new(InMemoryStorage::new());
+        storage.put_with_ttl(b"peer:1", b"a", Duration::ZERO).unwrap();
+        storage.put_with_ttl(b"peer:2", b"b", Duration::from_secs(60)).unwrap();
+        storage.put_with_ttl(b"peer:3", b"c", Duration::ZERO).unwrap();
+
+        assert_eq!(storage.keys().unwrap(), vec![b"peer:2".to_vec()]);
+        assert_eq!(storage.scan_prefix(b"peer:").unwrap().count(), 1);
+        assert_eq!(storage.inner().len(), 3);
+
+        assert_eq!(storage.sweep_expired().unwrap(), 2);
+        assert_eq!(storage.inner().len(), 1);
+        assert_eq!(storage.sweep_expired().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_background_sweeper() {
+        let storage = Arc::new(TtlStorage::new(InMemoryStorage::new()));
+        storage.put_with_ttl(b"marker", b"x", Duration::from_millis(10)).unwrap();
+
+        let mut sweeper = TtlSweeper::start(storage.clone(), Duration::from_millis(20));
+        thread::sleep(Duration::from_millis(200));
+        sweeper.stop();
+
+        assert!(storage.inner().is_empty());
+        assert_eq!(storage.expired_count(), 1);
+    }
+}
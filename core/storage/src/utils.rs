@@ -0,0 +1,214 @@
+use crate::{
+    BatchOperation, BatchStorage, Result, Storage, StorageError, StorageIterator, StorageSnapshot,
+};
+use std::io::{BufReader, BufWriter, Read, Write};
+
+/// Magic bytes (with format version) at the start of every export stream
+const EXPORT_MAGIC: &[u8; 8] = b"CCEXPRT1";
+/// Key length marking the trailer record
+const END_MARKER: u32 = u32::MAX;
+/// Entries written per batch during an import
+const IMPORT_BATCH_SIZE: usize = 1024;
+
+/// Bulk data utilities for moving entries between stores
+///
+/// Exports use a length-prefixed binary stream, so a store can be migrated
+/// between backends (e.g. RocksDB to sled) one entry at a time without
+/// holding the keys in memory:
+///
+/// ```text
+/// magic "CCEXPRT1"
+/// { key_len: u32 BE, key, value_len: u32 BE, value }*
+/// end marker: u32::MAX, entry count: u64 BE, crc32 of all records: u32 BE
+/// ```
+///
+/// The trailer lets `import_from` detect truncated or corrupted streams.
+pub struct StorageUtils;
+
+impl StorageUtils {
+    /// Stream every entry of `storage` into `writer`, returning the entry count
+    ///
+    /// Entries are read with a live scan, so writes made during the export
+    /// may or may not be included; use `export_snapshot_to` for a consistent
+    /// point-in-time copy.
+    pub fn export_to<W: Write>(storage: &dyn Storage, writer: W) -> Result<u64> {
+        write_entries(storage.scan_prefix(&[])?, writer)
+    }
+
+    /// Stream every entry of a snapshot into `writer`, returning the entry count
+    pub fn export_snapshot_to<W: Write>(snapshot: &dyn StorageSnapshot, writer: W) -> Result<u64> {
+        write_entries(snapshot.scan_prefix(&[])?, writer)
+    }
+
+    /// Read an export from `reader` into `target`, returning the entry count
+    ///
+    /// Entries are applied in batches as they are read. The import is not
+    /// atomic: if the stream turns out to be truncated or corrupted, the
+    /// batches written before the error remain in `target`.
+    pub fn import_from<R: Read>(target: &dyn BatchStorage, reader: R) -> Result<u64> {
+        let mut reader = BufReader::new(reader);
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != EXPORT_MAGIC {
+            return Err(StorageError::Serialization(
+                "Not a storage export stream".to_string(),
+            ));
+        }
+
+        let mut hasher = crc32fast::Hasher::new();
+        let mut imported = 0u64;
+        let mut operations = Vec::with_capacity(IMPORT_BATCH_SIZE);
+        loop {
+            let key_len = read_u32(&mut reader)?;
+            if key_len == END_MARKER {
+                break;
+            }
+            let key = read_bytes(&mut reader, key_len)?;
+            let value_len = read_u32(&mut reader)?;
+            let value = read_bytes(&mut reader, value_len)?;
+            hash_record(&mut hasher, &key, &value);
+
+            operations.push(BatchOperation::Put { key, value });
+            imported += 1;
+            if operations.len() == IMPORT_BATCH_SIZE {
+                target.batch(std::mem::take(&mut operations))?;
+            }
+        }
+
+        let mut count = [0u8; 8];
+        reader.read_exact(&mut count)?;
+        let count = u64::from_be_bytes(count);
+        let expected = read_u32(&mut reader)?;
+        let actual = hasher.finalize();
+        if count != imported || expected != actual {
+            return Err(StorageError::Serialization(format!(
+                "Export stream is corrupted: expected {} entries (crc {:08x}), read {} (crc {:08x})",
+                count, expected, imported, actual
+            )));
+        }
+
+        if !operations.is_empty() {
+            target.batch(operations)?;
+        }
+        Ok(imported)
+    }
+}
+
+fn write_entries<W: Write>(entries: StorageIterator<'_>, writer: W) -> Result<u64> {
+    let mut writer = BufWriter::new(writer);
+    writer.write_all(EXPORT_MAGIC)?;
+
+    let mut hasher = crc32fast::Hasher::new();
+    let mut exported = 0u64;
+    for entry in entries {
+        let (key, value) = entry?;
+        writer.write_all(&length_prefix(&key)?)?;
+        writer.write_all(&key)?;
+        writer.write_all(&length_prefix(&value)?)?;
+        writer.write_all(&value)?;
+        hash_record(&mut hasher, &key, &value);
+        exported += 1;
+    }
+
+    writer.write_all(&END_MARKER.to_be_bytes())?;
+    writer.write_all(&exported.to_be_bytes())?;
+    writer.write_all(&hasher.finalize().to_be_bytes())?;
+    writer.flush()?;
+    Ok(exported)
+}
+
+fn length_prefix(bytes: &[u8]) -> Result<[u8; 4]> {
+    match u32::try_from(bytes.len()) {
+        Ok(len) if len != END_MARKER => Ok(len.to_be_bytes()),
+        _ => Err(StorageError::Serialization(format!(
+            "Entry of {} bytes is too large to export",
+            bytes.len()
+        ))),
+    }
+}
+
+fn hash_record(hasher: &mut crc32fast::Hasher, key: &[u8], value: &[u8]) {
+    hasher.update(&(key.len() as u32).to_be_bytes());
+    hasher.update(key);
+    hasher.update(&(value.len() as u32).to_be_bytes());
+    hasher.update(value);
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_be_bytes(bytes))
+}
+
+fn read_bytes(reader: &mut impl Read, len: u32) -> Result<Vec<u8>> {
+    // Read through `take` rather than preallocating, so a corrupted length
+    // cannot trigger a multi-GB allocation
+    let mut bytes = Vec::new();
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len as usize {
+        return Err(StorageError::Serialization(
+            "Export stream ended in the middle of an entry".to_string(),
+        ));
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InMemoryStorage, ShardedMemoryStorage};
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let source = InMemoryStorage::new();
+        for i in 0..3000u32 {
+            source
+                .put(&i.to_be_bytes(), format!("value-{}", i).as_bytes())
+                .unwrap();
+        }
+        source.put(b"empty", b"").unwrap();
+
+        let mut stream = Vec::new();
+        assert_eq!(StorageUtils::export_to(&source, &mut stream).unwrap(), 3001);
+
+        let target = ShardedMemoryStorage::new();
+        assert_eq!(
+            StorageUtils::import_from(&target, stream.as_slice()).unwrap(),
+            3001
+        );
+        assert_eq!(target.len(), 3001);
+        assert_eq!(
+            target.get(&7u32.to_be_bytes()).unwrap(),
+            Some(b"value-7".to_vec())
+        );
+        assert_eq!(target.get(b"empty").unwrap(), Some(Vec::new()));
+
+        let mut from_snapshot = Vec::new();
+        StorageUtils::export_snapshot_to(source.snapshot().unwrap().as_ref(), &mut from_snapshot)
+            .unwrap();
+        assert_eq!(from_snapshot, stream);
+    }
+
+    #[test]
+    fn test_import_rejects_damaged_streams() {
+        let source = InMemoryStorage::new();
+        source.put(b"block:1", b"genesis").unwrap();
+        source.put(b"block:2", b"next").unwrap();
+        let mut stream = Vec::new();
+        StorageUtils::export_to(&source, &mut stream).unwrap();
+
+        let truncated = &stream[..stream.len() - 6];
+        let result = StorageUtils::import_from(&InMemoryStorage::new(), truncated);
+        assert!(result.is_err());
+
+        let mut corrupted = stream.clone();
+        let position = corrupted.len() - 20;
+        corrupted[position] ^= 0xff;
+        let target = InMemoryStorage::new();
+        assert!(StorageUtils::import_from(&target, corrupted.as_slice()).is_err());
+        assert!(target.is_empty());
+
+        let result = StorageUtils::import_from(&target, &b"not an export"[..]);
+        assert!(matches!(result, Err(StorageError::Serialization(_))));
+    }
+}
@@ -0,0 +1,369 @@
+use crate::{
+    BatchOperation, BatchStorage, MetadataStorage, Result, Storage, StorageIterator,
+    StorageMetadata, StorageSnapshot, StorageStats,
+};
+use parking_lot::Mutex;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Record header: payload length (u32) and truncated BLAKE3 checksum (u64)
+const RECORD_HEADER_LEN: usize = 12;
+
+/// When the write-ahead log is fsynced
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncPolicy {
+    /// fsync before every commit returns; no acknowledged write can be lost
+    Always,
+    /// fsync on commit if the last sync is older than the interval. There is
+    /// no background timer: everything since the last sync can be lost, and
+    /// after the last commit of a burst that stays unsynced until the next
+    /// commit or an explicit `sync`
+    Interval(Duration),
+    /// Leave syncing to the OS (or explicit `sync` calls)
+    Never,
+}
+
+/// Write-ahead log configuration
+#[derive(Debug, Clone)]
+pub struct WalConfig {
+    pub path: PathBuf,
+    pub sync_policy: SyncPolicy,
+}
+
+impl WalConfig {
+    /// Log at `path` synced on every commit
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            sync_policy: SyncPolicy::Always,
+        }
+    }
+
+    /// Set the fsync policy
+    pub fn with_sync_policy(mut self, sync_policy: SyncPolicy) -> Self {
+        self.sync_policy = sync_policy;
+        self
+    }
+}
+
+struct WalWriter {
+    file: File,
+    size: u64,
+    last_sync: Instant,
+    dirty: bool,
+}
+
+impl WalWriter {
+    fn append(&mut self, payload: &[u8]) -> Result<()> {
+        let mut record = Vec::with_capacity(RECORD_HEADER_LEN + payload.len());
+        record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        record.extend_from_slice(&checksum(payload).to_le_bytes());
+        record.extend_from_slice(payload);
+        if let Err(err) = self.file.write_all(&record) {
+            // Cut off whatever part of the record made it out so the next one
+            // does not land after a torn record, which replay would stop at
+            self.file.set_len(self.size)?;
+            self.file.seek(SeekFrom::Start(self.size))?;
+            return Err(err.into());
+        }
+        self.size += record.len() as u64;
+        self.dirty = true;
+        Ok(())
+    }
+
+    fn truncate(&mut self, size: u64) -> Result<()> {
+        self.file.set_len(size)?;
+        self.file.seek(SeekFrom::Start(size))?;
+        self.file.sync_all()?;
+        self.size = size;
+        self.dirty = false;
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+
+    fn sync(&mut self) -> Result<()> {
+        if self.dirty {
+            self.file.sync_data()?;
+            self.dirty = false;
+        }
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+}
+
+/// Journaling wrapper giving any backend crash durability
+///
+/// Every batch is appended to a write-ahead log before it is applied to the
+/// wrapped backend. On open, records still in the log are replayed, so a
+/// backend without its own WAL (or a purely in-memory one) recovers all
+/// synced writes after a crash. A torn record at the end of the log, left by
+/// a crash mid-append, is discarded.
+pub struct JournaledStorage<S: BatchStorage> {
+    inner: S,
+    config: WalConfig,
+    writer: Mutex<WalWriter>,
+}
+
+impl<S: BatchStorage> JournaledStorage<S> {
+    /// Open the log, replay it into `inner` and wrap the backend
+    pub fn open(inner: S, config: WalConfig) -> Result<Self> {
+        if let Some(parent) = config.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&config.path)?;
+
+        let valid_len = replay(&mut file, &inner)?;
+        // Drop any torn tail so new records follow the last complete one
+        file.set_len(valid_len)?;
+        file.seek(SeekFrom::End(0))?;
+        file.sync_all()?;
+
+        Ok(Self {
+            inner,
+            config,
+            writer: Mutex::new(WalWriter {
+                file,
+                size: valid_len,
+                last_sync: Instant::now(),
+                dirty: false,
+            }),
+        })
+    }
+
+    /// Get the wrapped backend
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Get the log configuration
+    pub fn config(&self) -> &WalConfig {
+        &self.config
+    }
+
+    /// Current size of the log in bytes
+    pub fn log_size(&self) -> u64 {
+        self.writer.lock().size
+    }
+
+    /// fsync the log now, regardless of the sync policy
+    pub fn sync(&self) -> Result<()> {
+        self.writer.lock().sync()
+    }
+
+    /// Discard the log
+    ///
+    /// Only call this once the wrapped backend has durably persisted every
+    /// applied write (for example after flushing an on-disk backend);
+    /// otherwise those writes are lost on crash.
+    pub fn checkpoint(&self) -> Result<()> {
+        self.writer.lock().truncate(0)
+    }
+}
+
+/// Apply every complete record in the log, returning the length of the valid prefix
+fn replay<S: BatchStorage>(file: &mut File, inner: &S) -> Result<u64> {
+    let mut contents = Vec::new();
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_end(&mut contents)?;
+
+    let mut offset = 0;
+    while contents.len() - offset >= RECORD_HEADER_LEN {
+        let header = &contents[offset..offset + RECORD_HEADER_LEN];
+        let len = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let mut expected = [0u8; 8];
+        expected.copy_from_slice(&header[4..]);
+
+        let start = offset + RECORD_HEADER_LEN;
+        let Some(payload) = contents.get(start..start + len) else {
+            break;
+        };
+        if checksum(payload) != u64::from_le_bytes(expected) {
+            break;
+        }
+
+        let operations: Vec<BatchOperation> = bincode::deserialize(payload)?;
+        inner.batch(operations)?;
+        offset = start + len;
+    }
+    Ok(offset as u64)
+}
+
+fn checksum(payload: &[u8]) -> u64 {
+    let hash = blake3::hash(payload);
+    let mut truncated = [0u8; 8];
+    truncated.copy_from_slice(&hash.as_bytes()[..8]);
+    u64::from_le_bytes(truncated)
+}
+
+impl<S: BatchStorage> Storage for JournaledStorage<S> {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.inner.get(key)
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.batch(vec![BatchOperation::Put {
+            key: key.to_vec(),
+            value: value.to_vec(),
+        }])
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        self.batch(vec![BatchOperation::Delete { key: key.to_vec() }])
+    }
+
+    fn contains(&self, key: &[u8]) -> Result<bool> {
+        self.inner.contains(key)
+    }
+
+    fn keys(&self) -> Result<Vec<Vec<u8>>> {
+        self.inner.keys()
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<StorageIterator<'_>> {
+        self.inner.scan_prefix(prefix)
+    }
+
+    fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<StorageIterator<'_>> {
+        self.inner.scan_range(start, end)
+    }
+
+    fn snapshot(&self) -> Result<Box<dyn StorageSnapshot + '_>> {
+        self.inner.snapshot()
+    }
+
+    fn stats(&self) -> StorageStats {
+        self.inner.stats()
+    }
+}
+
+impl<S: BatchStorage> BatchStorage for JournaledStorage<S> {
+    fn batch(&self, operations: Vec<BatchOperation>) -> Result<()> {
+        let payload = bincode::serialize(&operations)?;
+
+        // Hold the log lock while applying so replay order matches apply order
+        let mut writer = self.writer.lock();
+        let committed_size = writer.size;
+        writer.append(&payload)?;
+        let synced = match self.config.sync_policy {
+            SyncPolicy::Always => writer.sync(),
+            SyncPolicy::Interval(interval) if writer.last_sync.elapsed() >= interval => {
+                writer.sync()
+            }
+            _ => Ok(()),
+        };
+        if let Err(err) = synced {
+            // The batch is reported as failed, so it must not be replayed either
+            writer.truncate(committed_size)?;
+            return Err(err);
+        }
+
+        if let Err(err) = self.inner.batch(operations) {
+            // The backend rejected the batch as a whole; drop its record so replay does not retry it
+            writer.truncate(committed_size)?;
+            return Err(err);
+        }
+        Ok(())
+    }
+}
+
+impl<S: BatchStorage + MetadataStorage> MetadataStorage for JournaledStorage<S> {
+    fn get_metadata(&self, key: &[u8]) -> Result<Option<StorageMetadata>> {
+        self.inner.get_metadata(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryStorage;
+
+    fn open(dir: &tempfile::TempDir) -> JournaledStorage<InMemoryStorage> {
+        JournaledStorage::open(InMemoryStorage::new(), WalConfig::new(dir.path().join("wal.log")))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_replay_after_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let storage = open(&dir);
+            storage.put(b"a", b"1").unwrap();
+            storage.put(b"b", b"2").unwrap();
+            storage.delete(b"a").unwrap();
+        }
+
+        // A fresh in-memory backend recovers everything from the log
+        let storage = open(&dir);
+        assert_eq!(storage.get(b"a").unwrap(), None);
+        assert_eq!(storage.get(b"b").unwrap(), Some(b"2".to_vec()));
+    }
+
+    #[test]
+    fn test_torn_tail_is_discarded() {
+        let dir = tempfile::tempdir().unwrap();
+        let complete_len = {
+            let storage = open(&dir);
+            storage.put(b"a", b"1").unwrap();
+            storage.log_size()
+        };
+
+        // Simulate a crash partway through appending the next record
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(dir.path().join("wal.log"))
+            .unwrap();
+        file.write_all(&[42, 0, 0, 0, 1, 2, 3]).unwrap();
+
+        let storage = open(&dir);
+        assert_eq!(storage.get(b"a").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(storage.log_size(), complete_len);
+
+        storage.put(b"b", b"2").unwrap();
+        drop(storage);
+        assert_eq!(open(&dir).get(b"b").unwrap(), Some(b"2".to_vec()));
+    }
+
+    #[test]
+    fn test_checkpoint_and_sync_policies() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = WalConfig::new(dir.path().join("wal.log"))
+            .with_sync_policy(SyncPolicy::Interval(Duration::from_secs(60)));
+        let storage = JournaledStorage::open(InMemoryStorage::new(), config).unwrap();
+
+        storage.put(b"k", b"v").unwrap();
+        assert!(storage.writer.lock().dirty);
+        storage.sync().unwrap();
+        assert!(!storage.writer.lock().dirty);
+
+        storage.checkpoint().unwrap();
+        assert_eq!(storage.log_size(), 0);
+        assert_eq!(storage.get(b"k").unwrap(), Some(b"v".to_vec()));
+    }
+
+    #[test]
+    fn test_rejected_batch_is_not_journaled() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wal.log");
+        {
+            let storage =
+                JournaledStorage::open(InMemoryStorage::with_max_size(8), WalConfig::new(&path))
+                    .unwrap();
+            storage.put(b"k", b"v").unwrap();
+            let size = storage.log_size();
+            assert!(storage.put(b"big", b"too large for the limit").is_err());
+            assert_eq!(storage.log_size(), size);
+        }
+
+        let storage =
+            JournaledStorage::open(InMemoryStorage::with_max_size(8), WalConfig::new(&path))
+                .unwrap();
+        assert_eq!(storage.get(b"k").unwrap(), Some(b"v".to_vec()));
+    }
+}
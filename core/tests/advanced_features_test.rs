@@ -117,11 +117,80 @@ fn test_merkle_proof_generation_and_verification() {
     // Generate and verify proof for each leaf
     for (i, leaf) in leaves.iter().enumerate() {
         if let Some(proof) = tree.proof(i) {
-            assert!(MerkleTree::verify_proof(&root, leaf, &proof, i));
+            assert!(MerkleTree::verify(&root, leaf, &proof));
+            let siblings: Vec<Hash> = proof.iter().map(|(sibling, _)| *sibling).collect();
+            assert!(MerkleTree::verify_proof(&root, leaf, &siblings, i));
+            assert!(tree.merkle_proof(i).unwrap().verify(leaf));
         } else {
             panic!("Failed to generate proof for leaf {}", i);
         }
     }
+
+    // V2 hashes leaves and internal nodes apart, so an internal node cannot
+    // be passed off as a leaf with a shortened path
+    let tree = MerkleTree::build_with_format(&leaves, MerkleFormat::V2);
+    let root = tree.root();
+    let proof = tree.proof(0).unwrap();
+    assert!(MerkleFormat::V2.verify(&root, &leaves[0], &proof));
+    assert!(!MerkleTree::verify(&root, &leaves[0], &proof));
+    let internal = cc_core_data_structures::internal_hash(
+        &cc_core_data_structures::leaf_hash(&[&leaves[0]]),
+        &proof[0].0,
+    );
+    assert!(!MerkleFormat::V2.verify(&root, &internal, &proof[1..]));
+}
+
+#[test]
+fn test_merkle_v1_roots_are_unchanged() {
+    let leaves: Vec<Hash> = (0..3)
+        .map(|i| crate::crypto::hash(format!("leaf{}", i).as_bytes()))
+        .collect();
+    let left = crate::crypto::hash_multiple(&[&leaves[0], &leaves[1]]);
+    let right = crate::crypto::hash_multiple(&[&leaves[2], &leaves[2]]);
+    assert_eq!(MerkleTree::build(&leaves).root(), crate::crypto::hash_multiple(&[&left, &right]));
+    assert_eq!(MerkleTree::build(&leaves[..1]).root(), leaves[0]);
+    assert_eq!(MerkleTree::build(&[]).root(), [0u8; 32]);
+}
+
+#[test]
+fn test_merkle_audit_paths_for_odd_trees() {
+    for format in [MerkleFormat::V1, MerkleFormat::V2] {
+        for size in [1usize, 3, 5, 7, 12] {
+            let leaves: Vec<Hash> = (0..size)
+                .map(|i| crate::crypto::hash(format!("leaf{}", i).as_bytes()))
+                .collect();
+            let tree = MerkleTree::build_with_format(&leaves, format);
+            let root = tree.root();
+            assert_eq!(tree.leaf_count(), size);
+
+            for (i, leaf) in leaves.iter().enumerate() {
+                let proof = tree.proof(i).unwrap();
+                assert!(format.verify(&root, leaf, &proof));
+
+                // Sides follow the leaf index bits
+                for (level, (_, side)) in proof.iter().enumerate() {
+                    let expected = if (i >> level) & 1 == 0 { Side::Right } else { Side::Left };
+                    assert_eq!(*side, expected);
+                }
+
+                let wrong_leaf = crate::crypto::hash(b"not a leaf");
+                assert!(!format.verify(&root, &wrong_leaf, &proof));
+                // V1 pairs the last node of an odd level with itself, where
+                // the side makes no difference, so only V2 binds every side
+                if let (MerkleFormat::V2, Some((_, side))) = (format, proof.first().copied()) {
+                    let mut flipped = proof.clone();
+                    flipped[0].1 = if side == Side::Left { Side::Right } else { Side::Left };
+                    assert!(!format.verify(&root, leaf, &flipped));
+                }
+            }
+            assert!(tree.proof(size).is_none());
+        }
+    }
+
+    // A single leaf has an empty audit path
+    let single = MerkleTree::build(&[crate::crypto::hash(b"only")]);
+    assert_eq!(single.proof(0), Some(Vec::new()));
+    assert!(MerkleTree::build(&[]).proof(0).is_none());
 }
 
 #[test]
@@ -0,0 +1,64 @@
+//! ccBFT message I/O over gossip.
+//!
+//! Proposals travel on the blocks topic and every other ccBFT message on
+//! the votes topic, both encoded as `CcBftNetworkMessage`. Payloads that do
+//! not decode are dropped before they are delivered or forwarded.
+
+use crate::gossip::Topic;
+use crate::p2p::P2pNode;
+use consensus::ccbft::CcBftNetworkMessage;
+use consensus::CcBftConsensus;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Topic a ccBFT message is gossiped on
+pub fn topic_for(message: &CcBftNetworkMessage) -> Topic {
+    match message {
+        CcBftNetworkMessage::Proposal(_) => Topic::Blocks,
+        _ => Topic::Votes,
+    }
+}
+
+/// Connect `consensus` to the network: messages it sends are gossiped, and
+/// gossiped messages are queued for it to process
+pub fn attach_ccbft(node: &Arc<P2pNode>, consensus: Arc<CcBftConsensus>) -> Vec<JoinHandle<()>> {
+    for topic in [Topic::Blocks, Topic::Votes] {
+        node.gossip().set_validator(
+            topic,
+            Box::new(move |data| {
+                bincode::deserialize::<CcBftNetworkMessage>(data).is_ok_and(|message| topic_for(&message) == topic)
+            }),
+        );
+    }
+
+    let (sender, mut outbound) = mpsc::unbounded_channel();
+    consensus.set_network_sender(sender);
+    let publisher = node.clone();
+    let mut tasks = vec![tokio::spawn(async move {
+        while let Some(message) = outbound.recv().await {
+            match bincode::serialize(&message) {
+                Ok(data) => {
+                    publisher.publish(topic_for(&message), data);
+                }
+                Err(e) => tracing::warn!("Failed to encode ccBFT message: {}", e),
+            }
+        }
+    })];
+
+    for topic in [Topic::Blocks, Topic::Votes] {
+        let mut inbound = node.subscribe(topic);
+        let consensus = consensus.clone();
+        tasks.push(tokio::spawn(async move {
+            while let Some(message) = inbound.recv().await {
+                // Validated on receipt, so this decodes
+                if let Ok(message) = bincode::deserialize::<CcBftNetworkMessage>(&message.data) {
+                    if let Err(e) = consensus.receive_from_network(message) {
+                        tracing::warn!("ccBFT rejected network message: {}", e);
+                    }
+                }
+            }
+        }));
+    }
+    tasks
+}
@@ -0,0 +1,709 @@
+//! Peer discovery: static bootnodes, peer exchange and an optional
+//! Kademlia DHT, feeding a peer store persisted through core storage.
+//!
+//! Each round, discovery dials stored peers until the node reaches its
+//! outbound limit. A peer that cannot be reached is retried with
+//! exponential backoff and, unless it is a bootnode, forgotten after too
+//! many failures in a row. While short of peers, connected peers are asked
+//! for theirs.
+//!
+//! With the DHT enabled, connected peers also go into a Kademlia routing
+//! table keyed by the hash of their node id. Lookups ask the connected
+//! peers closest to a key for the nodes they know closest to it. Answers
+//! are only taken from peers that were asked, and only go into the peer
+//! store; a node enters the routing table once it is connected, so lookups
+//! converge over successive rounds. An address in the table only changes
+//! when dialing the new one succeeds. Refreshes look up the node's own key
+//! and a random one.
+
+use crate::p2p::{Direction, P2pNode, PeerEvent, WireMessage, MAX_EXCHANGED_PEERS};
+use cc_core::crypto::hash;
+use cc_core::{CCError, Hash, Result};
+use cc_core_storage::Storage;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// Storage key prefix of peer records
+const PEER_KEY_PREFIX: &[u8] = b"peer:";
+
+/// How long a peer asked for neighbours has to answer
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Discovery settings
+#[derive(Debug, Clone)]
+pub struct DiscoveryConfig {
+    pub bootnodes: Vec<SocketAddr>,
+    /// Time between discovery rounds
+    pub interval: Duration,
+    /// Delay before retrying a peer after its first failure; doubles with
+    /// each further failure
+    pub backoff_base: Duration,
+    pub backoff_max: Duration,
+    /// Consecutive failures after which a peer is forgotten
+    pub max_failures: u32,
+    /// Peers remembered in the store
+    pub max_stored_peers: usize,
+    /// Ask connected peers for their peers
+    pub peer_exchange: bool,
+    pub dht: Option<KademliaConfig>,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            bootnodes: Vec::new(),
+            interval: Duration::from_secs(30),
+            backoff_base: Duration::from_secs(5),
+            backoff_max: Duration::from_secs(30 * 60),
+            max_failures: 8,
+            max_stored_peers: 1000,
+            peer_exchange: true,
+            dht: None,
+        }
+    }
+}
+
+/// Kademlia settings
+#[derive(Debug, Clone)]
+pub struct KademliaConfig {
+    /// Nodes per bucket (k)
+    pub bucket_size: usize,
+    /// Peers asked per lookup (alpha)
+    pub parallelism: usize,
+    pub refresh_interval: Duration,
+}
+
+impl Default for KademliaConfig {
+    fn default() -> Self {
+        Self {
+            bucket_size: 16,
+            parallelism: 3,
+            refresh_interval: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+/// How a peer became known
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PeerSource {
+    Bootnode,
+    Inbound,
+    Exchange,
+    Dht,
+}
+
+/// What the store knows about a peer address
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerRecord {
+    pub address: SocketAddr,
+    pub node_id: Option<String>,
+    pub source: PeerSource,
+    /// Last successful connection, in seconds since the epoch
+    pub last_seen: Option<u64>,
+    /// Consecutive failed dials
+    pub failures: u32,
+    /// Earliest time to dial again, in seconds since the epoch
+    pub next_attempt: u64,
+}
+
+/// Known peers, persisted so a restarted node need not start from its
+/// bootnodes
+pub struct PeerStore {
+    storage: Arc<dyn Storage>,
+    records: Mutex<HashMap<SocketAddr, PeerRecord>>,
+    capacity: usize,
+}
+
+impl PeerStore {
+    /// Open the store, loading the records already persisted
+    pub fn open(storage: Arc<dyn Storage>, capacity: usize) -> Result<Self> {
+        let mut records = HashMap::new();
+        for entry in storage.scan_prefix(PEER_KEY_PREFIX).map_err(store_error)? {
+            let (_, value) = entry.map_err(store_error)?;
+            let record: PeerRecord = serde_json::from_slice(&value)?;
+            records.insert(record.address, record);
+        }
+        Ok(Self {
+            storage,
+            records: Mutex::new(records),
+            capacity: capacity.max(1),
+        })
+    }
+
+    pub fn get(&self, address: &SocketAddr) -> Option<PeerRecord> {
+        self.records.lock().get(address).cloned()
+    }
+
+    pub fn records(&self) -> Vec<PeerRecord> {
+        self.records.lock().values().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remember an address, keeping what is already known about it; a
+    /// full store makes room by forgetting its least reliable peer
+    pub fn add(&self, address: SocketAddr, source: PeerSource) -> Result<()> {
+        let mut records = self.records.lock();
+        if let Some(record) = records.get_mut(&address) {
+            if source == PeerSource::Bootnode && record.source != PeerSource::Bootnode {
+                record.source = source;
+                return self.persist(record);
+            }
+            return Ok(());
+        }
+
+        if records.len() >= self.capacity {
+            let evict = records
+                .values()
+                .filter(|record| record.source != PeerSource::Bootnode)
+                .max_by_key(|record| (record.failures, std::cmp::Reverse(record.last_seen)))
+                .map(|record| record.address);
+            match evict {
+                Some(evict) => {
+                    records.remove(&evict);
+                    self.storage.delete(&key(&evict)).map_err(store_error)?;
+                }
+                None => return Ok(()),
+            }
+        }
+
+        let record = PeerRecord {
+            address,
+            node_id: None,
+            source,
+            last_seen: None,
+            failures: 0,
+            next_attempt: 0,
+        };
+        self.persist(&record)?;
+        records.insert(address, record);
+        Ok(())
+    }
+
+    /// Record a successful connection
+    pub fn record_success(&self, address: SocketAddr, node_id: &str) -> Result<()> {
+        let mut records = self.records.lock();
+        match records.get_mut(&address) {
+            Some(record) => {
+                record.node_id = Some(node_id.to_string());
+                record.last_seen = Some(unix_now());
+                record.failures = 0;
+                record.next_attempt = 0;
+                self.persist(record)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Record a failed dial, backing off or forgetting the peer
+    pub fn record_failure(&self, address: SocketAddr, config: &DiscoveryConfig) -> Result<()> {
+        let mut records = self.records.lock();
+        let Some(record) = records.get_mut(&address) else {
+            return Ok(());
+        };
+        record.failures += 1;
+        if record.failures >= config.max_failures && record.source != PeerSource::Bootnode {
+            records.remove(&address);
+            return self.storage.delete(&key(&address)).map_err(store_error);
+        }
+
+        let backoff = config
+            .backoff_base
+            .saturating_mul(1u32 << (record.failures - 1).min(16))
+            .min(config.backoff_max);
+        record.next_attempt = unix_now() + backoff.as_secs();
+        self.persist(record)
+    }
+
+    /// Forget a peer
+    pub fn remove(&self, address: &SocketAddr) -> Result<()> {
+        self.records.lock().remove(address);
+        self.storage.delete(&key(address)).map_err(store_error)
+    }
+
+    /// Peers due to be dialed, most recently seen first
+    pub fn dial_candidates(&self, limit: usize) -> Vec<SocketAddr> {
+        let now = unix_now();
+        let mut due: Vec<PeerRecord> = self
+            .records
+            .lock()
+            .values()
+            .filter(|record| record.next_attempt <= now)
+            .cloned()
+            .collect();
+        due.sort_by_key(|record| (std::cmp::Reverse(record.last_seen), record.failures));
+        due.into_iter().take(limit).map(|record| record.address).collect()
+    }
+
+    fn persist(&self, record: &PeerRecord) -> Result<()> {
+        self.storage
+            .put(&key(&record.address), &serde_json::to_vec(record)?)
+            .map_err(store_error)
+    }
+}
+
+fn key(address: &SocketAddr) -> Vec<u8> {
+    [PEER_KEY_PREFIX, address.to_string().as_bytes()].concat()
+}
+
+fn store_error(error: cc_core_storage::StorageError) -> CCError {
+    CCError::Network(format!("Peer store: {}", error))
+}
+
+/// A node in the DHT
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DhtNode {
+    pub node_id: String,
+    pub address: SocketAddr,
+}
+
+impl DhtNode {
+    pub fn key(&self) -> Hash {
+        dht_key(&self.node_id)
+    }
+}
+
+/// Position of a node id in the DHT key space
+pub fn dht_key(node_id: &str) -> Hash {
+    hash(node_id.as_bytes())
+}
+
+fn distance(a: &Hash, b: &Hash) -> Hash {
+    let mut distance = [0u8; 32];
+    for (i, byte) in distance.iter_mut().enumerate() {
+        *byte = a[i] ^ b[i];
+    }
+    distance
+}
+
+/// Kademlia routing table: one bucket per bit of distance from the local
+/// key, least recently seen node first
+pub struct RoutingTable {
+    local: Hash,
+    buckets: Vec<VecDeque<DhtNode>>,
+    bucket_size: usize,
+}
+
+impl RoutingTable {
+    pub fn new(local_node_id: &str, bucket_size: usize) -> Self {
+        Self {
+            local: dht_key(local_node_id),
+            buckets: vec![VecDeque::new(); 256],
+            bucket_size: bucket_size.max(1),
+        }
+    }
+
+    fn bucket_index(&self, key: &Hash) -> Option<usize> {
+        let distance = distance(&self.local, key);
+        let leading_zeros = distance
+            .iter()
+            .position(|byte| *byte != 0)
+            .map(|i| i * 8 + distance[i].leading_zeros() as usize)?;
+        Some(255 - leading_zeros)
+    }
+
+    /// Add or refresh a node; a full bucket keeps its long-lived nodes,
+    /// and a known node keeps its address
+    pub fn insert(&mut self, node: DhtNode) -> bool {
+        self.upsert(node, false)
+    }
+
+    /// Add or refresh a node whose address was confirmed by dialing it,
+    /// replacing the address known for it
+    pub fn confirm(&mut self, node: DhtNode) -> bool {
+        self.upsert(node, true)
+    }
+
+    fn upsert(&mut self, node: DhtNode, confirmed: bool) -> bool {
+        let Some(index) = self.bucket_index(&node.key()) else {
+            return false;
+        };
+        let bucket_size = self.bucket_size;
+        let bucket = &mut self.buckets[index];
+        if let Some(position) = bucket.iter().position(|known| known.node_id == node.node_id) {
+            let known = bucket.remove(position).expect("position is in the bucket");
+            if !confirmed && known.address != node.address {
+                bucket.push_back(known);
+                return false;
+            }
+        } else if bucket.len() >= bucket_size {
+            return false;
+        }
+        bucket.push_back(node);
+        true
+    }
+
+    pub fn remove(&mut self, node_id: &str) {
+        if let Some(index) = self.bucket_index(&dht_key(node_id)) {
+            self.buckets[index].retain(|node| node.node_id != node_id);
+        }
+    }
+
+    /// Up to `count` known nodes closest to `target`
+    pub fn closest(&self, target: &Hash, count: usize) -> Vec<DhtNode> {
+        let mut nodes: Vec<DhtNode> = self.buckets.iter().flatten().cloned().collect();
+        nodes.sort_by_key(|node| distance(&node.key(), target));
+        nodes.truncate(count);
+        nodes
+    }
+
+    pub fn len(&self) -> usize {
+        self.buckets.iter().map(VecDeque::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Finds peers and keeps the node connected to them
+pub struct Discovery {
+    node: Arc<P2pNode>,
+    config: DiscoveryConfig,
+    store: PeerStore,
+    table: Option<Mutex<RoutingTable>>,
+    /// Peers asked for neighbours, and when
+    pending_lookups: Mutex<HashMap<String, Instant>>,
+    last_refresh: Mutex<Option<Instant>>,
+}
+
+impl Discovery {
+    /// Discovery for `node`, with its peer store in `storage`
+    pub fn new(node: Arc<P2pNode>, storage: Arc<dyn Storage>, config: DiscoveryConfig) -> Result<Arc<Self>> {
+        let store = PeerStore::open(storage, config.max_stored_peers)?;
+        for bootnode in &config.bootnodes {
+            store.add(*bootnode, PeerSource::Bootnode)?;
+        }
+        let table = config
+            .dht
+            .as_ref()
+            .map(|dht| Mutex::new(RoutingTable::new(node.node_id(), dht.bucket_size)));
+        Ok(Arc::new(Self {
+            node,
+            config,
+            store,
+            table,
+            pending_lookups: Mutex::new(HashMap::new()),
+            last_refresh: Mutex::new(None),
+        }))
+    }
+
+    pub fn peer_store(&self) -> &PeerStore {
+        &self.store
+    }
+
+    /// Nodes in the routing table, if the DHT is enabled
+    pub fn routing_table_size(&self) -> Option<usize> {
+        self.table.as_ref().map(|table| table.lock().len())
+    }
+
+    /// Run discovery rounds and handle peer events until the node is gone
+    pub fn start(self: &Arc<Self>) -> JoinHandle<()> {
+        let discovery = self.clone();
+        let mut events = self.node.events();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(discovery.config.interval);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => discovery.run_round().await,
+                    event = events.recv() => match event {
+                        Some(event) => discovery.handle_event(event),
+                        None => break,
+                    },
+                }
+            }
+        })
+    }
+
+    /// One discovery round
+    pub async fn run_round(&self) {
+        let open_slots = self
+            .node
+            .config()
+            .max_outbound
+            .saturating_sub(self.node.peer_count(Direction::Outbound));
+        if open_slots > 0 {
+            self.dial(open_slots).await;
+            if self.config.peer_exchange {
+                for peer in self.node.peers() {
+                    self.node.send(&peer.node_id, WireMessage::PeerRequest);
+                }
+            }
+        }
+
+        if let Some(dht) = &self.config.dht {
+            let due = self
+                .last_refresh
+                .lock()
+                .is_none_or(|last| last.elapsed() >= dht.refresh_interval);
+            if due {
+                *self.last_refresh.lock() = Some(Instant::now());
+                self.lookup(dht_key(self.node.node_id()));
+                self.lookup(dht_key(&uuid::Uuid::new_v4().to_string()));
+            }
+        }
+    }
+
+    /// Ask the connected peers closest to `target` for nodes near it
+    pub fn lookup(&self, target: Hash) {
+        let (Some(table), Some(dht)) = (&self.table, &self.config.dht) else {
+            return;
+        };
+        let closest = table.lock().closest(&target, usize::MAX);
+        closest
+            .into_iter()
+            .filter(|node| self.node.is_connected(&node.node_id))
+            .take(dht.parallelism)
+            .for_each(|node| {
+                if self.node.send(&node.node_id, WireMessage::FindNode(target)) {
+                    self.pending_lookups.lock().insert(node.node_id, Instant::now());
+                }
+            });
+    }
+
+    async fn dial(&self, limit: usize) {
+        let connected: Vec<SocketAddr> = self
+            .node
+            .peers()
+            .into_iter()
+            .flat_map(|peer| [Some(peer.address), peer.listen_addr])
+            .flatten()
+            .collect();
+        let candidates: Vec<SocketAddr> = self
+            .store
+            .dial_candidates(usize::MAX)
+            .into_iter()
+            .filter(|address| !connected.contains(address) && Some(*address) != self.node.local_addr())
+            .filter(|address| !self.node.is_banned(&address.ip().to_string()))
+            .take(limit)
+            .collect();
+
+        for address in candidates {
+            let result = match self.node.connect(address).await {
+                Ok(node_id) => self.store.record_success(address, &node_id),
+                Err(e) => {
+                    tracing::debug!("Failed to dial {}: {}", address, e);
+                    self.store.record_failure(address, &self.config)
+                }
+            };
+            if let Err(e) = result {
+                tracing::warn!("Failed to update peer store: {}", e);
+            }
+        }
+    }
+
+    fn handle_event(&self, event: PeerEvent) {
+        let result = match event {
+            PeerEvent::Connected(peer) => {
+                if let Some(table) = &self.table {
+                    // Only an address this node dialed is confirmed
+                    match (peer.direction, peer.listen_addr) {
+                        (Direction::Outbound, _) => table.lock().confirm(DhtNode {
+                            node_id: peer.node_id.clone(),
+                            address: peer.address,
+                        }),
+                        (Direction::Inbound, Some(address)) => table.lock().insert(DhtNode {
+                            node_id: peer.node_id.clone(),
+                            address,
+                        }),
+                        (Direction::Inbound, None) => false,
+                    };
+                }
+                match (peer.direction, peer.listen_addr) {
+                    (Direction::Inbound, Some(address)) => self.store.add(address, PeerSource::Inbound),
+                    _ => Ok(()),
+                }
+            }
+            PeerEvent::Disconnected(node_id) => {
+                if let Some(table) = &self.table {
+                    table.lock().remove(&node_id);
+                }
+                self.pending_lookups.lock().remove(&node_id);
+                Ok(())
+            }
+            PeerEvent::Addresses { addresses, .. } if self.config.peer_exchange => addresses
+                .into_iter()
+                .try_for_each(|address| self.store.add(address, PeerSource::Exchange)),
+            PeerEvent::Addresses { .. } => Ok(()),
+            PeerEvent::FindNode { from, target } => {
+                if let (Some(table), Some(dht)) = (&self.table, &self.config.dht) {
+                    let mut nodes = table.lock().closest(&target, dht.bucket_size.min(MAX_EXCHANGED_PEERS));
+                    nodes.retain(|node| node.node_id != from);
+                    self.node.send(&from, WireMessage::Neighbors(nodes));
+                }
+                Ok(())
+            }
+            PeerEvent::Neighbors { from, nodes } => {
+                let asked = self
+                    .pending_lookups
+                    .lock()
+                    .remove(&from)
+                    .is_some_and(|sent| sent.elapsed() < LOOKUP_TIMEOUT);
+                if !asked {
+                    tracing::debug!("Ignored unsolicited neighbours from {}", from);
+                    return;
+                }
+                nodes
+                    .into_iter()
+                    .filter(|node| node.node_id != self.node.node_id())
+                    .try_for_each(|node| self.store.add(node.address, PeerSource::Dht))
+            }
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to update peer store: {}", e);
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p2p::P2pConfig;
+    use cc_core_storage::InMemoryStorage;
+
+    fn local_node() -> Arc<P2pNode> {
+        P2pNode::new(P2pConfig {
+            listen_addr: "127.0.0.1:0".parse().unwrap(),
+            ..P2pConfig::default()
+        })
+    }
+
+    #[test]
+    fn test_peer_store_backoff_and_persistence() {
+        let storage: Arc<dyn Storage> = Arc::new(InMemoryStorage::new());
+        let config = DiscoveryConfig {
+            max_failures: 2,
+            ..DiscoveryConfig::default()
+        };
+        let bootnode: SocketAddr = "192.0.2.1:30303".parse().unwrap();
+        let peer: SocketAddr = "192.0.2.2:30303".parse().unwrap();
+
+        let store = PeerStore::open(storage.clone(), 10).unwrap();
+        store.add(bootnode, PeerSource::Bootnode).unwrap();
+        store.add(peer, PeerSource::Exchange).unwrap();
+        store.record_failure(bootnode, &config).unwrap();
+        assert_eq!(store.dial_candidates(10), vec![peer]);
+        assert!(store.get(&bootnode).unwrap().next_attempt >= unix_now() + config.backoff_base.as_secs());
+
+        // Bootnodes are never forgotten; other peers are
+        store.record_failure(bootnode, &config).unwrap();
+        store.record_failure(peer, &config).unwrap();
+        store.record_failure(peer, &config).unwrap();
+        let reopened = PeerStore::open(storage, 10).unwrap();
+        assert_eq!(reopened.records().len(), 1);
+        assert_eq!(reopened.get(&bootnode).unwrap().failures, 2);
+    }
+
+    #[test]
+    fn test_routing_table_orders_by_distance() {
+        let mut table = RoutingTable::new("local", 2);
+        for i in 0..50 {
+            table.insert(DhtNode {
+                node_id: format!("node-{}", i),
+                address: format!("192.0.2.{}:30303", i).parse().unwrap(),
+            });
+        }
+        assert!(!table.insert(DhtNode {
+            node_id: "local".to_string(),
+            address: "192.0.2.100:30303".parse().unwrap(),
+        }));
+        assert!(table.len() < 50, "full buckets keep their nodes");
+
+        // A known node's address only changes once dialing confirms it
+        let known = table.closest(&dht_key("node-7"), 1).remove(0);
+        let moved = DhtNode {
+            address: "198.51.100.1:30303".parse().unwrap(),
+            ..known.clone()
+        };
+        assert!(!table.insert(moved.clone()));
+        assert_eq!(table.closest(&known.key(), 1)[0].address, known.address);
+        assert!(table.confirm(moved.clone()));
+        assert_eq!(table.closest(&known.key(), 1)[0].address, moved.address);
+
+        let target = dht_key("node-7");
+        let closest = table.closest(&target, 3);
+        assert_eq!(closest.len(), 3);
+        let distances: Vec<Hash> = closest.iter().map(|node| distance(&node.key(), &target)).collect();
+        assert!(distances.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn test_only_solicited_neighbors_are_accepted() {
+        let config = DiscoveryConfig {
+            dht: Some(KademliaConfig::default()),
+            ..DiscoveryConfig::default()
+        };
+        let discovery = Discovery::new(local_node(), Arc::new(InMemoryStorage::new()), config).unwrap();
+        let neighbors = |from: &str| PeerEvent::Neighbors {
+            from: from.to_string(),
+            nodes: vec![DhtNode {
+                node_id: "far".to_string(),
+                address: "192.0.2.7:30303".parse().unwrap(),
+            }],
+        };
+
+        discovery.handle_event(neighbors("asked"));
+        assert!(discovery.peer_store().is_empty());
+
+        discovery.pending_lookups.lock().insert("asked".to_string(), Instant::now());
+        discovery.handle_event(neighbors("stranger"));
+        assert!(discovery.peer_store().is_empty());
+        discovery.handle_event(neighbors("asked"));
+        assert_eq!(discovery.peer_store().len(), 1);
+        assert_eq!(discovery.routing_table_size(), Some(0), "unconfirmed nodes stay out of the table");
+    }
+
+    #[tokio::test]
+    async fn test_discovery_finds_peers_through_bootnode() {
+        let bootnode = local_node();
+        let bootnode_addr = bootnode.listen().await.unwrap();
+        let first = local_node();
+        first.listen().await.unwrap();
+        first.connect(bootnode_addr).await.unwrap();
+
+        let node = local_node();
+        node.listen().await.unwrap();
+        let config = DiscoveryConfig {
+            bootnodes: vec![bootnode_addr],
+            dht: Some(KademliaConfig::default()),
+            ..DiscoveryConfig::default()
+        };
+        let discovery = Discovery::new(node.clone(), Arc::new(InMemoryStorage::new()), config).unwrap();
+        let mut events = node.events();
+        discovery.run_round().await;
+        assert!(node.is_connected(bootnode.node_id()));
+
+        // The bootnode's answer to the peer request names the first node
+        while let Some(event) = events.recv().await {
+            discovery.handle_event(event.clone());
+            if matches!(event, PeerEvent::Addresses { .. }) {
+                break;
+            }
+        }
+        let first_addr = first.local_addr().unwrap();
+        assert_eq!(discovery.peer_store().get(&first_addr).unwrap().source, PeerSource::Exchange);
+        assert_eq!(discovery.routing_table_size(), Some(1));
+
+        discovery.run_round().await;
+        assert!(node.is_connected(first.node_id()));
+        assert!(discovery.peer_store().get(&first_addr).unwrap().last_seen.is_some());
+    }
+}
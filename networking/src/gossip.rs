@@ -0,0 +1,185 @@
+//! Topic-based gossip for blocks, votes and transactions.
+//!
+//! A message is identified by the hash of its topic and payload, so every
+//! node derives the same id and a peer cannot replay a message under a new
+//! one. Each node remembers the ids it has recently seen, delivers a new
+//! message to local subscribers once, and forwards it to peers subscribed
+//! to the topic until its hop budget runs out.
+
+use cc_core::crypto::hash;
+use cc_core::Hash;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use tokio::sync::mpsc;
+
+/// Gossip topic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Topic {
+    Blocks,
+    Votes,
+    Transactions,
+}
+
+impl Topic {
+    pub const ALL: [Topic; 3] = [Topic::Blocks, Topic::Votes, Topic::Transactions];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Topic::Blocks => "cc/blocks/1",
+            Topic::Votes => "cc/votes/1",
+            Topic::Transactions => "cc/transactions/1",
+        }
+    }
+}
+
+/// A gossiped message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GossipMessage {
+    pub topic: Topic,
+    /// Encoded payload; the gossip layer does not interpret it
+    pub data: Vec<u8>,
+    /// Hops the message may still travel
+    pub ttl: u8,
+}
+
+impl GossipMessage {
+    pub fn id(&self) -> Hash {
+        let mut bytes = Vec::with_capacity(self.data.len() + 1);
+        bytes.push(self.topic as u8);
+        bytes.extend_from_slice(&self.data);
+        hash(&bytes)
+    }
+}
+
+/// Gossip settings
+#[derive(Debug, Clone)]
+pub struct GossipConfig {
+    /// Hops a message published here may travel
+    pub ttl: u8,
+    /// Message ids remembered for deduplication
+    pub seen_cache_size: usize,
+}
+
+impl Default for GossipConfig {
+    fn default() -> Self {
+        Self {
+            ttl: 6,
+            seen_cache_size: 10_000,
+        }
+    }
+}
+
+/// Checks a payload before it is delivered or forwarded
+pub type PayloadValidator = Box<dyn Fn(&[u8]) -> bool + Send + Sync>;
+
+/// What became of a received message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GossipOutcome {
+    /// New and valid; delivered locally and should be forwarded
+    Accepted,
+    Duplicate,
+    Invalid,
+}
+
+/// Recently seen message ids, oldest evicted first
+struct SeenCache {
+    ids: HashSet<Hash>,
+    order: VecDeque<Hash>,
+    capacity: usize,
+}
+
+impl SeenCache {
+    /// Record `id`, returning whether it was new
+    fn insert(&mut self, id: Hash) -> bool {
+        if !self.ids.insert(id) {
+            return false;
+        }
+        self.order.push_back(id);
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.ids.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+/// Deduplicates, validates and locally delivers gossip
+pub struct GossipEngine {
+    config: GossipConfig,
+    seen: Mutex<SeenCache>,
+    subscribers: Mutex<HashMap<Topic, Vec<mpsc::UnboundedSender<GossipMessage>>>>,
+    validators: Mutex<HashMap<Topic, PayloadValidator>>,
+}
+
+impl GossipEngine {
+    pub fn new(config: GossipConfig) -> Self {
+        Self {
+            seen: Mutex::new(SeenCache {
+                ids: HashSet::new(),
+                order: VecDeque::new(),
+                capacity: config.seen_cache_size.max(1),
+            }),
+            config,
+            subscribers: Mutex::new(HashMap::new()),
+            validators: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Receive messages on `topic` from now on
+    pub fn subscribe(&self, topic: Topic) -> mpsc::UnboundedReceiver<GossipMessage> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.subscribers.lock().entry(topic).or_default().push(sender);
+        receiver
+    }
+
+    /// Only deliver and forward messages on `topic` that `validator` accepts
+    pub fn set_validator(&self, topic: Topic, validator: PayloadValidator) {
+        self.validators.lock().insert(topic, validator);
+    }
+
+    /// Wrap a payload published by this node, marking it seen
+    pub fn publish(&self, topic: Topic, data: Vec<u8>) -> GossipMessage {
+        let message = GossipMessage {
+            topic,
+            data,
+            ttl: self.config.ttl,
+        };
+        self.seen.lock().insert(message.id());
+        message
+    }
+
+    /// Handle a message from a peer
+    pub fn receive(&self, message: &GossipMessage) -> GossipOutcome {
+        if !self.seen.lock().insert(message.id()) {
+            return GossipOutcome::Duplicate;
+        }
+        if let Some(validator) = self.validators.lock().get(&message.topic) {
+            if !validator(&message.data) {
+                return GossipOutcome::Invalid;
+            }
+        }
+
+        if let Some(subscribers) = self.subscribers.lock().get_mut(&message.topic) {
+            subscribers.retain(|subscriber| subscriber.send(message.clone()).is_ok());
+        }
+        GossipOutcome::Accepted
+    }
+
+    /// The message to pass on after receiving `message`, if it may travel
+    /// further; peers cannot grant more hops than this node would
+    pub fn forward(&self, message: &GossipMessage) -> Option<GossipMessage> {
+        let ttl = message.ttl.min(self.config.ttl);
+        (ttl > 1).then(|| GossipMessage {
+            ttl: ttl - 1,
+            ..message.clone()
+        })
+    }
+}
+
+impl Default for GossipEngine {
+    fn default() -> Self {
+        Self::new(GossipConfig::default())
+    }
+}
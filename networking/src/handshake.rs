@@ -0,0 +1,116 @@
+//! Peer handshake, exchanged as the first frames on every connection.
+//!
+//! Both sides send their handshake and check the other's: peers must be on
+//! the same chain, with the same genesis, speaking the same protocol
+//! version, and must not be this node itself.
+//!
+//! A node id is the hex-encoded public key of the node's identity key.
+//! Each handshake carries a fresh random nonce, and each side answers with
+//! a signature over the other's nonce, proving it holds the key its id
+//! names. Everything else in a handshake is the peer's own claim.
+
+use crate::gossip::Topic;
+use crate::transport::Connection;
+use cc_core::crypto::{CCKeypair, CCPublicKey, CCSignature};
+use cc_core::{CCError, Hash, Result};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Version of the peer-to-peer protocol spoken by this node
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// What a node tells a peer about itself when connecting
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Handshake {
+    pub chain_id: String,
+    pub protocol_version: u32,
+    pub node_id: String,
+    /// Identity key the node id is derived from
+    pub public_key: CCPublicKey,
+    /// Challenge the peer signs to prove it holds its key
+    pub nonce: [u8; 32],
+    /// Address the node accepts connections on, if any
+    pub listen_addr: Option<SocketAddr>,
+    pub height: u64,
+    pub genesis_hash: Hash,
+    /// Gossip topics the node wants to receive
+    pub topics: Vec<Topic>,
+}
+
+/// Signature over the peer's nonce, sent once both handshakes are exchanged
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HandshakeProof {
+    pub signature: CCSignature,
+}
+
+/// Node id of the node holding `public_key`
+pub fn node_id(public_key: &CCPublicKey) -> String {
+    hex::encode(public_key.0)
+}
+
+impl Handshake {
+    /// Check that `remote` is a peer this node can talk to
+    pub fn check(&self, remote: &Handshake) -> Result<()> {
+        if remote.chain_id != self.chain_id {
+            return Err(CCError::Network(format!(
+                "Peer is on chain {}, expected {}",
+                remote.chain_id, self.chain_id
+            )));
+        }
+        if remote.genesis_hash != self.genesis_hash {
+            return Err(CCError::Network(format!(
+                "Peer has genesis {}, expected {}",
+                hex::encode(remote.genesis_hash),
+                hex::encode(self.genesis_hash)
+            )));
+        }
+        if remote.protocol_version != self.protocol_version {
+            return Err(CCError::Network(format!(
+                "Peer speaks protocol version {}, expected {}",
+                remote.protocol_version, self.protocol_version
+            )));
+        }
+        if remote.node_id != node_id(&remote.public_key) {
+            return Err(CCError::Network("Peer node id does not match its key".to_string()));
+        }
+        if remote.node_id == self.node_id {
+            return Err(CCError::Network("Connected to self".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// What a node signs to answer `nonce` on `chain_id`
+fn proof_message(chain_id: &str, nonce: &[u8; 32]) -> Vec<u8> {
+    [b"cc-p2p-handshake:".as_slice(), chain_id.as_bytes(), b":", nonce].concat()
+}
+
+/// Send `local`, read and check the peer's handshake, then prove to each
+/// other that both hold the keys their ids name, all within `timeout`
+pub async fn exchange(
+    connection: &mut Connection,
+    local: &Handshake,
+    keypair: &CCKeypair,
+    timeout: Duration,
+) -> Result<Handshake> {
+    tokio::time::timeout(timeout, async {
+        connection.writer.write_message(local).await?;
+        let (remote, _) = connection.reader.read_message::<Handshake>().await?;
+        local.check(&remote)?;
+
+        let proof = HandshakeProof {
+            signature: keypair.sign(&proof_message(&local.chain_id, &remote.nonce)),
+        };
+        connection.writer.write_message(&proof).await?;
+        let (remote_proof, _) = connection.reader.read_message::<HandshakeProof>().await?;
+        if !remote
+            .public_key
+            .verify(&proof_message(&local.chain_id, &local.nonce), &remote_proof.signature)
+        {
+            return Err(CCError::Network(format!("Peer {} failed to prove its key", remote.node_id)));
+        }
+        Ok(remote)
+    })
+    .await?
+}
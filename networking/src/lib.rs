@@ -1,13 +1,28 @@
 //! CC Chain Networking Layer
 //!
 //! This crate handles all network-related functionality:
-//! - Peer-to-peer networking
+//! - Peer-to-peer networking: TCP/QUIC transports, peer handshake and
+//!   topic-based gossip
+//! - Peer discovery: bootnodes, peer exchange and a Kademlia DHT
+//! - Peer reputation, throttling and bans
 //! - Cross-chain bridge functionality
 //! - Network communication protocols
 
 pub mod bridge;
+pub mod consensus_io;
+pub mod discovery;
+pub mod gossip;
+pub mod handshake;
 pub mod network;
+pub mod p2p;
+pub mod reputation;
+pub mod transport;
 
 // Re-export main networking types
 pub use bridge::CrossChainBridge;
-pub use network::{NetworkManager, NetworkStats};
\ No newline at end of file
+pub use discovery::{Discovery, DiscoveryConfig, KademliaConfig};
+pub use gossip::{GossipMessage, Topic};
+pub use network::{NetworkManager, NetworkStats};
+pub use p2p::{P2pConfig, P2pNode};
+pub use reputation::{PeerBehavior, PeerReputation, PeerStanding, ReputationConfig};
+pub use transport::TransportKind;
\ No newline at end of file
@@ -69,7 +69,7 @@ pub struct NetworkManager {
     validator_addresses: Arc<dashmap::DashSet<SocketAddr>>,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct NetworkStats {
     pub messages_sent: u64,
     pub messages_received: u64,
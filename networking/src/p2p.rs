@@ -0,0 +1,704 @@
+//! Peer-to-peer node: accepts and dials connections, runs the handshake on
+//! each, and gossips over every established peer.
+//!
+//! Every peer gets a bounded outbound queue drained by its own writer task.
+//! Gossip to a peer whose queue is full is dropped for that peer rather
+//! than holding up the others; the message still reaches it through other
+//! paths in the mesh. Inbound connections beyond the limit are refused
+//! before the handshake, counting those still handshaking; the limits are
+//! checked again once a handshake completes. Peer exchange requests are answered here;
+//! everything else discovery needs is reported as `PeerEvent`s.
+//!
+//! Every message a peer sends is scored (see `reputation`). Peers that sink
+//! below the ban threshold are disconnected, and their node id, which the
+//! handshake proves they hold the key for, and the IP address they
+//! connected from are quarantined. Quarantined ids and IPs are refused in
+//! either direction. The quarantine list can be shared with the consensus
+//! safety system.
+
+use crate::discovery::DhtNode;
+use crate::gossip::{GossipConfig, GossipEngine, GossipMessage, GossipOutcome, Topic};
+use crate::handshake::{self, Handshake, PROTOCOL_VERSION};
+use crate::network::NetworkStats;
+use crate::reputation::{PeerBehavior, PeerReputation, PeerStanding, ReputationConfig};
+use crate::transport::{self, Connection, Listener, TransportKind};
+use cc_core::crypto::CCKeypair;
+use cc_core::{CCError, Hash, Result};
+use consensus::safety::QuarantineList;
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Notify};
+
+/// Peer-to-peer settings
+#[derive(Debug, Clone)]
+pub struct P2pConfig {
+    pub chain_id: String,
+    pub genesis_hash: Hash,
+    pub listen_addr: SocketAddr,
+    pub transport: TransportKind,
+    /// Gossip topics this node receives
+    pub topics: Vec<Topic>,
+    pub handshake_timeout: Duration,
+    /// Messages queued per peer before further gossip to it is dropped
+    pub peer_queue_size: usize,
+    pub max_inbound: usize,
+    pub max_outbound: usize,
+    pub gossip: GossipConfig,
+    pub reputation: ReputationConfig,
+}
+
+impl Default for P2pConfig {
+    fn default() -> Self {
+        Self {
+            chain_id: "cc-chain".to_string(),
+            genesis_hash: [0u8; 32],
+            listen_addr: "0.0.0.0:30303".parse().unwrap(),
+            transport: TransportKind::Tcp,
+            topics: Topic::ALL.to_vec(),
+            handshake_timeout: Duration::from_secs(10),
+            peer_queue_size: 1024,
+            max_inbound: 40,
+            max_outbound: 10,
+            gossip: GossipConfig::default(),
+            reputation: ReputationConfig::default(),
+        }
+    }
+}
+
+/// Messages exchanged after the handshake
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WireMessage {
+    Gossip(GossipMessage),
+    /// Replace the topics the sender wants to receive
+    Subscribe(Vec<Topic>),
+    /// Ask for addresses of the receiver's peers
+    PeerRequest,
+    Peers(Vec<SocketAddr>),
+    /// Ask for the nodes the receiver knows closest to a DHT key
+    FindNode(Hash),
+    Neighbors(Vec<DhtNode>),
+}
+
+/// Addresses handed out per peer exchange request, and the most a peer may
+/// send in one `Peers` or `Neighbors` message
+pub(crate) const MAX_EXCHANGED_PEERS: usize = 32;
+
+/// Something that happened to a peer, for discovery to act on
+#[derive(Debug, Clone)]
+pub enum PeerEvent {
+    Connected(ConnectedPeer),
+    Disconnected(String),
+    /// Addresses a peer shared through peer exchange
+    Addresses { from: String, addresses: Vec<SocketAddr> },
+    FindNode { from: String, target: Hash },
+    Neighbors { from: String, nodes: Vec<DhtNode> },
+}
+
+/// Which side opened a connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
+/// A peer with an established connection
+#[derive(Debug, Clone)]
+pub struct ConnectedPeer {
+    pub node_id: String,
+    pub address: SocketAddr,
+    pub listen_addr: Option<SocketAddr>,
+    pub direction: Direction,
+    pub transport: TransportKind,
+    pub protocol_version: u32,
+    pub height: u64,
+    pub topics: HashSet<Topic>,
+    pub connected_at: Instant,
+}
+
+struct PeerEntry {
+    info: ConnectedPeer,
+    sender: mpsc::Sender<WireMessage>,
+    shutdown: Arc<Notify>,
+}
+
+/// A node in the peer-to-peer network
+pub struct P2pNode {
+    config: P2pConfig,
+    keypair: CCKeypair,
+    node_id: String,
+    height: AtomicU64,
+    local_addr: RwLock<Option<SocketAddr>>,
+    peers: DashMap<String, PeerEntry>,
+    /// Inbound connections still in the handshake
+    pending_inbound: AtomicUsize,
+    gossip: GossipEngine,
+    reputation: PeerReputation,
+    quarantine: RwLock<Arc<QuarantineList>>,
+    events: RwLock<Vec<mpsc::UnboundedSender<PeerEvent>>>,
+    stats: RwLock<NetworkStats>,
+}
+
+impl P2pNode {
+    /// A node with a freshly generated identity key
+    pub fn new(config: P2pConfig) -> Arc<Self> {
+        Self::with_keypair(config, CCKeypair::generate())
+    }
+
+    /// A node identified by `keypair`
+    pub fn with_keypair(config: P2pConfig, keypair: CCKeypair) -> Arc<Self> {
+        Arc::new(Self {
+            gossip: GossipEngine::new(config.gossip.clone()),
+            reputation: PeerReputation::new(config.reputation.clone()),
+            quarantine: RwLock::new(Arc::new(QuarantineList::new())),
+            config,
+            node_id: handshake::node_id(&keypair.public_key()),
+            keypair,
+            height: AtomicU64::new(0),
+            local_addr: RwLock::new(None),
+            peers: DashMap::new(),
+            pending_inbound: AtomicUsize::new(0),
+            events: RwLock::new(Vec::new()),
+            stats: RwLock::new(NetworkStats::default()),
+        })
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    pub fn config(&self) -> &P2pConfig {
+        &self.config
+    }
+
+    /// Address accepting connections, once listening
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        *self.local_addr.read()
+    }
+
+    /// Chain height advertised in handshakes
+    pub fn set_height(&self, height: u64) {
+        self.height.store(height, Ordering::Relaxed);
+    }
+
+    pub fn gossip(&self) -> &GossipEngine {
+        &self.gossip
+    }
+
+    pub fn reputation(&self) -> &PeerReputation {
+        &self.reputation
+    }
+
+    /// Keep bans in `quarantine`, typically the consensus safety system's
+    /// list, instead of a list of the node's own
+    pub fn use_quarantine(&self, quarantine: Arc<QuarantineList>) {
+        *self.quarantine.write() = quarantine;
+    }
+
+    /// Whether a peer node id or IP address is quarantined
+    pub fn is_banned(&self, id: &str) -> bool {
+        self.quarantine.read().is_quarantined(id)
+    }
+
+    /// Disconnect a peer and quarantine its node id and the IP address it
+    /// connected from
+    pub fn ban(&self, node_id: &str, reason: &str) {
+        let quarantine = self.quarantine.read().clone();
+        let duration = self.config.reputation.ban_duration;
+        if let Some(address) = self.peers.get(node_id).map(|peer| peer.info.address) {
+            quarantine.quarantine(address.ip().to_string(), reason, duration);
+        }
+        quarantine.quarantine(node_id, reason, duration);
+        tracing::warn!("Banned peer {}: {}", node_id, reason);
+        self.disconnect(node_id);
+    }
+
+    /// Score a peer's behaviour, banning it if its score falls too low
+    pub fn report(&self, node_id: &str, behavior: PeerBehavior) {
+        if self.reputation.record(node_id, behavior) == PeerStanding::Banned {
+            self.ban(node_id, &format!("Reputation fell below the ban threshold after {:?}", behavior));
+        }
+    }
+
+    /// Start accepting connections, returning the bound address
+    pub async fn listen(self: &Arc<Self>) -> Result<SocketAddr> {
+        let listener = Listener::bind(self.config.transport, self.config.listen_addr).await?;
+        let local_addr = listener.local_addr()?;
+        *self.local_addr.write() = Some(local_addr);
+        tracing::info!("P2P listener started on {} ({:?})", local_addr, self.config.transport);
+
+        let node = self.clone();
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok(incoming) => {
+                        // Refused before the handshake, so connections held
+                        // open without handshaking cannot pile up
+                        let pending = node.pending_inbound.load(Ordering::Relaxed);
+                        if node.peer_count(Direction::Inbound) + pending >= node.config.max_inbound {
+                            tracing::debug!(
+                                "Refused inbound connection from {}: inbound peer limit reached",
+                                incoming.remote_addr
+                            );
+                            incoming.refuse();
+                            continue;
+                        }
+                        node.pending_inbound.fetch_add(1, Ordering::Relaxed);
+                        let node = node.clone();
+                        tokio::spawn(async move {
+                            let address = incoming.remote_addr;
+                            // Transport setup (the QUIC handshake) happens here
+                            // rather than in the accept loop, so a slow peer
+                            // only holds up its own connection
+                            let result = async {
+                                let connection =
+                                    tokio::time::timeout(node.config.handshake_timeout, incoming.connect()).await??;
+                                node.establish(connection, Direction::Inbound).await
+                            }
+                            .await;
+                            node.pending_inbound.fetch_sub(1, Ordering::Relaxed);
+                            if let Err(e) = result {
+                                tracing::debug!("Rejected inbound peer {}: {}", address, e);
+                            }
+                        });
+                    }
+                    Err(e) => tracing::error!("Failed to accept connection: {}", e),
+                }
+            }
+        });
+        Ok(local_addr)
+    }
+
+    /// Dial `addr` and complete the handshake, returning the peer's node id
+    pub async fn connect(self: &Arc<Self>, addr: SocketAddr) -> Result<String> {
+        if self.peer_count(Direction::Outbound) >= self.config.max_outbound {
+            return Err(CCError::Network("Outbound peer limit reached".to_string()));
+        }
+        if self.is_banned(&addr.ip().to_string()) {
+            return Err(CCError::Network(format!("Address {} is banned", addr.ip())));
+        }
+        let connection = tokio::time::timeout(
+            self.config.handshake_timeout,
+            transport::dial(self.config.transport, addr),
+        )
+        .await??;
+        self.establish(connection, Direction::Outbound).await
+    }
+
+    /// Close the connection to a peer
+    pub fn disconnect(&self, node_id: &str) -> bool {
+        match self.peers.remove(node_id) {
+            Some((_, entry)) => {
+                entry.shutdown.notify_one();
+                self.stats.write().connected_peers = self.peers.len();
+                self.reputation.disconnected(node_id);
+                self.emit(PeerEvent::Disconnected(node_id.to_string()));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn peers(&self) -> Vec<ConnectedPeer> {
+        self.peers.iter().map(|entry| entry.info.clone()).collect()
+    }
+
+    pub fn is_connected(&self, node_id: &str) -> bool {
+        self.peers.contains_key(node_id)
+    }
+
+    pub fn peer_count(&self, direction: Direction) -> usize {
+        self.peers.iter().filter(|peer| peer.info.direction == direction).count()
+    }
+
+    /// Queue `message` for a peer, returning whether it was accepted
+    pub fn send(&self, node_id: &str, message: WireMessage) -> bool {
+        self.peers
+            .get(node_id)
+            .is_some_and(|peer| peer.sender.try_send(message).is_ok())
+    }
+
+    /// Receive peer events from now on
+    pub fn events(&self) -> mpsc::UnboundedReceiver<PeerEvent> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.events.write().push(sender);
+        receiver
+    }
+
+    fn emit(&self, event: PeerEvent) {
+        self.events
+            .write()
+            .retain(|sender| sender.send(event.clone()).is_ok());
+    }
+
+    /// Receive gossip on `topic` from now on
+    pub fn subscribe(&self, topic: Topic) -> mpsc::UnboundedReceiver<GossipMessage> {
+        self.gossip.subscribe(topic)
+    }
+
+    /// Gossip `data` on `topic`, returning the message id
+    pub fn publish(&self, topic: Topic, data: Vec<u8>) -> Hash {
+        let message = self.gossip.publish(topic, data);
+        let id = message.id();
+        self.broadcast(&message, None);
+        id
+    }
+
+    pub fn stats(&self) -> NetworkStats {
+        self.stats.read().clone()
+    }
+
+    /// Send `message` to every peer subscribed to its topic except `except`
+    fn broadcast(&self, message: &GossipMessage, except: Option<&str>) {
+        for peer in self.peers.iter() {
+            if Some(peer.key().as_str()) == except || !peer.info.topics.contains(&message.topic) {
+                continue;
+            }
+            if peer.sender.try_send(WireMessage::Gossip(message.clone())).is_err() {
+                tracing::debug!("Dropped gossip to {}: queue full", peer.key());
+            }
+        }
+    }
+
+    fn local_handshake(&self) -> Handshake {
+        Handshake {
+            chain_id: self.config.chain_id.clone(),
+            protocol_version: PROTOCOL_VERSION,
+            node_id: self.node_id.clone(),
+            public_key: self.keypair.public_key(),
+            nonce: rand::random(),
+            listen_addr: self.local_addr(),
+            height: self.height.load(Ordering::Relaxed),
+            genesis_hash: self.config.genesis_hash,
+            topics: self.config.topics.clone(),
+        }
+    }
+
+    /// Handshake over `connection` and start serving the peer
+    async fn establish(self: &Arc<Self>, mut connection: Connection, direction: Direction) -> Result<String> {
+        let ip = connection.remote_addr.ip();
+        if self.is_banned(&ip.to_string()) {
+            return Err(CCError::Network(format!("Address {} is banned", ip)));
+        }
+        let remote = handshake::exchange(
+            &mut connection,
+            &self.local_handshake(),
+            &self.keypair,
+            self.config.handshake_timeout,
+        )
+        .await?;
+        let node_id = remote.node_id.clone();
+        let limit = match direction {
+            Direction::Inbound => self.config.max_inbound,
+            Direction::Outbound => self.config.max_outbound,
+        };
+        if self.peer_count(direction) >= limit {
+            return Err(CCError::Network(format!("{:?} peer limit reached", direction)));
+        }
+        // A peer listening on all interfaces is reachable where it dialed from
+        let listen_addr = remote.listen_addr.map(|addr| {
+            if addr.ip().is_unspecified() {
+                SocketAddr::new(connection.remote_addr.ip(), addr.port())
+            } else {
+                addr
+            }
+        });
+        if self.is_banned(&node_id) {
+            return Err(CCError::Network(format!("Peer {} is banned", node_id)));
+        }
+
+        let (sender, mut receiver) = mpsc::channel(self.config.peer_queue_size.max(1));
+        let shutdown = Arc::new(Notify::new());
+        let info = ConnectedPeer {
+            node_id: node_id.clone(),
+            address: connection.remote_addr,
+            listen_addr,
+            direction,
+            transport: connection.kind,
+            protocol_version: remote.protocol_version,
+            height: remote.height,
+            topics: remote.topics.into_iter().collect(),
+            connected_at: Instant::now(),
+        };
+        match self.peers.entry(node_id.clone()) {
+            dashmap::mapref::entry::Entry::Occupied(_) => {
+                return Err(CCError::Network(format!("Already connected to {}", node_id)));
+            }
+            dashmap::mapref::entry::Entry::Vacant(entry) => {
+                entry.insert(PeerEntry {
+                    info: info.clone(),
+                    sender,
+                    shutdown: shutdown.clone(),
+                });
+            }
+        }
+        self.stats.write().connected_peers = self.peers.len();
+        self.emit(PeerEvent::Connected(info));
+        tracing::info!("Established {:?} connection with peer {} ({})", direction, node_id, connection.remote_addr);
+
+        let (mut reader, mut writer) = connection.split();
+        let node = self.clone();
+        tokio::spawn(async move {
+            while let Some(message) = receiver.recv().await {
+                match writer.write_message(&message).await {
+                    Ok(bytes) => {
+                        let mut stats = node.stats.write();
+                        stats.messages_sent += 1;
+                        stats.bytes_sent += bytes as u64;
+                    }
+                    Err(e) => {
+                        tracing::debug!("Write to peer failed: {}", e);
+                        break;
+                    }
+                }
+            }
+        });
+
+        let node = self.clone();
+        let peer_id = node_id.clone();
+        tokio::spawn(async move {
+            loop {
+                let frame = tokio::select! {
+                    _ = shutdown.notified() => break,
+                    frame = reader.read_frame() => frame,
+                };
+                match frame {
+                    Ok(frame) => {
+                        {
+                            let mut stats = node.stats.write();
+                            stats.messages_received += 1;
+                            stats.bytes_received += frame.len() as u64;
+                        }
+                        match bincode::deserialize::<WireMessage>(&frame) {
+                            Ok(message) => node.handle_message(&peer_id, message),
+                            Err(e) => {
+                                tracing::debug!("Undecodable message from peer {}: {}", peer_id, e);
+                                node.report(&peer_id, PeerBehavior::ProtocolViolation);
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        tracing::debug!("Connection with peer {} closed: {}", peer_id, e);
+                        break;
+                    }
+                }
+            }
+            node.disconnect(&peer_id);
+        });
+
+        Ok(node_id)
+    }
+
+    fn handle_message(&self, peer_id: &str, message: WireMessage) {
+        if !self.reputation.allow(peer_id) {
+            tracing::debug!("Dropped message from throttled peer {}", peer_id);
+            return;
+        }
+        match message {
+            WireMessage::Gossip(message) => match self.gossip.receive(&message) {
+                GossipOutcome::Accepted => {
+                    self.report(peer_id, PeerBehavior::UsefulMessage);
+                    if let Some(forward) = self.gossip.forward(&message) {
+                        self.broadcast(&forward, Some(peer_id));
+                    }
+                }
+                GossipOutcome::Duplicate => self.report(peer_id, PeerBehavior::DuplicateMessage),
+                GossipOutcome::Invalid => self.report(peer_id, PeerBehavior::InvalidMessage),
+            },
+            WireMessage::Subscribe(topics) => {
+                if let Some(mut peer) = self.peers.get_mut(peer_id) {
+                    peer.info.topics = topics.into_iter().collect();
+                }
+            }
+            WireMessage::PeerRequest => {
+                let addresses = self
+                    .peers
+                    .iter()
+                    .filter(|peer| peer.key() != peer_id)
+                    .filter_map(|peer| peer.info.listen_addr)
+                    .take(MAX_EXCHANGED_PEERS)
+                    .collect();
+                self.send(peer_id, WireMessage::Peers(addresses));
+            }
+            WireMessage::Peers(mut addresses) => {
+                if addresses.len() > MAX_EXCHANGED_PEERS {
+                    self.report(peer_id, PeerBehavior::ProtocolViolation);
+                    addresses.truncate(MAX_EXCHANGED_PEERS);
+                }
+                self.emit(PeerEvent::Addresses {
+                    from: peer_id.to_string(),
+                    addresses,
+                });
+            }
+            WireMessage::FindNode(target) => self.emit(PeerEvent::FindNode {
+                from: peer_id.to_string(),
+                target,
+            }),
+            WireMessage::Neighbors(mut nodes) => {
+                if nodes.len() > MAX_EXCHANGED_PEERS {
+                    self.report(peer_id, PeerBehavior::ProtocolViolation);
+                    nodes.truncate(MAX_EXCHANGED_PEERS);
+                }
+                self.emit(PeerEvent::Neighbors {
+                    from: peer_id.to_string(),
+                    nodes,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local_config() -> P2pConfig {
+        P2pConfig {
+            listen_addr: "127.0.0.1:0".parse().unwrap(),
+            ..P2pConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gossip_reaches_peers_of_peers_once() {
+        let nodes: Vec<Arc<P2pNode>> = (0..3).map(|_| P2pNode::new(local_config())).collect();
+        let mut addrs = Vec::new();
+        for node in &nodes {
+            addrs.push(node.listen().await.unwrap());
+        }
+        // A line: 0 - 1 - 2
+        nodes[0].connect(addrs[1]).await.unwrap();
+        nodes[1].connect(addrs[2]).await.unwrap();
+        let mut received = nodes[2].subscribe(Topic::Transactions);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        nodes[0].publish(Topic::Transactions, b"tx".to_vec());
+        nodes[0].publish(Topic::Transactions, b"tx".to_vec());
+        let message = tokio::time::timeout(Duration::from_secs(5), received.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(message.data, b"tx");
+        assert_eq!(message.ttl, GossipConfig::default().ttl - 1);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(received.try_recv().is_err());
+        assert_eq!(nodes[1].peers().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_handshake_rejects_other_chains() {
+        let node = P2pNode::new(local_config());
+        let addr = node.listen().await.unwrap();
+        let other = P2pNode::new(P2pConfig {
+            chain_id: "other-chain".to_string(),
+            ..local_config()
+        });
+
+        let error = other.connect(addr).await.unwrap_err();
+        assert!(error.to_string().contains("chain"));
+        assert!(other.peers().is_empty());
+        assert!(node.connect(addr).await.is_err(), "connecting to self is refused");
+    }
+
+    #[tokio::test]
+    async fn test_inbound_limit_refuses_before_handshake() {
+        let node = P2pNode::new(P2pConfig {
+            max_inbound: 1,
+            ..local_config()
+        });
+        let addr = node.listen().await.unwrap();
+
+        P2pNode::new(local_config()).connect(addr).await.unwrap();
+        let refused = P2pNode::new(local_config());
+        assert!(refused.connect(addr).await.is_err());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(node.peer_count(Direction::Inbound), 1);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_gossip_gets_peer_banned() {
+        let safety = consensus::SafetySystem::new(consensus::SafetyConfig::default());
+        let node = P2pNode::new(local_config());
+        node.use_quarantine(safety.quarantine().clone());
+        node.gossip().set_validator(Topic::Blocks, Box::new(|data| data.starts_with(b"block")));
+        let addr = node.listen().await.unwrap();
+        let other = P2pNode::new(local_config());
+        let other_addr = other.listen().await.unwrap();
+        other.connect(addr).await.unwrap();
+        let other_id = other.node_id().to_string();
+
+        for i in 0..10 {
+            other.publish(Topic::Blocks, format!("junk-{}", i).into_bytes());
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!node.is_connected(&other_id));
+        assert_eq!(node.reputation().standing(&other_id), PeerStanding::Banned);
+        assert!(safety.quarantine().is_quarantined(&other_id));
+        assert!(safety.quarantine().is_quarantined(&other_addr.ip().to_string()));
+        assert!(node.connect(other_addr).await.is_err());
+
+        // A new identity from the same address is refused before the handshake
+        let renamed = P2pNode::new(local_config());
+        assert!(renamed.connect(addr).await.is_err());
+        assert!(node.peers().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_handshake_binds_node_id_to_key() {
+        let node = P2pNode::new(local_config());
+        let honest = P2pNode::new(local_config());
+        let mut impostor = P2pNode::new(local_config()).local_handshake();
+        impostor.node_id = honest.node_id().to_string();
+
+        let error = node.local_handshake().check(&impostor).unwrap_err();
+        assert!(error.to_string().contains("does not match its key"));
+        assert!(node.local_handshake().check(&honest.local_handshake()).is_ok());
+    }
+
+    #[cfg(feature = "quic")]
+    #[tokio::test]
+    async fn test_quic_transport() {
+        let config = P2pConfig {
+            transport: TransportKind::Quic,
+            ..local_config()
+        };
+        let node = P2pNode::new(config.clone());
+        let addr = node.listen().await.unwrap();
+        let other = P2pNode::new(config);
+        let mut received = node.subscribe(Topic::Blocks);
+
+        other.connect(addr).await.unwrap();
+        other.publish(Topic::Blocks, b"block".to_vec());
+        let message = tokio::time::timeout(Duration::from_secs(5), received.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(message.data, b"block");
+    }
+
+    #[cfg(feature = "quic")]
+    #[tokio::test]
+    async fn test_stalled_quic_peer_does_not_block_accepts() {
+        let config = P2pConfig {
+            transport: TransportKind::Quic,
+            ..local_config()
+        };
+        let node = P2pNode::new(config.clone());
+        let addr = node.listen().await.unwrap();
+
+        // Completes the QUIC handshake but never sends on its stream
+        let _stalled = transport::dial(TransportKind::Quic, addr).await.unwrap();
+        let other = P2pNode::new(config);
+        tokio::time::timeout(Duration::from_secs(5), other.connect(addr))
+            .await
+            .unwrap()
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(node.peer_count(Direction::Inbound), 1);
+    }
+}
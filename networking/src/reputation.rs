@@ -0,0 +1,276 @@
+//! Peer reputation: scores peers on what they send and throttles or bans
+//! those that fall too low.
+//!
+//! A peer starts at zero. Messages that turn out useful raise its score;
+//! duplicates, invalid payloads and protocol violations lower it. Scores
+//! decay toward zero, so old behaviour is eventually forgiven. Duplicates
+//! cost little, because peers in a mesh forward copies of the same message
+//! in good faith.
+//!
+//! Below the throttle threshold a peer's messages are rate limited. Below
+//! the ban threshold the node disconnects it and quarantines its node id
+//! and IP address in the safety quarantine list.
+//!
+//! Scores of disconnected peers are kept until they decay to nearly zero,
+//! and at most `max_tracked_peers` are kept at all: when full, the score
+//! nearest zero is dropped first.
+
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Reputation settings
+#[derive(Debug, Clone)]
+pub struct ReputationConfig {
+    pub useful_reward: f64,
+    pub duplicate_penalty: f64,
+    pub invalid_penalty: f64,
+    pub violation_penalty: f64,
+    /// Highest score a peer can build up
+    pub max_score: f64,
+    pub throttle_threshold: f64,
+    pub ban_threshold: f64,
+    /// Time for a score to decay halfway to zero
+    pub half_life: Duration,
+    /// Messages per second processed from a throttled peer
+    pub throttled_rate: u32,
+    pub ban_duration: Duration,
+    /// Peers whose scores are kept
+    pub max_tracked_peers: usize,
+}
+
+impl Default for ReputationConfig {
+    fn default() -> Self {
+        Self {
+            useful_reward: 1.0,
+            duplicate_penalty: 0.1,
+            invalid_penalty: 10.0,
+            violation_penalty: 25.0,
+            max_score: 100.0,
+            throttle_threshold: -20.0,
+            ban_threshold: -50.0,
+            half_life: Duration::from_secs(10 * 60),
+            throttled_rate: 10,
+            ban_duration: Duration::from_secs(60 * 60),
+            max_tracked_peers: 10_000,
+        }
+    }
+}
+
+/// Behaviour a peer is scored on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerBehavior {
+    UsefulMessage,
+    DuplicateMessage,
+    InvalidMessage,
+    ProtocolViolation,
+}
+
+/// Scores closer to zero than this are forgotten
+const FORGOTTEN_SCORE: f64 = 1.0;
+
+/// How a peer is treated given its score
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerStanding {
+    Good,
+    Throttled,
+    Banned,
+}
+
+struct PeerScore {
+    score: f64,
+    updated: Instant,
+    /// Start of the current rate-limiting window and messages allowed in it
+    window_start: Instant,
+    window_count: u32,
+}
+
+/// Scores of connected and recently seen peers
+pub struct PeerReputation {
+    config: ReputationConfig,
+    scores: Mutex<HashMap<String, PeerScore>>,
+}
+
+impl PeerReputation {
+    pub fn new(config: ReputationConfig) -> Self {
+        Self {
+            config,
+            scores: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &ReputationConfig {
+        &self.config
+    }
+
+    /// Score `behavior` by a peer, returning its standing afterwards
+    pub fn record(&self, node_id: &str, behavior: PeerBehavior) -> PeerStanding {
+        let change = match behavior {
+            PeerBehavior::UsefulMessage => self.config.useful_reward,
+            PeerBehavior::DuplicateMessage => -self.config.duplicate_penalty,
+            PeerBehavior::InvalidMessage => -self.config.invalid_penalty,
+            PeerBehavior::ProtocolViolation => -self.config.violation_penalty,
+        };
+        let now = Instant::now();
+        let mut scores = self.scores.lock();
+        if !scores.contains_key(node_id) && scores.len() >= self.config.max_tracked_peers.max(1) {
+            self.make_room(&mut scores, now);
+        }
+        let entry = scores.entry(node_id.to_string()).or_insert_with(|| PeerScore {
+            score: 0.0,
+            updated: now,
+            window_start: now,
+            window_count: 0,
+        });
+        entry.score = (self.decay(entry, now) + change).min(self.config.max_score);
+        entry.updated = now;
+        self.standing_for(entry.score)
+    }
+
+    /// Current score of a peer; unknown peers score zero
+    pub fn score(&self, node_id: &str) -> f64 {
+        self.scores
+            .lock()
+            .get(node_id)
+            .map_or(0.0, |entry| self.decay(entry, Instant::now()))
+    }
+
+    pub fn standing(&self, node_id: &str) -> PeerStanding {
+        self.standing_for(self.score(node_id))
+    }
+
+    /// Whether a message from the peer should be processed: always for
+    /// peers in good standing, within the rate limit for throttled ones
+    pub fn allow(&self, node_id: &str) -> bool {
+        let now = Instant::now();
+        let mut scores = self.scores.lock();
+        let Some(entry) = scores.get_mut(node_id) else {
+            return true;
+        };
+        if self.standing_for(self.decay(entry, now)) == PeerStanding::Good {
+            return true;
+        }
+        if now.duration_since(entry.window_start) >= Duration::from_secs(1) {
+            entry.window_start = now;
+            entry.window_count = 0;
+        }
+        entry.window_count += 1;
+        entry.window_count <= self.config.throttled_rate
+    }
+
+    /// Forget a disconnected peer unless it has something held against it,
+    /// along with grudges that have decayed away
+    pub fn disconnected(&self, node_id: &str) {
+        let now = Instant::now();
+        let mut scores = self.scores.lock();
+        if scores.get(node_id).is_some_and(|entry| self.decay(entry, now) >= 0.0) {
+            scores.remove(node_id);
+        }
+        scores.retain(|_, entry| self.decay(entry, now) <= -FORGOTTEN_SCORE || entry.score >= 0.0);
+    }
+
+    /// Peers with a score kept
+    pub fn len(&self) -> usize {
+        self.scores.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop decayed scores, then the one nearest zero if still full
+    fn make_room(&self, scores: &mut HashMap<String, PeerScore>, now: Instant) {
+        scores.retain(|_, entry| self.decay(entry, now).abs() >= FORGOTTEN_SCORE);
+        if scores.len() < self.config.max_tracked_peers.max(1) {
+            return;
+        }
+        let nearest_zero = scores
+            .iter()
+            .min_by(|a, b| self.decay(a.1, now).abs().total_cmp(&self.decay(b.1, now).abs()))
+            .map(|(node_id, _)| node_id.clone());
+        if let Some(node_id) = nearest_zero {
+            scores.remove(&node_id);
+        }
+    }
+
+    fn decay(&self, entry: &PeerScore, now: Instant) -> f64 {
+        let half_lives = now.duration_since(entry.updated).as_secs_f64()
+            / self.config.half_life.as_secs_f64().max(f64::EPSILON);
+        entry.score * 0.5f64.powf(half_lives)
+    }
+
+    fn standing_for(&self, score: f64) -> PeerStanding {
+        if score <= self.config.ban_threshold {
+            PeerStanding::Banned
+        } else if score <= self.config.throttle_threshold {
+            PeerStanding::Throttled
+        } else {
+            PeerStanding::Good
+        }
+    }
+}
+
+impl Default for PeerReputation {
+    fn default() -> Self {
+        Self::new(ReputationConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scores_throttle_then_ban() {
+        let reputation = PeerReputation::default();
+        for _ in 0..10 {
+            reputation.record("useful", PeerBehavior::UsefulMessage);
+            reputation.record("useful", PeerBehavior::DuplicateMessage);
+        }
+        assert!(reputation.score("useful") > 0.0);
+        assert_eq!(reputation.standing("useful"), PeerStanding::Good);
+
+        assert_eq!(reputation.record("faulty", PeerBehavior::InvalidMessage), PeerStanding::Good);
+        assert_eq!(reputation.record("faulty", PeerBehavior::ProtocolViolation), PeerStanding::Throttled);
+        let allowed = (0..100).filter(|_| reputation.allow("faulty")).count();
+        assert_eq!(allowed, ReputationConfig::default().throttled_rate as usize);
+        assert_eq!(reputation.record("faulty", PeerBehavior::ProtocolViolation), PeerStanding::Banned);
+
+        reputation.disconnected("useful");
+        reputation.disconnected("faulty");
+        assert_eq!(reputation.score("useful"), 0.0);
+        assert_eq!(reputation.standing("faulty"), PeerStanding::Banned);
+    }
+
+    #[test]
+    fn test_scores_decay() {
+        let reputation = PeerReputation::new(ReputationConfig {
+            half_life: Duration::from_millis(10),
+            ..ReputationConfig::default()
+        });
+        reputation.record("peer", PeerBehavior::ProtocolViolation);
+        reputation.record("peer", PeerBehavior::ProtocolViolation);
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(reputation.score("peer") > -1.0);
+        assert!(reputation.allow("peer"));
+        reputation.disconnected("other");
+        assert!(reputation.is_empty(), "decayed grudges are forgotten");
+    }
+
+    #[test]
+    fn test_tracked_peers_are_capped() {
+        let reputation = PeerReputation::new(ReputationConfig {
+            max_tracked_peers: 3,
+            ..ReputationConfig::default()
+        });
+        for _ in 0..3 {
+            reputation.record("worst", PeerBehavior::ProtocolViolation);
+        }
+        for i in 0..100 {
+            reputation.record(&format!("sybil-{}", i), PeerBehavior::InvalidMessage);
+            reputation.disconnected(&format!("sybil-{}", i));
+        }
+        assert_eq!(reputation.len(), 3);
+        assert_eq!(reputation.standing("worst"), PeerStanding::Banned);
+    }
+}
@@ -0,0 +1,188 @@
+//! Doc comments and the `#[serde]` attributes that change a schema
+
+use syn::meta::ParseNestedMeta;
+use syn::{Attribute, Expr, ExprLit, Lit, LitStr, Meta, Token};
+
+/// Text of the `///` comments in `attrs`
+pub fn doc_comment(attrs: &[Attribute]) -> String {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(doc) => match &doc.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(text),
+                    ..
+                }) => Some(text.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .map(|line| {
+            line.strip_prefix(' ')
+                .unwrap_or(&line)
+                .trim_end()
+                .to_string()
+        })
+        .collect();
+    lines.join("\n").trim().to_string()
+}
+
+/// The `#[serde]` options of a type, field or variant that affect its
+/// schema; any other option is accepted and ignored
+#[derive(Default)]
+pub struct SerdeAttrs {
+    pub rename: Option<String>,
+    pub rename_all: Option<RenameRule>,
+    pub skip: bool,
+    pub default: bool,
+    pub flatten: bool,
+}
+
+impl SerdeAttrs {
+    pub fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut serde = Self::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+            attr.parse_nested_meta(|meta| {
+                let path = &meta.path;
+                if path.is_ident("rename") && meta.input.peek(Token![=]) {
+                    serde.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if path.is_ident("rename_all") && meta.input.peek(Token![=]) {
+                    let rule: LitStr = meta.value()?.parse()?;
+                    serde.rename_all = Some(RenameRule::parse(&rule)?);
+                } else if path.is_ident("skip")
+                    || path.is_ident("skip_serializing")
+                    || path.is_ident("skip_deserializing")
+                {
+                    serde.skip = true;
+                } else if path.is_ident("default") {
+                    serde.default = true;
+                    skip_value(meta)?;
+                } else if path.is_ident("flatten") {
+                    serde.flatten = true;
+                } else {
+                    skip_value(meta)?;
+                }
+                Ok(())
+            })?;
+        }
+        Ok(serde)
+    }
+}
+
+/// Consume the value of an option we don't interpret, be it `= value` or
+/// a nested list
+fn skip_value(meta: ParseNestedMeta) -> syn::Result<()> {
+    if meta.input.peek(Token![=]) {
+        meta.value()?.parse::<Expr>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        meta.parse_nested_meta(skip_value)?;
+    }
+    Ok(())
+}
+
+/// A serde `rename_all` case convention
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn parse(rule: &LitStr) -> syn::Result<Self> {
+        Ok(match rule.value().as_str() {
+            "lowercase" => RenameRule::Lower,
+            "UPPERCASE" => RenameRule::Upper,
+            "PascalCase" => RenameRule::Pascal,
+            "camelCase" => RenameRule::Camel,
+            "snake_case" => RenameRule::Snake,
+            "SCREAMING_SNAKE_CASE" => RenameRule::ScreamingSnake,
+            "kebab-case" => RenameRule::Kebab,
+            "SCREAMING-KEBAB-CASE" => RenameRule::ScreamingKebab,
+            _ => return Err(syn::Error::new(rule.span(), "unknown rename_all rule")),
+        })
+    }
+
+    /// Rename a snake case field name, as serde does
+    pub fn apply_to_field(self, field: &str) -> String {
+        match self {
+            RenameRule::Lower | RenameRule::Snake => field.to_string(),
+            RenameRule::Upper | RenameRule::ScreamingSnake => field.to_ascii_uppercase(),
+            RenameRule::Pascal => field.split('_').map(capitalize).collect(),
+            RenameRule::Camel => lower_first(&RenameRule::Pascal.apply_to_field(field)),
+            RenameRule::Kebab => field.replace('_', "-"),
+            RenameRule::ScreamingKebab => field.replace('_', "-").to_ascii_uppercase(),
+        }
+    }
+
+    /// Rename a Pascal case variant name, as serde does
+    pub fn apply_to_variant(self, variant: &str) -> String {
+        match self {
+            RenameRule::Pascal => variant.to_string(),
+            RenameRule::Lower => variant.to_ascii_lowercase(),
+            RenameRule::Upper => variant.to_ascii_uppercase(),
+            RenameRule::Camel => lower_first(variant),
+            RenameRule::Snake => snake_case(variant),
+            RenameRule::ScreamingSnake => snake_case(variant).to_ascii_uppercase(),
+            RenameRule::Kebab => snake_case(variant).replace('_', "-"),
+            RenameRule::ScreamingKebab => {
+                snake_case(variant).replace('_', "-").to_ascii_uppercase()
+            }
+        }
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+        .unwrap_or_default()
+}
+
+fn lower_first(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|first| first.to_ascii_lowercase().to_string() + chars.as_str())
+        .unwrap_or_default()
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (index, c) in name.char_indices() {
+        if c.is_ascii_uppercase() && index > 0 {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+/// `get_block_by_height` as `getBlockByHeight`
+pub fn camel_case(name: &str) -> String {
+    RenameRule::Camel.apply_to_field(name.trim_matches('_'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rename_rules() {
+        assert_eq!(RenameRule::Camel.apply_to_field("block_hash"), "blockHash");
+        assert_eq!(
+            RenameRule::ScreamingKebab.apply_to_field("block_hash"),
+            "BLOCK-HASH"
+        );
+        assert_eq!(RenameRule::Snake.apply_to_variant("NotFound"), "not_found");
+        assert_eq!(RenameRule::Camel.apply_to_variant("NotFound"), "notFound");
+        assert_eq!(camel_case("get_block_by_height"), "getBlockByHeight");
+    }
+}
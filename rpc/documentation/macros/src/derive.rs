@@ -0,0 +1,157 @@
+//! `#[derive(DocSchema)]`: the schema of a type's serde representation
+//!
+//! Structs with named fields are objects whose fields are named, skipped,
+//! optional or flattened as serde would; newtypes take their inner type's
+//! schema, other tuple structs are arrays and unit structs `null`. Enums of
+//! unit variants are string enums; any other enum accepts any value, as
+//! its JSON form depends on the variant.
+
+use crate::attrs::{doc_comment, SerdeAttrs};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::ext::IdentExt;
+use syn::{parse_quote, Data, DeriveInput, Fields};
+
+pub fn expand(mut input: DeriveInput) -> syn::Result<TokenStream> {
+    let serde = SerdeAttrs::parse(&input.attrs)?;
+    let docs = doc_comment(&input.attrs);
+    let description = if docs.is_empty() {
+        quote! { ::std::option::Option::None }
+    } else {
+        quote! { ::std::option::Option::Some(#docs.to_string()) }
+    };
+
+    let (schema, fields) = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(named) => {
+                let mut fields = Vec::new();
+                for field in &named.named {
+                    let attrs = SerdeAttrs::parse(&field.attrs)?;
+                    if attrs.skip {
+                        continue;
+                    }
+                    let ty = &field.ty;
+                    if attrs.flatten {
+                        fields.push(quote! {
+                            fields.extend(<#ty as ::rpc_documentation::DocSchema>::doc_fields());
+                        });
+                        continue;
+                    }
+                    let ident = field.ident.as_ref().map(|ident| ident.unraw().to_string());
+                    let name = match (attrs.rename, serde.rename_all) {
+                        (Some(name), _) => name,
+                        (None, Some(rule)) => rule.apply_to_field(&ident.unwrap_or_default()),
+                        (None, None) => ident.unwrap_or_default(),
+                    };
+                    let docs = doc_comment(&field.attrs);
+                    let default = attrs.default || serde.default;
+                    fields.push(quote! {
+                        fields.push(::rpc_documentation::annotations::field::<#ty>(#name, #docs, #default));
+                    });
+                }
+                let schema = quote! {
+                    ::rpc_documentation::annotations::object(#description, Self::doc_fields())
+                };
+                let fields = quote! {
+                    let mut fields = ::std::vec::Vec::new();
+                    #(#fields)*
+                    fields
+                };
+                (schema, Some(fields))
+            }
+            Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => {
+                let ty = &unnamed.unnamed[0].ty;
+                let schema = quote! {
+                    let mut schema = <#ty as ::rpc_documentation::DocSchema>::doc_schema();
+                    if let ::std::option::Option::Some(description) = #description {
+                        schema.description = ::std::option::Option::Some(description);
+                    }
+                    schema
+                };
+                let fields = quote! { <#ty as ::rpc_documentation::DocSchema>::doc_fields() };
+                (schema, Some(fields))
+            }
+            Fields::Unnamed(_) => (typed("array", &description), None),
+            Fields::Unit => (typed("null", &description), None),
+        },
+        Data::Enum(data) => {
+            let mut variants = Vec::new();
+            let mut unit_only = true;
+            for variant in &data.variants {
+                let attrs = SerdeAttrs::parse(&variant.attrs)?;
+                if attrs.skip {
+                    continue;
+                }
+                unit_only &= matches!(variant.fields, Fields::Unit);
+                let ident = variant.ident.unraw().to_string();
+                variants.push(match (attrs.rename, serde.rename_all) {
+                    (Some(name), _) => name,
+                    (None, Some(rule)) => rule.apply_to_variant(&ident),
+                    (None, None) => ident,
+                });
+            }
+            if unit_only {
+                let schema = quote! {
+                    ::rpc_documentation::SchemaDoc {
+                        schema_type: "string".to_string(),
+                        description: #description,
+                        enum_values: ::std::option::Option::Some(::std::vec![
+                            #(::rpc_documentation::annotations::json_string(#variants)),*
+                        ]),
+                        ..::std::default::Default::default()
+                    }
+                };
+                (schema, None)
+            } else {
+                (typed("any", &description), None)
+            }
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new_spanned(
+                data.union_token,
+                "DocSchema can't be derived for unions",
+            ))
+        }
+    };
+
+    let type_params: Vec<_> = input
+        .generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .collect();
+    let where_clause = input.generics.make_where_clause();
+    for param in type_params {
+        where_clause
+            .predicates
+            .push(parse_quote!(#param: ::rpc_documentation::DocSchema));
+    }
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let name = &input.ident;
+    let fields = fields.map(|fields| {
+        quote! {
+            fn doc_fields() -> ::std::vec::Vec<::rpc_documentation::ParameterDoc> {
+                #fields
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::rpc_documentation::DocSchema for #name #type_generics #where_clause {
+            fn doc_schema() -> ::rpc_documentation::SchemaDoc {
+                #schema
+            }
+
+            #fields
+        }
+    })
+}
+
+fn typed(schema_type: &str, description: &TokenStream) -> TokenStream {
+    quote! {
+        ::rpc_documentation::SchemaDoc {
+            schema_type: #schema_type.to_string(),
+            description: #description,
+            ..::std::default::Default::default()
+        }
+    }
+}
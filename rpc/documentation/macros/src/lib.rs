@@ -0,0 +1,47 @@
+//! CC Chain RPC documentation macros
+//!
+//! `#[rpc_doc]` derives a handler's `MethodDocumentation` from its
+//! signature and doc comments, and `#[derive(DocSchema)]` gives a type the
+//! schema of its serde representation. Both expand to code using
+//! `rpc_documentation`, which re-exports them.
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput, ItemFn};
+
+mod attrs;
+mod derive;
+mod rpc_doc;
+
+/// Document an RPC handler
+///
+/// Adds `<handler>_doc()`, returning the handler's `MethodDocumentation`.
+/// Parameters are the handler's typed arguments, or the fields of the
+/// `params` type, and the result is the `Ok` type of its return value or
+/// the `result` type. The summary and description default to the doc
+/// comments, and the method name to `cc_` and the handler's name in
+/// camel case.
+///
+/// ```ignore
+/// /// Get the balance of an account
+/// #[rpc_doc(tag = "accounts", since = "1.1.0")]
+/// fn get_balance(address: String, block: Option<u64>) -> Result<u64> { .. }
+/// ```
+#[proc_macro_attribute]
+pub fn rpc_doc(args: TokenStream, item: TokenStream) -> TokenStream {
+    let mut parsed = rpc_doc::Args::default();
+    let parser = syn::meta::parser(|meta| parsed.parse(meta));
+    parse_macro_input!(args with parser);
+    let function = parse_macro_input!(item as ItemFn);
+    rpc_doc::expand(parsed, function)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implement `DocSchema` from a type's definition and `#[serde]` attributes
+#[proc_macro_derive(DocSchema, attributes(serde))]
+pub fn derive_doc_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
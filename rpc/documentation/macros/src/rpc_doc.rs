@@ -0,0 +1,196 @@
+//! `#[rpc_doc]`: method documentation from a handler's signature
+
+use crate::attrs::{camel_case, doc_comment};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use syn::meta::ParseNestedMeta;
+use syn::visit_mut::{self, VisitMut};
+use syn::{FnArg, GenericArgument, ItemFn, Lifetime, LitStr, Pat, PathArguments, ReturnType, Type};
+
+/// Options given to `#[rpc_doc(...)]`
+#[derive(Default)]
+pub struct Args {
+    name: Option<LitStr>,
+    summary: Option<LitStr>,
+    tags: Vec<LitStr>,
+    since: Option<LitStr>,
+    deprecated: bool,
+    params: Option<Type>,
+    result: Option<Type>,
+}
+
+impl Args {
+    pub fn parse(&mut self, meta: ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("name") {
+            self.name = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("summary") {
+            self.summary = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("tag") {
+            self.tags.push(meta.value()?.parse()?);
+        } else if meta.path.is_ident("since") {
+            self.since = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("deprecated") {
+            self.deprecated = true;
+        } else if meta.path.is_ident("params") {
+            self.params = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("result") {
+            self.result = Some(meta.value()?.parse()?);
+        } else {
+            return Err(
+                meta.error("expected name, summary, tag, since, deprecated, params or result")
+            );
+        }
+        Ok(())
+    }
+}
+
+pub fn expand(args: Args, function: ItemFn) -> syn::Result<TokenStream> {
+    let signature = &function.sig;
+    let generic = signature.generics.type_params().next().is_some();
+    if generic && (args.params.is_none() || args.result.is_none()) {
+        return Err(syn::Error::new_spanned(
+            &signature.generics,
+            "rpc_doc can't infer schemas from generic handlers; give `params` and `result`",
+        ));
+    }
+
+    let handler = signature.ident.unraw().to_string();
+    let name = match &args.name {
+        Some(name) => name.value(),
+        None => format!("cc_{}", camel_case(&handler)),
+    };
+    let docs = doc_comment(&function.attrs);
+    let summary = match &args.summary {
+        Some(summary) => summary.value(),
+        None => docs.lines().next().unwrap_or_default().to_string(),
+    };
+    let description = if docs.is_empty() {
+        summary.clone()
+    } else {
+        docs
+    };
+    let since = args
+        .since
+        .as_ref()
+        .map_or_else(|| "1.0.0".to_string(), LitStr::value);
+    let deprecated = args.deprecated
+        || function
+            .attrs
+            .iter()
+            .any(|attr| attr.path().is_ident("deprecated"));
+    let tags = &args.tags;
+
+    let parameters = match &args.params {
+        Some(params) => quote! {
+            <#params as ::rpc_documentation::DocSchema>::doc_fields()
+        },
+        None => {
+            let fields = signature
+                .inputs
+                .iter()
+                .filter_map(|input| match input {
+                    FnArg::Typed(arg) => Some(arg),
+                    FnArg::Receiver(_) => None,
+                })
+                .map(|arg| {
+                    let name = match &*arg.pat {
+                        Pat::Ident(pat) => pat.ident.unraw().to_string(),
+                        pat => {
+                            return Err(syn::Error::new_spanned(
+                                pat,
+                                "rpc_doc needs a named argument",
+                            ))
+                        }
+                    };
+                    let ty = owned_type(&arg.ty);
+                    Ok(quote! {
+                        ::rpc_documentation::annotations::field::<#ty>(#name, "", false)
+                    })
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+            quote! { ::std::vec![#(#fields),*] }
+        }
+    };
+
+    let result = match args.result.clone().or_else(|| ok_type(&signature.output)) {
+        Some(ty) => quote! {
+            ::std::option::Option::Some(
+                ::rpc_documentation::annotations::result::<#ty>()
+            )
+        },
+        None => quote! { ::std::option::Option::None },
+    };
+
+    let vis = &function.vis;
+    let doc_fn = format_ident!("{}_doc", handler);
+    let doc = format!("Documentation of the `{name}` RPC method");
+    Ok(quote! {
+        #function
+
+        #[doc = #doc]
+        #vis fn #doc_fn() -> ::rpc_documentation::MethodDocumentation {
+            ::rpc_documentation::MethodDocumentation {
+                name: #name.to_string(),
+                summary: #summary.to_string(),
+                description: #description.to_string(),
+                parameters: #parameters,
+                result: #result,
+                errors: ::std::vec::Vec::new(),
+                examples: ::std::vec::Vec::new(),
+                tags: ::std::vec![#(#tags.to_string()),*],
+                deprecated: #deprecated,
+                since_version: #since.to_string(),
+            }
+        }
+    })
+}
+
+/// The `Ok` type of a `Result` return type, or the return type itself;
+/// `None` when nothing is returned
+fn ok_type(output: &ReturnType) -> Option<Type> {
+    let ReturnType::Type(_, ty) = output else {
+        return None;
+    };
+    let ty = match &**ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .filter(|segment| segment.ident == "Result")
+            .and_then(|segment| match &segment.arguments {
+                PathArguments::AngleBracketed(args) => args.args.first(),
+                _ => None,
+            })
+            .and_then(|arg| match arg {
+                GenericArgument::Type(ok) => Some(ok.clone()),
+                _ => None,
+            })
+            .unwrap_or_else(|| (**ty).clone()),
+        ty => ty.clone(),
+    };
+    match &ty {
+        Type::Tuple(tuple) if tuple.elems.is_empty() => None,
+        _ => Some(owned_type(&ty)),
+    }
+}
+
+/// `ty` as it can be named in the generated function: without a leading
+/// reference and with every lifetime `'static`
+fn owned_type(ty: &Type) -> Type {
+    let mut ty = match ty {
+        Type::Reference(reference) => (*reference.elem).clone(),
+        ty => ty.clone(),
+    };
+    StaticLifetimes.visit_type_mut(&mut ty);
+    ty
+}
+
+struct StaticLifetimes;
+
+impl VisitMut for StaticLifetimes {
+    fn visit_lifetime_mut(&mut self, lifetime: &mut Lifetime) {
+        *lifetime = Lifetime::new("'static", lifetime.span());
+        visit_mut::visit_lifetime_mut(self, lifetime);
+    }
+}
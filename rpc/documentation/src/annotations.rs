@@ -0,0 +1,330 @@
+//! Documentation derived from Rust types
+//!
+//! `#[rpc_doc]` builds a handler's `MethodDocumentation` from its signature
+//! and doc comments, taking parameter and result schemas from `DocSchema`.
+//! `#[derive(DocSchema)]` gives a type the schema of its serde form,
+//! honouring `rename`, `rename_all`, `skip`, `default` and `flatten`.
+
+use crate::{ParameterDoc, ResultDoc, SchemaDoc};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+
+/// A type with a documented JSON schema
+pub trait DocSchema {
+    /// Schema of the type's JSON form
+    fn doc_schema() -> SchemaDoc;
+
+    /// Whether the value may be left out, as with `Option`
+    fn doc_optional() -> bool {
+        false
+    }
+
+    /// Fields of a struct in declaration order, as named parameters
+    fn doc_fields() -> Vec<ParameterDoc> {
+        Vec::new()
+    }
+}
+
+/// A field or parameter named `name` of type `T`
+#[doc(hidden)]
+pub fn field<T: DocSchema + ?Sized>(name: &str, description: &str, default: bool) -> ParameterDoc {
+    let mut schema = T::doc_schema();
+    if !description.is_empty() {
+        schema.description = Some(description.to_string());
+    }
+    ParameterDoc {
+        name: name.to_string(),
+        description: schema.description.clone().unwrap_or_default(),
+        schema,
+        required: !default && !T::doc_optional(),
+        example: None,
+    }
+}
+
+/// The result of a method returning `T`
+#[doc(hidden)]
+pub fn result<T: DocSchema + ?Sized>() -> ResultDoc {
+    let schema = T::doc_schema();
+    ResultDoc {
+        name: "result".to_string(),
+        description: schema.description.clone().unwrap_or_default(),
+        schema,
+        example: None,
+    }
+}
+
+/// Object schema with `fields` as its properties
+#[doc(hidden)]
+pub fn object(description: Option<String>, fields: Vec<ParameterDoc>) -> SchemaDoc {
+    let required: Vec<String> = fields
+        .iter()
+        .filter(|field| field.required)
+        .map(|field| field.name.clone())
+        .collect();
+    SchemaDoc {
+        schema_type: "object".to_string(),
+        description,
+        properties: Some(
+            fields
+                .into_iter()
+                .map(|field| (field.name, field.schema))
+                .collect(),
+        ),
+        required: (!required.is_empty()).then_some(required),
+        ..SchemaDoc::default()
+    }
+}
+
+#[doc(hidden)]
+pub fn json_string(value: &str) -> Value {
+    Value::String(value.to_string())
+}
+
+fn typed(schema_type: &str) -> SchemaDoc {
+    SchemaDoc {
+        schema_type: schema_type.to_string(),
+        ..SchemaDoc::default()
+    }
+}
+
+macro_rules! integer_schema {
+    ($($ty:ty => $format:literal),* $(,)?) => {
+        $(impl DocSchema for $ty {
+            fn doc_schema() -> SchemaDoc {
+                SchemaDoc {
+                    format: Some($format.to_string()),
+                    minimum: (<$ty>::MIN == 0).then_some(0.0),
+                    ..typed("integer")
+                }
+            }
+        })*
+    };
+}
+
+integer_schema! {
+    i8 => "int8", i16 => "int16", i32 => "int32", i64 => "int64", i128 => "int128",
+    isize => "int64", u8 => "uint8", u16 => "uint16", u32 => "uint32", u64 => "uint64",
+    u128 => "uint128", usize => "uint64",
+}
+
+macro_rules! simple_schema {
+    ($($ty:ty => $schema_type:literal),* $(,)?) => {
+        $(impl DocSchema for $ty {
+            fn doc_schema() -> SchemaDoc {
+                typed($schema_type)
+            }
+        })*
+    };
+}
+
+simple_schema! {
+    f32 => "number", f64 => "number", bool => "boolean", String => "string", str => "string",
+    () => "null", Value => "any", serde_json::Map<String, Value> => "object",
+}
+
+impl DocSchema for char {
+    fn doc_schema() -> SchemaDoc {
+        SchemaDoc {
+            min_length: Some(1),
+            max_length: Some(1),
+            ..typed("string")
+        }
+    }
+}
+
+impl<T: DocSchema> DocSchema for Option<T> {
+    fn doc_schema() -> SchemaDoc {
+        T::doc_schema()
+    }
+
+    fn doc_optional() -> bool {
+        true
+    }
+}
+
+macro_rules! wrapper_schema {
+    ($($ty:ty),*) => {
+        $(impl<T: DocSchema + ?Sized> DocSchema for $ty {
+            fn doc_schema() -> SchemaDoc {
+                T::doc_schema()
+            }
+
+            fn doc_optional() -> bool {
+                T::doc_optional()
+            }
+
+            fn doc_fields() -> Vec<ParameterDoc> {
+                T::doc_fields()
+            }
+        })*
+    };
+}
+
+wrapper_schema!(&T, Box<T>, Arc<T>);
+
+macro_rules! array_schema {
+    ($($ty:ty),*) => {
+        $(impl<T: DocSchema> DocSchema for $ty {
+            fn doc_schema() -> SchemaDoc {
+                SchemaDoc {
+                    items: Some(Box::new(T::doc_schema())),
+                    ..typed("array")
+                }
+            }
+        })*
+    };
+}
+
+array_schema!([T], Vec<T>, VecDeque<T>, HashSet<T>, BTreeSet<T>);
+
+impl<T: DocSchema, const N: usize> DocSchema for [T; N] {
+    fn doc_schema() -> SchemaDoc {
+        <[T]>::doc_schema()
+    }
+}
+
+impl<K, V> DocSchema for HashMap<K, V> {
+    fn doc_schema() -> SchemaDoc {
+        typed("object")
+    }
+}
+
+impl<K, V> DocSchema for BTreeMap<K, V> {
+    fn doc_schema() -> SchemaDoc {
+        typed("object")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rpc_doc, MethodDocumentation};
+    use serde::Serialize;
+    use serde_json::json;
+
+    /// Paging of a listing
+    #[derive(Serialize, crate::DocSchema)]
+    #[serde(rename_all = "camelCase")]
+    struct Page {
+        /// Entries to skip
+        #[serde(default)]
+        start_index: u32,
+        limit: Option<u16>,
+    }
+
+    /// Block lookup
+    #[derive(Serialize, crate::DocSchema)]
+    struct BlockQuery {
+        /// Height of the block
+        height: u64,
+        #[serde(rename = "fullTransactions")]
+        full: bool,
+        #[serde(skip)]
+        #[allow(dead_code)]
+        cache_key: String,
+        #[serde(flatten)]
+        page: Page,
+        order: Order,
+    }
+
+    #[derive(Serialize, crate::DocSchema)]
+    #[serde(rename_all = "snake_case")]
+    enum Order {
+        OldestFirst,
+        #[serde(rename = "newest")]
+        NewestFirst,
+    }
+
+    /// Get a block by height
+    ///
+    /// Blocks older than the pruning horizon are not found.
+    #[rpc_doc(tag = "blockchain", tag = "blocks")]
+    #[allow(dead_code)]
+    fn get_block_by_height(height: u64, full: Option<bool>) -> Result<BlockQuery, String> {
+        Err(format!("no block {height} {full:?}"))
+    }
+
+    struct Handlers;
+
+    impl Handlers {
+        #[rpc_doc(name = "cc_listBlocks", summary = "List blocks", since = "1.2.0", params = BlockQuery)]
+        #[deprecated]
+        #[allow(dead_code)]
+        pub fn list(&self, _params: &Value) -> Value {
+            Value::Null
+        }
+    }
+
+    #[test]
+    fn test_derived_schema_follows_serde() {
+        let query = BlockQuery {
+            height: 7,
+            full: false,
+            cache_key: String::new(),
+            page: Page {
+                start_index: 0,
+                limit: None,
+            },
+            order: Order::NewestFirst,
+        };
+        let schema = BlockQuery::doc_schema();
+        let properties = schema.properties.as_ref().unwrap();
+        let serialized = serde_json::to_value(&query).unwrap();
+        let mut names: Vec<_> = properties.keys().collect();
+        names.sort();
+        assert_eq!(
+            names,
+            serialized.as_object().unwrap().keys().collect::<Vec<_>>()
+        );
+
+        assert_eq!(schema.description.as_deref(), Some("Block lookup"));
+        assert_eq!(
+            schema.required.unwrap(),
+            ["height", "fullTransactions", "order"]
+        );
+        assert_eq!(properties["height"].format.as_deref(), Some("uint64"));
+        assert_eq!(
+            properties["startIndex"].description.as_deref(),
+            Some("Entries to skip")
+        );
+        assert_eq!(
+            properties["order"].enum_values,
+            Some(vec![
+                serde_json::to_value(Order::OldestFirst).unwrap(),
+                json!("newest")
+            ])
+        );
+    }
+
+    #[test]
+    fn test_rpc_doc_from_signature() {
+        let method: MethodDocumentation = get_block_by_height_doc();
+        assert_eq!(method.name, "cc_getBlockByHeight");
+        assert_eq!(method.summary, "Get a block by height");
+        assert!(method
+            .description
+            .ends_with("pruning horizon are not found."));
+        assert_eq!(method.tags, ["blockchain", "blocks"]);
+        assert_eq!(method.parameters.len(), 2);
+        assert!(method.parameters[0].required && !method.parameters[1].required);
+        assert_eq!(method.parameters[1].schema.schema_type, "boolean");
+        let result = method.result.unwrap();
+        assert_eq!(result.description, "Block lookup");
+
+        let list = Handlers::list_doc();
+        assert_eq!(list.name, "cc_listBlocks");
+        assert!(list.deprecated);
+        assert_eq!(list.since_version, "1.2.0");
+        let names: Vec<_> = list
+            .parameters
+            .iter()
+            .map(|param| param.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            ["height", "fullTransactions", "startIndex", "limit", "order"]
+        );
+        assert_eq!(list.result.unwrap().schema.schema_type, "any");
+    }
+}
@@ -0,0 +1,545 @@
+//! Client code generation
+//!
+//! Emits a typed TypeScript client (interfaces plus `fetch` and WebSocket
+//! transports) and a Rust client module from the documented methods and
+//! schemas. Named schemas become TypeScript interfaces and Rust structs,
+//! and results whose schema equals a named one are typed with it. With
+//! `generate_types` off, params and results are left untyped.
+
+use crate::{DocumentationGenerator, MethodDocumentation, SchemaDoc};
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+/// Transports and error type shared by every TypeScript client
+const TYPESCRIPT_RUNTIME: &str = include_str!("../templates/client.ts");
+
+/// `Transport` trait, `ClientError` and `Client` shared by every Rust client
+const RUST_RUNTIME: &str = include_str!("../templates/client.rs");
+
+/// Namespace of the chain's own methods, left out of client method names
+const OWN_NAMESPACE: &str = "cc_";
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "static", "struct", "trait", "true", "type", "unsafe", "use",
+    "where", "while", "yield",
+];
+
+/// Language of a generated client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientLanguage {
+    TypeScript,
+    Rust,
+}
+
+impl ClientLanguage {
+    /// File the client is conventionally written to
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            ClientLanguage::TypeScript => "client.ts",
+            ClientLanguage::Rust => "client.rs",
+        }
+    }
+}
+
+impl DocumentationGenerator {
+    /// Generate a client for every documented method
+    pub fn generate_client(&self, language: ClientLanguage) -> String {
+        let codegen = Codegen::new(self);
+        match language {
+            ClientLanguage::TypeScript => codegen.typescript(),
+            ClientLanguage::Rust => codegen.rust(),
+        }
+    }
+}
+
+/// A named schema, with its JSON form to recognise identical inline schemas
+struct NamedType<'a> {
+    name: &'a str,
+    json: Value,
+    schema: &'a SchemaDoc,
+}
+
+struct Codegen<'a> {
+    generator: &'a DocumentationGenerator,
+    named: Vec<NamedType<'a>>,
+    typed: bool,
+}
+
+impl<'a> Codegen<'a> {
+    fn new(generator: &'a DocumentationGenerator) -> Self {
+        let typed = generator.config.generate_types;
+        let mut named: Vec<_> = generator
+            .schemas
+            .iter()
+            .filter(|_| typed)
+            .map(|(name, schema)| NamedType {
+                name: name.as_str(),
+                json: serde_json::to_value(schema).unwrap_or_default(),
+                schema,
+            })
+            .collect();
+        named.sort_by(|a, b| a.name.cmp(b.name));
+        Self {
+            generator,
+            named,
+            typed,
+        }
+    }
+
+    fn header(&self, comment: &str) -> String {
+        let config = &self.generator.config;
+        format!(
+            "{comment} Generated from the {} {} documentation; do not edit.\n\n",
+            config.title, config.version
+        )
+    }
+
+    fn methods(&self) -> Vec<&'a MethodDocumentation> {
+        self.generator.sorted_methods()
+    }
+
+    fn named_type(&self, schema: &SchemaDoc) -> Option<&'a str> {
+        let json = serde_json::to_value(schema).ok()?;
+        self.named
+            .iter()
+            .find(|named| named.json == json)
+            .map(|named| named.name)
+    }
+
+    fn typescript(&self) -> String {
+        let mut ts = self.header("//");
+        ts.push_str(TYPESCRIPT_RUNTIME);
+
+        for named in &self.named {
+            ts.push('\n');
+            ts.push_str(&js_doc(named.schema.description.as_deref(), false, ""));
+            ts.push_str(&format!("export interface {} ", named.name));
+            ts.push_str(&self.ts_object(named.schema, ""));
+            ts.push('\n');
+        }
+
+        ts.push_str("\n/** Typed client over any `Transport` */\n");
+        ts.push_str("export class Client {\n");
+        ts.push_str("  constructor(private readonly transport: Transport) {}\n");
+        for method in self.methods() {
+            ts.push('\n');
+            ts.push_str(&js_doc(Some(&method.summary), method.deprecated, "  "));
+            let returns = match (&method.result, self.typed) {
+                (Some(result), true) => self.ts_type(&result.schema, "  "),
+                _ => "unknown".to_string(),
+            };
+            let name = camel_case(client_name(&method.name));
+            if method.parameters.is_empty() {
+                ts.push_str(&format!("  {name}(): Promise<{returns}> {{\n"));
+                ts.push_str(&format!(
+                    "    return this.transport.call({:?});\n",
+                    method.name
+                ));
+            } else {
+                let params = if self.typed {
+                    let fields = method
+                        .parameters
+                        .iter()
+                        .map(|param| (param.name.as_str(), &param.schema, param.required));
+                    self.ts_fields(fields, "  ")
+                } else {
+                    "Record<string, unknown>".to_string()
+                };
+                let default = if method.parameters.iter().any(|param| param.required) {
+                    ""
+                } else {
+                    " = {}"
+                };
+                ts.push_str(&format!(
+                    "  {name}(params: {params}{default}): Promise<{returns}> {{\n"
+                ));
+                ts.push_str(&format!(
+                    "    return this.transport.call({:?}, params);\n",
+                    method.name
+                ));
+            }
+            ts.push_str("  }\n");
+        }
+        ts.push_str("}\n");
+        ts
+    }
+
+    fn ts_type(&self, schema: &SchemaDoc, indent: &str) -> String {
+        if let Some(name) = self.named_type(schema) {
+            return name.to_string();
+        }
+        if let Some(values) = &schema.enum_values {
+            let literals: Vec<_> = values.iter().map(Value::to_string).collect();
+            return literals.join(" | ");
+        }
+        match schema.schema_type.as_str() {
+            "integer" | "number" => "number".to_string(),
+            "string" | "boolean" | "null" => schema.schema_type.clone(),
+            "array" => match &schema.items {
+                Some(items) => format!("Array<{}>", self.ts_type(items, indent)),
+                None => "unknown[]".to_string(),
+            },
+            "object" if schema.properties.is_some() => self.ts_object(schema, indent),
+            "object" => "Record<string, unknown>".to_string(),
+            _ => "unknown".to_string(),
+        }
+    }
+
+    fn ts_object(&self, schema: &SchemaDoc, indent: &str) -> String {
+        let required = required_fields(schema);
+        let mut properties: Vec<_> = schema.properties.iter().flatten().collect();
+        properties.sort_by(|a, b| a.0.cmp(b.0));
+        let fields = properties
+            .into_iter()
+            .map(|(name, property)| (name.as_str(), property, required.contains(name.as_str())));
+        self.ts_fields(fields, indent)
+    }
+
+    fn ts_fields<'s>(
+        &self,
+        fields: impl Iterator<Item = (&'s str, &'s SchemaDoc, bool)>,
+        indent: &str,
+    ) -> String {
+        let inner = format!("{indent}  ");
+        let mut ts = "{\n".to_string();
+        for (name, schema, required) in fields {
+            ts.push_str(&js_doc(schema.description.as_deref(), false, &inner));
+            let key = if is_identifier(name) {
+                name.to_string()
+            } else {
+                format!("{name:?}")
+            };
+            let optional = if required { "" } else { "?" };
+            let field_type = self.ts_type(schema, &inner);
+            ts.push_str(&format!("{inner}{key}{optional}: {field_type};\n"));
+        }
+        ts.push_str(indent);
+        ts.push('}');
+        ts
+    }
+
+    fn rust(&self) -> String {
+        let mut structs = Vec::new();
+        let mut defined = BTreeSet::new();
+        for named in &self.named {
+            self.rust_struct(named.name, named.schema, &mut structs, &mut defined);
+        }
+
+        let mut methods = String::new();
+        for method in self.methods() {
+            let name = snake_case(client_name(&method.name));
+            let pascal = pascal_case(&method.name);
+            let mut args = String::new();
+            let mut params = Vec::new();
+            if self.typed {
+                for param in &method.parameters {
+                    let hint = format!("{pascal}{}", pascal_case(&param.name));
+                    let mut arg_type =
+                        self.rust_type(&param.schema, &hint, &mut structs, &mut defined);
+                    if !param.required {
+                        arg_type = format!("Option<{arg_type}>");
+                    }
+                    let arg = rust_ident(&snake_case(&param.name));
+                    args.push_str(&format!(", {arg}: {arg_type}"));
+                    params.push(format!("{:?}: {arg}", param.name));
+                }
+            } else if !method.parameters.is_empty() {
+                args.push_str(", params: serde_json::Value");
+            }
+            let params = if !self.typed && !method.parameters.is_empty() {
+                "params".to_string()
+            } else {
+                if params.is_empty() {
+                    "serde_json::json!({})".to_string()
+                } else {
+                    format!("serde_json::json!({{ {} }})", params.join(", "))
+                }
+            };
+            let returns = match (&method.result, self.typed) {
+                (Some(result), true) => self.rust_type(
+                    &result.schema,
+                    &format!("{pascal}Result"),
+                    &mut structs,
+                    &mut defined,
+                ),
+                _ => "serde_json::Value".to_string(),
+            };
+
+            methods.push('\n');
+            methods.push_str(&rust_doc(Some(&method.summary), "    "));
+            if method.deprecated {
+                methods.push_str("    #[deprecated]\n");
+            }
+            methods.push_str(&format!(
+                "    pub async fn {}(&self{args}) -> Result<{returns}, ClientError> {{\n",
+                rust_ident(&name)
+            ));
+            methods.push_str(&format!(
+                "        self.request({:?}, {params}).await\n",
+                method.name
+            ));
+            methods.push_str("    }\n");
+        }
+
+        let mut rs = self.header("//!");
+        if !structs.is_empty() {
+            rs.push_str("use serde::{Deserialize, Serialize};\n");
+        }
+        rs.push_str(RUST_RUNTIME);
+        for definition in structs {
+            rs.push('\n');
+            rs.push_str(&definition);
+        }
+        rs.push_str("\nimpl<T: Transport> Client<T> {");
+        rs.push_str(&methods);
+        rs.push_str("}\n");
+        rs
+    }
+
+    /// Rust type for `schema`, defining a struct named `hint` for inline
+    /// objects with properties
+    fn rust_type(
+        &self,
+        schema: &SchemaDoc,
+        hint: &str,
+        structs: &mut Vec<String>,
+        defined: &mut BTreeSet<String>,
+    ) -> String {
+        if let Some(name) = self.named_type(schema) {
+            return name.to_string();
+        }
+        match schema.schema_type.as_str() {
+            "integer" => {
+                let unsigned = schema.minimum.is_some_and(|minimum| minimum >= 0.0)
+                    || schema
+                        .format
+                        .as_deref()
+                        .is_some_and(|format| format.starts_with("uint"));
+                if unsigned { "u64" } else { "i64" }.to_string()
+            }
+            "number" => "f64".to_string(),
+            "string" => "String".to_string(),
+            "boolean" => "bool".to_string(),
+            "null" => "()".to_string(),
+            "array" => match &schema.items {
+                Some(items) => format!(
+                    "Vec<{}>",
+                    self.rust_type(items, &format!("{hint}Item"), structs, defined)
+                ),
+                None => "Vec<serde_json::Value>".to_string(),
+            },
+            "object" if schema.properties.is_some() => {
+                self.rust_struct(hint, schema, structs, defined);
+                hint.to_string()
+            }
+            "object" => "serde_json::Map<String, serde_json::Value>".to_string(),
+            _ => "serde_json::Value".to_string(),
+        }
+    }
+
+    fn rust_struct(
+        &self,
+        name: &str,
+        schema: &SchemaDoc,
+        structs: &mut Vec<String>,
+        defined: &mut BTreeSet<String>,
+    ) {
+        if !defined.insert(name.to_string()) {
+            return;
+        }
+        let required = required_fields(schema);
+        let mut properties: Vec<_> = schema.properties.iter().flatten().collect();
+        properties.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut fields = String::new();
+        for (field, property) in properties {
+            let hint = format!("{name}{}", pascal_case(field));
+            let mut field_type = self.rust_type(property, &hint, structs, defined);
+            let ident = snake_case(field);
+            fields.push_str(&rust_doc(property.description.as_deref(), "    "));
+            if ident != *field {
+                fields.push_str(&format!("    #[serde(rename = {field:?})]\n"));
+            }
+            if !required.contains(field.as_str()) {
+                fields
+                    .push_str("    #[serde(default, skip_serializing_if = \"Option::is_none\")]\n");
+                field_type = format!("Option<{field_type}>");
+            }
+            fields.push_str(&format!("    pub {}: {field_type},\n", rust_ident(&ident)));
+        }
+
+        let mut definition = rust_doc(schema.description.as_deref(), "");
+        definition.push_str("#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n");
+        definition.push_str(&format!("pub struct {name} {{\n{fields}}}\n"));
+        structs.push(definition);
+    }
+}
+
+fn required_fields(schema: &SchemaDoc) -> BTreeSet<&str> {
+    schema
+        .required
+        .iter()
+        .flatten()
+        .map(String::as_str)
+        .collect()
+}
+
+/// Method name without the chain's own namespace
+fn client_name(method: &str) -> &str {
+    method.strip_prefix(OWN_NAMESPACE).unwrap_or(method)
+}
+
+/// Lowercase words of an identifier in snake, kebab or camel case
+fn words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if !c.is_ascii_alphanumeric() {
+            words.push(std::mem::take(&mut word));
+            previous_lower = false;
+            continue;
+        }
+        if c.is_ascii_uppercase() && previous_lower {
+            words.push(std::mem::take(&mut word));
+        }
+        previous_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        word.push(c.to_ascii_lowercase());
+    }
+    words.push(word);
+    words.retain(|word| !word.is_empty());
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+        .unwrap_or_default()
+}
+
+fn snake_case(name: &str) -> String {
+    words(name).join("_")
+}
+
+fn camel_case(name: &str) -> String {
+    let words = words(name);
+    let mut camel = words.first().cloned().unwrap_or_default();
+    for word in &words[1.min(words.len())..] {
+        camel.push_str(&capitalize(word));
+    }
+    camel
+}
+
+pub(crate) fn pascal_case(name: &str) -> String {
+    words(name).iter().map(|word| capitalize(word)).collect()
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_' || first == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+fn rust_ident(name: &str) -> String {
+    if RUST_KEYWORDS.contains(&name) {
+        format!("r#{name}")
+    } else {
+        name.to_string()
+    }
+}
+
+fn js_doc(text: Option<&str>, deprecated: bool, indent: &str) -> String {
+    match (text.filter(|text| !text.is_empty()), deprecated) {
+        (None, false) => String::new(),
+        (Some(text), false) => format!("{indent}/** {} */\n", text.replace("*/", "*\\/")),
+        (text, true) => format!(
+            "{indent}/**\n{indent} * {}\n{indent} * @deprecated\n{indent} */\n",
+            text.unwrap_or_default().replace("*/", "*\\/")
+        ),
+    }
+}
+
+fn rust_doc(text: Option<&str>, indent: &str) -> String {
+    text.filter(|text| !text.is_empty())
+        .map(|text| {
+            text.lines()
+                .map(|line| format!("{indent}/// {line}\n").replace("/// \n", "///\n"))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DocumentationConfig, ParameterDoc};
+
+    #[test]
+    fn test_case_conversion() {
+        assert_eq!(snake_case("getBlockByHeight"), "get_block_by_height");
+        assert_eq!(camel_case("admin_add_peer"), "adminAddPeer");
+        assert_eq!(pascal_case("cc_getTransaction"), "CcGetTransaction");
+        assert_eq!(rust_ident(&snake_case("type")), "r#type");
+        assert!(!is_identifier("content-type"));
+    }
+
+    #[test]
+    fn test_typescript_client() {
+        let ts = DocumentationGenerator::new().generate_client(ClientLanguage::TypeScript);
+        assert!(ts.contains("export interface Block {"));
+        assert!(ts.contains("export class WebSocketTransport implements Transport"));
+        assert!(ts.contains("  getBlockByHeight(params: {\n"));
+        assert!(ts.contains("    height: number;\n  }): Promise<Block> {"));
+        assert!(ts
+            .contains("  ping(): Promise<string> {\n    return this.transport.call(\"cc_ping\");"));
+    }
+
+    #[test]
+    fn test_rust_client() {
+        let mut generator = DocumentationGenerator::new();
+        generator.add_method(MethodDocumentation {
+            name: "admin_setLogLevel".to_string(),
+            summary: "Change the log level".to_string(),
+            description: String::new(),
+            parameters: vec![ParameterDoc {
+                name: "type".to_string(),
+                description: String::new(),
+                schema: SchemaDoc {
+                    schema_type: "string".to_string(),
+                    ..SchemaDoc::default()
+                },
+                required: false,
+                example: None,
+            }],
+            result: None,
+            errors: vec![],
+            examples: vec![],
+            tags: vec![],
+            deprecated: true,
+            since_version: "1.0.0".to_string(),
+        });
+        let rs = generator.generate_client(ClientLanguage::Rust);
+        assert!(rs.contains("pub struct Block {"));
+        assert!(rs.contains(
+            "pub async fn get_block_by_height(&self, height: u64) -> Result<Block, ClientError>"
+        ));
+        assert!(rs.contains("    #[deprecated]\n    pub async fn admin_set_log_level(&self, r#type: Option<String>)"));
+        assert!(rs.contains("serde_json::json!({ \"type\": r#type })"));
+
+        let untyped = DocumentationGenerator::with_config(DocumentationConfig {
+            generate_types: false,
+            ..DocumentationConfig::default()
+        })
+        .generate_client(ClientLanguage::Rust);
+        assert!(!untyped.contains("pub struct Block"));
+        assert!(untyped.contains(
+            "get_block_by_height(&self, params: serde_json::Value) -> Result<serde_json::Value, ClientError>"
+        ));
+    }
+}
@@ -0,0 +1,231 @@
+//! Postman and Insomnia collections
+//!
+//! Both contain one ready-to-send JSON-RPC request per method, with params
+//! taken from the method's first example or, failing that, from the
+//! examples of its parameters. The endpoint is a collection variable set to
+//! the first configured server, so integrators only change it in one place.
+
+use crate::{file_stem, DocumentationGenerator, MethodDocumentation, Result};
+use serde_json::{json, Map, Value};
+
+/// Schema URL identifying Postman collection format v2.1
+const POSTMAN_SCHEMA: &str = "https://schema.getpostman.com/json/collection/v2.1.0/collection.json";
+
+/// Id of the Insomnia workspace every other resource belongs to
+const INSOMNIA_WORKSPACE: &str = "wrk_cc_chain";
+
+impl DocumentationGenerator {
+    /// Generate a Postman collection, with one folder per method's first tag
+    pub(crate) fn generate_postman(&self) -> Result<String> {
+        let mut folders: Vec<(String, Vec<Value>)> = Vec::new();
+        let mut top_level = Vec::new();
+        for method in self.sorted_methods() {
+            let item = self.postman_item(method)?;
+            match method.tags.first() {
+                Some(tag) => match folders.iter_mut().find(|(name, _)| name == tag) {
+                    Some((_, items)) => items.push(item),
+                    None => folders.push((tag.clone(), vec![item])),
+                },
+                None => top_level.push(item),
+            }
+        }
+        folders.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut items: Vec<Value> = folders
+            .into_iter()
+            .map(|(name, item)| json!({"name": name, "item": item}))
+            .collect();
+        items.extend(top_level);
+
+        let collection = json!({
+            "info": {
+                "name": self.config.title,
+                "description": self.config.description,
+                "version": self.config.version,
+                "schema": POSTMAN_SCHEMA
+            },
+            "variable": [{"key": "baseUrl", "value": self.base_url()}],
+            "item": items
+        });
+        Ok(serde_json::to_string_pretty(&collection)?)
+    }
+
+    fn postman_item(&self, method: &MethodDocumentation) -> Result<Value> {
+        let request = json!({
+            "method": "POST",
+            "header": [{"key": "Content-Type", "value": "application/json"}],
+            "body": {
+                "mode": "raw",
+                "raw": serde_json::to_string_pretty(&example_request(method))?,
+                "options": {"raw": {"language": "json"}}
+            },
+            "url": {"raw": "{{baseUrl}}", "host": ["{{baseUrl}}"]},
+            "description": method.description
+        });
+
+        // Documented results become saved example responses
+        let mut responses = Vec::new();
+        if self.config.include_examples {
+            for example in &method.examples {
+                if let Some(result) = &example.result {
+                    let body = json!({"jsonrpc": "2.0", "result": result, "id": 1});
+                    responses.push(json!({
+                        "name": example.name,
+                        "originalRequest": request,
+                        "status": "OK",
+                        "code": 200,
+                        "header": [{"key": "Content-Type", "value": "application/json"}],
+                        "body": serde_json::to_string_pretty(&body)?
+                    }));
+                }
+            }
+        }
+
+        Ok(json!({
+            "name": method.name,
+            "request": request,
+            "response": responses
+        }))
+    }
+
+    /// Generate an Insomnia v4 export with a workspace, a base environment
+    /// and one request per method
+    pub(crate) fn generate_insomnia(&self) -> Result<String> {
+        let mut resources = vec![
+            json!({
+                "_id": INSOMNIA_WORKSPACE,
+                "_type": "workspace",
+                "name": self.config.title,
+                "description": self.config.description
+            }),
+            json!({
+                "_id": "env_cc_chain",
+                "_type": "environment",
+                "parentId": INSOMNIA_WORKSPACE,
+                "name": "Base Environment",
+                "data": {"base_url": self.base_url()}
+            }),
+        ];
+        for method in self.sorted_methods() {
+            resources.push(json!({
+                "_id": format!("req_{}", file_stem(&method.name)),
+                "_type": "request",
+                "parentId": INSOMNIA_WORKSPACE,
+                "name": method.name,
+                "description": method.description,
+                "method": "POST",
+                "url": "{{ _.base_url }}",
+                "headers": [{"name": "Content-Type", "value": "application/json"}],
+                "body": {
+                    "mimeType": "application/json",
+                    "text": serde_json::to_string_pretty(&example_request(method))?
+                }
+            }));
+        }
+
+        let export = json!({
+            "_type": "export",
+            "__export_format": 4,
+            "__export_source": "cc-chain.rpc-documentation",
+            "resources": resources
+        });
+        Ok(serde_json::to_string_pretty(&export)?)
+    }
+
+    fn base_url(&self) -> &str {
+        self.config
+            .servers
+            .first()
+            .map_or("http://localhost:8545", |server| server.url.as_str())
+    }
+}
+
+/// JSON-RPC request for `method` with example params, if it takes any
+pub(crate) fn example_request(method: &MethodDocumentation) -> Value {
+    let mut request = json!({"jsonrpc": "2.0", "method": method.name, "id": 1});
+    let documented = method
+        .examples
+        .iter()
+        .find_map(|example| example.params.clone());
+    let params = documented.or_else(|| {
+        let params: Map<String, Value> = method
+            .parameters
+            .iter()
+            .filter_map(|param| {
+                let example = param
+                    .example
+                    .clone()
+                    .or_else(|| param.schema.example.clone())?;
+                Some((param.name.clone(), example))
+            })
+            .collect();
+        (!method.parameters.is_empty()).then_some(Value::Object(params))
+    });
+    if let Some(params) = params {
+        request["params"] = params;
+    }
+    request
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParameterDoc;
+
+    fn body(raw: &Value) -> Value {
+        serde_json::from_str(raw.as_str().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_postman_collection() {
+        let generator = DocumentationGenerator::new();
+        let collection: Value =
+            serde_json::from_str(&generator.generate_postman().unwrap()).unwrap();
+
+        assert_eq!(collection["info"]["schema"], POSTMAN_SCHEMA);
+        assert_eq!(collection["variable"][0]["value"], "http://localhost:8545");
+        let folder = &collection["item"][0];
+        assert_eq!(folder["name"], "blockchain");
+        let item = &folder["item"][0];
+        assert_eq!(item["name"], "cc_getBlockByHeight");
+        let request = body(&item["request"]["body"]["raw"]);
+        assert_eq!(request["params"], json!({"height": 12345}));
+        let response = body(&item["response"][0]["body"]);
+        assert_eq!(response["result"]["height"], 12345);
+    }
+
+    #[test]
+    fn test_insomnia_export() {
+        let mut generator = DocumentationGenerator::new();
+        let mut method = generator.get_method("cc_ping").unwrap().clone();
+        method.name = "cc_getBalance".to_string();
+        method.examples.clear();
+        method.parameters.push(ParameterDoc {
+            name: "address".to_string(),
+            description: String::new(),
+            schema: Default::default(),
+            required: true,
+            example: Some(json!("0x1234")),
+        });
+        generator.add_method(method);
+
+        let export: Value = serde_json::from_str(&generator.generate_insomnia().unwrap()).unwrap();
+        assert_eq!(export["__export_format"], 4);
+        let resources = export["resources"].as_array().unwrap();
+        assert_eq!(resources[1]["data"]["base_url"], "http://localhost:8545");
+        let balance = resources
+            .iter()
+            .find(|resource| resource["name"] == "cc_getBalance")
+            .unwrap();
+        assert_eq!(balance["_id"], "req_cc_getBalance");
+        assert_eq!(balance["parentId"], INSOMNIA_WORKSPACE);
+        let request = body(&balance["body"]["text"]);
+        assert_eq!(request["params"], json!({"address": "0x1234"}));
+
+        let ping = resources
+            .iter()
+            .find(|resource| resource["name"] == "cc_ping")
+            .unwrap();
+        assert!(body(&ping["body"]["text"]).get("params").is_none());
+    }
+}
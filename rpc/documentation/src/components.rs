@@ -0,0 +1,240 @@
+//! Shared schema components of the OpenRPC output
+//!
+//! Registered schemas, and any object schema repeated across methods, are
+//! emitted once under `components.schemas` and referenced with `$ref`
+//! wherever they appear, so a change to a shared structure shows up in one
+//! place of a spec diff. Repeated schemas are named after their first use in
+//! method order, e.g. `CcGetBlockByHeightError32007` for an error's data.
+
+use crate::codegen::pascal_case;
+use crate::{DocumentationGenerator, SchemaDoc};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Prefix of every component reference
+const SCHEMA_REF: &str = "#/components/schemas/";
+
+/// Schemas referenced by name, in the form they are serialized in
+pub(crate) struct Components {
+    schemas: Vec<(String, Value)>,
+}
+
+impl Components {
+    /// The generator's schemas plus every unnamed object schema used more
+    /// than once
+    pub(crate) fn new(generator: &DocumentationGenerator) -> Self {
+        let mut schemas: Vec<(String, Value)> = generator
+            .schemas
+            .iter()
+            .map(|(name, schema)| (name.clone(), to_value(schema)))
+            .collect();
+        schemas.sort_by(|a, b| a.0.cmp(&b.0));
+
+        // Count object schemas at every position, remembering where each
+        // was first seen
+        let mut uses: HashMap<String, (usize, usize, String)> = HashMap::new();
+        let mut order = 0;
+        let mut visit = |schema: &SchemaDoc, hint: String| {
+            walk(schema, hint, &mut |value: &Value, hint: &str| {
+                let entry = uses
+                    .entry(value.to_string())
+                    .or_insert_with(|| (0, order, hint.to_string()));
+                entry.0 += 1;
+                order += 1;
+            });
+        };
+        for method in generator.sorted_methods() {
+            let prefix = pascal_case(&method.name);
+            for param in &method.parameters {
+                visit(
+                    &param.schema,
+                    format!("{}{}", prefix, pascal_case(&param.name)),
+                );
+            }
+            if let Some(result) = &method.result {
+                visit(&result.schema, format!("{prefix}Result"));
+            }
+            for error in &method.errors {
+                if let Some(data) = &error.data_schema {
+                    visit(data, format!("{prefix}Error{}", error.code.unsigned_abs()));
+                }
+            }
+        }
+
+        let named: HashSet<String> = schemas
+            .iter()
+            .map(|(_, schema)| schema.to_string())
+            .collect();
+        let mut repeated: Vec<_> = uses
+            .into_iter()
+            .filter(|(json, (count, _, _))| *count > 1 && !named.contains(json))
+            .map(|(json, (_, first, hint))| (first, hint, json))
+            .collect();
+        repeated.sort();
+        for (_, hint, json) in repeated {
+            let mut name = hint.clone();
+            let mut suffix = 2;
+            while schemas.iter().any(|(taken, _)| *taken == name) {
+                name = format!("{hint}{suffix}");
+                suffix += 1;
+            }
+            schemas.push((name, serde_json::from_str(&json).unwrap_or_default()));
+        }
+        Self { schemas }
+    }
+
+    /// No components: every schema is written out in place
+    pub(crate) fn none() -> Self {
+        Self {
+            schemas: Vec::new(),
+        }
+    }
+
+    /// `schema` with every component in it replaced by a `$ref`
+    pub(crate) fn reference(&self, schema: &SchemaDoc) -> Value {
+        self.replace(to_value(schema))
+    }
+
+    /// The `components.schemas` object, components referencing each other
+    pub(crate) fn schemas(&self) -> Value {
+        let schemas: BTreeMap<_, _> = self
+            .schemas
+            .iter()
+            .map(|(name, schema)| (name.clone(), self.replace_children(schema.clone())))
+            .collect();
+        json!(schemas)
+    }
+
+    fn replace(&self, value: Value) -> Value {
+        match self.schemas.iter().find(|(_, schema)| *schema == value) {
+            Some((name, _)) => json!({ "$ref": format!("{SCHEMA_REF}{name}") }),
+            None => self.replace_children(value),
+        }
+    }
+
+    fn replace_children(&self, mut value: Value) -> Value {
+        if let Some(Value::Object(properties)) = value.get_mut("properties") {
+            for property in properties.values_mut() {
+                *property = self.replace(property.take());
+            }
+        }
+        if let Some(items) = value.get_mut("items").filter(|items| !items.is_null()) {
+            *items = self.replace(items.take());
+        }
+        value
+    }
+}
+
+/// Replace `$ref`s to `components` by the schemas they name, so specs can
+/// be read back into `SchemaDoc`s
+pub(crate) fn resolve(value: &Value, components: &Map<String, Value>) -> Value {
+    resolve_within(value, components, 0)
+}
+
+fn resolve_within(value: &Value, components: &Map<String, Value>, depth: usize) -> Value {
+    // Self-referencing schemas can't be expanded, so leave them as they are
+    if depth > 32 {
+        return value.clone();
+    }
+    let target = value
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|reference| reference.strip_prefix(SCHEMA_REF))
+        .and_then(|name| components.get(name));
+    match (target, value) {
+        (Some(target), _) => resolve_within(target, components, depth + 1),
+        (None, Value::Object(object)) => Value::Object(
+            object
+                .iter()
+                .map(|(key, value)| (key.clone(), resolve_within(value, components, depth + 1)))
+                .collect(),
+        ),
+        (None, value) => value.clone(),
+    }
+}
+
+fn to_value(schema: &SchemaDoc) -> Value {
+    serde_json::to_value(schema).unwrap_or_default()
+}
+
+/// Call `found` with every object schema with properties in `schema`,
+/// itself included, and a name hint for it
+fn walk(schema: &SchemaDoc, hint: String, found: &mut impl FnMut(&Value, &str)) {
+    if schema.properties.is_some() {
+        found(&to_value(schema), &hint);
+    }
+    let mut properties: Vec<_> = schema.properties.iter().flatten().collect();
+    properties.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, property) in properties {
+        walk(property, format!("{hint}{}", pascal_case(name)), found);
+    }
+    if let Some(items) = &schema.items {
+        walk(items, format!("{hint}Item"), found);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MethodDocumentation, ParameterDoc};
+
+    fn point() -> SchemaDoc {
+        SchemaDoc {
+            schema_type: "object".to_string(),
+            properties: Some(HashMap::from([(
+                "x".to_string(),
+                SchemaDoc {
+                    schema_type: "integer".to_string(),
+                    ..SchemaDoc::default()
+                },
+            )])),
+            ..SchemaDoc::default()
+        }
+    }
+
+    fn method(name: &str, param: SchemaDoc) -> MethodDocumentation {
+        let mut method = DocumentationGenerator::new()
+            .get_method("cc_ping")
+            .unwrap()
+            .clone();
+        method.name = name.to_string();
+        method.parameters = vec![ParameterDoc {
+            name: "point".to_string(),
+            description: String::new(),
+            schema: param,
+            required: true,
+            example: None,
+        }];
+        method
+    }
+
+    #[test]
+    fn test_repeated_schemas_become_components() {
+        let mut generator = DocumentationGenerator::new();
+        generator.add_method(method("cc_move", point()));
+        let path = SchemaDoc {
+            schema_type: "array".to_string(),
+            items: Some(Box::new(point())),
+            ..SchemaDoc::default()
+        };
+        generator.add_method(method("cc_draw", path.clone()));
+
+        let components = Components::new(&generator);
+        let schemas = components.schemas();
+        assert!(schemas.get("Block").is_some());
+        // First used by cc_draw, which sorts before cc_move
+        assert_eq!(schemas["CcDrawPointItem"], to_value(&point()));
+        assert_eq!(
+            components.reference(&point()),
+            json!({"$ref": "#/components/schemas/CcDrawPointItem"})
+        );
+        assert_eq!(
+            components.reference(&path)["items"],
+            json!({"$ref": "#/components/schemas/CcDrawPointItem"})
+        );
+
+        let resolved = resolve(&components.reference(&path), schemas.as_object().unwrap());
+        assert_eq!(resolved, to_value(&path));
+        assert_eq!(Components::none().reference(&point()), to_value(&point()));
+    }
+}
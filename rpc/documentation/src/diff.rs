@@ -0,0 +1,531 @@
+//! Spec diffing and breaking-change detection
+//!
+//! Compares two OpenRPC specs as written by `DocumentationGenerator` and
+//! lists every change to methods, params and schemas, following `$ref`s
+//! into the shared components. Whether a schema change breaks clients
+//! depends on which side of the call it is on: tightening a param rejects
+//! calls that used to succeed, while loosening a result stops guaranteeing
+//! what clients read from it.
+
+use crate::components::resolve;
+use crate::{DocumentationError, DocumentationGenerator, Result, SchemaDoc};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// What changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    MethodAdded,
+    MethodRemoved,
+    MethodDeprecated,
+    ParamAdded,
+    ParamRemoved,
+    /// An optional param became required
+    ParamRequired,
+    /// A required param became optional
+    ParamOptional,
+    ResultAdded,
+    ResultRemoved,
+    PropertyAdded,
+    PropertyRemoved,
+    TypeChanged,
+    /// Fewer values are accepted than before
+    Tightened,
+    /// More values are accepted than before
+    Loosened,
+}
+
+/// One entry of the changelog
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecChange {
+    pub method: String,
+    /// Where in the method the change is, e.g. `params.height` or
+    /// `result.transactions[]`; empty for the method itself
+    pub path: String,
+    pub kind: ChangeKind,
+    pub detail: String,
+    /// Whether clients written against the old spec may stop working
+    pub breaking: bool,
+}
+
+impl fmt::Display for SpecChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}: {}", self.method, self.detail)
+        } else {
+            write!(f, "{} {}: {}", self.method, self.path, self.detail)
+        }
+    }
+}
+
+/// Changelog between two specs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpecDiff {
+    pub changes: Vec<SpecChange>,
+}
+
+impl SpecDiff {
+    /// Whether any change breaks existing clients
+    pub fn is_breaking(&self) -> bool {
+        self.changes.iter().any(|change| change.breaking)
+    }
+
+    /// Changes that break existing clients
+    pub fn breaking_changes(&self) -> impl Iterator<Item = &SpecChange> {
+        self.changes.iter().filter(|change| change.breaking)
+    }
+
+    /// Whether the specs describe the same API
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl fmt::Display for SpecDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (breaking, other): (Vec<_>, Vec<_>) =
+            self.changes.iter().partition(|change| change.breaking);
+        for (title, changes) in [("Breaking changes", breaking), ("Other changes", other)] {
+            if changes.is_empty() {
+                continue;
+            }
+            writeln!(f, "{title}:")?;
+            for change in changes {
+                writeln!(f, "- {change}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DocumentationGenerator {
+    /// Compare two OpenRPC specs and list what changed from `old_spec`
+    /// to `new_spec`
+    pub fn diff(old_spec: &str, new_spec: &str) -> Result<SpecDiff> {
+        let old = parse_methods(old_spec)?;
+        let new = parse_methods(new_spec)?;
+        let mut diff = Differ::default();
+
+        for (name, method) in &old {
+            match new.get(name) {
+                Some(updated) => diff.method(name, method, updated),
+                None => diff.push(name, "", ChangeKind::MethodRemoved, "removed".to_string()),
+            }
+        }
+        for name in new.keys().filter(|name| !old.contains_key(*name)) {
+            diff.push(name, "", ChangeKind::MethodAdded, "added".to_string());
+        }
+        Ok(SpecDiff {
+            changes: diff.changes,
+        })
+    }
+}
+
+/// The parts of a method that make up its contract
+struct MethodSpec {
+    params: BTreeMap<String, (bool, SchemaDoc)>,
+    result: Option<SchemaDoc>,
+    deprecated: bool,
+}
+
+fn parse_methods(spec: &str) -> Result<BTreeMap<String, MethodSpec>> {
+    let invalid =
+        |reason: String| DocumentationError::ValidationError(format!("Invalid spec: {reason}"));
+    let spec: Value = serde_json::from_str(spec)?;
+    let methods = spec["methods"]
+        .as_array()
+        .ok_or_else(|| invalid("missing methods".to_string()))?;
+    let empty = Map::new();
+    let components = spec["components"]["schemas"].as_object().unwrap_or(&empty);
+    let schema = |value: &Value, what: &str| {
+        serde_json::from_value::<SchemaDoc>(resolve(value, components))
+            .map_err(|error| invalid(format!("{what}: {error}")))
+    };
+
+    let mut parsed = BTreeMap::new();
+    for method in methods {
+        let name = method["name"]
+            .as_str()
+            .ok_or_else(|| invalid("method without a name".to_string()))?;
+        let mut params = BTreeMap::new();
+        for param in method["params"].as_array().into_iter().flatten() {
+            let param_name = param["name"].as_str().unwrap_or_default();
+            let required = param["required"].as_bool().unwrap_or(false);
+            let param_schema = schema(&param["schema"], &format!("{name} param {param_name}"))?;
+            params.insert(param_name.to_string(), (required, param_schema));
+        }
+        let result = match &method["result"] {
+            Value::Null => None,
+            result => Some(schema(&result["schema"], &format!("{name} result"))?),
+        };
+        parsed.insert(
+            name.to_string(),
+            MethodSpec {
+                params,
+                result,
+                deprecated: method["deprecated"].as_bool().unwrap_or(false),
+            },
+        );
+    }
+    Ok(parsed)
+}
+
+/// Which side of the call a schema describes
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Params,
+    Result,
+}
+
+#[derive(Default)]
+struct Differ {
+    changes: Vec<SpecChange>,
+}
+
+impl Differ {
+    fn push(&mut self, method: &str, path: &str, kind: ChangeKind, detail: String) {
+        let breaking = matches!(
+            kind,
+            ChangeKind::MethodRemoved
+                | ChangeKind::ParamAdded
+                | ChangeKind::ParamRemoved
+                | ChangeKind::ParamRequired
+                | ChangeKind::ResultRemoved
+                | ChangeKind::TypeChanged
+        );
+        self.changes.push(SpecChange {
+            method: method.to_string(),
+            path: path.to_string(),
+            kind,
+            detail,
+            breaking,
+        });
+    }
+
+    /// Record a change whose impact depends on the side of the call
+    fn push_schema(
+        &mut self,
+        side: Side,
+        method: &str,
+        path: &str,
+        kind: ChangeKind,
+        detail: String,
+    ) {
+        self.push(method, path, kind, detail);
+        let change = self.changes.last_mut().unwrap();
+        change.breaking = match (side, kind) {
+            (Side::Params, ChangeKind::Tightened) => true,
+            (Side::Result, ChangeKind::Loosened | ChangeKind::PropertyRemoved) => true,
+            _ => change.breaking,
+        };
+    }
+
+    fn method(&mut self, name: &str, old: &MethodSpec, new: &MethodSpec) {
+        if new.deprecated && !old.deprecated {
+            self.push(
+                name,
+                "",
+                ChangeKind::MethodDeprecated,
+                "deprecated".to_string(),
+            );
+        }
+
+        for (param, (required, schema)) in &old.params {
+            let path = format!("params.{param}");
+            match new.params.get(param) {
+                None => self.push(name, &path, ChangeKind::ParamRemoved, "removed".to_string()),
+                Some((now_required, updated)) => {
+                    if *now_required && !required {
+                        self.push(
+                            name,
+                            &path,
+                            ChangeKind::ParamRequired,
+                            "now required".to_string(),
+                        );
+                    } else if *required && !now_required {
+                        self.push(
+                            name,
+                            &path,
+                            ChangeKind::ParamOptional,
+                            "now optional".to_string(),
+                        );
+                    }
+                    self.schema(Side::Params, name, &path, schema, updated);
+                }
+            }
+        }
+        for (param, (required, _)) in &new.params {
+            if !old.params.contains_key(param) {
+                let path = format!("params.{param}");
+                if *required {
+                    self.push(
+                        name,
+                        &path,
+                        ChangeKind::ParamAdded,
+                        "added as required".to_string(),
+                    );
+                } else {
+                    // Optional params can be left out, so old calls still work
+                    self.push(
+                        name,
+                        &path,
+                        ChangeKind::ParamAdded,
+                        "added as optional".to_string(),
+                    );
+                    self.changes.last_mut().unwrap().breaking = false;
+                }
+            }
+        }
+
+        match (&old.result, &new.result) {
+            (Some(old), Some(new)) => self.schema(Side::Result, name, "result", old, new),
+            (None, Some(_)) => {
+                self.push(name, "result", ChangeKind::ResultAdded, "added".to_string())
+            }
+            (Some(_), None) => self.push(
+                name,
+                "result",
+                ChangeKind::ResultRemoved,
+                "removed".to_string(),
+            ),
+            (None, None) => {}
+        }
+    }
+
+    fn schema(&mut self, side: Side, method: &str, path: &str, old: &SchemaDoc, new: &SchemaDoc) {
+        if old.schema_type != new.schema_type {
+            let detail = format!(
+                "type changed from {} to {}",
+                old.schema_type, new.schema_type
+            );
+            self.push(method, path, ChangeKind::TypeChanged, detail);
+            return;
+        }
+        let mut bound =
+            |kind: ChangeKind, detail: String| self.push_schema(side, method, path, kind, detail);
+
+        match (&old.enum_values, &new.enum_values) {
+            (Some(old), Some(new)) => {
+                let dropped: Vec<_> = old
+                    .iter()
+                    .filter(|value| !new.contains(value))
+                    .map(Value::to_string)
+                    .collect();
+                let added: Vec<_> = new
+                    .iter()
+                    .filter(|value| !old.contains(value))
+                    .map(Value::to_string)
+                    .collect();
+                if !dropped.is_empty() {
+                    bound(
+                        ChangeKind::Tightened,
+                        format!("no longer allows {}", dropped.join(", ")),
+                    );
+                }
+                if !added.is_empty() {
+                    bound(
+                        ChangeKind::Loosened,
+                        format!("now also allows {}", added.join(", ")),
+                    );
+                }
+            }
+            (None, Some(_)) => bound(ChangeKind::Tightened, "restricted to an enum".to_string()),
+            (Some(_), None) => bound(
+                ChangeKind::Loosened,
+                "no longer restricted to an enum".to_string(),
+            ),
+            (None, None) => {}
+        }
+
+        compare_bound("minimum", old.minimum, new.minimum, true, &mut bound);
+        compare_bound("maximum", old.maximum, new.maximum, false, &mut bound);
+        let lengths = |length: Option<usize>| length.map(|length| length as f64);
+        compare_bound(
+            "minLength",
+            lengths(old.min_length),
+            lengths(new.min_length),
+            true,
+            &mut bound,
+        );
+        compare_bound(
+            "maxLength",
+            lengths(old.max_length),
+            lengths(new.max_length),
+            false,
+            &mut bound,
+        );
+
+        match (&old.pattern, &new.pattern) {
+            (old, Some(new)) if old.as_ref() != Some(new) => {
+                // Whether one pattern accepts a superset of another can't be
+                // decided in general, so any new pattern counts as tighter
+                bound(ChangeKind::Tightened, format!("must match pattern {new}"));
+            }
+            (Some(_), None) => bound(ChangeKind::Loosened, "pattern removed".to_string()),
+            _ => {}
+        }
+
+        let old_required: BTreeSet<_> = old.required.iter().flatten().collect();
+        let new_required: BTreeSet<_> = new.required.iter().flatten().collect();
+        for name in new_required.difference(&old_required) {
+            bound(
+                ChangeKind::Tightened,
+                format!("property {name} now required"),
+            );
+        }
+        for name in old_required.difference(&new_required) {
+            bound(
+                ChangeKind::Loosened,
+                format!("property {name} no longer required"),
+            );
+        }
+
+        let empty = Default::default();
+        let old_properties = old.properties.as_ref().unwrap_or(&empty);
+        let new_properties = new.properties.as_ref().unwrap_or(&empty);
+        let names: BTreeSet<_> = old_properties.keys().chain(new_properties.keys()).collect();
+        for name in names {
+            let property_path = format!("{path}.{name}");
+            match (old_properties.get(name), new_properties.get(name)) {
+                (Some(old), Some(new)) => self.schema(side, method, &property_path, old, new),
+                (Some(_), None) => self.push_schema(
+                    side,
+                    method,
+                    &property_path,
+                    ChangeKind::PropertyRemoved,
+                    "removed".to_string(),
+                ),
+                (None, Some(_)) => self.push_schema(
+                    side,
+                    method,
+                    &property_path,
+                    ChangeKind::PropertyAdded,
+                    "added".to_string(),
+                ),
+                (None, None) => {}
+            }
+        }
+
+        if let (Some(old), Some(new)) = (&old.items, &new.items) {
+            self.schema(side, method, &format!("{path}[]"), old, new);
+        }
+    }
+}
+
+/// Compare a lower (`is_lower`) or upper bound
+fn compare_bound(
+    name: &str,
+    old: Option<f64>,
+    new: Option<f64>,
+    is_lower: bool,
+    record: &mut impl FnMut(ChangeKind, String),
+) {
+    match (old, new) {
+        (Some(old), Some(new)) if old != new => {
+            let tighter = if is_lower { new > old } else { new < old };
+            let kind = if tighter {
+                ChangeKind::Tightened
+            } else {
+                ChangeKind::Loosened
+            };
+            record(kind, format!("{name} changed from {old} to {new}"));
+        }
+        (None, Some(new)) => record(ChangeKind::Tightened, format!("{name} {new} added")),
+        (Some(old), None) => record(ChangeKind::Loosened, format!("{name} {old} removed")),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MethodDocumentation, ParameterDoc};
+
+    fn spec(generator: &DocumentationGenerator) -> String {
+        generator.generate_openrpc().unwrap()
+    }
+
+    fn changed(
+        generator: &mut DocumentationGenerator,
+        name: &str,
+        change: impl FnOnce(&mut MethodDocumentation),
+    ) {
+        let mut method = generator.get_method(name).unwrap().clone();
+        change(&mut method);
+        generator.add_method(method);
+    }
+
+    #[test]
+    fn test_identical_specs() {
+        let generator = DocumentationGenerator::new();
+        let diff = DocumentationGenerator::diff(&spec(&generator), &spec(&generator)).unwrap();
+        assert!(diff.is_empty());
+        assert!(!diff.is_breaking());
+        assert!(DocumentationGenerator::diff("{}", &spec(&generator)).is_err());
+    }
+
+    #[test]
+    fn test_breaking_changes() {
+        let old = DocumentationGenerator::new();
+        let mut new = DocumentationGenerator::new();
+        changed(&mut new, "cc_getBlockByHeight", |method| {
+            method.parameters[0].schema.minimum = Some(1.0);
+            method.parameters.push(ParameterDoc {
+                name: "full".to_string(),
+                description: String::new(),
+                schema: SchemaDoc {
+                    schema_type: "boolean".to_string(),
+                    ..SchemaDoc::default()
+                },
+                required: false,
+                example: None,
+            });
+            if let Some(result) = &mut method.result {
+                result.schema.required = Some(vec!["height".to_string(), "timestamp".to_string()]);
+            }
+        });
+        changed(&mut new, "cc_getLatestBlock", |method| {
+            method.deprecated = true
+        });
+        let mut ping = new.get_method("cc_ping").unwrap().clone();
+        ping.name = "cc_version".to_string();
+        new.add_method(ping);
+
+        let diff = DocumentationGenerator::diff(&spec(&old), &spec(&new)).unwrap();
+        assert!(diff.is_breaking());
+        let breaking: Vec<_> = diff.breaking_changes().map(ToString::to_string).collect();
+        assert_eq!(
+            breaking,
+            vec![
+                "cc_getBlockByHeight params.height: minimum changed from 0 to 1".to_string(),
+                "cc_getBlockByHeight result: property hash no longer required".to_string(),
+            ]
+        );
+
+        let kinds: Vec<_> = diff
+            .changes
+            .iter()
+            .filter(|change| !change.breaking)
+            .map(|change| change.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ChangeKind::ParamAdded,
+                ChangeKind::MethodDeprecated,
+                ChangeKind::MethodAdded
+            ]
+        );
+        assert!(diff
+            .to_string()
+            .starts_with("Breaking changes:\n- cc_getBlockByHeight"));
+
+        // Removing the method again is breaking the other way round
+        let reverse = DocumentationGenerator::diff(&spec(&new), &spec(&old)).unwrap();
+        assert!(reverse
+            .changes
+            .iter()
+            .any(|change| change.kind == ChangeKind::MethodRemoved && change.breaking));
+    }
+}
@@ -0,0 +1,224 @@
+//! Error reference
+//!
+//! Every error code documented by any method, once, with what it means for
+//! each method returning it, the schema of its data and how to resolve it.
+//! Method sections link each of their errors to its entry here, which is
+//! anchored as `error-<code>` with the code's minus sign left out.
+
+use crate::{escape_html, file_stem, DocumentationGenerator, ErrorDoc, Result, SchemaDoc};
+
+/// One error code and every method documenting it
+pub(crate) struct ErrorEntry<'a> {
+    pub(crate) code: i32,
+    pub(crate) message: &'a str,
+    /// Method name and what the error means for it
+    pub(crate) uses: Vec<(&'a str, &'a str)>,
+    /// Distinct data schemas, usually one
+    pub(crate) data_schemas: Vec<&'a SchemaDoc>,
+    pub(crate) remediation: Option<&'a str>,
+}
+
+impl DocumentationGenerator {
+    /// Documented error codes in numeric order
+    pub(crate) fn error_entries(&self) -> Vec<ErrorEntry<'_>> {
+        let mut entries: Vec<ErrorEntry> = Vec::new();
+        for method in self.sorted_methods() {
+            for error in &method.errors {
+                let index = match entries.iter().position(|entry| entry.code == error.code) {
+                    Some(index) => index,
+                    None => {
+                        entries.push(ErrorEntry {
+                            code: error.code,
+                            message: &error.message,
+                            uses: Vec::new(),
+                            data_schemas: Vec::new(),
+                            remediation: None,
+                        });
+                        entries.len() - 1
+                    }
+                };
+                let entry = &mut entries[index];
+                entry.uses.push((&method.name, &error.description));
+                if let Some(schema) = &error.data_schema {
+                    let json = serde_json::to_value(schema).ok();
+                    let known = entry
+                        .data_schemas
+                        .iter()
+                        .any(|known| serde_json::to_value(known).ok() == json);
+                    if !known {
+                        entry.data_schemas.push(schema);
+                    }
+                }
+                if entry.remediation.is_none() {
+                    entry.remediation = error.remediation.as_deref();
+                }
+            }
+        }
+        entries.sort_by_key(|entry| entry.code);
+        entries
+    }
+
+    /// Generate the error reference as a Markdown document
+    pub(crate) fn generate_error_reference(&self) -> Result<String> {
+        let entries = self.error_entries();
+        let mut markdown = format!("# {} Errors\n\n", self.config.title);
+        markdown.push_str("| Code | Message | Methods |\n|------|---------|---------|\n");
+        for entry in &entries {
+            let methods: Vec<String> = entry
+                .uses
+                .iter()
+                .map(|(method, _)| format!("`{method}`"))
+                .collect();
+            markdown.push_str(&format!(
+                "| [`{}`](#{}) | {} | {} |\n",
+                entry.code,
+                error_anchor(entry.code),
+                entry.message,
+                methods.join(", ")
+            ));
+        }
+        markdown.push('\n');
+
+        for entry in &entries {
+            markdown.push_str(&format!(
+                "## <a id=\"{}\"></a>`{}` {}\n\n",
+                error_anchor(entry.code),
+                entry.code,
+                entry.message
+            ));
+            for (method, description) in &entry.uses {
+                markdown.push_str(&format!(
+                    "- [`{method}`](methods/{}.md): {description}\n",
+                    file_stem(method)
+                ));
+            }
+            markdown.push('\n');
+            for schema in &entry.data_schemas {
+                markdown.push_str("**Data:**\n```json\n");
+                markdown.push_str(&serde_json::to_string_pretty(&schema.to_json_schema())?);
+                markdown.push_str("\n```\n\n");
+            }
+            if let Some(remediation) = entry.remediation {
+                markdown.push_str(&format!("**Remediation:** {remediation}\n\n"));
+            }
+        }
+        Ok(markdown)
+    }
+
+    /// HTML section of the error reference; `method_page` is where method
+    /// sections are, by method file stem
+    pub(crate) fn html_error_reference(&self, method_page: &str) -> Result<String> {
+        let mut html = String::from("<h2 id=\"errors\">Errors</h2>\n");
+        for entry in self.error_entries() {
+            html.push_str(&format!(
+                "<div class=\"error\" id=\"{}\">\n<h3><code>{}</code> {}</h3>\n<ul>\n",
+                error_anchor(entry.code),
+                entry.code,
+                escape_html(entry.message)
+            ));
+            for (method, description) in &entry.uses {
+                let link = method_page.replace("{}", &file_stem(method));
+                html.push_str(&format!(
+                    "<li><a href=\"{}\"><code>{}</code></a>: {}</li>\n",
+                    link,
+                    escape_html(method),
+                    escape_html(description)
+                ));
+            }
+            html.push_str("</ul>\n");
+            for schema in &entry.data_schemas {
+                html.push_str(&format!(
+                    "<details class=\"schema\">\n<summary>Data schema</summary>\n<pre>{}</pre>\n</details>\n",
+                    escape_html(&serde_json::to_string_pretty(&schema.to_json_schema())?)
+                ));
+            }
+            if let Some(remediation) = entry.remediation {
+                html.push_str(&format!(
+                    "<p><strong>Remediation:</strong> {}</p>\n",
+                    escape_html(remediation)
+                ));
+            }
+            html.push_str("</div>\n");
+        }
+        Ok(html)
+    }
+}
+
+/// Anchor of an error code's entry, e.g. `error-32007`
+pub(crate) fn error_anchor(code: i32) -> String {
+    format!("error-{}", code.unsigned_abs())
+}
+
+/// Markdown list item for `error` linking to its entry in `reference`
+pub(crate) fn markdown_error_link(error: &ErrorDoc, reference: &str) -> String {
+    format!(
+        "- [`{}`]({}#{}) {}: {}\n",
+        error.code,
+        reference,
+        error_anchor(error.code),
+        error.message,
+        error.description
+    )
+}
+
+/// HTML list item for `error` linking to its entry in `reference`
+pub(crate) fn html_error_link(error: &ErrorDoc, reference: &str) -> String {
+    format!(
+        "<li><a href=\"{}#{}\"><code>{}</code></a> {}: {}</li>\n",
+        reference,
+        error_anchor(error.code),
+        error.code,
+        escape_html(&error.message),
+        escape_html(&error.description)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DocumentationFormat;
+
+    #[test]
+    fn test_error_reference_deduplicates_codes() {
+        let mut generator = DocumentationGenerator::new();
+        let mut method = generator.get_method("cc_getBlockByHeight").unwrap().clone();
+        method.name = "cc_getBlockByHash".to_string();
+        method.errors[1].description = "No block has the hash".to_string();
+        method.errors[1].remediation = None;
+        generator.add_method(method);
+
+        let entries = generator.error_entries();
+        let codes: Vec<i32> = entries.iter().map(|entry| entry.code).collect();
+        assert_eq!(codes, [-32603, -32602, -32007]);
+        let not_found = &entries[2];
+        assert_eq!(not_found.uses.len(), 2);
+        assert_eq!(
+            not_found.uses[0],
+            ("cc_getBlockByHash", "No block has the hash")
+        );
+        assert_eq!(not_found.data_schemas.len(), 1);
+        assert!(not_found.remediation.is_some());
+
+        let markdown = generator.generate_error_reference().unwrap();
+        assert!(markdown.contains("| [`-32007`](#error-32007) | Block not found | `cc_getBlockByHash`, `cc_getBlockByHeight` |"));
+        assert!(markdown.contains("## <a id=\"error-32007\"></a>`-32007` Block not found"));
+        assert!(markdown.contains("**Remediation:** "));
+    }
+
+    #[test]
+    fn test_methods_link_to_error_reference() {
+        let mut generator = DocumentationGenerator::new();
+        generator.config.output_format = DocumentationFormat::Markdown;
+        let markdown = generator.generate().unwrap();
+        assert!(markdown.contains("- [`-32007`](errors.md#error-32007) Block not found: "));
+
+        generator.config.output_format = DocumentationFormat::Html;
+        let html = generator.generate().unwrap();
+        assert!(
+            html.contains("<li><a href=\"#error-32007\"><code>-32007</code></a> Block not found: ")
+        );
+        assert!(html.contains("<div class=\"error\" id=\"error-32007\">"));
+        assert!(html
+            .contains("<li><a href=\"#cc_getBlockByHeight\"><code>cc_getBlockByHeight</code></a>"));
+    }
+}
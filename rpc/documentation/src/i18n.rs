@@ -0,0 +1,184 @@
+//! Localized documentation
+//!
+//! Translations give the text of the API and its methods in one locale.
+//! Whatever a translation leaves out stays in English, the language the
+//! documentation is written in, so a partial translation is still a
+//! complete document.
+
+use crate::DocumentationGenerator;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Locale of the untranslated documentation
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// Text of the documentation in one locale
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Translation {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Translated methods, by method name
+    pub methods: HashMap<String, MethodTranslation>,
+}
+
+/// Text of one method in one locale
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MethodTranslation {
+    pub summary: Option<String>,
+    pub description: Option<String>,
+    /// Parameter descriptions, by parameter name
+    pub parameters: HashMap<String, String>,
+    pub result: Option<String>,
+}
+
+impl Translation {
+    /// Overlay `other`, whose text wins where both translate the same thing
+    fn merge(&mut self, other: Translation) {
+        self.title = other.title.or(self.title.take());
+        self.description = other.description.or(self.description.take());
+        for (name, method) in other.methods {
+            let merged = self.methods.entry(name).or_default();
+            merged.summary = method.summary.or(merged.summary.take());
+            merged.description = method.description.or(merged.description.take());
+            merged.parameters.extend(method.parameters);
+            merged.result = method.result.or(merged.result.take());
+        }
+    }
+}
+
+impl DocumentationGenerator {
+    /// Add text in `locale`, such as `de` or `pt-BR`, on top of any earlier
+    /// translation into it
+    pub fn add_translation(&mut self, locale: &str, translation: Translation) {
+        self.translations
+            .entry(locale.to_string())
+            .or_default()
+            .merge(translation);
+    }
+
+    /// The default locale followed by every translated one
+    pub fn locales(&self) -> Vec<String> {
+        let mut locales: Vec<String> = self
+            .translations
+            .keys()
+            .filter(|locale| *locale != DEFAULT_LOCALE)
+            .cloned()
+            .collect();
+        locales.sort();
+        locales.insert(0, DEFAULT_LOCALE.to_string());
+        locales
+    }
+
+    /// Documentation in `locale`, falling back to English for anything not
+    /// translated
+    pub fn for_locale(&self, locale: &str) -> DocumentationGenerator {
+        let mut localized = DocumentationGenerator {
+            config: self.config.clone(),
+            methods: self.methods.clone(),
+            subscriptions: self.subscriptions.clone(),
+            schemas: self.schemas.clone(),
+            translations: HashMap::new(),
+        };
+        localized.config.locale = locale.to_string();
+        let Some(translation) = self.translations.get(locale) else {
+            return localized;
+        };
+
+        let config = &mut localized.config;
+        if let Some(title) = &translation.title {
+            config.title = title.clone();
+        }
+        if let Some(description) = &translation.description {
+            config.description = description.clone();
+        }
+        for (name, text) in &translation.methods {
+            let Some(method) = localized.methods.get_mut(name) else {
+                continue;
+            };
+            if let Some(summary) = &text.summary {
+                method.summary = summary.clone();
+            }
+            if let Some(description) = &text.description {
+                method.description = description.clone();
+            }
+            for param in &mut method.parameters {
+                if let Some(description) = text.parameters.get(&param.name) {
+                    param.description = description.clone();
+                }
+            }
+            if let (Some(result), Some(description)) = (&mut method.result, &text.result) {
+                result.description = description.clone();
+            }
+        }
+        localized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DocumentationConfig, DocumentationFormat};
+
+    fn german() -> Translation {
+        serde_json::from_str(
+            r#"{
+                "title": "CC Chain RPC-Schnittstelle",
+                "methods": {
+                    "cc_getBlockByHeight": {
+                        "summary": "Block nach Höhe abrufen",
+                        "parameters": {"height": "Höhe des Blocks"}
+                    }
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_translation_falls_back_to_english() {
+        let mut generator = DocumentationGenerator::new();
+        generator.add_translation("de", german());
+        generator.add_translation(
+            "de",
+            Translation {
+                description: Some("Dokumentation der RPC-Schnittstelle".to_string()),
+                ..Translation::default()
+            },
+        );
+        assert_eq!(generator.locales(), ["en", "de"]);
+
+        let german = generator.for_locale("de");
+        assert_eq!(german.config.title, "CC Chain RPC-Schnittstelle");
+        assert_eq!(
+            german.config.description,
+            "Dokumentation der RPC-Schnittstelle"
+        );
+        let block = german.get_method("cc_getBlockByHeight").unwrap();
+        assert_eq!(block.summary, "Block nach Höhe abrufen");
+        assert_eq!(block.parameters[0].description, "Höhe des Blocks");
+        let english = generator.get_method("cc_getBlockByHeight").unwrap();
+        assert_eq!(block.description, english.description);
+        assert_eq!(
+            german.get_method("cc_ping").unwrap().summary,
+            generator.get_method("cc_ping").unwrap().summary
+        );
+
+        let french = generator.for_locale("fr");
+        assert_eq!(french.config.title, generator.config.title);
+    }
+
+    #[test]
+    fn test_localized_html() {
+        let mut generator = DocumentationGenerator::with_config(DocumentationConfig {
+            output_format: DocumentationFormat::Html,
+            ..DocumentationConfig::default()
+        });
+        generator.add_translation("de", german());
+        let html = generator.for_locale("de").generate().unwrap();
+        assert!(html.contains("<html lang=\"de\">"));
+        assert!(html.contains("<h1>CC Chain RPC-Schnittstelle</h1>"));
+        assert!(generator.generate().unwrap().contains("<html lang=\"en\">"));
+    }
+}
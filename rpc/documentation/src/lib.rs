@@ -6,7 +6,40 @@
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
+use components::Components;
+
+// Lets the annotation macros name this crate from inside it too
+extern crate self as rpc_documentation;
+
+pub mod annotations;
+pub mod codegen;
+mod collections;
+mod components;
+pub mod diff;
+mod error_reference;
+pub mod i18n;
+pub mod lint;
+mod snippets;
+pub mod validation;
+pub mod versions;
+
+pub use annotations::DocSchema;
+pub use codegen::ClientLanguage;
+pub use rpc_documentation_macros::{rpc_doc, DocSchema};
+pub use diff::{ChangeKind, SpecChange, SpecDiff};
+pub use i18n::{MethodTranslation, Translation};
+pub use lint::{LintConfig, LintReport, LintRule, LintSeverity, LintViolation};
+pub use validation::{SchemaValidator, SchemaViolation};
+pub use versions::Availability;
+
+/// Stylesheet shared by the HTML output
+const DOCS_CSS: &str = include_str!("../templates/docs.css");
+
+/// Method search and try-it console of the HTML output
+const DOCS_JS: &str = include_str!("../templates/docs.js");
 
 #[derive(Error, Debug)]
 pub enum DocumentationError {
@@ -41,6 +74,10 @@ pub struct DocumentationConfig {
     pub include_schemas: bool,
     pub generate_types: bool,
     pub output_format: DocumentationFormat,
+    /// Language of the documentation text, as a tag like `en` or `pt-BR`
+    pub locale: String,
+    /// Severity of each lint rule; error-level findings fail generation
+    pub lint: LintConfig,
 }
 
 impl Default for DocumentationConfig {
@@ -68,6 +105,8 @@ impl Default for DocumentationConfig {
             include_schemas: true,
             generate_types: true,
             output_format: DocumentationFormat::OpenRpc,
+            locale: i18n::DEFAULT_LOCALE.to_string(),
+            lint: LintConfig::default(),
         }
     }
 }
@@ -102,6 +141,51 @@ pub enum DocumentationFormat {
     Markdown,
     Html,
     Json,
+    /// AsyncAPI description of the WebSocket subscription channels
+    AsyncApi,
+    /// Postman collection with one request per method
+    Postman,
+    /// Insomnia export with one request per method
+    Insomnia,
+    /// Markdown reference of every documented error code
+    ErrorReference,
+}
+
+/// What `DocumentationGenerator::export_to_dir` writes
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    /// Formats to export, each to its own place in the layout
+    pub formats: Vec<DocumentationFormat>,
+    /// Write one Markdown file per method instead of a single document
+    pub split_markdown: bool,
+    /// Also write `bundle.html`, one self-contained page with every method
+    pub bundle: bool,
+    /// Also write TypeScript and Rust clients under `clients/`
+    pub clients: bool,
+    /// Also export every protocol version under `versions/<version>/`, with
+    /// a compatibility matrix in `versions/compatibility.md`
+    pub versions: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            formats: vec![
+                DocumentationFormat::OpenRpc,
+                DocumentationFormat::OpenApi,
+                DocumentationFormat::Markdown,
+                DocumentationFormat::Html,
+                DocumentationFormat::Json,
+                DocumentationFormat::AsyncApi,
+                DocumentationFormat::Postman,
+                DocumentationFormat::Insomnia,
+            ],
+            split_markdown: true,
+            bundle: false,
+            clients: false,
+            versions: false,
+        }
+    }
 }
 
 /// RPC method documentation
@@ -119,6 +203,20 @@ pub struct MethodDocumentation {
     pub since_version: String,
 }
 
+/// Subscription channel documentation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriptionDocumentation {
+    /// Kind passed to `cc_subscribe`, e.g. `newHeads`
+    pub name: String,
+    pub summary: String,
+    pub description: String,
+    /// Options passed after the kind, such as the `logs` filter
+    pub options: Option<SchemaDoc>,
+    /// Schema of each event delivered
+    pub event: SchemaDoc,
+    pub example: Option<Value>,
+}
+
 /// Parameter documentation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParameterDoc {
@@ -145,6 +243,9 @@ pub struct ErrorDoc {
     pub message: String,
     pub description: String,
     pub data_schema: Option<SchemaDoc>,
+    /// What a client can do about the error
+    #[serde(default)]
+    pub remediation: Option<String>,
 }
 
 /// Example documentation
@@ -172,13 +273,23 @@ pub struct SchemaDoc {
     pub maximum: Option<f64>,
     pub min_length: Option<usize>,
     pub max_length: Option<usize>,
+    /// Regular expression string values must match
+    pub pattern: Option<String>,
+}
+
+/// Registry of the methods a server actually serves
+pub trait MethodRegistry {
+    /// Documentation of every registered method
+    fn documented_methods(&self) -> Vec<MethodDocumentation>;
 }
 
 /// Documentation generator
 pub struct DocumentationGenerator {
     config: DocumentationConfig,
     methods: HashMap<String, MethodDocumentation>,
+    subscriptions: HashMap<String, SubscriptionDocumentation>,
     schemas: HashMap<String, SchemaDoc>,
+    translations: HashMap<String, Translation>,
 }
 
 impl DocumentationGenerator {
@@ -192,11 +303,25 @@ impl DocumentationGenerator {
         let mut generator = Self {
             config,
             methods: HashMap::new(),
+            subscriptions: HashMap::new(),
             schemas: HashMap::new(),
+            translations: HashMap::new(),
         };
         
         generator.register_standard_methods();
         generator.register_standard_schemas();
+        generator.register_standard_subscriptions();
+        generator
+    }
+
+    /// Document exactly the methods registered with `registry`, such as a
+    /// live `RpcProtocol`, instead of the built-in method list
+    pub fn from_protocol(registry: &impl MethodRegistry) -> Self {
+        let mut generator = Self::new();
+        generator.methods.clear();
+        for method in registry.documented_methods() {
+            generator.add_method(method);
+        }
         generator
     }
 
@@ -261,6 +386,7 @@ impl DocumentationGenerator {
                     message: "Internal error".to_string(),
                     description: "Server internal error occurred".to_string(),
                     data_schema: None,
+                    remediation: Some("Retry later; report the error if it persists.".to_string()),
                 },
             ],
             examples: vec![
@@ -309,7 +435,8 @@ impl DocumentationGenerator {
                 schema: self.create_block_schema(),
                 example: Some(json!({
                     "height": 12345,
-                    "hash": "0x1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef"
+                    "hash": "0x1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef",
+                    "timestamp": 1640000000
                 })),
             }),
             errors: vec![
@@ -318,6 +445,7 @@ impl DocumentationGenerator {
                     message: "Invalid params".to_string(),
                     description: "Invalid block height parameter".to_string(),
                     data_schema: None,
+                    remediation: Some("Pass the height as a non-negative integer.".to_string()),
                 },
                 ErrorDoc {
                     code: -32007,
@@ -336,6 +464,7 @@ impl DocumentationGenerator {
                         }),
                         ..Default::default()
                     }),
+                    remediation: Some("Check the height against the latest block.".to_string()),
                 },
             ],
             examples: vec![
@@ -346,7 +475,8 @@ impl DocumentationGenerator {
                     params: Some(json!({"height": 12345})),
                     result: Some(json!({
                         "height": 12345,
-                        "hash": "0x1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef"
+                        "hash": "0x1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef",
+                        "timestamp": 1640000000
                     })),
                 },
             ],
@@ -361,6 +491,67 @@ impl DocumentationGenerator {
         self.schemas.insert("Block".to_string(), self.create_block_schema());
         self.schemas.insert("Transaction".to_string(), self.create_transaction_schema());
         self.schemas.insert("Account".to_string(), self.create_account_schema());
+        self.schemas.insert("Log".to_string(), self.create_log_schema());
+    }
+
+    /// Register the subscription channels served over WebSocket
+    fn register_standard_subscriptions(&mut self) {
+        self.add_subscription(SubscriptionDocumentation {
+            name: "newHeads".to_string(),
+            summary: "New block headers".to_string(),
+            description: "Sends the header of every block appended to the chain".to_string(),
+            options: None,
+            event: self.create_block_schema(),
+            example: Some(json!({
+                "height": 12345,
+                "hash": "0x1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef",
+                "timestamp": 1640000000
+            })),
+        });
+
+        self.add_subscription(SubscriptionDocumentation {
+            name: "pendingTransactions".to_string(),
+            summary: "Pending transactions".to_string(),
+            description: "Sends every transaction accepted into the mempool".to_string(),
+            options: None,
+            event: self.create_transaction_schema(),
+            example: Some(json!({
+                "hash": "0xabcdef1234567890abcdef1234567890abcdef1234567890abcdef1234567890",
+                "from": "0x1234567890abcdef1234567890abcdef12345678",
+                "value": "1000000000000000000"
+            })),
+        });
+
+        let mut filter = HashMap::new();
+        filter.insert("address".to_string(), SchemaDoc {
+            schema_type: "any".to_string(),
+            description: Some("Emitting address, or list of addresses; all when omitted".to_string()),
+            ..Default::default()
+        });
+        filter.insert("topics".to_string(), SchemaDoc {
+            schema_type: "array".to_string(),
+            description: Some("Topics matched by position: null matches any topic, a list any of its entries".to_string()),
+            ..Default::default()
+        });
+        self.add_subscription(SubscriptionDocumentation {
+            name: "logs".to_string(),
+            summary: "Contract logs".to_string(),
+            description: "Sends every log matching the filter".to_string(),
+            options: Some(SchemaDoc {
+                schema_type: "object".to_string(),
+                description: Some("Log filter".to_string()),
+                properties: Some(filter),
+                ..Default::default()
+            }),
+            event: self.create_log_schema(),
+            example: Some(json!({
+                "address": "0x1234567890abcdef1234567890abcdef12345678",
+                "topics": ["0x01"],
+                "data": "0x",
+                "block_height": 12345,
+                "transaction_hash": "0xabcdef1234567890abcdef1234567890abcdef1234567890abcdef1234567890"
+            })),
+        });
     }
 
     fn create_block_schema(&self) -> SchemaDoc {
@@ -465,29 +656,157 @@ impl DocumentationGenerator {
         }
     }
 
+    fn create_log_schema(&self) -> SchemaDoc {
+        let mut properties = HashMap::new();
+        
+        properties.insert("address".to_string(), SchemaDoc {
+            schema_type: "string".to_string(),
+            format: Some("address".to_string()),
+            description: Some("Emitting contract address".to_string()),
+            ..Default::default()
+        });
+        
+        properties.insert("topics".to_string(), SchemaDoc {
+            schema_type: "array".to_string(),
+            description: Some("Indexed log topics".to_string()),
+            items: Some(Box::new(SchemaDoc {
+                schema_type: "string".to_string(),
+                format: Some("hex".to_string()),
+                ..Default::default()
+            })),
+            ..Default::default()
+        });
+        
+        properties.insert("data".to_string(), SchemaDoc {
+            schema_type: "string".to_string(),
+            format: Some("hex".to_string()),
+            description: Some("Unindexed log data".to_string()),
+            ..Default::default()
+        });
+        
+        properties.insert("block_height".to_string(), SchemaDoc {
+            schema_type: "integer".to_string(),
+            description: Some("Height of the block including the log".to_string()),
+            minimum: Some(0.0),
+            ..Default::default()
+        });
+        
+        properties.insert("transaction_hash".to_string(), SchemaDoc {
+            schema_type: "string".to_string(),
+            format: Some("hex".to_string()),
+            description: Some("Transaction that emitted the log".to_string()),
+            ..Default::default()
+        });
+
+        SchemaDoc {
+            schema_type: "object".to_string(),
+            description: Some("Contract log".to_string()),
+            properties: Some(properties),
+            required: Some(vec!["address".to_string(), "topics".to_string()]),
+            ..Default::default()
+        }
+    }
+
     /// Add a method to the documentation
     pub fn add_method(&mut self, method: MethodDocumentation) {
         self.methods.insert(method.name.clone(), method);
     }
 
+    /// Add a subscription channel to the documentation
+    pub fn add_subscription(&mut self, subscription: SubscriptionDocumentation) {
+        self.subscriptions.insert(subscription.name.clone(), subscription);
+    }
+
     /// Add a schema to the documentation
     pub fn add_schema(&mut self, name: String, schema: SchemaDoc) {
         self.schemas.insert(name, schema);
     }
 
-    /// Generate documentation in the specified format
+    /// Generate documentation in the specified format, after checking that
+    /// every example matches its schema and no lint rule reports an error
     pub fn generate(&self) -> Result<String> {
+        self.validate()?;
+        self.lint().into_result()?;
         match self.config.output_format {
             DocumentationFormat::OpenRpc => self.generate_openrpc(),
             DocumentationFormat::OpenApi => self.generate_openapi(),
             DocumentationFormat::Markdown => self.generate_markdown(),
             DocumentationFormat::Html => self.generate_html(),
             DocumentationFormat::Json => self.generate_json(),
+            DocumentationFormat::AsyncApi => self.generate_asyncapi(),
+            DocumentationFormat::Postman => self.generate_postman(),
+            DocumentationFormat::Insomnia => self.generate_insomnia(),
+            DocumentationFormat::ErrorReference => self.generate_error_reference(),
+        }
+    }
+
+    /// Check every example against the schema it illustrates
+    ///
+    /// Covers the params and results of each `ExampleDoc` as well as the
+    /// examples given inline with parameters and results. Every mismatch is
+    /// reported with the method, the example and the path of the value.
+    pub fn validate(&self) -> Result<()> {
+        let validator = SchemaValidator::new();
+        let mut problems = Vec::new();
+        
+        for method in self.sorted_methods() {
+            let mut report = |what: &str, violation: SchemaViolation| {
+                problems.push(format!("{} {}: {}", method.name, what, violation));
+            };
+            
+            for param in &method.parameters {
+                if let Some(example) = &param.example {
+                    let path = format!("params.{}", param.name);
+                    if let Err(violation) = validator.validate(&param.schema, example, &path) {
+                        report("parameter example", violation);
+                    }
+                }
+            }
+            if let Some(ResultDoc { schema, example: Some(example), .. }) = &method.result {
+                if let Err(violation) = validator.validate(schema, example, "result") {
+                    report("result example", violation);
+                }
+            }
+            
+            for example in &method.examples {
+                let what = format!("example '{}'", example.name);
+                if let Some(params) = &example.params {
+                    for violation in example_params_violations(&validator, method, params) {
+                        report(&what, violation);
+                    }
+                }
+                match (&example.result, &method.result) {
+                    (Some(value), Some(result)) => {
+                        if let Err(violation) = validator.validate(&result.schema, value, "result") {
+                            report(&what, violation);
+                        }
+                    }
+                    (Some(_), None) => report(&what, SchemaViolation {
+                        path: "result".to_string(),
+                        reason: "is given but the method documents no result".to_string(),
+                    }),
+                    _ => {}
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(DocumentationError::ValidationError(problems.join("; ")))
         }
     }
 
     /// Generate OpenRPC specification
+    ///
+    /// Methods are sorted by name and shared schemas are referenced from
+    /// `components.schemas`, so the same API always gives the same spec.
     fn generate_openrpc(&self) -> Result<String> {
+        let components = if self.config.include_schemas {
+            Components::new(self)
+        } else {
+            Components::none()
+        };
         let spec = json!({
             "openrpc": "1.2.6",
             "info": {
@@ -498,7 +817,7 @@ impl DocumentationGenerator {
                 "license": self.config.license
             },
             "servers": self.config.servers,
-            "methods": self.methods.values().map(|method| {
+            "methods": self.sorted_methods().into_iter().map(|method| {
                 json!({
                     "name": method.name,
                     "summary": method.summary,
@@ -508,14 +827,14 @@ impl DocumentationGenerator {
                             "name": param.name,
                             "description": param.description,
                             "required": param.required,
-                            "schema": param.schema
+                            "schema": components.reference(&param.schema)
                         })
                     }).collect::<Vec<_>>(),
                     "result": method.result.as_ref().map(|result| {
                         json!({
                             "name": result.name,
                             "description": result.description,
-                            "schema": result.schema
+                            "schema": components.reference(&result.schema)
                         })
                     }),
                     "errors": method.errors.iter().map(|error| {
@@ -523,7 +842,7 @@ impl DocumentationGenerator {
                             "code": error.code,
                             "message": error.message,
                             "description": error.description,
-                            "data": error.data_schema
+                            "data": error.data_schema.as_ref().map(|data| components.reference(data))
                         })
                     }).collect::<Vec<_>>(),
                     "examples": if self.config.include_examples {
@@ -545,7 +864,7 @@ impl DocumentationGenerator {
             }).collect::<Vec<_>>(),
             "components": if self.config.include_schemas {
                 json!({
-                    "schemas": self.schemas
+                    "schemas": components.schemas()
                 })
             } else {
                 json!({})
@@ -680,8 +999,130 @@ impl DocumentationGenerator {
         Ok(json!(schemas))
     }
 
+    /// Generate AsyncAPI specification of the subscription channels
+    fn generate_asyncapi(&self) -> Result<String> {
+        let servers: serde_json::Map<String, Value> = self.config.servers.iter().enumerate().map(|(i, server)| {
+            let url = snippets::websocket_url(&server.url);
+            let protocol = if url.starts_with("wss:") { "wss" } else { "ws" };
+            (format!("server{}", i), json!({
+                "url": url,
+                "protocol": protocol,
+                "description": server.description
+            }))
+        }).collect();
+
+        let mut channels = serde_json::Map::new();
+        let mut messages = serde_json::Map::new();
+        for subscription in self.sorted_subscriptions() {
+            let name = &subscription.name;
+            let mut params = vec![json!({"type": "string", "enum": [name]})];
+            if let Some(options) = &subscription.options {
+                params.push(options.to_json_schema());
+            }
+            
+            messages.insert(format!("{}Request", name), json!({
+                "name": format!("{}Request", name),
+                "title": format!("Subscribe to {}", name),
+                "summary": "cc_subscribe call opening the subscription; its result is the subscription id to pass to cc_unsubscribe",
+                "payload": {
+                    "type": "object",
+                    "required": ["jsonrpc", "method", "params", "id"],
+                    "properties": {
+                        "jsonrpc": {"type": "string", "enum": ["2.0"]},
+                        "method": {"type": "string", "enum": ["cc_subscribe"]},
+                        "params": {"type": "array", "items": params, "minItems": 1},
+                        "id": {"type": ["integer", "string"]}
+                    }
+                }
+            }));
+            
+            let mut message = json!({
+                "name": name,
+                "title": subscription.summary,
+                "summary": format!("cc_subscription notification carrying one {} event", name),
+                "payload": {
+                    "type": "object",
+                    "required": ["jsonrpc", "method", "params"],
+                    "properties": {
+                        "jsonrpc": {"type": "string", "enum": ["2.0"]},
+                        "method": {"type": "string", "enum": ["cc_subscription"]},
+                        "params": {
+                            "type": "object",
+                            "required": ["subscription", "result"],
+                            "properties": {
+                                "subscription": {"type": "string", "description": "Id returned by cc_subscribe"},
+                                "result": subscription.event.to_json_schema()
+                            }
+                        }
+                    }
+                }
+            });
+            if let (true, Some(example)) = (self.config.include_examples, &subscription.example) {
+                message["examples"] = json!([{
+                    "payload": {
+                        "jsonrpc": "2.0",
+                        "method": "cc_subscription",
+                        "params": {"subscription": "0x1", "result": example}
+                    }
+                }]);
+            }
+            messages.insert(name.clone(), message);
+            
+            channels.insert(name.clone(), json!({
+                "description": subscription.description,
+                "publish": {
+                    "operationId": format!("{}Subscribe", name),
+                    "message": {"$ref": format!("#/components/messages/{}Request", name)}
+                },
+                "subscribe": {
+                    "operationId": format!("{}Event", name),
+                    "summary": subscription.summary,
+                    "message": {"$ref": format!("#/components/messages/{}", name)}
+                }
+            }));
+        }
+
+        let mut components = json!({"messages": messages});
+        if self.config.include_schemas {
+            let schemas: serde_json::Map<String, Value> = self.schemas.iter()
+                .map(|(name, schema)| (name.clone(), schema.to_json_schema()))
+                .collect();
+            components["schemas"] = Value::Object(schemas);
+        }
+        
+        let spec = json!({
+            "asyncapi": "2.6.0",
+            "info": {
+                "title": self.config.title,
+                "version": self.config.version,
+                "description": self.config.description,
+                "contact": self.config.contact,
+                "license": self.config.license
+            },
+            "servers": servers,
+            "defaultContentType": "application/json",
+            "channels": channels,
+            "components": components
+        });
+
+        Ok(serde_json::to_string_pretty(&spec)?)
+    }
+
     /// Generate Markdown documentation
     fn generate_markdown(&self) -> Result<String> {
+        let mut markdown = self.markdown_header();
+        markdown.push_str("## Methods\n\n");
+        
+        for method in self.sorted_methods() {
+            markdown.push_str(&self.markdown_method(method, "errors.md")?);
+            markdown.push_str("---\n\n");
+        }
+
+        Ok(markdown)
+    }
+
+    /// Title, description, contact and servers shared by every Markdown page
+    fn markdown_header(&self) -> String {
         let mut markdown = String::new();
         
         markdown.push_str(&format!("# {}\n\n", self.config.title));
@@ -700,122 +1141,271 @@ impl DocumentationGenerator {
         for server in &self.config.servers {
             markdown.push_str(&format!("- **{}**: {}\n", server.url, server.description));
         }
-        markdown.push_str("\n");
+        markdown.push('\n');
+        markdown
+    }
 
-        markdown.push_str("## Methods\n\n");
+    /// Markdown section documenting one method, linking its errors to
+    /// their entries in the error reference at `errors`
+    fn markdown_method(&self, method: &MethodDocumentation, errors: &str) -> Result<String> {
+        let mut markdown = String::new();
+        markdown.push_str(&format!("### {}\n\n", method.name));
+        markdown.push_str(&format!("{}\n\n", method.description));
         
-        let mut methods: Vec<_> = self.methods.values().collect();
-        methods.sort_by(|a, b| a.name.cmp(&b.name));
+        if method.deprecated {
+            markdown.push_str("**⚠️ Deprecated**\n\n");
+        }
         
-        for method in methods {
-            markdown.push_str(&format!("### {}\n\n", method.name));
-            markdown.push_str(&format!("{}\n\n", method.description));
-            
-            if method.deprecated {
-                markdown.push_str("**⚠️ Deprecated**\n\n");
-            }
-            
-            if !method.parameters.is_empty() {
-                markdown.push_str("**Parameters:**\n\n");
-                for param in &method.parameters {
-                    let required = if param.required { " (required)" } else { " (optional)" };
-                    markdown.push_str(&format!("- `{}` ({}){}: {}\n", 
-                        param.name, param.schema.schema_type, required, param.description));
-                }
-                markdown.push_str("\n");
+        if !method.parameters.is_empty() {
+            markdown.push_str("**Parameters:**\n\n");
+            for param in &method.parameters {
+                let required = if param.required { " (required)" } else { " (optional)" };
+                markdown.push_str(&format!("- `{}` ({}){}: {}\n", 
+                    param.name, param.schema.schema_type, required, param.description));
             }
-            
-            if let Some(result) = &method.result {
-                markdown.push_str(&format!("**Returns:** {} - {}\n\n", 
-                    result.schema.schema_type, result.description));
+            markdown.push('\n');
+        }
+        
+        if let Some(result) = &method.result {
+            markdown.push_str(&format!("**Returns:** {} - {}\n\n", 
+                result.schema.schema_type, result.description));
+        }
+        
+        if !method.errors.is_empty() {
+            markdown.push_str("**Errors:**\n\n");
+            for error in &method.errors {
+                markdown.push_str(&error_reference::markdown_error_link(error, errors));
             }
-            
-            if !method.errors.is_empty() {
-                markdown.push_str("**Errors:**\n\n");
-                for error in &method.errors {
-                    markdown.push_str(&format!("- `{}`: {} - {}\n", 
-                        error.code, error.message, error.description));
+            markdown.push('\n');
+        }
+        
+        if self.config.include_examples && !method.examples.is_empty() {
+            markdown.push_str("**Examples:**\n\n");
+            for example in &method.examples {
+                markdown.push_str(&format!("*{}*\n\n", example.summary));
+                
+                if let Some(params) = &example.params {
+                    markdown.push_str("Request:\n```json\n");
+                    markdown.push_str(&serde_json::to_string_pretty(&json!({
+                        "jsonrpc": "2.0",
+                        "method": method.name,
+                        "params": params,
+                        "id": 1
+                    }))?);
+                    markdown.push_str("\n```\n\n");
                 }
-                markdown.push_str("\n");
-            }
-            
-            if self.config.include_examples && !method.examples.is_empty() {
-                markdown.push_str("**Examples:**\n\n");
-                for example in &method.examples {
-                    markdown.push_str(&format!("*{}*\n\n", example.summary));
-                    
-                    if let Some(params) = &example.params {
-                        markdown.push_str("Request:\n```json\n");
-                        markdown.push_str(&serde_json::to_string_pretty(&json!({
-                            "jsonrpc": "2.0",
-                            "method": method.name,
-                            "params": params,
-                            "id": 1
-                        }))?);
-                        markdown.push_str("\n```\n\n");
-                    }
-                    
-                    if let Some(result) = &example.result {
-                        markdown.push_str("Response:\n```json\n");
-                        markdown.push_str(&serde_json::to_string_pretty(&json!({
-                            "jsonrpc": "2.0",
-                            "result": result,
-                            "id": 1
-                        }))?);
-                        markdown.push_str("\n```\n\n");
-                    }
+                
+                if let Some(result) = &example.result {
+                    markdown.push_str("Response:\n```json\n");
+                    markdown.push_str(&serde_json::to_string_pretty(&json!({
+                        "jsonrpc": "2.0",
+                        "result": result,
+                        "id": 1
+                    }))?);
+                    markdown.push_str("\n```\n\n");
                 }
             }
-            
-            markdown.push_str("---\n\n");
+        }
+        
+        if self.config.include_examples {
+            markdown.push_str("**From the command line:**\n\n");
+            for snippet in self.snippets(method) {
+                markdown.push_str(&format!("*{}*\n\n", snippet.title));
+                markdown.push_str(&format!("```bash\n{}\n```\n\n", snippet.curl));
+                markdown.push_str(&format!("```bash\n{}\n```\n\n", snippet.wscat));
+            }
         }
 
         Ok(markdown)
     }
 
-    /// Generate HTML documentation
+    /// Markdown index linking to one page per method
+    fn markdown_index(&self) -> String {
+        let mut markdown = self.markdown_header();
+        markdown.push_str("## Methods\n\n");
+        for method in self.sorted_methods() {
+            markdown.push_str(&format!("- [`{}`](methods/{}.md): {}\n",
+                method.name, file_stem(&method.name), method.summary));
+        }
+        markdown.push_str("\nSee the [error reference](errors.md) for every error code.\n");
+        markdown
+    }
+
+    /// Generate HTML documentation: a single page with method search and
+    /// a try-it console sending requests to a configurable endpoint
     fn generate_html(&self) -> Result<String> {
+        let style = format!("<style>\n{}</style>\n", DOCS_CSS);
+        let mut body = self.html_intro();
+        body.push_str(&self.html_toolbar(true));
+        body.push_str("<h2>Methods</h2>\n");
+        for method in self.sorted_methods() {
+            body.push_str(&self.html_method(method, "")?);
+        }
+        body.push_str("<p id=\"no-results\" hidden>No methods match the search.</p>\n");
+        body.push_str(&self.html_error_reference("#{}")?);
+        body.push_str(&format!("<script>\n{}</script>\n", DOCS_JS));
+        
+        Ok(self.html_document(&self.config.title, &style, &body))
+    }
+
+    /// Wrap a page body in an HTML document
+    fn html_document(&self, title: &str, head: &str, body: &str) -> String {
         let mut html = String::new();
         
-        html.push_str("<!DOCTYPE html>\n<html>\n<head>\n");
-        html.push_str(&format!("<title>{}</title>\n", self.config.title));
-        html.push_str("<style>\n");
-        html.push_str(include_str!("../templates/docs.css"));
-        html.push_str("</style>\n");
+        html.push_str(&format!("<!DOCTYPE html>\n<html lang=\"{}\">\n<head>\n", escape_html(&self.config.locale)));
+        html.push_str("<meta charset=\"utf-8\">\n");
+        html.push_str(&format!("<title>{}</title>\n", escape_html(title)));
+        html.push_str(head);
         html.push_str("</head>\n<body>\n");
+        html.push_str(body);
+        html.push_str("</body>\n</html>");
+        html
+    }
+
+    /// Title, description and version heading every HTML page
+    fn html_intro(&self) -> String {
+        let mut html = String::new();
+        html.push_str(&format!("<h1>{}</h1>\n", escape_html(&self.config.title)));
+        html.push_str(&format!("<p>{}</p>\n", escape_html(&self.config.description)));
+        html.push_str(&format!("<p><strong>Version:</strong> {}</p>\n", escape_html(&self.config.version)));
+        html
+    }
+
+    /// Endpoint the try-it console sends to and, on pages listing several
+    /// methods, the search box
+    fn html_toolbar(&self, search: bool) -> String {
+        let endpoint = self.config.servers.first().map_or("", |server| server.url.as_str());
+        let mut html = String::from("<div class=\"toolbar\">\n");
+        html.push_str(&format!("<label>Endpoint <input id=\"endpoint\" type=\"url\" value=\"{}\"></label>\n",
+            escape_html(endpoint)));
+        if search {
+            html.push_str("<input id=\"search\" type=\"search\" placeholder=\"Search methods\" aria-label=\"Search methods\">\n");
+        }
+        html.push_str("</div>\n");
+        html
+    }
+
+    /// HTML block documenting one method, with collapsible schemas, errors
+    /// linked to their entries in the error reference at `errors` and a
+    /// try-it panel pre-filled with an example request
+    fn html_method(&self, method: &MethodDocumentation, errors: &str) -> Result<String> {
+        let search = format!("{} {} {}", method.name, method.summary, method.tags.join(" ")).to_lowercase();
+        let mut html = String::new();
+        html.push_str(&format!("<div class=\"method\" id=\"{}\" data-search=\"{}\">\n",
+            file_stem(&method.name), escape_html(&search)));
+        html.push_str(&format!("<h3>{}</h3>\n", escape_html(&method.name)));
+        for tag in &method.tags {
+            html.push_str(&format!("<span class=\"tag\">{}</span>\n", escape_html(tag)));
+        }
+        html.push_str(&format!("<p>{}</p>\n", escape_html(&method.description)));
         
-        html.push_str(&format!("<h1>{}</h1>\n", self.config.title));
-        html.push_str(&format!("<p>{}</p>\n", self.config.description));
-        html.push_str(&format!("<p><strong>Version:</strong> {}</p>\n", self.config.version));
+        if method.deprecated {
+            html.push_str("<div class=\"deprecated\">Deprecated</div>\n");
+        }
         
-        html.push_str("<h2>Methods</h2>\n");
+        if !method.parameters.is_empty() {
+            html.push_str("<h4>Parameters</h4>\n<ul>\n");
+            for param in &method.parameters {
+                let required = if param.required { " (required)" } else { " (optional)" };
+                html.push_str(&format!("<li><code>{}</code> ({}){}: {}</li>\n", 
+                    escape_html(&param.name), escape_html(&param.schema.schema_type), required, escape_html(&param.description)));
+            }
+            html.push_str("</ul>\n");
+            
+            let params = json!({
+                "type": "object",
+                "properties": method.parameters.iter()
+                    .map(|param| (param.name.clone(), param.schema.to_json_schema()))
+                    .collect::<serde_json::Map<_, _>>(),
+                "required": method.parameters.iter()
+                    .filter(|param| param.required)
+                    .map(|param| param.name.as_str())
+                    .collect::<Vec<_>>()
+            });
+            html.push_str(&html_schema("Parameters schema", &params)?);
+        }
         
-        let mut methods: Vec<_> = self.methods.values().collect();
-        methods.sort_by(|a, b| a.name.cmp(&b.name));
+        if let Some(result) = &method.result {
+            html.push_str("<h4>Result</h4>\n");
+            html.push_str(&format!("<p><code>{}</code>: {}</p>\n",
+                escape_html(&result.schema.schema_type), escape_html(&result.description)));
+            html.push_str(&html_schema("Result schema", &result.schema.to_json_schema())?);
+        }
         
-        for method in methods {
-            html.push_str(&format!("<div class=\"method\">\n"));
-            html.push_str(&format!("<h3>{}</h3>\n", method.name));
-            html.push_str(&format!("<p>{}</p>\n", method.description));
-            
-            if !method.parameters.is_empty() {
-                html.push_str("<h4>Parameters</h4>\n<ul>\n");
-                for param in &method.parameters {
-                    let required = if param.required { " (required)" } else { " (optional)" };
-                    html.push_str(&format!("<li><code>{}</code> ({}){}: {}</li>\n", 
-                        param.name, param.schema.schema_type, required, param.description));
-                }
-                html.push_str("</ul>\n");
+        if !method.errors.is_empty() {
+            html.push_str("<h4>Errors</h4>\n<ul>\n");
+            for error in &method.errors {
+                html.push_str(&error_reference::html_error_link(error, errors));
             }
-            
-            html.push_str("</div>\n");
+            html.push_str("</ul>\n");
         }
         
-        html.push_str("</body>\n</html>");
+        if self.config.include_examples {
+            html.push_str("<details class=\"snippets\">\n<summary>cURL and wscat</summary>\n");
+            for snippet in self.snippets(method) {
+                html.push_str(&format!("<p>{}</p>\n<pre>{}</pre>\n<pre>{}</pre>\n",
+                    escape_html(&snippet.title), escape_html(&snippet.curl), escape_html(&snippet.wscat)));
+            }
+            html.push_str("</details>\n");
+        }
         
+        let request = serde_json::to_string_pretty(&collections::example_request(method))?;
+        html.push_str("<details class=\"try-it\">\n<summary>Try it</summary>\n");
+        html.push_str(&format!("<textarea class=\"request\" rows=\"{}\" spellcheck=\"false\" aria-label=\"Request\">{}</textarea>\n",
+            request.lines().count() + 1, escape_html(&request)));
+        html.push_str("<button type=\"button\" class=\"send\">Send</button>\n");
+        html.push_str("<pre class=\"response\" hidden></pre>\n</details>\n");
+        
+        html.push_str("</div>\n");
         Ok(html)
     }
 
+    /// HTML index linking to one page per method, with a shared stylesheet
+    fn html_index(&self) -> String {
+        let mut body = self.html_intro();
+        body.push_str("<h2>Methods</h2>\n<ul>\n");
+        for method in self.sorted_methods() {
+            body.push_str(&format!("<li><a href=\"methods/{}.html\"><code>{}</code></a>: {}</li>\n",
+                file_stem(&method.name), escape_html(&method.name), escape_html(&method.summary)));
+        }
+        body.push_str("</ul>\n");
+        body.push_str("<p>See the <a href=\"errors.html\">error reference</a> for every error code.</p>\n");
+        
+        let style = "<link rel=\"stylesheet\" href=\"docs.css\">\n";
+        self.html_document(&self.config.title, style, &body)
+    }
+
+    /// Error reference page of the split layout
+    fn html_errors_page(&self) -> Result<String> {
+        let body = format!("<p><a href=\"index.html\">{}</a></p>\n{}",
+            escape_html(&self.config.title), self.html_error_reference("methods/{}.html")?);
+        let title = format!("Errors - {}", self.config.title);
+        Ok(self.html_document(&title, "<link rel=\"stylesheet\" href=\"docs.css\">\n", &body))
+    }
+
+    /// Stand-alone HTML page for one method of the split layout
+    fn html_method_page(&self, method: &MethodDocumentation) -> Result<String> {
+        let body = format!("<p><a href=\"../index.html\">{}</a></p>\n{}{}",
+            escape_html(&self.config.title), self.html_toolbar(false), self.html_method(method, "../errors.html")?);
+        let title = format!("{} - {}", method.name, self.config.title);
+        let head = "<link rel=\"stylesheet\" href=\"../docs.css\">\n<script src=\"../docs.js\" defer></script>\n";
+        Ok(self.html_document(&title, head, &body))
+    }
+
+    /// Methods in name order
+    fn sorted_methods(&self) -> Vec<&MethodDocumentation> {
+        let mut methods: Vec<_> = self.methods.values().collect();
+        methods.sort_by(|a, b| a.name.cmp(&b.name));
+        methods
+    }
+
+    /// Subscription channels in name order
+    fn sorted_subscriptions(&self) -> Vec<&SubscriptionDocumentation> {
+        let mut subscriptions: Vec<_> = self.subscriptions.values().collect();
+        subscriptions.sort_by(|a, b| a.name.cmp(&b.name));
+        subscriptions
+    }
+
     /// Generate JSON documentation
     fn generate_json(&self) -> Result<String> {
         let doc = json!({
@@ -847,12 +1437,202 @@ impl DocumentationGenerator {
         self.schemas.get(name)
     }
 
-    /// Export to file (mock implementation)
-    pub fn export_to_file(&self, _filename: &str) -> Result<()> {
-        // In a real implementation, this would write to a file
-        let _content = self.generate()?;
-        Ok(())
+    /// Write the configured output format to a single file
+    pub fn export_to_file(&self, filename: impl AsRef<Path>) -> Result<()> {
+        write_file(filename.as_ref(), &self.generate()?)
+    }
+
+    /// Export documentation under `dir`, returning the paths written
+    /// relative to it
+    ///
+    /// Each format has its own place in the layout:
+    ///
+    /// ```text
+    /// openrpc.json                    OpenRpc
+    /// openapi.json                    OpenApi
+    /// api.json                        Json
+    /// asyncapi.json                   AsyncApi
+    /// postman_collection.json         Postman
+    /// insomnia.json                   Insomnia
+    /// html/index.html                 Html: method index, with html/docs.css
+    ///                                   and the try-it script html/docs.js
+    /// html/methods/<method>.html        and one page per method
+    /// html/errors.html                  and the error reference
+    /// markdown/README.md              Markdown: method index
+    /// markdown/methods/<method>.md      and one file per method, or the
+    ///                                   whole document in README.md
+    /// markdown/errors.md              Markdown or ErrorReference: error
+    ///                                   reference the methods link to
+    /// bundle.html                     single self-contained page, if bundled
+    /// clients/typescript/client.ts    generated clients, if requested
+    /// clients/rust/client.rs
+    /// versions/compatibility.md       method availability per protocol
+    ///                                   version, if versions are requested
+    /// versions/<version>/...            and the layout above per version
+    /// locales/<locale>/...            Markdown and HTML of each translation
+    /// ```
+    pub fn export_to_dir(&self, dir: impl AsRef<Path>, options: &ExportOptions) -> Result<Vec<PathBuf>> {
+        let dir = dir.as_ref();
+        let mut written = Vec::new();
+        let mut write = |relative: PathBuf, content: &str| -> Result<()> {
+            write_file(&dir.join(&relative), content)?;
+            written.push(relative);
+            Ok(())
+        };
+
+        for format in &options.formats {
+            match format {
+                DocumentationFormat::OpenRpc => write("openrpc.json".into(), &self.generate_openrpc()?)?,
+                DocumentationFormat::OpenApi => write("openapi.json".into(), &self.generate_openapi()?)?,
+                DocumentationFormat::Json => write("api.json".into(), &self.generate_json()?)?,
+                DocumentationFormat::AsyncApi => write("asyncapi.json".into(), &self.generate_asyncapi()?)?,
+                DocumentationFormat::Postman => write("postman_collection.json".into(), &self.generate_postman()?)?,
+                DocumentationFormat::Insomnia => write("insomnia.json".into(), &self.generate_insomnia()?)?,
+                DocumentationFormat::Html => {
+                    write("html/docs.css".into(), DOCS_CSS)?;
+                    write("html/docs.js".into(), DOCS_JS)?;
+                    write("html/index.html".into(), &self.html_index())?;
+                    write("html/errors.html".into(), &self.html_errors_page()?)?;
+                    for method in self.sorted_methods() {
+                        let page = format!("html/methods/{}.html", file_stem(&method.name));
+                        write(page.into(), &self.html_method_page(method)?)?;
+                    }
+                }
+                DocumentationFormat::Markdown if options.split_markdown => {
+                    write("markdown/README.md".into(), &self.markdown_index())?;
+                    for method in self.sorted_methods() {
+                        let page = format!("markdown/methods/{}.md", file_stem(&method.name));
+                        write(page.into(), &self.markdown_method(method, "../errors.md")?)?;
+                    }
+                    write("markdown/errors.md".into(), &self.generate_error_reference()?)?;
+                }
+                DocumentationFormat::Markdown => {
+                    write("markdown/README.md".into(), &self.generate_markdown()?)?;
+                    write("markdown/errors.md".into(), &self.generate_error_reference()?)?;
+                }
+                // Markdown exports include the error reference their
+                // methods link to
+                DocumentationFormat::ErrorReference if options.formats.contains(&DocumentationFormat::Markdown) => {}
+                DocumentationFormat::ErrorReference => {
+                    write("markdown/errors.md".into(), &self.generate_error_reference()?)?;
+                }
+            }
+        }
+
+        if options.bundle {
+            write("bundle.html".into(), &self.generate_html()?)?;
+        }
+        if options.clients {
+            for (dir, language) in [("typescript", ClientLanguage::TypeScript), ("rust", ClientLanguage::Rust)] {
+                let path = format!("clients/{}/{}", dir, language.file_name());
+                write(path.into(), &self.generate_client(language))?;
+            }
+        }
+        if options.versions {
+            write("versions/compatibility.md".into(), &self.compatibility_matrix())?;
+            let per_version = ExportOptions { versions: false, ..options.clone() };
+            for version in self.versions() {
+                let relative = Path::new("versions").join(&version);
+                for path in self.for_version(&version).export_to_dir(dir.join(&relative), &per_version)? {
+                    written.push(relative.join(path));
+                }
+            }
+        }
+        
+        // Translated Markdown and HTML, each locale in its own directory
+        let localized = ExportOptions {
+            formats: options.formats.iter()
+                .filter(|format| matches!(format, DocumentationFormat::Markdown | DocumentationFormat::Html))
+                .cloned()
+                .collect(),
+            clients: false,
+            versions: false,
+            ..options.clone()
+        };
+        if !localized.formats.is_empty() || localized.bundle {
+            for locale in self.locales().iter().filter(|locale| *locale != i18n::DEFAULT_LOCALE) {
+                let relative = Path::new("locales").join(locale);
+                for path in self.for_locale(locale).export_to_dir(dir.join(&relative), &localized)? {
+                    written.push(relative.join(path));
+                }
+            }
+        }
+        Ok(written)
+    }
+}
+
+/// Mismatches between example params, named or positional, and the
+/// method's parameters
+fn example_params_violations(validator: &SchemaValidator, method: &MethodDocumentation, params: &Value) -> Vec<SchemaViolation> {
+    let violation = |path: String, reason: &str| SchemaViolation { path, reason: reason.to_string() };
+    let mut violations = Vec::new();
+    let mut check = |param: &ParameterDoc, value: Option<&Value>, path: String| {
+        match value.filter(|value| !value.is_null()) {
+            Some(value) => violations.extend(validator.validate(&param.schema, value, &path).err()),
+            None if param.required => violations.push(violation(path, "is required")),
+            None => {}
+        }
+    };
+
+    match params {
+        Value::Object(named) => {
+            for param in &method.parameters {
+                check(param, named.get(&param.name), format!("params.{}", param.name));
+            }
+            for name in named.keys().filter(|name| !method.parameters.iter().any(|param| &param.name == *name)) {
+                violations.push(violation(format!("params.{}", name), "is not a documented parameter"));
+            }
+        }
+        Value::Array(positional) => {
+            for (index, param) in method.parameters.iter().enumerate() {
+                check(param, positional.get(index), format!("params[{}]", index));
+            }
+            for index in method.parameters.len()..positional.len() {
+                violations.push(violation(format!("params[{}]", index), "is not a documented parameter"));
+            }
+        }
+        _ => violations.push(violation("params".to_string(), "must be an object or array")),
+    }
+    violations
+}
+
+/// Collapsible block showing a JSON schema
+fn html_schema(title: &str, schema: &Value) -> Result<String> {
+    Ok(format!("<details class=\"schema\">\n<summary>{}</summary>\n<pre>{}</pre>\n</details>\n",
+        title, escape_html(&serde_json::to_string_pretty(schema)?)))
+}
+
+/// Escape text for use in HTML content and attribute values
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Write `content` to `path`, creating missing parent directories
+fn write_file(path: &Path, content: &str) -> Result<()> {
+    let io_error = |e: std::io::Error| DocumentationError::IoError(format!("{}: {}", path.display(), e));
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(io_error)?;
     }
+    fs::write(path, content).map_err(io_error)
+}
+
+/// File name for a method's page, keeping only characters safe on every
+/// filesystem
+fn file_stem(method: &str) -> String {
+    method.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect()
 }
 
 impl Default for DocumentationGenerator {
@@ -861,6 +1641,54 @@ impl Default for DocumentationGenerator {
     }
 }
 
+impl SchemaDoc {
+    /// Render as a standard JSON Schema object, omitting unset keywords
+    pub fn to_json_schema(&self) -> Value {
+        let mut schema = serde_json::Map::new();
+        schema.insert("type".to_string(), json!(self.schema_type));
+        if let Some(format) = &self.format {
+            schema.insert("format".to_string(), json!(format));
+        }
+        if let Some(description) = &self.description {
+            schema.insert("description".to_string(), json!(description));
+        }
+        if let Some(properties) = &self.properties {
+            let properties: serde_json::Map<String, Value> = properties.iter()
+                .map(|(name, property)| (name.clone(), property.to_json_schema()))
+                .collect();
+            schema.insert("properties".to_string(), Value::Object(properties));
+        }
+        if let Some(items) = &self.items {
+            schema.insert("items".to_string(), items.to_json_schema());
+        }
+        if let Some(required) = &self.required {
+            schema.insert("required".to_string(), json!(required));
+        }
+        if let Some(example) = &self.example {
+            schema.insert("examples".to_string(), json!([example]));
+        }
+        if let Some(enum_values) = &self.enum_values {
+            schema.insert("enum".to_string(), json!(enum_values));
+        }
+        if let Some(minimum) = self.minimum {
+            schema.insert("minimum".to_string(), json!(minimum));
+        }
+        if let Some(maximum) = self.maximum {
+            schema.insert("maximum".to_string(), json!(maximum));
+        }
+        if let Some(min_length) = self.min_length {
+            schema.insert("minLength".to_string(), json!(min_length));
+        }
+        if let Some(max_length) = self.max_length {
+            schema.insert("maxLength".to_string(), json!(max_length));
+        }
+        if let Some(pattern) = &self.pattern {
+            schema.insert("pattern".to_string(), json!(pattern));
+        }
+        Value::Object(schema)
+    }
+}
+
 impl Default for SchemaDoc {
     fn default() -> Self {
         Self {
@@ -876,6 +1704,7 @@ impl Default for SchemaDoc {
             maximum: None,
             min_length: None,
             max_length: None,
+            pattern: None,
         }
     }
 }
@@ -940,6 +1769,28 @@ mod tests {
         assert!(openrpc.contains("cc_ping"));
     }
 
+    #[test]
+    fn test_openrpc_is_deterministic() {
+        let spec = |generator: &DocumentationGenerator| generator.generate_openrpc().unwrap();
+        let generator = DocumentationGenerator::new();
+        assert_eq!(spec(&generator), spec(&DocumentationGenerator::new()));
+        
+        let parsed: Value = serde_json::from_str(&spec(&generator)).unwrap();
+        let names: Vec<_> = parsed["methods"].as_array().unwrap().iter()
+            .map(|method| method["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["cc_getBlockByHeight", "cc_getLatestBlock", "cc_ping"]);
+        assert_eq!(parsed["methods"][1]["result"]["schema"], json!({"$ref": "#/components/schemas/Block"}));
+        assert_eq!(parsed["components"]["schemas"]["Block"]["schema_type"], "object");
+        
+        let inline = DocumentationGenerator::with_config(DocumentationConfig {
+            include_schemas: false,
+            ..DocumentationConfig::default()
+        });
+        let parsed: Value = serde_json::from_str(&spec(&inline)).unwrap();
+        assert_eq!(parsed["methods"][1]["result"]["schema"]["schema_type"], "object");
+    }
+
     #[test]
     fn test_markdown_generation() {
         let mut config = DocumentationConfig::default();
@@ -979,6 +1830,79 @@ mod tests {
         assert!(html.contains("<h1>CC Chain RPC API</h1>"));
     }
 
+    #[test]
+    fn test_interactive_html() {
+        let mut generator = DocumentationGenerator::new();
+        let mut method = generator.get_method("cc_ping").unwrap().clone();
+        method.name = "cc_echo".to_string();
+        method.description = "Echoes <b>\"text\"</b>".to_string();
+        generator.add_method(method);
+        let html = generator.generate_html().unwrap();
+        
+        assert!(html.contains("<input id=\"endpoint\" type=\"url\" value=\"http://localhost:8545\">"));
+        assert!(html.contains("<input id=\"search\""));
+        assert!(html.contains("<div class=\"method\" id=\"cc_getBlockByHeight\" data-search=\"cc_getblockbyheight get block by height blockchain blocks\">"));
+        assert!(html.contains("<summary>Result schema</summary>"));
+        assert!(html.contains("&quot;method&quot;: &quot;cc_getBlockByHeight&quot;"));
+        assert!(html.contains("<p>Echoes &lt;b&gt;&quot;text&quot;&lt;/b&gt;</p>"));
+        assert!(html.contains(DOCS_JS));
+    }
+
+    #[test]
+    fn test_asyncapi_generation() {
+        let config = DocumentationConfig {
+            output_format: DocumentationFormat::AsyncApi,
+            ..DocumentationConfig::default()
+        };
+        
+        let generator = DocumentationGenerator::with_config(config);
+        let spec: Value = serde_json::from_str(&generator.generate().unwrap()).unwrap();
+        
+        assert_eq!(spec["asyncapi"], "2.6.0");
+        assert_eq!(spec["servers"]["server0"]["url"], "ws://localhost:8545");
+        for channel in ["newHeads", "pendingTransactions", "logs"] {
+            let reference = &spec["channels"][channel]["subscribe"]["message"]["$ref"];
+            assert_eq!(reference, &json!(format!("#/components/messages/{}", channel)));
+        }
+        
+        let logs = &spec["components"]["messages"]["logs"]["payload"]["properties"]["params"];
+        assert_eq!(logs["properties"]["result"]["required"], json!(["address", "topics"]));
+        let request = &spec["components"]["messages"]["logsRequest"]["payload"]["properties"]["params"];
+        assert_eq!(request["items"][0]["enum"], json!(["logs"]));
+        assert_eq!(request["items"][1]["type"], "object");
+    }
+
+    #[test]
+    fn test_examples_match_schemas() {
+        let mut generator = DocumentationGenerator::new();
+        assert!(generator.validate().is_ok());
+        
+        let mut method = generator.get_method("cc_getBlockByHeight").unwrap().clone();
+        method.examples[0].params = Some(json!({"height": -1, "full": true}));
+        method.examples[0].result = Some(json!({"height": 12345, "hash": 7, "timestamp": 1}));
+        method.parameters[0].example = Some(json!("12345"));
+        generator.add_method(method);
+        
+        let error = generator.generate().unwrap_err().to_string();
+        let expected = [
+            "cc_getBlockByHeight parameter example: 'params.height' must be of type integer",
+            "cc_getBlockByHeight example 'Get block by height': 'params.height' must be at least 0",
+            "cc_getBlockByHeight example 'Get block by height': 'params.full' is not a documented parameter",
+            "cc_getBlockByHeight example 'Get block by height': 'result.hash' must be of type string",
+        ];
+        for problem in expected {
+            assert!(error.contains(problem), "{}", error);
+        }
+        
+        let mut method = generator.get_method("cc_getBlockByHeight").unwrap().clone();
+        method.parameters[0].example = None;
+        method.examples[0].params = Some(json!([]));
+        method.examples[0].result = None;
+        generator.add_method(method);
+        let error = generator.validate().unwrap_err().to_string();
+        assert!(error.ends_with("'params[0]' is required"), "{}", error);
+    }
+
     #[test]
     fn test_schema_creation() {
         let generator = DocumentationGenerator::new();
@@ -1015,6 +1939,17 @@ mod tests {
         assert!(method_names.contains(&"cc_getLatestBlock".to_string()));
     }
 
+    #[test]
+    fn test_json_schema_rendering() {
+        let generator = DocumentationGenerator::new();
+        let schema = generator.get_schema("Block").unwrap().to_json_schema();
+        
+        assert_eq!(schema["type"], "object");
+        assert_eq!(schema["properties"]["hash"]["minLength"], 66);
+        assert_eq!(schema["properties"]["height"]["format"], "uint64");
+        assert!(schema.get("pattern").is_none());
+    }
+
     #[test]
     fn test_schema_retrieval() {
         let generator = DocumentationGenerator::new();
@@ -1059,7 +1994,98 @@ mod tests {
     #[test]
     fn test_export_to_file() {
         let generator = DocumentationGenerator::new();
-        let result = generator.export_to_file("test.json");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested/test.json");
+        let result = generator.export_to_file(&path);
         assert!(result.is_ok());
+        
+        let written = fs::read_to_string(&path).unwrap();
+        assert_eq!(written, generator.generate().unwrap());
+    }
+
+    #[test]
+    fn test_export_to_dir() {
+        let generator = DocumentationGenerator::new();
+        let dir = tempfile::tempdir().unwrap();
+        let options = ExportOptions { bundle: true, clients: true, ..ExportOptions::default() };
+        let written = generator.export_to_dir(dir.path(), &options).unwrap();
+        
+        for file in ["openrpc.json", "openapi.json", "api.json", "asyncapi.json", "postman_collection.json", "insomnia.json",
+                     "html/index.html", "html/docs.css", "html/docs.js", "html/errors.html", "markdown/errors.md",
+                     "html/methods/cc_ping.html", "markdown/README.md", "markdown/methods/cc_ping.md", "bundle.html",
+                     "clients/typescript/client.ts", "clients/rust/client.rs"] {
+            assert!(written.contains(&PathBuf::from(file)), "{}", file);
+            assert!(dir.path().join(file).is_file(), "{}", file);
+        }
+        
+        let index = fs::read_to_string(dir.path().join("html/index.html")).unwrap();
+        assert!(index.contains("href=\"methods/cc_getBlockByHeight.html\""));
+        let readme = fs::read_to_string(dir.path().join("markdown/README.md")).unwrap();
+        assert!(readme.contains("(methods/cc_ping.md)"));
+        let ping = fs::read_to_string(dir.path().join("markdown/methods/cc_ping.md")).unwrap();
+        assert!(ping.starts_with("### cc_ping"));
+        let bundle = fs::read_to_string(dir.path().join("bundle.html")).unwrap();
+        assert!(bundle.contains("<style>") && bundle.contains("cc_getLatestBlock"));
+    }
+
+    #[test]
+    fn test_export_single_markdown_document() {
+        let generator = DocumentationGenerator::new();
+        let dir = tempfile::tempdir().unwrap();
+        let options = ExportOptions {
+            formats: vec![DocumentationFormat::Markdown],
+            split_markdown: false,
+            bundle: false,
+            clients: false,
+            versions: false,
+        };
+        let written = generator.export_to_dir(dir.path(), &options).unwrap();
+        
+        assert_eq!(written, vec![PathBuf::from("markdown/README.md"), PathBuf::from("markdown/errors.md")]);
+        let readme = fs::read_to_string(dir.path().join("markdown/README.md")).unwrap();
+        assert!(readme.contains("### cc_ping"));
+        assert!(!dir.path().join("markdown/methods").exists());
+    }
+
+    #[test]
+    fn test_export_versions() {
+        let mut generator = DocumentationGenerator::new();
+        let mut method = generator.get_method("cc_ping").unwrap().clone();
+        method.name = "cc_getPeers".to_string();
+        method.since_version = "1.1.0".to_string();
+        generator.add_method(method);
+        let dir = tempfile::tempdir().unwrap();
+        let options = ExportOptions {
+            formats: vec![DocumentationFormat::OpenRpc],
+            versions: true,
+            ..ExportOptions::default()
+        };
+        let written = generator.export_to_dir(dir.path(), &options).unwrap();
+        
+        assert_eq!(written, ["openrpc.json", "versions/compatibility.md", "versions/1.0.0/openrpc.json",
+            "versions/1.1.0/openrpc.json"].map(PathBuf::from));
+        let old = fs::read_to_string(dir.path().join("versions/1.0.0/openrpc.json")).unwrap();
+        assert!(old.contains("cc_ping") && !old.contains("cc_getPeers"));
+    }
+
+    #[test]
+    fn test_export_locales() {
+        let mut generator = DocumentationGenerator::new();
+        generator.add_translation("es", Translation {
+            title: Some("API RPC de CC Chain".to_string()),
+            ..Translation::default()
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let options = ExportOptions {
+            formats: vec![DocumentationFormat::OpenRpc, DocumentationFormat::Markdown],
+            split_markdown: false,
+            ..ExportOptions::default()
+        };
+        let written = generator.export_to_dir(dir.path(), &options).unwrap();
+        
+        assert_eq!(written, ["openrpc.json", "markdown/README.md", "markdown/errors.md", "locales/es/markdown/README.md",
+            "locales/es/markdown/errors.md"].map(PathBuf::from));
+        let readme = fs::read_to_string(dir.path().join("locales/es/markdown/README.md")).unwrap();
+        assert!(readme.starts_with("# API RPC de CC Chain"));
     }
 }
@@ -0,0 +1,353 @@
+//! Documentation linting
+//!
+//! Rules flag documentation that is valid but unhelpful, like a parameter
+//! nobody shows a value for. Each rule reports at the severity configured
+//! in `DocumentationConfig::lint`; `generate` fails on error-level
+//! findings, so CI can hold the documentation to a chosen standard.
+
+use crate::{DocumentationError, DocumentationGenerator, MethodDocumentation, Result, SchemaDoc};
+use std::collections::HashMap;
+use std::fmt;
+
+/// A documentation lint rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LintRule {
+    /// A method, parameter, result or error without a description
+    MissingDescription,
+    /// A parameter no example gives a value for
+    ParamWithoutExample,
+    /// An enum schema listing no values
+    EnumWithoutValues,
+    /// A deprecated method not saying what replaces it
+    DeprecatedWithoutReplacement,
+}
+
+impl LintRule {
+    /// Every rule
+    pub const ALL: [LintRule; 4] = [
+        LintRule::MissingDescription,
+        LintRule::ParamWithoutExample,
+        LintRule::EnumWithoutValues,
+        LintRule::DeprecatedWithoutReplacement,
+    ];
+
+    /// Name of the rule in reports
+    pub fn name(&self) -> &'static str {
+        match self {
+            LintRule::MissingDescription => "missing-description",
+            LintRule::ParamWithoutExample => "param-without-example",
+            LintRule::EnumWithoutValues => "enum-without-values",
+            LintRule::DeprecatedWithoutReplacement => "deprecated-without-replacement",
+        }
+    }
+}
+
+/// How much a lint finding matters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LintSeverity {
+    /// The rule is not checked
+    Off,
+    Warning,
+    /// The finding fails generation
+    Error,
+}
+
+/// Severity of each rule; rules not listed are warnings
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    pub rules: HashMap<LintRule, LintSeverity>,
+}
+
+impl LintConfig {
+    /// Every rule at `severity`
+    pub fn all(severity: LintSeverity) -> Self {
+        Self {
+            rules: LintRule::ALL
+                .into_iter()
+                .map(|rule| (rule, severity))
+                .collect(),
+        }
+    }
+
+    /// Set the severity of `rule`
+    pub fn with(mut self, rule: LintRule, severity: LintSeverity) -> Self {
+        self.rules.insert(rule, severity);
+        self
+    }
+
+    pub fn severity(&self, rule: LintRule) -> LintSeverity {
+        self.rules
+            .get(&rule)
+            .copied()
+            .unwrap_or(LintSeverity::Warning)
+    }
+}
+
+/// One lint finding
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintViolation {
+    pub rule: LintRule,
+    pub severity: LintSeverity,
+    pub method: String,
+    /// Where in the method, e.g. `params.height`; empty for the method itself
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for LintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            LintSeverity::Error => "error",
+            _ => "warning",
+        };
+        write!(f, "{}[{}] {}", severity, self.rule.name(), self.method)?;
+        if !self.path.is_empty() {
+            write!(f, " {}", self.path)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Findings of a lint pass, in method order
+#[derive(Debug, Clone, Default)]
+pub struct LintReport {
+    pub violations: Vec<LintViolation>,
+}
+
+impl LintReport {
+    pub fn errors(&self) -> impl Iterator<Item = &LintViolation> {
+        self.violations
+            .iter()
+            .filter(|violation| violation.severity == LintSeverity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &LintViolation> {
+        self.violations
+            .iter()
+            .filter(|violation| violation.severity == LintSeverity::Warning)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    /// `Err` listing every error-level finding, if there are any
+    pub fn into_result(self) -> Result<()> {
+        if !self.has_errors() {
+            return Ok(());
+        }
+        let errors: Vec<String> = self.errors().map(ToString::to_string).collect();
+        Err(DocumentationError::ValidationError(errors.join("; ")))
+    }
+}
+
+impl DocumentationGenerator {
+    /// Check every method against the configured lint rules
+    pub fn lint(&self) -> LintReport {
+        let config = &self.config.lint;
+        let mut report = LintReport::default();
+        for method in self.sorted_methods() {
+            let mut flag = |rule: LintRule, path: String, message: &str| {
+                let severity = config.severity(rule);
+                if severity != LintSeverity::Off {
+                    report.violations.push(LintViolation {
+                        rule,
+                        severity,
+                        method: method.name.clone(),
+                        path,
+                        message: message.to_string(),
+                    });
+                }
+            };
+
+            if method.description.trim().is_empty() {
+                flag(
+                    LintRule::MissingDescription,
+                    String::new(),
+                    "has no description",
+                );
+            }
+            for param in &method.parameters {
+                let path = format!("params.{}", param.name);
+                if param.description.trim().is_empty() {
+                    flag(
+                        LintRule::MissingDescription,
+                        path.clone(),
+                        "has no description",
+                    );
+                }
+                if !has_example(method, &param.name) && param.schema.example.is_none() {
+                    flag(
+                        LintRule::ParamWithoutExample,
+                        path.clone(),
+                        "has no example value",
+                    );
+                }
+                for enum_path in empty_enums(&param.schema, &path) {
+                    flag(
+                        LintRule::EnumWithoutValues,
+                        enum_path,
+                        "is an enum without values",
+                    );
+                }
+            }
+            if let Some(result) = &method.result {
+                if result.description.trim().is_empty() {
+                    flag(
+                        LintRule::MissingDescription,
+                        "result".to_string(),
+                        "has no description",
+                    );
+                }
+                for enum_path in empty_enums(&result.schema, "result") {
+                    flag(
+                        LintRule::EnumWithoutValues,
+                        enum_path,
+                        "is an enum without values",
+                    );
+                }
+            }
+            for error in &method.errors {
+                if error.description.trim().is_empty() {
+                    let path = format!("errors.{}", error.code);
+                    flag(LintRule::MissingDescription, path, "has no description");
+                }
+            }
+            if method.deprecated && !names_replacement(method, self) {
+                flag(
+                    LintRule::DeprecatedWithoutReplacement,
+                    String::new(),
+                    "is deprecated without naming a replacement",
+                );
+            }
+        }
+        report
+    }
+}
+
+/// Whether the param or an example of the method gives `param` a value
+fn has_example(method: &MethodDocumentation, param: &str) -> bool {
+    let documented = method
+        .parameters
+        .iter()
+        .any(|doc| doc.name == param && doc.example.is_some());
+    documented
+        || method.examples.iter().any(|example| {
+            example
+                .params
+                .as_ref()
+                .and_then(|params| params.get(param))
+                .is_some()
+        })
+}
+
+/// Paths of the enums in `schema` that list no values
+fn empty_enums(schema: &SchemaDoc, path: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    if schema.enum_values.as_ref().is_some_and(Vec::is_empty) {
+        paths.push(path.to_string());
+    }
+    let mut properties: Vec<_> = schema.properties.iter().flatten().collect();
+    properties.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, property) in properties {
+        paths.extend(empty_enums(property, &format!("{path}.{name}")));
+    }
+    if let Some(items) = &schema.items {
+        paths.extend(empty_enums(items, &format!("{path}[]")));
+    }
+    paths
+}
+
+/// Whether a deprecated method's text points to what to use instead: another
+/// documented method, or wording like "use ... instead"
+fn names_replacement(method: &MethodDocumentation, generator: &DocumentationGenerator) -> bool {
+    let text = format!("{} {}", method.summary, method.description);
+    let lower = text.to_lowercase();
+    lower.contains("instead")
+        || lower.contains("replaced by")
+        || generator
+            .methods
+            .keys()
+            .any(|name| *name != method.name && text.contains(name.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DocumentationConfig, ParameterDoc};
+    use serde_json::json;
+
+    fn generator(lint: LintConfig) -> DocumentationGenerator {
+        let mut generator = DocumentationGenerator::with_config(DocumentationConfig {
+            lint,
+            ..DocumentationConfig::default()
+        });
+        let mut method = generator.get_method("cc_ping").unwrap().clone();
+        method.name = "cc_getStatus".to_string();
+        method.description = "Node status".to_string();
+        method.deprecated = true;
+        method.parameters.push(ParameterDoc {
+            name: "detail".to_string(),
+            description: String::new(),
+            schema: SchemaDoc {
+                schema_type: "string".to_string(),
+                enum_values: Some(vec![]),
+                ..SchemaDoc::default()
+            },
+            required: false,
+            example: None,
+        });
+        generator.add_method(method);
+        generator
+    }
+
+    #[test]
+    fn test_lint_rules() {
+        let report = generator(LintConfig::default()).lint();
+        let status: Vec<String> = report
+            .violations
+            .iter()
+            .filter(|violation| violation.method == "cc_getStatus")
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            status,
+            [
+                "warning[missing-description] cc_getStatus params.detail: has no description",
+                "warning[param-without-example] cc_getStatus params.detail: has no example value",
+                "warning[enum-without-values] cc_getStatus params.detail: is an enum without values",
+                "warning[deprecated-without-replacement] cc_getStatus: is deprecated without naming a replacement",
+            ]
+        );
+        assert!(!report.has_errors());
+
+        let mut generator = generator(LintConfig::default());
+        let mut method = generator.get_method("cc_getStatus").unwrap().clone();
+        method.description = "Use cc_ping instead".to_string();
+        method.parameters.clear();
+        generator.add_method(method);
+        let report = generator.lint();
+        assert!(report
+            .violations
+            .iter()
+            .all(|violation| violation.method != "cc_getStatus"));
+    }
+
+    #[test]
+    fn test_lint_errors_fail_generation() {
+        let strict = LintConfig::all(LintSeverity::Off)
+            .with(LintRule::EnumWithoutValues, LintSeverity::Error);
+        let generator = generator(strict);
+        let report = generator.lint();
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(report.errors().count(), 1);
+        let error = generator.generate().unwrap_err().to_string();
+        assert!(error.contains("error[enum-without-values] cc_getStatus params.detail"));
+
+        let mut generator = generator;
+        let mut method = generator.get_method("cc_getStatus").unwrap().clone();
+        method.parameters[0].schema.enum_values = Some(vec![json!("full")]);
+        generator.add_method(method);
+        assert!(generator.generate().is_ok());
+    }
+}
@@ -0,0 +1,152 @@
+//! Command-line snippets
+//!
+//! Each documented request is shown as a cURL call to the first server and
+//! a wscat session on its WebSocket endpoint, ready to paste into a shell.
+//! Requests come from the method's examples, or from its parameter
+//! examples when it has none.
+
+use crate::collections::example_request;
+use crate::{DocumentationGenerator, MethodDocumentation};
+use serde_json::{json, Value};
+
+/// Seconds wscat waits for the response before exiting
+const WSCAT_WAIT_SECONDS: u32 = 5;
+
+/// A request shown as a cURL and a wscat command
+pub(crate) struct Snippet {
+    pub(crate) title: String,
+    pub(crate) curl: String,
+    pub(crate) wscat: String,
+}
+
+impl DocumentationGenerator {
+    /// Snippets for every example request of `method`
+    pub(crate) fn snippets(&self, method: &MethodDocumentation) -> Vec<Snippet> {
+        let requests: Vec<(String, Value)> = if method.examples.is_empty() {
+            vec![(method.summary.clone(), example_request(method))]
+        } else {
+            method
+                .examples
+                .iter()
+                .map(|example| {
+                    let mut request = json!({"jsonrpc": "2.0", "method": method.name, "id": 1});
+                    if let Some(params) = &example.params {
+                        request["params"] = params.clone();
+                    }
+                    (example.summary.clone(), request)
+                })
+                .collect()
+        };
+
+        let http = self
+            .config
+            .servers
+            .iter()
+            .map(|server| server.url.as_str())
+            .find(|url| url.starts_with("http"))
+            .unwrap_or("http://localhost:8545");
+        let ws = self
+            .config
+            .servers
+            .iter()
+            .map(|server| server.url.as_str())
+            .find(|url| url.starts_with("ws"))
+            .map_or_else(|| websocket_url(http), str::to_string);
+
+        requests
+            .into_iter()
+            .map(|(title, request)| {
+                let body = shell_quote(&request.to_string());
+                Snippet {
+                    title,
+                    curl: format!(
+                        "curl -X POST {} \\\n  -H 'Content-Type: application/json' \\\n  -d {}",
+                        shell_quote(http),
+                        body
+                    ),
+                    wscat: format!(
+                        "wscat -c {} -w {} -x {}",
+                        shell_quote(&ws),
+                        WSCAT_WAIT_SECONDS,
+                        body
+                    ),
+                }
+            })
+            .collect()
+    }
+}
+
+/// WebSocket URL served alongside an HTTP one
+pub(crate) fn websocket_url(url: &str) -> String {
+    match url.strip_prefix("http") {
+        Some(rest) => format!("ws{rest}"),
+        None => url.to_string(),
+    }
+}
+
+/// `text` as a single POSIX shell word
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DocumentationConfig, DocumentationFormat, ServerInfo};
+
+    #[test]
+    fn test_snippets_use_examples_and_servers() {
+        let generator = DocumentationGenerator::new();
+        let method = generator.get_method("cc_getBlockByHeight").unwrap();
+        let snippets = generator.snippets(method);
+        assert_eq!(snippets.len(), 1);
+        assert_eq!(
+            snippets[0].curl,
+            "curl -X POST 'http://localhost:8545' \\\n  -H 'Content-Type: application/json' \\\n  \
+             -d '{\"id\":1,\"jsonrpc\":\"2.0\",\"method\":\"cc_getBlockByHeight\",\"params\":{\"height\":12345}}'"
+        );
+        assert!(snippets[0]
+            .wscat
+            .starts_with("wscat -c 'ws://localhost:8545' -w 5 -x '{\"id\":1,"));
+
+        let generator = DocumentationGenerator::with_config(DocumentationConfig {
+            servers: vec![
+                ServerInfo {
+                    url: "https://rpc.example.com".to_string(),
+                    description: String::new(),
+                },
+                ServerInfo {
+                    url: "wss://rpc.example.com/ws".to_string(),
+                    description: String::new(),
+                },
+            ],
+            ..DocumentationConfig::default()
+        });
+        let mut method = generator.get_method("cc_ping").unwrap().clone();
+        method.examples.clear();
+        method.summary = "It's alive".to_string();
+        let snippet = &generator.snippets(&method)[0];
+        assert_eq!(snippet.title, "It's alive");
+        assert!(snippet
+            .curl
+            .starts_with("curl -X POST 'https://rpc.example.com'"));
+        assert!(snippet
+            .wscat
+            .starts_with("wscat -c 'wss://rpc.example.com/ws'"));
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+
+    #[test]
+    fn test_snippets_in_documents() {
+        let mut generator = DocumentationGenerator::new();
+        generator.config.output_format = DocumentationFormat::Markdown;
+        let markdown = generator.generate().unwrap();
+        assert!(markdown.contains("```bash\ncurl -X POST 'http://localhost:8545'"));
+        assert!(markdown.contains("```bash\nwscat -c 'ws://localhost:8545' -w 5 -x '{"));
+
+        generator.config.output_format = DocumentationFormat::Html;
+        let html = generator.generate().unwrap();
+        assert!(html.contains("<summary>cURL and wscat</summary>"));
+        assert!(html.contains("<pre>curl -X POST &#39;http://localhost:8545&#39;"));
+    }
+}
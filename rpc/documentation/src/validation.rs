@@ -0,0 +1,276 @@
+//! JSON Schema validation against `SchemaDoc`s
+//!
+//! Used both to enforce call parameters in the protocol layer and to check
+//! the documentation's own examples, so the documented contract is the
+//! enforced one. The supported subset covers what `SchemaDoc` can express:
+//! `type`, `enum`, `minimum`/`maximum`, `minLength`/`maxLength`, `pattern`,
+//! `required`, `properties` and `items`, applied recursively.
+
+use crate::SchemaDoc;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+/// Where and why a value failed its schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// Path to the offending value, e.g. `transaction.outputs[1].amount`
+    pub path: String,
+    pub reason: String,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}' {}", self.path, self.reason)
+    }
+}
+
+impl std::error::Error for SchemaViolation {}
+
+/// Validates values against `SchemaDoc`s, compiling each pattern once
+#[derive(Default)]
+pub struct SchemaValidator {
+    patterns: Mutex<HashMap<String, Regex>>,
+}
+
+impl SchemaValidator {
+    /// Create a validator with an empty pattern cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check `value`, found at `path`, against `schema`
+    pub fn validate(
+        &self,
+        schema: &SchemaDoc,
+        value: &Value,
+        path: &str,
+    ) -> Result<(), SchemaViolation> {
+        let violation = |reason: String| SchemaViolation {
+            path: path.to_string(),
+            reason,
+        };
+
+        if !matches_type(&schema.schema_type, value) {
+            return Err(violation(format!("must be of type {}", schema.schema_type)));
+        }
+        if let Some(allowed) = &schema.enum_values {
+            if !allowed.contains(value) {
+                return Err(violation(format!(
+                    "must be one of {}",
+                    Value::Array(allowed.clone())
+                )));
+            }
+        }
+
+        match value {
+            Value::Number(number) => {
+                let n = number.as_f64().unwrap_or_default();
+                if let Some(minimum) = schema.minimum.filter(|minimum| n < *minimum) {
+                    return Err(violation(format!("must be at least {minimum}")));
+                }
+                if let Some(maximum) = schema.maximum.filter(|maximum| n > *maximum) {
+                    return Err(violation(format!("must be at most {maximum}")));
+                }
+            }
+            Value::String(s) => {
+                // Lengths count characters, as in JSON Schema
+                let length = s.chars().count();
+                if let Some(min) = schema.min_length.filter(|min| length < *min) {
+                    return Err(violation(format!("must be at least {min} characters")));
+                }
+                if let Some(max) = schema.max_length.filter(|max| length > *max) {
+                    return Err(violation(format!("must be at most {max} characters")));
+                }
+                if let Some(pattern) = &schema.pattern {
+                    if !self.is_match(pattern, s).map_err(violation)? {
+                        return Err(violation(format!("must match pattern {pattern}")));
+                    }
+                }
+            }
+            Value::Object(object) => {
+                for name in schema.required.iter().flatten() {
+                    if !object.contains_key(name) {
+                        return Err(SchemaViolation {
+                            path: join(path, name),
+                            reason: "is required".to_string(),
+                        });
+                    }
+                }
+                for (name, property) in schema.properties.iter().flatten() {
+                    if let Some(value) = object.get(name) {
+                        self.validate(property, value, &join(path, name))?;
+                    }
+                }
+            }
+            Value::Array(items) => {
+                if let Some(item_schema) = &schema.items {
+                    for (index, item) in items.iter().enumerate() {
+                        self.validate(item_schema, item, &format!("{path}[{index}]"))?;
+                    }
+                }
+            }
+            Value::Bool(_) | Value::Null => {}
+        }
+        Ok(())
+    }
+
+    /// Whether `pattern` matches anywhere in `s`; patterns are unanchored
+    /// as in JSON Schema
+    fn is_match(&self, pattern: &str, s: &str) -> Result<bool, String> {
+        let mut patterns = self.patterns.lock().unwrap();
+        if let Some(regex) = patterns.get(pattern) {
+            return Ok(regex.is_match(s));
+        }
+        let regex =
+            Regex::new(pattern).map_err(|error| format!("has an invalid pattern: {error}"))?;
+        let matched = regex.is_match(s);
+        patterns.insert(pattern.to_string(), regex);
+        Ok(matched)
+    }
+}
+
+/// Whether `value` has the JSON Schema type `schema_type`; types
+/// `SchemaDoc` uses without a JSON Schema meaning, like `any`, accept
+/// every value
+fn matches_type(schema_type: &str, value: &Value) -> bool {
+    match schema_type {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn join(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{path}.{name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema(schema_type: &str) -> SchemaDoc {
+        SchemaDoc {
+            schema_type: schema_type.to_string(),
+            ..SchemaDoc::default()
+        }
+    }
+
+    fn transaction_schema() -> SchemaDoc {
+        let output = SchemaDoc {
+            required: Some(vec!["address".to_string(), "amount".to_string()]),
+            properties: Some(HashMap::from([
+                (
+                    "address".to_string(),
+                    SchemaDoc {
+                        pattern: Some("^0x[0-9a-f]{4}$".to_string()),
+                        ..schema("string")
+                    },
+                ),
+                (
+                    "amount".to_string(),
+                    SchemaDoc {
+                        minimum: Some(1.0),
+                        ..schema("integer")
+                    },
+                ),
+            ])),
+            ..schema("object")
+        };
+        SchemaDoc {
+            required: Some(vec!["kind".to_string()]),
+            properties: Some(HashMap::from([
+                (
+                    "kind".to_string(),
+                    SchemaDoc {
+                        enum_values: Some(vec![json!("transfer"), json!("stake")]),
+                        ..schema("string")
+                    },
+                ),
+                (
+                    "outputs".to_string(),
+                    SchemaDoc {
+                        items: Some(Box::new(output)),
+                        ..schema("array")
+                    },
+                ),
+            ])),
+            ..schema("object")
+        }
+    }
+
+    #[test]
+    fn test_nested_values() {
+        let validator = SchemaValidator::new();
+        let schema = transaction_schema();
+        let check = |value: Value| validator.validate(&schema, &value, "tx");
+
+        assert!(check(json!({"kind": "stake"})).is_ok());
+        assert!(check(json!({
+            "kind": "transfer",
+            "outputs": [{"address": "0xab12", "amount": 5}]
+        }))
+        .is_ok());
+
+        let cases = [
+            (json!({}), "tx.kind", "is required"),
+            (json!({"kind": "burn"}), "tx.kind", "must be one of"),
+            (json!({"kind": 1}), "tx.kind", "must be of type string"),
+            (
+                json!({"kind": "transfer", "outputs": [{"address": "0xab12", "amount": 0}]}),
+                "tx.outputs[0].amount",
+                "must be at least 1",
+            ),
+            (
+                json!({"kind": "transfer", "outputs": [{"address": "ab12", "amount": 1}]}),
+                "tx.outputs[0].address",
+                "must match pattern",
+            ),
+            (
+                json!({"kind": "transfer", "outputs": [{"address": "0xab12", "amount": 1.5}]}),
+                "tx.outputs[0].amount",
+                "must be of type integer",
+            ),
+        ];
+        for (value, path, reason) in cases {
+            let violation = check(value).unwrap_err();
+            assert_eq!(violation.path, path);
+            assert!(violation.reason.starts_with(reason), "{violation}");
+        }
+    }
+
+    #[test]
+    fn test_string_lengths_and_invalid_patterns() {
+        let validator = SchemaValidator::new();
+        let name = SchemaDoc {
+            min_length: Some(2),
+            max_length: Some(3),
+            ..schema("string")
+        };
+        assert!(validator.validate(&name, &json!("äö"), "name").is_ok());
+        assert!(validator.validate(&name, &json!("a"), "name").is_err());
+        assert!(validator.validate(&name, &json!("abcd"), "name").is_err());
+        assert!(validator.validate(&schema("any"), &json!([1]), "x").is_ok());
+
+        let broken = SchemaDoc {
+            pattern: Some("(".to_string()),
+            ..schema("string")
+        };
+        let violation = validator.validate(&broken, &json!("a"), "x").unwrap_err();
+        assert!(violation.reason.starts_with("has an invalid pattern"));
+    }
+}
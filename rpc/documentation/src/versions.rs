@@ -0,0 +1,197 @@
+//! Versioned documentation
+//!
+//! A method exists from its `since_version` on, so the documentation of an
+//! older protocol version is the current one minus the methods added after
+//! it. Deprecation isn't versioned: a deprecated method is marked so from
+//! the configured version on and documented as current in earlier ones.
+
+use crate::{DocumentationGenerator, Result};
+use std::cmp::Ordering;
+
+/// Availability of a method in one protocol version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Availability {
+    Unavailable,
+    Available,
+    Deprecated,
+}
+
+impl DocumentationGenerator {
+    /// Every protocol version a method was introduced in, plus the
+    /// configured one, oldest first
+    pub fn versions(&self) -> Vec<String> {
+        let mut versions: Vec<String> = self
+            .methods
+            .values()
+            .map(|method| method.since_version.clone())
+            .chain([self.config.version.clone()])
+            .collect();
+        versions.sort_by(|a, b| compare_versions(a, b));
+        versions.dedup();
+        versions
+    }
+
+    /// Documentation of protocol `version`, with only the methods it has
+    pub fn for_version(&self, version: &str) -> DocumentationGenerator {
+        let methods = self
+            .methods
+            .iter()
+            .filter_map(|(name, method)| {
+                let mut method = method.clone();
+                match self.availability(name, version) {
+                    Availability::Unavailable => return None,
+                    Availability::Available => method.deprecated = false,
+                    Availability::Deprecated => {}
+                }
+                Some((name.clone(), method))
+            })
+            .collect();
+
+        let mut config = self.config.clone();
+        config.version = version.to_string();
+        DocumentationGenerator {
+            config,
+            methods,
+            subscriptions: self.subscriptions.clone(),
+            schemas: self.schemas.clone(),
+            translations: self.translations.clone(),
+        }
+    }
+
+    /// Generate the configured format once per protocol version
+    pub fn generate_versions(&self) -> Result<Vec<(String, String)>> {
+        self.versions()
+            .into_iter()
+            .map(|version| {
+                let document = self.for_version(&version).generate()?;
+                Ok((version, document))
+            })
+            .collect()
+    }
+
+    /// Availability of `method` in protocol `version`
+    pub fn availability(&self, method: &str, version: &str) -> Availability {
+        match self.methods.get(method) {
+            Some(method) if compare_versions(&method.since_version, version).is_le() => {
+                let deprecated =
+                    method.deprecated && compare_versions(version, &self.config.version).is_ge();
+                if deprecated {
+                    Availability::Deprecated
+                } else {
+                    Availability::Available
+                }
+            }
+            _ => Availability::Unavailable,
+        }
+    }
+
+    /// Markdown table of which methods exist in which protocol versions
+    pub fn compatibility_matrix(&self) -> String {
+        let versions = self.versions();
+        let mut table = format!("# {} Compatibility\n\n", self.config.title);
+        table.push_str("| Method |");
+        for version in &versions {
+            table.push_str(&format!(" {version} |"));
+        }
+        table.push_str("\n|--------|");
+        table.push_str(&"---|".repeat(versions.len()));
+        table.push('\n');
+
+        for method in self.sorted_methods() {
+            table.push_str(&format!("| `{}` |", method.name));
+            for version in &versions {
+                let cell = match self.availability(&method.name, version) {
+                    Availability::Unavailable => "-",
+                    Availability::Available => "✓",
+                    Availability::Deprecated => "deprecated",
+                };
+                table.push_str(&format!(" {cell} |"));
+            }
+            table.push('\n');
+        }
+        table
+    }
+}
+
+/// Order dotted versions component by component, numerically where both
+/// components are numbers, so `1.10.0` comes after `1.9.0`
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let mut a = a.trim_start_matches('v').split('.');
+    let mut b = b.trim_start_matches('v').split('.');
+    loop {
+        let ordering = match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (Some(_), None) => Ordering::Greater,
+            (None, Some(_)) => Ordering::Less,
+            (Some(a), Some(b)) => match (a.parse::<u64>(), b.parse::<u64>()) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                _ => a.cmp(b),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DocumentationConfig;
+
+    fn generator() -> DocumentationGenerator {
+        let mut generator = DocumentationGenerator::with_config(DocumentationConfig {
+            version: "1.10.0".to_string(),
+            ..DocumentationConfig::default()
+        });
+        let mut method = generator.get_method("cc_ping").unwrap().clone();
+        method.name = "cc_getPeers".to_string();
+        method.since_version = "1.9.0".to_string();
+        generator.add_method(method);
+        let mut method = generator.get_method("cc_ping").unwrap().clone();
+        method.name = "cc_echo".to_string();
+        method.deprecated = true;
+        generator.add_method(method);
+        generator
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("1.10.0", "1.9.0"), Ordering::Greater);
+        assert_eq!(compare_versions("v1.0", "1.0"), Ordering::Equal);
+        assert_eq!(compare_versions("1.0", "1.0.1"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_versioned_documents() {
+        let generator = generator();
+        assert_eq!(generator.versions(), ["1.0.0", "1.9.0", "1.10.0"]);
+
+        let old = generator.for_version("1.0.0");
+        assert!(old.get_method("cc_getPeers").is_none());
+        assert!(!old.get_method("cc_echo").unwrap().deprecated);
+        assert!(generator
+            .for_version("1.9.0")
+            .get_method("cc_getPeers")
+            .is_some());
+
+        let documents = generator.generate_versions().unwrap();
+        assert_eq!(documents.len(), 3);
+        assert!(documents[0].1.contains("\"version\": \"1.0.0\""));
+        assert!(!documents[0].1.contains("cc_getPeers"));
+        assert!(documents[2].1.contains("cc_getPeers"));
+    }
+
+    #[test]
+    fn test_compatibility_matrix() {
+        let generator = generator();
+        assert_eq!(
+            generator.availability("cc_echo", "1.10.0"),
+            Availability::Deprecated
+        );
+        let matrix = generator.compatibility_matrix();
+        assert!(matrix.contains("| Method | 1.0.0 | 1.9.0 | 1.10.0 |\n|--------|---|---|---|\n"));
+        assert!(matrix.contains("| `cc_echo` | ✓ | ✓ | deprecated |\n"));
+        assert!(matrix.contains("| `cc_getPeers` | - | ✓ | ✓ |\n"));
+    }
+}
@@ -0,0 +1,60 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::fmt;
+use std::future::Future;
+
+/// Why a call failed
+#[derive(Debug)]
+pub enum ClientError {
+    /// The server answered with a JSON-RPC error
+    Rpc {
+        code: i32,
+        message: String,
+        data: Option<Value>,
+    },
+    /// The call could not be sent or its response read
+    Transport(String),
+    /// The result did not have the documented type
+    Decode(serde_json::Error),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Rpc { code, message, .. } => write!(f, "RPC error {code}: {message}"),
+            ClientError::Transport(error) => write!(f, "Transport error: {error}"),
+            ClientError::Decode(error) => write!(f, "Invalid result: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+/// Sends one JSON-RPC call and returns its result
+pub trait Transport {
+    fn call(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> impl Future<Output = Result<Value, ClientError>> + Send;
+}
+
+/// Typed client over any `Transport`
+pub struct Client<T> {
+    transport: T,
+}
+
+impl<T: Transport> Client<T> {
+    pub fn new(transport: T) -> Self {
+        Self { transport }
+    }
+
+    async fn request<R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<R, ClientError> {
+        let result = self.transport.call(method, params).await?;
+        serde_json::from_value(result).map_err(ClientError::Decode)
+    }
+}
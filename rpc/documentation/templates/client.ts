@@ -0,0 +1,101 @@
+export class RpcError extends Error {
+  constructor(
+    readonly code: number,
+    message: string,
+    readonly data?: unknown,
+  ) {
+    super(message);
+    this.name = "RpcError";
+  }
+}
+
+interface JsonRpcResponse {
+  id: number | string | null;
+  result?: unknown;
+  error?: { code: number; message: string; data?: unknown };
+}
+
+function unwrap<T>(response: JsonRpcResponse): T {
+  if (response.error) {
+    throw new RpcError(response.error.code, response.error.message, response.error.data);
+  }
+  return response.result as T;
+}
+
+/** Sends one JSON-RPC call and resolves with its result */
+export interface Transport {
+  call<T>(method: string, params?: object): Promise<T>;
+}
+
+/** Transport posting each call to an HTTP endpoint */
+export class HttpTransport implements Transport {
+  private nextId = 1;
+
+  constructor(
+    private readonly url: string,
+    private readonly headers: Record<string, string> = {},
+  ) {}
+
+  async call<T>(method: string, params?: object): Promise<T> {
+    const response = await fetch(this.url, {
+      method: "POST",
+      headers: { "Content-Type": "application/json", ...this.headers },
+      body: JSON.stringify({ jsonrpc: "2.0", method, params, id: this.nextId++ }),
+    });
+    if (!response.ok) {
+      throw new RpcError(-32603, `HTTP ${response.status}`);
+    }
+    return unwrap<T>(await response.json());
+  }
+}
+
+/** Transport multiplexing calls over one WebSocket connection */
+export class WebSocketTransport implements Transport {
+  private nextId = 1;
+  private readonly pending = new Map<
+    number,
+    { resolve: (result: unknown) => void; reject: (error: unknown) => void }
+  >();
+  private readonly socket: WebSocket;
+  private readonly ready: Promise<void>;
+
+  constructor(url: string) {
+    this.socket = new WebSocket(url);
+    this.ready = new Promise((resolve, reject) => {
+      this.socket.onopen = () => resolve();
+      this.socket.onerror = () => reject(new RpcError(-32603, "WebSocket error"));
+    });
+    this.socket.onmessage = (event) => {
+      const response: JsonRpcResponse = JSON.parse(String(event.data));
+      const entry = typeof response.id === "number" ? this.pending.get(response.id) : undefined;
+      if (!entry) {
+        return;
+      }
+      this.pending.delete(response.id as number);
+      try {
+        entry.resolve(unwrap(response));
+      } catch (error) {
+        entry.reject(error);
+      }
+    };
+    this.socket.onclose = () => {
+      for (const entry of this.pending.values()) {
+        entry.reject(new RpcError(-32603, "WebSocket closed"));
+      }
+      this.pending.clear();
+    };
+  }
+
+  async call<T>(method: string, params?: object): Promise<T> {
+    await this.ready;
+    const id = this.nextId++;
+    return new Promise<T>((resolve, reject) => {
+      this.pending.set(id, { resolve: (result) => resolve(result as T), reject });
+      this.socket.send(JSON.stringify({ jsonrpc: "2.0", method, params, id }));
+    });
+  }
+
+  close(): void {
+    this.socket.close();
+  }
+}
@@ -0,0 +1,91 @@
+// CC Chain RPC Documentation: method search and try-it console
+
+(function () {
+  "use strict";
+
+  var ENDPOINT_KEY = "cc-chain-docs-endpoint";
+  var endpoint = document.getElementById("endpoint");
+  var search = document.getElementById("search");
+
+  // Remember the endpoint across pages and reloads
+  if (endpoint) {
+    var saved = window.localStorage.getItem(ENDPOINT_KEY);
+    if (saved) {
+      endpoint.value = saved;
+    }
+    endpoint.addEventListener("change", function () {
+      window.localStorage.setItem(ENDPOINT_KEY, endpoint.value);
+    });
+  }
+
+  // Show only methods whose name, summary or tags contain every term
+  if (search) {
+    search.addEventListener("input", function () {
+      var terms = search.value.toLowerCase().split(/\s+/).filter(Boolean);
+      var shown = 0;
+      document.querySelectorAll(".method").forEach(function (method) {
+        var text = method.getAttribute("data-search") || "";
+        var match = terms.every(function (term) {
+          return text.indexOf(term) !== -1;
+        });
+        method.hidden = !match;
+        if (match) {
+          shown += 1;
+        }
+      });
+      var none = document.getElementById("no-results");
+      if (none) {
+        none.hidden = shown > 0;
+      }
+    });
+  }
+
+  function show(output, text, ok) {
+    output.hidden = false;
+    output.textContent = text;
+    output.classList.remove("response-success", "response-error");
+    output.classList.add(ok ? "response-success" : "response-error");
+  }
+
+  document.querySelectorAll(".try-it").forEach(function (panel) {
+    var button = panel.querySelector(".send");
+    var request = panel.querySelector(".request");
+    var output = panel.querySelector(".response");
+
+    button.addEventListener("click", function () {
+      var body;
+      try {
+        body = JSON.parse(request.value);
+      } catch (error) {
+        show(output, "Invalid JSON: " + error.message, false);
+        return;
+      }
+
+      button.disabled = true;
+      fetch(endpoint.value, {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+        body: JSON.stringify(body),
+      })
+        .then(function (response) {
+          return response.text().then(function (text) {
+            var parsed = null;
+            try {
+              parsed = JSON.parse(text);
+              text = JSON.stringify(parsed, null, 2);
+            } catch (error) {
+              // Not JSON: show the body as it is
+            }
+            var ok = response.ok && parsed !== null && !parsed.error;
+            show(output, "HTTP " + response.status + "\n" + text, ok);
+          });
+        })
+        .catch(function (error) {
+          show(output, "Request failed: " + error.message, false);
+        })
+        .then(function () {
+          button.disabled = false;
+        });
+    });
+  });
+})();
@@ -3,27 +3,33 @@
 //! This module provides comprehensive error handling for RPC operations,
 //! including standard JSON-RPC 2.0 error codes and CC Chain-specific errors.
 
+use cc_core::CCError;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fmt;
 use thiserror::Error;
 
 /// Standard JSON-RPC 2.0 error codes
+///
+/// Numeric values of the `RpcErrorCode` catalog, for matching on raw codes.
 pub mod error_codes {
+    use super::RpcErrorCode;
+
     /// Parse error - Invalid JSON was received by the server
-    pub const PARSE_ERROR: i32 = -32700;
+    pub const PARSE_ERROR: i32 = RpcErrorCode::ParseError.code();
     
     /// Invalid Request - The JSON sent is not a valid Request object
-    pub const INVALID_REQUEST: i32 = -32600;
+    pub const INVALID_REQUEST: i32 = RpcErrorCode::InvalidRequest.code();
     
     /// Method not found - The method does not exist / is not available
-    pub const METHOD_NOT_FOUND: i32 = -32601;
+    pub const METHOD_NOT_FOUND: i32 = RpcErrorCode::MethodNotFound.code();
     
     /// Invalid params - Invalid method parameter(s)
-    pub const INVALID_PARAMS: i32 = -32602;
+    pub const INVALID_PARAMS: i32 = RpcErrorCode::InvalidParams.code();
     
     /// Internal error - Internal JSON-RPC error
-    pub const INTERNAL_ERROR: i32 = -32603;
+    pub const INTERNAL_ERROR: i32 = RpcErrorCode::InternalError.code();
     
     /// Server errors - Reserved for implementation-defined server-errors
     pub const SERVER_ERROR_START: i32 = -32099;
@@ -31,40 +37,277 @@ pub mod error_codes {
     
     // CC Chain specific error codes
     /// Transaction pool full
-    pub const TRANSACTION_POOL_FULL: i32 = -32001;
+    pub const TRANSACTION_POOL_FULL: i32 = RpcErrorCode::TransactionPoolFull.code();
     
     /// Insufficient funds
-    pub const INSUFFICIENT_FUNDS: i32 = -32002;
+    pub const INSUFFICIENT_FUNDS: i32 = RpcErrorCode::InsufficientFunds.code();
     
     /// Gas limit exceeded
-    pub const GAS_LIMIT_EXCEEDED: i32 = -32003;
+    pub const GAS_LIMIT_EXCEEDED: i32 = RpcErrorCode::GasLimitExceeded.code();
     
     /// Nonce too low
-    pub const NONCE_TOO_LOW: i32 = -32004;
+    pub const NONCE_TOO_LOW: i32 = RpcErrorCode::NonceTooLow.code();
     
     /// Nonce too high
-    pub const NONCE_TOO_HIGH: i32 = -32005;
+    pub const NONCE_TOO_HIGH: i32 = RpcErrorCode::NonceTooHigh.code();
     
     /// Account does not exist
-    pub const ACCOUNT_NOT_FOUND: i32 = -32006;
+    pub const ACCOUNT_NOT_FOUND: i32 = RpcErrorCode::AccountNotFound.code();
     
     /// Block not found
-    pub const BLOCK_NOT_FOUND: i32 = -32007;
+    pub const BLOCK_NOT_FOUND: i32 = RpcErrorCode::BlockNotFound.code();
     
     /// Transaction not found
-    pub const TRANSACTION_NOT_FOUND: i32 = -32008;
+    pub const TRANSACTION_NOT_FOUND: i32 = RpcErrorCode::TransactionNotFound.code();
     
     /// Network not synced
-    pub const NETWORK_NOT_SYNCED: i32 = -32009;
+    pub const NETWORK_NOT_SYNCED: i32 = RpcErrorCode::NodeSyncing.code();
     
     /// Rate limit exceeded
-    pub const RATE_LIMIT_EXCEEDED: i32 = -32010;
+    pub const RATE_LIMIT_EXCEEDED: i32 = RpcErrorCode::RateLimitExceeded.code();
     
     /// Unauthorized access
-    pub const UNAUTHORIZED: i32 = -32011;
+    pub const UNAUTHORIZED: i32 = RpcErrorCode::Unauthorized.code();
     
     /// Service unavailable
-    pub const SERVICE_UNAVAILABLE: i32 = -32012;
+    pub const SERVICE_UNAVAILABLE: i32 = RpcErrorCode::ServiceUnavailable.code();
+
+    /// Transaction rejected by validation
+    pub const TRANSACTION_REJECTED: i32 = RpcErrorCode::TransactionRejected.code();
+
+    /// Request exceeded its timeout
+    pub const REQUEST_TIMEOUT: i32 = RpcErrorCode::RequestTimeout.code();
+
+    /// Response exceeded the size limit
+    pub const RESPONSE_TOO_LARGE: i32 = RpcErrorCode::ResponseTooLarge.code();
+
+    /// Connection has too many subscriptions
+    pub const SUBSCRIPTION_LIMIT_REACHED: i32 = RpcErrorCode::SubscriptionLimitReached.code();
+
+    /// No protocol version or encoding shared with the client
+    pub const NEGOTIATION_FAILED: i32 = RpcErrorCode::NegotiationFailed.code();
+
+    /// Contract execution failed
+    pub const EXECUTION_FAILED: i32 = RpcErrorCode::ExecutionFailed.code();
+
+    /// Client-supplied deadline passed before the call finished
+    pub const DEADLINE_EXCEEDED: i32 = RpcErrorCode::DeadlineExceeded.code();
+
+    /// Filter was never installed, was uninstalled or expired
+    pub const FILTER_NOT_FOUND: i32 = RpcErrorCode::FilterNotFound.code();
+}
+
+/// Catalog of every error code returned by the CC Chain RPC
+///
+/// Codes are stable: once published a code keeps its meaning, so clients
+/// can match on it instead of on messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RpcErrorCode {
+    ParseError,
+    InvalidRequest,
+    MethodNotFound,
+    InvalidParams,
+    InternalError,
+    /// Generic server error with no more specific code
+    ServerError,
+    TransactionPoolFull,
+    InsufficientFunds,
+    GasLimitExceeded,
+    NonceTooLow,
+    NonceTooHigh,
+    AccountNotFound,
+    BlockNotFound,
+    TransactionNotFound,
+    NodeSyncing,
+    Unauthorized,
+    ServiceUnavailable,
+    TransactionRejected,
+    RequestTimeout,
+    ResponseTooLarge,
+    SubscriptionLimitReached,
+    NegotiationFailed,
+    ExecutionFailed,
+    DeadlineExceeded,
+    FilterNotFound,
+    RateLimitExceeded,
+}
+
+impl RpcErrorCode {
+    /// Every code in the catalog
+    pub const ALL: [RpcErrorCode; 26] = [
+        RpcErrorCode::ParseError,
+        RpcErrorCode::InvalidRequest,
+        RpcErrorCode::MethodNotFound,
+        RpcErrorCode::InvalidParams,
+        RpcErrorCode::InternalError,
+        RpcErrorCode::ServerError,
+        RpcErrorCode::TransactionPoolFull,
+        RpcErrorCode::InsufficientFunds,
+        RpcErrorCode::GasLimitExceeded,
+        RpcErrorCode::NonceTooLow,
+        RpcErrorCode::NonceTooHigh,
+        RpcErrorCode::AccountNotFound,
+        RpcErrorCode::BlockNotFound,
+        RpcErrorCode::TransactionNotFound,
+        RpcErrorCode::NodeSyncing,
+        RpcErrorCode::Unauthorized,
+        RpcErrorCode::ServiceUnavailable,
+        RpcErrorCode::TransactionRejected,
+        RpcErrorCode::RequestTimeout,
+        RpcErrorCode::ResponseTooLarge,
+        RpcErrorCode::SubscriptionLimitReached,
+        RpcErrorCode::NegotiationFailed,
+        RpcErrorCode::ExecutionFailed,
+        RpcErrorCode::DeadlineExceeded,
+        RpcErrorCode::FilterNotFound,
+        RpcErrorCode::RateLimitExceeded,
+    ];
+
+    /// Numeric JSON-RPC code
+    pub const fn code(self) -> i32 {
+        match self {
+            RpcErrorCode::ParseError => -32700,
+            RpcErrorCode::InvalidRequest => -32600,
+            RpcErrorCode::MethodNotFound => -32601,
+            RpcErrorCode::InvalidParams => -32602,
+            RpcErrorCode::InternalError => -32603,
+            RpcErrorCode::ServerError => -32000,
+            RpcErrorCode::TransactionPoolFull => -32001,
+            RpcErrorCode::InsufficientFunds => -32002,
+            RpcErrorCode::GasLimitExceeded => -32003,
+            RpcErrorCode::NonceTooLow => -32004,
+            RpcErrorCode::NonceTooHigh => -32005,
+            RpcErrorCode::AccountNotFound => -32006,
+            RpcErrorCode::BlockNotFound => -32007,
+            RpcErrorCode::TransactionNotFound => -32008,
+            RpcErrorCode::NodeSyncing => -32009,
+            RpcErrorCode::Unauthorized => -32011,
+            RpcErrorCode::ServiceUnavailable => -32012,
+            RpcErrorCode::TransactionRejected => -32013,
+            RpcErrorCode::RequestTimeout => -32014,
+            RpcErrorCode::ResponseTooLarge => -32015,
+            RpcErrorCode::SubscriptionLimitReached => -32016,
+            RpcErrorCode::NegotiationFailed => -32017,
+            RpcErrorCode::ExecutionFailed => -32018,
+            RpcErrorCode::DeadlineExceeded => -32019,
+            RpcErrorCode::FilterNotFound => -32020,
+            RpcErrorCode::RateLimitExceeded => -32029,
+        }
+    }
+
+    /// Catalog entry for a numeric code
+    pub fn from_code(code: i32) -> Option<Self> {
+        Self::ALL.into_iter().find(|entry| entry.code() == code)
+    }
+
+    /// Default message sent with this code
+    pub fn message(self) -> &'static str {
+        match self {
+            RpcErrorCode::ParseError => "Parse error",
+            RpcErrorCode::InvalidRequest => "Invalid Request",
+            RpcErrorCode::MethodNotFound => "Method not found",
+            RpcErrorCode::InvalidParams => "Invalid params",
+            RpcErrorCode::InternalError => "Internal error",
+            RpcErrorCode::ServerError => "Server error",
+            RpcErrorCode::TransactionPoolFull => "Transaction pool is full",
+            RpcErrorCode::InsufficientFunds => "Insufficient funds",
+            RpcErrorCode::GasLimitExceeded => "Gas limit exceeded",
+            RpcErrorCode::NonceTooLow => "Nonce too low",
+            RpcErrorCode::NonceTooHigh => "Nonce too high",
+            RpcErrorCode::AccountNotFound => "Account not found",
+            RpcErrorCode::BlockNotFound => "Block not found",
+            RpcErrorCode::TransactionNotFound => "Transaction not found",
+            RpcErrorCode::NodeSyncing => "Network not fully synced",
+            RpcErrorCode::Unauthorized => "Unauthorized",
+            RpcErrorCode::ServiceUnavailable => "Service unavailable",
+            RpcErrorCode::TransactionRejected => "Transaction rejected",
+            RpcErrorCode::RequestTimeout => "Request timed out",
+            RpcErrorCode::ResponseTooLarge => "Response too large",
+            RpcErrorCode::SubscriptionLimitReached => "Subscription limit reached",
+            RpcErrorCode::NegotiationFailed => "Protocol negotiation failed",
+            RpcErrorCode::ExecutionFailed => "Execution failed",
+            RpcErrorCode::DeadlineExceeded => "Deadline exceeded",
+            RpcErrorCode::FilterNotFound => "Filter not found",
+            RpcErrorCode::RateLimitExceeded => "Rate limit exceeded",
+        }
+    }
+
+    /// What a client can do about this error
+    pub fn remediation(self) -> &'static str {
+        match self {
+            RpcErrorCode::ParseError => "Send well-formed JSON.",
+            RpcErrorCode::InvalidRequest => "Send a JSON-RPC 2.0 request object with `jsonrpc`, `method` and `id`.",
+            RpcErrorCode::MethodNotFound => "Check the method name against the API reference, and that its namespace is enabled.",
+            RpcErrorCode::InvalidParams => "Check the params against the method's parameter schemas.",
+            RpcErrorCode::InternalError | RpcErrorCode::ServerError => {
+                "Retry later; report the error if it persists."
+            }
+            RpcErrorCode::TransactionPoolFull => "Retry later or raise the gas price.",
+            RpcErrorCode::InsufficientFunds => "Fund the account or lower the value and gas.",
+            RpcErrorCode::GasLimitExceeded => "Raise the gas limit, using cc_estimateGas for the amount.",
+            RpcErrorCode::NonceTooLow | RpcErrorCode::NonceTooHigh => {
+                "Use the account's next nonce from cc_getTransactionCount."
+            }
+            RpcErrorCode::AccountNotFound => "Check the address; accounts exist once they have received funds.",
+            RpcErrorCode::BlockNotFound => "Check the height or hash against the latest block.",
+            RpcErrorCode::TransactionNotFound => "Check the hash; pending transactions may not be indexed yet.",
+            RpcErrorCode::NodeSyncing => "Wait for the node to sync or query a synced node.",
+            RpcErrorCode::Unauthorized => "Authenticate with a token allowed to call the method.",
+            RpcErrorCode::ServiceUnavailable => "Retry later or use another node.",
+            RpcErrorCode::TransactionRejected => "Fix the reason given in the error data and resubmit.",
+            RpcErrorCode::RequestTimeout | RpcErrorCode::DeadlineExceeded => {
+                "Narrow the request or allow it more time."
+            }
+            RpcErrorCode::ResponseTooLarge => "Request less data, such as a smaller page or block range.",
+            RpcErrorCode::SubscriptionLimitReached => "Unsubscribe from subscriptions no longer needed.",
+            RpcErrorCode::NegotiationFailed => "Use a protocol version and encoding the server supports.",
+            RpcErrorCode::ExecutionFailed => "Check the call's inputs; the error data holds the revert reason.",
+            RpcErrorCode::FilterNotFound => "Install the filter again; filters expire when not polled.",
+            RpcErrorCode::RateLimitExceeded => "Wait for the time given in the error data before retrying.",
+        }
+    }
+
+    /// Error category as a string
+    pub fn category(self) -> &'static str {
+        match self {
+            RpcErrorCode::ParseError => "parse",
+            RpcErrorCode::InvalidRequest => "request",
+            RpcErrorCode::MethodNotFound => "method",
+            RpcErrorCode::InvalidParams => "params",
+            RpcErrorCode::InternalError => "internal",
+            RpcErrorCode::ServerError => "server",
+            RpcErrorCode::TransactionPoolFull => "pool",
+            RpcErrorCode::InsufficientFunds => "funds",
+            RpcErrorCode::GasLimitExceeded => "gas",
+            RpcErrorCode::NonceTooLow | RpcErrorCode::NonceTooHigh => "nonce",
+            RpcErrorCode::AccountNotFound
+            | RpcErrorCode::BlockNotFound
+            | RpcErrorCode::TransactionNotFound
+            | RpcErrorCode::FilterNotFound => "not_found",
+            RpcErrorCode::NodeSyncing => "sync",
+            RpcErrorCode::Unauthorized => "auth",
+            RpcErrorCode::ServiceUnavailable => "service",
+            RpcErrorCode::TransactionRejected => "transaction",
+            RpcErrorCode::RequestTimeout | RpcErrorCode::DeadlineExceeded => "timeout",
+            RpcErrorCode::ResponseTooLarge | RpcErrorCode::SubscriptionLimitReached => "limit",
+            RpcErrorCode::NegotiationFailed => "protocol",
+            RpcErrorCode::ExecutionFailed => "execution",
+            RpcErrorCode::RateLimitExceeded => "rate_limit",
+        }
+    }
+}
+
+impl fmt::Display for RpcErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.message(), self.code())
+    }
+}
+
+impl From<RpcErrorCode> for i32 {
+    fn from(code: RpcErrorCode) -> Self {
+        code.code()
+    }
 }
 
 /// JSON-RPC 2.0 error structure
@@ -246,6 +489,90 @@ impl RpcError {
         Self::new(error_codes::SERVICE_UNAVAILABLE, reason)
     }
 
+    /// Create a transaction rejected error
+    pub fn transaction_rejected(reason: impl Into<String>) -> Self {
+        Self::with_data(
+            error_codes::TRANSACTION_REJECTED,
+            "Transaction rejected",
+            serde_json::json!({
+                "reason": reason.into()
+            }),
+        )
+    }
+
+    /// Create a request timeout error
+    pub fn request_timeout(timeout_seconds: u64) -> Self {
+        Self::with_data(
+            error_codes::REQUEST_TIMEOUT,
+            format!("Request timed out after {} seconds", timeout_seconds),
+            serde_json::json!({
+                "timeout_seconds": timeout_seconds
+            }),
+        )
+    }
+
+    /// Create a deadline exceeded error for a client timeout in milliseconds
+    pub fn deadline_exceeded(timeout_ms: u64) -> Self {
+        Self::with_data(
+            error_codes::DEADLINE_EXCEEDED,
+            format!("Deadline of {} ms exceeded", timeout_ms),
+            serde_json::json!({
+                "timeout_ms": timeout_ms
+            }),
+        )
+    }
+
+    /// Create a response too large error
+    pub fn response_too_large(size: usize, limit: usize) -> Self {
+        Self::with_data(
+            error_codes::RESPONSE_TOO_LARGE,
+            "Response too large",
+            serde_json::json!({
+                "size": size,
+                "limit": limit
+            }),
+        )
+    }
+
+    /// Create a subscription limit error
+    pub fn subscription_limit_reached(limit: usize) -> Self {
+        Self::with_data(
+            error_codes::SUBSCRIPTION_LIMIT_REACHED,
+            format!("Connection already has the maximum of {} subscriptions", limit),
+            serde_json::json!({
+                "limit": limit
+            }),
+        )
+    }
+
+    /// Create a protocol negotiation error
+    pub fn negotiation_failed(reason: impl Into<String>) -> Self {
+        Self::new(error_codes::NEGOTIATION_FAILED, reason)
+    }
+
+    /// Create an execution failed error
+    pub fn execution_failed(reason: impl Into<String>) -> Self {
+        Self::with_data(
+            error_codes::EXECUTION_FAILED,
+            "Execution failed",
+            serde_json::json!({
+                "reason": reason.into()
+            }),
+        )
+    }
+
+    /// Catalog entry for this error's code, if it has one
+    pub fn error_code(&self) -> Option<RpcErrorCode> {
+        RpcErrorCode::from_code(self.code)
+    }
+
+    /// Decode the `data` payload into a typed value
+    pub fn data_as<T: DeserializeOwned>(&self) -> Option<T> {
+        self.data
+            .as_ref()
+            .and_then(|data| serde_json::from_value(data.clone()).ok())
+    }
+
     /// Check if this is a client error (4xx equivalent)
     pub fn is_client_error(&self) -> bool {
         matches!(self.code,
@@ -256,7 +583,8 @@ impl RpcError {
             error_codes::INSUFFICIENT_FUNDS |
             error_codes::NONCE_TOO_LOW |
             error_codes::NONCE_TOO_HIGH |
-            error_codes::UNAUTHORIZED
+            error_codes::UNAUTHORIZED |
+            error_codes::TRANSACTION_REJECTED
         )
     }
 
@@ -274,30 +602,20 @@ impl RpcError {
         matches!(self.code,
             error_codes::ACCOUNT_NOT_FOUND |
             error_codes::BLOCK_NOT_FOUND |
-            error_codes::TRANSACTION_NOT_FOUND
+            error_codes::TRANSACTION_NOT_FOUND |
+            error_codes::FILTER_NOT_FOUND
         )
     }
 
     /// Get the error category as a string
     pub fn category(&self) -> &'static str {
-        match self.code {
-            error_codes::PARSE_ERROR => "parse",
-            error_codes::INVALID_REQUEST => "request",
-            error_codes::METHOD_NOT_FOUND => "method",
-            error_codes::INVALID_PARAMS => "params",
-            error_codes::INTERNAL_ERROR => "internal",
-            error_codes::TRANSACTION_POOL_FULL => "pool",
-            error_codes::INSUFFICIENT_FUNDS => "funds",
-            error_codes::GAS_LIMIT_EXCEEDED => "gas",
-            error_codes::NONCE_TOO_LOW | error_codes::NONCE_TOO_HIGH => "nonce",
-            error_codes::ACCOUNT_NOT_FOUND | error_codes::BLOCK_NOT_FOUND | 
-            error_codes::TRANSACTION_NOT_FOUND => "not_found",
-            error_codes::NETWORK_NOT_SYNCED => "sync",
-            error_codes::RATE_LIMIT_EXCEEDED => "rate_limit",
-            error_codes::UNAUTHORIZED => "auth",
-            error_codes::SERVICE_UNAVAILABLE => "service",
-            _ => "unknown",
-        }
+        self.error_code().map_or("unknown", RpcErrorCode::category)
+    }
+}
+
+impl From<RpcErrorCode> for RpcError {
+    fn from(code: RpcErrorCode) -> Self {
+        Self::new(code.code(), code.message())
     }
 }
 
@@ -362,6 +680,59 @@ pub enum RpcErrorType {
     
     #[error("Service unavailable: {reason}")]
     ServiceUnavailable { reason: String },
+
+    #[error("Transaction rejected: {reason}")]
+    TransactionRejected { reason: String },
+
+    #[error("Request timed out after {timeout_seconds} seconds")]
+    RequestTimeout { timeout_seconds: u64 },
+
+    #[error("Deadline of {timeout_ms} ms exceeded")]
+    DeadlineExceeded { timeout_ms: u64 },
+
+    #[error("Response too large: {size} bytes, limit {limit}")]
+    ResponseTooLarge { size: usize, limit: usize },
+
+    #[error("Subscription limit of {limit} reached")]
+    SubscriptionLimitReached { limit: usize },
+
+    #[error("Protocol negotiation failed: {reason}")]
+    NegotiationFailed { reason: String },
+
+    #[error("Execution failed: {reason}")]
+    ExecutionFailed { reason: String },
+}
+
+impl RpcErrorType {
+    /// Catalog code this error is reported with
+    pub fn code(&self) -> RpcErrorCode {
+        match self {
+            RpcErrorType::ParseError(_) => RpcErrorCode::ParseError,
+            RpcErrorType::InvalidRequest(_) => RpcErrorCode::InvalidRequest,
+            RpcErrorType::MethodNotFound { .. } => RpcErrorCode::MethodNotFound,
+            RpcErrorType::InvalidParams(_) => RpcErrorCode::InvalidParams,
+            RpcErrorType::InternalError(_) => RpcErrorCode::InternalError,
+            RpcErrorType::TransactionPoolFull => RpcErrorCode::TransactionPoolFull,
+            RpcErrorType::InsufficientFunds { .. } => RpcErrorCode::InsufficientFunds,
+            RpcErrorType::GasLimitExceeded { .. } => RpcErrorCode::GasLimitExceeded,
+            RpcErrorType::NonceTooLow { .. } => RpcErrorCode::NonceTooLow,
+            RpcErrorType::NonceTooHigh { .. } => RpcErrorCode::NonceTooHigh,
+            RpcErrorType::AccountNotFound { .. } => RpcErrorCode::AccountNotFound,
+            RpcErrorType::BlockNotFound { .. } => RpcErrorCode::BlockNotFound,
+            RpcErrorType::TransactionNotFound { .. } => RpcErrorCode::TransactionNotFound,
+            RpcErrorType::NetworkNotSynced { .. } => RpcErrorCode::NodeSyncing,
+            RpcErrorType::RateLimitExceeded { .. } => RpcErrorCode::RateLimitExceeded,
+            RpcErrorType::Unauthorized { .. } => RpcErrorCode::Unauthorized,
+            RpcErrorType::ServiceUnavailable { .. } => RpcErrorCode::ServiceUnavailable,
+            RpcErrorType::TransactionRejected { .. } => RpcErrorCode::TransactionRejected,
+            RpcErrorType::RequestTimeout { .. } => RpcErrorCode::RequestTimeout,
+            RpcErrorType::DeadlineExceeded { .. } => RpcErrorCode::DeadlineExceeded,
+            RpcErrorType::ResponseTooLarge { .. } => RpcErrorCode::ResponseTooLarge,
+            RpcErrorType::SubscriptionLimitReached { .. } => RpcErrorCode::SubscriptionLimitReached,
+            RpcErrorType::NegotiationFailed { .. } => RpcErrorCode::NegotiationFailed,
+            RpcErrorType::ExecutionFailed { .. } => RpcErrorCode::ExecutionFailed,
+        }
+    }
 }
 
 impl From<RpcErrorType> for RpcError {
@@ -396,10 +767,59 @@ impl From<RpcErrorType> for RpcError {
             }
             RpcErrorType::Unauthorized { reason } => RpcError::unauthorized(reason),
             RpcErrorType::ServiceUnavailable { reason } => RpcError::service_unavailable(reason),
+            RpcErrorType::TransactionRejected { reason } => RpcError::transaction_rejected(reason),
+            RpcErrorType::RequestTimeout { timeout_seconds } => {
+                RpcError::request_timeout(timeout_seconds)
+            }
+            RpcErrorType::DeadlineExceeded { timeout_ms } => RpcError::deadline_exceeded(timeout_ms),
+            RpcErrorType::ResponseTooLarge { size, limit } => {
+                RpcError::response_too_large(size, limit)
+            }
+            RpcErrorType::SubscriptionLimitReached { limit } => {
+                RpcError::subscription_limit_reached(limit)
+            }
+            RpcErrorType::NegotiationFailed { reason } => RpcError::negotiation_failed(reason),
+            RpcErrorType::ExecutionFailed { reason } => RpcError::execution_failed(reason),
+        }
+    }
+}
+
+/// Node and consensus errors as seen by RPC clients
+///
+/// Consensus reports its failures as `CCError::Consensus`, so this covers
+/// both. Errors that say nothing useful to a client become internal errors.
+impl From<CCError> for RpcErrorType {
+    fn from(error: CCError) -> Self {
+        match error {
+            CCError::Transaction(reason) => RpcErrorType::TransactionRejected { reason },
+            CCError::OutOfGas { required, available } => RpcErrorType::GasLimitExceeded {
+                used: required,
+                limit: available,
+            },
+            CCError::ContractExecutionFailed(reason) => RpcErrorType::ExecutionFailed { reason },
+            CCError::InvalidInput(msg) | CCError::InvalidData(msg) => {
+                RpcErrorType::InvalidParams(msg)
+            }
+            error @ (CCError::Json(_) | CCError::HexDecode(_)) => {
+                RpcErrorType::InvalidParams(error.to_string())
+            }
+            CCError::Consensus(reason) | CCError::Network(reason) | CCError::Timeout(reason) => {
+                RpcErrorType::ServiceUnavailable { reason }
+            }
+            error @ CCError::NetworkTimeout(_) => RpcErrorType::ServiceUnavailable {
+                reason: error.to_string(),
+            },
+            error => RpcErrorType::InternalError(error.to_string()),
         }
     }
 }
 
+impl From<CCError> for RpcError {
+    fn from(error: CCError) -> Self {
+        RpcErrorType::from(error).into()
+    }
+}
+
 /// Error handler that can convert various error types to RpcError
 pub struct RpcErrorHandler;
 
@@ -571,4 +991,53 @@ mod tests {
             assert_eq!(data["retry_after_seconds"], 60);
         }
     }
+
+    #[test]
+    fn test_error_code_catalog() {
+        let mut codes: Vec<i32> = RpcErrorCode::ALL.iter().map(|code| code.code()).collect();
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes.len(), RpcErrorCode::ALL.len());
+
+        for code in RpcErrorCode::ALL {
+            assert_eq!(RpcErrorCode::from_code(code.code()), Some(code));
+            assert_eq!(RpcError::from(code).error_code(), Some(code));
+            assert!(code.remediation().ends_with('.'));
+        }
+        assert_eq!(RpcErrorCode::from_code(-31999), None);
+        assert_eq!(RpcErrorCode::RateLimitExceeded.code(), -32029);
+        assert_eq!(
+            RpcError::from(RpcErrorType::BlockNotFound { identifier: "7".to_string() }).error_code(),
+            Some(RpcErrorCode::BlockNotFound)
+        );
+
+        #[derive(Deserialize)]
+        struct Limit {
+            size: usize,
+            limit: usize,
+        }
+        let limit: Limit = RpcError::response_too_large(2048, 1024).data_as().unwrap();
+        assert_eq!((limit.size, limit.limit), (2048, 1024));
+    }
+
+    #[test]
+    fn test_core_error_conversion() {
+        let error = RpcError::from(CCError::Transaction("bad signature".to_string()));
+        assert_eq!(error.error_code(), Some(RpcErrorCode::TransactionRejected));
+        assert_eq!(error.data.unwrap()["reason"], "bad signature");
+
+        let error = RpcError::from(CCError::OutOfGas { required: 500, available: 100 });
+        assert_eq!(error.error_code(), Some(RpcErrorCode::GasLimitExceeded));
+        assert_eq!(error.data.unwrap()["used"], 500);
+
+        let error = RpcError::from(CCError::Consensus("no quorum".to_string()));
+        assert_eq!(error.error_code(), Some(RpcErrorCode::ServiceUnavailable));
+
+        let error = RpcError::from(CCError::InvalidInput("empty address".to_string()));
+        assert_eq!(error.error_code(), Some(RpcErrorCode::InvalidParams));
+
+        let error = RpcError::from(CCError::State("corrupt trie".to_string()));
+        assert_eq!(error.error_code(), Some(RpcErrorCode::InternalError));
+        assert!(error.message.contains("corrupt trie"));
+    }
 }
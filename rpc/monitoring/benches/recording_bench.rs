@@ -0,0 +1,63 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rpc_monitoring::slo::SloConfig;
+use rpc_monitoring::{MonitoringConfig, RpcMonitor};
+use std::sync::Arc;
+use std::thread;
+
+const REQUESTS_PER_THREAD: usize = 2_000;
+
+/// Start and finish `REQUESTS_PER_THREAD` requests on each of `threads`
+/// threads, one in ten failing
+fn record_requests(monitor: &Arc<RpcMonitor>, threads: usize) {
+    let handles: Vec<_> = (0..threads)
+        .map(|thread_id| {
+            let monitor = monitor.clone();
+            thread::spawn(move || {
+                for i in 0..REQUESTS_PER_THREAD {
+                    let id = format!("{thread_id}-{i}");
+                    monitor
+                        .start_client_request(
+                            id.clone(),
+                            "cc_getBlock".to_string(),
+                            128,
+                            Some("127.0.0.1".to_string()),
+                        )
+                        .unwrap();
+                    if i % 10 == 0 {
+                        monitor.fail_request(id, -32603).unwrap();
+                    } else {
+                        monitor.complete_request(id, 512).unwrap();
+                    }
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
+
+fn benchmark_recording(c: &mut Criterion) {
+    let mut group = c.benchmark_group("request_recording");
+
+    for threads in [1, 4, 8] {
+        let monitor = Arc::new(RpcMonitor::with_config(MonitoringConfig {
+            slos: vec![
+                SloConfig::availability("availability", 99.9),
+                SloConfig::latency("latency", 500, 99.0),
+            ],
+            ..MonitoringConfig::default()
+        }));
+        group.throughput(Throughput::Elements((threads * REQUESTS_PER_THREAD) as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(threads),
+            &threads,
+            |b, &threads| b.iter(|| record_requests(&monitor, threads)),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_recording);
+criterion_main!(benches);
@@ -0,0 +1,306 @@
+//! Anomaly detection
+//!
+//! Fixed thresholds suit the node as a whole but not every method: 300ms is
+//! slow for `cc_ping` and fast for a call walking the state. The detector
+//! learns a baseline of each method's average latency and error rate from
+//! the aggregated windows, as an exponentially weighted mean and standard
+//! deviation, and flags a window whose value rises more standard deviations
+//! above the mean than the z-score threshold. Anomalous windows still feed
+//! the baseline, so a lasting change stops alerting once it is the norm.
+
+use crate::AggregatedMetrics;
+use cc_core_metrics::MetricSample;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Anomaly detection configuration
+#[derive(Debug, Clone)]
+pub struct AnomalyConfig {
+    /// Standard deviations above the baseline at which a value is anomalous
+    pub z_score_threshold: f64,
+    /// Weight of each new window in the baseline, between 0 and 1
+    pub smoothing: f64,
+    /// Windows a baseline learns from before it can flag anomalies
+    pub warmup_windows: u32,
+    /// Windows with fewer requests for a method are ignored
+    pub min_requests: u64,
+    /// Smallest latency deviation assumed, so steady methods are not
+    /// flagged for a millisecond more
+    pub min_latency_stddev_ms: f64,
+    /// Smallest error rate deviation assumed, in percentage points
+    pub min_error_rate_stddev_percent: f64,
+}
+
+impl Default for AnomalyConfig {
+    fn default() -> Self {
+        Self {
+            z_score_threshold: 3.0,
+            smoothing: 0.1,
+            warmup_windows: 10,
+            min_requests: 10,
+            min_latency_stddev_ms: 5.0,
+            min_error_rate_stddev_percent: 1.0,
+        }
+    }
+}
+
+/// A metric the detector keeps a baseline of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum AnomalyMetric {
+    /// Average duration of the method's requests, in millis
+    Latency,
+    /// Share of the method's requests that failed, in percent
+    ErrorRate,
+}
+
+impl AnomalyMetric {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnomalyMetric::Latency => "latency",
+            AnomalyMetric::ErrorRate => "error_rate",
+        }
+    }
+}
+
+/// A method's value in the latest window, against its baseline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Anomaly {
+    pub method: String,
+    pub metric: AnomalyMetric,
+    pub value: f64,
+    pub baseline_mean: f64,
+    pub baseline_stddev: f64,
+    pub z_score: f64,
+}
+
+/// Exponentially weighted mean and variance of one metric
+#[derive(Debug, Clone, Default)]
+struct Baseline {
+    mean: f64,
+    variance: f64,
+    windows: u32,
+}
+
+impl Baseline {
+    fn update(&mut self, value: f64, smoothing: f64) {
+        if self.windows == 0 {
+            self.mean = value;
+        } else {
+            let delta = value - self.mean;
+            self.mean += smoothing * delta;
+            self.variance = (1.0 - smoothing) * (self.variance + smoothing * delta * delta);
+        }
+        self.windows += 1;
+    }
+}
+
+/// Learns per-method baselines from aggregated windows
+pub(crate) struct AnomalyDetector {
+    config: AnomalyConfig,
+    baselines: Mutex<HashMap<(String, AnomalyMetric), Baseline>>,
+    /// Latest evaluation of every warmed-up baseline, anomalous or not
+    latest: Mutex<HashMap<(String, AnomalyMetric), Anomaly>>,
+}
+
+impl AnomalyDetector {
+    pub(crate) fn new(config: AnomalyConfig) -> Self {
+        Self {
+            config,
+            baselines: Mutex::new(HashMap::new()),
+            latest: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Compare each method of a closed window to its baseline, then learn
+    /// from it
+    pub(crate) fn observe(&self, window: &AggregatedMetrics) {
+        let mut baselines = self.baselines.lock().unwrap();
+        let mut latest = self.latest.lock().unwrap();
+        latest.clear();
+        for (method, metrics) in &window.method_breakdown {
+            if metrics.call_count < self.config.min_requests.max(1) {
+                continue;
+            }
+            let failed = metrics.call_count - metrics.success_count;
+            let values = [
+                (
+                    AnomalyMetric::Latency,
+                    metrics.avg_duration_ms,
+                    self.config.min_latency_stddev_ms,
+                ),
+                (
+                    AnomalyMetric::ErrorRate,
+                    failed as f64 / metrics.call_count as f64 * 100.0,
+                    self.config.min_error_rate_stddev_percent,
+                ),
+            ];
+            for (metric, value, min_stddev) in values {
+                let key = (method.clone(), metric);
+                let baseline = baselines.entry(key.clone()).or_default();
+                if baseline.windows >= self.config.warmup_windows.max(1) {
+                    let stddev = baseline.variance.sqrt().max(min_stddev);
+                    latest.insert(
+                        key,
+                        Anomaly {
+                            method: method.clone(),
+                            metric,
+                            value,
+                            baseline_mean: baseline.mean,
+                            baseline_stddev: stddev,
+                            z_score: (value - baseline.mean) / stddev,
+                        },
+                    );
+                }
+                baseline.update(value, self.config.smoothing);
+            }
+        }
+    }
+
+    /// Latest evaluations that rose past the threshold, by method and metric
+    pub(crate) fn anomalies(&self) -> Vec<Anomaly> {
+        let mut anomalies: Vec<Anomaly> = self
+            .latest
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|anomaly| anomaly.z_score > self.config.z_score_threshold)
+            .cloned()
+            .collect();
+        anomalies.sort_by(|a, b| (&a.method, a.metric).cmp(&(&b.method, b.metric)));
+        anomalies
+    }
+
+    /// Every method and metric with a baseline by alert id, with a message
+    /// when its latest window was anomalous
+    pub(crate) fn alerts(&self) -> Vec<(String, Option<String>)> {
+        let mut keys: Vec<(String, AnomalyMetric)> =
+            self.baselines.lock().unwrap().keys().cloned().collect();
+        keys.sort();
+        let latest = self.latest.lock().unwrap();
+        keys.into_iter()
+            .map(|key| {
+                let message = latest
+                    .get(&key)
+                    .filter(|anomaly| anomaly.z_score > self.config.z_score_threshold)
+                    .map(|anomaly| {
+                        format!(
+                            "{} {} ({:.1}) is {:.1} standard deviations above its baseline ({:.1} ± {:.1})",
+                            key.0,
+                            key.1.as_str().replace('_', " "),
+                            anomaly.value,
+                            anomaly.z_score,
+                            anomaly.baseline_mean,
+                            anomaly.baseline_stddev
+                        )
+                    });
+                (format!("anomaly_{}_{}", key.0, key.1.as_str()), message)
+            })
+            .collect()
+    }
+
+    /// Z-score of each method's latest window, as metric samples
+    pub(crate) fn samples(&self) -> Vec<MetricSample> {
+        let latest = self.latest.lock().unwrap();
+        let mut evaluated: Vec<&Anomaly> = latest.values().collect();
+        evaluated.sort_by(|a, b| (&a.method, a.metric).cmp(&(&b.method, b.metric)));
+        evaluated
+            .into_iter()
+            .map(|anomaly| {
+                MetricSample::gauge(
+                    "cc_rpc_anomaly_z_score",
+                    "Standard deviations of the latest window above the method's baseline",
+                    anomaly.z_score,
+                )
+                .with_label("method", &anomaly.method)
+                .with_label("metric", anomaly.metric.as_str())
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{aggregate_window, RequestMetrics, RequestStatus};
+    use std::time::Duration;
+
+    /// A window of `count` `cc_getBlock` requests taking `duration_ms`, of
+    /// which `failed` failed
+    fn window(count: u64, duration_ms: u64, failed: u64) -> AggregatedMetrics {
+        let requests: Vec<RequestMetrics> = (0..count)
+            .map(|i| RequestMetrics {
+                method: "cc_getBlock".to_string(),
+                start_time: 0,
+                end_time: Some(duration_ms),
+                duration_ms: Some(duration_ms),
+                status: if i < failed {
+                    RequestStatus::Error
+                } else {
+                    RequestStatus::Success
+                },
+                error_code: None,
+                request_size: 10,
+                response_size: None,
+                client_id: None,
+                sample_weight: 1,
+            })
+            .collect();
+        let requests: Vec<&RequestMetrics> = requests.iter().collect();
+        aggregate_window(0, Duration::from_secs(60), &requests)
+    }
+
+    #[test]
+    fn test_latency_anomaly() {
+        let detector = AnomalyDetector::new(AnomalyConfig::default());
+        for i in 0..20 {
+            detector.observe(&window(20, 40 + i % 3 * 5, 0));
+            assert!(detector.anomalies().is_empty());
+        }
+        // Too few requests to judge
+        detector.observe(&window(5, 400, 0));
+        assert!(detector.anomalies().is_empty());
+
+        detector.observe(&window(20, 400, 0));
+        let anomalies = detector.anomalies();
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].metric, AnomalyMetric::Latency);
+        assert!(anomalies[0].z_score > 3.0);
+
+        let alerts = detector.alerts();
+        assert_eq!(alerts.len(), 2);
+        assert_eq!(alerts[0].0, "anomaly_cc_getBlock_latency");
+        assert!(alerts[0].1.as_ref().unwrap().contains("above its baseline"));
+        assert_eq!(alerts[1].0, "anomaly_cc_getBlock_error_rate");
+        assert!(alerts[1].1.is_none());
+
+        // Faster than usual is not an anomaly
+        detector.observe(&window(20, 1, 0));
+        assert!(detector.anomalies().is_empty());
+    }
+
+    #[test]
+    fn test_error_rate_anomaly_raises_alert() {
+        let monitor = crate::RpcMonitor::with_config(crate::MonitoringConfig {
+            anomaly: Some(AnomalyConfig {
+                warmup_windows: 3,
+                ..AnomalyConfig::default()
+            }),
+            // No window closes during the test
+            aggregation_window: Duration::from_secs(24 * 60 * 60),
+            ..crate::MonitoringConfig::default()
+        });
+        let detector = monitor.anomaly.as_ref().unwrap();
+        for _ in 0..5 {
+            detector.observe(&window(100, 40, 1));
+        }
+        detector.observe(&window(100, 40, 30));
+
+        let alerts = monitor.check_alerts().unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].id, "anomaly_cc_getBlock_error_rate");
+        assert!(
+            matches!(&alerts[0].alert_type, crate::AlertType::Custom(kind) if kind == "anomaly")
+        );
+    }
+}
@@ -0,0 +1,363 @@
+//! Canary probes
+//!
+//! Request metrics only show the calls clients happen to make. A canary
+//! makes real JSON-RPC calls of its own, by default `cc_ping` and
+//! `cc_getLatestBlock`, against the local server or a remote peer on every
+//! health check, so an endpoint nobody is calling is still known to be up.
+//! Each target is its own component in the health status and raises its
+//! own alerts when calls fail or answer slowly.
+
+use crate::transport::{HttpTransport, Transport};
+use crate::{ComponentHealth, HealthLevel, HealthProbe};
+use cc_core_metrics::MetricSample;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Checks availability is measured over
+const AVAILABILITY_CHECKS: usize = 60;
+
+/// An endpoint to probe
+#[derive(Debug, Clone)]
+pub struct CanaryTarget {
+    /// Identifies the target in the health status, alerts and metrics
+    pub name: String,
+    /// JSON-RPC endpoint, such as `http://127.0.0.1:8545/`
+    pub url: String,
+    /// Methods called on every check, without params
+    pub methods: Vec<String>,
+    /// Calls slower than this are a warning
+    pub max_latency: Duration,
+    pub timeout: Duration,
+}
+
+impl CanaryTarget {
+    pub fn new(name: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            url: url.into(),
+            methods: vec!["cc_ping".to_string(), "cc_getLatestBlock".to_string()],
+            max_latency: Duration::from_secs(1),
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// One call made by a canary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CanaryCall {
+    pub method: String,
+    pub latency_ms: u64,
+    /// Why the call failed, if it did
+    pub error: Option<String>,
+}
+
+/// The calls made by one check of a target
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CanaryOutcome {
+    pub calls: Vec<CanaryCall>,
+    pub checked_at: u64,
+}
+
+impl CanaryOutcome {
+    pub fn failed_calls(&self) -> impl Iterator<Item = &CanaryCall> {
+        self.calls.iter().filter(|call| call.error.is_some())
+    }
+
+    /// Slowest call that was answered
+    pub fn slowest_call(&self) -> Option<&CanaryCall> {
+        self.calls
+            .iter()
+            .filter(|call| call.error.is_none())
+            .max_by_key(|call| call.latency_ms)
+    }
+}
+
+/// Probes one target, as the health component `canary_<name>`
+pub struct CanaryProbe {
+    target: CanaryTarget,
+    component: String,
+    transport: Arc<dyn Transport>,
+    next_id: AtomicU64,
+    last_outcome: Mutex<Option<CanaryOutcome>>,
+    /// Calls made and answered by recent checks, oldest first
+    recent: Mutex<VecDeque<(usize, usize)>>,
+}
+
+impl CanaryProbe {
+    pub fn new(target: CanaryTarget) -> Self {
+        let transport = Arc::new(HttpTransport::new(target.timeout));
+        Self::with_transport(target, transport)
+    }
+
+    /// Make calls through `transport`, which must return response bodies
+    pub fn with_transport(target: CanaryTarget, transport: Arc<dyn Transport>) -> Self {
+        Self {
+            component: format!("canary_{}", target.name),
+            target,
+            transport,
+            next_id: AtomicU64::new(1),
+            last_outcome: Mutex::new(None),
+            recent: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn target(&self) -> &CanaryTarget {
+        &self.target
+    }
+
+    /// Calls made by the latest check, if there was one
+    pub fn last_outcome(&self) -> Option<CanaryOutcome> {
+        self.last_outcome.lock().unwrap().clone()
+    }
+
+    /// Share of calls answered over recent checks; 100 before the first
+    pub fn availability_percent(&self) -> f64 {
+        let recent = self.recent.lock().unwrap();
+        let (calls, answered) = recent.iter().fold((0, 0), |(calls, answered), (c, a)| {
+            (calls + c, answered + a)
+        });
+        if calls == 0 {
+            return 100.0;
+        }
+        answered as f64 / calls as f64 * 100.0
+    }
+
+    /// Call `method` and check the answer is a JSON-RPC result
+    fn call(&self, method: &str) -> CanaryCall {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = json!({"jsonrpc": "2.0", "method": method, "params": [], "id": id});
+        let started = Instant::now();
+        let response = self
+            .transport
+            .exchange_json(&self.target.url, &request.to_string());
+        let latency_ms = started.elapsed().as_millis() as u64;
+        let error = match response {
+            Ok(body) => match serde_json::from_str::<Value>(&body) {
+                Ok(response) if response.get("error").is_some_and(|e| !e.is_null()) => {
+                    Some(format!("error response: {}", response["error"]))
+                }
+                Ok(response) if response.get("result").is_some() => None,
+                Ok(_) => Some("response without a result".to_string()),
+                Err(e) => Some(format!("invalid response: {e}")),
+            },
+            Err(e) => Some(e.to_string()),
+        };
+        CanaryCall {
+            method: method.to_string(),
+            latency_ms,
+            error,
+        }
+    }
+}
+
+impl HealthProbe for CanaryProbe {
+    fn name(&self) -> &str {
+        &self.component
+    }
+
+    fn check(&self) -> ComponentHealth {
+        let calls: Vec<CanaryCall> = self
+            .target
+            .methods
+            .iter()
+            .map(|method| self.call(method))
+            .collect();
+        let outcome = CanaryOutcome {
+            calls,
+            checked_at: crate::current_timestamp(),
+        };
+
+        let failed: Vec<&CanaryCall> = outcome.failed_calls().collect();
+        let slowest = outcome.slowest_call();
+        let max_latency_ms = self.target.max_latency.as_millis() as u64;
+        let (status, message) = if !failed.is_empty() {
+            let errors: Vec<String> = failed
+                .iter()
+                .map(|call| format!("{}: {}", call.method, call.error.as_deref().unwrap_or("")))
+                .collect();
+            let status = if failed.len() == outcome.calls.len() {
+                HealthLevel::Down
+            } else {
+                HealthLevel::Critical
+            };
+            (status, errors.join("; "))
+        } else if let Some(slow) = slowest.filter(|call| call.latency_ms > max_latency_ms) {
+            (
+                HealthLevel::Warning,
+                format!(
+                    "{} took {}ms, over {}ms",
+                    slow.method, slow.latency_ms, max_latency_ms
+                ),
+            )
+        } else {
+            (
+                HealthLevel::Healthy,
+                format!("{} calls answered", outcome.calls.len()),
+            )
+        };
+        let health = ComponentHealth {
+            status,
+            message,
+            last_check: outcome.checked_at,
+            response_time_ms: slowest.map(|call| call.latency_ms),
+        };
+
+        let mut recent = self.recent.lock().unwrap();
+        if recent.len() == AVAILABILITY_CHECKS {
+            recent.pop_front();
+        }
+        recent.push_back((outcome.calls.len(), outcome.calls.len() - failed.len()));
+        drop(recent);
+        *self.last_outcome.lock().unwrap() = Some(outcome);
+        health
+    }
+}
+
+/// Whether each target answered its latest check, the latency of each call
+/// and availability over recent checks, as metric samples
+pub(crate) fn canary_samples(canaries: &[Arc<CanaryProbe>]) -> Vec<MetricSample> {
+    let checked: Vec<(&CanaryProbe, CanaryOutcome)> = canaries
+        .iter()
+        .filter_map(|canary| Some((canary.as_ref(), canary.last_outcome()?)))
+        .collect();
+    let mut samples = Vec::new();
+    for (canary, outcome) in &checked {
+        let up = outcome.failed_calls().next().is_none();
+        samples.push(
+            MetricSample::gauge(
+                "cc_rpc_canary_up",
+                "Whether every canary call of the latest check was answered",
+                if up { 1.0 } else { 0.0 },
+            )
+            .with_label("target", &canary.target.name),
+        );
+    }
+    for (canary, outcome) in &checked {
+        for call in outcome.calls.iter().filter(|call| call.error.is_none()) {
+            samples.push(
+                MetricSample::gauge(
+                    "cc_rpc_canary_latency_ms",
+                    "Latency of the latest answered canary call in milliseconds",
+                    call.latency_ms as f64,
+                )
+                .with_label("target", &canary.target.name)
+                .with_label("method", &call.method),
+            );
+        }
+    }
+    for (canary, _) in &checked {
+        samples.push(
+            MetricSample::gauge(
+                "cc_rpc_canary_availability_percent",
+                "Share of canary calls answered over recent checks",
+                canary.availability_percent(),
+            )
+            .with_label("target", &canary.target.name),
+        );
+    }
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AlertType, MonitoringConfig, MonitoringError, Result, RpcMonitor};
+
+    /// Answers `cc_ping` after `ping_delay` and fails other methods while
+    /// `broken` is set
+    struct FakeNode {
+        ping_delay: Duration,
+        broken: Mutex<bool>,
+    }
+
+    impl Transport for FakeNode {
+        fn post_json(&self, _url: &str, _body: &str) -> Result<()> {
+            unreachable!("canaries read responses")
+        }
+
+        fn exchange_json(&self, _url: &str, body: &str) -> Result<String> {
+            let request: Value = serde_json::from_str(body).unwrap();
+            let id = &request["id"];
+            if request["method"] == "cc_ping" {
+                std::thread::sleep(self.ping_delay);
+                return Ok(json!({"jsonrpc": "2.0", "result": "pong", "id": id}).to_string());
+            }
+            if *self.broken.lock().unwrap() {
+                return Err(MonitoringError::ExportError(
+                    "connection refused".to_string(),
+                ));
+            }
+            Ok(json!({"jsonrpc": "2.0", "result": {"height": 7}, "id": id}).to_string())
+        }
+    }
+
+    fn monitor(node: Arc<FakeNode>, max_latency: Duration) -> RpcMonitor {
+        let mut target = CanaryTarget::new("peer", "http://10.0.0.2:8545/");
+        target.max_latency = max_latency;
+        RpcMonitor::with_config(MonitoringConfig {
+            canaries: vec![target],
+            ..MonitoringConfig::default()
+        })
+        .with_canary_transport(node)
+    }
+
+    #[test]
+    fn test_canary_health_and_alerts() {
+        let node = Arc::new(FakeNode {
+            ping_delay: Duration::ZERO,
+            broken: Mutex::new(false),
+        });
+        let monitor = monitor(node.clone(), Duration::from_secs(1));
+        monitor.run_health_probes();
+        let health = monitor.get_health_status().unwrap();
+        let canary = &health.component_statuses["canary_peer"];
+        assert!(matches!(canary.status, HealthLevel::Healthy));
+        assert!(monitor.check_alerts().unwrap().is_empty());
+
+        *node.broken.lock().unwrap() = true;
+        monitor.run_health_probes();
+        let health = monitor.get_health_status().unwrap();
+        let canary = &health.component_statuses["canary_peer"];
+        assert!(matches!(canary.status, HealthLevel::Critical));
+        assert!(canary.message.contains("cc_getLatestBlock"));
+        let alerts = monitor.check_alerts().unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].id, "canary_unavailable_peer");
+        assert!(matches!(alerts[0].alert_type, AlertType::CanaryUnavailable));
+
+        let metrics = monitor
+            .export_metrics(crate::ExportFormat::Prometheus)
+            .unwrap();
+        assert!(metrics.contains("cc_rpc_canary_up{target=\"peer\"} 0"));
+        assert!(metrics.contains("cc_rpc_canary_availability_percent{target=\"peer\"} 75"));
+        assert!(metrics.contains("cc_rpc_canary_latency_ms{target=\"peer\",method=\"cc_ping\"}"));
+
+        *node.broken.lock().unwrap() = false;
+        monitor.run_health_probes();
+        let alerts = monitor.check_alerts().unwrap();
+        assert!(alerts[0].resolved_at.is_some());
+    }
+
+    #[test]
+    fn test_canary_latency() {
+        let node = Arc::new(FakeNode {
+            ping_delay: Duration::from_millis(20),
+            broken: Mutex::new(false),
+        });
+        let monitor = monitor(node, Duration::from_millis(5));
+        monitor.run_health_probes();
+        let health = monitor.get_health_status().unwrap();
+        let canary = &health.component_statuses["canary_peer"];
+        assert!(matches!(canary.status, HealthLevel::Warning));
+        assert!(canary.response_time_ms.unwrap() >= 20);
+
+        let alerts = monitor.check_alerts().unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].id, "canary_high_latency_peer");
+        assert!(matches!(alerts[0].alert_type, AlertType::CanaryHighLatency));
+    }
+}
@@ -0,0 +1,387 @@
+//! Grafana dashboard
+//!
+//! A dashboard over the metrics the monitor exports to Prometheus, built
+//! from the same metric names so the two stay in step. The JSON is ready
+//! to import: Grafana asks for the Prometheus data source while importing,
+//! and an `instance` variable picks the nodes shown.
+
+use crate::{MonitoringConfig, MonitoringError, Result, RpcMonitor};
+use serde_json::{json, Value};
+
+/// Label selector applied to every query
+const SELECTOR: &str = r#"instance=~"$instance""#;
+
+const PANEL_WIDTH: u64 = 12;
+const PANEL_HEIGHT: u64 = 8;
+
+/// Dashboard settings
+#[derive(Debug, Clone)]
+pub struct DashboardConfig {
+    pub title: String,
+    /// Identifies the dashboard, so importing again replaces it
+    pub uid: String,
+    /// How often Grafana reloads the panels, such as `30s`
+    pub refresh: String,
+}
+
+impl Default for DashboardConfig {
+    fn default() -> Self {
+        Self {
+            title: "CC Chain RPC".to_string(),
+            uid: "cc-chain-rpc".to_string(),
+            refresh: "30s".to_string(),
+        }
+    }
+}
+
+impl RpcMonitor {
+    /// Grafana dashboard JSON for this monitor's metrics, with SLO and
+    /// canary panels when those are configured
+    pub fn grafana_dashboard(&self, config: &DashboardConfig) -> Result<String> {
+        serde_json::to_string_pretty(&dashboard(config, &self.config))
+            .map_err(|e| MonitoringError::ExportError(e.to_string()))
+    }
+}
+
+/// A query and the legend of its series
+type Target = (String, &'static str);
+
+/// Rows of panels as `(title, unit, targets)`, two panels abreast
+fn layout(
+    monitoring: &MonitoringConfig,
+) -> Vec<(&'static str, Vec<(&'static str, &'static str, Vec<Target>)>)> {
+    let rate = |metric: &str| format!("rate({metric}{{{SELECTOR}}}[$__rate_interval])");
+    let quantile = |q: f64, by: &str| {
+        format!(
+            "histogram_quantile({q}, sum by ({by}) ({}))",
+            rate("cc_rpc_request_duration_ms_bucket")
+        )
+    };
+    let gauge = |metric: &str| format!("{metric}{{{SELECTOR}}}");
+
+    let mut rows = vec![
+        (
+            "Overview",
+            vec![
+                (
+                    "Requests per second",
+                    "reqps",
+                    vec![(
+                        format!("sum({})", rate("cc_rpc_requests_total")),
+                        "requests",
+                    )],
+                ),
+                (
+                    "Error rate",
+                    "percent",
+                    vec![(
+                        format!(
+                            "100 * sum({}) / sum({})",
+                            rate("cc_rpc_method_errors_total"),
+                            rate("cc_rpc_method_requests_total")
+                        ),
+                        "errors",
+                    )],
+                ),
+                (
+                    "Latency percentiles",
+                    "ms",
+                    vec![
+                        (quantile(0.5, "le"), "p50"),
+                        (quantile(0.9, "le"), "p90"),
+                        (quantile(0.99, "le"), "p99"),
+                    ],
+                ),
+                (
+                    "Average response time",
+                    "ms",
+                    vec![(gauge("cc_rpc_response_time_ms"), "{{instance}}")],
+                ),
+            ],
+        ),
+        (
+            "Methods",
+            vec![
+                (
+                    "Requests per second by method",
+                    "reqps",
+                    vec![(
+                        format!("sum by (method) ({})", rate("cc_rpc_method_requests_total")),
+                        "{{method}}",
+                    )],
+                ),
+                (
+                    "Errors per second by method",
+                    "reqps",
+                    vec![(
+                        format!("sum by (method) ({})", rate("cc_rpc_method_errors_total")),
+                        "{{method}}",
+                    )],
+                ),
+                (
+                    "p99 latency by method",
+                    "ms",
+                    vec![(quantile(0.99, "method, le"), "{{method}}")],
+                ),
+                (
+                    "Average latency by method",
+                    "ms",
+                    vec![(
+                        format!(
+                            "sum by (method) ({}) / sum by (method) ({})",
+                            rate("cc_rpc_request_duration_ms_sum"),
+                            rate("cc_rpc_request_duration_ms_count")
+                        ),
+                        "{{method}}",
+                    )],
+                ),
+            ],
+        ),
+        (
+            "Process",
+            vec![
+                (
+                    "Memory",
+                    "decmbytes",
+                    vec![(gauge("cc_rpc_memory_usage_mb"), "{{instance}}")],
+                ),
+                (
+                    "CPU",
+                    "percent",
+                    vec![(gauge("cc_rpc_cpu_usage_percent"), "{{instance}}")],
+                ),
+            ],
+        ),
+    ];
+    if !monitoring.slos.is_empty() {
+        rows.push((
+            "SLOs",
+            vec![
+                (
+                    "SLO compliance",
+                    "percent",
+                    vec![(gauge("cc_rpc_slo_compliance_percent"), "{{slo}}")],
+                ),
+                (
+                    "Error budget remaining",
+                    "percent",
+                    vec![(
+                        gauge("cc_rpc_slo_error_budget_remaining_percent"),
+                        "{{slo}}",
+                    )],
+                ),
+                (
+                    "Error budget burn rate",
+                    "none",
+                    vec![(gauge("cc_rpc_slo_burn_rate"), "{{slo}} {{window}}")],
+                ),
+            ],
+        ));
+    }
+    if !monitoring.canaries.is_empty() {
+        rows.push((
+            "Canaries",
+            vec![
+                (
+                    "Canary availability",
+                    "percent",
+                    vec![(gauge("cc_rpc_canary_availability_percent"), "{{target}}")],
+                ),
+                (
+                    "Canary latency",
+                    "ms",
+                    vec![(gauge("cc_rpc_canary_latency_ms"), "{{target}} {{method}}")],
+                ),
+            ],
+        ));
+    }
+    rows
+}
+
+fn dashboard(config: &DashboardConfig, monitoring: &MonitoringConfig) -> Value {
+    let datasource = json!({"type": "prometheus", "uid": "${DS_PROMETHEUS}"});
+    let mut panels = Vec::new();
+    let mut y = 0;
+    for (row, row_panels) in layout(monitoring) {
+        panels.push(json!({
+            "id": panels.len() + 1,
+            "type": "row",
+            "title": row,
+            "collapsed": false,
+            "panels": [],
+            "gridPos": {"x": 0, "y": y, "w": 2 * PANEL_WIDTH, "h": 1},
+        }));
+        y += 1;
+        let rows_of_panels = row_panels.len().div_ceil(2) as u64;
+        for (index, (title, unit, targets)) in row_panels.into_iter().enumerate() {
+            let targets: Vec<Value> = targets
+                .into_iter()
+                .zip('A'..)
+                .map(|((expr, legend), ref_id)| {
+                    json!({
+                        "datasource": datasource,
+                        "expr": expr,
+                        "legendFormat": legend,
+                        "refId": ref_id.to_string(),
+                    })
+                })
+                .collect();
+            panels.push(json!({
+                "id": panels.len() + 1,
+                "type": "timeseries",
+                "title": title,
+                "datasource": datasource,
+                "gridPos": {
+                    "x": index as u64 % 2 * PANEL_WIDTH,
+                    "y": y + index as u64 / 2 * PANEL_HEIGHT,
+                    "w": PANEL_WIDTH,
+                    "h": PANEL_HEIGHT,
+                },
+                "fieldConfig": {"defaults": {"unit": unit}, "overrides": []},
+                "options": {
+                    "legend": {"displayMode": "list", "placement": "bottom"},
+                    "tooltip": {"mode": "multi"},
+                },
+                "targets": targets,
+            }));
+        }
+        y += rows_of_panels * PANEL_HEIGHT;
+    }
+
+    json!({
+        "__inputs": [{
+            "name": "DS_PROMETHEUS",
+            "label": "Prometheus",
+            "type": "datasource",
+            "pluginId": "prometheus",
+            "pluginName": "Prometheus",
+        }],
+        "__requires": [
+            {"type": "datasource", "id": "prometheus", "name": "Prometheus", "version": "1.0.0"},
+            {"type": "panel", "id": "timeseries", "name": "Time series", "version": ""},
+        ],
+        "uid": config.uid,
+        "title": config.title,
+        "tags": ["cc-chain", "rpc"],
+        "timezone": "browser",
+        "editable": true,
+        "schemaVersion": 39,
+        "version": 1,
+        "refresh": config.refresh,
+        "time": {"from": "now-6h", "to": "now"},
+        "annotations": {"list": []},
+        "templating": {"list": [{
+            "name": "instance",
+            "label": "Instance",
+            "type": "query",
+            "datasource": datasource,
+            "query": "label_values(cc_rpc_uptime_seconds, instance)",
+            "definition": "label_values(cc_rpc_uptime_seconds, instance)",
+            "refresh": 2,
+            "multi": true,
+            "includeAll": true,
+            "allValue": ".*",
+            "current": {},
+        }]},
+        "panels": panels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::slo::SloConfig;
+    use crate::ExportFormat;
+    use std::collections::HashSet;
+
+    /// Metric names a query refers to
+    fn metric_names(expr: &str) -> Vec<String> {
+        expr.match_indices("cc_rpc_")
+            .map(|(start, _)| {
+                expr[start..]
+                    .chars()
+                    .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_dashboard_matches_exported_metrics() {
+        let monitor = RpcMonitor::with_config(MonitoringConfig {
+            slos: vec![SloConfig::availability("availability", 99.9)],
+            ..MonitoringConfig::default()
+        });
+        monitor
+            .start_request("1".to_string(), "cc_getBlock".to_string(), 10)
+            .unwrap();
+        monitor.complete_request("1".to_string(), 100).unwrap();
+        let exported = monitor.export_metrics(ExportFormat::Prometheus).unwrap();
+        let exported: HashSet<&str> = exported
+            .lines()
+            .filter_map(|line| line.strip_prefix("# TYPE "))
+            .filter_map(|line| line.split(' ').next())
+            .collect();
+        let summary = monitor.get_health_status().unwrap().metrics_summary;
+
+        let dashboard: Value = serde_json::from_str(
+            &monitor
+                .grafana_dashboard(&DashboardConfig::default())
+                .unwrap(),
+        )
+        .unwrap();
+        let panels = dashboard["panels"].as_array().unwrap();
+        let ids: HashSet<u64> = panels.iter().map(|p| p["id"].as_u64().unwrap()).collect();
+        assert_eq!(ids.len(), panels.len());
+        let titles: Vec<&str> = panels
+            .iter()
+            .map(|p| p["title"].as_str().unwrap())
+            .collect();
+        assert!(titles.contains(&"Latency percentiles"));
+        assert!(titles.contains(&"Requests per second by method"));
+        assert!(titles.contains(&"SLO compliance"));
+
+        let queries = panels
+            .iter()
+            .filter_map(|panel| panel["targets"].as_array())
+            .flatten()
+            .map(|target| target["expr"].as_str().unwrap());
+        for name in queries.flat_map(metric_names) {
+            let process = name == "cc_rpc_memory_usage_mb" || name == "cc_rpc_cpu_usage_percent";
+            if process && summary.memory_usage_mb.is_none() {
+                // No /proc on this platform
+                continue;
+            }
+            assert!(exported.contains(name.as_str()), "{name} is not exported");
+        }
+    }
+
+    #[test]
+    fn test_layout() {
+        let dashboard = dashboard(&DashboardConfig::default(), &MonitoringConfig::default());
+        assert_eq!(dashboard["uid"], "cc-chain-rpc");
+        assert_eq!(dashboard["__inputs"][0]["name"], "DS_PROMETHEUS");
+        let panels = dashboard["panels"].as_array().unwrap();
+        assert!(panels
+            .iter()
+            .all(|p| p["title"] != "SLOs" && p["title"] != "Canaries"));
+
+        // Panels sit two abreast below their row, without overlapping
+        let position = |panel: &Value| {
+            let grid = &panel["gridPos"];
+            ["x", "y", "w", "h"].map(|key| grid[key].as_u64().unwrap())
+        };
+        let positions: Vec<[u64; 4]> = panels.iter().map(position).collect();
+        for (i, a) in positions.iter().enumerate() {
+            for b in &positions[i + 1..] {
+                let apart = a[0] + a[2] <= b[0]
+                    || b[0] + b[2] <= a[0]
+                    || a[1] + a[3] <= b[1]
+                    || b[1] + b[3] <= a[1];
+                assert!(apart, "{a:?} overlaps {b:?}");
+            }
+        }
+        assert_eq!(positions[1], [0, 1, PANEL_WIDTH, PANEL_HEIGHT]);
+        assert_eq!(positions[2], [PANEL_WIDTH, 1, PANEL_WIDTH, PANEL_HEIGHT]);
+    }
+}
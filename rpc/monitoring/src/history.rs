@@ -0,0 +1,321 @@
+//! Metrics history
+//!
+//! Aggregated windows can be kept in a storage backend so dashboards still
+//! have them after a restart. The history is a ring of time buckets: each
+//! window is stored under its slot in a ring covering the retention period,
+//! overwriting the window one retention period older, so the history never
+//! outgrows retention. Windows are read back when the monitor starts, and
+//! `get_metrics_range` rolls them up to the resolution a dashboard asks for.
+
+use crate::{AggregatedMetrics, MethodMetrics, MonitoringError, Result, RpcMonitor};
+use cc_core_storage::Storage;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Aggregated windows persisted in a ring of time buckets
+pub struct MetricsHistory {
+    storage: Arc<dyn Storage>,
+    /// Window width in millis
+    width: u64,
+    /// Buckets in the ring
+    slots: u64,
+}
+
+impl MetricsHistory {
+    /// Keep windows `window` wide for `retention` in `storage`, which should
+    /// be reserved for the history, such as a namespace of its own
+    pub fn new(storage: Arc<dyn Storage>, window: Duration, retention: Duration) -> Result<Self> {
+        let width = window.as_millis() as u64;
+        if width == 0 {
+            return Err(MonitoringError::ConfigError(
+                "aggregation_window must not be zero".to_string(),
+            ));
+        }
+        Ok(Self {
+            storage,
+            width,
+            slots: (retention.as_millis() as u64).div_ceil(width).max(1),
+        })
+    }
+
+    fn key(&self, timestamp: u64) -> [u8; 8] {
+        (timestamp / self.width % self.slots).to_be_bytes()
+    }
+
+    /// Store a window in its bucket
+    pub fn record(&self, metrics: &AggregatedMetrics) -> Result<()> {
+        let value = serde_json::to_vec(metrics)
+            .map_err(|e| MonitoringError::StorageError(e.to_string()))?;
+        self.storage
+            .put(&self.key(metrics.timestamp), &value)
+            .map_err(|e| MonitoringError::StorageError(e.to_string()))
+    }
+
+    /// Stored windows starting at or after `since`, oldest first
+    pub fn load(&self, since: u64) -> Result<Vec<AggregatedMetrics>> {
+        let entries = self
+            .storage
+            .scan_prefix(&[])
+            .map_err(|e| MonitoringError::StorageError(e.to_string()))?;
+        let mut windows = Vec::new();
+        for entry in entries {
+            let (_, value) = entry.map_err(|e| MonitoringError::StorageError(e.to_string()))?;
+            let metrics: AggregatedMetrics = serde_json::from_slice(&value)
+                .map_err(|e| MonitoringError::StorageError(e.to_string()))?;
+            // Buckets written with a different window width are stale
+            if metrics.timestamp >= since
+                && metrics.window_duration.as_millis() as u64 == self.width
+            {
+                windows.push(metrics);
+            }
+        }
+        windows.sort_by_key(|metrics| metrics.timestamp);
+        Ok(windows)
+    }
+}
+
+impl RpcMonitor {
+    /// Persist aggregated windows in `storage` and restore the ones it
+    /// holds from within the retention period
+    pub fn with_history(mut self, storage: Arc<dyn Storage>) -> Result<Self> {
+        let history = MetricsHistory::new(
+            storage,
+            self.config.aggregation_window,
+            self.config.metrics_retention,
+        )?;
+        let retention_cutoff = crate::current_timestamp()
+            .saturating_sub(self.config.metrics_retention.as_millis() as u64);
+        let restored = history.load(retention_cutoff)?;
+        {
+            let mut aggregated = self.aggregated_metrics.lock().unwrap();
+            let restored: Vec<_> = restored
+                .into_iter()
+                .filter(|metrics| aggregated.iter().all(|m| m.timestamp != metrics.timestamp))
+                .collect();
+            aggregated.extend(restored);
+            aggregated
+                .make_contiguous()
+                .sort_by_key(|metrics| metrics.timestamp);
+        }
+        self.history = Some(Arc::new(history));
+        Ok(self)
+    }
+
+    /// Windows starting in `[from, to)`, in epoch millis, rolled up into
+    /// buckets `resolution` wide; buckets without windows are left out
+    pub fn get_metrics_range(
+        &self,
+        from: u64,
+        to: u64,
+        resolution: Duration,
+    ) -> Result<Vec<AggregatedMetrics>> {
+        if from > to {
+            return Err(MonitoringError::InvalidTimeRange(format!(
+                "from ({from}) is after to ({to})"
+            )));
+        }
+        if resolution < self.config.aggregation_window {
+            return Err(MonitoringError::InvalidTimeRange(format!(
+                "resolution ({:?}) is finer than the aggregation window ({:?})",
+                resolution, self.config.aggregation_window
+            )));
+        }
+        let width = resolution.as_millis() as u64;
+
+        let aggregated = self.aggregated_metrics.lock().unwrap();
+        let mut buckets: Vec<(u64, Vec<&AggregatedMetrics>)> = Vec::new();
+        for metrics in aggregated
+            .iter()
+            .filter(|m| m.timestamp >= from && m.timestamp < to)
+        {
+            let bucket = metrics.timestamp - metrics.timestamp % width;
+            match buckets.last_mut() {
+                Some((start, windows)) if *start == bucket => windows.push(metrics),
+                _ => buckets.push((bucket, vec![metrics])),
+            }
+        }
+        Ok(buckets
+            .into_iter()
+            .map(|(timestamp, windows)| merge_windows(timestamp, resolution, &windows))
+            .collect())
+    }
+}
+
+/// Roll consecutive windows up into one starting at `timestamp`
+fn merge_windows(
+    timestamp: u64,
+    window_duration: Duration,
+    windows: &[&AggregatedMetrics],
+) -> AggregatedMetrics {
+    let total_requests: u64 = windows.iter().map(|m| m.total_requests).sum();
+    let successful_requests = windows.iter().map(|m| m.successful_requests).sum();
+    let failed_requests = windows.iter().map(|m| m.failed_requests).sum();
+    let busy: Vec<_> = windows.iter().filter(|m| m.total_requests > 0).collect();
+
+    let mut method_breakdown: HashMap<String, MethodMetrics> = HashMap::new();
+    for (method, metrics) in windows.iter().flat_map(|m| &m.method_breakdown) {
+        let Some(merged) = method_breakdown.get_mut(method) else {
+            method_breakdown.insert(method.clone(), metrics.clone());
+            continue;
+        };
+        merged.avg_duration_ms = weighted_average(
+            [
+                (merged.avg_duration_ms, merged.call_count),
+                (metrics.avg_duration_ms, metrics.call_count),
+            ]
+            .into_iter(),
+        );
+        merged.min_duration_ms = merged.min_duration_ms.min(metrics.min_duration_ms);
+        merged.max_duration_ms = merged.max_duration_ms.max(metrics.max_duration_ms);
+        merged.call_count += metrics.call_count;
+        merged.success_count += metrics.success_count;
+        merged.total_request_size += metrics.total_request_size;
+        merged.total_response_size += metrics.total_response_size;
+    }
+
+    AggregatedMetrics {
+        timestamp,
+        window_duration,
+        total_requests,
+        successful_requests,
+        failed_requests,
+        avg_response_time_ms: weighted_average(
+            busy.iter()
+                .map(|m| (m.avg_response_time_ms, m.total_requests)),
+        ),
+        min_response_time_ms: busy
+            .iter()
+            .map(|m| m.min_response_time_ms)
+            .min()
+            .unwrap_or(0),
+        max_response_time_ms: busy
+            .iter()
+            .map(|m| m.max_response_time_ms)
+            .max()
+            .unwrap_or(0),
+        requests_per_second: total_requests as f64 / window_duration.as_secs_f64(),
+        error_rate_percent: if total_requests > 0 {
+            failed_requests as f64 / total_requests as f64 * 100.0
+        } else {
+            0.0
+        },
+        method_breakdown,
+    }
+}
+
+/// Average of `(value, weight)` pairs; 0 without weight
+fn weighted_average(values: impl Iterator<Item = (f64, u64)>) -> f64 {
+    let (sum, weight) = values.fold((0.0, 0), |(sum, weight), (value, w)| {
+        (sum + value * w as f64, weight + w)
+    });
+    if weight == 0 {
+        0.0
+    } else {
+        sum / weight as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MonitoringConfig, RequestMetrics, RequestStatus};
+    use cc_core_storage::InMemoryStorage;
+
+    const MINUTE: u64 = 60_000;
+
+    fn request(method: &str, end_time: u64, status: RequestStatus) -> RequestMetrics {
+        RequestMetrics {
+            method: method.to_string(),
+            start_time: end_time - 20,
+            end_time: Some(end_time),
+            duration_ms: Some(20),
+            status,
+            error_code: None,
+            request_size: 10,
+            response_size: Some(30),
+            client_id: None,
+            sample_weight: 1,
+        }
+    }
+
+    #[test]
+    fn test_history_survives_restart() {
+        let storage: Arc<dyn Storage> = Arc::new(InMemoryStorage::new());
+        let now = crate::current_timestamp();
+        let base = now - now % MINUTE - 10 * MINUTE;
+
+        let monitor = RpcMonitor::new().with_history(storage.clone()).unwrap();
+        *monitor.aggregated_until.lock().unwrap() = base;
+        {
+            let mut completed = monitor.completed_requests.lock().unwrap();
+            completed.push_back(request("cc_ping", base + 5, RequestStatus::Success));
+            completed.push_back(request("cc_ping", base + MINUTE + 5, RequestStatus::Error));
+        }
+        monitor.aggregate_until(base + 3 * MINUTE).unwrap();
+
+        let restarted = RpcMonitor::new().with_history(storage.clone()).unwrap();
+        let restored = restarted
+            .get_metrics_range(base, now, Duration::from_secs(60))
+            .unwrap();
+        let totals: Vec<u64> = restored.iter().map(|m| m.total_requests).collect();
+        assert_eq!(totals, [1, 1, 0]);
+        assert_eq!(restored[1].error_rate_percent, 100.0);
+
+        // In a three-bucket ring, windows three widths apart share a bucket
+        let history = MetricsHistory::new(
+            storage,
+            Duration::from_secs(60),
+            Duration::from_secs(3 * 60),
+        )
+        .unwrap();
+        assert_eq!(history.slots, 3);
+        assert_eq!(history.key(base), history.key(base + 3 * MINUTE));
+        assert!(history.load(base + 3 * MINUTE).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_metrics_range_resolution() {
+        let monitor = RpcMonitor::with_config(MonitoringConfig::default());
+        let base = 1_700_000_000_000 - 1_700_000_000_000 % (5 * MINUTE);
+        *monitor.aggregated_until.lock().unwrap() = base;
+        {
+            let mut completed = monitor.completed_requests.lock().unwrap();
+            for minute in 0..7 {
+                completed.push_back(request(
+                    "cc_ping",
+                    base + minute * MINUTE + 1,
+                    RequestStatus::Success,
+                ));
+            }
+            completed.push_back(request(
+                "cc_getBlock",
+                base + 2 * MINUTE + 9,
+                RequestStatus::Error,
+            ));
+        }
+        monitor.aggregate_until(base + 7 * MINUTE).unwrap();
+
+        let buckets = monitor
+            .get_metrics_range(base, base + 10 * MINUTE, Duration::from_secs(5 * 60))
+            .unwrap();
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].timestamp, base);
+        assert_eq!(buckets[0].total_requests, 6);
+        assert_eq!(buckets[0].failed_requests, 1);
+        assert_eq!(buckets[0].requests_per_second, 6.0 / 300.0);
+        assert_eq!(buckets[0].method_breakdown["cc_ping"].call_count, 5);
+        assert_eq!(buckets[0].method_breakdown["cc_ping"].avg_duration_ms, 20.0);
+        assert_eq!(buckets[1].timestamp, base + 5 * MINUTE);
+        assert_eq!(buckets[1].total_requests, 2);
+
+        assert!(matches!(
+            monitor.get_metrics_range(base + MINUTE, base, Duration::from_secs(60)),
+            Err(MonitoringError::InvalidTimeRange(_))
+        ));
+        assert!(matches!(
+            monitor.get_metrics_range(base, base + MINUTE, Duration::from_secs(1)),
+            Err(MonitoringError::InvalidTimeRange(_))
+        ));
+    }
+}
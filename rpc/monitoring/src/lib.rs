@@ -3,11 +3,39 @@
 //! This module provides comprehensive monitoring capabilities for RPC operations,
 //! including performance metrics, health checks, and operational insights.
 
+pub mod anomaly;
+pub mod canary;
+pub mod grafana;
+pub mod history;
+pub mod notify;
+pub mod otlp;
+mod recording;
+pub mod sampling;
+pub mod scheduler;
+pub mod slo;
+pub mod slow_log;
+pub mod system;
+pub mod transport;
+
+use anomaly::{Anomaly, AnomalyConfig, AnomalyDetector};
+use canary::{CanaryProbe, CanaryTarget};
+use cc_core_metrics::{MetricSample, MetricsSource};
+use history::MetricsHistory;
+use notify::{NotificationChannel, NotificationConfig, Notifier};
+use crossbeam::queue::ArrayQueue;
+use otlp::{OtlpConfig, OtlpExporter};
+use recording::{ActiveRequests, FINISHED_QUEUE_CAPACITY};
+use sampling::{CardinalityConfig, MethodNames, Sampler, SamplingConfig};
+use slo::{SloConfig, SloStatus, SloTracker};
+use slow_log::{SlowLog, SlowLogConfig, SlowRequest};
+use system::SystemProbe;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
+use transport::Transport;
 
 #[derive(Error, Debug)]
 pub enum MonitoringError {
@@ -22,6 +50,9 @@ pub enum MonitoringError {
     
     #[error("Configuration error: {0}")]
     ConfigError(String),
+    
+    #[error("Export error: {0}")]
+    ExportError(String),
 }
 
 pub type Result<T> = std::result::Result<T, MonitoringError>;
@@ -35,6 +66,26 @@ pub struct MonitoringConfig {
     pub health_check_interval: Duration,
     pub alert_thresholds: AlertThresholds,
     pub export_interval: Duration,
+    /// Width of the windows requests are aggregated in
+    pub aggregation_window: Duration,
+    /// OpenTelemetry collector to push spans and metrics to
+    pub otlp: Option<OtlpConfig>,
+    /// Where triggered and resolved alerts are sent
+    pub notifications: NotificationConfig,
+    /// Service level objectives tracked, with error budget burn alerts
+    pub slos: Vec<SloConfig>,
+    /// Which slow requests are kept for investigation
+    pub slow_log: SlowLogConfig,
+    /// Endpoints called on every health check, such as the local server
+    /// and remote peers
+    pub canaries: Vec<CanaryTarget>,
+    /// Alert when a method's latency or error rate leaves its learned
+    /// baseline
+    pub anomaly: Option<AnomalyConfig>,
+    /// Share of successful requests recorded in full
+    pub sampling: SamplingConfig,
+    /// Limits on the method names recorded
+    pub cardinality: CardinalityConfig,
 }
 
 impl Default for MonitoringConfig {
@@ -46,6 +97,15 @@ impl Default for MonitoringConfig {
             health_check_interval: Duration::from_secs(30),
             alert_thresholds: AlertThresholds::default(),
             export_interval: Duration::from_secs(60),
+            aggregation_window: Duration::from_secs(60),
+            otlp: None,
+            notifications: NotificationConfig::default(),
+            slos: Vec::new(),
+            slow_log: SlowLogConfig::default(),
+            canaries: Vec::new(),
+            anomaly: None,
+            sampling: SamplingConfig::default(),
+            cardinality: CardinalityConfig::default(),
         }
     }
 }
@@ -57,6 +117,11 @@ pub struct AlertThresholds {
     pub max_error_rate_percent: f64,
     pub max_concurrent_requests: u32,
     pub min_success_rate_percent: f64,
+    /// Memory use of the node process above which it is degraded
+    pub max_memory_mb: Option<u64>,
+    /// CPU use of the node process, in percent of one core, above which it
+    /// is degraded
+    pub max_cpu_percent: Option<f64>,
 }
 
 impl Default for AlertThresholds {
@@ -66,6 +131,8 @@ impl Default for AlertThresholds {
             max_error_rate_percent: 5.0,
             max_concurrent_requests: 1000,
             min_success_rate_percent: 95.0,
+            max_memory_mb: None,
+            max_cpu_percent: None,
         }
     }
 }
@@ -82,6 +149,14 @@ pub struct RequestMetrics {
     pub request_size: usize,
     pub response_size: Option<usize>,
     pub client_id: Option<String>,
+    /// Requests this one stands for in the history when successful
+    /// requests are sampled
+    #[serde(default = "default_sample_weight")]
+    pub sample_weight: u32,
+}
+
+fn default_sample_weight() -> u32 {
+    1
 }
 
 /// Request status enumeration
@@ -93,9 +168,22 @@ pub enum RequestStatus {
     Timeout,
 }
 
+impl RequestStatus {
+    /// Lowercase name used in exported telemetry
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RequestStatus::Pending => "pending",
+            RequestStatus::Success => "success",
+            RequestStatus::Error => "error",
+            RequestStatus::Timeout => "timeout",
+        }
+    }
+}
+
 /// Aggregated metrics for time windows
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AggregatedMetrics {
+    /// Start of the window, in epoch millis
     pub timestamp: u64,
     pub window_duration: Duration,
     pub total_requests: u64,
@@ -121,6 +209,60 @@ pub struct MethodMetrics {
     pub total_response_size: u64,
 }
 
+/// Upper bounds of the request duration histogram buckets, in milliseconds
+const DURATION_BUCKETS_MS: [u64; 12] = [1, 5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000];
+
+/// Running totals of a method since the monitor started
+#[derive(Debug, Clone, Default)]
+struct MethodTotals {
+    requests: u64,
+    errors: u64,
+    duration_sum_ms: u64,
+    /// Requests per duration bucket, not cumulative
+    buckets: [u64; DURATION_BUCKETS_MS.len()],
+}
+
+impl MethodTotals {
+    fn record(&mut self, metrics: &RequestMetrics) {
+        let duration_ms = metrics.duration_ms.unwrap_or_default();
+        self.requests += 1;
+        if !matches!(metrics.status, RequestStatus::Success) {
+            self.errors += 1;
+        }
+        self.duration_sum_ms += duration_ms;
+        if let Some(bucket) = DURATION_BUCKETS_MS.iter().position(|bound| duration_ms <= *bound) {
+            self.buckets[bucket] += 1;
+        }
+    }
+}
+
+/// Per-client metrics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientMetrics {
+    pub client_id: String,
+    pub request_count: u64,
+    pub error_count: u64,
+    pub error_rate_percent: f64,
+    pub avg_duration_ms: f64,
+    pub total_request_size: u64,
+    pub total_response_size: u64,
+}
+
+impl ClientMetrics {
+    /// Bytes received from and sent to the client
+    pub fn total_bytes(&self) -> u64 {
+        self.total_request_size + self.total_response_size
+    }
+}
+
+/// What the top-clients report ranks clients by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClientRanking {
+    RequestCount,
+    ErrorRate,
+    Bytes,
+}
+
 /// Health check result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HealthStatus {
@@ -148,6 +290,15 @@ pub struct ComponentHealth {
     pub response_time_ms: Option<u64>,
 }
 
+/// A component whose health is checked on the health check interval
+pub trait HealthProbe: Send + Sync {
+    /// Name of the component in the health status
+    fn name(&self) -> &str;
+
+    /// Check the component now
+    fn check(&self) -> ComponentHealth;
+}
+
 /// Summary of key metrics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MetricsSummary {
@@ -180,6 +331,11 @@ pub enum AlertType {
     HighErrorRate,
     LowSuccessRate,
     HighConcurrency,
+    HighMemoryUsage,
+    HighCpuUsage,
+    ErrorBudgetBurn,
+    CanaryUnavailable,
+    CanaryHighLatency,
     ServiceDown,
     Custom(String),
 }
@@ -195,12 +351,35 @@ pub enum AlertSeverity {
 /// RPC monitoring system
 pub struct RpcMonitor {
     config: MonitoringConfig,
-    active_requests: Arc<Mutex<HashMap<String, RequestMetrics>>>,
+    active_requests: Arc<ActiveRequests>,
+    /// Finished requests not yet applied to the history and counters
+    finished: Arc<ArrayQueue<(String, RequestMetrics)>>,
+    /// Held while applying finished requests, so they keep their order
+    flush_lock: Arc<Mutex<()>>,
     completed_requests: Arc<Mutex<VecDeque<RequestMetrics>>>,
     aggregated_metrics: Arc<Mutex<VecDeque<AggregatedMetrics>>>,
     active_alerts: Arc<Mutex<HashMap<String, Alert>>>,
     start_time: Instant,
-    last_aggregation: Arc<Mutex<Instant>>,
+    /// End of the last aggregated window, in epoch millis
+    aggregated_until: Arc<Mutex<u64>>,
+    /// When the current window closes, checked without taking the lock
+    next_aggregation: Arc<AtomicU64>,
+    metrics_sources: Arc<Mutex<Vec<Arc<dyn MetricsSource>>>>,
+    otlp: Option<Arc<OtlpExporter>>,
+    notifier: Arc<Notifier>,
+    health_probes: Arc<Mutex<Vec<Arc<dyn HealthProbe>>>>,
+    /// Latest result of each health probe, by component
+    probe_results: Arc<Mutex<HashMap<String, ComponentHealth>>>,
+    canaries: Vec<Arc<CanaryProbe>>,
+    anomaly: Option<Arc<AnomalyDetector>>,
+    sampler: Sampler,
+    method_names: MethodNames,
+    system: Arc<SystemProbe>,
+    history: Option<Arc<MetricsHistory>>,
+    slo: Arc<SloTracker>,
+    slow_log: Arc<SlowLog>,
+    /// Totals per method, exported as counters
+    method_totals: Arc<Mutex<HashMap<String, MethodTotals>>>,
 }
 
 impl RpcMonitor {
@@ -211,25 +390,116 @@ impl RpcMonitor {
 
     /// Create a new RPC monitor with custom configuration
     pub fn with_config(config: MonitoringConfig) -> Self {
+        let otlp = config.otlp.clone().map(|otlp| Arc::new(OtlpExporter::new(otlp)));
+        let notifier = Arc::new(Notifier::new(config.notifications.clone()));
+        let width = (config.aggregation_window.as_millis() as u64).max(1);
+        let now = current_timestamp();
+        let slo = Arc::new(SloTracker::new(&config.slos, config.aggregation_window));
+        let slow_log = Arc::new(SlowLog::new(config.slow_log.clone()));
+        let sampler = Sampler::new(&config.sampling, config.slow_log.threshold);
+        let method_names = MethodNames::new(config.cardinality.clone());
+        let anomaly = config.anomaly.clone().map(|anomaly| Arc::new(AnomalyDetector::new(anomaly)));
+        let canaries = config.canaries.iter().map(|target| Arc::new(CanaryProbe::new(target.clone()))).collect();
         Self {
             config,
-            active_requests: Arc::new(Mutex::new(HashMap::new())),
+            active_requests: Arc::new(ActiveRequests::new()),
+            finished: Arc::new(ArrayQueue::new(FINISHED_QUEUE_CAPACITY)),
+            flush_lock: Arc::new(Mutex::new(())),
             completed_requests: Arc::new(Mutex::new(VecDeque::new())),
             aggregated_metrics: Arc::new(Mutex::new(VecDeque::new())),
             active_alerts: Arc::new(Mutex::new(HashMap::new())),
             start_time: Instant::now(),
-            last_aggregation: Arc::new(Mutex::new(Instant::now())),
+            aggregated_until: Arc::new(Mutex::new(now - now % width)),
+            next_aggregation: Arc::new(AtomicU64::new(now - now % width + width)),
+            metrics_sources: Arc::new(Mutex::new(Vec::new())),
+            otlp,
+            notifier,
+            health_probes: Arc::new(Mutex::new(Vec::new())),
+            probe_results: Arc::new(Mutex::new(HashMap::new())),
+            canaries,
+            anomaly,
+            sampler,
+            method_names,
+            system: Arc::new(SystemProbe::new()),
+            history: None,
+            slo,
+            slow_log,
+            method_totals: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Send OpenTelemetry data through `transport` instead of HTTP; has no
+    /// effect unless `MonitoringConfig::otlp` is set
+    pub fn with_otlp_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        if let Some(otlp) = &self.config.otlp {
+            self.otlp = Some(Arc::new(OtlpExporter::with_transport(otlp.clone(), transport)));
+        }
+        self
+    }
+
+    /// Make canary calls through `transport` instead of HTTP; it must
+    /// return response bodies
+    pub fn with_canary_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.canaries = self.config.canaries.iter()
+            .map(|target| Arc::new(CanaryProbe::with_transport(target.clone(), transport.clone())))
+            .collect();
+        self
+    }
+
+    /// The OpenTelemetry exporter, when one is configured
+    pub fn otlp_exporter(&self) -> Option<&Arc<OtlpExporter>> {
+        self.otlp.as_ref()
+    }
+
+    /// Register an external component whose metrics are included in exports
+    pub fn register_metrics_source(&self, source: Arc<dyn MetricsSource>) {
+        self.metrics_sources.lock().unwrap().push(source);
+    }
+
+    /// Send alert notifications to `channel` as well as the configured ones
+    pub fn add_notification_channel(&self, channel: Arc<dyn NotificationChannel>) {
+        self.notifier.add_channel(channel);
+    }
+
+    /// Register a component to check on the health check interval; its
+    /// latest result is part of the health status
+    pub fn register_health_probe(&self, probe: Arc<dyn HealthProbe>) {
+        self.health_probes.lock().unwrap().push(probe);
+    }
+
+    /// Check every registered component and canary target now
+    pub fn run_health_probes(&self) {
+        let mut probes = self.health_probes.lock().unwrap().clone();
+        probes.extend(self.canaries.iter().map(|canary| canary.clone() as Arc<dyn HealthProbe>));
+        for probe in probes {
+            let started = Instant::now();
+            let mut health = probe.check();
+            health.last_check = current_timestamp();
+            health.response_time_ms.get_or_insert(started.elapsed().as_millis() as u64);
+            self.probe_results.lock().unwrap().insert(probe.name().to_string(), health);
         }
     }
 
+    /// Collect current samples from all registered metrics sources
+    pub fn collect_source_metrics(&self) -> Vec<MetricSample> {
+        let sources = self.metrics_sources.lock().unwrap();
+        sources.iter().flat_map(|source| source.collect()).collect()
+    }
+
     /// Start monitoring a request
     pub fn start_request(&self, request_id: String, method: String, request_size: usize) -> Result<()> {
+        self.start_client_request(request_id, method, request_size, None)
+    }
+
+    /// Start monitoring a request made by `client_id`, such as an API key
+    /// or IP address
+    pub fn start_client_request(&self, request_id: String, method: String, request_size: usize, client_id: Option<String>) -> Result<()> {
         if !self.config.enabled {
             return Ok(());
         }
 
         let metrics = RequestMetrics {
-            method,
+            method: self.method_names.record(method),
             start_time: current_timestamp(),
             end_time: None,
             duration_ms: None,
@@ -237,11 +507,11 @@ impl RpcMonitor {
             error_code: None,
             request_size,
             response_size: None,
-            client_id: None,
+            client_id,
+            sample_weight: 1,
         };
 
-        let mut active = self.active_requests.lock().unwrap();
-        active.insert(request_id, metrics);
+        self.active_requests.insert(request_id, metrics);
         
         Ok(())
     }
@@ -268,36 +538,146 @@ impl RpcMonitor {
 
         let now = current_timestamp();
         
-        let mut active = self.active_requests.lock().unwrap();
-        if let Some(mut metrics) = active.remove(&request_id) {
+        let finished = self.active_requests.remove(&request_id);
+        if let Some(mut metrics) = finished {
             metrics.end_time = Some(now);
             metrics.duration_ms = Some(now - metrics.start_time);
             metrics.status = status;
             metrics.error_code = error_code;
             metrics.response_size = response_size;
 
-            let mut completed = self.completed_requests.lock().unwrap();
-            completed.push_back(metrics);
-
-            // Maintain history size limit
-            while completed.len() > self.config.max_history_size {
-                completed.pop_front();
+            // Applied to the history by the next reader, or here once the
+            // queue is full
+            let mut finished = (request_id, metrics);
+            while let Err(rejected) = self.finished.push(finished) {
+                self.flush();
+                finished = rejected;
             }
         }
 
         // Check if we need to aggregate metrics
         self.maybe_aggregate_metrics()?;
+        self.maybe_export_otlp()?;
         
         Ok(())
     }
 
+    /// Apply queued finished requests to the history, counters, SLOs and
+    /// logs
+    fn flush(&self) {
+        if self.finished.is_empty() {
+            return;
+        }
+        let _flushing = self.flush_lock.lock().unwrap();
+        let mut batch = Vec::with_capacity(self.finished.len());
+        while let Some(finished) = self.finished.pop() {
+            batch.push(finished);
+        }
+
+        // Every request is counted, but only sampled ones are kept in full
+        let mut method_totals = self.method_totals.lock().unwrap();
+        let mut sampled = Vec::with_capacity(batch.len());
+        for (request_id, mut metrics) in batch {
+            self.slo.record(&metrics);
+            self.slow_log.finish(&request_id, &metrics);
+            method_totals.entry(metrics.method.clone()).or_default().record(&metrics);
+            if self.sampler.sample(&mut metrics) {
+                if let Some(otlp) = &self.otlp {
+                    otlp.record(&request_id, &metrics);
+                }
+                sampled.push(metrics);
+            }
+        }
+        drop(method_totals);
+
+        let mut completed = self.completed_requests.lock().unwrap();
+        completed.extend(sampled);
+
+        // Maintain history size limit
+        while completed.len() > self.config.max_history_size {
+            completed.pop_front();
+        }
+    }
+
+    /// Note that an active request spent `duration` in `phase`, shown in
+    /// the slow log if the request turns out slow
+    pub fn record_phase(&self, request_id: &str, phase: &str, duration: Duration) {
+        if !self.config.enabled || !self.slow_log.enabled() {
+            return;
+        }
+        // Holding the request keeps it from finishing meanwhile and
+        // leaving its trace behind
+        self.active_requests.with(request_id, |_| {
+            self.slow_log.add_phase(request_id, phase, duration);
+        });
+    }
+
+    /// Hand over an active request's params before finishing it; they are
+    /// kept, sanitized, if the request is already over the slow log threshold
+    pub fn capture_params(&self, request_id: &str, params: Option<&serde_json::Value>) {
+        let Some(params) = params else {
+            return;
+        };
+        if !self.config.enabled || !self.slow_log.enabled() {
+            return;
+        }
+        let threshold = self.slow_log.threshold().as_millis() as u64;
+        self.active_requests.with(request_id, |metrics| {
+            if current_timestamp().saturating_sub(metrics.start_time) >= threshold {
+                self.slow_log.capture_params(request_id, params);
+            }
+        });
+    }
+
+    /// Up to `limit` requests from the slow log, newest first, optionally
+    /// only those for `method`
+    pub fn get_slow_requests(&self, limit: usize, method: Option<&str>) -> Result<Vec<SlowRequest>> {
+        self.flush();
+        Ok(self.slow_log.entries(limit, method))
+    }
+
+    /// Push buffered spans and current metrics to the OpenTelemetry collector
+    pub fn export_otlp(&self) -> Result<()> {
+        match &self.otlp {
+            Some(otlp) => otlp.export(&self.otlp_samples()?),
+            None => Err(MonitoringError::ConfigError("OpenTelemetry export is not configured".to_string())),
+        }
+    }
+
+    /// Export in the background once the export interval has passed or the
+    /// span buffer is full, keeping the collector off the request path
+    fn maybe_export_otlp(&self) -> Result<()> {
+        let Some(otlp) = &self.otlp else {
+            return Ok(());
+        };
+        if !otlp.claim_export(self.config.export_interval) {
+            return Ok(());
+        }
+
+        let samples = self.otlp_samples()?;
+        let otlp = otlp.clone();
+        std::thread::spawn(move || {
+            // Failures are counted by the exporter
+            let _ = otlp.export(&samples);
+        });
+        Ok(())
+    }
+
+    fn otlp_samples(&self) -> Result<Vec<MetricSample>> {
+        let health = self.get_health_status()?;
+        let mut samples = self.own_samples(&health);
+        samples.extend(self.collect_source_metrics());
+        Ok(samples)
+    }
+
     /// Get current health status
     pub fn get_health_status(&self) -> Result<HealthStatus> {
         let now = current_timestamp();
         let uptime = self.start_time.elapsed().as_secs();
         
+        self.flush();
         let completed = self.completed_requests.lock().unwrap();
-        let active = self.active_requests.lock().unwrap();
+        let active_count = self.active_requests.len();
         
         // Calculate recent metrics (last 5 minutes)
         let recent_window = Duration::from_secs(300);
@@ -307,10 +687,11 @@ impl RpcMonitor {
             .filter(|r| r.start_time >= cutoff_time)
             .collect();
         
-        let total_recent = recent_requests.len() as u64;
-        let successful_recent = recent_requests.iter()
+        let total_recent: u64 = recent_requests.iter().map(|r| u64::from(r.sample_weight)).sum();
+        let successful_recent: u64 = recent_requests.iter()
             .filter(|r| matches!(r.status, RequestStatus::Success))
-            .count() as u64;
+            .map(|r| u64::from(r.sample_weight))
+            .sum();
         
         let error_rate = if total_recent > 0 {
             ((total_recent - successful_recent) as f64 / total_recent as f64) * 100.0
@@ -318,23 +699,20 @@ impl RpcMonitor {
             0.0
         };
         
-        let avg_response_time = if !recent_requests.is_empty() {
-            recent_requests.iter()
-                .filter_map(|r| r.duration_ms)
-                .map(|d| d as f64)
-                .sum::<f64>() / recent_requests.len() as f64
-        } else {
-            0.0
-        };
+        let avg_response_time = weighted_average_duration(&timed_durations(&recent_requests));
         
         let current_rps = total_recent as f64 / recent_window.as_secs() as f64;
+        let usage = self.system.sample();
+        let thresholds = &self.config.alert_thresholds;
+        let over_memory = matches!((usage.memory_usage_mb, thresholds.max_memory_mb), (Some(used), Some(max)) if used > max);
+        let over_cpu = matches!((usage.cpu_usage_percent, thresholds.max_cpu_percent), (Some(used), Some(max)) if used > max);
         
         // Determine overall health
-        let overall_status = if error_rate > self.config.alert_thresholds.max_error_rate_percent {
+        let mut overall_status = if error_rate > self.config.alert_thresholds.max_error_rate_percent {
             HealthLevel::Critical
         } else if avg_response_time > self.config.alert_thresholds.max_response_time_ms as f64 {
             HealthLevel::Warning
-        } else if active.len() > self.config.alert_thresholds.max_concurrent_requests as usize {
+        } else if active_count > self.config.alert_thresholds.max_concurrent_requests as usize || over_memory || over_cpu {
             HealthLevel::Warning
         } else {
             HealthLevel::Healthy
@@ -348,15 +726,23 @@ impl RpcMonitor {
             response_time_ms: Some(avg_response_time as u64),
         });
 
+        // The node is as healthy as its least healthy component
+        for (component, health) in self.probe_results.lock().unwrap().iter() {
+            if health_rank(&health.status) > health_rank(&overall_status) {
+                overall_status = health.status.clone();
+            }
+            component_statuses.insert(component.clone(), health.clone());
+        }
+
         let metrics_summary = MetricsSummary {
             uptime_seconds: uptime,
-            total_requests: completed.len() as u64,
+            total_requests: completed.iter().map(|r| u64::from(r.sample_weight)).sum(),
             current_rps,
             avg_response_time_ms: avg_response_time,
             error_rate_percent: error_rate,
-            memory_usage_mb: None, // Would be implemented with system metrics
-            cpu_usage_percent: None, // Would be implemented with system metrics
-            concurrent_requests: active.len() as u32,
+            memory_usage_mb: usage.memory_usage_mb,
+            cpu_usage_percent: usage.cpu_usage_percent,
+            concurrent_requests: active_count as u32,
         };
 
         Ok(HealthStatus {
@@ -370,7 +756,7 @@ impl RpcMonitor {
     /// Get aggregated metrics for a time range
     pub fn get_metrics(&self, window: Duration) -> Result<Vec<AggregatedMetrics>> {
         let aggregated = self.aggregated_metrics.lock().unwrap();
-        let cutoff_time = current_timestamp() - window.as_millis() as u64;
+        let cutoff_time = current_timestamp().saturating_sub(window.as_millis() as u64);
         
         Ok(aggregated.iter()
             .filter(|m| m.timestamp >= cutoff_time)
@@ -380,8 +766,9 @@ impl RpcMonitor {
 
     /// Get method-specific metrics
     pub fn get_method_metrics(&self, method: &str, window: Duration) -> Result<Vec<RequestMetrics>> {
+        self.flush();
         let completed = self.completed_requests.lock().unwrap();
-        let cutoff_time = current_timestamp() - window.as_millis() as u64;
+        let cutoff_time = current_timestamp().saturating_sub(window.as_millis() as u64);
         
         Ok(completed.iter()
             .filter(|r| r.method == method && r.start_time >= cutoff_time)
@@ -389,6 +776,79 @@ impl RpcMonitor {
             .collect())
     }
 
+    /// Get per-client metrics for a time range, in no particular order;
+    /// requests without a client are left out
+    pub fn get_client_metrics(&self, window: Duration) -> Result<Vec<ClientMetrics>> {
+        self.flush();
+        let completed = self.completed_requests.lock().unwrap();
+        let cutoff_time = current_timestamp().saturating_sub(window.as_millis() as u64);
+        
+        let mut clients: HashMap<&str, (ClientMetrics, u64)> = HashMap::new();
+        for request in completed.iter().filter(|r| r.start_time >= cutoff_time) {
+            let Some(client_id) = request.client_id.as_deref() else {
+                continue;
+            };
+            let (entry, total_duration) = clients.entry(client_id).or_insert_with(|| (ClientMetrics {
+                client_id: client_id.to_string(),
+                request_count: 0,
+                error_count: 0,
+                error_rate_percent: 0.0,
+                avg_duration_ms: 0.0,
+                total_request_size: 0,
+                total_response_size: 0,
+            }, 0));
+            
+            let weight = u64::from(request.sample_weight);
+            entry.request_count += weight;
+            if !matches!(request.status, RequestStatus::Success) {
+                entry.error_count += weight;
+            }
+            entry.total_request_size += request.request_size as u64 * weight;
+            entry.total_response_size += request.response_size.unwrap_or(0) as u64 * weight;
+            *total_duration += request.duration_ms.unwrap_or(0) * weight;
+        }
+        
+        Ok(clients.into_values()
+            .map(|(mut metrics, total_duration)| {
+                metrics.error_rate_percent = (metrics.error_count as f64 / metrics.request_count as f64) * 100.0;
+                metrics.avg_duration_ms = total_duration as f64 / metrics.request_count as f64;
+                metrics
+            })
+            .collect())
+    }
+
+    /// The `limit` clients ranking highest by `ranking` over a time range;
+    /// ties go to the client with more requests
+    pub fn top_clients(&self, ranking: ClientRanking, limit: usize, window: Duration) -> Result<Vec<ClientMetrics>> {
+        let mut clients = self.get_client_metrics(window)?;
+        clients.sort_by(|a, b| {
+            let order = match ranking {
+                ClientRanking::RequestCount => a.request_count.cmp(&b.request_count),
+                ClientRanking::ErrorRate => a.error_rate_percent.total_cmp(&b.error_rate_percent),
+                ClientRanking::Bytes => a.total_bytes().cmp(&b.total_bytes()),
+            };
+            order.then(a.request_count.cmp(&b.request_count))
+                .reverse()
+                .then_with(|| a.client_id.cmp(&b.client_id))
+        });
+        clients.truncate(limit);
+        Ok(clients)
+    }
+
+    /// Compliance, remaining error budget and burn rates of each SLO
+    pub fn get_slo_status(&self) -> Result<Vec<SloStatus>> {
+        self.flush();
+        Ok(self.slo.status(current_timestamp()))
+    }
+
+    /// Methods whose latency or error rate in the latest aggregated window
+    /// rose past the z-score threshold; empty unless anomaly detection is
+    /// configured
+    pub fn get_anomalies(&self) -> Result<Vec<Anomaly>> {
+        self.maybe_aggregate_metrics()?;
+        Ok(self.anomaly.as_ref().map(|anomaly| anomaly.anomalies()).unwrap_or_default())
+    }
+
     /// Get active alerts
     pub fn get_active_alerts(&self) -> Result<Vec<Alert>> {
         let alerts = self.active_alerts.lock().unwrap();
@@ -401,118 +861,69 @@ impl RpcMonitor {
     }
 
     fn maybe_aggregate_metrics(&self) -> Result<()> {
-        let mut last_agg = self.last_aggregation.lock().unwrap();
-        
-        if last_agg.elapsed() < Duration::from_secs(60) {
+        let now = current_timestamp();
+        if now < self.next_aggregation.load(Ordering::Relaxed) {
             return Ok(());
         }
+        self.aggregate_until(now)
+    }
 
-        let now = current_timestamp();
-        let window_start = *last_agg;
-        let window_duration = window_start.elapsed();
+    /// Aggregate every window that ended by `now`, in epoch millis
+    ///
+    /// Windows are `aggregation_window` wide and aligned to the epoch, and a
+    /// request counts towards the window it finished in.
+    fn aggregate_until(&self, now: u64) -> Result<()> {
+        let width = self.config.aggregation_window.as_millis() as u64;
+        if width == 0 {
+            return Err(MonitoringError::ConfigError("aggregation_window must not be zero".to_string()));
+        }
+        let current_window = now - now % width;
         
-        let completed = self.completed_requests.lock().unwrap();
-        let window_requests: Vec<_> = completed.iter()
-            .filter(|r| {
-                let request_instant = Instant::now() - Duration::from_millis((now - r.start_time) as u64);
-                request_instant >= window_start
-            })
-            .collect();
-
-        if window_requests.is_empty() {
-            *last_agg = Instant::now();
+        self.flush();
+        let mut aggregated_until = self.aggregated_until.lock().unwrap();
+        self.next_aggregation.store(current_window + width, Ordering::Relaxed);
+        if *aggregated_until >= current_window {
             return Ok(());
         }
+        let retention_cutoff = now.saturating_sub(self.config.metrics_retention.as_millis() as u64);
+        let first_window = (*aggregated_until).max(retention_cutoff - retention_cutoff % width);
 
-        let total_requests = window_requests.len() as u64;
-        let successful_requests = window_requests.iter()
-            .filter(|r| matches!(r.status, RequestStatus::Success))
-            .count() as u64;
-        let failed_requests = total_requests - successful_requests;
-
-        let durations: Vec<u64> = window_requests.iter()
-            .filter_map(|r| r.duration_ms)
-            .collect();
-
-        let avg_response_time_ms = if !durations.is_empty() {
-            durations.iter().sum::<u64>() as f64 / durations.len() as f64
-        } else {
-            0.0
-        };
-
-        let min_response_time_ms = durations.iter().min().copied().unwrap_or(0);
-        let max_response_time_ms = durations.iter().max().copied().unwrap_or(0);
-        let requests_per_second = total_requests as f64 / window_duration.as_secs() as f64;
-        let error_rate_percent = (failed_requests as f64 / total_requests as f64) * 100.0;
-
-        // Calculate method breakdown
-        let mut method_breakdown = HashMap::new();
-        for request in &window_requests {
-            let entry = method_breakdown.entry(request.method.clone()).or_insert(MethodMetrics {
-                call_count: 0,
-                success_count: 0,
-                avg_duration_ms: 0.0,
-                min_duration_ms: u64::MAX,
-                max_duration_ms: 0,
-                total_request_size: 0,
-                total_response_size: 0,
-            });
-
-            entry.call_count += 1;
-            if matches!(request.status, RequestStatus::Success) {
-                entry.success_count += 1;
-            }
-            entry.total_request_size += request.request_size as u64;
-            if let Some(response_size) = request.response_size {
-                entry.total_response_size += response_size as u64;
-            }
-            if let Some(duration) = request.duration_ms {
-                entry.min_duration_ms = entry.min_duration_ms.min(duration);
-                entry.max_duration_ms = entry.max_duration_ms.max(duration);
+        let completed = self.completed_requests.lock().unwrap();
+        let mut windows: HashMap<u64, Vec<&RequestMetrics>> = HashMap::new();
+        for request in completed.iter() {
+            let finished = request.end_time.unwrap_or(request.start_time);
+            if finished >= first_window && finished < current_window {
+                windows.entry(finished - finished % width).or_default().push(request);
             }
         }
 
-        // Calculate average durations for each method
-        for (method, metrics) in &mut method_breakdown {
-            let method_durations: Vec<u64> = window_requests.iter()
-                .filter(|r| r.method == *method)
-                .filter_map(|r| r.duration_ms)
-                .collect();
-            
-            if !method_durations.is_empty() {
-                metrics.avg_duration_ms = method_durations.iter().sum::<u64>() as f64 / method_durations.len() as f64;
+        let mut agg_metrics = self.aggregated_metrics.lock().unwrap();
+        let mut window_start = first_window;
+        let mut persisted = Ok(());
+        while window_start < current_window {
+            let window_requests = windows.remove(&window_start).unwrap_or_default();
+            let window = aggregate_window(window_start, self.config.aggregation_window, &window_requests);
+            if let Some(history) = &self.history {
+                persisted = persisted.and(history.record(&window));
+            }
+            if let Some(anomaly) = &self.anomaly {
+                anomaly.observe(&window);
             }
+            agg_metrics.push_back(window);
+            window_start += width;
         }
 
-        let aggregated = AggregatedMetrics {
-            timestamp: now,
-            window_duration,
-            total_requests,
-            successful_requests,
-            failed_requests,
-            avg_response_time_ms,
-            min_response_time_ms,
-            max_response_time_ms,
-            requests_per_second,
-            error_rate_percent,
-            method_breakdown,
-        };
-
-        let mut agg_metrics = self.aggregated_metrics.lock().unwrap();
-        agg_metrics.push_back(aggregated);
-
-        // Clean up old metrics
-        let retention_cutoff = now - self.config.metrics_retention.as_millis() as u64;
+        // Drop windows that ended before the retention period
         while let Some(front) = agg_metrics.front() {
-            if front.timestamp < retention_cutoff {
+            if front.timestamp + width <= retention_cutoff {
                 agg_metrics.pop_front();
             } else {
                 break;
             }
         }
 
-        *last_agg = Instant::now();
-        Ok(())
+        *aggregated_until = current_window;
+        persisted
     }
 
     /// Check for alert conditions and trigger alerts
@@ -521,60 +932,86 @@ impl RpcMonitor {
         let mut new_alerts = Vec::new();
         let mut alerts = self.active_alerts.lock().unwrap();
 
+        let thresholds = &self.config.alert_thresholds;
+        let summary = &health.metrics_summary;
+
         // High response time alert
-        if health.metrics_summary.avg_response_time_ms > self.config.alert_thresholds.max_response_time_ms as f64 {
-            let alert_id = "high_response_time".to_string();
-            if !alerts.contains_key(&alert_id) {
-                let alert = Alert {
-                    id: alert_id.clone(),
-                    alert_type: AlertType::HighResponseTime,
-                    severity: AlertSeverity::Warning,
-                    message: format!("Average response time ({:.1}ms) exceeds threshold ({}ms)", 
-                        health.metrics_summary.avg_response_time_ms, 
-                        self.config.alert_thresholds.max_response_time_ms),
-                    triggered_at: current_timestamp(),
-                    resolved_at: None,
-                    metadata: HashMap::new(),
-                };
-                alerts.insert(alert_id, alert.clone());
-                new_alerts.push(alert);
-            }
-        } else {
-            // Resolve alert if it exists
-            if let Some(mut alert) = alerts.remove("high_response_time") {
-                alert.resolved_at = Some(current_timestamp());
-                new_alerts.push(alert);
-            }
-        }
+        let high_response_time = (summary.avg_response_time_ms > thresholds.max_response_time_ms as f64).then(|| {
+            format!("Average response time ({:.1}ms) exceeds threshold ({}ms)", 
+                summary.avg_response_time_ms, 
+                thresholds.max_response_time_ms)
+        });
+        update_alert(&mut alerts, &mut new_alerts, "high_response_time", AlertType::HighResponseTime, AlertSeverity::Warning, high_response_time);
 
         // High error rate alert
-        if health.metrics_summary.error_rate_percent > self.config.alert_thresholds.max_error_rate_percent {
-            let alert_id = "high_error_rate".to_string();
-            if !alerts.contains_key(&alert_id) {
-                let alert = Alert {
-                    id: alert_id.clone(),
-                    alert_type: AlertType::HighErrorRate,
-                    severity: AlertSeverity::Critical,
-                    message: format!("Error rate ({:.1}%) exceeds threshold ({:.1}%)", 
-                        health.metrics_summary.error_rate_percent, 
-                        self.config.alert_thresholds.max_error_rate_percent),
-                    triggered_at: current_timestamp(),
-                    resolved_at: None,
-                    metadata: HashMap::new(),
-                };
-                alerts.insert(alert_id, alert.clone());
-                new_alerts.push(alert);
-            }
-        } else {
-            if let Some(mut alert) = alerts.remove("high_error_rate") {
-                alert.resolved_at = Some(current_timestamp());
-                new_alerts.push(alert);
-            }
+        let high_error_rate = (summary.error_rate_percent > thresholds.max_error_rate_percent).then(|| {
+            format!("Error rate ({:.1}%) exceeds threshold ({:.1}%)", 
+                summary.error_rate_percent, 
+                thresholds.max_error_rate_percent)
+        });
+        update_alert(&mut alerts, &mut new_alerts, "high_error_rate", AlertType::HighErrorRate, AlertSeverity::Critical, high_error_rate);
+
+        // Process resource alerts
+        let high_memory = match (summary.memory_usage_mb, thresholds.max_memory_mb) {
+            (Some(used), Some(max)) if used > max => Some(format!("Memory usage ({}MB) exceeds threshold ({}MB)", used, max)),
+            _ => None,
+        };
+        update_alert(&mut alerts, &mut new_alerts, "high_memory_usage", AlertType::HighMemoryUsage, AlertSeverity::Warning, high_memory);
+
+        let high_cpu = match (summary.cpu_usage_percent, thresholds.max_cpu_percent) {
+            (Some(used), Some(max)) if used > max => Some(format!("CPU usage ({:.1}%) exceeds threshold ({:.1}%)", used, max)),
+            _ => None,
+        };
+        update_alert(&mut alerts, &mut new_alerts, "high_cpu_usage", AlertType::HighCpuUsage, AlertSeverity::Warning, high_cpu);
+
+        // Error budget burn alerts
+        for (id, severity, message) in self.slo.burn_alerts(current_timestamp()) {
+            update_alert(&mut alerts, &mut new_alerts, &id, AlertType::ErrorBudgetBurn, severity, message);
+        }
+
+        // Anomalies in the latest window, against each method's baseline
+        for (id, message) in self.anomaly.iter().flat_map(|anomaly| anomaly.alerts()) {
+            update_alert(&mut alerts, &mut new_alerts, &id, AlertType::Custom("anomaly".to_string()), AlertSeverity::Warning, message);
+        }
+
+        // Canary alerts, from each target's latest check
+        for canary in &self.canaries {
+            let target = canary.target();
+            let outcome = canary.last_outcome();
+            let unavailable = outcome.as_ref().and_then(|outcome| {
+                let failed: Vec<&str> = outcome.failed_calls().map(|call| call.method.as_str()).collect();
+                (!failed.is_empty()).then(|| format!("Canary {} failed to call {}", target.name, failed.join(", ")))
+            });
+            update_alert(&mut alerts, &mut new_alerts, &format!("canary_unavailable_{}", target.name), AlertType::CanaryUnavailable, AlertSeverity::Critical, unavailable);
+
+            let max_latency_ms = target.max_latency.as_millis() as u64;
+            let high_latency = outcome.as_ref()
+                .and_then(|outcome| outcome.slowest_call())
+                .filter(|call| call.latency_ms > max_latency_ms)
+                .map(|call| format!("Canary {} call to {} took {}ms, exceeding threshold ({}ms)", target.name, call.method, call.latency_ms, max_latency_ms));
+            update_alert(&mut alerts, &mut new_alerts, &format!("canary_high_latency_{}", target.name), AlertType::CanaryHighLatency, AlertSeverity::Warning, high_latency);
         }
 
+        self.notify(&new_alerts);
         Ok(new_alerts)
     }
 
+    /// Send triggered and resolved alerts in the background, as deliveries
+    /// may be retried for a while
+    fn notify(&self, alerts: &[Alert]) {
+        if alerts.is_empty() || !self.notifier.has_channels() {
+            return;
+        }
+        let notifier = self.notifier.clone();
+        let alerts = alerts.to_vec();
+        std::thread::spawn(move || {
+            for alert in &alerts {
+                // Failures are counted by the notifier
+                let _ = notifier.notify(alert);
+            }
+        });
+    }
+
     /// Export metrics in various formats
     pub fn export_metrics(&self, format: ExportFormat) -> Result<String> {
         let health = self.get_health_status()?;
@@ -590,29 +1027,22 @@ impl RpcMonitor {
         }
     }
 
+    /// The monitor's own metrics: request totals, per-method counters and
+    /// durations, SLO compliance, canary results and anomaly scores
+    fn own_samples(&self, health: &HealthStatus) -> Vec<MetricSample> {
+        let mut samples = monitor_samples(health);
+        samples.extend(method_samples(&self.method_totals.lock().unwrap()));
+        samples.extend(slo::slo_samples(&self.slo.status(current_timestamp())));
+        samples.extend(canary::canary_samples(&self.canaries));
+        if let Some(anomaly) = &self.anomaly {
+            samples.extend(anomaly.samples());
+        }
+        samples
+    }
+
     fn format_prometheus_metrics(&self, health: &HealthStatus) -> String {
-        let mut output = String::new();
-        
-        output.push_str(&format!("# HELP cc_rpc_uptime_seconds Total uptime in seconds\n"));
-        output.push_str(&format!("# TYPE cc_rpc_uptime_seconds counter\n"));
-        output.push_str(&format!("cc_rpc_uptime_seconds {}\n\n", health.metrics_summary.uptime_seconds));
-        
-        output.push_str(&format!("# HELP cc_rpc_requests_total Total number of RPC requests\n"));
-        output.push_str(&format!("# TYPE cc_rpc_requests_total counter\n"));
-        output.push_str(&format!("cc_rpc_requests_total {}\n\n", health.metrics_summary.total_requests));
-        
-        output.push_str(&format!("# HELP cc_rpc_requests_per_second Current requests per second\n"));
-        output.push_str(&format!("# TYPE cc_rpc_requests_per_second gauge\n"));
-        output.push_str(&format!("cc_rpc_requests_per_second {}\n\n", health.metrics_summary.current_rps));
-        
-        output.push_str(&format!("# HELP cc_rpc_response_time_ms Average response time in milliseconds\n"));
-        output.push_str(&format!("# TYPE cc_rpc_response_time_ms gauge\n"));
-        output.push_str(&format!("cc_rpc_response_time_ms {}\n\n", health.metrics_summary.avg_response_time_ms));
-        
-        output.push_str(&format!("# HELP cc_rpc_error_rate_percent Error rate percentage\n"));
-        output.push_str(&format!("# TYPE cc_rpc_error_rate_percent gauge\n"));
-        output.push_str(&format!("cc_rpc_error_rate_percent {}\n\n", health.metrics_summary.error_rate_percent));
-        
+        let mut output = format_metric_samples(&self.own_samples(health));
+        output.push_str(&format_metric_samples(&self.collect_source_metrics()));
         output
     }
 }
@@ -630,6 +1060,214 @@ pub enum ExportFormat {
     Prometheus,
 }
 
+/// Metrics of the requests that finished in the window starting at `timestamp`
+/// `(duration_ms, sample_weight)` of the requests that recorded a duration
+fn timed_durations(requests: &[&RequestMetrics]) -> Vec<(u64, u64)> {
+    requests.iter()
+        .filter_map(|r| Some((r.duration_ms?, u64::from(r.sample_weight))))
+        .collect()
+}
+
+/// Mean duration weighted by sample weight, over timed requests only
+fn weighted_average_duration(durations: &[(u64, u64)]) -> f64 {
+    let weights: u64 = durations.iter().map(|(_, weight)| weight).sum();
+    if weights == 0 {
+        return 0.0;
+    }
+    durations.iter().map(|(duration, weight)| duration * weight).sum::<u64>() as f64 / weights as f64
+}
+
+fn aggregate_window(timestamp: u64, window_duration: Duration, window_requests: &[&RequestMetrics]) -> AggregatedMetrics {
+    // Sampled requests stand for `sample_weight` requests each
+    let total_requests: u64 = window_requests.iter().map(|r| u64::from(r.sample_weight)).sum();
+    let successful_requests: u64 = window_requests.iter()
+        .filter(|r| matches!(r.status, RequestStatus::Success))
+        .map(|r| u64::from(r.sample_weight))
+        .sum();
+    let failed_requests = total_requests - successful_requests;
+
+    let durations = timed_durations(window_requests);
+    let avg_response_time_ms = weighted_average_duration(&durations);
+
+    let min_response_time_ms = durations.iter().map(|(duration, _)| *duration).min().unwrap_or(0);
+    let max_response_time_ms = durations.iter().map(|(duration, _)| *duration).max().unwrap_or(0);
+    let requests_per_second = total_requests as f64 / window_duration.as_secs_f64();
+    let error_rate_percent = if total_requests > 0 {
+        (failed_requests as f64 / total_requests as f64) * 100.0
+    } else {
+        0.0
+    };
+
+    // Calculate method breakdown
+    let mut method_breakdown = HashMap::new();
+    let mut method_durations: HashMap<&str, (u64, u64)> = HashMap::new();
+    for request in window_requests {
+        let entry = method_breakdown.entry(request.method.clone()).or_insert(MethodMetrics {
+            call_count: 0,
+            success_count: 0,
+            avg_duration_ms: 0.0,
+            min_duration_ms: 0,
+            max_duration_ms: 0,
+            total_request_size: 0,
+            total_response_size: 0,
+        });
+
+        let weight = u64::from(request.sample_weight);
+        entry.call_count += weight;
+        if matches!(request.status, RequestStatus::Success) {
+            entry.success_count += weight;
+        }
+        entry.total_request_size += request.request_size as u64 * weight;
+        if let Some(response_size) = request.response_size {
+            entry.total_response_size += response_size as u64 * weight;
+        }
+        if let Some(duration) = request.duration_ms {
+            let (total, count) = method_durations.entry(&request.method).or_insert((0, 0));
+            entry.min_duration_ms = if *count == 0 { duration } else { entry.min_duration_ms.min(duration) };
+            entry.max_duration_ms = entry.max_duration_ms.max(duration);
+            *total += duration * weight;
+            *count += weight;
+        }
+    }
+
+    // Calculate average durations for each method
+    for (method, metrics) in &mut method_breakdown {
+        if let Some((total, count)) = method_durations.get(method.as_str()) {
+            metrics.avg_duration_ms = *total as f64 / *count as f64;
+        }
+    }
+
+    AggregatedMetrics {
+        timestamp,
+        window_duration,
+        total_requests,
+        successful_requests,
+        failed_requests,
+        avg_response_time_ms,
+        min_response_time_ms,
+        max_response_time_ms,
+        requests_per_second,
+        error_rate_percent,
+        method_breakdown,
+    }
+}
+
+/// The monitor's own metrics, as exported to Prometheus and OpenTelemetry
+fn monitor_samples(health: &HealthStatus) -> Vec<MetricSample> {
+    let summary = &health.metrics_summary;
+    let mut samples = vec![
+        MetricSample::counter("cc_rpc_uptime_seconds", "Total uptime in seconds", summary.uptime_seconds as f64),
+        MetricSample::counter("cc_rpc_requests_total", "Total number of RPC requests", summary.total_requests as f64),
+        MetricSample::gauge("cc_rpc_requests_per_second", "Current requests per second", summary.current_rps),
+        MetricSample::gauge("cc_rpc_response_time_ms", "Average response time in milliseconds", summary.avg_response_time_ms),
+        MetricSample::gauge("cc_rpc_error_rate_percent", "Error rate percentage", summary.error_rate_percent),
+    ];
+    if let Some(memory) = summary.memory_usage_mb {
+        samples.push(MetricSample::gauge("cc_rpc_memory_usage_mb", "Resident memory of the node process in megabytes", memory as f64));
+    }
+    if let Some(cpu) = summary.cpu_usage_percent {
+        samples.push(MetricSample::gauge("cc_rpc_cpu_usage_percent", "CPU usage of the node process in percent of one core", cpu));
+    }
+    samples
+}
+
+/// Per-method request and error counters, and a request duration histogram
+/// in Prometheus form; each metric's samples are kept together
+fn method_samples(totals: &HashMap<String, MethodTotals>) -> Vec<MetricSample> {
+    let mut methods: Vec<(&String, &MethodTotals)> = totals.iter().collect();
+    methods.sort_by_key(|(method, _)| *method);
+    let mut samples = Vec::new();
+    
+    for (method, totals) in &methods {
+        samples.push(MetricSample::counter("cc_rpc_method_requests_total", "Total number of RPC requests per method", totals.requests as f64).with_label("method", *method));
+    }
+    for (method, totals) in &methods {
+        samples.push(MetricSample::counter("cc_rpc_method_errors_total", "Total number of failed or timed out RPC requests per method", totals.errors as f64).with_label("method", *method));
+    }
+    for (method, totals) in &methods {
+        let mut cumulative = 0;
+        for (bound, count) in DURATION_BUCKETS_MS.iter().zip(totals.buckets) {
+            cumulative += count;
+            samples.push(MetricSample::counter("cc_rpc_request_duration_ms_bucket", "RPC requests per method finished within each duration in milliseconds", cumulative as f64)
+                .with_label("method", *method)
+                .with_label("le", bound.to_string()));
+        }
+        samples.push(MetricSample::counter("cc_rpc_request_duration_ms_bucket", "RPC requests per method finished within each duration in milliseconds", totals.requests as f64)
+            .with_label("method", *method)
+            .with_label("le", "+Inf"));
+    }
+    for (method, totals) in &methods {
+        samples.push(MetricSample::counter("cc_rpc_request_duration_ms_sum", "Total duration of RPC requests per method in milliseconds", totals.duration_sum_ms as f64).with_label("method", *method));
+    }
+    for (method, totals) in &methods {
+        samples.push(MetricSample::counter("cc_rpc_request_duration_ms_count", "Total number of timed RPC requests per method", totals.requests as f64).with_label("method", *method));
+    }
+    samples
+}
+
+/// Render metric samples in Prometheus text format, grouping samples by name
+fn format_metric_samples(samples: &[MetricSample]) -> String {
+    let mut output = String::new();
+    let mut described = std::collections::HashSet::new();
+
+    for sample in samples {
+        if described.insert(sample.name.as_str()) {
+            output.push_str(&format!("# HELP {} {}\n", sample.name, sample.help));
+            output.push_str(&format!("# TYPE {} {}\n", sample.name, sample.kind.as_str()));
+        }
+
+        if sample.labels.is_empty() {
+            output.push_str(&format!("{} {}\n", sample.name, sample.value));
+        } else {
+            let labels: Vec<String> = sample.labels.iter()
+                .map(|(key, value)| format!("{}=\"{}\"", key, value))
+                .collect();
+            output.push_str(&format!("{}{{{}}} {}\n", sample.name, labels.join(","), sample.value));
+        }
+    }
+
+    output
+}
+
+/// Raise the alert `id` with `message` if it is not active, or resolve it
+/// when there is no message; changes are added to `changed`
+fn update_alert(alerts: &mut HashMap<String, Alert>, changed: &mut Vec<Alert>, id: &str, alert_type: AlertType, severity: AlertSeverity, message: Option<String>) {
+    match message {
+        Some(message) => {
+            if !alerts.contains_key(id) {
+                let alert = Alert {
+                    id: id.to_string(),
+                    alert_type,
+                    severity,
+                    message,
+                    triggered_at: current_timestamp(),
+                    resolved_at: None,
+                    metadata: HashMap::new(),
+                };
+                alerts.insert(id.to_string(), alert.clone());
+                changed.push(alert);
+            }
+        }
+        None => {
+            // Resolve alert if it exists
+            if let Some(mut alert) = alerts.remove(id) {
+                alert.resolved_at = Some(current_timestamp());
+                changed.push(alert);
+            }
+        }
+    }
+}
+
+/// Order of health levels from healthy to down
+fn health_rank(level: &HealthLevel) -> u8 {
+    match level {
+        HealthLevel::Healthy => 0,
+        HealthLevel::Warning => 1,
+        HealthLevel::Critical => 2,
+        HealthLevel::Down => 3,
+    }
+}
+
 /// Utility function to get current timestamp
 fn current_timestamp() -> u64 {
     SystemTime::now()
@@ -672,6 +1310,7 @@ mod tests {
         monitor.complete_request(request_id, 200).unwrap();
         
         // Check that request was recorded
+        monitor.flush();
         let completed = monitor.completed_requests.lock().unwrap();
         assert_eq!(completed.len(), 1);
         
@@ -690,6 +1329,7 @@ mod tests {
         monitor.start_request(request_id.clone(), "test_method".to_string(), 100).unwrap();
         monitor.fail_request(request_id, -32602).unwrap();
         
+        monitor.flush();
         let completed = monitor.completed_requests.lock().unwrap();
         assert_eq!(completed.len(), 1);
         
@@ -708,6 +1348,33 @@ mod tests {
         assert_eq!(health.metrics_summary.concurrent_requests, 0);
     }
 
+    #[test]
+    fn test_health_average_counts_timed_requests_only() {
+        let monitor = RpcMonitor::new();
+        let now = current_timestamp();
+        let request = |duration_ms: Option<u64>, sample_weight: u32| RequestMetrics {
+            method: "test_method".to_string(),
+            start_time: now,
+            end_time: duration_ms.map(|duration| now + duration),
+            duration_ms,
+            status: RequestStatus::Success,
+            error_code: None,
+            request_size: 10,
+            response_size: None,
+            client_id: None,
+            sample_weight,
+        };
+        {
+            let mut completed = monitor.completed_requests.lock().unwrap();
+            completed.push_back(request(Some(80), 2));
+            completed.push_back(request(Some(20), 1));
+            completed.push_back(request(None, 3));
+        }
+
+        let health = monitor.get_health_status().unwrap();
+        assert_eq!(health.metrics_summary.avg_response_time_ms, 60.0);
+    }
+
     #[test]
     fn test_metrics_aggregation() {
         let monitor = RpcMonitor::new();
@@ -726,6 +1393,63 @@ mod tests {
         // This is expected behavior in the test environment
     }
 
+    #[test]
+    fn test_window_aggregation() {
+        let monitor = RpcMonitor::new();
+        let minute = 60_000;
+        let base = 1_700_000_040_000 - 1_700_000_040_000 % minute;
+        *monitor.aggregated_until.lock().unwrap() = base;
+        
+        let finished = |method: &str, end_time: u64, duration_ms: u64, status: RequestStatus| RequestMetrics {
+            method: method.to_string(),
+            start_time: end_time - duration_ms,
+            end_time: Some(end_time),
+            duration_ms: Some(duration_ms),
+            status,
+            error_code: None,
+            request_size: 10,
+            response_size: Some(20),
+            client_id: None,
+            sample_weight: 1,
+        };
+        {
+            let mut completed = monitor.completed_requests.lock().unwrap();
+            // Started in the first window, finished in the second
+            completed.push_back(finished("method_a", base + minute + 10, 50, RequestStatus::Success));
+            completed.push_back(finished("method_a", base + 100, 30, RequestStatus::Success));
+            completed.push_back(finished("method_b", base + 200, 90, RequestStatus::Error));
+            completed.push_back(finished("method_a", base + 3 * minute + 5, 10, RequestStatus::Success));
+        }
+        
+        monitor.aggregate_until(base + 3 * minute + 30).unwrap();
+        let aggregated = monitor.aggregated_metrics.lock().unwrap().clone();
+        let timestamps: Vec<u64> = aggregated.iter().map(|m| m.timestamp).collect();
+        assert_eq!(timestamps, [base, base + minute, base + 2 * minute]);
+        
+        let first = &aggregated[0];
+        assert_eq!(first.total_requests, 2);
+        assert_eq!(first.failed_requests, 1);
+        assert_eq!(first.error_rate_percent, 50.0);
+        assert_eq!(first.requests_per_second, 2.0 / 60.0);
+        assert_eq!((first.min_response_time_ms, first.max_response_time_ms), (30, 90));
+        assert_eq!(first.method_breakdown["method_b"].min_duration_ms, 90);
+        assert_eq!(aggregated[1].total_requests, 1);
+        assert_eq!(aggregated[2].total_requests, 0);
+        assert_eq!(aggregated[2].error_rate_percent, 0.0);
+        
+        // Closed windows are not aggregated twice
+        monitor.aggregate_until(base + 3 * minute + 50).unwrap();
+        assert_eq!(monitor.aggregated_metrics.lock().unwrap().len(), 3);
+        
+        // Windows that ended before the retention period are dropped
+        let day = 24 * 60 * minute;
+        monitor.aggregate_until(base + day + 2 * minute).unwrap();
+        let aggregated = monitor.aggregated_metrics.lock().unwrap();
+        assert_eq!(aggregated.front().unwrap().timestamp, base + 2 * minute);
+        assert_eq!(aggregated.back().unwrap().timestamp, base + day + minute);
+        assert_eq!(aggregated[1].total_requests, 1);
+    }
+
     #[test]
     fn test_method_metrics() {
         let monitor = RpcMonitor::new();
@@ -745,6 +1469,47 @@ mod tests {
         assert_eq!(method_b_metrics[0].method, "method_b");
     }
 
+    #[test]
+    fn test_top_clients() {
+        let monitor = RpcMonitor::new();
+        let requests = [
+            ("a", "1.2.3.4", true, 500),
+            ("b", "1.2.3.4", true, 10),
+            ("c", "5.6.7.8", false, 0),
+            ("d", "api-key", true, 20),
+            ("e", "api-key", true, 20),
+            ("f", "api-key", false, 0),
+        ];
+        for (id, client, success, response_size) in requests {
+            monitor.start_client_request(id.to_string(), "test_method".to_string(), 100, Some(client.to_string())).unwrap();
+            if success {
+                monitor.complete_request(id.to_string(), response_size).unwrap();
+            } else {
+                monitor.fail_request(id.to_string(), -32603).unwrap();
+            }
+        }
+        monitor.start_request("g".to_string(), "test_method".to_string(), 100).unwrap();
+        monitor.complete_request("g".to_string(), 100).unwrap();
+        
+        let window = Duration::from_secs(60 * 60);
+        assert_eq!(monitor.get_client_metrics(window).unwrap().len(), 3);
+        
+        let busiest = monitor.top_clients(ClientRanking::RequestCount, 1, window).unwrap();
+        assert_eq!(busiest.len(), 1);
+        assert_eq!(busiest[0].client_id, "api-key");
+        assert_eq!(busiest[0].request_count, 3);
+        assert_eq!(busiest[0].error_count, 1);
+        
+        let failing = monitor.top_clients(ClientRanking::ErrorRate, 3, window).unwrap();
+        let ids: Vec<&str> = failing.iter().map(|c| c.client_id.as_str()).collect();
+        assert_eq!(ids, ["5.6.7.8", "api-key", "1.2.3.4"]);
+        assert_eq!(failing[0].error_rate_percent, 100.0);
+        
+        let heaviest = monitor.top_clients(ClientRanking::Bytes, 1, window).unwrap();
+        assert_eq!(heaviest[0].client_id, "1.2.3.4");
+        assert_eq!(heaviest[0].total_bytes(), 710);
+    }
+
     #[test]
     fn test_alert_thresholds() {
         let thresholds = AlertThresholds::default();
@@ -764,6 +1529,79 @@ mod tests {
         assert!(prometheus_export.contains("cc_rpc_requests_total"));
     }
 
+    #[test]
+    fn test_metrics_source_export() {
+        struct StaticSource;
+
+        impl MetricsSource for StaticSource {
+            fn source_name(&self) -> &str {
+                "static"
+            }
+
+            fn collect(&self) -> Vec<MetricSample> {
+                vec![
+                    MetricSample::counter("cc_test_hits_total", "Test hits", 7.0).with_label("cache", "state"),
+                    MetricSample::counter("cc_test_hits_total", "Test hits", 2.0).with_label("cache", "blocks"),
+                ]
+            }
+        }
+
+        let monitor = RpcMonitor::new();
+        monitor.register_metrics_source(Arc::new(StaticSource));
+
+        let prometheus_export = monitor.export_metrics(ExportFormat::Prometheus).unwrap();
+        assert_eq!(prometheus_export.matches("# TYPE cc_test_hits_total counter").count(), 1);
+        assert!(prometheus_export.contains("cc_test_hits_total{cache=\"state\"} 7"));
+        assert!(prometheus_export.contains("cc_test_hits_total{cache=\"blocks\"} 2"));
+    }
+
+    #[test]
+    fn test_resource_alerts() {
+        let config = MonitoringConfig {
+            alert_thresholds: AlertThresholds {
+                max_memory_mb: Some(0),
+                ..AlertThresholds::default()
+            },
+            ..Default::default()
+        };
+        let monitor = RpcMonitor::with_config(config);
+        let health = monitor.get_health_status().unwrap();
+        if health.metrics_summary.memory_usage_mb.is_none() {
+            // No /proc on this platform
+            return;
+        }
+        assert!(matches!(health.overall_status, HealthLevel::Warning));
+        
+        let alerts = monitor.check_alerts().unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert!(matches!(alerts[0].alert_type, AlertType::HighMemoryUsage));
+        assert!(monitor.export_metrics(ExportFormat::Prometheus).unwrap().contains("cc_rpc_memory_usage_mb "));
+    }
+
+    #[test]
+    fn test_slo_burn_alerts() {
+        let config = MonitoringConfig {
+            slos: vec![SloConfig::availability("availability", 99.9)],
+            ..Default::default()
+        };
+        let monitor = RpcMonitor::with_config(config);
+        monitor.start_request("1".to_string(), "cc_getBlock".to_string(), 10).unwrap();
+        monitor.fail_request("1".to_string(), -32603).unwrap();
+        
+        let slos = monitor.get_slo_status().unwrap();
+        assert_eq!(slos[0].total_requests, 1);
+        assert_eq!(slos[0].compliance_percent, 0.0);
+        
+        let alerts = monitor.check_alerts().unwrap();
+        let burning: Vec<&Alert> = alerts.iter().filter(|a| matches!(a.alert_type, AlertType::ErrorBudgetBurn)).collect();
+        assert_eq!(burning.len(), 3);
+        assert!(matches!(burning[0].severity, AlertSeverity::Critical));
+        assert!(matches!(burning[2].severity, AlertSeverity::Warning));
+        
+        let exported = monitor.export_metrics(ExportFormat::Prometheus).unwrap();
+        assert!(exported.contains("cc_rpc_slo_burn_rate{slo=\"availability\",window=\"5m\"} 1000"));
+    }
+
     #[test]
     fn test_alert_detection() {
         let monitor = RpcMonitor::new();
@@ -0,0 +1,576 @@
+//! Alert notifications
+//!
+//! Alerts raised or resolved by `RpcMonitor::check_alerts` are sent to every
+//! configured channel: a generic JSON webhook, a Slack incoming webhook or
+//! email over SMTP. Failed deliveries are retried with exponential backoff,
+//! and the same event for the same alert is sent at most once per
+//! deduplication window, so a flapping alert does not flood the channels.
+
+use crate::transport::{connect, HttpTransport, Transport};
+use crate::{Alert, AlertSeverity, MonitoringError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Notification configuration
+#[derive(Debug, Clone)]
+pub struct NotificationConfig {
+    pub channels: Vec<ChannelConfig>,
+    /// Further attempts after a failed delivery
+    pub max_retries: u32,
+    /// Wait before the first retry, doubled for each one after it
+    pub retry_backoff: Duration,
+    /// Repeats of an alert event within this window are not sent
+    pub dedup_window: Duration,
+    pub timeout: Duration,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            channels: Vec::new(),
+            max_retries: 3,
+            retry_backoff: Duration::from_secs(1),
+            dedup_window: Duration::from_secs(15 * 60),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// A notification channel to create from configuration
+#[derive(Debug, Clone)]
+pub enum ChannelConfig {
+    /// POST each notification as JSON to `url`
+    Webhook {
+        url: String,
+    },
+    /// Post to a Slack incoming webhook, optionally overriding its channel
+    Slack {
+        webhook_url: String,
+        channel: Option<String>,
+    },
+    Email(SmtpConfig),
+}
+
+/// SMTP relay to send alert emails through
+#[derive(Debug, Clone)]
+pub struct SmtpConfig {
+    /// `host[:port]` of the relay, port 25 by default
+    pub server: String,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+/// Whether an alert started or ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertEvent {
+    Triggered,
+    Resolved,
+}
+
+/// An alert event sent to the channels
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertNotification {
+    pub event: AlertEvent,
+    pub alert: Alert,
+}
+
+impl AlertNotification {
+    pub fn new(alert: Alert) -> Self {
+        let event = match alert.resolved_at {
+            Some(_) => AlertEvent::Resolved,
+            None => AlertEvent::Triggered,
+        };
+        Self { event, alert }
+    }
+
+    /// One-line description, e.g. `[Critical] high_error_rate triggered: ...`
+    pub fn summary(&self) -> String {
+        let event = match self.event {
+            AlertEvent::Triggered => "triggered",
+            AlertEvent::Resolved => "resolved",
+        };
+        format!(
+            "[{:?}] {} {}: {}",
+            self.alert.severity, self.alert.id, event, self.alert.message
+        )
+    }
+}
+
+/// Somewhere alert notifications are delivered
+pub trait NotificationChannel: Send + Sync {
+    /// Name of the channel in delivery errors
+    fn name(&self) -> &str;
+
+    /// Deliver one notification
+    fn send(&self, notification: &AlertNotification) -> Result<()>;
+}
+
+/// Posts notifications as JSON to a URL
+pub struct WebhookChannel {
+    url: String,
+    transport: Arc<dyn Transport>,
+}
+
+impl WebhookChannel {
+    pub fn new(url: impl Into<String>, transport: Arc<dyn Transport>) -> Self {
+        Self {
+            url: url.into(),
+            transport,
+        }
+    }
+}
+
+impl NotificationChannel for WebhookChannel {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    fn send(&self, notification: &AlertNotification) -> Result<()> {
+        let body = serde_json::to_string(notification)
+            .map_err(|e| MonitoringError::ExportError(e.to_string()))?;
+        self.transport.post_json(&self.url, &body)
+    }
+}
+
+/// Posts notifications to a Slack incoming webhook
+pub struct SlackChannel {
+    webhook_url: String,
+    channel: Option<String>,
+    transport: Arc<dyn Transport>,
+}
+
+impl SlackChannel {
+    pub fn new(
+        webhook_url: impl Into<String>,
+        channel: Option<String>,
+        transport: Arc<dyn Transport>,
+    ) -> Self {
+        Self {
+            webhook_url: webhook_url.into(),
+            channel,
+            transport,
+        }
+    }
+}
+
+impl NotificationChannel for SlackChannel {
+    fn name(&self) -> &str {
+        "slack"
+    }
+
+    fn send(&self, notification: &AlertNotification) -> Result<()> {
+        let alert = &notification.alert;
+        let color = match (notification.event, &alert.severity) {
+            (AlertEvent::Resolved, _) => "good",
+            (_, AlertSeverity::Critical) => "danger",
+            (_, AlertSeverity::Warning) => "warning",
+            (_, AlertSeverity::Info) => "#439fe0",
+        };
+        let mut fields = vec![
+            json!({"title": "Alert", "value": alert.id, "short": true}),
+            json!({"title": "Severity", "value": format!("{:?}", alert.severity), "short": true}),
+        ];
+        let mut metadata: Vec<_> = alert.metadata.iter().collect();
+        metadata.sort();
+        for (key, value) in metadata {
+            fields.push(json!({"title": key, "value": value, "short": true}));
+        }
+
+        let mut payload = json!({
+            "text": notification.summary(),
+            "attachments": [{
+                "color": color,
+                "fields": fields,
+                "ts": alert.resolved_at.unwrap_or(alert.triggered_at) / 1000,
+            }],
+        });
+        if let Some(channel) = &self.channel {
+            payload["channel"] = json!(channel);
+        }
+        self.transport
+            .post_json(&self.webhook_url, &payload.to_string())
+    }
+}
+
+/// Emails notifications through an SMTP relay, without TLS or
+/// authentication, as a relay on the local network accepts them
+pub struct EmailChannel {
+    config: SmtpConfig,
+    timeout: Duration,
+}
+
+impl EmailChannel {
+    pub fn new(config: SmtpConfig, timeout: Duration) -> Self {
+        Self { config, timeout }
+    }
+
+    /// Run one SMTP session delivering `message`
+    fn deliver(&self, message: &str) -> std::io::Result<()> {
+        let mut stream = connect(&self.config.server, 25, self.timeout)?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        smtp_reply(&mut reader, 220)?;
+        smtp_command(&mut stream, &mut reader, "EHLO cc-chain", 250)?;
+        smtp_command(
+            &mut stream,
+            &mut reader,
+            &format!("MAIL FROM:<{}>", self.config.from),
+            250,
+        )?;
+        for to in &self.config.to {
+            smtp_command(&mut stream, &mut reader, &format!("RCPT TO:<{to}>"), 250)?;
+        }
+        smtp_command(&mut stream, &mut reader, "DATA", 354)?;
+        // CRLF line endings, with lines dot-stuffed so none ends the data
+        let data: Vec<String> = message
+            .lines()
+            .map(|line| {
+                if line.starts_with('.') {
+                    format!(".{line}")
+                } else {
+                    line.to_string()
+                }
+            })
+            .collect();
+        smtp_command(
+            &mut stream,
+            &mut reader,
+            &format!("{}\r\n.", data.join("\r\n")),
+            250,
+        )?;
+        // The message is accepted; a failed goodbye changes nothing
+        let _ = smtp_command(&mut stream, &mut reader, "QUIT", 221);
+        Ok(())
+    }
+}
+
+impl NotificationChannel for EmailChannel {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    fn send(&self, notification: &AlertNotification) -> Result<()> {
+        let alert = &notification.alert;
+        let mut body = format!("{}\n\nAlert: {}\n", alert.message, alert.id);
+        body.push_str(&format!("Triggered at: {} ms\n", alert.triggered_at));
+        if let Some(resolved_at) = alert.resolved_at {
+            body.push_str(&format!("Resolved at: {resolved_at} ms\n"));
+        }
+        let mut metadata: Vec<_> = alert.metadata.iter().collect();
+        metadata.sort();
+        for (key, value) in metadata {
+            body.push_str(&format!("{key}: {value}\n"));
+        }
+
+        let message = format!(
+            "From: <{}>\nTo: {}\nSubject: [CC Chain] {}\nContent-Type: text/plain; charset=utf-8\n\n{}",
+            self.config.from,
+            self.config
+                .to
+                .iter()
+                .map(|to| format!("<{to}>"))
+                .collect::<Vec<_>>()
+                .join(", "),
+            notification.summary().replace(['\r', '\n'], " "),
+            body.trim_end()
+        );
+        self.deliver(&message).map_err(|e| {
+            MonitoringError::ExportError(format!("smtp {}: {}", self.config.server, e))
+        })
+    }
+}
+
+/// Send `command` and expect a reply with status `expected`
+fn smtp_command(
+    stream: &mut impl Write,
+    reader: &mut impl BufRead,
+    command: &str,
+    expected: u16,
+) -> std::io::Result<()> {
+    stream.write_all(format!("{command}\r\n").as_bytes())?;
+    smtp_reply(reader, expected)
+}
+
+/// Read a possibly multi-line reply, failing unless its status is `expected`
+fn smtp_reply(reader: &mut impl BufRead, expected: u16) -> std::io::Result<()> {
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        // `250-...` continues the reply, `250 ...` ends it
+        if line.as_bytes().get(3) == Some(&b'-') {
+            continue;
+        }
+        return match line.get(..3).and_then(|code| code.parse::<u16>().ok()) {
+            Some(code) if code == expected => Ok(()),
+            _ => Err(std::io::Error::other(format!(
+                "unexpected reply {:?}",
+                line.trim_end()
+            ))),
+        };
+    }
+}
+
+/// Sends alert events to the channels, retrying and deduplicating
+pub struct Notifier {
+    config: NotificationConfig,
+    channels: Mutex<Vec<Arc<dyn NotificationChannel>>>,
+    last_sent: Mutex<HashMap<(String, AlertEvent), Instant>>,
+    failed_deliveries: AtomicU64,
+}
+
+impl Notifier {
+    /// Create a notifier with the channels in `config`
+    pub fn new(config: NotificationConfig) -> Self {
+        let transport: Arc<dyn Transport> = Arc::new(HttpTransport::new(config.timeout));
+        let channels = config
+            .channels
+            .iter()
+            .map(|channel| -> Arc<dyn NotificationChannel> {
+                match channel {
+                    ChannelConfig::Webhook { url } => {
+                        Arc::new(WebhookChannel::new(url, transport.clone()))
+                    }
+                    ChannelConfig::Slack {
+                        webhook_url,
+                        channel,
+                    } => Arc::new(SlackChannel::new(
+                        webhook_url,
+                        channel.clone(),
+                        transport.clone(),
+                    )),
+                    ChannelConfig::Email(smtp) => {
+                        Arc::new(EmailChannel::new(smtp.clone(), config.timeout))
+                    }
+                }
+            })
+            .collect();
+        Self {
+            config,
+            channels: Mutex::new(channels),
+            last_sent: Mutex::new(HashMap::new()),
+            failed_deliveries: AtomicU64::new(0),
+        }
+    }
+
+    /// Also notify `channel`
+    pub fn add_channel(&self, channel: Arc<dyn NotificationChannel>) {
+        self.channels.lock().unwrap().push(channel);
+    }
+
+    pub fn has_channels(&self) -> bool {
+        !self.channels.lock().unwrap().is_empty()
+    }
+
+    /// Deliveries that failed after every retry
+    pub fn failed_deliveries(&self) -> u64 {
+        self.failed_deliveries.load(Ordering::Relaxed)
+    }
+
+    /// Send `alert`'s event to every channel, unless it was sent within the
+    /// deduplication window; returns whether it was sent
+    ///
+    /// Blocks while retrying. Channels that still fail are listed in the
+    /// error, after the others have been notified.
+    pub fn notify(&self, alert: &Alert) -> Result<bool> {
+        let notification = AlertNotification::new(alert.clone());
+        {
+            let mut last_sent = self.last_sent.lock().unwrap();
+            last_sent.retain(|_, sent| sent.elapsed() < self.config.dedup_window);
+            let key = (alert.id.clone(), notification.event);
+            if last_sent.contains_key(&key) {
+                return Ok(false);
+            }
+            last_sent.insert(key, Instant::now());
+        }
+
+        let channels = self.channels.lock().unwrap().clone();
+        let mut failures = Vec::new();
+        for channel in &channels {
+            if let Err(error) = self.send_with_retries(channel.as_ref(), &notification) {
+                self.failed_deliveries.fetch_add(1, Ordering::Relaxed);
+                failures.push(format!("{}: {}", channel.name(), error));
+            }
+        }
+        if failures.is_empty() {
+            Ok(!channels.is_empty())
+        } else {
+            Err(MonitoringError::ExportError(failures.join("; ")))
+        }
+    }
+
+    fn send_with_retries(
+        &self,
+        channel: &dyn NotificationChannel,
+        notification: &AlertNotification,
+    ) -> Result<()> {
+        let mut backoff = self.config.retry_backoff;
+        let mut attempt = 0;
+        loop {
+            match channel.send(notification) {
+                Err(_) if attempt < self.config.max_retries => {
+                    std::thread::sleep(backoff);
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                outcome => return outcome,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::RecordingTransport;
+    use crate::{AlertType, MonitoringConfig, RpcMonitor};
+    use std::net::TcpListener;
+
+    fn alert(resolved: bool) -> Alert {
+        Alert {
+            id: "high_error_rate".to_string(),
+            alert_type: AlertType::HighErrorRate,
+            severity: AlertSeverity::Critical,
+            message: "Error rate (50.0%) exceeds threshold (5.0%)".to_string(),
+            triggered_at: 1_700_000_000_000,
+            resolved_at: resolved.then_some(1_700_000_060_000),
+            metadata: HashMap::new(),
+        }
+    }
+
+    fn notifier(transport: &Arc<RecordingTransport>) -> Notifier {
+        let notifier = Notifier::new(NotificationConfig {
+            retry_backoff: Duration::ZERO,
+            max_retries: 2,
+            ..NotificationConfig::default()
+        });
+        notifier.add_channel(Arc::new(WebhookChannel::new(
+            "http://alerts/hook",
+            transport.clone(),
+        )));
+        notifier.add_channel(Arc::new(SlackChannel::new(
+            "http://slack/hook",
+            Some("#ops".to_string()),
+            transport.clone(),
+        )));
+        notifier
+    }
+
+    #[test]
+    fn test_notify_retries_and_deduplicates() {
+        let transport = Arc::new(RecordingTransport::default());
+        transport.failures.store(2, Ordering::Relaxed);
+        let notifier = notifier(&transport);
+
+        assert!(notifier.notify(&alert(false)).unwrap());
+        assert!(!notifier.notify(&alert(false)).unwrap());
+        assert!(notifier.notify(&alert(true)).unwrap());
+
+        let sent = transport.sent();
+        assert_eq!(sent.len(), 4);
+        assert_eq!(sent[0].0, "http://alerts/hook");
+        assert_eq!(sent[0].1["event"], "triggered");
+        assert_eq!(sent[0].1["alert"]["id"], "high_error_rate");
+        assert_eq!(sent[1].0, "http://slack/hook");
+        assert_eq!(sent[1].1["channel"], "#ops");
+        assert_eq!(
+            sent[1].1["text"],
+            "[Critical] high_error_rate triggered: Error rate (50.0%) exceeds threshold (5.0%)"
+        );
+        assert_eq!(sent[1].1["attachments"][0]["color"], "danger");
+        assert_eq!(sent[2].1["event"], "resolved");
+        assert_eq!(sent[3].1["attachments"][0]["color"], "good");
+
+        transport.failures.store(3, Ordering::Relaxed);
+        let mut flapping = alert(false);
+        flapping.id = "high_response_time".to_string();
+        let error = notifier.notify(&flapping).unwrap_err().to_string();
+        assert!(error.contains("webhook: "));
+        assert_eq!(notifier.failed_deliveries(), 1);
+    }
+
+    #[test]
+    fn test_email_channel() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let server = listener.local_addr().unwrap().to_string();
+        let relay = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            stream.write_all(b"220 relay ready\r\n").unwrap();
+            let mut transcript = String::new();
+            let mut in_data = false;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 {
+                    break;
+                }
+                transcript.push_str(&line);
+                let reply: &[u8] = if in_data {
+                    if line != ".\r\n" {
+                        continue;
+                    }
+                    in_data = false;
+                    b"250 queued\r\n"
+                } else if line.starts_with("EHLO") {
+                    b"250-relay\r\n250 SIZE 1000000\r\n"
+                } else if line.starts_with("DATA") {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                } else if line.starts_with("QUIT") {
+                    stream.write_all(b"221 bye\r\n").unwrap();
+                    break;
+                } else {
+                    b"250 ok\r\n"
+                };
+                stream.write_all(reply).unwrap();
+            }
+            transcript
+        });
+
+        let channel = EmailChannel::new(
+            SmtpConfig {
+                server,
+                from: "node@example.com".to_string(),
+                to: vec!["ops@example.com".to_string()],
+            },
+            Duration::from_secs(5),
+        );
+        let mut alert = alert(false);
+        alert.message = "line one\r\n.hidden".to_string();
+        channel.send(&AlertNotification::new(alert)).unwrap();
+
+        let transcript = relay.join().unwrap();
+        assert!(transcript.starts_with("EHLO cc-chain\r\nMAIL FROM:<node@example.com>\r\n"));
+        assert!(transcript.contains("RCPT TO:<ops@example.com>\r\nDATA\r\n"));
+        assert!(transcript.contains("Subject: [CC Chain] [Critical] high_error_rate triggered: "));
+        assert!(transcript.contains("line one\r\n..hidden\r\n"));
+        assert!(transcript.ends_with("\r\n.\r\nQUIT\r\n"));
+    }
+
+    #[test]
+    fn test_check_alerts_notifies() {
+        let transport = Arc::new(RecordingTransport::default());
+        let monitor = RpcMonitor::with_config(MonitoringConfig::default());
+        monitor.add_notification_channel(Arc::new(WebhookChannel::new(
+            "http://alerts/hook",
+            transport.clone(),
+        )));
+        monitor
+            .start_request("1".to_string(), "cc_ping".to_string(), 10)
+            .unwrap();
+        monitor.fail_request("1".to_string(), -32603).unwrap();
+        assert_eq!(monitor.check_alerts().unwrap().len(), 1);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while transport.sent().is_empty() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(transport.sent()[0].1["alert"]["id"], "high_error_rate");
+    }
+}
@@ -0,0 +1,392 @@
+//! OpenTelemetry export
+//!
+//! Completed requests become OTLP server spans, sampled by trace id at the
+//! configured rate, and the monitor's metrics become OTLP data points. Both
+//! are pushed as OTLP/HTTP JSON to a collector under `/v1/traces` and
+//! `/v1/metrics` of its endpoint.
+
+use crate::transport::{HttpTransport, Transport};
+use crate::{current_timestamp, RequestMetrics, RequestStatus, Result};
+use cc_core_metrics::{MetricKind, MetricSample};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Name of the instrumentation scope in exported data
+const SCOPE_NAME: &str = "rpc-monitoring";
+
+/// OTLP span kind of a request served by this node
+const SPAN_KIND_SERVER: u8 = 2;
+
+/// OTLP cumulative aggregation temporality
+const TEMPORALITY_CUMULATIVE: u8 = 2;
+
+/// OpenTelemetry collector configuration
+#[derive(Debug, Clone)]
+pub struct OtlpConfig {
+    /// Base URL of the collector's OTLP/HTTP receiver
+    pub endpoint: String,
+    /// Fraction of requests traced, from 0.0 to 1.0
+    pub sampling_rate: f64,
+    /// `service.name` of the exported resource
+    pub service_name: String,
+    /// Buffered spans that trigger an export before the export interval
+    pub max_batch_size: usize,
+    pub timeout: Duration,
+}
+
+impl Default for OtlpConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: "http://localhost:4318".to_string(),
+            sampling_rate: 1.0,
+            service_name: "cc-chain-rpc".to_string(),
+            max_batch_size: 512,
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// A sampled request waiting to be exported
+struct PendingSpan {
+    trace_id: u128,
+    span_id: u64,
+    request_id: String,
+    metrics: RequestMetrics,
+}
+
+/// Buffers request spans and pushes them, with metrics, to a collector
+pub struct OtlpExporter {
+    config: OtlpConfig,
+    transport: Arc<dyn Transport>,
+    spans: Mutex<Vec<PendingSpan>>,
+    /// Length of `spans`, readable without the lock
+    buffered_spans: AtomicUsize,
+    start_time: u64,
+    created: Instant,
+    /// Millis after `created` of the last export
+    last_export: AtomicU64,
+    failed_exports: AtomicU64,
+}
+
+impl OtlpExporter {
+    /// Create an exporter sending over HTTP
+    pub fn new(config: OtlpConfig) -> Self {
+        let transport = Arc::new(HttpTransport::new(config.timeout));
+        Self::with_transport(config, transport)
+    }
+
+    /// Create an exporter sending through `transport`
+    pub fn with_transport(config: OtlpConfig, transport: Arc<dyn Transport>) -> Self {
+        Self {
+            config,
+            transport,
+            spans: Mutex::new(Vec::new()),
+            buffered_spans: AtomicUsize::new(0),
+            start_time: current_timestamp(),
+            created: Instant::now(),
+            last_export: AtomicU64::new(0),
+            failed_exports: AtomicU64::new(0),
+        }
+    }
+
+    pub fn config(&self) -> &OtlpConfig {
+        &self.config
+    }
+
+    /// Buffer a span for a finished request if its trace is sampled
+    pub fn record(&self, request_id: &str, metrics: &RequestMetrics) {
+        let trace_id = rand::random::<u128>().max(1);
+        if !self.is_sampled(trace_id) {
+            return;
+        }
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(PendingSpan {
+            trace_id,
+            span_id: rand::random::<u64>().max(1),
+            request_id: request_id.to_string(),
+            metrics: metrics.clone(),
+        });
+        self.buffered_spans.store(spans.len(), Ordering::Relaxed);
+    }
+
+    /// Whether a trace is kept: its low 64 bits fall under the sampling rate
+    fn is_sampled(&self, trace_id: u128) -> bool {
+        let rate = self.config.sampling_rate;
+        if rate >= 1.0 {
+            return true;
+        }
+        rate > 0.0 && (trace_id as u64) < (rate * u64::MAX as f64) as u64
+    }
+
+    /// Spans buffered for the next export
+    pub fn pending_spans(&self) -> usize {
+        self.buffered_spans.load(Ordering::Relaxed)
+    }
+
+    /// Exports that failed; their spans are dropped
+    pub fn failed_exports(&self) -> u64 {
+        self.failed_exports.load(Ordering::Relaxed)
+    }
+
+    /// Whether `interval` has passed since the last export or the span buffer
+    /// is full; claims the export when it is due
+    ///
+    /// Called for every finished request, so it takes no lock.
+    pub(crate) fn claim_export(&self, interval: Duration) -> bool {
+        let now = self.created.elapsed().as_millis() as u64;
+        let last_export = self.last_export.load(Ordering::Relaxed);
+        let due = now.saturating_sub(last_export) >= interval.as_millis() as u64
+            || self.pending_spans() >= self.config.max_batch_size;
+        // Only one of the callers finding it due claims it
+        due && self
+            .last_export
+            .compare_exchange(last_export, now, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    /// Push buffered spans and `samples` to the collector
+    pub fn export(&self, samples: &[MetricSample]) -> Result<()> {
+        let spans = {
+            let mut spans = self.spans.lock().unwrap();
+            self.buffered_spans.store(0, Ordering::Relaxed);
+            std::mem::take(&mut *spans)
+        };
+        let traces = if spans.is_empty() {
+            Ok(())
+        } else {
+            self.send("/v1/traces", &self.traces_payload(&spans))
+        };
+        let metrics = if samples.is_empty() {
+            Ok(())
+        } else {
+            self.send("/v1/metrics", &self.metrics_payload(samples))
+        };
+        traces.and(metrics)
+    }
+
+    fn send(&self, path: &str, payload: &Value) -> Result<()> {
+        let url = format!("{}{}", self.config.endpoint.trim_end_matches('/'), path);
+        let outcome = self.transport.post_json(&url, &payload.to_string());
+        if outcome.is_err() {
+            self.failed_exports.fetch_add(1, Ordering::Relaxed);
+        }
+        outcome
+    }
+
+    fn resource(&self) -> Value {
+        json!({"attributes": [attribute("service.name", json!({"stringValue": self.config.service_name}))]})
+    }
+
+    fn scope() -> Value {
+        json!({"name": SCOPE_NAME, "version": env!("CARGO_PKG_VERSION")})
+    }
+
+    /// `ExportTraceServiceRequest` for `spans`
+    fn traces_payload(&self, spans: &[PendingSpan]) -> Value {
+        let spans: Vec<Value> = spans.iter().map(span_json).collect();
+        json!({
+            "resourceSpans": [{
+                "resource": self.resource(),
+                "scopeSpans": [{"scope": Self::scope(), "spans": spans}],
+            }]
+        })
+    }
+
+    /// `ExportMetricsServiceRequest` for `samples`, one metric per name
+    fn metrics_payload(&self, samples: &[MetricSample]) -> Value {
+        let now = nanos(current_timestamp());
+        let start = nanos(self.start_time);
+        let mut metrics: Vec<(&MetricSample, Vec<Value>)> = Vec::new();
+        for sample in samples {
+            let attributes: Vec<Value> = sample
+                .labels
+                .iter()
+                .map(|(key, value)| attribute(key, json!({"stringValue": value})))
+                .collect();
+            let point = json!({
+                "startTimeUnixNano": start,
+                "timeUnixNano": now,
+                "asDouble": sample.value,
+                "attributes": attributes,
+            });
+            match metrics
+                .iter_mut()
+                .find(|(first, _)| first.name == sample.name)
+            {
+                Some((_, points)) => points.push(point),
+                None => metrics.push((sample, vec![point])),
+            }
+        }
+
+        let metrics: Vec<Value> = metrics
+            .into_iter()
+            .map(|(sample, points)| {
+                let mut metric = json!({"name": sample.name, "description": sample.help});
+                match sample.kind {
+                    MetricKind::Counter => {
+                        metric["sum"] = json!({
+                            "dataPoints": points,
+                            "aggregationTemporality": TEMPORALITY_CUMULATIVE,
+                            "isMonotonic": true,
+                        })
+                    }
+                    MetricKind::Gauge => metric["gauge"] = json!({"dataPoints": points}),
+                }
+                metric
+            })
+            .collect();
+        json!({
+            "resourceMetrics": [{
+                "resource": self.resource(),
+                "scopeMetrics": [{"scope": Self::scope(), "metrics": metrics}],
+            }]
+        })
+    }
+}
+
+/// OTLP JSON of one request span
+fn span_json(span: &PendingSpan) -> Value {
+    let metrics = &span.metrics;
+    let mut attributes = vec![
+        attribute("rpc.system", json!({"stringValue": "jsonrpc"})),
+        attribute("rpc.method", json!({"stringValue": metrics.method})),
+        attribute(
+            "rpc.jsonrpc.request_id",
+            json!({"stringValue": span.request_id}),
+        ),
+        attribute(
+            "cc.rpc.status",
+            json!({"stringValue": metrics.status.as_str()}),
+        ),
+        attribute(
+            "cc.rpc.request_size",
+            json!({"intValue": metrics.request_size.to_string()}),
+        ),
+    ];
+    if let Some(code) = metrics.error_code {
+        attributes.push(attribute(
+            "rpc.jsonrpc.error_code",
+            json!({"intValue": code.to_string()}),
+        ));
+    }
+    if let Some(size) = metrics.response_size {
+        attributes.push(attribute(
+            "cc.rpc.response_size",
+            json!({"intValue": size.to_string()}),
+        ));
+    }
+    if let Some(client_id) = &metrics.client_id {
+        attributes.push(attribute("client.id", json!({"stringValue": client_id})));
+    }
+
+    let status = match metrics.status {
+        RequestStatus::Success => json!({"code": 1}),
+        RequestStatus::Pending => json!({"code": 0}),
+        RequestStatus::Error | RequestStatus::Timeout => {
+            json!({"code": 2, "message": metrics.status.as_str()})
+        }
+    };
+    json!({
+        "traceId": format!("{:032x}", span.trace_id),
+        "spanId": format!("{:016x}", span.span_id),
+        "name": metrics.method,
+        "kind": SPAN_KIND_SERVER,
+        "startTimeUnixNano": nanos(metrics.start_time),
+        "endTimeUnixNano": nanos(metrics.end_time.unwrap_or(metrics.start_time)),
+        "attributes": attributes,
+        "status": status,
+    })
+}
+
+fn attribute(key: &str, value: Value) -> Value {
+    json!({"key": key, "value": value})
+}
+
+/// Epoch milliseconds as the OTLP JSON encoding of epoch nanoseconds
+fn nanos(millis: u64) -> String {
+    (u128::from(millis) * 1_000_000).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::RecordingTransport;
+    use crate::{MonitoringConfig, RpcMonitor};
+
+    fn monitor(sampling_rate: f64, transport: Arc<RecordingTransport>) -> RpcMonitor {
+        RpcMonitor::with_config(MonitoringConfig {
+            otlp: Some(OtlpConfig {
+                endpoint: "http://collector:4318/".to_string(),
+                sampling_rate,
+                ..OtlpConfig::default()
+            }),
+            ..MonitoringConfig::default()
+        })
+        .with_otlp_transport(transport)
+    }
+
+    #[test]
+    fn test_requests_export_spans_and_metrics() {
+        let transport = Arc::new(RecordingTransport::default());
+        let monitor = monitor(1.0, transport.clone());
+        monitor
+            .start_request("1".to_string(), "cc_ping".to_string(), 40)
+            .unwrap();
+        monitor.complete_request("1".to_string(), 60).unwrap();
+        monitor
+            .start_request("2".to_string(), "cc_getBlock".to_string(), 50)
+            .unwrap();
+        monitor.fail_request("2".to_string(), -32602).unwrap();
+        monitor.export_otlp().unwrap();
+
+        let sent = transport.sent();
+        assert_eq!(sent[0].0, "http://collector:4318/v1/traces");
+        let spans = &sent[0].1["resourceSpans"][0]["scopeSpans"][0]["spans"];
+        assert_eq!(spans.as_array().unwrap().len(), 2);
+        assert_eq!(spans[0]["name"], "cc_ping");
+        assert_eq!(spans[0]["status"]["code"], 1);
+        assert_eq!(spans[0]["traceId"].as_str().unwrap().len(), 32);
+        assert_eq!(spans[1]["status"]["code"], 2);
+        assert!(spans[1]["attributes"]
+            .as_array()
+            .unwrap()
+            .contains(&attribute(
+                "rpc.jsonrpc.error_code",
+                json!({"intValue": "-32602"})
+            )));
+
+        assert_eq!(sent[1].0, "http://collector:4318/v1/metrics");
+        let metrics = sent[1].1["resourceMetrics"][0]["scopeMetrics"][0]["metrics"]
+            .as_array()
+            .unwrap();
+        let total = metrics
+            .iter()
+            .find(|metric| metric["name"] == "cc_rpc_requests_total")
+            .unwrap();
+        assert_eq!(total["sum"]["isMonotonic"], true);
+        assert_eq!(total["sum"]["dataPoints"][0]["asDouble"], 2.0);
+    }
+
+    #[test]
+    fn test_sampling_rate() {
+        let transport = Arc::new(RecordingTransport::default());
+        let unsampled = monitor(0.0, transport.clone());
+        unsampled
+            .start_request("1".to_string(), "cc_ping".to_string(), 40)
+            .unwrap();
+        unsampled.complete_request("1".to_string(), 60).unwrap();
+        assert_eq!(unsampled.otlp_exporter().unwrap().pending_spans(), 0);
+
+        let exporter = OtlpExporter::with_transport(
+            OtlpConfig {
+                sampling_rate: 0.25,
+                ..OtlpConfig::default()
+            },
+            transport,
+        );
+        assert!(exporter.is_sampled(u128::from(u64::MAX / 8)));
+        assert!(!exporter.is_sampled(u128::from(u64::MAX / 2)));
+    }
+}
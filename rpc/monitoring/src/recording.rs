@@ -0,0 +1,143 @@
+//! Request recording path
+//!
+//! Every RPC call starts and finishes a request in the monitor, from many
+//! threads at once. Active requests are kept in a sharded map, so calls
+//! rarely wait on each other, and finished requests are pushed to a
+//! lock-free bounded queue instead of into the shared history, counters and
+//! logs. Readers apply the queued requests before looking at those, as do
+//! writers finding the queue full, so no background thread is needed.
+
+use crate::RequestMetrics;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::sync::Mutex;
+
+/// Shards of the active request map; enough that threads serving requests
+/// seldom hash to the same one
+pub(crate) const ACTIVE_SHARDS: usize = 32;
+
+/// Finished requests queued before writers have to apply them
+pub(crate) const FINISHED_QUEUE_CAPACITY: usize = 4096;
+
+/// Requests in flight, by request id
+pub(crate) struct ActiveRequests {
+    shards: Vec<Mutex<HashMap<String, RequestMetrics>>>,
+    hasher: RandomState,
+}
+
+impl ActiveRequests {
+    pub(crate) fn new() -> Self {
+        Self {
+            shards: (0..ACTIVE_SHARDS).map(|_| Mutex::default()).collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    fn shard(&self, request_id: &str) -> &Mutex<HashMap<String, RequestMetrics>> {
+        let index = self.hasher.hash_one(request_id) % self.shards.len() as u64;
+        &self.shards[index as usize]
+    }
+
+    pub(crate) fn insert(&self, request_id: String, metrics: RequestMetrics) {
+        self.shard(&request_id)
+            .lock()
+            .unwrap()
+            .insert(request_id, metrics);
+    }
+
+    pub(crate) fn remove(&self, request_id: &str) -> Option<RequestMetrics> {
+        self.shard(request_id).lock().unwrap().remove(request_id)
+    }
+
+    /// Run `f` on an active request; it cannot finish until `f` returns
+    pub(crate) fn with<R>(
+        &self,
+        request_id: &str,
+        f: impl FnOnce(&RequestMetrics) -> R,
+    ) -> Option<R> {
+        self.shard(request_id)
+            .lock()
+            .unwrap()
+            .get(request_id)
+            .map(f)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.lock().unwrap().len())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MonitoringConfig, RequestStatus, RpcMonitor};
+    use std::sync::Arc;
+
+    fn pending(method: &str) -> RequestMetrics {
+        RequestMetrics {
+            method: method.to_string(),
+            start_time: 0,
+            end_time: None,
+            duration_ms: None,
+            status: RequestStatus::Pending,
+            error_code: None,
+            request_size: 0,
+            response_size: None,
+            client_id: None,
+            sample_weight: 1,
+        }
+    }
+
+    #[test]
+    fn test_active_requests() {
+        let active = ActiveRequests::new();
+        for id in 0..100 {
+            active.insert(id.to_string(), pending("cc_ping"));
+        }
+        assert_eq!(active.len(), 100);
+        assert_eq!(active.with("7", |m| m.method.clone()).unwrap(), "cc_ping");
+        assert!(active.remove("7").is_some());
+        assert!(active.remove("7").is_none());
+        assert!(active.with("7", |_| ()).is_none());
+        assert_eq!(active.len(), 99);
+    }
+
+    #[test]
+    fn test_concurrent_recording_overflows_queue() {
+        const THREADS: usize = 8;
+        let per_thread = FINISHED_QUEUE_CAPACITY;
+        let monitor = Arc::new(RpcMonitor::with_config(MonitoringConfig {
+            max_history_size: THREADS * per_thread,
+            ..MonitoringConfig::default()
+        }));
+        let handles: Vec<_> = (0..THREADS)
+            .map(|thread| {
+                let monitor = monitor.clone();
+                std::thread::spawn(move || {
+                    for i in 0..per_thread {
+                        let id = format!("{thread}-{i}");
+                        monitor
+                            .start_request(id.clone(), "cc_ping".to_string(), 10)
+                            .unwrap();
+                        monitor.complete_request(id, 20).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let health = monitor.get_health_status().unwrap();
+        assert_eq!(
+            health.metrics_summary.total_requests,
+            (THREADS * per_thread) as u64
+        );
+        assert_eq!(health.metrics_summary.concurrent_requests, 0);
+        assert!(monitor.finished.is_empty());
+    }
+}
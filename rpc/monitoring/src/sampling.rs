@@ -0,0 +1,203 @@
+//! Sampling and cardinality limits
+//!
+//! Under extreme load, recording every request in full costs memory and
+//! export bandwidth the node needs for serving. With sampling, only a share
+//! of successful requests is kept in the history and exported as spans,
+//! each standing in for the ones left out through its `sample_weight`, so
+//! windows and summaries still estimate the full traffic. Failed, timed out
+//! and slow requests are always kept. Method totals and SLOs count every
+//! request either way.
+//!
+//! Method names come from clients, so each unknown name would otherwise add
+//! counters and series for good. Names outside the allowlist, or past the
+//! limit of distinct names, are recorded as `other`.
+
+use crate::{RequestMetrics, RequestStatus};
+use rand::Rng;
+use std::collections::HashSet;
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// Name recorded for methods over the cardinality limits
+pub const OTHER_METHOD: &str = "other";
+
+/// Sampling configuration
+#[derive(Debug, Clone)]
+pub struct SamplingConfig {
+    /// Share of successful requests kept in full, rounded to one in a whole
+    /// number; 0.1 keeps one in ten
+    pub success_sample_rate: f64,
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self {
+            success_sample_rate: 1.0,
+        }
+    }
+}
+
+/// Limits on the method names recorded
+#[derive(Debug, Clone)]
+pub struct CardinalityConfig {
+    /// Methods recorded under their own name; without one, the first
+    /// `max_methods` names seen are
+    pub method_allowlist: Option<HashSet<String>>,
+    pub max_methods: usize,
+}
+
+impl Default for CardinalityConfig {
+    fn default() -> Self {
+        Self {
+            method_allowlist: None,
+            max_methods: 256,
+        }
+    }
+}
+
+/// Decides which finished requests are kept in full
+pub(crate) struct Sampler {
+    /// Successful requests each kept one stands for
+    one_in: u32,
+    /// Requests at least this slow are always kept
+    slow_threshold: Duration,
+}
+
+impl Sampler {
+    pub(crate) fn new(config: &SamplingConfig, slow_threshold: Duration) -> Self {
+        let rate = config.success_sample_rate.clamp(f64::MIN_POSITIVE, 1.0);
+        Self {
+            one_in: (1.0 / rate).round().min(u32::MAX as f64) as u32,
+            slow_threshold,
+        }
+    }
+
+    /// Whether to keep `metrics` in full, setting the requests it stands for
+    pub(crate) fn sample(&self, metrics: &mut RequestMetrics) -> bool {
+        let slow = metrics
+            .duration_ms
+            .is_some_and(|ms| ms >= self.slow_threshold.as_millis() as u64);
+        if self.one_in <= 1 || !matches!(metrics.status, RequestStatus::Success) || slow {
+            metrics.sample_weight = 1;
+            return true;
+        }
+        metrics.sample_weight = self.one_in;
+        rand::thread_rng().gen_ratio(1, self.one_in)
+    }
+}
+
+/// Method names recorded so far, within the cardinality limits
+pub(crate) struct MethodNames {
+    config: CardinalityConfig,
+    seen: RwLock<HashSet<String>>,
+}
+
+impl MethodNames {
+    pub(crate) fn new(config: CardinalityConfig) -> Self {
+        Self {
+            config,
+            seen: RwLock::new(HashSet::new()),
+        }
+    }
+
+    /// `method`, or `other` when it is over the limits
+    pub(crate) fn record(&self, method: String) -> String {
+        if let Some(allowlist) = &self.config.method_allowlist {
+            return if allowlist.contains(&method) {
+                method
+            } else {
+                OTHER_METHOD.to_string()
+            };
+        }
+        if self.seen.read().unwrap().contains(&method) {
+            return method;
+        }
+        let mut seen = self.seen.write().unwrap();
+        if seen.len() < self.config.max_methods {
+            seen.insert(method.clone());
+        }
+        if seen.contains(&method) {
+            method
+        } else {
+            OTHER_METHOD.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MonitoringConfig, RpcMonitor};
+
+    #[test]
+    fn test_sampled_history_estimates_traffic() {
+        let monitor = RpcMonitor::with_config(MonitoringConfig {
+            sampling: SamplingConfig {
+                success_sample_rate: 0.1,
+            },
+            ..MonitoringConfig::default()
+        });
+        for i in 0..1000 {
+            let id = i.to_string();
+            monitor
+                .start_request(id.clone(), "cc_getBlock".to_string(), 10)
+                .unwrap();
+            if i % 10 == 0 {
+                monitor.fail_request(id, -32603).unwrap();
+            } else {
+                monitor.complete_request(id, 20).unwrap();
+            }
+        }
+
+        let health = monitor.get_health_status().unwrap();
+        let completed = monitor.completed_requests.lock().unwrap();
+        let failed = completed
+            .iter()
+            .filter(|r| !matches!(r.status, RequestStatus::Success))
+            .count();
+        assert_eq!(failed, 100);
+        assert!(completed.len() < 400);
+        assert!(completed
+            .iter()
+            .all(|r| r.sample_weight == if r.error_code.is_some() { 1 } else { 10 }));
+        // Kept requests stand for the ones left out
+        let estimated = health.metrics_summary.total_requests;
+        assert_eq!(estimated, 100 + 10 * (completed.len() as u64 - 100));
+        drop(completed);
+
+        // Totals count every request
+        let totals = monitor.method_totals.lock().unwrap();
+        assert_eq!(totals["cc_getBlock"].requests, 1000);
+        assert_eq!(totals["cc_getBlock"].errors, 100);
+    }
+
+    #[test]
+    fn test_method_cardinality() {
+        let capped = MethodNames::new(CardinalityConfig {
+            method_allowlist: None,
+            max_methods: 2,
+        });
+        assert_eq!(capped.record("cc_ping".to_string()), "cc_ping");
+        assert_eq!(capped.record("cc_getBlock".to_string()), "cc_getBlock");
+        assert_eq!(capped.record("cc_bogus".to_string()), OTHER_METHOD);
+        assert_eq!(capped.record("cc_ping".to_string()), "cc_ping");
+
+        let monitor = RpcMonitor::with_config(MonitoringConfig {
+            cardinality: CardinalityConfig {
+                method_allowlist: Some(HashSet::from(["cc_ping".to_string()])),
+                ..CardinalityConfig::default()
+            },
+            ..MonitoringConfig::default()
+        });
+        for (id, method) in ["cc_ping", "x1", "x2"].into_iter().enumerate() {
+            monitor
+                .start_request(id.to_string(), method.to_string(), 10)
+                .unwrap();
+            monitor.complete_request(id.to_string(), 20).unwrap();
+        }
+        monitor.flush();
+        let totals = monitor.method_totals.lock().unwrap();
+        assert_eq!(totals.len(), 2);
+        assert_eq!(totals[OTHER_METHOD].requests, 2);
+    }
+}
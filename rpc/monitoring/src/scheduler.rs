@@ -0,0 +1,170 @@
+//! Background monitoring
+//!
+//! Without a scheduler, windows are aggregated and telemetry exported only
+//! when a request finishes, so an idle node reports nothing. The scheduler
+//! is a tokio task doing this work on the configured intervals: it closes
+//! aggregation windows, runs health probes and checks alerts, and pushes
+//! OpenTelemetry data.
+
+use crate::RpcMonitor;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, MissedTickBehavior};
+
+/// A running scheduler, stopped when dropped
+pub struct MonitorScheduler {
+    task: JoinHandle<()>,
+}
+
+impl MonitorScheduler {
+    /// Stop the scheduler
+    pub fn stop(self) {
+        self.task.abort();
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.task.is_finished()
+    }
+}
+
+impl Drop for MonitorScheduler {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Work the scheduler does on a tick
+#[derive(Clone, Copy)]
+enum Job {
+    Aggregate,
+    CheckHealth,
+    Export,
+}
+
+impl RpcMonitor {
+    /// Run aggregation, health probes, alert checks and exports in the
+    /// background until the returned handle or the monitor is dropped
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn start_scheduler(self: &Arc<Self>) -> MonitorScheduler {
+        let monitor = Arc::downgrade(self);
+        let config = &self.config;
+        let intervals = [
+            (Job::Aggregate, config.aggregation_window),
+            (Job::CheckHealth, config.health_check_interval),
+            (Job::Export, config.export_interval),
+        ];
+        MonitorScheduler {
+            task: tokio::spawn(run(monitor, intervals)),
+        }
+    }
+
+    fn run_job(&self, job: Job) {
+        // Failures are transient or counted where they happen; the next
+        // tick tries again
+        match job {
+            Job::Aggregate => {
+                let _ = self.aggregate_metrics();
+            }
+            Job::CheckHealth => {
+                self.run_health_probes();
+                let _ = self.check_alerts();
+            }
+            Job::Export => {
+                let _ = self.maybe_export_otlp();
+            }
+        }
+    }
+}
+
+async fn run(monitor: Weak<RpcMonitor>, intervals: [(Job, Duration); 3]) {
+    let mut ticks = intervals.map(|(job, period)| {
+        let mut ticks = interval(period.max(Duration::from_millis(1)));
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        (job, ticks)
+    });
+
+    loop {
+        let [aggregate, health, export] = &mut ticks;
+        let job = tokio::select! {
+            _ = aggregate.1.tick() => aggregate.0,
+            _ = health.1.tick() => health.0,
+            _ = export.1.tick() => export.0,
+        };
+        let Some(monitor) = monitor.upgrade() else {
+            return;
+        };
+        // Probes and exports may block on I/O
+        if tokio::task::spawn_blocking(move || monitor.run_job(job))
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ComponentHealth, HealthLevel, HealthProbe, MonitoringConfig};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct CountingProbe {
+        checks: AtomicUsize,
+    }
+
+    impl HealthProbe for CountingProbe {
+        fn name(&self) -> &str {
+            "storage"
+        }
+
+        fn check(&self) -> ComponentHealth {
+            self.checks.fetch_add(1, Ordering::Relaxed);
+            ComponentHealth {
+                status: HealthLevel::Down,
+                message: "Disk full".to_string(),
+                last_check: 0,
+                response_time_ms: None,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_runs_while_idle() {
+        let monitor = Arc::new(RpcMonitor::with_config(MonitoringConfig {
+            aggregation_window: Duration::from_millis(20),
+            health_check_interval: Duration::from_millis(10),
+            ..MonitoringConfig::default()
+        }));
+        let probe = Arc::new(CountingProbe::default());
+        monitor.register_health_probe(probe.clone());
+        monitor
+            .start_request("1".to_string(), "cc_ping".to_string(), 10)
+            .unwrap();
+        monitor.fail_request("1".to_string(), -32603).unwrap();
+        assert!(monitor.get_active_alerts().unwrap().is_empty());
+
+        let scheduler = monitor.start_scheduler();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(scheduler.is_running());
+        assert!(probe.checks.load(Ordering::Relaxed) >= 2);
+        assert!(!monitor
+            .get_metrics(Duration::from_secs(60))
+            .unwrap()
+            .is_empty());
+        assert_eq!(monitor.get_active_alerts().unwrap().len(), 1);
+
+        let health = monitor.get_health_status().unwrap();
+        assert!(matches!(health.overall_status, HealthLevel::Down));
+        assert_eq!(health.component_statuses["storage"].message, "Disk full");
+
+        scheduler.stop();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let checks = probe.checks.load(Ordering::Relaxed);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(probe.checks.load(Ordering::Relaxed), checks);
+    }
+}
@@ -0,0 +1,426 @@
+//! Service level objectives
+//!
+//! An SLO sets the share of requests that must be good over a rolling
+//! period, e.g. 99.9% succeeding over 30 days, or 99% finishing within
+//! 500ms (a p99 latency objective). The remaining share is the error
+//! budget. Alerts follow the multi-window burn-rate approach: a rule fires
+//! when the budget is being spent faster than its rate over both a long
+//! window, so brief spikes are ignored, and a short one, so the alert
+//! resolves soon after the problem does.
+
+use crate::{AlertSeverity, RequestMetrics, RequestStatus};
+use cc_core_metrics::MetricSample;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+
+/// A service level objective for RPC requests
+#[derive(Debug, Clone)]
+pub struct SloConfig {
+    /// Identifies the SLO in alerts and metrics
+    pub name: String,
+    pub objective: SloObjective,
+    /// Share of requests that must be good, in percent
+    pub target_percent: f64,
+    /// Rolling period compliance is measured over
+    pub period: Duration,
+    pub burn_alerts: Vec<BurnRateAlert>,
+}
+
+impl SloConfig {
+    /// `target_percent` of requests succeed over 30 days
+    pub fn availability(name: impl Into<String>, target_percent: f64) -> Self {
+        Self::new(name, SloObjective::Success, target_percent)
+    }
+
+    /// `target_percent` of requests finish within `threshold_ms` over 30
+    /// days; a p99 objective has a target of 99%
+    pub fn latency(name: impl Into<String>, threshold_ms: u64, target_percent: f64) -> Self {
+        Self::new(name, SloObjective::Latency { threshold_ms }, target_percent)
+    }
+
+    fn new(name: impl Into<String>, objective: SloObjective, target_percent: f64) -> Self {
+        Self {
+            name: name.into(),
+            objective,
+            target_percent,
+            period: Duration::from_secs(30 * DAY),
+            burn_alerts: BurnRateAlert::defaults(),
+        }
+    }
+
+    fn is_good(&self, metrics: &RequestMetrics) -> bool {
+        match self.objective {
+            SloObjective::Success => matches!(metrics.status, RequestStatus::Success),
+            SloObjective::Latency { threshold_ms } => {
+                !matches!(metrics.status, RequestStatus::Timeout)
+                    && metrics.duration_ms.is_some_and(|ms| ms <= threshold_ms)
+            }
+        }
+    }
+
+    /// Share of requests allowed to be bad
+    fn error_budget(&self) -> f64 {
+        1.0 - self.target_percent / 100.0
+    }
+}
+
+/// What makes a request good
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SloObjective {
+    /// The request succeeded
+    Success,
+    /// The request finished, successfully or not, within the threshold
+    Latency { threshold_ms: u64 },
+}
+
+/// Alert when the error budget burns at `burn_rate` times the pace that
+/// would use it up exactly over the SLO period, over both windows
+#[derive(Debug, Clone)]
+pub struct BurnRateAlert {
+    pub long_window: Duration,
+    pub short_window: Duration,
+    pub burn_rate: f64,
+    pub severity: AlertSeverity,
+}
+
+impl BurnRateAlert {
+    /// The usual rules for a 30 day period: 2% of the budget spent in an
+    /// hour or 5% in six hours is critical, 10% in three days a warning
+    pub fn defaults() -> Vec<Self> {
+        let rule = |long: u64, short: u64, burn_rate, severity| Self {
+            long_window: Duration::from_secs(long),
+            short_window: Duration::from_secs(short),
+            burn_rate,
+            severity,
+        };
+        vec![
+            rule(HOUR, 5 * 60, 14.4, AlertSeverity::Critical),
+            rule(6 * HOUR, 30 * 60, 6.0, AlertSeverity::Critical),
+            rule(3 * DAY, 6 * HOUR, 1.0, AlertSeverity::Warning),
+        ]
+    }
+}
+
+/// Compliance of an SLO over its period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SloStatus {
+    pub name: String,
+    pub target_percent: f64,
+    pub total_requests: u64,
+    pub good_requests: u64,
+    /// Share of good requests; 100 without requests
+    pub compliance_percent: f64,
+    /// Share of the error budget left, negative once it is overspent
+    pub error_budget_remaining_percent: f64,
+    /// Burn rate over each window of the SLO's alert rules
+    pub burn_rates: Vec<BurnRate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BurnRate {
+    pub window: Duration,
+    pub rate: f64,
+}
+
+/// Good and total requests finished in one bucket
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    start: u64,
+    good: u64,
+    total: u64,
+}
+
+/// An SLO with its request counts
+struct TrackedSlo {
+    config: SloConfig,
+    /// Oldest first
+    buckets: VecDeque<Bucket>,
+    /// Longest span the counts are needed for, in millis
+    horizon: u64,
+}
+
+impl TrackedSlo {
+    /// Good and total requests finished after `since`
+    fn counts(&self, since: u64, width: u64) -> (u64, u64) {
+        self.buckets
+            .iter()
+            .rev()
+            .take_while(|bucket| bucket.start + width > since)
+            .fold((0, 0), |(good, total), bucket| {
+                (good + bucket.good, total + bucket.total)
+            })
+    }
+
+    fn burn_rate(&self, window: Duration, now: u64, width: u64) -> f64 {
+        let (good, total) = self.counts(now.saturating_sub(window.as_millis() as u64), width);
+        let bad = total - good;
+        if bad == 0 {
+            return 0.0;
+        }
+        bad as f64 / total as f64 / self.config.error_budget()
+    }
+}
+
+/// Counts good and bad requests per SLO in buckets one aggregation window
+/// wide
+pub(crate) struct SloTracker {
+    /// Bucket width in millis
+    width: u64,
+    slos: Mutex<Vec<TrackedSlo>>,
+}
+
+impl SloTracker {
+    pub(crate) fn new(slos: &[SloConfig], bucket_width: Duration) -> Self {
+        let slos = slos
+            .iter()
+            .map(|config| {
+                let longest_window = config.burn_alerts.iter().map(|rule| rule.long_window);
+                TrackedSlo {
+                    horizon: longest_window
+                        .fold(config.period, Duration::max)
+                        .as_millis() as u64,
+                    config: config.clone(),
+                    buckets: VecDeque::new(),
+                }
+            })
+            .collect();
+        Self {
+            width: (bucket_width.as_millis() as u64).max(1),
+            slos: Mutex::new(slos),
+        }
+    }
+
+    /// Count a finished request against every SLO
+    pub(crate) fn record(&self, metrics: &RequestMetrics) {
+        let Some(end_time) = metrics.end_time else {
+            return;
+        };
+        let start = end_time - end_time % self.width;
+        let mut slos = self.slos.lock().unwrap();
+        for slo in slos.iter_mut() {
+            let good = u64::from(slo.config.is_good(metrics));
+            match slo.buckets.back_mut() {
+                Some(bucket) if bucket.start == start => {
+                    bucket.good += good;
+                    bucket.total += 1;
+                }
+                _ => slo.buckets.push_back(Bucket {
+                    start,
+                    good,
+                    total: 1,
+                }),
+            }
+            let cutoff = end_time.saturating_sub(slo.horizon);
+            while slo
+                .buckets
+                .front()
+                .is_some_and(|bucket| bucket.start + self.width <= cutoff)
+            {
+                slo.buckets.pop_front();
+            }
+        }
+    }
+
+    pub(crate) fn status(&self, now: u64) -> Vec<SloStatus> {
+        let slos = self.slos.lock().unwrap();
+        slos.iter()
+            .map(|slo| {
+                let config = &slo.config;
+                let since = now.saturating_sub(config.period.as_millis() as u64);
+                let (good, total) = slo.counts(since, self.width);
+                let bad_share = if total == 0 {
+                    0.0
+                } else {
+                    (total - good) as f64 / total as f64
+                };
+                let mut windows: Vec<Duration> = config
+                    .burn_alerts
+                    .iter()
+                    .flat_map(|rule| [rule.long_window, rule.short_window])
+                    .collect();
+                windows.sort();
+                windows.dedup();
+                SloStatus {
+                    name: config.name.clone(),
+                    target_percent: config.target_percent,
+                    total_requests: total,
+                    good_requests: good,
+                    compliance_percent: (1.0 - bad_share) * 100.0,
+                    error_budget_remaining_percent: (1.0 - bad_share / config.error_budget())
+                        * 100.0,
+                    burn_rates: windows
+                        .into_iter()
+                        .map(|window| BurnRate {
+                            window,
+                            rate: slo.burn_rate(window, now, self.width),
+                        })
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+
+    /// Every burn-rate rule by alert id, with a message when it fires
+    pub(crate) fn burn_alerts(&self, now: u64) -> Vec<(String, AlertSeverity, Option<String>)> {
+        let slos = self.slos.lock().unwrap();
+        let mut alerts = Vec::new();
+        for slo in slos.iter() {
+            for rule in &slo.config.burn_alerts {
+                let long = slo.burn_rate(rule.long_window, now, self.width);
+                let short = slo.burn_rate(rule.short_window, now, self.width);
+                let message = (long > rule.burn_rate && short > rule.burn_rate).then(|| {
+                    format!(
+                        "SLO {} is burning its error budget at {:.1}x over {} and {:.1}x over {} (threshold {}x)",
+                        slo.config.name,
+                        long,
+                        window_label(rule.long_window),
+                        short,
+                        window_label(rule.short_window),
+                        rule.burn_rate
+                    )
+                });
+                let id = format!(
+                    "slo_{}_burn_{}",
+                    slo.config.name,
+                    window_label(rule.long_window)
+                );
+                alerts.push((id, rule.severity.clone(), message));
+            }
+        }
+        alerts
+    }
+}
+
+/// SLO compliance, remaining budget and burn rates as metric samples
+pub(crate) fn slo_samples(statuses: &[SloStatus]) -> Vec<MetricSample> {
+    let mut samples = Vec::new();
+    for status in statuses {
+        samples.push(
+            MetricSample::gauge(
+                "cc_rpc_slo_compliance_percent",
+                "Share of good requests over the SLO period",
+                status.compliance_percent,
+            )
+            .with_label("slo", &status.name),
+        );
+        samples.push(
+            MetricSample::gauge(
+                "cc_rpc_slo_error_budget_remaining_percent",
+                "Share of the SLO error budget left",
+                status.error_budget_remaining_percent,
+            )
+            .with_label("slo", &status.name),
+        );
+        for burn_rate in &status.burn_rates {
+            samples.push(
+                MetricSample::gauge(
+                    "cc_rpc_slo_burn_rate",
+                    "Pace the SLO error budget is spent at, relative to the SLO period",
+                    burn_rate.rate,
+                )
+                .with_label("slo", &status.name)
+                .with_label("window", window_label(burn_rate.window)),
+            );
+        }
+    }
+    samples
+}
+
+/// Short form of a window, such as `5m`, `6h` or `3d`
+fn window_label(window: Duration) -> String {
+    let secs = window.as_secs();
+    match secs {
+        0 => format!("{}ms", window.as_millis()),
+        _ if secs.is_multiple_of(DAY) => format!("{}d", secs / DAY),
+        _ if secs.is_multiple_of(HOUR) => format!("{}h", secs / HOUR),
+        _ if secs.is_multiple_of(60) => format!("{}m", secs / 60),
+        _ => format!("{secs}s"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: u64 = 60_000;
+    const NOW: u64 = 1_700_000_000_000;
+
+    fn request(end_time: u64, status: RequestStatus, duration_ms: u64) -> RequestMetrics {
+        RequestMetrics {
+            method: "cc_getBlock".to_string(),
+            start_time: end_time - duration_ms,
+            end_time: Some(end_time),
+            duration_ms: Some(duration_ms),
+            status,
+            error_code: None,
+            request_size: 10,
+            response_size: None,
+            client_id: None,
+            sample_weight: 1,
+        }
+    }
+
+    #[test]
+    fn test_compliance_and_budget() {
+        let slos = [
+            SloConfig::availability("availability", 99.0),
+            SloConfig::latency("latency", 500, 90.0),
+        ];
+        let tracker = SloTracker::new(&slos, Duration::from_secs(60));
+        // 40 days ago, outside the period
+        tracker.record(&request(
+            NOW - 40 * 24 * 60 * MINUTE,
+            RequestStatus::Error,
+            10,
+        ));
+        for i in 0..198 {
+            tracker.record(&request(
+                NOW - 2 * 24 * 60 * MINUTE + i,
+                RequestStatus::Success,
+                100,
+            ));
+        }
+        tracker.record(&request(NOW - MINUTE, RequestStatus::Error, 900));
+        tracker.record(&request(NOW - MINUTE, RequestStatus::Timeout, 100));
+
+        let status = tracker.status(NOW);
+        assert_eq!(status[0].total_requests, 200);
+        assert_eq!(status[0].good_requests, 198);
+        assert!((status[0].compliance_percent - 99.0).abs() < 1e-9);
+        assert!(status[0].error_budget_remaining_percent.abs() < 1e-6);
+        // Both requests in the last five minutes failed
+        let five_minutes = &status[0].burn_rates[0];
+        assert_eq!(five_minutes.window, Duration::from_secs(5 * 60));
+        assert!((five_minutes.rate - 100.0).abs() < 1e-9);
+
+        assert_eq!(status[1].good_requests, 198);
+        assert!((status[1].error_budget_remaining_percent - 90.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_multi_window_burn_alerts() {
+        let mut slo = SloConfig::availability("availability", 99.9);
+        slo.burn_alerts.truncate(1);
+        let tracker = SloTracker::new(&[slo], Duration::from_secs(60));
+        // An outage half an hour ago, since recovered
+        for i in 0..100 {
+            tracker.record(&request(NOW - 30 * MINUTE + i, RequestStatus::Error, 10));
+            tracker.record(&request(NOW - 2 * MINUTE + i, RequestStatus::Success, 10));
+        }
+        let alerts = tracker.burn_alerts(NOW);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].0, "slo_availability_burn_1h");
+        assert!(alerts[0].2.is_none());
+
+        // Failing again
+        tracker.record(&request(NOW - MINUTE, RequestStatus::Error, 10));
+        let alerts = tracker.burn_alerts(NOW);
+        assert!(matches!(alerts[0].1, AlertSeverity::Critical));
+        assert!(alerts[0].2.as_ref().unwrap().contains("over 1h"));
+    }
+}
@@ -0,0 +1,319 @@
+//! Slow request log
+//!
+//! Requests taking longer than a threshold are kept in a bounded buffer,
+//! newest replacing oldest, with what is needed to investigate them: the
+//! method and client, where the time went, and the params. Transports
+//! report the phases of a request as they pass them and hand over the
+//! params when it finishes; params are only kept for requests already over
+//! the threshold, after secrets are redacted and long values cut short.
+//! Secrets passed as positional params cannot be told apart from other
+//! values, and are only shortened.
+
+use crate::{RequestMetrics, RequestStatus};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Object keys whose values are redacted, matched ignoring case, `_` and `-`
+const SECRET_KEYS: [&str; 8] = [
+    "password",
+    "passphrase",
+    "secret",
+    "privatekey",
+    "mnemonic",
+    "seed",
+    "token",
+    "apikey",
+];
+
+/// Strings longer than this are cut short
+const MAX_STRING_CHARS: usize = 64;
+
+/// Arrays longer than this are cut short
+const MAX_ARRAY_ITEMS: usize = 16;
+
+/// Slow log configuration
+#[derive(Debug, Clone)]
+pub struct SlowLogConfig {
+    /// Requests taking at least this long are logged
+    pub threshold: Duration,
+    /// Requests kept; 0 disables the log
+    pub capacity: usize,
+    /// Longest params kept, in bytes of JSON
+    pub max_params_bytes: usize,
+}
+
+impl Default for SlowLogConfig {
+    fn default() -> Self {
+        Self {
+            threshold: Duration::from_secs(1),
+            capacity: 100,
+            max_params_bytes: 1024,
+        }
+    }
+}
+
+/// A logged slow request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlowRequest {
+    pub request_id: String,
+    pub method: String,
+    pub client_id: Option<String>,
+    pub start_time: u64,
+    pub duration_ms: u64,
+    pub status: RequestStatus,
+    pub error_code: Option<i32>,
+    pub request_size: usize,
+    pub response_size: Option<usize>,
+    /// BLAKE3 of the params as received, equal for identical payloads
+    pub params_digest: Option<String>,
+    /// Params with secrets redacted and long values cut short
+    pub params: Option<Value>,
+    /// Phases the request went through, in order
+    pub phases: Vec<RequestPhase>,
+}
+
+/// Time a request spent in one phase, such as waiting for a worker or
+/// running its handler
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestPhase {
+    pub name: String,
+    pub duration_ms: u64,
+}
+
+/// What is known about an active request beyond its metrics
+#[derive(Default)]
+struct Trace {
+    phases: Vec<RequestPhase>,
+    params_digest: Option<String>,
+    params: Option<Value>,
+}
+
+pub(crate) struct SlowLog {
+    config: SlowLogConfig,
+    /// Active requests by id
+    traces: Mutex<HashMap<String, Trace>>,
+    /// Oldest first
+    entries: Mutex<VecDeque<SlowRequest>>,
+}
+
+impl SlowLog {
+    pub(crate) fn new(config: SlowLogConfig) -> Self {
+        Self {
+            config,
+            traces: Mutex::new(HashMap::new()),
+            entries: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub(crate) fn enabled(&self) -> bool {
+        self.config.capacity > 0
+    }
+
+    pub(crate) fn threshold(&self) -> Duration {
+        self.config.threshold
+    }
+
+    pub(crate) fn add_phase(&self, request_id: &str, name: &str, duration: Duration) {
+        let mut traces = self.traces.lock().unwrap();
+        traces
+            .entry(request_id.to_string())
+            .or_default()
+            .phases
+            .push(RequestPhase {
+                name: name.to_string(),
+                duration_ms: duration.as_millis() as u64,
+            });
+    }
+
+    pub(crate) fn capture_params(&self, request_id: &str, params: &Value) {
+        let params_digest = params_digest(params);
+        let params = sanitize_params(params, self.config.max_params_bytes);
+        let mut traces = self.traces.lock().unwrap();
+        let trace = traces.entry(request_id.to_string()).or_default();
+        trace.params_digest = Some(params_digest);
+        trace.params = Some(params);
+    }
+
+    /// Log a finished request if it was slow, and forget its trace
+    pub(crate) fn finish(&self, request_id: &str, metrics: &RequestMetrics) {
+        let trace = self.traces.lock().unwrap().remove(request_id);
+        let duration_ms = metrics.duration_ms.unwrap_or_default();
+        if !self.enabled() || duration_ms < self.config.threshold.as_millis() as u64 {
+            return;
+        }
+        let trace = trace.unwrap_or_default();
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == self.config.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(SlowRequest {
+            request_id: request_id.to_string(),
+            method: metrics.method.clone(),
+            client_id: metrics.client_id.clone(),
+            start_time: metrics.start_time,
+            duration_ms,
+            status: metrics.status.clone(),
+            error_code: metrics.error_code,
+            request_size: metrics.request_size,
+            response_size: metrics.response_size,
+            params_digest: trace.params_digest,
+            params: trace.params,
+            phases: trace.phases,
+        });
+    }
+
+    /// Up to `limit` logged requests, newest first, optionally only those
+    /// for `method`
+    pub(crate) fn entries(&self, limit: usize, method: Option<&str>) -> Vec<SlowRequest> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .iter()
+            .rev()
+            .filter(|entry| method.is_none_or(|method| entry.method == method))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Hex BLAKE3 of the params' JSON
+pub fn params_digest(params: &Value) -> String {
+    hex::encode(blake3::hash(params.to_string().as_bytes()).as_bytes())
+}
+
+/// `params` with the values of secret-looking keys redacted and long
+/// strings and arrays cut short; params still over `max_bytes` of JSON are
+/// replaced by the start of their JSON
+pub fn sanitize_params(params: &Value, max_bytes: usize) -> Value {
+    let sanitized = sanitize(params);
+    let json = sanitized.to_string();
+    if json.len() <= max_bytes {
+        return sanitized;
+    }
+    let mut end = max_bytes;
+    while !json.is_char_boundary(end) {
+        end -= 1;
+    }
+    Value::String(format!("{}…(+{} bytes)", &json[..end], json.len() - end))
+}
+
+fn sanitize(value: &Value) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .iter()
+                .map(|(key, value)| {
+                    let value = if is_secret_key(key) {
+                        Value::String("[redacted]".to_string())
+                    } else {
+                        sanitize(value)
+                    };
+                    (key.clone(), value)
+                })
+                .collect(),
+        ),
+        Value::Array(values) => {
+            let mut sanitized: Vec<Value> =
+                values.iter().take(MAX_ARRAY_ITEMS).map(sanitize).collect();
+            if values.len() > MAX_ARRAY_ITEMS {
+                sanitized.push(Value::String(format!(
+                    "…(+{} items)",
+                    values.len() - MAX_ARRAY_ITEMS
+                )));
+            }
+            Value::Array(sanitized)
+        }
+        Value::String(string) if string.chars().count() > MAX_STRING_CHARS => {
+            let kept: String = string.chars().take(MAX_STRING_CHARS / 2).collect();
+            Value::String(format!("{kept}…(+{} bytes)", string.len() - kept.len()))
+        }
+        other => other.clone(),
+    }
+}
+
+fn is_secret_key(key: &str) -> bool {
+    let key: String = key
+        .chars()
+        .filter(|c| *c != '_' && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect();
+    SECRET_KEYS.iter().any(|secret| key.contains(secret))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sanitize_params() {
+        let params = json!([{
+            "from": "0x01",
+            "private_key": "deadbeef",
+            "apiKey": "k",
+            "nested": {"Pass-Phrase": "hunter2"},
+            "data": "ab".repeat(100),
+            "hashes": (0..20).collect::<Vec<_>>(),
+        }]);
+        let sanitized = sanitize_params(&params, 1024);
+        let call = &sanitized[0];
+        assert_eq!(call["from"], "0x01");
+        assert_eq!(call["private_key"], "[redacted]");
+        assert_eq!(call["apiKey"], "[redacted]");
+        assert_eq!(call["nested"]["Pass-Phrase"], "[redacted]");
+        assert_eq!(call["data"], format!("{}…(+168 bytes)", "ab".repeat(16)));
+        assert_eq!(
+            call["hashes"].as_array().unwrap().len(),
+            MAX_ARRAY_ITEMS + 1
+        );
+        assert_eq!(call["hashes"][MAX_ARRAY_ITEMS], "…(+4 items)");
+
+        let truncated = sanitize_params(&params, 32);
+        assert!(truncated.as_str().unwrap().starts_with("[{\""));
+        assert_eq!(params_digest(&params), params_digest(&params.clone()));
+        assert_ne!(params_digest(&params), params_digest(&json!([])));
+    }
+
+    #[test]
+    fn test_bounded_log() {
+        let log = SlowLog::new(SlowLogConfig {
+            threshold: Duration::from_millis(100),
+            capacity: 2,
+            max_params_bytes: 1024,
+        });
+        let finished = |method: &str, duration_ms| RequestMetrics {
+            method: method.to_string(),
+            start_time: 1_000,
+            end_time: Some(1_000 + duration_ms),
+            duration_ms: Some(duration_ms),
+            status: RequestStatus::Success,
+            error_code: None,
+            request_size: 10,
+            response_size: Some(20),
+            client_id: Some("127.0.0.1".to_string()),
+            sample_weight: 1,
+        };
+
+        log.add_phase("1", "handler", Duration::from_millis(5));
+        log.finish("1", &finished("cc_ping", 5));
+        for (id, method) in [("2", "cc_getBlock"), ("3", "cc_call"), ("4", "cc_getBlock")] {
+            log.add_phase(id, "queued", Duration::from_millis(40));
+            log.add_phase(id, "handler", Duration::from_millis(110));
+            log.capture_params(id, &json!([id]));
+            log.finish(id, &finished(method, 150));
+        }
+        assert!(log.traces.lock().unwrap().is_empty());
+
+        let entries = log.entries(10, None);
+        let ids: Vec<&str> = entries.iter().map(|e| e.request_id.as_str()).collect();
+        assert_eq!(ids, ["4", "3"]);
+        assert_eq!(entries[0].phases.len(), 2);
+        assert_eq!(entries[0].phases[1].duration_ms, 110);
+        assert_eq!(entries[0].params, Some(json!(["4"])));
+        assert_eq!(log.entries(10, Some("cc_call"))[0].request_id, "3");
+        assert_eq!(log.entries(1, None).len(), 1);
+    }
+}
@@ -0,0 +1,141 @@
+//! Process resource usage
+//!
+//! Memory and CPU usage of the node process, read from `/proc/self`. CPU
+//! usage is the share of one core used since the previous sample, so a
+//! process busy on several cores reports over 100%. Where `/proc` is not
+//! available, such as on macOS, usage is unknown.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Clock ticks per second in `/proc` CPU times, fixed by the kernel ABI
+const USER_HZ: f64 = 100.0;
+
+/// Shortest span CPU usage is measured over; samples taken sooner repeat
+/// the previous value
+const MIN_CPU_INTERVAL: Duration = Duration::from_secs(1);
+
+/// One reading of the process's resource usage
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SystemUsage {
+    pub memory_usage_mb: Option<u64>,
+    pub cpu_usage_percent: Option<f64>,
+}
+
+struct CpuSample {
+    taken: Instant,
+    ticks: u64,
+    percent: f64,
+}
+
+/// Samples the process's resource usage
+#[derive(Default)]
+pub struct SystemProbe {
+    last_cpu: Mutex<Option<CpuSample>>,
+}
+
+impl SystemProbe {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Current memory usage and CPU usage since the previous sample, or
+    /// since the process started on the first
+    pub fn sample(&self) -> SystemUsage {
+        let memory_usage_mb = std::fs::read_to_string("/proc/self/status")
+            .ok()
+            .and_then(|status| parse_rss_kb(&status))
+            .map(|kb| kb / 1024);
+        SystemUsage {
+            memory_usage_mb,
+            cpu_usage_percent: self.sample_cpu(),
+        }
+    }
+
+    fn sample_cpu(&self) -> Option<f64> {
+        let stat = std::fs::read_to_string("/proc/self/stat").ok()?;
+        let (ticks, start_ticks) = parse_cpu_ticks(&stat)?;
+        let now = Instant::now();
+
+        let mut last = self.last_cpu.lock().unwrap();
+        let percent = match last.as_ref() {
+            Some(last) if now.duration_since(last.taken) < MIN_CPU_INTERVAL => {
+                return Some(last.percent)
+            }
+            Some(last) => {
+                let busy = ticks.saturating_sub(last.ticks) as f64 / USER_HZ;
+                busy / now.duration_since(last.taken).as_secs_f64() * 100.0
+            }
+            None => {
+                let uptime = std::fs::read_to_string("/proc/uptime").ok()?;
+                let uptime: f64 = uptime.split_whitespace().next()?.parse().ok()?;
+                let alive = uptime - start_ticks as f64 / USER_HZ;
+                if alive <= 0.0 {
+                    0.0
+                } else {
+                    ticks as f64 / USER_HZ / alive * 100.0
+                }
+            }
+        };
+        *last = Some(CpuSample {
+            taken: now,
+            ticks,
+            percent,
+        });
+        Some(percent)
+    }
+}
+
+/// Resident set size in kB from the contents of `/proc/<pid>/status`
+fn parse_rss_kb(status: &str) -> Option<u64> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// User plus system CPU ticks, and the start time in ticks after boot,
+/// from the contents of `/proc/<pid>/stat`
+fn parse_cpu_ticks(stat: &str) -> Option<(u64, u64)> {
+    // The command name may hold spaces and parentheses; fields resume after
+    // its closing parenthesis, starting with field 3
+    let fields: Vec<&str> = stat
+        .get(stat.rfind(')')? + 1..)?
+        .split_whitespace()
+        .collect();
+    let field = |number: usize| fields.get(number - 3)?.parse::<u64>().ok();
+    Some((field(14)? + field(15)?, field(22)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_files() {
+        let status = "Name:\tcc-node\nVmPeak:\t  300000 kB\nVmRSS:\t  204800 kB\nThreads:\t12\n";
+        assert_eq!(parse_rss_kb(status), Some(204800));
+        assert_eq!(parse_rss_kb("Name:\tcc-node\n"), None);
+
+        let stat = "4242 (cc node (main)) S 1 4242 4242 0 -1 4194560 5000 0 12 0 \
+                    250 50 0 0 20 0 12 0 123456 1000000 51200 18446744073709551615";
+        assert_eq!(parse_cpu_ticks(stat), Some((300, 123456)));
+        assert_eq!(parse_cpu_ticks("4242 (cc-node) S 1"), None);
+    }
+
+    #[test]
+    fn test_sample() {
+        let probe = SystemProbe::new();
+        let usage = probe.sample();
+        if cfg!(target_os = "linux") {
+            assert!(usage.memory_usage_mb.is_some());
+            let cpu = usage.cpu_usage_percent.unwrap();
+            assert!(cpu >= 0.0);
+            // Within the minimum interval the previous value is repeated
+            assert_eq!(probe.sample().cpu_usage_percent, Some(cpu));
+        }
+    }
+}
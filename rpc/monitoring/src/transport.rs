@@ -0,0 +1,207 @@
+//! Outbound HTTP
+//!
+//! Telemetry and alert notifications leave the node as JSON POSTs, as do
+//! canary calls, which also read the answer. The
+//! `Transport` trait lets deployments route them through their own client,
+//! e.g. one speaking TLS; `HttpTransport` is a plain HTTP/1.1 client for
+//! collectors and relays on the local network.
+
+use crate::{MonitoringError, Result};
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// Delivers JSON payloads over HTTP
+pub trait Transport: Send + Sync {
+    /// POST the JSON `body` to `url`, failing unless answered with 2xx
+    fn post_json(&self, url: &str, body: &str) -> Result<()>;
+
+    /// POST the JSON `body` to `url` and return the response body, failing
+    /// unless answered with 2xx; transports that only deliver return an
+    /// empty body
+    fn exchange_json(&self, url: &str, body: &str) -> Result<String> {
+        self.post_json(url, body).map(|_| String::new())
+    }
+}
+
+/// Plain HTTP/1.1 transport; `https://` URLs need a TLS-terminating proxy
+/// or a custom `Transport`
+pub struct HttpTransport {
+    timeout: Duration,
+}
+
+impl HttpTransport {
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+}
+
+impl Transport for HttpTransport {
+    fn post_json(&self, url: &str, body: &str) -> Result<()> {
+        self.exchange_json(url, body).map(|_| ())
+    }
+
+    fn exchange_json(&self, url: &str, body: &str) -> Result<String> {
+        let export_error =
+            |error: &dyn std::fmt::Display| MonitoringError::ExportError(format!("{url}: {error}"));
+
+        let rest = url.strip_prefix("http://").ok_or_else(|| {
+            MonitoringError::ConfigError(format!("Expected an http:// URL: {url}"))
+        })?;
+        let (authority, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, "/"),
+        };
+        let mut stream =
+            connect(authority, 80, self.timeout).map_err(|error| export_error(&error))?;
+        let request = format!(
+            "POST {path} HTTP/1.1\r\nHost: {authority}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream
+            .write_all(request.as_bytes())
+            .map_err(|error| export_error(&error))?;
+
+        let mut response = Vec::new();
+        stream
+            .read_to_end(&mut response)
+            .map_err(|error| export_error(&error))?;
+        let response = String::from_utf8_lossy(&response);
+        let status = response.lines().next().unwrap_or_default();
+        match status.split_whitespace().nth(1) {
+            Some(code) if code.starts_with('2') => Ok(response_body(&response)),
+            _ => Err(export_error(&format!("server returned {status:?}"))),
+        }
+    }
+}
+
+/// Body of a whole HTTP/1.1 response, joining chunks if it was chunked
+fn response_body(response: &str) -> String {
+    let Some((head, body)) = response.split_once("\r\n\r\n") else {
+        return String::new();
+    };
+    let chunked = head.lines().any(|line| {
+        let line = line.to_ascii_lowercase();
+        line.starts_with("transfer-encoding:") && line.contains("chunked")
+    });
+    if !chunked {
+        return body.to_string();
+    }
+
+    let mut joined = String::new();
+    let mut rest = body;
+    while let Some((size, after)) = rest.split_once("\r\n") {
+        // Chunk extensions follow a `;`
+        let size = size.split(';').next().unwrap_or_default().trim();
+        let Ok(size) = usize::from_str_radix(size, 16) else {
+            break;
+        };
+        if size == 0 || after.len() < size {
+            break;
+        }
+        joined.push_str(&after[..size]);
+        rest = after[size..].trim_start_matches("\r\n");
+    }
+    joined
+}
+
+/// Connect to `host[:port]` with `timeout` applied to the connection and
+/// to every read and write on it
+pub(crate) fn connect(
+    authority: &str,
+    default_port: u16,
+    timeout: Duration,
+) -> std::io::Result<TcpStream> {
+    let address = if authority.contains(':') {
+        authority.to_string()
+    } else {
+        format!("{authority}:{default_port}")
+    };
+    let address = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no address for host"))?;
+    let stream = TcpStream::connect_timeout(&address, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    Ok(stream)
+}
+
+/// Records what is posted instead of sending it
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct RecordingTransport {
+    sent: std::sync::Mutex<Vec<(String, serde_json::Value)>>,
+    /// Posts to fail before succeeding
+    pub(crate) failures: std::sync::atomic::AtomicUsize,
+}
+
+#[cfg(test)]
+impl RecordingTransport {
+    pub(crate) fn sent(&self) -> Vec<(String, serde_json::Value)> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+impl Transport for RecordingTransport {
+    fn post_json(&self, url: &str, body: &str) -> Result<()> {
+        use std::sync::atomic::Ordering;
+
+        let failing = self
+            .failures
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| {
+                left.checked_sub(1)
+            })
+            .is_ok();
+        if failing {
+            return Err(MonitoringError::ExportError(format!("{url}: unavailable")));
+        }
+        let body = serde_json::from_str(body).unwrap();
+        self.sent.lock().unwrap().push((url.to_string(), body));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_http_transport() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = vec![0; 4096];
+            let read = stream.read(&mut request).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            String::from_utf8_lossy(&request[..read]).to_string()
+        });
+
+        let transport = HttpTransport::new(Duration::from_secs(5));
+        transport
+            .post_json(&format!("{endpoint}/v1/traces"), "{}")
+            .unwrap();
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /v1/traces HTTP/1.1\r\n"));
+        assert!(request.ends_with("\r\n\r\n{}"));
+
+        assert!(matches!(
+            transport.post_json("https://collector/v1/traces", "{}"),
+            Err(MonitoringError::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn test_response_body() {
+        let plain = "HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\n{\"id\":1}\r\n";
+        assert_eq!(response_body(plain), "{\"id\":1}\r\n");
+        let chunked = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\n{\"id\r\n4;ext=1\r\n\":1}\r\n0\r\n\r\n";
+        assert_eq!(response_body(chunked), "{\"id\":1}");
+        assert_eq!(response_body("HTTP/1.1 204 No Content"), "");
+    }
+}
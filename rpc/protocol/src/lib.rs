@@ -3,11 +3,21 @@
 //! This module defines the RPC protocol specifications, message formats,
 //! and communication patterns for CC Chain RPC interactions.
 
+use rpc_documentation::{ErrorDoc, MethodDocumentation, MethodRegistry, ParameterDoc, ResultDoc};
+use rpc_errors::RpcErrorCode;
+use rpc_serialization::{RpcSerializer, SerializationConfig, SerializationFormat};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 use thiserror::Error;
 
+pub mod pagination;
+pub mod schema;
+
+pub use pagination::{Cursor, Page, PageRequest};
+pub use rpc_documentation::SchemaDoc;
+pub use schema::{SchemaValidator, SchemaViolation};
+
 #[derive(Error, Debug)]
 pub enum ProtocolError {
     #[error("Protocol version mismatch: expected {expected}, got {actual}")]
@@ -158,6 +168,37 @@ impl RpcEnvelope {
         self
     }
 
+    /// Set content type (JSON, CBOR or MessagePack)
+    pub fn with_content_type(mut self, content_type: String) -> Self {
+        self.content_type = content_type;
+        self
+    }
+
+    /// Encode the envelope in its content type
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Self::serializer(&self.content_type)?
+            .serialize(self)
+            .map_err(|e| ProtocolError::InvalidMessageFormat(e.to_string()))
+    }
+
+    /// Decode an envelope received with the given content type
+    pub fn from_bytes(data: &[u8], content_type: &str) -> Result<Self> {
+        Self::serializer(content_type)?
+            .deserialize(data)
+            .map_err(|e| ProtocolError::InvalidMessageFormat(e.to_string()))
+    }
+
+    fn serializer(content_type: &str) -> Result<RpcSerializer> {
+        let format = SerializationFormat::from_mime_type(content_type).ok_or_else(|| {
+            ProtocolError::InvalidMessageFormat(format!("Unsupported content type: {}", content_type))
+        })?;
+        Ok(RpcSerializer::with_config(SerializationConfig {
+            format,
+            pretty_print: false,
+            ..Default::default()
+        }))
+    }
+
     /// Validate the envelope
     pub fn validate(&self) -> Result<()> {
         // Check protocol version compatibility
@@ -169,7 +210,7 @@ impl RpcEnvelope {
         }
 
         // Validate content type
-        if !["application/json", "application/cbor", "application/msgpack"].contains(&self.content_type.as_str()) {
+        if SerializationFormat::from_mime_type(&self.content_type).is_none() {
             return Err(ProtocolError::InvalidMessageFormat(
                 format!("Unsupported content type: {}", self.content_type)
             ));
@@ -209,6 +250,9 @@ pub struct MethodMetadata {
     pub since_version: ProtocolVersion,
     pub rate_limit: Option<RateLimit>,
     pub auth_required: bool,
+    /// Permissions callers need, on top of being authenticated
+    #[serde(default)]
+    pub required_permissions: Vec<String>,
 }
 
 /// Parameter specification
@@ -220,6 +264,45 @@ pub struct ParameterSpec {
     pub description: String,
     pub default_value: Option<Value>,
     pub validation: Option<ValidationRule>,
+    /// Full schema of the value, for nested objects and arrays; without one
+    /// the schema is derived from `parameter_type` and `validation`
+    #[serde(default)]
+    pub schema: Option<SchemaDoc>,
+}
+
+impl ParameterSpec {
+    /// Schema the parameter is validated and documented with
+    pub fn to_schema(&self) -> SchemaDoc {
+        if let Some(schema) = &self.schema {
+            return schema.clone();
+        }
+        let rule = self.validation.clone().unwrap_or_default();
+        SchemaDoc {
+            schema_type: self.parameter_type.clone(),
+            description: Some(self.description.clone()),
+            enum_values: rule.allowed_values,
+            minimum: rule.min_value,
+            maximum: rule.max_value,
+            min_length: rule.min_length,
+            max_length: rule.max_length,
+            pattern: rule.pattern,
+            ..SchemaDoc::default()
+        }
+    }
+}
+
+impl From<&ParameterDoc> for ParameterSpec {
+    fn from(doc: &ParameterDoc) -> Self {
+        Self {
+            name: doc.name.clone(),
+            parameter_type: doc.schema.schema_type.clone(),
+            required: doc.required,
+            description: doc.description.clone(),
+            default_value: None,
+            validation: None,
+            schema: Some(doc.schema.clone()),
+        }
+    }
 }
 
 /// Return value specification
@@ -241,6 +324,95 @@ pub struct ValidationRule {
     pub allowed_values: Option<Vec<Value>>,
 }
 
+impl From<&MethodDocumentation> for MethodMetadata {
+    /// Metadata validating calls against the documented parameter schemas
+    fn from(doc: &MethodDocumentation) -> Self {
+        Self {
+            name: doc.name.clone(),
+            description: doc.description.clone(),
+            parameters: doc.parameters.iter().map(ParameterSpec::from).collect(),
+            returns: doc.result.as_ref().map(|result| ReturnSpec {
+                return_type: result.schema.schema_type.clone(),
+                description: result.description.clone(),
+                example: result.example.clone(),
+            }),
+            deprecated: doc.deprecated,
+            since_version: ProtocolVersion::from_string(&doc.since_version)
+                .unwrap_or(ProtocolVersion::CURRENT),
+            rate_limit: None,
+            auth_required: false,
+            required_permissions: Vec::new(),
+        }
+    }
+}
+
+impl From<&MethodMetadata> for MethodDocumentation {
+    /// Documentation of a registered method, listing its rate limit and
+    /// authentication requirement with the errors they cause
+    fn from(metadata: &MethodMetadata) -> Self {
+        let mut description = metadata.description.clone();
+        let mut errors = Vec::new();
+        let mut tags = vec![metadata.name.split('_').next().unwrap_or_default().to_string()];
+
+        if !metadata.parameters.is_empty() {
+            errors.push(error_doc(RpcErrorCode::InvalidParams, "Parameters are missing or do not match their schema"));
+        }
+        if let Some(limit) = &metadata.rate_limit {
+            description.push_str(&format!(
+                "\n\nRate limit: {} requests per minute, bursts of {} over {}s.",
+                limit.requests_per_minute, limit.burst_size, limit.window_seconds
+            ));
+            errors.push(error_doc(RpcErrorCode::RateLimitExceeded, "Rate limit exceeded"));
+        }
+        if metadata.auth_required {
+            description.push_str("\n\nRequires authentication.");
+            if !metadata.required_permissions.is_empty() {
+                description.push_str(&format!(" Permissions: {}.", metadata.required_permissions.join(", ")));
+            }
+            errors.push(error_doc(RpcErrorCode::Unauthorized, "Request is not authenticated"));
+            tags.push("auth".to_string());
+        }
+
+        Self {
+            name: metadata.name.clone(),
+            summary: metadata.description.clone(),
+            description,
+            parameters: metadata.parameters.iter().map(|param| ParameterDoc {
+                name: param.name.clone(),
+                description: param.description.clone(),
+                schema: param.to_schema(),
+                required: param.required,
+                example: param.default_value.clone(),
+            }).collect(),
+            result: metadata.returns.as_ref().map(|returns| ResultDoc {
+                name: "result".to_string(),
+                description: returns.description.clone(),
+                schema: SchemaDoc {
+                    schema_type: returns.return_type.clone(),
+                    example: returns.example.clone(),
+                    ..SchemaDoc::default()
+                },
+                example: returns.example.clone(),
+            }),
+            errors,
+            examples: vec![],
+            tags,
+            deprecated: metadata.deprecated,
+            since_version: metadata.since_version.to_string(),
+        }
+    }
+}
+
+fn error_doc(code: RpcErrorCode, description: &str) -> ErrorDoc {
+    ErrorDoc {
+        code: code.code(),
+        message: code.message().to_string(),
+        description: description.to_string(),
+        data_schema: None,
+        remediation: Some(code.remediation().to_string()),
+    }
+}
+
 /// Rate limiting specification
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RateLimit {
@@ -261,6 +433,13 @@ pub struct ProtocolCapabilities {
     pub max_request_size: usize,
     pub max_response_size: usize,
     pub timeout_seconds: u32,
+    /// Payloads smaller than this are sent uncompressed
+    #[serde(default = "default_compression_threshold")]
+    pub compression_threshold: usize,
+}
+
+fn default_compression_threshold() -> usize {
+    1024
 }
 
 impl Default for ProtocolCapabilities {
@@ -275,6 +454,7 @@ impl Default for ProtocolCapabilities {
                 "identity".to_string(),
                 "gzip".to_string(),
                 "deflate".to_string(),
+                "zstd".to_string(),
             ],
             supports_batching: true,
             supports_notifications: true,
@@ -282,14 +462,71 @@ impl Default for ProtocolCapabilities {
             max_request_size: 1024 * 1024, // 1MB
             max_response_size: 1024 * 1024, // 1MB
             timeout_seconds: 30,
+            compression_threshold: default_compression_threshold(),
         }
     }
 }
 
+impl ProtocolCapabilities {
+    /// Select the protocol version and content encoding for a client
+    ///
+    /// The highest advertised version this server can serve is chosen, and
+    /// the client's most preferred encoding that the server supports. A
+    /// client advertising no encodings gets `identity`.
+    pub fn negotiate(&self, request: &NegotiationRequest) -> Result<NegotiationResponse> {
+        let version = request.versions.iter()
+            .filter(|version| version.is_compatible_with(&self.version))
+            .max_by_key(|version| (version.major, version.minor, version.patch))
+            .cloned()
+            .ok_or_else(|| ProtocolError::VersionMismatch {
+                expected: self.version.to_string(),
+                actual: request.versions.iter()
+                    .map(|version| version.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            })?;
+
+        let encoding = if request.encodings.is_empty() {
+            "identity".to_string()
+        } else {
+            request.encodings.iter()
+                .find(|encoding| self.supported_encodings.iter().any(|supported| supported.eq_ignore_ascii_case(encoding)))
+                .map(|encoding| encoding.to_ascii_lowercase())
+                .ok_or_else(|| ProtocolError::NegotiationFailed(format!(
+                    "No common encoding among {}",
+                    request.encodings.join(", ")
+                )))?
+        };
+
+        Ok(NegotiationResponse {
+            version,
+            encoding,
+            capabilities: self.clone(),
+        })
+    }
+}
+
+/// Versions and content encodings a client supports, most preferred first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NegotiationRequest {
+    pub versions: Vec<ProtocolVersion>,
+    #[serde(default)]
+    pub encodings: Vec<String>,
+}
+
+/// Version and encoding selected by the server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NegotiationResponse {
+    pub version: ProtocolVersion,
+    pub encoding: String,
+    pub capabilities: ProtocolCapabilities,
+}
+
 /// RPC protocol handler
 pub struct RpcProtocol {
     capabilities: ProtocolCapabilities,
     methods: HashMap<String, MethodMetadata>,
+    validator: SchemaValidator,
 }
 
 impl RpcProtocol {
@@ -298,6 +535,7 @@ impl RpcProtocol {
         let mut protocol = Self {
             capabilities: ProtocolCapabilities::default(),
             methods: HashMap::new(),
+            validator: SchemaValidator::new(),
         };
         
         protocol.register_standard_methods();
@@ -309,6 +547,7 @@ impl RpcProtocol {
         let mut protocol = Self {
             capabilities,
             methods: HashMap::new(),
+            validator: SchemaValidator::new(),
         };
         
         protocol.register_standard_methods();
@@ -333,6 +572,7 @@ impl RpcProtocol {
                         max_value: None,
                         ..Default::default()
                     }),
+                    schema: None,
                 },
             ],
             returns: Some(ReturnSpec {
@@ -348,6 +588,7 @@ impl RpcProtocol {
                 window_seconds: 60,
             }),
             auth_required: false,
+            required_permissions: Vec::new(),
         });
 
         self.register_method(MethodMetadata {
@@ -361,6 +602,7 @@ impl RpcProtocol {
                     description: "Transaction object".to_string(),
                     default_value: None,
                     validation: None,
+                    schema: None,
                 },
             ],
             returns: Some(ReturnSpec {
@@ -376,6 +618,7 @@ impl RpcProtocol {
                 window_seconds: 60,
             }),
             auth_required: false,
+            required_permissions: Vec::new(),
         });
 
         // Add more standard methods...
@@ -401,6 +644,7 @@ impl RpcProtocol {
                 window_seconds: 60,
             }),
             auth_required: false,
+            required_permissions: Vec::new(),
         });
     }
 
@@ -426,6 +670,7 @@ impl RpcProtocol {
                 window_seconds: 60,
             }),
             auth_required: false,
+            required_permissions: Vec::new(),
         });
     }
 
@@ -484,70 +729,34 @@ impl RpcProtocol {
         Ok(())
     }
 
+    /// Validate named parameters by name and positional ones by order
+    /// against each parameter's schema
     fn validate_parameters(&self, param_specs: &[ParameterSpec], params: &Value) -> Result<()> {
-        // Basic parameter validation
-        if let Some(params_obj) = params.as_object() {
-            for spec in param_specs {
-                if spec.required && !params_obj.contains_key(&spec.name) {
+        for (index, spec) in param_specs.iter().enumerate() {
+            let value = match params {
+                Value::Object(params_obj) => params_obj.get(&spec.name),
+                Value::Array(values) => values.get(index),
+                _ => {
                     return Err(ProtocolError::InvalidMessageFormat(
-                        format!("Required parameter '{}' is missing", spec.name)
+                        "Parameters must be an object or an array".to_string()
                     ));
                 }
+            };
 
-                if let Some(param_value) = params_obj.get(&spec.name) {
-                    self.validate_parameter_value(spec, param_value)?;
-                }
-            }
-        }
-
-        Ok(())
-    }
-
-    fn validate_parameter_value(&self, spec: &ParameterSpec, value: &Value) -> Result<()> {
-        if let Some(validation) = &spec.validation {
-            // Validate string length
-            if let Some(s) = value.as_str() {
-                if let Some(min_len) = validation.min_length {
-                    if s.len() < min_len {
-                        return Err(ProtocolError::InvalidMessageFormat(
-                            format!("Parameter '{}' is too short", spec.name)
-                        ));
-                    }
-                }
-                if let Some(max_len) = validation.max_length {
-                    if s.len() > max_len {
-                        return Err(ProtocolError::InvalidMessageFormat(
-                            format!("Parameter '{}' is too long", spec.name)
-                        ));
-                    }
-                }
-            }
-
-            // Validate numeric range
-            if let Some(n) = value.as_f64() {
-                if let Some(min_val) = validation.min_value {
-                    if n < min_val {
-                        return Err(ProtocolError::InvalidMessageFormat(
-                            format!("Parameter '{}' is too small", spec.name)
-                        ));
-                    }
-                }
-                if let Some(max_val) = validation.max_value {
-                    if n > max_val {
-                        return Err(ProtocolError::InvalidMessageFormat(
-                            format!("Parameter '{}' is too large", spec.name)
-                        ));
-                    }
-                }
-            }
-
-            // Validate allowed values
-            if let Some(allowed) = &validation.allowed_values {
-                if !allowed.contains(value) {
+            match value {
+                // Optional parameters may be passed as null
+                None | Some(Value::Null) if !spec.required => {}
+                None => {
                     return Err(ProtocolError::InvalidMessageFormat(
-                        format!("Parameter '{}' has invalid value", spec.name)
+                        format!("Required parameter '{}' is missing", spec.name)
                     ));
                 }
+                Some(value) => {
+                    self.validator.validate(&spec.to_schema(), value, &spec.name)
+                        .map_err(|violation| ProtocolError::InvalidMessageFormat(
+                            format!("Parameter {}", violation)
+                        ))?;
+                }
             }
         }
 
@@ -571,9 +780,7 @@ impl RpcProtocol {
                         serde_json::json!({
                             "name": p.name,
                             "required": p.required,
-                            "schema": {
-                                "type": p.parameter_type
-                            },
+                            "schema": p.to_schema().to_json_schema(),
                             "description": p.description
                         })
                     }).collect::<Vec<_>>(),
@@ -592,6 +799,12 @@ impl RpcProtocol {
     }
 }
 
+impl MethodRegistry for RpcProtocol {
+    fn documented_methods(&self) -> Vec<MethodDocumentation> {
+        self.methods.values().map(MethodDocumentation::from).collect()
+    }
+}
+
 impl Default for RpcProtocol {
     fn default() -> Self {
         Self::new()
@@ -701,6 +914,32 @@ mod tests {
         assert!(protocol.validate_method_call("cc_getBlockByHeight", Some(&invalid_params)).is_err());
     }
 
+    #[test]
+    fn test_documented_schemas_are_enforced() {
+        let docs = rpc_documentation::DocumentationGenerator::new();
+        let mut protocol = RpcProtocol::new();
+        protocol.register_method(MethodMetadata::from(docs.get_method("cc_getBlockByHeight").unwrap()));
+        
+        let check = |params: Value| protocol.validate_method_call("cc_getBlockByHeight", Some(&params));
+        assert!(check(serde_json::json!({"height": 7})).is_ok());
+        assert!(check(serde_json::json!([7])).is_ok());
+        assert!(check(serde_json::json!([])).is_err());
+        assert!(check(serde_json::json!("7")).is_err());
+        
+        let error = check(serde_json::json!(["7"])).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid message format: Parameter 'height' must be of type integer");
+        let error = check(serde_json::json!({"height": -1})).unwrap_err();
+        assert!(error.to_string().contains("'height' must be at least 0"));
+        
+        // The published spec carries the same schema
+        let spec = protocol.generate_openrpc_spec();
+        let method = spec["methods"].as_array().unwrap().iter()
+            .find(|method| method["name"] == "cc_getBlockByHeight")
+            .unwrap();
+        assert_eq!(method["params"][0]["schema"]["minimum"], 0.0);
+        assert_eq!(method["params"][0]["schema"]["format"], "uint64");
+    }
+
     #[test]
     fn test_capabilities() {
         let capabilities = ProtocolCapabilities::default();
@@ -750,6 +989,71 @@ mod tests {
         assert!(envelope.validate().is_err());
     }
 
+    #[test]
+    fn test_envelope_encodings() {
+        let envelope = RpcEnvelope::new(serde_json::json!({"method": "cc_getBlockByHeight", "params": {"height": 42}}))
+            .with_metadata("client".to_string(), serde_json::json!("cc-cli"));
+
+        for content_type in ["application/json", "application/cbor", "application/msgpack"] {
+            let envelope = envelope.clone().with_content_type(content_type.to_string());
+            assert!(envelope.validate().is_ok());
+
+            let bytes = envelope.to_bytes().unwrap();
+            let decoded = RpcEnvelope::from_bytes(&bytes, content_type).unwrap();
+            assert_eq!(decoded.payload, envelope.payload);
+            assert_eq!(decoded.metadata["client"], "cc-cli");
+            assert_eq!(decoded.content_type, content_type);
+        }
+
+        let cbor = envelope.clone().with_content_type("application/cbor".to_string()).to_bytes().unwrap();
+        assert!(RpcEnvelope::from_bytes(&cbor, "application/msgpack").is_err());
+        assert!(RpcEnvelope::from_bytes(&cbor, "text/plain").is_err());
+    }
+
+    #[test]
+    fn test_version_negotiation() {
+        let capabilities = ProtocolCapabilities {
+            version: ProtocolVersion::new(1, 2, 0),
+            ..ProtocolCapabilities::default()
+        };
+
+        let request = NegotiationRequest {
+            versions: vec![
+                ProtocolVersion::new(1, 0, 0),
+                ProtocolVersion::new(2, 0, 0),
+                ProtocolVersion::new(1, 2, 3),
+                ProtocolVersion::new(1, 3, 0),
+            ],
+            encodings: vec!["br".to_string(), "GZIP".to_string()],
+        };
+        let response = capabilities.negotiate(&request).unwrap();
+        assert_eq!(response.version, ProtocolVersion::new(1, 2, 3));
+        assert_eq!(response.encoding, "gzip");
+
+        let defaults: NegotiationRequest = serde_json::from_value(serde_json::json!({
+            "versions": [{"major": 1, "minor": 0, "patch": 0}]
+        })).unwrap();
+        assert_eq!(capabilities.negotiate(&defaults).unwrap().encoding, "identity");
+
+        let too_new = NegotiationRequest {
+            versions: vec![ProtocolVersion::new(2, 0, 0), ProtocolVersion::new(1, 3, 0)],
+            encodings: Vec::new(),
+        };
+        match capabilities.negotiate(&too_new) {
+            Err(ProtocolError::VersionMismatch { expected, actual }) => {
+                assert_eq!(expected, "1.2.0");
+                assert_eq!(actual, "2.0.0, 1.3.0");
+            }
+            other => panic!("expected a version mismatch, got {:?}", other),
+        }
+
+        let no_encoding = NegotiationRequest {
+            versions: vec![ProtocolVersion::new(1, 0, 0)],
+            encodings: vec!["br".to_string()],
+        };
+        assert!(matches!(capabilities.negotiate(&no_encoding), Err(ProtocolError::NegotiationFailed(_))));
+    }
+
     #[test]
     fn test_validation_rules() {
         let rule = ValidationRule {
@@ -776,6 +1080,32 @@ mod tests {
         assert!(matches!(auth.auth_type, AuthenticationType::Bearer));
     }
 
+    #[test]
+    fn test_documentation_from_protocol() {
+        let mut protocol = RpcProtocol::new();
+        let mut method = protocol.get_method("cc_getBlockByHeight").unwrap().clone();
+        method.name = "admin_rotateKeys".to_string();
+        method.auth_required = true;
+        protocol.register_method(method);
+
+        let generator = rpc_documentation::DocumentationGenerator::from_protocol(&protocol);
+        let mut documented = generator.get_method_names();
+        let mut served = protocol.get_supported_methods();
+        documented.sort();
+        served.sort();
+        assert_eq!(documented, served);
+
+        let doc = generator.get_method("admin_rotateKeys").unwrap();
+        assert!(doc.description.contains("Rate limit: 60 requests per minute"));
+        assert!(doc.description.contains("Requires authentication"));
+        assert_eq!(doc.tags, vec!["admin".to_string(), "auth".to_string()]);
+        let codes: Vec<i32> = doc.errors.iter().map(|error| error.code).collect();
+        assert!(codes.contains(&RpcErrorCode::Unauthorized.code()));
+        assert!(codes.contains(&RpcErrorCode::RateLimitExceeded.code()));
+        assert_eq!(doc.parameters[0].schema.minimum, Some(0.0));
+        assert_eq!(doc.since_version, "1.0.0");
+    }
+
     #[test]
     fn test_rate_limit() {
         let rate_limit = RateLimit {
@@ -0,0 +1,277 @@
+//! Cursor-based pagination
+//!
+//! Methods returning large result sets take an optional `cursor` and
+//! `limit` next to their other named parameters and answer with a `Page`.
+//! A cursor records the sort key of the last item returned rather than an
+//! offset, so items inserted or removed between calls never shift the
+//! following pages: walking `next_cursor` until it is `null` visits every
+//! item that existed throughout exactly once, in key order.
+//!
+//! Cursors are opaque to clients. Handlers build them from whatever key
+//! their results are ordered by, encoded so that byte order matches that
+//! order (e.g. big-endian integers).
+
+use crate::{ParameterSpec, ProtocolError, Result, ValidationRule};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Items per page when the client does not ask for a limit
+pub const DEFAULT_PAGE_LIMIT: usize = 100;
+
+/// Largest `limit` a client may ask for
+pub const MAX_PAGE_LIMIT: usize = 1000;
+
+/// Version tag leading every encoded cursor, so the format can change
+/// without old cursors being misread
+const CURSOR_VERSION: u8 = 1;
+
+/// Position in an ordered result set, just after the item it was taken from
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cursor(Vec<u8>);
+
+impl Cursor {
+    /// Cursor for an item with the given sort key
+    pub fn new(key: impl Into<Vec<u8>>) -> Self {
+        Self(key.into())
+    }
+
+    /// The sort key the cursor points after
+    pub fn key(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// The key as a `u64`, for cursors made with `From<u64>`
+    pub fn as_u64(&self) -> Option<u64> {
+        self.0.as_slice().try_into().ok().map(u64::from_be_bytes)
+    }
+
+    /// Opaque string handed to clients
+    pub fn encode(&self) -> String {
+        let mut bytes = Vec::with_capacity(self.0.len() + 1);
+        bytes.push(CURSOR_VERSION);
+        bytes.extend_from_slice(&self.0);
+        hex::encode(bytes)
+    }
+
+    /// Parse a cursor a client sent back
+    pub fn decode(cursor: &str) -> Result<Self> {
+        match hex::decode(cursor).as_deref() {
+            Ok([CURSOR_VERSION, key @ ..]) => Ok(Self(key.to_vec())),
+            _ => Err(ProtocolError::InvalidMessageFormat(format!(
+                "Invalid cursor '{cursor}'"
+            ))),
+        }
+    }
+}
+
+impl From<u64> for Cursor {
+    fn from(key: u64) -> Self {
+        Self(key.to_be_bytes().to_vec())
+    }
+}
+
+/// The `cursor` and `limit` a client asked for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageRequest {
+    /// Resume after this position; `None` starts from the beginning
+    pub cursor: Option<Cursor>,
+    pub limit: usize,
+}
+
+impl PageRequest {
+    /// First page of `limit` items
+    pub fn first(limit: usize) -> Self {
+        Self {
+            cursor: None,
+            limit,
+        }
+    }
+
+    /// Read `cursor` and `limit` from named params, allowing at most
+    /// `max_limit` items per page
+    ///
+    /// Missing or null values fall back to the first page and
+    /// `DEFAULT_PAGE_LIMIT` (capped at `max_limit`).
+    pub fn from_params(params: Option<&Value>, max_limit: usize) -> Result<Self> {
+        let field = |name: &str| {
+            params
+                .and_then(|params| params.get(name))
+                .filter(|value| !value.is_null())
+        };
+
+        let cursor = match field("cursor") {
+            None => None,
+            Some(Value::String(cursor)) => Some(Cursor::decode(cursor)?),
+            Some(_) => {
+                return Err(ProtocolError::InvalidMessageFormat(
+                    "Parameter 'cursor' must be a string".to_string(),
+                ))
+            }
+        };
+        let limit = match field("limit") {
+            None => DEFAULT_PAGE_LIMIT.min(max_limit),
+            Some(limit) => limit
+                .as_u64()
+                .and_then(|limit| usize::try_from(limit).ok())
+                .filter(|limit| (1..=max_limit).contains(limit))
+                .ok_or_else(|| {
+                    ProtocolError::InvalidMessageFormat(format!(
+                        "Parameter 'limit' must be between 1 and {max_limit}"
+                    ))
+                })?,
+        };
+        Ok(Self { cursor, limit })
+    }
+
+    /// Whether an item at `position` comes after the cursor
+    pub fn includes(&self, position: &Cursor) -> bool {
+        self.cursor.as_ref().is_none_or(|cursor| position > cursor)
+    }
+
+    /// Cut a page from items sorted ascending by their cursor
+    ///
+    /// Items at or before the request's cursor are skipped, so handlers may
+    /// pass the whole set or start it at the cursor themselves, e.g. with
+    /// `BTreeMap::range`. At most one item past the page is read.
+    pub fn paginate<T>(&self, items: impl IntoIterator<Item = (Cursor, T)>) -> Page<T> {
+        let mut items = items
+            .into_iter()
+            .skip_while(|(position, _)| !self.includes(position))
+            .peekable();
+        let mut page = Vec::with_capacity(self.limit.min(DEFAULT_PAGE_LIMIT));
+        let mut last = None;
+        while page.len() < self.limit {
+            let Some((position, item)) = items.next() else {
+                break;
+            };
+            page.push(item);
+            last = Some(position);
+        }
+        Page {
+            items: page,
+            next_cursor: last
+                .filter(|_| items.peek().is_some())
+                .map(|position| position.encode()),
+        }
+    }
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        Self::first(DEFAULT_PAGE_LIMIT)
+    }
+}
+
+/// One page of results
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Pass back as `cursor` for the next page; `None` on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// `cursor` and `limit` parameter specs for a paginated method's metadata
+pub fn pagination_parameters(max_limit: usize) -> Vec<ParameterSpec> {
+    vec![
+        ParameterSpec {
+            name: "cursor".to_string(),
+            parameter_type: "string".to_string(),
+            required: false,
+            description: "Cursor from the previous page's next_cursor".to_string(),
+            default_value: None,
+            validation: Some(ValidationRule {
+                pattern: Some("^([0-9a-fA-F]{2})+$".to_string()),
+                ..ValidationRule::default()
+            }),
+            schema: None,
+        },
+        ParameterSpec {
+            name: "limit".to_string(),
+            parameter_type: "integer".to_string(),
+            required: false,
+            description: "Maximum items to return".to_string(),
+            default_value: Some(Value::from(DEFAULT_PAGE_LIMIT.min(max_limit))),
+            validation: Some(ValidationRule {
+                min_value: Some(1.0),
+                max_value: Some(max_limit as f64),
+                ..ValidationRule::default()
+            }),
+            schema: None,
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = Cursor::from(42);
+        let decoded = Cursor::decode(&cursor.encode()).unwrap();
+        assert_eq!(decoded, cursor);
+        assert_eq!(decoded.as_u64(), Some(42));
+        assert!(Cursor::from(255) < Cursor::from(256));
+
+        for invalid in ["", "zz", "02ff", "1"] {
+            assert!(Cursor::decode(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_params() {
+        let request = PageRequest::from_params(None, 50).unwrap();
+        assert_eq!(request, PageRequest::first(50));
+
+        let cursor = Cursor::from(7).encode();
+        let params = json!({"cursor": cursor, "limit": 10});
+        let request = PageRequest::from_params(Some(&params), 50).unwrap();
+        assert_eq!(request.cursor, Some(Cursor::from(7)));
+        assert_eq!(request.limit, 10);
+
+        for invalid in [
+            json!({"limit": 0}),
+            json!({"limit": 51}),
+            json!({"limit": "10"}),
+            json!({"cursor": 7}),
+            json!({"cursor": "nothex"}),
+        ] {
+            assert!(
+                PageRequest::from_params(Some(&invalid), 50).is_err(),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_pages_are_stable_under_inserts() {
+        let mut state: BTreeMap<u64, &'static str> = (0..5).map(|key| (key * 10, "old")).collect();
+        let walk = |state: &BTreeMap<u64, &'static str>, request: &PageRequest| {
+            request.paginate(
+                state
+                    .iter()
+                    .map(|(key, value)| (Cursor::from(*key), (*key, *value))),
+            )
+        };
+
+        let first = walk(&state, &PageRequest::first(2));
+        assert_eq!(first.items, vec![(0, "old"), (10, "old")]);
+
+        // Items added before the cursor do not shift the next page
+        state.insert(5, "new");
+        state.insert(25, "new");
+        let mut request = PageRequest {
+            cursor: Some(Cursor::decode(first.next_cursor.as_deref().unwrap()).unwrap()),
+            limit: 2,
+        };
+        let second = walk(&state, &request);
+        assert_eq!(second.items, vec![(20, "old"), (25, "new")]);
+
+        request.cursor = Some(Cursor::decode(second.next_cursor.as_deref().unwrap()).unwrap());
+        let last = walk(&state, &request);
+        assert_eq!(last.items, vec![(30, "old"), (40, "old")]);
+        assert_eq!(last.next_cursor, None);
+    }
+}
@@ -0,0 +1,7 @@
+//! JSON Schema validation of call parameters
+//!
+//! Parameters are checked against the same `SchemaDoc` the documentation
+//! is generated from, with the validator the documentation also checks its
+//! examples with.
+
+pub use rpc_documentation::validation::{SchemaValidator, SchemaViolation};
@@ -0,0 +1,181 @@
+//! Content encodings for compressed payloads
+//!
+//! Names follow HTTP `Content-Encoding`: `deflate` is the zlib format, as
+//! RFC 9110 specifies, rather than a raw deflate stream.
+
+use crate::{Result, SerializationError};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+use std::io::{Read, Write};
+
+/// zstd level used for responses, favouring speed over ratio
+const ZSTD_LEVEL: i32 = 3;
+
+/// Supported content encodings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentEncoding {
+    Identity,
+    Gzip,
+    Deflate,
+    Zstd,
+}
+
+impl ContentEncoding {
+    /// The encoding's `Content-Encoding` token
+    pub fn name(&self) -> &'static str {
+        match self {
+            ContentEncoding::Identity => "identity",
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Deflate => "deflate",
+            ContentEncoding::Zstd => "zstd",
+        }
+    }
+
+    /// Parse a `Content-Encoding` token, case-insensitively
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "identity" => Some(ContentEncoding::Identity),
+            "gzip" | "x-gzip" => Some(ContentEncoding::Gzip),
+            "deflate" => Some(ContentEncoding::Deflate),
+            "zstd" => Some(ContentEncoding::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Pick the encoding to respond with from an `Accept-Encoding` header
+    ///
+    /// Codings are tried in order of their `q` value and `*` selects the
+    /// first of `supported` not otherwise listed. `identity` is acceptable
+    /// unless refused with `q=0`, in which case `None` is returned if
+    /// nothing else matches.
+    pub fn negotiate(accept_encoding: &str, supported: &[ContentEncoding]) -> Option<Self> {
+        let mut codings: Vec<(&str, f32)> = accept_encoding
+            .split(',')
+            .map(|coding| {
+                let mut parts = coding.split(';');
+                let name = parts.next().unwrap_or_default().trim();
+                let quality = parts
+                    .filter_map(|param| param.trim().strip_prefix("q="))
+                    .find_map(|q| q.trim().parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                (name, quality)
+            })
+            .filter(|(name, _)| !name.is_empty())
+            .collect();
+        codings.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let quality = |name: &str| {
+            codings
+                .iter()
+                .find(|(coding, _)| coding.eq_ignore_ascii_case(name))
+                .map(|&(_, quality)| quality)
+        };
+        let listed = |encoding: &ContentEncoding| quality(encoding.name()).is_some();
+
+        let chosen = codings
+            .iter()
+            .filter(|&&(_, quality)| quality > 0.0)
+            .find_map(|&(name, _)| match name {
+                "*" => supported.iter().find(|encoding| !listed(encoding)).copied(),
+                name => Self::from_name(name).filter(|encoding| supported.contains(encoding)),
+            });
+        chosen.or_else(|| {
+            let identity = quality("identity").or_else(|| quality("*")).unwrap_or(1.0);
+            (identity > 0.0).then_some(ContentEncoding::Identity)
+        })
+    }
+
+    /// Compress data
+    pub fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let compressed = match self {
+            ContentEncoding::Identity => return Ok(data.to_vec()),
+            ContentEncoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data).and_then(|_| encoder.finish())
+            }
+            ContentEncoding::Deflate => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data).and_then(|_| encoder.finish())
+            }
+            ContentEncoding::Zstd => zstd::stream::encode_all(data, ZSTD_LEVEL),
+        };
+        compressed.map_err(|e| {
+            SerializationError::EncodingError(format!("{} compression failed: {}", self.name(), e))
+        })
+    }
+
+    /// Decompress data, refusing to inflate it past `max_size` bytes
+    pub fn decompress(&self, data: &[u8], max_size: usize) -> Result<Vec<u8>> {
+        let decoder: Box<dyn Read + '_> = match self {
+            ContentEncoding::Identity => Box::new(data),
+            ContentEncoding::Gzip => Box::new(GzDecoder::new(data)),
+            ContentEncoding::Deflate => Box::new(ZlibDecoder::new(data)),
+            ContentEncoding::Zstd => {
+                Box::new(zstd::stream::read::Decoder::new(data).map_err(|e| {
+                    SerializationError::DecodingError(format!("Invalid zstd data: {}", e))
+                })?)
+            }
+        };
+
+        let mut out = Vec::new();
+        decoder
+            .take((max_size as u64).saturating_add(1))
+            .read_to_end(&mut out)
+            .map_err(|e| {
+                SerializationError::DecodingError(format!("Invalid {} data: {}", self.name(), e))
+            })?;
+        if out.len() > max_size {
+            return Err(SerializationError::SizeLimitExceeded(max_size));
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [ContentEncoding; 4] = [
+        ContentEncoding::Identity,
+        ContentEncoding::Gzip,
+        ContentEncoding::Deflate,
+        ContentEncoding::Zstd,
+    ];
+
+    #[test]
+    fn test_round_trips_and_limits() {
+        let data = br#"{"jsonrpc":"2.0","result":"0x00"}"#.repeat(100);
+        for encoding in ALL {
+            let compressed = encoding.compress(&data).unwrap();
+            if encoding != ContentEncoding::Identity {
+                assert!(compressed.len() < data.len() / 4, "{}", encoding.name());
+            }
+            assert_eq!(encoding.decompress(&compressed, data.len()).unwrap(), data);
+            assert!(matches!(
+                encoding.decompress(&compressed, data.len() - 1),
+                Err(SerializationError::SizeLimitExceeded(_))
+            ));
+        }
+        assert!(ContentEncoding::Gzip.decompress(b"not gzip", 1024).is_err());
+        assert!(ContentEncoding::Zstd.decompress(b"not zstd", 1024).is_err());
+    }
+
+    #[test]
+    fn test_negotiation() {
+        let supported = [ContentEncoding::Gzip, ContentEncoding::Zstd];
+        let negotiate = |header: &str| ContentEncoding::negotiate(header, &supported);
+
+        assert_eq!(negotiate(""), Some(ContentEncoding::Identity));
+        assert_eq!(negotiate("gzip, deflate, br"), Some(ContentEncoding::Gzip));
+        assert_eq!(negotiate("GZIP;q=0.5, zstd"), Some(ContentEncoding::Zstd));
+        assert_eq!(negotiate("deflate, br"), Some(ContentEncoding::Identity));
+        assert_eq!(negotiate("gzip;q=0, *"), Some(ContentEncoding::Zstd));
+        assert_eq!(negotiate("identity;q=0, br"), None);
+        assert_eq!(negotiate("*;q=0"), None);
+        assert_eq!(
+            ContentEncoding::from_name(" x-gzip "),
+            Some(ContentEncoding::Gzip)
+        );
+    }
+}
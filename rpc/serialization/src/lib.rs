@@ -7,6 +7,9 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use thiserror::Error;
 
+pub mod compression;
+pub mod msgpack;
+
 #[derive(Error, Debug)]
 pub enum SerializationError {
     #[error("JSON serialization error: {0}")]
@@ -23,6 +26,9 @@ pub enum SerializationError {
     
     #[error("Schema validation error: {0}")]
     SchemaError(String),
+
+    #[error("Payload exceeds {0} bytes")]
+    SizeLimitExceeded(usize),
 }
 
 pub type Result<T> = std::result::Result<T, SerializationError>;
@@ -67,6 +73,50 @@ impl SerializationFormat {
             _ => None,
         }
     }
+
+    /// Parse format from a MIME type, ignoring parameters such as `charset`
+    pub fn from_mime_type(mime_type: &str) -> Option<Self> {
+        let essence = mime_type.split(';').next().unwrap_or_default().trim();
+        match essence.to_ascii_lowercase().as_str() {
+            "application/json" => Some(SerializationFormat::Json),
+            "application/cbor" => Some(SerializationFormat::Cbor),
+            "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+                Some(SerializationFormat::MessagePack)
+            }
+            _ => None,
+        }
+    }
+
+    /// Pick the format to respond with from an `Accept` header
+    ///
+    /// Media ranges are tried in order of their `q` value, and wildcards
+    /// select the first of `supported`. An empty header accepts anything.
+    /// Returns `None` if nothing acceptable is supported.
+    pub fn negotiate(accept: &str, supported: &[SerializationFormat]) -> Option<Self> {
+        if accept.trim().is_empty() {
+            return supported.first().copied();
+        }
+
+        let mut ranges: Vec<(&str, f32)> = accept
+            .split(',')
+            .map(|range| {
+                let mut parts = range.split(';');
+                let mime_type = parts.next().unwrap_or_default().trim();
+                let quality = parts
+                    .filter_map(|param| param.trim().strip_prefix("q="))
+                    .find_map(|q| q.trim().parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                (mime_type, quality)
+            })
+            .filter(|&(_, quality)| quality > 0.0)
+            .collect();
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        ranges.into_iter().find_map(|(mime_type, _)| match mime_type {
+            "*/*" | "application/*" => supported.first().copied(),
+            mime_type => Self::from_mime_type(mime_type).filter(|format| supported.contains(format)),
+        })
+    }
 }
 
 impl Default for SerializationFormat {
@@ -131,15 +181,12 @@ impl RpcSerializer {
                 let json = serde_json::to_vec(value)?;
                 Ok(json)
             }
-            SerializationFormat::MessagePack => {
-                // Mock implementation - in real code would use rmp-serde
-                let json = serde_json::to_vec(value)?;
-                Ok(json) // Placeholder
-            }
+            SerializationFormat::MessagePack => msgpack::to_vec(value),
             SerializationFormat::Cbor => {
-                // Mock implementation - in real code would use ciborium
-                let json = serde_json::to_vec(value)?;
-                Ok(json) // Placeholder
+                let mut cbor = Vec::new();
+                ciborium::into_writer(value, &mut cbor)
+                    .map_err(|e| SerializationError::EncodingError(e.to_string()))?;
+                Ok(cbor)
             }
         }
     }
@@ -157,16 +204,9 @@ impl RpcSerializer {
                 let value = serde_json::from_slice(data)?;
                 Ok(value)
             }
-            SerializationFormat::MessagePack => {
-                // Mock implementation
-                let value = serde_json::from_slice(data)?;
-                Ok(value)
-            }
-            SerializationFormat::Cbor => {
-                // Mock implementation
-                let value = serde_json::from_slice(data)?;
-                Ok(value)
-            }
+            SerializationFormat::MessagePack => msgpack::from_slice(data),
+            SerializationFormat::Cbor => ciborium::from_reader(data)
+                .map_err(|e| SerializationError::DecodingError(e.to_string())),
         }
     }
 
@@ -233,8 +273,7 @@ impl RpcSerializer {
     pub fn get_metadata<T: Serialize>(&self, value: &T) -> Result<SerializationMetadata> {
         let serialized = self.serialize(value)?;
         let compressed_size = if self.config.compress {
-            // Mock compression - in real implementation would use a compression library
-            serialized.len() / 2
+            compression::ContentEncoding::Gzip.compress(&serialized)?.len()
         } else {
             serialized.len()
         };
@@ -619,4 +658,69 @@ mod tests {
         let unsupported = SerializationError::UnsupportedFormat("test".to_string());
         assert!(unsupported.to_string().contains("Unsupported format"));
     }
+
+    #[test]
+    fn test_binary_formats_round_trip() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Request {
+            jsonrpc: String,
+            method: String,
+            params: Vec<Value>,
+            id: u64,
+        }
+
+        let request = Request {
+            jsonrpc: "2.0".to_string(),
+            method: "cc_sendTransaction".to_string(),
+            params: vec![json!({"to": "0xabc", "value": 1_000_000_000_000u64, "fee": 0.25})],
+            id: 7,
+        };
+        let json_bytes = RpcSerializer::new().serialize(&request).unwrap();
+
+        for format in [SerializationFormat::MessagePack, SerializationFormat::Cbor] {
+            let serializer = RpcSerializer::with_config(SerializationConfig {
+                format,
+                ..Default::default()
+            });
+            let encoded = serializer.serialize(&request).unwrap();
+            assert!(encoded.len() < json_bytes.len());
+            assert!(serde_json::from_slice::<Value>(&encoded).is_err());
+            assert_eq!(serializer.deserialize::<Request>(&encoded).unwrap(), request);
+
+            let converted = serializer.convert(&json_bytes, SerializationFormat::Json, format).unwrap();
+            assert_eq!(serializer.deserialize::<Request>(&converted).unwrap(), request);
+        }
+
+        let cbor = RpcSerializer::with_config(SerializationConfig {
+            format: SerializationFormat::Cbor,
+            ..Default::default()
+        });
+        assert!(matches!(
+            cbor.deserialize::<Request>(&[0xff, 0x00]),
+            Err(SerializationError::DecodingError(_))
+        ));
+    }
+
+    #[test]
+    fn test_content_negotiation() {
+        use SerializationFormat::*;
+        assert_eq!(SerializationFormat::from_mime_type("application/json; charset=utf-8"), Some(Json));
+        assert_eq!(SerializationFormat::from_mime_type("Application/CBOR"), Some(Cbor));
+        assert_eq!(SerializationFormat::from_mime_type("application/x-msgpack"), Some(MessagePack));
+        assert_eq!(SerializationFormat::from_mime_type("text/plain"), None);
+
+        let supported = [Json, Cbor, MessagePack];
+        assert_eq!(SerializationFormat::negotiate("", &supported), Some(Json));
+        assert_eq!(SerializationFormat::negotiate("application/cbor", &supported), Some(Cbor));
+        assert_eq!(
+            SerializationFormat::negotiate("application/json;q=0.5, application/msgpack", &supported),
+            Some(MessagePack)
+        );
+        assert_eq!(
+            SerializationFormat::negotiate("text/html, application/*;q=0.1", &supported),
+            Some(Json)
+        );
+        assert_eq!(SerializationFormat::negotiate("application/cbor", &[Json]), None);
+        assert_eq!(SerializationFormat::negotiate("application/cbor;q=0", &supported), None);
+    }
 }
@@ -0,0 +1,328 @@
+//! MessagePack encoding
+//!
+//! Values are encoded through `serde_json::Value`, so anything that
+//! serializes to JSON can be sent as MessagePack and read back. Binary
+//! payloads decode to arrays of bytes; extension types are rejected.
+
+use crate::{Result, SerializationError};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Number, Value};
+
+/// Deepest nesting of arrays and maps accepted when decoding
+const MAX_NESTING: u32 = 128;
+
+/// Encode a value as MessagePack
+pub fn to_vec<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    let value = serde_json::to_value(value)?;
+    let mut out = Vec::new();
+    write_value(&mut out, &value)?;
+    Ok(out)
+}
+
+/// Decode a MessagePack document, rejecting trailing bytes
+pub fn from_slice<T: DeserializeOwned>(data: &[u8]) -> Result<T> {
+    let mut reader = Reader { data, pos: 0 };
+    let value = reader.read_value(0)?;
+    if reader.pos != data.len() {
+        return Err(decoding("trailing bytes after value"));
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+fn write_value(out: &mut Vec<u8>, value: &Value) -> Result<()> {
+    match value {
+        Value::Null => out.push(0xc0),
+        Value::Bool(false) => out.push(0xc2),
+        Value::Bool(true) => out.push(0xc3),
+        Value::Number(n) => write_number(out, n),
+        Value::String(s) => {
+            write_len(out, s.len(), (0xa0, 32), Some(0xd9), 0xda, 0xdb)?;
+            out.extend_from_slice(s.as_bytes());
+        }
+        Value::Array(items) => {
+            write_len(out, items.len(), (0x90, 16), None, 0xdc, 0xdd)?;
+            for item in items {
+                write_value(out, item)?;
+            }
+        }
+        Value::Object(entries) => {
+            write_len(out, entries.len(), (0x80, 16), None, 0xde, 0xdf)?;
+            for (key, item) in entries {
+                write_value(out, &Value::String(key.clone()))?;
+                write_value(out, item)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_number(out: &mut Vec<u8>, n: &Number) {
+    if let Some(n) = n.as_u64() {
+        match n {
+            0..=0x7f => out.push(n as u8),
+            0x80..=0xff => out.extend_from_slice(&[0xcc, n as u8]),
+            0x100..=0xffff => {
+                out.push(0xcd);
+                out.extend_from_slice(&(n as u16).to_be_bytes());
+            }
+            0x1_0000..=0xffff_ffff => {
+                out.push(0xce);
+                out.extend_from_slice(&(n as u32).to_be_bytes());
+            }
+            _ => {
+                out.push(0xcf);
+                out.extend_from_slice(&n.to_be_bytes());
+            }
+        }
+    } else if let Some(n) = n.as_i64() {
+        // Only negative values reach here
+        if n >= -32 {
+            out.push(n as i8 as u8);
+        } else if n >= i64::from(i8::MIN) {
+            out.extend_from_slice(&[0xd0, n as i8 as u8]);
+        } else if n >= i64::from(i16::MIN) {
+            out.push(0xd1);
+            out.extend_from_slice(&(n as i16).to_be_bytes());
+        } else if n >= i64::from(i32::MIN) {
+            out.push(0xd2);
+            out.extend_from_slice(&(n as i32).to_be_bytes());
+        } else {
+            out.push(0xd3);
+            out.extend_from_slice(&n.to_be_bytes());
+        }
+    } else {
+        out.push(0xcb);
+        out.extend_from_slice(&n.as_f64().unwrap_or_default().to_be_bytes());
+    }
+}
+
+/// Write a length prefix using the fix form when it fits, then the 8-bit
+/// form if the type has one, then the 16- and 32-bit forms
+fn write_len(
+    out: &mut Vec<u8>,
+    len: usize,
+    (fix, fix_limit): (u8, usize),
+    marker8: Option<u8>,
+    marker16: u8,
+    marker32: u8,
+) -> Result<()> {
+    if len < fix_limit {
+        out.push(fix | len as u8);
+    } else if let (Some(marker), Ok(len)) = (marker8, u8::try_from(len)) {
+        out.extend_from_slice(&[marker, len]);
+    } else if let Ok(len) = u16::try_from(len) {
+        out.push(marker16);
+        out.extend_from_slice(&len.to_be_bytes());
+    } else if let Ok(len) = u32::try_from(len) {
+        out.push(marker32);
+        out.extend_from_slice(&len.to_be_bytes());
+    } else {
+        return Err(SerializationError::EncodingError(format!(
+            "Length {} too large for MessagePack",
+            len
+        )));
+    }
+    Ok(())
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| decoding("unexpected end of input"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(self.take(N)?);
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<usize> {
+        Ok(u16::from_be_bytes(self.take_array()?) as usize)
+    }
+
+    fn read_u32(&mut self) -> Result<usize> {
+        Ok(u32::from_be_bytes(self.take_array()?) as usize)
+    }
+
+    fn read_value(&mut self, depth: u32) -> Result<Value> {
+        if depth > MAX_NESTING {
+            return Err(decoding("nesting too deep"));
+        }
+        let marker = self.read_u8()?;
+        let value = match marker {
+            0x00..=0x7f => Value::from(marker),
+            0x80..=0x8f => self.read_map((marker & 0x0f) as usize, depth)?,
+            0x90..=0x9f => self.read_array((marker & 0x0f) as usize, depth)?,
+            0xa0..=0xbf => self.read_str((marker & 0x1f) as usize)?,
+            0xc0 => Value::Null,
+            0xc2 => Value::Bool(false),
+            0xc3 => Value::Bool(true),
+            0xc4 => {
+                let len = self.read_u8()? as usize;
+                self.read_bin(len)?
+            }
+            0xc5 => {
+                let len = self.read_u16()?;
+                self.read_bin(len)?
+            }
+            0xc6 => {
+                let len = self.read_u32()?;
+                self.read_bin(len)?
+            }
+            0xca => float(f32::from_be_bytes(self.take_array()?) as f64)?,
+            0xcb => float(f64::from_be_bytes(self.take_array()?))?,
+            0xcc => Value::from(self.read_u8()?),
+            0xcd => Value::from(u16::from_be_bytes(self.take_array()?)),
+            0xce => Value::from(u32::from_be_bytes(self.take_array()?)),
+            0xcf => Value::from(u64::from_be_bytes(self.take_array()?)),
+            0xd0 => Value::from(i8::from_be_bytes(self.take_array()?)),
+            0xd1 => Value::from(i16::from_be_bytes(self.take_array()?)),
+            0xd2 => Value::from(i32::from_be_bytes(self.take_array()?)),
+            0xd3 => Value::from(i64::from_be_bytes(self.take_array()?)),
+            0xd9 => {
+                let len = self.read_u8()? as usize;
+                self.read_str(len)?
+            }
+            0xda => {
+                let len = self.read_u16()?;
+                self.read_str(len)?
+            }
+            0xdb => {
+                let len = self.read_u32()?;
+                self.read_str(len)?
+            }
+            0xdc => {
+                let len = self.read_u16()?;
+                self.read_array(len, depth)?
+            }
+            0xdd => {
+                let len = self.read_u32()?;
+                self.read_array(len, depth)?
+            }
+            0xde => {
+                let len = self.read_u16()?;
+                self.read_map(len, depth)?
+            }
+            0xdf => {
+                let len = self.read_u32()?;
+                self.read_map(len, depth)?
+            }
+            0xe0..=0xff => Value::from(marker as i8),
+            0xc7..=0xc9 | 0xd4..=0xd8 => return Err(decoding("extension types are not supported")),
+            0xc1 => return Err(decoding("reserved marker 0xc1")),
+        };
+        Ok(value)
+    }
+
+    fn read_str(&mut self, len: usize) -> Result<Value> {
+        let bytes = self.take(len)?;
+        let s = std::str::from_utf8(bytes).map_err(|_| decoding("string is not valid UTF-8"))?;
+        Ok(Value::String(s.to_string()))
+    }
+
+    fn read_bin(&mut self, len: usize) -> Result<Value> {
+        Ok(Value::Array(
+            self.take(len)?.iter().map(|&b| Value::from(b)).collect(),
+        ))
+    }
+
+    fn read_array(&mut self, len: usize, depth: u32) -> Result<Value> {
+        // Every element takes at least one byte, so cap the allocation by the input left
+        let mut items = Vec::with_capacity(len.min(self.data.len() - self.pos));
+        for _ in 0..len {
+            items.push(self.read_value(depth + 1)?);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn read_map(&mut self, len: usize, depth: u32) -> Result<Value> {
+        let mut entries = Map::new();
+        for _ in 0..len {
+            let Value::String(key) = self.read_value(depth + 1)? else {
+                return Err(decoding("map keys must be strings"));
+            };
+            let value = self.read_value(depth + 1)?;
+            entries.insert(key, value);
+        }
+        Ok(Value::Object(entries))
+    }
+}
+
+fn float(f: f64) -> Result<Value> {
+    Number::from_f64(f)
+        .map(Value::Number)
+        .ok_or_else(|| decoding("non-finite floats are not supported"))
+}
+
+fn decoding(reason: &str) -> SerializationError {
+    SerializationError::DecodingError(format!("Invalid MessagePack: {}", reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_encodes_compact_forms() {
+        assert_eq!(
+            to_vec(&json!({"a": 1})).unwrap(),
+            vec![0x81, 0xa1, b'a', 0x01]
+        );
+        assert_eq!(to_vec(&json!(-1)).unwrap(), vec![0xff]);
+        assert_eq!(to_vec(&json!(-33)).unwrap(), vec![0xd0, 0xdf]);
+        assert_eq!(to_vec(&json!(300)).unwrap(), vec![0xcd, 0x01, 0x2c]);
+        assert_eq!(
+            to_vec(&json!([true, null])).unwrap(),
+            vec![0x92, 0xc3, 0xc0]
+        );
+        assert_eq!(to_vec(&"x".repeat(40)).unwrap()[..2], [0xd9, 40]);
+    }
+
+    #[test]
+    fn test_round_trips_values() {
+        let value = json!({
+            "jsonrpc": "2.0",
+            "method": "cc_getBalance",
+            "params": [u64::MAX, i64::MIN, -70000, 1.5, "y".repeat(70_000)],
+            "nested": {"list": (0..20).collect::<Vec<_>>(), "empty": {}},
+            "id": null
+        });
+        let encoded = to_vec(&value).unwrap();
+        assert_eq!(from_slice::<Value>(&encoded).unwrap(), value);
+
+        // float32 and bin8 produced by other encoders
+        assert_eq!(
+            from_slice::<Value>(&[0xca, 0x3f, 0xc0, 0, 0]).unwrap(),
+            json!(1.5)
+        );
+        assert_eq!(from_slice::<Vec<u8>>(&[0xc4, 2, 7, 9]).unwrap(), vec![7, 9]);
+    }
+
+    #[test]
+    fn test_rejects_malformed_input() {
+        assert!(from_slice::<Value>(&[0x92, 0x01]).is_err());
+        assert!(from_slice::<Value>(&[0x01, 0x02]).is_err());
+        assert!(from_slice::<Value>(&[0x81, 0x01, 0x02]).is_err());
+        assert!(from_slice::<Value>(&[0xd4, 0x01, 0x02]).is_err());
+        assert!(from_slice::<Value>(&[0xdd, 0xff, 0xff, 0xff, 0xff]).is_err());
+        assert!(from_slice::<Value>(&[0x91; 200]).is_err());
+    }
+}
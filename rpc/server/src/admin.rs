@@ -0,0 +1,481 @@
+//! Privileged `admin_` namespace
+//!
+//! Peer management, mempool inspection, halting and resuming the node,
+//! config reloads and the slow request log. These methods live in their own registry, never in the
+//! public server's, and are served on a separate listener where every
+//! request must carry the admin bearer token.
+
+use crate::http::HttpRpcServer;
+use crate::{Result, RpcError, RpcMethodHandler, RpcServer, RpcServerConfig};
+use axum::extract::{Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::Router;
+use rpc_errors::RpcErrorCode;
+use rpc_monitoring::RpcMonitor;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+/// Prefix shared by every admin method
+pub const ADMIN_NAMESPACE: &str = "admin_";
+
+/// Slow request log of the monitor given to `AdminRpcServer::with_slow_log`
+pub const SLOW_REQUESTS_METHOD: &str = "admin_slowRequests";
+
+/// Slow requests returned when the call sets no limit
+const DEFAULT_SLOW_REQUESTS_LIMIT: usize = 50;
+
+/// Node operations behind the admin methods
+pub trait AdminBackend: Send + Sync {
+    /// Connected peers
+    fn peers(&self) -> Result<Vec<Value>>;
+
+    /// Connect to a peer, returning whether it was newly added
+    fn add_peer(&self, address: &str) -> Result<bool>;
+
+    /// Disconnect a peer, returning whether it was connected
+    fn remove_peer(&self, address: &str) -> Result<bool>;
+
+    /// Summary of the mempool's contents
+    fn mempool(&self) -> Result<Value>;
+
+    /// Stop producing and voting on blocks until resumed
+    fn halt(&self, reason: &str) -> Result<()>;
+
+    /// Undo a `halt`
+    fn resume(&self) -> Result<()>;
+
+    /// Re-read the node configuration, returning what changed
+    fn reload_config(&self) -> Result<Value>;
+}
+
+/// Methods in the admin namespace served by an `AdminBackend`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdminMethod {
+    Peers,
+    AddPeer,
+    RemovePeer,
+    Mempool,
+    Halt,
+    Resume,
+    ReloadConfig,
+}
+
+impl AdminMethod {
+    /// Every admin method
+    pub const ALL: [AdminMethod; 7] = [
+        AdminMethod::Peers,
+        AdminMethod::AddPeer,
+        AdminMethod::RemovePeer,
+        AdminMethod::Mempool,
+        AdminMethod::Halt,
+        AdminMethod::Resume,
+        AdminMethod::ReloadConfig,
+    ];
+
+    /// JSON-RPC method name
+    pub fn name(self) -> &'static str {
+        match self {
+            AdminMethod::Peers => "admin_peers",
+            AdminMethod::AddPeer => "admin_addPeer",
+            AdminMethod::RemovePeer => "admin_removePeer",
+            AdminMethod::Mempool => "admin_mempool",
+            AdminMethod::Halt => "admin_halt",
+            AdminMethod::Resume => "admin_resume",
+            AdminMethod::ReloadConfig => "admin_reloadConfig",
+        }
+    }
+}
+
+struct AdminHandler {
+    backend: Arc<dyn AdminBackend>,
+    method: AdminMethod,
+}
+
+impl RpcMethodHandler for AdminHandler {
+    fn handle(&self, params: Option<Value>) -> Result<Value> {
+        let backend = &self.backend;
+        match self.method {
+            AdminMethod::Peers => backend.peers().map(Value::Array),
+            AdminMethod::AddPeer => backend
+                .add_peer(&string_param(&params, "address")?)
+                .map(Value::Bool),
+            AdminMethod::RemovePeer => backend
+                .remove_peer(&string_param(&params, "address")?)
+                .map(Value::Bool),
+            AdminMethod::Mempool => backend.mempool(),
+            AdminMethod::Halt => {
+                let reason = string_param(&params, "reason").unwrap_or_default();
+                backend
+                    .halt(&reason)
+                    .map(|_| json!({"halted": true, "reason": reason}))
+            }
+            AdminMethod::Resume => backend.resume().map(|_| json!({"halted": false})),
+            AdminMethod::ReloadConfig => backend.reload_config(),
+        }
+    }
+
+    fn description(&self) -> &str {
+        match self.method {
+            AdminMethod::Peers => "List connected peers",
+            AdminMethod::AddPeer => "Connect to a peer",
+            AdminMethod::RemovePeer => "Disconnect a peer",
+            AdminMethod::Mempool => "Inspect the mempool",
+            AdminMethod::Halt => "Halt block production and voting",
+            AdminMethod::Resume => "Resume after a halt",
+            AdminMethod::ReloadConfig => "Reload the node configuration",
+        }
+    }
+
+    fn param_schema(&self) -> Option<&str> {
+        match self.method {
+            AdminMethod::AddPeer | AdminMethod::RemovePeer => Some(r#"{"address": "string"}"#),
+            AdminMethod::Halt => Some(r#"{"reason": "string"}"#),
+            _ => None,
+        }
+    }
+}
+
+/// `admin_slowRequests`, answered from a monitor's slow log
+struct SlowRequestsHandler {
+    monitor: Arc<RpcMonitor>,
+}
+
+impl RpcMethodHandler for SlowRequestsHandler {
+    fn handle(&self, params: Option<Value>) -> Result<Value> {
+        let limit = match param(&params, 0, "limit") {
+            None | Some(Value::Null) => DEFAULT_SLOW_REQUESTS_LIMIT,
+            Some(limit) => limit.as_u64().ok_or_else(|| {
+                RpcError::InvalidParams("'limit' must be a non-negative integer".to_string())
+            })? as usize,
+        };
+        let method = param(&params, 1, "method").and_then(Value::as_str);
+        let slow = self
+            .monitor
+            .get_slow_requests(limit, method)
+            .map_err(|e| RpcError::InternalError(e.to_string()))?;
+        serde_json::to_value(slow).map_err(|e| RpcError::InternalError(e.to_string()))
+    }
+
+    fn description(&self) -> &str {
+        "List the slowest recent requests, newest first"
+    }
+
+    fn param_schema(&self) -> Option<&str> {
+        Some(r#"{"limit": "number", "method": "string"}"#)
+    }
+}
+
+/// The parameter at `index` of positional params, or `name` of named ones
+fn param<'a>(params: &'a Option<Value>, index: usize, name: &str) -> Option<&'a Value> {
+    match params {
+        Some(Value::Array(values)) => values.get(index),
+        Some(Value::Object(object)) => object.get(name),
+        _ => None,
+    }
+}
+
+/// A string given as `[value]` or `{name: value}`
+fn string_param(params: &Option<Value>, name: &str) -> Result<String> {
+    param(params, 0, name)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| RpcError::InvalidParams(format!("Missing '{}' parameter", name)))
+}
+
+/// JSON-RPC server for the admin namespace
+///
+/// Wraps an `HttpRpcServer` over a private registry, so admin calls get the
+/// same limits, timeouts and monitoring as public ones. Serve it on a
+/// listener of its own, normally bound to localhost; requests without
+/// `Authorization: Bearer <token>` are refused before they are parsed.
+#[derive(Clone)]
+pub struct AdminRpcServer {
+    http: HttpRpcServer,
+    token: Arc<str>,
+}
+
+impl AdminRpcServer {
+    /// Serve `backend`'s operations to holders of `token`
+    pub fn new(backend: Arc<dyn AdminBackend>, token: impl Into<String>) -> Result<Self> {
+        let token = token.into();
+        if token.is_empty() {
+            return Err(RpcError::InvalidRequest(
+                "Admin token must not be empty".to_string(),
+            ));
+        }
+        let server = RpcServer::new(RpcServerConfig::default());
+        for method in AdminMethod::ALL {
+            let handler = AdminHandler {
+                backend: backend.clone(),
+                method,
+            };
+            server.insert_method(method.name(), handler)?;
+        }
+        Ok(Self {
+            http: HttpRpcServer::new(server),
+            token: token.into(),
+        })
+    }
+
+    /// Adjust the HTTP server, e.g. `|http| http.with_capabilities(..)` to
+    /// change its limits
+    pub fn map_http(mut self, map: impl FnOnce(HttpRpcServer) -> HttpRpcServer) -> Self {
+        self.http = map(self.http);
+        self
+    }
+
+    /// Serve the slow request log of `monitor`, normally the public
+    /// server's, as `admin_slowRequests`
+    pub fn with_slow_log(self, monitor: Arc<RpcMonitor>) -> Result<Self> {
+        self.http
+            .server()
+            .insert_method(SLOW_REQUESTS_METHOD, SlowRequestsHandler { monitor })?;
+        Ok(self)
+    }
+
+    /// HTTP server answering the admin calls
+    pub fn http(&self) -> &HttpRpcServer {
+        &self.http
+    }
+
+    /// Router serving the admin namespace behind the token check
+    pub fn router(&self) -> Router {
+        self.http.router().layer(middleware::from_fn_with_state(
+            self.token.clone(),
+            authorize,
+        ))
+    }
+
+    /// Serve the admin namespace on `listener`
+    pub async fn serve(self, listener: TcpListener) -> std::io::Result<()> {
+        let service = self
+            .router()
+            .into_make_service_with_connect_info::<SocketAddr>();
+        axum::serve(listener, service).await
+    }
+}
+
+/// Refuse requests without the admin bearer token
+async fn authorize(State(token): State<Arc<str>>, request: Request, next: Next) -> Response {
+    let presented = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match presented {
+        Some(presented) if constant_time_eq(presented.as_bytes(), token.as_bytes()) => {
+            next.run(request).await
+        }
+        _ => {
+            let body = json!({
+                "jsonrpc": "2.0",
+                "error": {
+                    "code": RpcErrorCode::Unauthorized.code(),
+                    "message": "Admin token required",
+                },
+                "id": null,
+            });
+            (
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, "Bearer")],
+                axum::Json(body),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Compare without exiting at the first mismatch, so timing does not leak
+/// how much of the token was right
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use rpc_monitoring::slow_log::SlowLogConfig;
+    use rpc_monitoring::MonitoringConfig;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    #[derive(Default)]
+    struct FakeNode {
+        peers: Mutex<Vec<String>>,
+        halted: Mutex<Option<String>>,
+    }
+
+    impl AdminBackend for FakeNode {
+        fn peers(&self) -> Result<Vec<Value>> {
+            Ok(self
+                .peers
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|p| json!(p))
+                .collect())
+        }
+
+        fn add_peer(&self, address: &str) -> Result<bool> {
+            let mut peers = self.peers.lock().unwrap();
+            let added = !peers.iter().any(|peer| peer == address);
+            if added {
+                peers.push(address.to_string());
+            }
+            Ok(added)
+        }
+
+        fn remove_peer(&self, address: &str) -> Result<bool> {
+            let mut peers = self.peers.lock().unwrap();
+            let before = peers.len();
+            peers.retain(|peer| peer != address);
+            Ok(peers.len() < before)
+        }
+
+        fn mempool(&self) -> Result<Value> {
+            Ok(json!({"pending": 0}))
+        }
+
+        fn halt(&self, reason: &str) -> Result<()> {
+            *self.halted.lock().unwrap() = Some(reason.to_string());
+            Ok(())
+        }
+
+        fn resume(&self) -> Result<()> {
+            *self.halted.lock().unwrap() = None;
+            Ok(())
+        }
+
+        fn reload_config(&self) -> Result<Value> {
+            Ok(json!({"changed": []}))
+        }
+    }
+
+    async fn call(admin: &AdminRpcServer, token: Option<&str>, body: Value) -> (StatusCode, Value) {
+        let mut request = Request::post("/").header(header::CONTENT_TYPE, "application/json");
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let request = request.body(Body::from(body.to_string())).unwrap();
+        let response = admin.router().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_token_is_required() {
+        assert!(AdminRpcServer::new(Arc::new(FakeNode::default()), "").is_err());
+        let node = Arc::new(FakeNode::default());
+        let admin = AdminRpcServer::new(node.clone(), "s3cret").unwrap();
+        let halt =
+            json!({"jsonrpc": "2.0", "method": "admin_halt", "params": ["upgrade"], "id": 1});
+
+        for token in [None, Some("wrong"), Some("s3cret2")] {
+            let (status, body) = call(&admin, token, halt.clone()).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+            assert_eq!(body["error"]["code"], RpcErrorCode::Unauthorized.code());
+        }
+        assert!(node.halted.lock().unwrap().is_none());
+
+        let (status, body) = call(&admin, Some("s3cret"), halt).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["result"]["halted"], true);
+        assert_eq!(node.halted.lock().unwrap().as_deref(), Some("upgrade"));
+    }
+
+    #[tokio::test]
+    async fn test_admin_methods() {
+        let admin = AdminRpcServer::new(Arc::new(FakeNode::default()), "s3cret").unwrap();
+        let mut methods = admin.http().server().get_registered_methods();
+        methods.sort();
+        assert_eq!(methods.len(), AdminMethod::ALL.len());
+        assert!(methods
+            .iter()
+            .all(|method| method.starts_with(ADMIN_NAMESPACE)));
+        let public = RpcServer::new(RpcServerConfig::default());
+        let handler = AdminHandler {
+            backend: Arc::new(FakeNode::default()),
+            method: AdminMethod::Halt,
+        };
+        assert!(public.register_method("admin_halt", handler).is_err());
+
+        let add = json!({"jsonrpc": "2.0", "method": "admin_addPeer", "params": {"address": "10.0.0.2:30333"}, "id": 1});
+        assert_eq!(
+            call(&admin, Some("s3cret"), add.clone()).await.1["result"],
+            true
+        );
+        assert_eq!(call(&admin, Some("s3cret"), add).await.1["result"], false);
+        let peers = json!({"jsonrpc": "2.0", "method": "admin_peers", "id": 2});
+        assert_eq!(
+            call(&admin, Some("s3cret"), peers).await.1["result"],
+            json!(["10.0.0.2:30333"])
+        );
+
+        let remove = json!({"jsonrpc": "2.0", "method": "admin_removePeer", "id": 3});
+        let (_, body) = call(&admin, Some("s3cret"), remove).await;
+        assert_eq!(body["error"]["code"], RpcErrorCode::InvalidParams.code());
+    }
+
+    #[tokio::test]
+    async fn test_slow_requests() {
+        struct Sleepy;
+
+        impl RpcMethodHandler for Sleepy {
+            fn handle(&self, _params: Option<Value>) -> Result<Value> {
+                std::thread::sleep(Duration::from_millis(30));
+                Ok(json!("done"))
+            }
+
+            fn description(&self) -> &str {
+                "Sleep before answering"
+            }
+        }
+
+        let monitor = Arc::new(RpcMonitor::with_config(MonitoringConfig {
+            slow_log: SlowLogConfig {
+                threshold: Duration::from_millis(20),
+                ..SlowLogConfig::default()
+            },
+            ..MonitoringConfig::default()
+        }));
+        let server = RpcServer::new(RpcServerConfig::default());
+        server.register_method("cc_sleep", Sleepy).unwrap();
+        let public = HttpRpcServer::new(server).with_monitor(monitor.clone());
+        let body = json!({"jsonrpc": "2.0", "method": "cc_sleep", "params": {"account": "0x01", "password": "hunter2"}, "id": 1});
+        let request = Request::post("/")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        public.router().oneshot(request).await.unwrap();
+
+        let admin = AdminRpcServer::new(Arc::new(FakeNode::default()), "s3cret")
+            .unwrap()
+            .with_slow_log(monitor)
+            .unwrap();
+        let list = json!({"jsonrpc": "2.0", "method": "admin_slowRequests", "params": {"method": "cc_sleep"}, "id": 1});
+        let (_, body) = call(&admin, Some("s3cret"), list).await;
+        let slow = &body["result"][0];
+        assert_eq!(slow["method"], "cc_sleep");
+        assert_eq!(slow["params"]["account"], "0x01");
+        assert_eq!(slow["params"]["password"], "[redacted]");
+        let phases: Vec<&str> = slow["phases"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|phase| phase["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(phases, ["queued", "handler"]);
+        assert!(slow["phases"][1]["duration_ms"].as_u64().unwrap() >= 30);
+
+        let invalid = json!({"jsonrpc": "2.0", "method": "admin_slowRequests", "params": {"limit": "all"}, "id": 2});
+        let (_, body) = call(&admin, Some("s3cret"), invalid).await;
+        assert_eq!(body["error"]["code"], RpcErrorCode::InvalidParams.code());
+    }
+}
@@ -0,0 +1,240 @@
+//! Response cache for idempotent reads
+//!
+//! Results are keyed by method, params and, for methods whose answer moves
+//! with the chain, the head height when the call was made. Publishing a new
+//! head therefore invalidates those entries without touching the cache: the
+//! next call builds a key at the new height and the stale entries age out of
+//! the LRU. Results of immutable lookups such as `cc_getBlockByHeight` are
+//! kept until evicted, or until `clear` after a reorg.
+
+use cc_core_data_structures::lru::ConcurrentLruCache;
+use cc_core_metrics::{MetricSample, MetricsSource};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// How long a method's results stay valid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// The same params always give the same result
+    Immutable,
+    /// Results hold until the next block
+    UntilNextBlock,
+}
+
+/// Identifies a cached result
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct CacheKey {
+    method: String,
+    params: String,
+    height: Option<u64>,
+}
+
+/// Hit and miss counts of a `ResponseCache`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+}
+
+impl CacheStats {
+    /// Fraction of lookups answered from the cache (0 before any lookup)
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+/// LRU cache of successful results for read methods
+pub struct ResponseCache {
+    entries: ConcurrentLruCache<CacheKey, Value>,
+    policies: HashMap<String, CachePolicy>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ResponseCache {
+    /// Cache up to `capacity` results of the built-in read methods
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: ConcurrentLruCache::new(capacity),
+            policies: HashMap::new(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+        .with_policy("cc_getBlockByHeight", CachePolicy::Immutable)
+        .with_policy("cc_getBlockByHash", CachePolicy::Immutable)
+        .with_policy("cc_getTransaction", CachePolicy::Immutable)
+        .with_policy("cc_getLatestBlock", CachePolicy::UntilNextBlock)
+        .with_policy("cc_getAccount", CachePolicy::UntilNextBlock)
+        .with_policy("cc_getBalance", CachePolicy::UntilNextBlock)
+        .with_policy("cc_getTransactionCount", CachePolicy::UntilNextBlock)
+    }
+
+    /// Cache `method` with the given policy, replacing any existing one
+    pub fn with_policy(mut self, method: &str, policy: CachePolicy) -> Self {
+        self.policies.insert(method.to_string(), policy);
+        self
+    }
+
+    /// Stop caching `method`
+    pub fn without_method(mut self, method: &str) -> Self {
+        self.policies.remove(method);
+        self
+    }
+
+    /// Policy for `method`, if its results are cached
+    pub fn policy(&self, method: &str) -> Option<CachePolicy> {
+        self.policies.get(method).copied()
+    }
+
+    /// Key for a call made with the chain at `head`, or `None` if its
+    /// result must not be cached
+    ///
+    /// Per-block results are only cached while the head height is known,
+    /// since nothing else would invalidate them.
+    pub(crate) fn key(
+        &self,
+        method: &str,
+        params: Option<&Value>,
+        head: Option<u64>,
+    ) -> Option<CacheKey> {
+        let height = match self.policy(method)? {
+            CachePolicy::Immutable => None,
+            CachePolicy::UntilNextBlock => Some(head?),
+        };
+        Some(CacheKey {
+            method: method.to_string(),
+            params: params.map(Value::to_string).unwrap_or_default(),
+            height,
+        })
+    }
+
+    /// Cached result for `key`, counting the hit or miss
+    pub(crate) fn get(&self, key: &CacheKey) -> Option<Value> {
+        let result = self.entries.get(key);
+        let counter = if result.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        result
+    }
+
+    /// Store a result; nulls mean "not found yet" and are not cached
+    pub(crate) fn put(&self, key: CacheKey, result: Value) {
+        if !result.is_null() {
+            self.entries.put(key, result);
+        }
+    }
+
+    /// Drop every cached result, e.g. after a reorg
+    pub fn clear(&self) {
+        self.entries.clear();
+    }
+
+    /// Current hit and miss counts
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries.len(),
+        }
+    }
+
+    /// Fraction of lookups answered from the cache
+    pub fn hit_rate(&self) -> f64 {
+        self.stats().hit_rate()
+    }
+}
+
+impl MetricsSource for ResponseCache {
+    fn source_name(&self) -> &str {
+        "rpc_cache"
+    }
+
+    fn collect(&self) -> Vec<MetricSample> {
+        let stats = self.stats();
+        vec![
+            MetricSample::counter(
+                "cc_rpc_cache_hits_total",
+                "RPC calls answered from the response cache",
+                stats.hits as f64,
+            ),
+            MetricSample::counter(
+                "cc_rpc_cache_misses_total",
+                "Cacheable RPC calls not found in the response cache",
+                stats.misses as f64,
+            ),
+            MetricSample::gauge(
+                "cc_rpc_cache_hit_ratio",
+                "Fraction of cacheable RPC calls answered from the cache",
+                stats.hit_rate(),
+            ),
+            MetricSample::gauge(
+                "cc_rpc_cache_entries",
+                "Results held in the response cache",
+                stats.entries as f64,
+            ),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_keys_follow_policies() {
+        let cache = ResponseCache::new(16).without_method("cc_getAccount");
+        let params = json!([42]);
+
+        assert!(cache
+            .key("cc_sendTransaction", Some(&params), Some(1))
+            .is_none());
+        assert!(cache.key("cc_getAccount", Some(&params), Some(1)).is_none());
+        // Per-block results need a known head
+        assert!(cache.key("cc_getBalance", Some(&params), None).is_none());
+
+        let block = cache.key("cc_getBlockByHeight", Some(&params), Some(1));
+        assert_eq!(
+            block,
+            cache.key("cc_getBlockByHeight", Some(&params), Some(2))
+        );
+        let balance = cache.key("cc_getBalance", Some(&params), Some(1));
+        assert_ne!(balance, cache.key("cc_getBalance", Some(&params), Some(2)));
+    }
+
+    #[test]
+    fn test_hits_and_metrics() {
+        let cache = ResponseCache::new(16);
+        let key = cache
+            .key("cc_getBlockByHeight", Some(&json!([7])), None)
+            .unwrap();
+        assert_eq!(cache.get(&key), None);
+
+        cache.put(key.clone(), Value::Null);
+        assert_eq!(cache.get(&key), None);
+        cache.put(key.clone(), json!({"height": 7}));
+        assert_eq!(cache.get(&key), Some(json!({"height": 7})));
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 2, 1));
+        let samples = cache.collect();
+        let ratio = samples
+            .iter()
+            .find(|sample| sample.name == "cc_rpc_cache_hit_ratio")
+            .unwrap();
+        assert!((ratio.value - 1.0 / 3.0).abs() < 1e-9);
+
+        cache.clear();
+        assert_eq!(cache.stats().entries, 0);
+    }
+}
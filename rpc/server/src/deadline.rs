@@ -0,0 +1,168 @@
+//! Request deadlines and cancellation
+//!
+//! Every dispatched call carries a `CancellationToken`. The token trips
+//! when the call's deadline passes or the transport gives up on it, and
+//! handlers doing long work (range scans, large queries) check it as they
+//! go so they stop with `RpcError::DeadlineExceeded` instead of running on
+//! after nobody is waiting for the result.
+
+use crate::{Result, RpcError};
+use axum::http::HeaderMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Header carrying a client's timeout in milliseconds
+pub const TIMEOUT_HEADER: &str = "x-request-timeout";
+
+/// gRPC metadata carrying a client's timeout, e.g. `500m` for 500 ms
+pub const GRPC_TIMEOUT_HEADER: &str = "grpc-timeout";
+
+/// Signal telling a handler to stop working on a call
+///
+/// Clones share state, so cancelling one cancels them all.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<TokenState>,
+}
+
+#[derive(Debug, Default)]
+struct TokenState {
+    cancelled: AtomicBool,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    /// Token without a deadline, cancelled only explicitly
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Token that is cancelled once `deadline` passes
+    pub fn with_deadline(deadline: Instant) -> Self {
+        Self {
+            inner: Arc::new(TokenState {
+                cancelled: AtomicBool::new(false),
+                deadline: Some(deadline),
+            }),
+        }
+    }
+
+    /// Token that is cancelled `timeout` from now
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self::with_deadline(Instant::now() + timeout)
+    }
+
+    /// When the token expires, if it has a deadline
+    pub fn deadline(&self) -> Option<Instant> {
+        self.inner.deadline
+    }
+
+    /// Time left before the deadline; zero once it has passed
+    pub fn remaining(&self) -> Option<Duration> {
+        self.inner
+            .deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Cancel the call now
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::Release);
+    }
+
+    /// Whether the call was cancelled or its deadline has passed
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Acquire)
+            || self
+                .inner
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// `Err(RpcError::DeadlineExceeded)` once the token is cancelled, for
+    /// handlers to propagate with `?`
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(RpcError::DeadlineExceeded)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Timeout requested by the client, from `X-Request-Timeout` in
+/// milliseconds or else `grpc-timeout`
+///
+/// Malformed values are ignored, leaving the server's own timeout.
+pub fn client_timeout(headers: &HeaderMap) -> Option<Duration> {
+    if let Some(millis) = headers
+        .get(TIMEOUT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+    {
+        return Some(Duration::from_millis(millis));
+    }
+    headers
+        .get(GRPC_TIMEOUT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_grpc_timeout)
+}
+
+/// Parse a gRPC timeout: at most 8 digits followed by a unit of `H`, `M`,
+/// `S`, `m`, `u` or `n`
+fn parse_grpc_timeout(value: &str) -> Option<Duration> {
+    let unit = value.chars().last()?;
+    let amount = &value[..value.len() - unit.len_utf8()];
+    if amount.is_empty() || amount.len() > 8 || !amount.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let amount: u64 = amount.parse().ok()?;
+    Some(match unit {
+        'H' => Duration::from_secs(amount * 3600),
+        'M' => Duration::from_secs(amount * 60),
+        'S' => Duration::from_secs(amount),
+        'm' => Duration::from_millis(amount),
+        'u' => Duration::from_micros(amount),
+        'n' => Duration::from_nanos(amount),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_token_cancellation() {
+        let token = CancellationToken::new();
+        assert!(token.check().is_ok());
+        assert_eq!(token.remaining(), None);
+        token.clone().cancel();
+        assert!(matches!(token.check(), Err(RpcError::DeadlineExceeded)));
+
+        let expired = CancellationToken::with_deadline(Instant::now());
+        assert!(expired.is_cancelled());
+        assert_eq!(expired.remaining(), Some(Duration::ZERO));
+        let pending = CancellationToken::with_timeout(Duration::from_secs(60));
+        assert!(!pending.is_cancelled());
+        assert!(pending.remaining().unwrap() > Duration::from_secs(59));
+    }
+
+    #[test]
+    fn test_client_timeout_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(client_timeout(&headers), None);
+
+        headers.insert(GRPC_TIMEOUT_HEADER, HeaderValue::from_static("250m"));
+        assert_eq!(client_timeout(&headers), Some(Duration::from_millis(250)));
+        headers.insert(GRPC_TIMEOUT_HEADER, HeaderValue::from_static("2S"));
+        assert_eq!(client_timeout(&headers), Some(Duration::from_secs(2)));
+        headers.insert(TIMEOUT_HEADER, HeaderValue::from_static("1500"));
+        assert_eq!(client_timeout(&headers), Some(Duration::from_millis(1500)));
+
+        for invalid in ["", "m", "123456789S", "5x", "-5m"] {
+            assert_eq!(parse_grpc_timeout(invalid), None, "{invalid}");
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! Polled filters for clients without a persistent connection
+//!
+//! A filter buffers the events its `SubscriptionKind` would have pushed
+//! until the client collects them with `cc_getFilterChanges`. Buffers are
+//! bounded and drop their oldest events when full, and filters that go
+//! unpolled for longer than the timeout are removed, so abandoned filters
+//! cannot pile up.
+
+use crate::subscriptions::{SubscriptionError, SubscriptionKind};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Filter limits
+#[derive(Debug, Clone)]
+pub struct FilterConfig {
+    /// Maximum installed filters across all clients
+    pub max_filters: usize,
+
+    /// Events buffered per filter between polls
+    pub max_changes: usize,
+
+    /// How long a filter lives without being polled
+    pub timeout: Duration,
+}
+
+impl Default for FilterConfig {
+    fn default() -> Self {
+        Self {
+            max_filters: 1024,
+            max_changes: 1024,
+            timeout: Duration::from_secs(300),
+        }
+    }
+}
+
+struct Filter {
+    kind: SubscriptionKind,
+    changes: VecDeque<Value>,
+    last_polled: Instant,
+}
+
+/// Installed filters and the events waiting for them
+pub struct FilterRegistry {
+    config: FilterConfig,
+    next_id: AtomicU64,
+    filters: Mutex<HashMap<String, Filter>>,
+}
+
+impl FilterRegistry {
+    /// Create a registry with the given limits
+    pub fn new(config: FilterConfig) -> Self {
+        Self {
+            config,
+            next_id: AtomicU64::new(1),
+            filters: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Limits applied to filters
+    pub fn config(&self) -> &FilterConfig {
+        &self.config
+    }
+
+    /// Install a filter, returning its id
+    pub fn install(&self, kind: SubscriptionKind) -> Result<String, SubscriptionError> {
+        let mut filters = self.filters.lock().unwrap();
+        self.prune(&mut filters, Instant::now());
+        if filters.len() >= self.config.max_filters {
+            return Err(SubscriptionError::FilterLimitReached(
+                self.config.max_filters,
+            ));
+        }
+
+        let id = format!("0x{:x}", self.next_id.fetch_add(1, Ordering::Relaxed));
+        filters.insert(
+            id.clone(),
+            Filter {
+                kind,
+                changes: VecDeque::new(),
+                last_polled: Instant::now(),
+            },
+        );
+        Ok(id)
+    }
+
+    /// Take the events recorded for a filter since it was last polled
+    pub fn changes(&self, id: &str) -> Result<Vec<Value>, SubscriptionError> {
+        let mut filters = self.filters.lock().unwrap();
+        let now = Instant::now();
+        self.prune(&mut filters, now);
+        let filter = filters
+            .get_mut(id)
+            .ok_or_else(|| SubscriptionError::UnknownFilter(id.to_string()))?;
+        filter.last_polled = now;
+        Ok(filter.changes.drain(..).collect())
+    }
+
+    /// Remove a filter, returning whether it existed
+    pub fn uninstall(&self, id: &str) -> bool {
+        self.filters.lock().unwrap().remove(id).is_some()
+    }
+
+    /// Number of installed filters, including expired ones not yet pruned
+    pub fn len(&self) -> usize {
+        self.filters.lock().unwrap().len()
+    }
+
+    /// Whether no filters are installed
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Buffer an event for every live filter that wants it
+    pub(crate) fn record(&self, event: &Value, wanted: impl Fn(&SubscriptionKind) -> bool) {
+        let mut filters = self.filters.lock().unwrap();
+        self.prune(&mut filters, Instant::now());
+        for filter in filters.values_mut().filter(|filter| wanted(&filter.kind)) {
+            if filter.changes.len() >= self.config.max_changes {
+                filter.changes.pop_front();
+            }
+            filter.changes.push_back(event.clone());
+        }
+    }
+
+    fn prune(&self, filters: &mut HashMap<String, Filter>, now: Instant) {
+        filters.retain(|_, filter| now.duration_since(filter.last_polled) < self.config.timeout);
+    }
+}
+
+impl Default for FilterRegistry {
+    fn default() -> Self {
+        Self::new(FilterConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subscriptions::LogFilter;
+    use serde_json::json;
+
+    #[test]
+    fn test_filters_buffer_matching_events() {
+        let registry = FilterRegistry::new(FilterConfig {
+            max_filters: 2,
+            max_changes: 2,
+            ..FilterConfig::default()
+        });
+        let heads = registry.install(SubscriptionKind::NewHeads).unwrap();
+        let logs = registry
+            .install(SubscriptionKind::Logs(LogFilter {
+                addresses: vec!["0xabc".to_string()],
+                topics: Vec::new(),
+            }))
+            .unwrap();
+        assert_eq!(
+            registry.install(SubscriptionKind::PendingTransactions),
+            Err(SubscriptionError::FilterLimitReached(2))
+        );
+
+        for number in 1..=3 {
+            registry.record(&json!({"number": number}), |kind| {
+                *kind == SubscriptionKind::NewHeads
+            });
+        }
+        // Only the newest events are kept
+        let changes = registry.changes(&heads).unwrap();
+        assert_eq!(changes, vec![json!({"number": 2}), json!({"number": 3})]);
+        assert!(registry.changes(&heads).unwrap().is_empty());
+        assert!(registry.changes(&logs).unwrap().is_empty());
+
+        assert!(registry.uninstall(&heads));
+        assert!(!registry.uninstall(&heads));
+        assert_eq!(
+            registry.changes(&heads),
+            Err(SubscriptionError::UnknownFilter(heads))
+        );
+    }
+
+    #[test]
+    fn test_unpolled_filters_expire() {
+        let registry = FilterRegistry::new(FilterConfig {
+            timeout: Duration::from_millis(300),
+            ..FilterConfig::default()
+        });
+        let polled = registry.install(SubscriptionKind::NewHeads).unwrap();
+        let idle = registry.install(SubscriptionKind::NewHeads).unwrap();
+
+        std::thread::sleep(Duration::from_millis(200));
+        registry.changes(&polled).unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(registry.changes(&polled).is_ok());
+        assert!(matches!(
+            registry.changes(&idle),
+            Err(SubscriptionError::UnknownFilter(_))
+        ));
+        assert_eq!(registry.len(), 1);
+    }
+}
@@ -0,0 +1,805 @@
+//! gRPC gateway to the method registry
+//!
+//! Implements the `cc.rpc.v1.RpcGateway` service described in
+//! `proto/cc_rpc.proto`. The messages below mirror that file so clients can
+//! generate typed stubs from it while the server needs no protobuf
+//! compiler. Every call is forwarded through the `HttpRpcServer` pipeline,
+//! so rate limits, timeouts, size limits and monitoring apply exactly as
+//! they do for JSON-RPC.
+
+use crate::deadline;
+use crate::http::HttpRpcServer;
+use crate::rate_limit::ClientId;
+use crate::subscriptions::{
+    Connection, LogFilter, SubscriptionError, SubscriptionHub, SubscriptionKind,
+};
+use crate::JsonRpcResponse;
+use axum::extract::ConnectInfo;
+use axum::Router;
+use rpc_errors::RpcErrorCode;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_stream::Stream;
+use tonic::body::BoxBody;
+use tonic::codec::ProstCodec;
+use tonic::codegen::{http, Body, Service, StdError};
+use tonic::server::{Grpc, NamedService};
+use tonic::{Code, Request, Response, Status};
+
+/// Fully qualified name of the gateway service
+pub const SERVICE_NAME: &str = "cc.rpc.v1.RpcGateway";
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CallRequest {
+    #[prost(string, tag = "1")]
+    pub method: String,
+    /// JSON-encoded params; empty for none
+    #[prost(string, tag = "2")]
+    pub params_json: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CallResponse {
+    /// JSON-encoded result
+    #[prost(string, tag = "1")]
+    pub result_json: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ListMethodsRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ListMethodsResponse {
+    #[prost(string, repeated, tag = "1")]
+    pub methods: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GetBlockRequest {
+    #[prost(uint64, tag = "1")]
+    pub height: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message, Deserialize)]
+#[serde(default)]
+pub struct Block {
+    #[prost(uint64, tag = "1")]
+    #[serde(alias = "number")]
+    pub height: u64,
+    #[prost(string, tag = "2")]
+    pub hash: String,
+    #[prost(string, tag = "3")]
+    pub parent_hash: String,
+    #[prost(uint64, tag = "4")]
+    pub timestamp: u64,
+    #[prost(string, repeated, tag = "5")]
+    pub transactions: Vec<String>,
+    #[prost(uint64, tag = "6")]
+    pub size: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GetTransactionRequest {
+    #[prost(string, tag = "1")]
+    pub hash: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message, Deserialize)]
+#[serde(default)]
+pub struct Transaction {
+    #[prost(string, tag = "1")]
+    pub hash: String,
+    #[prost(string, tag = "2")]
+    pub from: String,
+    #[prost(string, tag = "3")]
+    pub to: String,
+    #[prost(uint64, tag = "4")]
+    pub value: u64,
+    #[prost(uint64, tag = "5")]
+    pub nonce: u64,
+    #[prost(uint64, optional, tag = "6")]
+    pub block_height: Option<u64>,
+    #[prost(string, tag = "7")]
+    pub status: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GetAccountRequest {
+    #[prost(string, tag = "1")]
+    pub address: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message, Deserialize)]
+#[serde(default)]
+pub struct Account {
+    #[prost(string, tag = "1")]
+    pub address: String,
+    #[prost(uint64, tag = "2")]
+    pub balance: u64,
+    #[prost(uint64, tag = "3")]
+    pub nonce: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SendTransactionRequest {
+    #[prost(string, tag = "1")]
+    pub from: String,
+    #[prost(string, tag = "2")]
+    pub to: String,
+    #[prost(uint64, tag = "3")]
+    pub value: u64,
+    #[prost(uint64, tag = "4")]
+    pub nonce: u64,
+    /// Hex-encoded call data
+    #[prost(string, tag = "5")]
+    pub data: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message, Deserialize)]
+#[serde(default)]
+pub struct SendTransactionResponse {
+    #[prost(string, tag = "1")]
+    #[serde(alias = "hash")]
+    pub transaction_hash: String,
+    #[prost(string, tag = "2")]
+    pub status: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeRequest {
+    /// `newHeads`, `pendingTransactions` or `logs`
+    #[prost(string, tag = "1")]
+    pub kind: String,
+    /// Only for `logs`
+    #[prost(message, optional, tag = "2")]
+    pub filter: Option<LogFilterMessage>,
+}
+
+/// The proto's `LogFilter` message
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct LogFilterMessage {
+    #[prost(string, repeated, tag = "1")]
+    pub addresses: Vec<String>,
+    /// One entry per topic position; an empty entry matches any topic
+    #[prost(message, repeated, tag = "2")]
+    pub topics: Vec<TopicSet>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TopicSet {
+    #[prost(string, repeated, tag = "1")]
+    pub any_of: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscriptionEvent {
+    #[prost(string, tag = "1")]
+    pub subscription: String,
+    /// JSON-encoded event
+    #[prost(string, tag = "2")]
+    pub result_json: String,
+}
+
+/// Registry methods backing the typed gateway calls
+#[derive(Debug, Clone)]
+pub struct GatewayMethods {
+    /// Called with `{"height": ..}`
+    pub get_block: String,
+    /// Called with `{"hash": ..}`
+    pub get_transaction: String,
+    /// Called with `{"address": ..}`
+    pub get_account: String,
+    /// Called with `{"transaction": {..}}`
+    pub send_transaction: String,
+}
+
+impl Default for GatewayMethods {
+    fn default() -> Self {
+        Self {
+            get_block: "cc_getBlockByHeight".to_string(),
+            get_transaction: "cc_getTransactionByHash".to_string(),
+            get_account: "cc_getAccount".to_string(),
+            send_transaction: "cc_sendTransaction".to_string(),
+        }
+    }
+}
+
+/// gRPC service exposing the methods registered on an `HttpRpcServer`
+///
+/// JSON-RPC errors become gRPC statuses carrying the original code in the
+/// `jsonrpc-code` metadata entry.
+#[derive(Clone)]
+pub struct GrpcGateway {
+    http: HttpRpcServer,
+    methods: Arc<GatewayMethods>,
+}
+
+impl GrpcGateway {
+    /// Forward calls to `http`
+    pub fn new(http: HttpRpcServer) -> Self {
+        Self {
+            http,
+            methods: Arc::new(GatewayMethods::default()),
+        }
+    }
+
+    /// Back the typed calls with different registry methods
+    pub fn with_methods(mut self, methods: GatewayMethods) -> Self {
+        self.methods = Arc::new(methods);
+        self
+    }
+
+    /// Registry methods backing the typed calls
+    pub fn methods(&self) -> &GatewayMethods {
+        &self.methods
+    }
+
+    /// Router serving the gateway, to be served over HTTP/2
+    pub fn router(&self) -> Router {
+        Router::new().route_service(&format!("/{}/*method", SERVICE_NAME), self.clone())
+    }
+
+    /// Serve the gateway on `listener`
+    pub async fn serve(self, listener: TcpListener) -> std::io::Result<()> {
+        let service = self
+            .router()
+            .into_make_service_with_connect_info::<SocketAddr>();
+        axum::serve(listener, service).await
+    }
+
+    async fn call(&self, request: Request<CallRequest>) -> Result<Response<CallResponse>, Status> {
+        let caller = self.caller(&request);
+        let request = request.into_inner();
+        let params = match request.params_json.trim() {
+            "" => None,
+            params => Some(serde_json::from_str(params).map_err(|e| {
+                Status::invalid_argument(format!("params_json is not valid JSON: {}", e))
+            })?),
+        };
+        let result = self.invoke(&caller, &request.method, params).await?;
+        Ok(Response::new(CallResponse {
+            result_json: result.to_string(),
+        }))
+    }
+
+    async fn list_methods(
+        &self,
+        _request: Request<ListMethodsRequest>,
+    ) -> Result<Response<ListMethodsResponse>, Status> {
+        let mut methods = self.http.server().get_registered_methods();
+        methods.sort();
+        Ok(Response::new(ListMethodsResponse { methods }))
+    }
+
+    async fn get_block(
+        &self,
+        request: Request<GetBlockRequest>,
+    ) -> Result<Response<Block>, Status> {
+        let caller = self.caller(&request);
+        let params = serde_json::json!({"height": request.get_ref().height});
+        self.typed(&caller, &self.methods.get_block, params).await
+    }
+
+    async fn get_transaction(
+        &self,
+        request: Request<GetTransactionRequest>,
+    ) -> Result<Response<Transaction>, Status> {
+        let caller = self.caller(&request);
+        let params = serde_json::json!({"hash": request.get_ref().hash});
+        self.typed(&caller, &self.methods.get_transaction, params)
+            .await
+    }
+
+    async fn get_account(
+        &self,
+        request: Request<GetAccountRequest>,
+    ) -> Result<Response<Account>, Status> {
+        let caller = self.caller(&request);
+        let params = serde_json::json!({"address": request.get_ref().address});
+        self.typed(&caller, &self.methods.get_account, params).await
+    }
+
+    async fn send_transaction(
+        &self,
+        request: Request<SendTransactionRequest>,
+    ) -> Result<Response<SendTransactionResponse>, Status> {
+        let caller = self.caller(&request);
+        let transaction = request.into_inner();
+        let params = serde_json::json!({"transaction": {
+            "from": transaction.from,
+            "to": transaction.to,
+            "value": transaction.value,
+            "nonce": transaction.nonce,
+            "data": transaction.data,
+        }});
+        let method = &self.methods.send_transaction;
+        match self.invoke(&caller, method, Some(params)).await? {
+            // The protocol defines the result as just the transaction hash
+            Value::String(transaction_hash) => Ok(Response::new(SendTransactionResponse {
+                transaction_hash,
+                status: "pending".to_string(),
+            })),
+            result => serde_json::from_value(result)
+                .map(Response::new)
+                .map_err(|e| unexpected_result(method, e)),
+        }
+    }
+
+    async fn subscribe(
+        &self,
+        request: Request<SubscribeRequest>,
+    ) -> Result<Response<EventStream>, Status> {
+        let request = request.into_inner();
+        let kind = match request.kind.as_str() {
+            "newHeads" => SubscriptionKind::NewHeads,
+            "pendingTransactions" => SubscriptionKind::PendingTransactions,
+            "logs" => {
+                let filter = request.filter.unwrap_or_default();
+                SubscriptionKind::Logs(LogFilter {
+                    addresses: filter.addresses,
+                    topics: filter
+                        .topics
+                        .into_iter()
+                        .map(|topic| (!topic.any_of.is_empty()).then_some(topic.any_of))
+                        .collect(),
+                })
+            }
+            kind => {
+                return Err(Status::invalid_argument(format!(
+                    "Unknown subscription kind '{}'",
+                    kind
+                )))
+            }
+        };
+
+        let hub = self.http.subscriptions().clone();
+        let (connection, notifications) = hub.connect();
+        let stream = EventStream {
+            hub,
+            connection,
+            notifications,
+            finished: false,
+        };
+        stream
+            .hub
+            .subscribe(&stream.connection, kind)
+            .map_err(|error| {
+                let code = match error {
+                    SubscriptionError::Invalid(_) => Code::InvalidArgument,
+                    SubscriptionError::LimitReached(_)
+                    | SubscriptionError::FilterLimitReached(_) => Code::ResourceExhausted,
+                    SubscriptionError::Closed => Code::Unavailable,
+                    SubscriptionError::UnknownFilter(_) => Code::NotFound,
+                };
+                Status::new(code, error.to_string())
+            })?;
+        Ok(Response::new(stream))
+    }
+
+    /// Identify the caller and its `grpc-timeout` like the HTTP transport does
+    fn caller<T>(&self, request: &Request<T>) -> Caller {
+        let addr = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| *addr);
+        let headers = request.metadata().clone().into_headers();
+        Caller {
+            client: ClientId::from_request(&headers, addr, &self.http.server().config),
+            timeout: deadline::client_timeout(&headers),
+        }
+    }
+
+    /// Call a registry method and decode its result into a message
+    async fn typed<T: DeserializeOwned>(
+        &self,
+        caller: &Caller,
+        method: &str,
+        params: Value,
+    ) -> Result<Response<T>, Status> {
+        let result = self.invoke(caller, method, Some(params)).await?;
+        serde_json::from_value(result)
+            .map(Response::new)
+            .map_err(|e| unexpected_result(method, e))
+    }
+
+    /// Run a call through the JSON-RPC pipeline
+    async fn invoke(
+        &self,
+        caller: &Caller,
+        method: &str,
+        params: Option<Value>,
+    ) -> Result<Value, Status> {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+            "id": 1,
+        });
+        let response = self
+            .http
+            .process(
+                request.to_string().as_bytes(),
+                &caller.client,
+                None,
+                caller.timeout,
+            )
+            .await
+            .and_then(|response| serde_json::from_str::<JsonRpcResponse>(&response).ok())
+            .ok_or_else(|| Status::internal("Malformed JSON-RPC response"))?;
+
+        match response.error {
+            Some(error) => {
+                let mut status = Status::new(status_code(error.code), error.message);
+                status
+                    .metadata_mut()
+                    .insert("jsonrpc-code", error.code.into());
+                Err(status)
+            }
+            None => Ok(response.result.unwrap_or(Value::Null)),
+        }
+    }
+}
+
+/// gRPC status code for a JSON-RPC error code
+fn status_code(code: i32) -> Code {
+    let Some(code) = RpcErrorCode::from_code(code) else {
+        return Code::Internal;
+    };
+    match code {
+        RpcErrorCode::ParseError
+        | RpcErrorCode::InvalidRequest
+        | RpcErrorCode::InvalidParams
+        | RpcErrorCode::TransactionRejected => Code::InvalidArgument,
+        RpcErrorCode::MethodNotFound => Code::Unimplemented,
+        RpcErrorCode::Unauthorized => Code::Unauthenticated,
+        RpcErrorCode::AccountNotFound
+        | RpcErrorCode::BlockNotFound
+        | RpcErrorCode::TransactionNotFound
+        | RpcErrorCode::FilterNotFound => Code::NotFound,
+        RpcErrorCode::TransactionPoolFull
+        | RpcErrorCode::GasLimitExceeded
+        | RpcErrorCode::ResponseTooLarge
+        | RpcErrorCode::SubscriptionLimitReached
+        | RpcErrorCode::RateLimitExceeded => Code::ResourceExhausted,
+        RpcErrorCode::InsufficientFunds
+        | RpcErrorCode::NonceTooLow
+        | RpcErrorCode::NonceTooHigh
+        | RpcErrorCode::NegotiationFailed
+        | RpcErrorCode::ExecutionFailed => Code::FailedPrecondition,
+        RpcErrorCode::RequestTimeout | RpcErrorCode::DeadlineExceeded => Code::DeadlineExceeded,
+        RpcErrorCode::NodeSyncing
+        | RpcErrorCode::ServiceUnavailable
+        | RpcErrorCode::ServerError => Code::Unavailable,
+        RpcErrorCode::InternalError => Code::Internal,
+    }
+}
+
+/// Who made a gRPC call and how long they will wait
+struct Caller {
+    client: ClientId,
+    timeout: Option<Duration>,
+}
+
+fn unexpected_result(method: &str, error: serde_json::Error) -> Status {
+    Status::internal(format!("Unexpected result from {}: {}", method, error))
+}
+
+/// Subscription events for one gRPC stream, unsubscribed when dropped
+pub struct EventStream {
+    hub: Arc<SubscriptionHub>,
+    connection: Arc<Connection>,
+    notifications: mpsc::Receiver<String>,
+    finished: bool,
+}
+
+impl Stream for EventStream {
+    type Item = Result<SubscriptionEvent, Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.finished {
+            return Poll::Ready(None);
+        }
+        match self.notifications.poll_recv(cx) {
+            Poll::Ready(Some(_)) if self.connection.is_overflowed() => {
+                self.finished = true;
+                Poll::Ready(Some(Err(Status::resource_exhausted(
+                    "Subscription queue full",
+                ))))
+            }
+            Poll::Ready(Some(notification)) => {
+                let params = serde_json::from_str::<Value>(&notification)
+                    .ok()
+                    .and_then(|mut notification| notification.get_mut("params").map(Value::take))
+                    .unwrap_or_default();
+                Poll::Ready(Some(Ok(SubscriptionEvent {
+                    subscription: params["subscription"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    result_json: params["result"].to_string(),
+                })))
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        self.hub.disconnect(&self.connection);
+    }
+}
+
+impl NamedService for GrpcGateway {
+    const NAME: &'static str = SERVICE_NAME;
+}
+
+/// Route a request to its handler, the way generated tonic servers do
+macro_rules! grpc_route {
+    ($kind:ident, $gateway:expr, $handler:ident, $request:expr) => {{
+        let gateway = $gateway;
+        let service = tower::service_fn(move |request| {
+            let gateway = gateway.clone();
+            async move { gateway.$handler(request).await }
+        });
+        Grpc::new(ProstCodec::default())
+            .$kind(service, $request)
+            .await
+    }};
+}
+
+impl<B> Service<http::Request<B>> for GrpcGateway
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let gateway = self.clone();
+        Box::pin(async move {
+            let method = request
+                .uri()
+                .path()
+                .strip_prefix(&format!("/{}/", SERVICE_NAME))
+                .unwrap_or_default()
+                .to_string();
+            let response = match method.as_str() {
+                "Call" => grpc_route!(unary, gateway, call, request),
+                "ListMethods" => grpc_route!(unary, gateway, list_methods, request),
+                "GetBlock" => grpc_route!(unary, gateway, get_block, request),
+                "GetTransaction" => grpc_route!(unary, gateway, get_transaction, request),
+                "GetAccount" => grpc_route!(unary, gateway, get_account, request),
+                "SendTransaction" => grpc_route!(unary, gateway, send_transaction, request),
+                "Subscribe" => grpc_route!(server_streaming, gateway, subscribe, request),
+                _ => Status::unimplemented(format!("Unknown method {}", method)).into_http(),
+            };
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rate_limit::MethodRateLimiter;
+    use crate::{BlockchainRpcMethods, RpcMethodHandler, RpcServer, RpcServerConfig};
+    use http_body_util::BodyExt;
+    use prost::Message;
+    use tower::ServiceExt;
+
+    struct BlockHandler;
+
+    impl RpcMethodHandler for BlockHandler {
+        fn handle(&self, params: Option<Value>) -> crate::Result<Value> {
+            let height = params
+                .and_then(|params| params["height"].as_u64())
+                .unwrap_or_default();
+            Ok(serde_json::json!({
+                "number": height,
+                "hash": format!("0x{:064x}", height),
+                "transactions": ["0xaa"],
+                "extra": true,
+            }))
+        }
+
+        fn description(&self) -> &str {
+            "Block by height"
+        }
+    }
+
+    struct SubmitHandler;
+
+    impl RpcMethodHandler for SubmitHandler {
+        fn handle(&self, params: Option<Value>) -> crate::Result<Value> {
+            let value = params
+                .and_then(|params| params["transaction"]["value"].as_u64())
+                .unwrap_or_default();
+            Ok(Value::String(format!("0x{:x}", value)))
+        }
+
+        fn description(&self) -> &str {
+            "Submit a transaction"
+        }
+    }
+
+    fn gateway() -> GrpcGateway {
+        let server = RpcServer::new(RpcServerConfig::default());
+        server
+            .register_method("ping", BlockchainRpcMethods::ping_handler())
+            .unwrap();
+        server
+            .register_method("cc_getBlockByHeight", BlockHandler)
+            .unwrap();
+        server
+            .register_method("cc_sendTransaction", SubmitHandler)
+            .unwrap();
+        let mut limiter = MethodRateLimiter::new();
+        limiter.set_limit(
+            "ping",
+            rpc_protocol::RateLimit {
+                requests_per_minute: 60,
+                burst_size: 2,
+                window_seconds: 60,
+            },
+        );
+        GrpcGateway::new(HttpRpcServer::new(server).with_rate_limiter(limiter))
+    }
+
+    async fn send(
+        gateway: &GrpcGateway,
+        method: &str,
+        message: impl Message,
+    ) -> axum::response::Response {
+        let body = message.encode_to_vec();
+        let mut framed = vec![0];
+        framed.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        framed.extend_from_slice(&body);
+        let request = http::Request::post(format!("/{}/{}", SERVICE_NAME, method))
+            .header("content-type", "application/grpc")
+            .header("te", "trailers")
+            .body(axum::body::Body::from(framed))
+            .unwrap();
+        gateway.router().oneshot(request).await.unwrap()
+    }
+
+    async fn unary<T: Message + Default>(
+        gateway: &GrpcGateway,
+        method: &str,
+        message: impl Message,
+    ) -> Result<T, Status> {
+        let response = send(gateway, method, message).await;
+        let headers_status = Status::from_header_map(response.headers());
+        let body = response.into_body().collect().await.unwrap();
+        let status = body
+            .trailers()
+            .and_then(Status::from_header_map)
+            .or(headers_status);
+        match status {
+            Some(status) if status.code() != Code::Ok => Err(status),
+            _ => Ok(T::decode(&body.to_bytes()[5..]).unwrap()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_typed_and_generic_calls() {
+        let gateway = gateway();
+
+        let block: Block = unary(&gateway, "GetBlock", GetBlockRequest { height: 42 })
+            .await
+            .unwrap();
+        assert_eq!((block.height, block.transactions.len()), (42, 1));
+        assert_eq!(block.hash, format!("0x{:064x}", 42));
+
+        let request = SendTransactionRequest {
+            value: 255,
+            ..Default::default()
+        };
+        let sent: SendTransactionResponse =
+            unary(&gateway, "SendTransaction", request).await.unwrap();
+        assert_eq!(sent.transaction_hash, "0xff");
+
+        let request = CallRequest {
+            method: "ping".to_string(),
+            params_json: String::new(),
+        };
+        let response: CallResponse = unary(&gateway, "Call", request).await.unwrap();
+        let result: Value = serde_json::from_str(&response.result_json).unwrap();
+        assert_eq!(result["status"], "ok");
+
+        let methods: ListMethodsResponse = unary(&gateway, "ListMethods", ListMethodsRequest {})
+            .await
+            .unwrap();
+        assert_eq!(methods.methods.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_errors_become_statuses() {
+        let gateway = gateway();
+
+        let request = GetAccountRequest {
+            address: "0x01".to_string(),
+        };
+        let status = unary::<Account>(&gateway, "GetAccount", request)
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Unimplemented);
+        assert_eq!(status.metadata().get("jsonrpc-code").unwrap(), "-32601");
+
+        let ping = || CallRequest {
+            method: "ping".to_string(),
+            params_json: String::new(),
+        };
+        for _ in 0..2 {
+            assert!(unary::<CallResponse>(&gateway, "Call", ping())
+                .await
+                .is_ok());
+        }
+        let status = unary::<CallResponse>(&gateway, "Call", ping())
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+
+        let request = CallRequest {
+            method: "ping".to_string(),
+            params_json: "{".to_string(),
+        };
+        let status = unary::<CallResponse>(&gateway, "Call", request)
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        let status = unary::<CallResponse>(&gateway, "Missing", ListMethodsRequest {})
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Unimplemented);
+    }
+
+    #[tokio::test]
+    async fn test_subscription_stream() {
+        let gateway = gateway();
+        let hub = gateway.http.subscriptions().clone();
+
+        let request = SubscribeRequest {
+            kind: "newHeads".to_string(),
+            filter: None,
+        };
+        let mut body = send(&gateway, "Subscribe", request).await.into_body();
+        assert_eq!(hub.subscription_count(), 1);
+        assert_eq!(hub.publish_new_head(&serde_json::json!({"number": 9})), 1);
+
+        let frame = body.frame().await.unwrap().unwrap().into_data().unwrap();
+        let event = SubscriptionEvent::decode(&frame[5..]).unwrap();
+        assert_eq!(event.subscription, "0x1");
+        assert_eq!(event.result_json, r#"{"number":9}"#);
+
+        // Closing the stream unsubscribes
+        drop(body);
+        assert_eq!(hub.subscription_count(), 0);
+
+        let request = SubscribeRequest {
+            kind: "blocks".to_string(),
+            filter: None,
+        };
+        let status = unary::<SubscriptionEvent>(&gateway, "Subscribe", request)
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+}
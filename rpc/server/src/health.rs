@@ -0,0 +1,176 @@
+//! Liveness and readiness probes
+//!
+//! `GET /health` reports whether the RPC server itself is working, from the
+//! `RpcMonitor`'s health status: 200 while it is healthy or degraded, 503
+//! once it is critical or down. `GET /ready` additionally requires the node
+//! to be synced and not halted, so load balancers and Kubernetes only route
+//! traffic to nodes that can answer with current state.
+
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use rpc_monitoring::{HealthLevel, RpcMonitor};
+use serde_json::{json, Value};
+
+/// Path of the liveness probe
+pub const HEALTH_PATH: &str = "/health";
+
+/// Path of the readiness probe
+pub const READY_PATH: &str = "/ready";
+
+/// Node state that decides readiness, typically backed by the sync manager
+/// and the safety subsystem
+pub trait NodeState: Send + Sync {
+    /// Whether the node is still catching up with the network
+    fn is_syncing(&self) -> bool;
+
+    /// Why the node is halted, if it is
+    fn halted(&self) -> Option<String>;
+}
+
+/// Outcome of a probe, as sent to the prober
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbeResult {
+    pub status: StatusCode,
+    pub body: Value,
+}
+
+impl IntoResponse for ProbeResult {
+    fn into_response(self) -> Response {
+        // Probes must always see the current state
+        (
+            self.status,
+            [(header::CACHE_CONTROL, "no-store")],
+            Json(self.body),
+        )
+            .into_response()
+    }
+}
+
+/// Liveness: the monitor's health, failing only when critical or down
+pub fn liveness(monitor: &RpcMonitor) -> ProbeResult {
+    let health = match monitor.get_health_status() {
+        Ok(health) => health,
+        Err(error) => {
+            return ProbeResult {
+                status: StatusCode::SERVICE_UNAVAILABLE,
+                body: json!({"status": "down", "error": error.to_string()}),
+            }
+        }
+    };
+    let summary = &health.metrics_summary;
+    ProbeResult {
+        status: status_code(is_live(&health.overall_status)),
+        body: json!({
+            "status": level_name(&health.overall_status),
+            "uptime_seconds": summary.uptime_seconds,
+            "error_rate_percent": summary.error_rate_percent,
+            "avg_response_time_ms": summary.avg_response_time_ms,
+            "concurrent_requests": summary.concurrent_requests,
+        }),
+    }
+}
+
+/// Readiness: live, synced and not halted; the body lists every reason
+/// the node is not ready
+pub fn readiness(monitor: &RpcMonitor, node: Option<&dyn NodeState>) -> ProbeResult {
+    let live = liveness(monitor);
+    let syncing = node.is_some_and(|node| node.is_syncing());
+    let halted = node.and_then(|node| node.halted());
+
+    let mut reasons = Vec::new();
+    if !live.status.is_success() {
+        reasons.push(format!(
+            "rpc server is {}",
+            live.body["status"].as_str().unwrap_or("down")
+        ));
+    }
+    if syncing {
+        reasons.push("node is syncing".to_string());
+    }
+    if let Some(reason) = &halted {
+        reasons.push(format!("node is halted: {reason}"));
+    }
+
+    let ready = reasons.is_empty();
+    ProbeResult {
+        status: status_code(ready),
+        body: json!({
+            "ready": ready,
+            "status": live.body["status"],
+            "syncing": syncing,
+            "halted": halted,
+            "reasons": reasons,
+        }),
+    }
+}
+
+fn is_live(level: &HealthLevel) -> bool {
+    matches!(level, HealthLevel::Healthy | HealthLevel::Warning)
+}
+
+fn level_name(level: &HealthLevel) -> &'static str {
+    match level {
+        HealthLevel::Healthy => "healthy",
+        HealthLevel::Warning => "warning",
+        HealthLevel::Critical => "critical",
+        HealthLevel::Down => "down",
+    }
+}
+
+fn status_code(ok: bool) -> StatusCode {
+    if ok {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Node {
+        syncing: bool,
+        halted: Mutex<Option<String>>,
+    }
+
+    impl NodeState for Node {
+        fn is_syncing(&self) -> bool {
+            self.syncing
+        }
+
+        fn halted(&self) -> Option<String> {
+            self.halted.lock().unwrap().clone()
+        }
+    }
+
+    #[test]
+    fn test_readiness_follows_node_state() {
+        let monitor = RpcMonitor::new();
+        let live = liveness(&monitor);
+        assert_eq!(live.status, StatusCode::OK);
+        assert_eq!(live.body["status"], "healthy");
+
+        let node = Node::default();
+        assert_eq!(readiness(&monitor, None).status, StatusCode::OK);
+        assert_eq!(readiness(&monitor, Some(&node)).status, StatusCode::OK);
+
+        *node.halted.lock().unwrap() = Some("upgrade".to_string());
+        let syncing = Node {
+            syncing: true,
+            ..Node::default()
+        };
+        let probe = readiness(&monitor, Some(&node));
+        assert_eq!(probe.status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(probe.body["halted"], "upgrade");
+        assert_eq!(probe.body["reasons"][0], "node is halted: upgrade");
+        let probe = readiness(&monitor, Some(&syncing));
+        assert_eq!(probe.status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(probe.body["syncing"], true);
+        // Still alive while syncing or halted
+        assert_eq!(liveness(&monitor).status, StatusCode::OK);
+    }
+}
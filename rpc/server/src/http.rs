@@ -0,0 +1,1330 @@
+//! HTTP transport for the JSON-RPC server
+
+use crate::cache::{CacheKey, ResponseCache};
+use crate::deadline::{self, CancellationToken};
+use crate::health::{self, NodeState};
+use crate::rate_limit::{ClientId, MethodRateLimiter};
+use crate::relay::{Relay, RelayError};
+use crate::subscriptions::{Connection, SubscriptionError, SubscriptionHub, SubscriptionKind};
+use crate::{ws, JsonRpcResponse, RpcServer};
+use axum::body::Bytes;
+use axum::extract::{ConnectInfo, DefaultBodyLimit, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::Router;
+use rpc_errors::RpcErrorCode;
+use rpc_monitoring::RpcMonitor;
+use rpc_protocol::{NegotiationRequest, ProtocolCapabilities, TransportType};
+use rpc_serialization::compression::ContentEncoding;
+use rpc_serialization::SerializationError;
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+
+/// JSON-RPC 2.0 over HTTP POST
+///
+/// Dispatches single and batch requests to the methods registered on an
+/// `RpcServer`. Request bodies over `max_request_size` are rejected with
+/// 413, calls still running after `timeout_seconds` and responses over
+/// `max_response_size` are answered with JSON-RPC errors, and every call is
+/// recorded with the `RpcMonitor`. Clients call `cc_negotiate` to agree on
+/// a protocol version and encoding before relying on newer behaviour.
+///
+/// Clients may shorten the timeout per request with `X-Request-Timeout`
+/// in milliseconds (or `grpc-timeout`); calls past that deadline are
+/// cancelled and answered with `RpcErrorCode::DeadlineExceeded`.
+///
+/// Methods limited by the `MethodRateLimiter` are throttled per client,
+/// identified by the server's API key or else the peer IP address; calls
+/// over the limit get `RpcErrorCode::RateLimitExceeded` with a
+/// `retry_after_seconds`.
+///
+/// Request bodies may be compressed with any of the `supported_encodings`,
+/// declared in `Content-Encoding`. Responses of at least
+/// `compression_threshold` bytes are compressed with the client's preferred
+/// supported encoding from `Accept-Encoding`.
+///
+/// When the capabilities list `TransportType::WebSocket`, GET requests on
+/// the same path upgrade to a WebSocket carrying the same calls plus
+/// `cc_subscribe`/`cc_unsubscribe`, fed by the `SubscriptionHub`. Wrap it
+/// in an `IpcRpcServer` to serve the same over a Unix domain socket.
+/// Clients on plain HTTP poll instead: `cc_newFilter` takes the same
+/// params as `cc_subscribe`, and `cc_getFilterChanges` returns the events
+/// since the last poll until `cc_uninstallFilter` or expiry.
+///
+/// With a `ResponseCache`, successful results of cacheable read methods are
+/// answered from the cache; per-block entries are keyed by the hub's
+/// `head_height`, so they go stale as soon as a new head is published.
+///
+/// With a `Relay`, calls to methods not registered here are forwarded to
+/// the upstream node within the same deadline, cached and monitored like
+/// local calls.
+///
+/// `GET /health` and `GET /ready` serve liveness and readiness probes from
+/// the monitor and, once set with `with_node_state`, the node's sync and
+/// halt state.
+#[derive(Clone)]
+pub struct HttpRpcServer {
+    server: Arc<RpcServer>,
+    capabilities: Arc<ProtocolCapabilities>,
+    monitor: Arc<RpcMonitor>,
+    subscriptions: Arc<SubscriptionHub>,
+    rate_limiter: Arc<MethodRateLimiter>,
+    cache: Option<Arc<ResponseCache>>,
+    relay: Option<Arc<Relay>>,
+    node_state: Option<Arc<dyn NodeState>>,
+    next_request_id: Arc<AtomicU64>,
+}
+
+/// A single call taken from a request body
+struct Call {
+    raw: String,
+    method: String,
+    params: Option<Value>,
+    id: Option<Value>,
+    notification: bool,
+    /// `cc_negotiate`, a subscription or a filter call, answered by the
+    /// transport itself
+    local: bool,
+    /// Where the result is cached, for cacheable read methods
+    cache_key: Option<CacheKey>,
+    /// Forwarded to the upstream node rather than handled here
+    relayed: bool,
+    monitor_id: String,
+}
+
+impl HttpRpcServer {
+    /// Serve `server` with the default protocol capabilities
+    pub fn new(server: RpcServer) -> Self {
+        Self {
+            server: Arc::new(server),
+            capabilities: Arc::new(ProtocolCapabilities::default()),
+            monitor: Arc::new(RpcMonitor::new()),
+            subscriptions: Arc::new(SubscriptionHub::new()),
+            rate_limiter: Arc::new(MethodRateLimiter::new()),
+            cache: None,
+            relay: None,
+            node_state: None,
+            next_request_id: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Enforce the given size limits and timeout
+    pub fn with_capabilities(mut self, capabilities: ProtocolCapabilities) -> Self {
+        self.capabilities = Arc::new(capabilities);
+        self
+    }
+
+    /// Record requests with a shared monitor
+    pub fn with_monitor(mut self, monitor: Arc<RpcMonitor>) -> Self {
+        self.monitor = monitor;
+        self
+    }
+
+    /// Publish subscription events through a shared hub
+    pub fn with_subscriptions(mut self, subscriptions: Arc<SubscriptionHub>) -> Self {
+        self.subscriptions = subscriptions;
+        self
+    }
+
+    /// Throttle methods per client
+    pub fn with_rate_limiter(mut self, rate_limiter: MethodRateLimiter) -> Self {
+        self.rate_limiter = Arc::new(rate_limiter);
+        self
+    }
+
+    /// Answer read methods from a response cache
+    ///
+    /// Register `cache()` with the monitor to export its hit rate.
+    pub fn with_cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = Some(Arc::new(cache));
+        self
+    }
+
+    /// Forward methods not registered here to an upstream node
+    ///
+    /// Register `relay()` with the monitor to export its metrics.
+    pub fn with_relay(mut self, relay: Relay) -> Self {
+        self.relay = Some(Arc::new(relay));
+        self
+    }
+
+    /// Report readiness from the node's sync and halt state
+    pub fn with_node_state(mut self, node_state: Arc<dyn NodeState>) -> Self {
+        self.node_state = Some(node_state);
+        self
+    }
+
+    /// The wrapped RPC server
+    pub fn server(&self) -> &Arc<RpcServer> {
+        &self.server
+    }
+
+    /// The monitor requests are recorded with
+    pub fn monitor(&self) -> &Arc<RpcMonitor> {
+        &self.monitor
+    }
+
+    /// The hub subscription events are published to
+    pub fn subscriptions(&self) -> &Arc<SubscriptionHub> {
+        &self.subscriptions
+    }
+
+    /// Per-method rate limits in force
+    pub fn rate_limiter(&self) -> &Arc<MethodRateLimiter> {
+        &self.rate_limiter
+    }
+
+    /// The response cache, if enabled
+    pub fn cache(&self) -> Option<&Arc<ResponseCache>> {
+        self.cache.as_ref()
+    }
+
+    /// The relay to the upstream node, if enabled
+    pub fn relay(&self) -> Option<&Arc<Relay>> {
+        self.relay.as_ref()
+    }
+
+    /// Protocol limits in force
+    pub fn capabilities(&self) -> &ProtocolCapabilities {
+        &self.capabilities
+    }
+
+    /// Router accepting JSON-RPC requests as POST bodies on `/`, and
+    /// WebSocket upgrades there if enabled, plus the health probes
+    pub fn router(&self) -> Router {
+        let mut route = post(handle_http);
+        if self
+            .capabilities
+            .supported_transports
+            .contains(&TransportType::WebSocket)
+        {
+            route = route.get(ws::upgrade);
+        }
+        Router::new()
+            .route("/", route)
+            .route(health::HEALTH_PATH, get(handle_health))
+            .route(health::READY_PATH, get(handle_ready))
+            .layer(DefaultBodyLimit::max(self.capabilities.max_request_size))
+            .with_state(self.clone())
+    }
+
+    /// Bind to the configured address and port and serve until the listener fails
+    pub async fn start(self) -> std::io::Result<()> {
+        let config = &self.server.config;
+        let listener = TcpListener::bind((config.bind_address.as_str(), config.port)).await?;
+        self.serve(listener).await
+    }
+
+    /// Serve requests accepted on `listener`
+    pub async fn serve(self, listener: TcpListener) -> std::io::Result<()> {
+        let _scheduler = self.monitor.start_scheduler();
+        let service = self
+            .router()
+            .into_make_service_with_connect_info::<SocketAddr>();
+        axum::serve(listener, service).await
+    }
+
+    /// Encodings from the capabilities this server can apply
+    fn content_encodings(&self) -> Vec<ContentEncoding> {
+        self.capabilities
+            .supported_encodings
+            .iter()
+            .filter_map(|name| ContentEncoding::from_name(name))
+            .collect()
+    }
+
+    /// Undo a request body's `Content-Encoding`, or return the status
+    /// rejecting it
+    fn decode_body(
+        &self,
+        headers: &HeaderMap,
+        body: Bytes,
+    ) -> std::result::Result<Bytes, StatusCode> {
+        let Some(value) = headers.get(header::CONTENT_ENCODING) else {
+            return Ok(body);
+        };
+        let value = value.to_str().map_err(|_| StatusCode::BAD_REQUEST)?;
+        let supported = self.content_encodings();
+
+        // Codings are listed in the order they were applied
+        let mut body = body;
+        for name in value.split(',').rev() {
+            let encoding = ContentEncoding::from_name(name)
+                .filter(|encoding| supported.contains(encoding))
+                .ok_or(StatusCode::UNSUPPORTED_MEDIA_TYPE)?;
+            body = encoding
+                .decompress(&body, self.capabilities.max_request_size)
+                .map(Bytes::from)
+                .map_err(|error| match error {
+                    SerializationError::SizeLimitExceeded(_) => StatusCode::PAYLOAD_TOO_LARGE,
+                    _ => StatusCode::BAD_REQUEST,
+                })?;
+        }
+        Ok(body)
+    }
+
+    /// Build the HTTP response for a JSON-RPC body, compressed if it
+    /// reaches the threshold and the client accepts a supported encoding
+    fn encode_response(&self, headers: &HeaderMap, body: String) -> Response {
+        let encoding = if body.len() < self.capabilities.compression_threshold {
+            ContentEncoding::Identity
+        } else {
+            let accept = headers
+                .get(header::ACCEPT_ENCODING)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default();
+            ContentEncoding::negotiate(accept, &self.content_encodings())
+                .unwrap_or(ContentEncoding::Identity)
+        };
+        let compressed = match encoding {
+            ContentEncoding::Identity => None,
+            encoding => encoding.compress(body.as_bytes()).ok(),
+        };
+        let (encoding, body) = match compressed {
+            Some(compressed) => (encoding, compressed),
+            None => (ContentEncoding::Identity, body.into_bytes()),
+        };
+
+        let mut response = (
+            [
+                (header::CONTENT_TYPE, "application/json"),
+                (header::VARY, "accept-encoding"),
+            ],
+            body,
+        )
+            .into_response();
+        if encoding != ContentEncoding::Identity {
+            response.headers_mut().insert(
+                header::CONTENT_ENCODING,
+                HeaderValue::from_static(encoding.name()),
+            );
+        }
+        response
+    }
+
+    /// Split a request body into calls, or return the error response for it
+    fn parse_calls(&self, body: &[u8]) -> std::result::Result<(Vec<Call>, bool), String> {
+        let value: Value = serde_json::from_slice(body).map_err(|_| {
+            self.server
+                .create_catalog_error_response(None, RpcErrorCode::ParseError.into())
+        })?;
+
+        let (values, batch) = match value {
+            Value::Array(_) if !self.capabilities.supports_batching => {
+                return Err(self.server.create_error_response(
+                    None,
+                    RpcErrorCode::InvalidRequest.code(),
+                    "Invalid Request".to_string(),
+                    Some(serde_json::json!({"reason": "Batch requests are not supported"})),
+                ));
+            }
+            Value::Array(values) if values.is_empty() => {
+                return Err(self.server.create_error_response(
+                    None,
+                    RpcErrorCode::InvalidRequest.code(),
+                    "Invalid Request".to_string(),
+                    Some(serde_json::json!({"reason": "Empty batch"})),
+                ));
+            }
+            Value::Array(values) => (values, true),
+            value => (vec![value], false),
+        };
+
+        let head = self.subscriptions.head_height();
+        let calls = values
+            .into_iter()
+            .map(|value| {
+                let method = value.get("method").and_then(Value::as_str);
+                let valid = value.get("jsonrpc").and_then(Value::as_str) == Some("2.0");
+                let params = value.get("params").cloned();
+                let local = valid
+                    && matches!(
+                        method,
+                        Some(
+                            "cc_negotiate"
+                                | "cc_subscribe"
+                                | "cc_unsubscribe"
+                                | "cc_newFilter"
+                                | "cc_getFilterChanges"
+                                | "cc_uninstallFilter"
+                        )
+                    );
+                let relayed = valid
+                    && !local
+                    && method.is_some_and(|method| {
+                        self.relay
+                            .as_ref()
+                            .is_some_and(|relay| relay.relays(method))
+                            && !self.server.has_method(method)
+                    });
+                Call {
+                    local,
+                    relayed,
+                    cache_key: self
+                        .cache
+                        .as_ref()
+                        .filter(|_| valid)
+                        .zip(method)
+                        .and_then(|(cache, method)| cache.key(method, params.as_ref(), head)),
+                    method: method.unwrap_or("<invalid>").to_string(),
+                    params,
+                    id: value.get("id").cloned(),
+                    notification: self.capabilities.supports_notifications
+                        && value.is_object()
+                        && value.get("id").is_none(),
+                    monitor_id: format!(
+                        "http-{}",
+                        self.next_request_id.fetch_add(1, Ordering::Relaxed)
+                    ),
+                    raw: value.to_string(),
+                }
+            })
+            .collect();
+        Ok((calls, batch))
+    }
+
+    /// Answer a request body, or return `None` if it held only notifications
+    ///
+    /// Rate limits are charged to `client`. Subscription calls are answered
+    /// for `connection`, or rejected when the request did not arrive on a
+    /// persistent connection. A client `timeout` shorter than the protocol
+    /// timeout becomes the calls' deadline.
+    pub(crate) async fn process(
+        &self,
+        body: &[u8],
+        client: &ClientId,
+        connection: Option<&Arc<Connection>>,
+        timeout: Option<Duration>,
+    ) -> Option<String> {
+        let (calls, batch) = match self.parse_calls(body) {
+            Ok(parsed) => parsed,
+            Err(response) => return Some(response),
+        };
+
+        let responses: Vec<String> = self
+            .dispatch(&calls, client, connection, timeout)
+            .await
+            .into_iter()
+            .zip(&calls)
+            .filter(|(_, call)| !call.notification)
+            .map(|(response, _)| response)
+            .collect();
+
+        if responses.is_empty() {
+            None
+        } else if batch {
+            Some(format!("[{}]", responses.join(",")))
+        } else {
+            Some(responses.concat())
+        }
+    }
+
+    /// Run calls on the blocking pool until the deadline
+    ///
+    /// Rate-limited calls, cache hits and calls handled by the transport
+    /// are answered without reaching the server, and relayed calls are
+    /// forwarded upstream in parallel with the rest. Handlers share a `CancellationToken`
+    /// that is cancelled at the deadline, so calls still running stop
+    /// instead of finishing for nobody.
+    async fn dispatch(
+        &self,
+        calls: &[Call],
+        client: &ClientId,
+        connection: Option<&Arc<Connection>>,
+        timeout: Option<Duration>,
+    ) -> Vec<String> {
+        let dispatched = Instant::now();
+        for call in calls {
+            let _ = self.monitor.start_client_request(
+                call.monitor_id.clone(),
+                call.method.clone(),
+                call.raw.len(),
+                Some(client.to_string()),
+            );
+        }
+
+        let answered: Vec<Option<String>> = calls
+            .iter()
+            .map(|call| {
+                if let Err(retry_after) = self.rate_limiter.check(client, &call.method) {
+                    Some(self.rate_limited(call, retry_after))
+                } else if call.local {
+                    Some(self.handle_local(call, connection))
+                } else {
+                    self.cached(call)
+                        .map(|result| self.server.create_success_response(call.id.clone(), result))
+                }
+            })
+            .collect();
+
+        let server_timeout = Duration::from_secs(u64::from(self.capabilities.timeout_seconds));
+        let (timeout, client_deadline) = match timeout {
+            Some(timeout) if timeout < server_timeout => (timeout, true),
+            _ => (server_timeout, false),
+        };
+        let token = CancellationToken::with_deadline(Instant::now() + timeout);
+
+        // Relayed calls run upstream while the rest run here
+        let relayed: Vec<_> = calls
+            .iter()
+            .zip(&answered)
+            .filter(|(call, answered)| answered.is_none() && call.relayed)
+            .filter_map(|(call, _)| {
+                let relay = self.relay.clone()?;
+                let monitor = self.monitor.clone();
+                let raw = call.raw.clone();
+                let monitor_id = call.monitor_id.clone();
+                Some(tokio::spawn(async move {
+                    let started = Instant::now();
+                    let response = relay.forward(&raw, timeout).await;
+                    monitor.record_phase(&monitor_id, "relay", started.elapsed());
+                    response
+                }))
+            })
+            .collect();
+
+        let server = self.server.clone();
+        let monitor = self.monitor.clone();
+        let task_token = token.clone();
+        let handled: Vec<(String, String)> = calls
+            .iter()
+            .zip(&answered)
+            .filter(|(call, answered)| answered.is_none() && !call.relayed)
+            .map(|(call, _)| (call.monitor_id.clone(), call.raw.clone()))
+            .collect();
+        let task = tokio::task::spawn_blocking(move || {
+            handled
+                .iter()
+                .map(|(monitor_id, raw)| {
+                    // Waiting covers the blocking pool and earlier calls in the batch
+                    let started = Instant::now();
+                    monitor.record_phase(monitor_id, "queued", started - dispatched);
+                    let response = server.handle_request_with_token(raw, &task_token);
+                    monitor.record_phase(monitor_id, "handler", started.elapsed());
+                    response
+                })
+                .collect::<Vec<_>>()
+        });
+        let outcome = tokio::time::timeout(timeout, task).await;
+        let timed_out = outcome.is_err();
+        if timed_out {
+            token.cancel();
+        }
+        let mut responses = match outcome {
+            Ok(Ok(responses)) => responses.into_iter(),
+            _ => Vec::new().into_iter(),
+        };
+        let mut forwarded = Vec::with_capacity(relayed.len());
+        for handle in relayed {
+            forwarded.push(handle.await);
+        }
+        let mut forwarded = forwarded.into_iter();
+
+        calls
+            .iter()
+            .zip(answered)
+            .map(|(call, answered)| {
+                if let Some(response) = answered {
+                    return self.finish(call, response);
+                }
+                if call.relayed {
+                    let response = match forwarded.next() {
+                        Some(Ok(Ok(response))) => response,
+                        Some(Ok(Err(RelayError::Timeout(limit))))
+                            if client_deadline && limit == timeout =>
+                        {
+                            self.server.create_catalog_error_response(
+                                call.id.clone(),
+                                rpc_errors::RpcError::deadline_exceeded(timeout.as_millis() as u64),
+                            )
+                        }
+                        Some(Ok(Err(error))) => self
+                            .server
+                            .create_catalog_error_response(call.id.clone(), error.into()),
+                        _ => self.server.create_catalog_error_response(
+                            call.id.clone(),
+                            RpcErrorCode::InternalError.into(),
+                        ),
+                    };
+                    self.remember(call, &response);
+                    return self.finish(call, response);
+                }
+                // Nothing comes back if the blocking task timed out or panicked
+                let response = if timed_out { None } else { responses.next() };
+                // A handler can see its token expire just before the timeout
+                // above fires; either way the call ran out of time
+                if timed_out || response.as_deref().is_some_and(is_deadline_exceeded) {
+                    self.monitor
+                        .capture_params(&call.monitor_id, call.params.as_ref());
+                    let _ = self.monitor.timeout_request(call.monitor_id.clone());
+                    let error = if client_deadline {
+                        rpc_errors::RpcError::deadline_exceeded(timeout.as_millis() as u64)
+                    } else {
+                        rpc_errors::RpcError::request_timeout(
+                            self.capabilities.timeout_seconds.into(),
+                        )
+                    };
+                    return self
+                        .server
+                        .create_catalog_error_response(call.id.clone(), error);
+                }
+                let response = response.unwrap_or_else(|| {
+                    self.server.create_catalog_error_response(
+                        call.id.clone(),
+                        RpcErrorCode::InternalError.into(),
+                    )
+                });
+                self.remember(call, &response);
+                self.finish(call, response)
+            })
+            .collect()
+    }
+
+    /// Cached result for a call, if it has one
+    fn cached(&self, call: &Call) -> Option<Value> {
+        let key = call.cache_key.as_ref()?;
+        self.cache.as_ref()?.get(key)
+    }
+
+    /// Cache the result of a successful cacheable call
+    fn remember(&self, call: &Call, response: &str) {
+        let (Some(cache), Some(key)) = (&self.cache, &call.cache_key) else {
+            return;
+        };
+        if let Ok(Value::Object(mut response)) = serde_json::from_str(response) {
+            if !response.contains_key("error") {
+                if let Some(result) = response.remove("result") {
+                    cache.put(key.clone(), result);
+                }
+            }
+        }
+    }
+
+    /// Reject a call over its method's rate limit
+    fn rate_limited(&self, call: &Call, retry_after: Duration) -> String {
+        let mut error =
+            rpc_errors::RpcError::rate_limit_exceeded(retry_after.as_secs_f64().ceil() as u64);
+        error.message = format!("Rate limit exceeded for {}", call.method);
+        self.server
+            .create_catalog_error_response(call.id.clone(), error)
+    }
+
+    /// Answer calls handled by the transport rather than registered methods
+    fn handle_local(&self, call: &Call, connection: Option<&Arc<Connection>>) -> String {
+        match call.method.as_str() {
+            "cc_negotiate" => return self.handle_negotiate(call),
+            "cc_newFilter" | "cc_getFilterChanges" | "cc_uninstallFilter" => {
+                return self.handle_filter(call)
+            }
+            _ => {}
+        }
+        let Some(connection) = connection else {
+            return self.server.create_error_response(
+                call.id.clone(),
+                RpcErrorCode::MethodNotFound.code(),
+                format!("{} requires a WebSocket or IPC connection", call.method),
+                None,
+            );
+        };
+
+        let result = if call.method == "cc_subscribe" {
+            SubscriptionKind::from_params(call.params.as_ref())
+                .and_then(|kind| self.subscriptions.subscribe(connection, kind))
+                .map(Value::String)
+        } else {
+            call.params
+                .as_ref()
+                .and_then(|params| params.get(0))
+                .and_then(Value::as_str)
+                .map(|id| Value::Bool(self.subscriptions.unsubscribe(connection, id)))
+                .ok_or_else(|| SubscriptionError::Invalid("Expected [subscription id]".to_string()))
+        };
+
+        match result {
+            Ok(result) => self.server.create_success_response(call.id.clone(), result),
+            Err(error) => self.server.create_error_response(
+                call.id.clone(),
+                error.code().code(),
+                error.to_string(),
+                None,
+            ),
+        }
+    }
+
+    /// Answer a filter call; filters are not tied to a connection, so plain
+    /// HTTP clients can poll them
+    fn handle_filter(&self, call: &Call) -> String {
+        let filters = self.subscriptions.filters();
+        let filter_id = || {
+            call.params
+                .as_ref()
+                .and_then(|params| params.get(0))
+                .and_then(Value::as_str)
+                .ok_or_else(|| SubscriptionError::Invalid("Expected [filter id]".to_string()))
+        };
+        let result = match call.method.as_str() {
+            "cc_newFilter" => SubscriptionKind::from_params(call.params.as_ref())
+                .and_then(|kind| filters.install(kind))
+                .map(Value::String),
+            "cc_getFilterChanges" => filter_id()
+                .and_then(|id| filters.changes(id))
+                .map(Value::Array),
+            _ => filter_id().map(|id| Value::Bool(filters.uninstall(id))),
+        };
+
+        match result {
+            Ok(result) => self.server.create_success_response(call.id.clone(), result),
+            Err(error) => self.server.create_error_response(
+                call.id.clone(),
+                error.code().code(),
+                error.to_string(),
+                None,
+            ),
+        }
+    }
+
+    /// Select the protocol version and encoding for a client's `cc_negotiate`
+    /// call, accepting the request object bare or as the only positional param
+    fn handle_negotiate(&self, call: &Call) -> String {
+        let request = match &call.params {
+            Some(Value::Array(params)) => params.first().cloned(),
+            params => params.clone(),
+        }
+        .and_then(|params| serde_json::from_value::<NegotiationRequest>(params).ok());
+        let Some(request) = request else {
+            return self.server.create_error_response(
+                call.id.clone(),
+                RpcErrorCode::InvalidParams.code(),
+                "Expected {\"versions\": [...], \"encodings\": [...]}".to_string(),
+                None,
+            );
+        };
+
+        match self.capabilities.negotiate(&request) {
+            Ok(response) => self.server.create_success_response(
+                call.id.clone(),
+                serde_json::to_value(response).unwrap_or_default(),
+            ),
+            Err(error) => self.server.create_error_response(
+                call.id.clone(),
+                RpcErrorCode::NegotiationFailed.code(),
+                error.to_string(),
+                Some(serde_json::json!({
+                    "supported_versions": [self.capabilities.version],
+                    "supported_encodings": self.capabilities.supported_encodings,
+                })),
+            ),
+        }
+    }
+
+    /// Apply the response size limit and record the call's outcome
+    fn finish(&self, call: &Call, response: String) -> String {
+        let response = if response.len() > self.capabilities.max_response_size {
+            self.server.create_catalog_error_response(
+                call.id.clone(),
+                rpc_errors::RpcError::response_too_large(
+                    response.len(),
+                    self.capabilities.max_response_size,
+                ),
+            )
+        } else {
+            response
+        };
+
+        let error_code = serde_json::from_str::<JsonRpcResponse>(&response)
+            .ok()
+            .and_then(|response| response.error)
+            .map(|error| error.code);
+        self.monitor
+            .capture_params(&call.monitor_id, call.params.as_ref());
+        let monitor_id = call.monitor_id.clone();
+        let _ = match error_code {
+            Some(code) if code == RpcErrorCode::DeadlineExceeded.code() => {
+                self.monitor.timeout_request(monitor_id)
+            }
+            Some(code) => self.monitor.fail_request(monitor_id, code),
+            None => self.monitor.complete_request(monitor_id, response.len()),
+        };
+        response
+    }
+}
+
+/// Whether a response is a handler's own deadline-exceeded error
+fn is_deadline_exceeded(response: &str) -> bool {
+    serde_json::from_str::<JsonRpcResponse>(response)
+        .ok()
+        .and_then(|response| response.error)
+        .is_some_and(|error| error.code == RpcErrorCode::DeadlineExceeded.code())
+}
+
+async fn handle_http(
+    State(http): State<HttpRpcServer>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let addr = connect_info.map(|ConnectInfo(addr)| addr);
+    let client = ClientId::from_request(&headers, addr, &http.server.config);
+    let body = match http.decode_body(&headers, body) {
+        Ok(body) => body,
+        Err(status) => {
+            let accepted = http.capabilities.supported_encodings.join(", ");
+            return (status, [(header::ACCEPT_ENCODING, accepted)]).into_response();
+        }
+    };
+    let timeout = deadline::client_timeout(&headers);
+    match http.process(&body, &client, None, timeout).await {
+        Some(body) => http.encode_response(&headers, body),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+async fn handle_health(State(http): State<HttpRpcServer>) -> Response {
+    health::liveness(&http.monitor).into_response()
+}
+
+async fn handle_ready(State(http): State<HttpRpcServer>) -> Response {
+    health::readiness(&http.monitor, http.node_state.as_deref()).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlockchainRpcMethods, RpcMethodHandler, RpcServerConfig};
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    struct SlowHandler;
+
+    impl RpcMethodHandler for SlowHandler {
+        fn handle(&self, _params: Option<Value>) -> crate::Result<Value> {
+            std::thread::sleep(Duration::from_millis(1500));
+            Ok(Value::Null)
+        }
+
+        fn description(&self) -> &str {
+            "Sleep past the request timeout"
+        }
+    }
+
+    fn http_server(capabilities: ProtocolCapabilities) -> HttpRpcServer {
+        let server = RpcServer::new(RpcServerConfig::default());
+        server
+            .register_method("ping", BlockchainRpcMethods::ping_handler())
+            .unwrap();
+        server.register_method("slow", SlowHandler).unwrap();
+        HttpRpcServer::new(server).with_capabilities(capabilities)
+    }
+
+    async fn post(http: &HttpRpcServer, body: impl Into<String>) -> (StatusCode, String) {
+        let request = Request::post("/")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.into()))
+            .unwrap();
+        let response = http.router().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_single_and_batch_requests() {
+        let http = http_server(ProtocolCapabilities::default());
+
+        let (status, body) = post(&http, r#"{"jsonrpc":"2.0","method":"ping","id":1}"#).await;
+        assert_eq!(status, StatusCode::OK);
+        let response: JsonRpcResponse = serde_json::from_str(&body).unwrap();
+        assert_eq!(response.result.unwrap()["status"], "ok");
+
+        // The notification gets no entry in the batch response
+        let batch = r#"[
+            {"jsonrpc":"2.0","method":"ping","id":2},
+            {"jsonrpc":"2.0","method":"ping"},
+            {"jsonrpc":"2.0","method":"missing","id":3}
+        ]"#;
+        let (_, body) = post(&http, batch).await;
+        let responses: Vec<JsonRpcResponse> = serde_json::from_str(&body).unwrap();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[1].error.as_ref().unwrap().code, -32601);
+
+        let (status, _) = post(&http, r#"{"jsonrpc":"2.0","method":"ping"}"#).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        let (_, body) = post(&http, "[]").await;
+        assert!(body.contains("-32600"));
+        let (_, body) = post(&http, "{not json").await;
+        assert!(body.contains("-32700"));
+
+        let window = Duration::from_secs(60);
+        assert_eq!(
+            http.monitor()
+                .get_method_metrics("ping", window)
+                .unwrap()
+                .len(),
+            4
+        );
+        let missing = http
+            .monitor()
+            .get_method_metrics("missing", window)
+            .unwrap();
+        assert_eq!(missing[0].error_code, Some(-32601));
+    }
+
+    #[tokio::test]
+    async fn test_size_limits() {
+        let capabilities = ProtocolCapabilities {
+            max_request_size: 128,
+            max_response_size: 64,
+            supports_batching: false,
+            ..ProtocolCapabilities::default()
+        };
+        let http = http_server(capabilities);
+
+        let oversized = format!(
+            r#"{{"jsonrpc":"2.0","method":"ping","params":"{}","id":1}}"#,
+            "x".repeat(256)
+        );
+        let (status, _) = post(&http, oversized).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+
+        let (_, body) = post(&http, r#"{"jsonrpc":"2.0","method":"ping","id":1}"#).await;
+        let response: JsonRpcResponse = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            response.error.unwrap().code,
+            RpcErrorCode::ResponseTooLarge.code()
+        );
+        assert_eq!(response.id, Some(serde_json::json!(1)));
+
+        let (_, body) = post(&http, r#"[{"jsonrpc":"2.0","method":"ping","id":1}]"#).await;
+        assert!(body.contains("Batch requests are not supported"));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_is_reported_and_monitored() {
+        let capabilities = ProtocolCapabilities {
+            timeout_seconds: 1,
+            ..ProtocolCapabilities::default()
+        };
+        let http = http_server(capabilities);
+
+        let (_, body) = post(&http, r#"{"jsonrpc":"2.0","method":"slow","id":"a"}"#).await;
+        let response: JsonRpcResponse = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            response.error.unwrap().code,
+            RpcErrorCode::RequestTimeout.code()
+        );
+        assert_eq!(response.id, Some(serde_json::json!("a")));
+
+        let metrics = http
+            .monitor()
+            .get_method_metrics("slow", Duration::from_secs(60))
+            .unwrap();
+        assert!(matches!(
+            metrics[0].status,
+            rpc_monitoring::RequestStatus::Timeout
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_version_negotiation() {
+        let http = http_server(ProtocolCapabilities::default());
+
+        let request = r#"{"jsonrpc":"2.0","method":"cc_negotiate","params":{
+            "versions":[{"major":1,"minor":0,"patch":0},{"major":2,"minor":0,"patch":0}],
+            "encodings":["gzip"]},"id":1}"#;
+        let (_, body) = post(&http, request).await;
+        let response: JsonRpcResponse = serde_json::from_str(&body).unwrap();
+        let result = response.result.unwrap();
+        assert_eq!(result["version"]["major"], 1);
+        assert_eq!(result["encoding"], "gzip");
+
+        let request = r#"{"jsonrpc":"2.0","method":"cc_negotiate","params":[
+            {"versions":[{"major":2,"minor":0,"patch":0}]}],"id":2}"#;
+        let (_, body) = post(&http, request).await;
+        let error = serde_json::from_str::<JsonRpcResponse>(&body)
+            .unwrap()
+            .error
+            .unwrap();
+        assert_eq!(error.code, RpcErrorCode::NegotiationFailed.code());
+        let data = error.data.unwrap();
+        assert_eq!(data["supported_versions"][0]["major"], 1);
+        assert!(data["supported_encodings"].as_array().unwrap().len() > 1);
+
+        let (_, body) = post(&http, r#"{"jsonrpc":"2.0","method":"cc_negotiate","id":3}"#).await;
+        assert!(body.contains("-32602"));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limits_per_client() {
+        let server = RpcServer::new(RpcServerConfig {
+            api_key: Some("secret".to_string()),
+            ..RpcServerConfig::default()
+        });
+        server
+            .register_method("ping", BlockchainRpcMethods::ping_handler())
+            .unwrap();
+        let mut limiter = MethodRateLimiter::new();
+        limiter.set_limit(
+            "ping",
+            rpc_protocol::RateLimit {
+                requests_per_minute: 60,
+                burst_size: 2,
+                window_seconds: 60,
+            },
+        );
+        let http = HttpRpcServer::new(server).with_rate_limiter(limiter);
+
+        let batch = r#"[
+            {"jsonrpc":"2.0","method":"ping","id":1},
+            {"jsonrpc":"2.0","method":"ping","id":2},
+            {"jsonrpc":"2.0","method":"ping","id":3}
+        ]"#;
+        let (_, body) = post(&http, batch).await;
+        let responses: Vec<JsonRpcResponse> = serde_json::from_str(&body).unwrap();
+        assert!(responses[0].result.is_some() && responses[1].result.is_some());
+        let error = responses[2].error.as_ref().unwrap();
+        assert_eq!(error.code, RpcErrorCode::RateLimitExceeded.code());
+        assert_eq!(error.data.as_ref().unwrap()["retry_after_seconds"], 1);
+        let metrics = http
+            .monitor()
+            .get_method_metrics("ping", Duration::from_secs(60))
+            .unwrap();
+        let limited = metrics
+            .iter()
+            .filter(|metrics| metrics.error_code == Some(RpcErrorCode::RateLimitExceeded.code()))
+            .count();
+        assert_eq!(limited, 1);
+
+        // A client presenting the API key has its own bucket
+        let request = Request::post("/")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(crate::rate_limit::API_KEY_HEADER, "secret")
+            .body(Body::from(r#"{"jsonrpc":"2.0","method":"ping","id":4}"#))
+            .unwrap();
+        let response = http.router().oneshot(request).await.unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let response: JsonRpcResponse = serde_json::from_slice(&body).unwrap();
+        assert!(response.result.is_some());
+
+        let clients = http
+            .monitor()
+            .top_clients(
+                rpc_monitoring::ClientRanking::RequestCount,
+                10,
+                Duration::from_secs(60),
+            )
+            .unwrap();
+        assert_eq!(clients.len(), 2);
+        assert_eq!(clients[0].request_count, 3);
+        assert_eq!(clients[1].client_id, "api-key");
+    }
+
+    #[tokio::test]
+    async fn test_compression() {
+        let capabilities = ProtocolCapabilities {
+            compression_threshold: 200,
+            ..ProtocolCapabilities::default()
+        };
+        let http = http_server(capabilities);
+        let send = |body: Vec<u8>, encoding: Option<&str>| {
+            let mut request = Request::post("/")
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::ACCEPT_ENCODING, "gzip;q=0.5, zstd");
+            if let Some(encoding) = encoding {
+                request = request.header(header::CONTENT_ENCODING, encoding);
+            }
+            http.router()
+                .oneshot(request.body(Body::from(body)).unwrap())
+        };
+
+        // Small responses are sent as is
+        let single = br#"{"jsonrpc":"2.0","method":"ping","id":1}"#.to_vec();
+        let response = send(single.clone(), None).await.unwrap();
+        assert!(response.headers().get(header::CONTENT_ENCODING).is_none());
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(body.len() < 200);
+
+        // A gzip request whose batch response crosses the threshold comes back as zstd
+        let batch = format!(
+            "[{}]",
+            vec![String::from_utf8(single).unwrap(); 5].join(",")
+        );
+        let compressed = ContentEncoding::Gzip.compress(batch.as_bytes()).unwrap();
+        let response = send(compressed, Some("gzip")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "zstd");
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = ContentEncoding::Zstd.decompress(&body, usize::MAX).unwrap();
+        let responses: Vec<JsonRpcResponse> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(responses.len(), 5);
+
+        let response = send(b"[]".to_vec(), Some("br")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let response = send(b"not gzip".to_vec(), Some("gzip")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let bomb = ContentEncoding::Deflate
+            .compress(&vec![b' '; 2 * 1024 * 1024])
+            .unwrap();
+        let response = send(bomb, Some("deflate")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_client_deadline_cancels_handler() {
+        struct Scan(Arc<std::sync::atomic::AtomicBool>);
+
+        impl RpcMethodHandler for Scan {
+            fn handle(&self, params: Option<Value>) -> crate::Result<Value> {
+                self.handle_with_token(params, &CancellationToken::new())
+            }
+
+            fn handle_with_token(
+                &self,
+                _params: Option<Value>,
+                token: &CancellationToken,
+            ) -> crate::Result<Value> {
+                while token.check().is_ok() {
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+                self.0.store(true, Ordering::SeqCst);
+                token.check().map(|_| Value::Null)
+            }
+
+            fn description(&self) -> &str {
+                "Scan until cancelled"
+            }
+        }
+
+        let stopped = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let server = RpcServer::new(RpcServerConfig::default());
+        server
+            .register_method("scan", Scan(stopped.clone()))
+            .unwrap();
+        let http = HttpRpcServer::new(server);
+
+        let request = Request::post("/")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(deadline::TIMEOUT_HEADER, "100")
+            .body(Body::from(r#"{"jsonrpc":"2.0","method":"scan","id":1}"#))
+            .unwrap();
+        let response = http.router().oneshot(request).await.unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let error = serde_json::from_slice::<JsonRpcResponse>(&body)
+            .unwrap()
+            .error
+            .unwrap();
+        assert_eq!(error.code, RpcErrorCode::DeadlineExceeded.code());
+        assert_eq!(error.data.unwrap()["timeout_ms"], 100);
+
+        // The handler saw the cancellation and stopped
+        let started = Instant::now();
+        while !stopped.load(Ordering::SeqCst) {
+            assert!(started.elapsed() < Duration::from_secs(5));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let metrics = http
+            .monitor()
+            .get_method_metrics("scan", Duration::from_secs(60))
+            .unwrap();
+        assert!(matches!(
+            metrics[0].status,
+            rpc_monitoring::RequestStatus::Timeout
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_filters_over_http() {
+        let http = http_server(ProtocolCapabilities::default());
+        let call = |method: &str, params: Value| {
+            serde_json::json!({"jsonrpc": "2.0", "method": method, "params": params, "id": 1})
+                .to_string()
+        };
+        let response = |body: String| serde_json::from_str::<JsonRpcResponse>(&body).unwrap();
+
+        let (_, body) = post(&http, call("cc_newFilter", serde_json::json!(["newHeads"]))).await;
+        let id = response(body).result.unwrap();
+        assert_eq!(
+            http.subscriptions()
+                .publish_new_head(&serde_json::json!({"height": 1})),
+            0
+        );
+        http.subscriptions()
+            .publish_new_head(&serde_json::json!({"height": 2}));
+
+        let changes = call("cc_getFilterChanges", serde_json::json!([id]));
+        let (_, body) = post(&http, changes.clone()).await;
+        let heights: Vec<Value> = response(body)
+            .result
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|head| head["height"].clone())
+            .collect();
+        assert_eq!(heights, vec![serde_json::json!(1), serde_json::json!(2)]);
+
+        let (_, body) = post(&http, call("cc_uninstallFilter", serde_json::json!([id]))).await;
+        assert_eq!(response(body).result, Some(Value::Bool(true)));
+        let (_, body) = post(&http, changes).await;
+        assert_eq!(
+            response(body).error.unwrap().code,
+            RpcErrorCode::FilterNotFound.code()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cached_reads_follow_the_head() {
+        struct Counting(Arc<AtomicU64>);
+
+        impl RpcMethodHandler for Counting {
+            fn handle(&self, _params: Option<Value>) -> crate::Result<Value> {
+                Ok(Value::from(self.0.fetch_add(1, Ordering::SeqCst)))
+            }
+
+            fn description(&self) -> &str {
+                "Count calls"
+            }
+        }
+
+        let calls = Arc::new(AtomicU64::new(0));
+        let server = RpcServer::new(RpcServerConfig::default());
+        for method in ["cc_getBlockByHeight", "cc_getBalance"] {
+            server
+                .register_method(method, Counting(calls.clone()))
+                .unwrap();
+        }
+        // Room in every shard, so no entry is evicted by another
+        let http = HttpRpcServer::new(server).with_cache(ResponseCache::new(1024));
+        async fn call(http: &HttpRpcServer, method: &str) -> Value {
+            let body =
+                serde_json::json!({"jsonrpc": "2.0", "method": method, "params": [1], "id": 1});
+            let (_, body) = post(http, body.to_string()).await;
+            serde_json::from_str::<JsonRpcResponse>(&body)
+                .unwrap()
+                .result
+                .unwrap()
+        }
+
+        assert_eq!(
+            call(&http, "cc_getBlockByHeight").await,
+            call(&http, "cc_getBlockByHeight").await
+        );
+        // Without a known head, per-block results are not cached
+        assert_ne!(
+            call(&http, "cc_getBalance").await,
+            call(&http, "cc_getBalance").await
+        );
+
+        http.subscriptions()
+            .publish_new_head(&serde_json::json!({"height": 5}));
+        let balance = call(&http, "cc_getBalance").await;
+        assert_eq!(call(&http, "cc_getBalance").await, balance);
+        http.subscriptions()
+            .publish_new_head(&serde_json::json!({"height": 6}));
+        assert_ne!(call(&http, "cc_getBalance").await, balance);
+        assert_eq!(call(&http, "cc_getBlockByHeight").await, Value::from(0));
+
+        let stats = http.cache().unwrap().stats();
+        assert_eq!((stats.hits, stats.misses), (3, 3));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_methods_are_relayed() {
+        let upstream = RpcServer::new(RpcServerConfig::default());
+        upstream
+            .register_method(
+                "cc_getBlockByHeight",
+                BlockchainRpcMethods::get_block_handler(),
+            )
+            .unwrap();
+        upstream
+            .register_method("cc_traceBlock", SlowHandler)
+            .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(HttpRpcServer::new(upstream).serve(listener));
+
+        let relay = Relay::new(crate::RelayConfig {
+            timeout: Duration::from_millis(500),
+            ..crate::RelayConfig::new(format!("http://{addr}/"))
+        })
+        .unwrap();
+        let http = http_server(ProtocolCapabilities::default())
+            .with_relay(relay)
+            .with_cache(ResponseCache::new(16));
+        let block = r#"{"jsonrpc":"2.0","method":"cc_getBlockByHeight","params":{"block_number":1},"id":7}"#;
+
+        let (_, body) = post(&http, block).await;
+        let response: JsonRpcResponse = serde_json::from_str(&body).unwrap();
+        assert_eq!(response.id, Some(serde_json::json!(7)));
+        assert!(response.result.is_some());
+        // Served from the cache the second time
+        let (_, cached) = post(&http, block).await;
+        assert_eq!(cached, body);
+
+        // Local methods stay local; upstream errors and timeouts come back
+        let (_, body) = post(&http, r#"{"jsonrpc":"2.0","method":"ping","id":1}"#).await;
+        assert!(body.contains("ok"));
+        let (_, body) = post(&http, r#"{"jsonrpc":"2.0","method":"missing","id":1}"#).await;
+        assert!(body.contains("-32601"));
+        let (_, body) = post(
+            &http,
+            r#"{"jsonrpc":"2.0","method":"cc_traceBlock","id":1}"#,
+        )
+        .await;
+        assert!(body.contains(&RpcErrorCode::RequestTimeout.code().to_string()));
+
+        let stats = http.relay().unwrap().stats();
+        assert_eq!((stats.forwarded, stats.timed_out), (2, 1));
+        let window = Duration::from_secs(60);
+        let metrics = http
+            .monitor()
+            .get_method_metrics("cc_getBlockByHeight", window)
+            .unwrap();
+        assert_eq!(metrics.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_health_probes() {
+        struct Syncing;
+
+        impl NodeState for Syncing {
+            fn is_syncing(&self) -> bool {
+                true
+            }
+
+            fn halted(&self) -> Option<String> {
+                None
+            }
+        }
+
+        let probe = |http: HttpRpcServer, path: &'static str| async move {
+            let request = Request::get(path).body(Body::empty()).unwrap();
+            let response = http.router().oneshot(request).await.unwrap();
+            assert_eq!(response.headers()[header::CACHE_CONTROL], "no-store");
+            let status = response.status();
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            (status, serde_json::from_slice::<Value>(&body).unwrap())
+        };
+
+        let http = http_server(ProtocolCapabilities::default());
+        let (status, body) = probe(http.clone(), "/health").await;
+        assert_eq!(
+            (status, body["status"].as_str()),
+            (StatusCode::OK, Some("healthy"))
+        );
+        assert_eq!(probe(http.clone(), "/ready").await.0, StatusCode::OK);
+
+        let http = http.with_node_state(Arc::new(Syncing));
+        assert_eq!(probe(http.clone(), "/health").await.0, StatusCode::OK);
+        let (status, body) = probe(http, "/ready").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["reasons"][0], "node is syncing");
+    }
+}
@@ -0,0 +1,360 @@
+//! IPC transport over a Unix domain socket
+//!
+//! Each request is one line of JSON, and each response or subscription
+//! notification is written back as one line. No network port is opened:
+//! who may connect is decided by the socket file's permissions.
+
+use crate::http::HttpRpcServer;
+use crate::rate_limit::ClientId;
+use crate::subscriptions::Connection;
+use rpc_errors::RpcErrorCode;
+use std::fs::{self, Permissions};
+use std::io;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::unix::OwnedWriteHalf;
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::mpsc;
+
+/// Socket permissions by default: only the owning user may connect
+pub const DEFAULT_SOCKET_MODE: u32 = 0o600;
+
+/// Responses queued for the writer; when full the reader stops reading
+/// requests until the client catches up
+const OUTBOUND_CAPACITY: usize = 64;
+
+/// JSON-RPC over a Unix domain socket
+///
+/// Serves the same calls, subscriptions and limits as the wrapped
+/// `HttpRpcServer`. Rate limits are charged to the connecting process's
+/// user id.
+#[derive(Clone)]
+pub struct IpcRpcServer {
+    http: HttpRpcServer,
+    path: PathBuf,
+    mode: u32,
+}
+
+impl IpcRpcServer {
+    /// Serve `http`'s methods on the socket at `path`
+    pub fn new(http: HttpRpcServer, path: impl Into<PathBuf>) -> Self {
+        Self {
+            http,
+            path: path.into(),
+            mode: DEFAULT_SOCKET_MODE,
+        }
+    }
+
+    /// Set the socket file's permission bits, e.g. `0o660` to admit the group
+    pub fn with_mode(mut self, mode: u32) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Path of the socket file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Permission bits applied to the socket file
+    pub fn mode(&self) -> u32 {
+        self.mode
+    }
+
+    /// Create the socket file with the configured permissions
+    ///
+    /// The socket is bound under a temporary name and renamed into place
+    /// once its permissions are set, so it is never reachable with looser
+    /// ones. A stale socket left by an earlier run is replaced; a socket
+    /// still being served, or any other file, is an error.
+    pub fn bind(&self) -> io::Result<UnixListener> {
+        self.check_existing()?;
+        let file_name = self.path.file_name().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "IPC path has no file name")
+        })?;
+        let temp = self.path.with_file_name(format!(
+            ".{}.{}.tmp",
+            file_name.to_string_lossy(),
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&temp);
+
+        let listener = UnixListener::bind(&temp)?;
+        let placed = fs::set_permissions(&temp, Permissions::from_mode(self.mode))
+            .and_then(|_| fs::rename(&temp, &self.path));
+        if let Err(error) = placed {
+            let _ = fs::remove_file(&temp);
+            return Err(error);
+        }
+        Ok(listener)
+    }
+
+    /// Bind the socket and serve until the listener fails, removing the
+    /// socket file afterwards
+    pub async fn start(self) -> io::Result<()> {
+        let listener = self.bind()?;
+        let _socket = SocketFile(self.path.clone());
+        self.serve(listener).await
+    }
+
+    /// Serve connections accepted on `listener`
+    pub async fn serve(self, listener: UnixListener) -> io::Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let client = stream
+                .peer_cred()
+                .map(|credentials| ClientId::Local(credentials.uid()))
+                .unwrap_or(ClientId::Unknown);
+            tokio::spawn(run_connection(self.http.clone(), client, stream));
+        }
+    }
+
+    fn check_existing(&self) -> io::Result<()> {
+        let metadata = match fs::symlink_metadata(&self.path) {
+            Ok(metadata) => metadata,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(error),
+        };
+        if !metadata.file_type().is_socket() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket", self.path.display()),
+            ));
+        }
+        match std::os::unix::net::UnixStream::connect(&self.path) {
+            Ok(_) => Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("{} is already being served", self.path.display()),
+            )),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+/// Removes the socket file when the server stops
+struct SocketFile(PathBuf);
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Serve JSON-RPC lines on a connection until either side closes it
+async fn run_connection(http: HttpRpcServer, client: ClientId, stream: UnixStream) {
+    let (reader, writer) = stream.into_split();
+    let (connection, notifications) = http.subscriptions().connect();
+    let (outbound, outbound_rx) = mpsc::channel(OUTBOUND_CAPACITY);
+    let mut writer_task = tokio::spawn(write_loop(
+        writer,
+        outbound_rx,
+        notifications,
+        connection.clone(),
+    ));
+
+    let max_request_size = http.capabilities().max_request_size;
+    let mut reader = BufReader::new(reader);
+    let mut writer_finished = false;
+    loop {
+        let line = tokio::select! {
+            line = read_line(&mut reader, max_request_size) => line,
+            _ = &mut writer_task => {
+                writer_finished = true;
+                break;
+            }
+        };
+        let (response, close) = match line {
+            Ok(Some(line)) if line.trim_ascii().is_empty() => continue,
+            Ok(Some(line)) => (
+                http.process(&line, &client, Some(&connection), None).await,
+                false,
+            ),
+            Err(error) if error.kind() == io::ErrorKind::InvalidData => {
+                let response = http.server().create_error_response(
+                    None,
+                    RpcErrorCode::InvalidRequest.code(),
+                    "Invalid Request".to_string(),
+                    Some(serde_json::json!({
+                        "reason": format!("Request exceeds {} bytes", max_request_size),
+                    })),
+                );
+                (Some(response), true)
+            }
+            Ok(None) | Err(_) => break,
+        };
+        if let Some(response) = response {
+            if outbound.send(response).await.is_err() {
+                break;
+            }
+        }
+        if close {
+            break;
+        }
+    }
+
+    http.subscriptions().disconnect(&connection);
+    drop(outbound);
+    if !writer_finished {
+        let _ = writer_task.await;
+    }
+}
+
+/// Read one request line without its newline; `None` means the client
+/// closed the connection, and `InvalidData` that the line is too long
+async fn read_line<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    max_size: usize,
+) -> io::Result<Option<Vec<u8>>> {
+    let mut line = Vec::new();
+    let limit = (max_size as u64).saturating_add(1);
+    if reader.take(limit).read_until(b'\n', &mut line).await? == 0 {
+        return Ok(None);
+    }
+    if line.last() == Some(&b'\n') {
+        line.pop();
+    } else if line.len() > max_size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "request too large",
+        ));
+    }
+    Ok(Some(line))
+}
+
+/// Write responses and notifications until the reader finishes or the
+/// connection falls too far behind its subscriptions
+async fn write_loop(
+    mut writer: OwnedWriteHalf,
+    mut outbound: mpsc::Receiver<String>,
+    mut notifications: mpsc::Receiver<String>,
+    connection: Arc<Connection>,
+) {
+    loop {
+        let message = tokio::select! {
+            biased;
+            message = outbound.recv() => match message {
+                Some(message) => message,
+                None => break,
+            },
+            Some(notification) = notifications.recv() => {
+                if connection.is_overflowed() {
+                    break;
+                }
+                notification
+            }
+        };
+        let mut line = message.into_bytes();
+        line.push(b'\n');
+        if writer.write_all(&line).await.is_err() {
+            break;
+        }
+    }
+    let _ = writer.shutdown().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlockchainRpcMethods, RpcServer, RpcServerConfig};
+    use rpc_protocol::ProtocolCapabilities;
+    use serde_json::{json, Value};
+    use tokio::io::{BufReader, Lines};
+    use tokio::net::unix::OwnedReadHalf;
+
+    fn ipc_server(path: &Path) -> IpcRpcServer {
+        let server = RpcServer::new(RpcServerConfig::default());
+        server
+            .register_method("ping", BlockchainRpcMethods::ping_handler())
+            .unwrap();
+        let capabilities = ProtocolCapabilities {
+            max_request_size: 1024,
+            ..ProtocolCapabilities::default()
+        };
+        IpcRpcServer::new(
+            HttpRpcServer::new(server).with_capabilities(capabilities),
+            path,
+        )
+    }
+
+    async fn call(
+        lines: &mut Lines<BufReader<OwnedReadHalf>>,
+        writer: &mut OwnedWriteHalf,
+        request: &str,
+    ) -> Value {
+        writer
+            .write_all(format!("{}\n", request).as_bytes())
+            .await
+            .unwrap();
+        serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_socket_file_permissions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cc.ipc");
+
+        let listener = ipc_server(&path).bind().unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, DEFAULT_SOCKET_MODE);
+        let error = ipc_server(&path).bind().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AddrInUse);
+
+        // The socket left behind by a stopped server is replaced
+        drop(listener);
+        let _listener = ipc_server(&path).with_mode(0o660).bind().unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o660);
+
+        let file = dir.path().join("config.toml");
+        fs::write(&file, "").unwrap();
+        let error = ipc_server(&file).bind().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+    }
+
+    #[tokio::test]
+    async fn test_calls_and_subscriptions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cc.ipc");
+        let ipc = ipc_server(&path);
+        let hub = ipc.http.subscriptions().clone();
+        let server = tokio::spawn(ipc.start());
+        while !path.exists() {
+            tokio::task::yield_now().await;
+        }
+
+        let (reader, mut writer) = UnixStream::connect(&path).await.unwrap().into_split();
+        let mut lines = BufReader::new(reader).lines();
+        let response = call(
+            &mut lines,
+            &mut writer,
+            r#"{"jsonrpc":"2.0","method":"ping","id":1}"#,
+        )
+        .await;
+        assert_eq!(response["result"]["status"], "ok");
+
+        let response = call(
+            &mut lines,
+            &mut writer,
+            r#"{"jsonrpc":"2.0","method":"cc_subscribe","params":["newHeads"],"id":2}"#,
+        )
+        .await;
+        let id = response["result"].as_str().unwrap().to_string();
+        assert_eq!(hub.publish_new_head(&json!({"height": 7})), 1);
+        let notification: Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(notification["params"]["subscription"], id);
+        assert_eq!(notification["params"]["result"]["height"], 7);
+
+        // An oversized request is answered with an error and the connection closed
+        let response = call(&mut lines, &mut writer, &"x".repeat(2048)).await;
+        assert_eq!(response["error"]["code"], -32600);
+        assert!(lines.next_line().await.unwrap().is_none());
+        assert_eq!(hub.subscription_count(), 0);
+
+        server.abort();
+        let _ = server.await;
+        assert!(!path.exists());
+    }
+}
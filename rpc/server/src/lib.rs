@@ -3,11 +3,41 @@
 //! This module provides a comprehensive RPC server for handling blockchain operations,
 //! including transaction processing, block queries, and smart contract interactions.
 
+use rpc_errors::RpcErrorCode;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use thiserror::Error;
 
+pub mod admin;
+pub mod cache;
+pub mod deadline;
+pub mod filters;
+pub mod grpc;
+pub mod health;
+pub mod http;
+#[cfg(unix)]
+pub mod ipc;
+pub mod rate_limit;
+pub mod relay;
+pub mod router;
+pub mod subscriptions;
+mod ws;
+
+pub use admin::{AdminBackend, AdminRpcServer};
+pub use cache::{CachePolicy, CacheStats, ResponseCache};
+pub use deadline::CancellationToken;
+pub use filters::{FilterConfig, FilterRegistry};
+pub use grpc::GrpcGateway;
+pub use health::NodeState;
+pub use http::HttpRpcServer;
+#[cfg(unix)]
+pub use ipc::IpcRpcServer;
+pub use rate_limit::{ClientId, MethodRateLimiter};
+pub use relay::{Relay, RelayConfig};
+pub use router::RpcRouter;
+pub use subscriptions::{SubscriptionHub, SubscriptionKind};
+
 /// RPC server error types
 #[derive(Error, Debug)]
 pub enum RpcError {
@@ -31,10 +61,60 @@ pub enum RpcError {
     
     #[error("Rate limit exceeded")]
     RateLimitExceeded,
+    
+    #[error("Deadline exceeded")]
+    DeadlineExceeded,
+
+    /// Error from the catalog, sent with its code and data unchanged
+    #[error(transparent)]
+    Catalog(#[from] rpc_errors::RpcError),
 }
 
 pub type Result<T> = std::result::Result<T, RpcError>;
 
+impl RpcError {
+    /// JSON-RPC error code for this error
+    pub fn code(&self) -> i32 {
+        match self {
+            RpcError::InvalidRequest(_) => RpcErrorCode::InvalidRequest.code(),
+            RpcError::MethodNotFound(_) => RpcErrorCode::MethodNotFound.code(),
+            RpcError::InvalidParams(_) => RpcErrorCode::InvalidParams.code(),
+            RpcError::InternalError(_) => RpcErrorCode::InternalError.code(),
+            RpcError::ServiceUnavailable(_) => RpcErrorCode::ServiceUnavailable.code(),
+            RpcError::AuthenticationFailed => RpcErrorCode::Unauthorized.code(),
+            RpcError::RateLimitExceeded => RpcErrorCode::RateLimitExceeded.code(),
+            RpcError::DeadlineExceeded => RpcErrorCode::DeadlineExceeded.code(),
+            RpcError::Catalog(error) => error.code,
+        }
+    }
+}
+
+impl From<RpcError> for JsonRpcError {
+    fn from(error: RpcError) -> Self {
+        let code = error.code();
+        let message = match error {
+            RpcError::InvalidRequest(msg)
+            | RpcError::MethodNotFound(msg)
+            | RpcError::InvalidParams(msg)
+            | RpcError::InternalError(msg)
+            | RpcError::ServiceUnavailable(msg) => msg,
+            RpcError::Catalog(error) => return error.into(),
+            other => other.to_string(),
+        };
+        Self { code, message, data: None }
+    }
+}
+
+impl From<rpc_errors::RpcError> for JsonRpcError {
+    fn from(error: rpc_errors::RpcError) -> Self {
+        Self {
+            code: error.code,
+            message: error.message,
+            data: error.data,
+        }
+    }
+}
+
 /// JSON-RPC 2.0 request structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsonRpcRequest {
@@ -88,6 +168,19 @@ pub trait RpcMethodHandler: Send + Sync {
     /// Handle an RPC method call
     fn handle(&self, params: Option<serde_json::Value>) -> Result<serde_json::Value>;
     
+    /// Handle an RPC method call that must stop once `token` is cancelled
+    ///
+    /// Long-running handlers override this and call `token.check()?` as
+    /// they work; the default checks once before calling `handle`.
+    fn handle_with_token(
+        &self,
+        params: Option<serde_json::Value>,
+        token: &CancellationToken,
+    ) -> Result<serde_json::Value> {
+        token.check()?;
+        self.handle(params)
+    }
+    
     /// Get method description
     fn description(&self) -> &str;
     
@@ -170,7 +263,24 @@ impl RpcServer {
     }
     
     /// Register an RPC method handler
+    ///
+    /// Names in the `admin_` namespace are refused; those methods are only
+    /// served by an `AdminRpcServer`.
     pub fn register_method<H>(&self, method_name: &str, handler: H) -> Result<()>
+    where
+        H: RpcMethodHandler + 'static,
+    {
+        if method_name.starts_with(admin::ADMIN_NAMESPACE) {
+            return Err(RpcError::InvalidRequest(format!(
+                "Method '{}' is reserved for the admin server",
+                method_name
+            )));
+        }
+        self.insert_method(method_name, handler)
+    }
+    
+    /// Register a handler without checking its namespace
+    fn insert_method<H>(&self, method_name: &str, handler: H) -> Result<()>
     where
         H: RpcMethodHandler + 'static,
     {
@@ -188,13 +298,18 @@ impl RpcServer {
     
     /// Handle a JSON-RPC request
     pub fn handle_request(&self, request: &str) -> String {
+        self.handle_request_with_token(request, &CancellationToken::new())
+    }
+    
+    /// Handle a JSON-RPC request, passing `token` to the method handler
+    pub fn handle_request_with_token(&self, request: &str, token: &CancellationToken) -> String {
         // Parse the request
         let parsed_request: JsonRpcRequest = match serde_json::from_str(request) {
             Ok(req) => req,
             Err(_) => {
                 return self.create_error_response(
                     None,
-                    -32700,
+                    RpcErrorCode::ParseError.code(),
                     "Parse error".to_string(),
                     None,
                 );
@@ -205,7 +320,7 @@ impl RpcServer {
         if parsed_request.jsonrpc != "2.0" {
             return self.create_error_response(
                 parsed_request.id,
-                -32600,
+                RpcErrorCode::InvalidRequest.code(),
                 "Invalid Request".to_string(),
                 Some(serde_json::json!({"reason": "JSON-RPC version must be 2.0"})),
             );
@@ -222,7 +337,7 @@ impl RpcServer {
         let methods = self.methods.lock().unwrap();
         match methods.get(&parsed_request.method) {
             Some(handler) => {
-                match handler.handle(parsed_request.params) {
+                match handler.handle_with_token(parsed_request.params, token) {
                     Ok(result) => {
                         // Update success statistics
                         {
@@ -239,17 +354,8 @@ impl RpcServer {
                             stats.failed_requests += 1;
                         }
                         
-                        let (code, message) = match error {
-                            RpcError::InvalidParams(msg) => (-32602, msg),
-                            RpcError::MethodNotFound(msg) => (-32601, msg),
-                            RpcError::InternalError(msg) => (-32603, msg),
-                            RpcError::ServiceUnavailable(msg) => (-32000, msg),
-                            RpcError::AuthenticationFailed => (-32001, "Authentication failed".to_string()),
-                            RpcError::RateLimitExceeded => (-32002, "Rate limit exceeded".to_string()),
-                            _ => (-32603, "Internal error".to_string()),
-                        };
-                        
-                        self.create_error_response(parsed_request.id, code, message, None)
+                        let error = JsonRpcError::from(error);
+                        self.create_error_response(parsed_request.id, error.code, error.message, error.data)
                     }
                 }
             }
@@ -262,7 +368,7 @@ impl RpcServer {
                 
                 self.create_error_response(
                     parsed_request.id,
-                    -32601,
+                    RpcErrorCode::MethodNotFound.code(),
                     format!("Method not found: {}", parsed_request.method),
                     None,
                 )
@@ -276,6 +382,11 @@ impl RpcServer {
         methods.keys().cloned().collect()
     }
     
+    /// Check whether a method is registered
+    pub fn has_method(&self, method_name: &str) -> bool {
+        self.methods.lock().unwrap().contains_key(method_name)
+    }
+    
     /// Get server statistics
     pub fn get_stats(&self) -> ServerStats {
         let stats = self.stats.lock().unwrap();
@@ -302,10 +413,19 @@ impl RpcServer {
         };
         
         serde_json::to_string(&response).unwrap_or_else(|_| {
-            self.create_error_response(id, -32603, "Internal error serializing response".to_string(), None)
+            self.create_error_response(id, RpcErrorCode::InternalError.code(), "Internal error serializing response".to_string(), None)
         })
     }
     
+    /// Create an error response for an error from the catalog
+    fn create_catalog_error_response(
+        &self,
+        id: Option<serde_json::Value>,
+        error: rpc_errors::RpcError,
+    ) -> String {
+        self.create_error_response(id, error.code, error.message, error.data)
+    }
+    
     /// Create an error response
     fn create_error_response(
         &self,
@@ -375,6 +495,11 @@ impl BlockchainRpcMethods {
     pub fn get_balance_handler() -> impl RpcMethodHandler {
         GetBalanceHandler
     }
+    
+    /// Create a get block range handler
+    pub fn get_block_range_handler() -> impl RpcMethodHandler {
+        GetBlockRangeHandler
+    }
 }
 
 /// Simple ping handler
@@ -497,6 +622,57 @@ impl RpcMethodHandler for GetBalanceHandler {
     }
 }
 
+/// Largest span of blocks returned by one range query
+pub const MAX_BLOCK_RANGE: u64 = 10_000;
+
+/// Get block range handler
+struct GetBlockRangeHandler;
+
+impl RpcMethodHandler for GetBlockRangeHandler {
+    fn handle(&self, params: Option<serde_json::Value>) -> Result<serde_json::Value> {
+        self.handle_with_token(params, &CancellationToken::new())
+    }
+    
+    fn handle_with_token(
+        &self,
+        params: Option<serde_json::Value>,
+        token: &CancellationToken,
+    ) -> Result<serde_json::Value> {
+        let params = params.ok_or_else(|| RpcError::InvalidParams("Missing parameters".to_string()))?;
+        let bound = |name: &str| {
+            params.get(name)
+                .and_then(|v| v.as_u64())
+                .ok_or_else(|| RpcError::InvalidParams(format!("Invalid '{}' parameter", name)))
+        };
+        let (from, to) = (bound("from")?, bound("to")?);
+        if to < from || to - from >= MAX_BLOCK_RANGE {
+            return Err(RpcError::InvalidParams(format!(
+                "Range must be ascending and span at most {} blocks",
+                MAX_BLOCK_RANGE
+            )));
+        }
+        
+        // Scan block by block so a cancelled call stops promptly
+        let mut blocks = Vec::new();
+        for number in from..=to {
+            token.check()?;
+            blocks.push(serde_json::json!({
+                "number": number,
+                "hash": format!("0x{:064x}", number),
+            }));
+        }
+        Ok(serde_json::Value::Array(blocks))
+    }
+    
+    fn description(&self) -> &str {
+        "Get summaries of the blocks in an inclusive height range"
+    }
+    
+    fn param_schema(&self) -> Option<&str> {
+        Some(r#"{"from": "integer", "to": "integer"}"#)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -585,4 +761,45 @@ mod tests {
         assert_eq!(stats.total_requests, 1);
         assert_eq!(stats.successful_requests, 1);
     }
+    
+    #[test]
+    fn test_catalog_errors_keep_their_data() {
+        struct MissingBlock;
+        
+        impl RpcMethodHandler for MissingBlock {
+            fn handle(&self, _params: Option<serde_json::Value>) -> Result<serde_json::Value> {
+                Err(rpc_errors::RpcError::block_not_found("0x7").into())
+            }
+            
+            fn description(&self) -> &str {
+                "Always missing"
+            }
+        }
+        
+        let server = RpcServer::new(RpcServerConfig::default());
+        server.register_method("get_block", MissingBlock).unwrap();
+        
+        let response = server.handle_request(r#"{"jsonrpc": "2.0", "method": "get_block", "id": 1}"#);
+        let error = serde_json::from_str::<JsonRpcResponse>(&response).unwrap().error.unwrap();
+        assert_eq!(error.code, RpcErrorCode::BlockNotFound.code());
+        assert_eq!(error.data.unwrap()["identifier"], "0x7");
+        assert_eq!(RpcError::AuthenticationFailed.code(), RpcErrorCode::Unauthorized.code());
+    }
+    
+    #[test]
+    fn test_block_range_stops_when_cancelled() {
+        let server = RpcServer::new(RpcServerConfig::default());
+        server.register_method("get_block_range", BlockchainRpcMethods::get_block_range_handler()).unwrap();
+        let request = r#"{"jsonrpc": "2.0", "method": "get_block_range", "params": {"from": 5, "to": 9}, "id": 1}"#;
+        
+        let response: JsonRpcResponse = serde_json::from_str(&server.handle_request(request)).unwrap();
+        assert_eq!(response.result.unwrap().as_array().unwrap().len(), 5);
+        
+        let token = CancellationToken::new();
+        token.cancel();
+        let response = server.handle_request_with_token(request, &token);
+        let error = serde_json::from_str::<JsonRpcResponse>(&response).unwrap().error.unwrap();
+        assert_eq!(error.code, RpcErrorCode::DeadlineExceeded.code());
+        assert_eq!(server.get_stats().failed_requests, 1);
+    }
 }
@@ -0,0 +1,272 @@
+//! Per-method rate limiting keyed by client identity
+
+use crate::admin::constant_time_eq;
+use crate::RpcServerConfig;
+use axum::http::HeaderMap;
+use rpc_protocol::{RateLimit, RpcProtocol};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Header carrying the client's API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Buckets tracked before idle ones are pruned
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Who a request is counted against
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ClientId {
+    ApiKey(String),
+    Ip(IpAddr),
+    /// A local process connected over IPC, by user id
+    Local(u32),
+    Unknown,
+}
+
+impl ClientId {
+    /// Identify a client by the server's API key if it presented it,
+    /// otherwise by IP address
+    ///
+    /// Only the configured key is trusted, so clients cannot dodge their
+    /// IP's limits by sending made-up keys.
+    pub fn from_request(
+        headers: &HeaderMap,
+        addr: Option<SocketAddr>,
+        config: &RpcServerConfig,
+    ) -> Self {
+        let key = headers
+            .get(API_KEY_HEADER)
+            .and_then(|key| key.to_str().ok());
+        match (key, &config.api_key, addr) {
+            (Some(key), Some(expected), _) if constant_time_eq(key.as_bytes(), expected.as_bytes()) => {
+                ClientId::ApiKey(key.to_string())
+            }
+            (_, _, Some(addr)) => ClientId::Ip(addr.ip()),
+            _ => ClientId::Unknown,
+        }
+    }
+}
+
+/// How the client appears in metrics; API keys are not shown
+impl fmt::Display for ClientId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientId::ApiKey(_) => write!(f, "api-key"),
+            ClientId::Ip(ip) => write!(f, "{ip}"),
+            ClientId::Local(uid) => write!(f, "local:{uid}"),
+            ClientId::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+struct Buckets {
+    buckets: HashMap<(ClientId, String), TokenBucket>,
+    prune_at: usize,
+}
+
+/// Token buckets enforcing each method's `RateLimit` per client
+///
+/// A client may burst up to `burst_size` calls of a method, refilled at
+/// `requests_per_minute`. Methods without a limit are never throttled.
+pub struct MethodRateLimiter {
+    limits: HashMap<String, RateLimit>,
+    state: Mutex<Buckets>,
+}
+
+impl MethodRateLimiter {
+    /// Create a limiter with no limits
+    pub fn new() -> Self {
+        Self {
+            limits: HashMap::new(),
+            state: Mutex::new(Buckets {
+                buckets: HashMap::new(),
+                prune_at: PRUNE_THRESHOLD,
+            }),
+        }
+    }
+
+    /// Enforce the `rate_limit` declared in each method's metadata
+    pub fn from_protocol(protocol: &RpcProtocol) -> Self {
+        let mut limiter = Self::new();
+        for method in protocol.get_supported_methods() {
+            if let Some(limit) = protocol
+                .get_method(&method)
+                .and_then(|metadata| metadata.rate_limit.clone())
+            {
+                limiter.set_limit(&method, limit);
+            }
+        }
+        limiter
+    }
+
+    /// Limit a method
+    pub fn set_limit(&mut self, method: &str, limit: RateLimit) {
+        self.limits.insert(method.to_string(), limit);
+    }
+
+    /// The limit enforced for a method
+    pub fn limit(&self, method: &str) -> Option<&RateLimit> {
+        self.limits.get(method)
+    }
+
+    /// Count a call, or return how long the client must wait before retrying
+    pub fn check(&self, client: &ClientId, method: &str) -> Result<(), Duration> {
+        self.check_at(client, method, Instant::now())
+    }
+
+    /// Count a call made at `now`
+    pub fn check_at(&self, client: &ClientId, method: &str, now: Instant) -> Result<(), Duration> {
+        let Some(limit) = self.limits.get(method) else {
+            return Ok(());
+        };
+        let (capacity, rate) = Self::bucket_shape(limit);
+
+        let mut state = self.state.lock().unwrap();
+        if state.buckets.len() >= state.prune_at {
+            self.prune(&mut state, now);
+        }
+        let bucket = state
+            .buckets
+            .entry((client.clone(), method.to_string()))
+            .or_insert(TokenBucket {
+                tokens: capacity,
+                updated: now,
+            });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity);
+        bucket.updated = bucket.updated.max(now);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else if rate > 0.0 {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+        } else {
+            Err(Duration::from_secs(u64::from(limit.window_seconds.max(1))))
+        }
+    }
+
+    /// Burst capacity and refill rate in tokens per second
+    fn bucket_shape(limit: &RateLimit) -> (f64, f64) {
+        (
+            f64::from(limit.burst_size.max(1)),
+            f64::from(limit.requests_per_minute) / 60.0,
+        )
+    }
+
+    /// Drop buckets that have refilled, which behave like fresh ones
+    fn prune(&self, state: &mut Buckets, now: Instant) {
+        state.buckets.retain(|(_, method), bucket| {
+            let Some(limit) = self.limits.get(method) else {
+                return false;
+            };
+            let (capacity, rate) = Self::bucket_shape(limit);
+            let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+            bucket.tokens + elapsed * rate < capacity
+        });
+        state.prune_at = (state.buckets.len() * 2).max(PRUNE_THRESHOLD);
+    }
+}
+
+impl Default for MethodRateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use rpc_protocol::MethodMetadata;
+
+    fn limit(requests_per_minute: u32, burst_size: u32) -> RateLimit {
+        RateLimit {
+            requests_per_minute,
+            burst_size,
+            window_seconds: 60,
+        }
+    }
+
+    #[test]
+    fn test_token_bucket_per_client() {
+        let mut limiter = MethodRateLimiter::new();
+        limiter.set_limit("cc_sendTransaction", limit(60, 2));
+        let alice = ClientId::Ip("10.0.0.1".parse().unwrap());
+        let bob = ClientId::Ip("10.0.0.2".parse().unwrap());
+        let start = Instant::now();
+
+        assert!(limiter
+            .check_at(&alice, "cc_sendTransaction", start)
+            .is_ok());
+        assert!(limiter
+            .check_at(&alice, "cc_sendTransaction", start)
+            .is_ok());
+        let retry_after = limiter
+            .check_at(&alice, "cc_sendTransaction", start)
+            .unwrap_err();
+        assert!(retry_after > Duration::from_millis(900) && retry_after <= Duration::from_secs(1));
+
+        // Other clients and unlimited methods are unaffected
+        assert!(limiter.check_at(&bob, "cc_sendTransaction", start).is_ok());
+        for _ in 0..10 {
+            assert!(limiter.check_at(&alice, "cc_getBalance", start).is_ok());
+        }
+
+        // One token refills per second
+        let later = start + Duration::from_secs(1);
+        assert!(limiter
+            .check_at(&alice, "cc_sendTransaction", later)
+            .is_ok());
+        assert!(limiter
+            .check_at(&alice, "cc_sendTransaction", later)
+            .is_err());
+    }
+
+    #[test]
+    fn test_limits_from_metadata_and_identity() {
+        let mut protocol = RpcProtocol::new();
+        let mut metadata: MethodMetadata =
+            protocol.get_method("cc_getBlockByHeight").unwrap().clone();
+        metadata.name = "test_limited".to_string();
+        metadata.rate_limit = Some(limit(0, 1));
+        protocol.register_method(metadata);
+
+        let limiter = MethodRateLimiter::from_protocol(&protocol);
+        assert_eq!(limiter.limit("test_limited").unwrap().burst_size, 1);
+        assert!(limiter.check(&ClientId::Unknown, "test_limited").is_ok());
+        assert_eq!(
+            limiter.check(&ClientId::Unknown, "test_limited"),
+            Err(Duration::from_secs(60))
+        );
+
+        let config = RpcServerConfig {
+            api_key: Some("secret".to_string()),
+            ..RpcServerConfig::default()
+        };
+        let addr: SocketAddr = "192.168.1.5:4000".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(API_KEY_HEADER, HeaderValue::from_static("secret"));
+        assert_eq!(
+            ClientId::from_request(&headers, Some(addr), &config),
+            ClientId::ApiKey("secret".to_string())
+        );
+        headers.insert(API_KEY_HEADER, HeaderValue::from_static("made-up"));
+        assert_eq!(
+            ClientId::from_request(&headers, Some(addr), &config),
+            ClientId::Ip(addr.ip())
+        );
+        assert_eq!(
+            ClientId::from_request(&headers, None, &config),
+            ClientId::Unknown
+        );
+    }
+}
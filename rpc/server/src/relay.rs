@@ -0,0 +1,300 @@
+//! Relay of methods served by an upstream node
+//!
+//! A node that does not serve some methods itself (typically archive
+//! queries on a pruned node) can forward them to an upstream JSON-RPC
+//! endpoint over HTTP. Calls keep their original id, the upstream answer is
+//! passed back unchanged, and the `admin_` namespace is never forwarded.
+
+use crate::admin::ADMIN_NAMESPACE;
+use axum::body::{Body, Bytes};
+use axum::http::{header, Request, StatusCode, Uri};
+use cc_core_metrics::{MetricSample, MetricsSource};
+use hyper_util::rt::TokioIo;
+use rpc_errors::RpcErrorCode;
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::net::TcpStream;
+
+/// Where and how calls are relayed
+#[derive(Debug, Clone)]
+pub struct RelayConfig {
+    /// Upstream JSON-RPC endpoint, e.g. `http://archive:8001/`
+    pub upstream: String,
+
+    /// Longest a relayed call may take, shortened by the call's own deadline
+    pub timeout: Duration,
+
+    /// Largest upstream response accepted
+    pub max_response_size: usize,
+}
+
+impl RelayConfig {
+    /// Relay to `upstream` with the default limits
+    pub fn new(upstream: impl Into<String>) -> Self {
+        Self {
+            upstream: upstream.into(),
+            timeout: Duration::from_secs(10),
+            max_response_size: 10 * 1024 * 1024,
+        }
+    }
+}
+
+/// Why a call could not be relayed
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum RelayError {
+    #[error("Invalid upstream URL: {0}")]
+    InvalidUpstream(String),
+
+    #[error("Upstream did not answer within {0:?}")]
+    Timeout(Duration),
+
+    #[error("Upstream unavailable: {0}")]
+    Unavailable(String),
+
+    #[error("Invalid upstream response: {0}")]
+    InvalidResponse(String),
+}
+
+impl From<RelayError> for rpc_errors::RpcError {
+    fn from(error: RelayError) -> Self {
+        match error {
+            RelayError::Timeout(timeout) => {
+                rpc_errors::RpcError::request_timeout(timeout.as_secs_f64().ceil() as u64)
+            }
+            error => rpc_errors::RpcError::new(
+                RpcErrorCode::ServiceUnavailable.code(),
+                error.to_string(),
+            ),
+        }
+    }
+}
+
+/// Counts of relayed calls
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RelayStats {
+    pub forwarded: u64,
+    pub failed: u64,
+    pub timed_out: u64,
+    /// Total time spent waiting on the upstream
+    pub latency: Duration,
+}
+
+/// Forwards calls to an upstream node
+pub struct Relay {
+    config: RelayConfig,
+    authority: String,
+    host: String,
+    port: u16,
+    path: String,
+    forwarded: AtomicU64,
+    failed: AtomicU64,
+    timed_out: AtomicU64,
+    latency_micros: AtomicU64,
+}
+
+impl Relay {
+    /// Relay to the configured upstream, which must be a plain `http://` URL
+    pub fn new(config: RelayConfig) -> Result<Self, RelayError> {
+        let invalid =
+            |reason: &str| RelayError::InvalidUpstream(format!("{}: {reason}", config.upstream));
+        let uri: Uri = config.upstream.parse().map_err(|_| invalid("not a URL"))?;
+        if uri.scheme_str() != Some("http") {
+            return Err(invalid("only http:// upstreams are supported"));
+        }
+        let authority = uri.authority().ok_or_else(|| invalid("missing host"))?;
+        let path = uri
+            .path_and_query()
+            .map_or("/", |path| path.as_str())
+            .to_string();
+
+        Ok(Self {
+            authority: authority.to_string(),
+            host: authority.host().to_string(),
+            port: authority.port_u16().unwrap_or(80),
+            path,
+            forwarded: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+            timed_out: AtomicU64::new(0),
+            latency_micros: AtomicU64::new(0),
+            config,
+        })
+    }
+
+    /// Limits in force
+    pub fn config(&self) -> &RelayConfig {
+        &self.config
+    }
+
+    /// Whether `method` may be forwarded at all
+    pub fn relays(&self, method: &str) -> bool {
+        !method.starts_with(ADMIN_NAMESPACE)
+    }
+
+    /// Send one JSON-RPC call upstream and return its response body
+    ///
+    /// The call gets at most `timeout`, or the relay's own timeout if that
+    /// is shorter; `RelayError::Timeout` carries the limit that applied.
+    /// Notifications come back as an empty body.
+    pub async fn forward(&self, request: &str, timeout: Duration) -> Result<String, RelayError> {
+        let limit = timeout.min(self.config.timeout);
+        let started = Instant::now();
+        let outcome = tokio::time::timeout(limit, self.send(request.to_string())).await;
+        self.latency_micros
+            .fetch_add(started.elapsed().as_micros() as u64, Ordering::Relaxed);
+
+        let counter = match &outcome {
+            Ok(Ok(_)) => &self.forwarded,
+            Ok(Err(_)) => &self.failed,
+            Err(_) => &self.timed_out,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        outcome.unwrap_or(Err(RelayError::Timeout(limit)))
+    }
+
+    async fn send(&self, request: String) -> Result<String, RelayError> {
+        let unavailable =
+            |error: &dyn std::fmt::Display| RelayError::Unavailable(error.to_string());
+
+        let stream = TcpStream::connect((self.host.as_str(), self.port))
+            .await
+            .map_err(|error| unavailable(&error))?;
+        let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+            .await
+            .map_err(|error| unavailable(&error))?;
+        tokio::spawn(connection);
+
+        let request = Request::post(self.path.as_str())
+            .header(header::HOST, self.authority.as_str())
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(request))
+            .map_err(|error| unavailable(&error))?;
+        let response = sender
+            .send_request(request)
+            .await
+            .map_err(|error| unavailable(&error))?;
+
+        match response.status() {
+            StatusCode::OK => {}
+            StatusCode::NO_CONTENT => return Ok(String::new()),
+            status => {
+                return Err(RelayError::Unavailable(format!(
+                    "upstream returned {status}"
+                )))
+            }
+        }
+        let body: Bytes = axum::body::to_bytes(
+            Body::new(response.into_body()),
+            self.config.max_response_size,
+        )
+        .await
+        .map_err(|error| RelayError::InvalidResponse(error.to_string()))?;
+        match serde_json::from_slice::<Value>(&body) {
+            Ok(Value::Object(_)) => String::from_utf8(body.to_vec())
+                .map_err(|error| RelayError::InvalidResponse(error.to_string())),
+            _ => Err(RelayError::InvalidResponse(
+                "expected a JSON-RPC response object".to_string(),
+            )),
+        }
+    }
+
+    /// Current counts of relayed calls
+    pub fn stats(&self) -> RelayStats {
+        RelayStats {
+            forwarded: self.forwarded.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            timed_out: self.timed_out.load(Ordering::Relaxed),
+            latency: Duration::from_micros(self.latency_micros.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl MetricsSource for Relay {
+    fn source_name(&self) -> &str {
+        "rpc_relay"
+    }
+
+    fn collect(&self) -> Vec<MetricSample> {
+        let stats = self.stats();
+        let upstream = self.config.upstream.as_str();
+        vec![
+            MetricSample::counter(
+                "cc_rpc_relay_forwarded_total",
+                "Calls answered by the upstream node",
+                stats.forwarded as f64,
+            )
+            .with_label("upstream", upstream),
+            MetricSample::counter(
+                "cc_rpc_relay_failures_total",
+                "Relayed calls the upstream could not answer",
+                stats.failed as f64,
+            )
+            .with_label("upstream", upstream),
+            MetricSample::counter(
+                "cc_rpc_relay_timeouts_total",
+                "Relayed calls that timed out",
+                stats.timed_out as f64,
+            )
+            .with_label("upstream", upstream),
+            MetricSample::counter(
+                "cc_rpc_relay_latency_seconds_total",
+                "Time spent waiting on the upstream node",
+                stats.latency.as_secs_f64(),
+            )
+            .with_label("upstream", upstream),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upstream_urls() {
+        let relay = Relay::new(RelayConfig::new("http://archive:9000/rpc")).unwrap();
+        assert_eq!(
+            (relay.host.as_str(), relay.port, relay.path.as_str()),
+            ("archive", 9000, "/rpc")
+        );
+        let relay = Relay::new(RelayConfig::new("http://127.0.0.1")).unwrap();
+        assert_eq!((relay.port, relay.path.as_str()), (80, "/"));
+        assert!(relay.relays("cc_getArchiveBlock"));
+        assert!(!relay.relays("admin_peers"));
+
+        for invalid in ["https://archive:9000", "archive:9000", "not a url"] {
+            assert!(
+                matches!(
+                    Relay::new(RelayConfig::new(invalid)),
+                    Err(RelayError::InvalidUpstream(_))
+                ),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_upstream_is_counted() {
+        // Bind and drop a listener to get a port nothing listens on
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let relay = Relay::new(RelayConfig::new(format!("http://{addr}/"))).unwrap();
+        let error = relay
+            .forward(
+                r#"{"jsonrpc":"2.0","method":"x","id":1}"#,
+                Duration::from_secs(5),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(error, RelayError::Unavailable(_)));
+        let stats = relay.stats();
+        assert_eq!((stats.forwarded, stats.failed, stats.timed_out), (0, 1, 0));
+        assert_eq!(
+            rpc_errors::RpcError::from(error).code,
+            RpcErrorCode::ServiceUnavailable.code()
+        );
+    }
+}
@@ -0,0 +1,297 @@
+//! Method dispatch driven by protocol metadata
+
+use crate::{JsonRpcError, JsonRpcRequest, JsonRpcResponse, Result, RpcError};
+use rpc_errors::RpcErrorCode;
+use rpc_protocol::{MethodMetadata, ProtocolError, RpcProtocol};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Boxed future returned by router handlers
+pub type HandlerFuture = Pin<Box<dyn Future<Output = Result<Value>> + Send>>;
+
+type Handler = Arc<dyn Fn(Option<Value>) -> HandlerFuture + Send + Sync>;
+
+/// Async method registry where every handler is described by its `MethodMetadata`
+///
+/// Calls are checked against the metadata with
+/// `RpcProtocol::validate_method_call` before the handler runs, so handlers
+/// only see parameters that passed the declared requirements and rules.
+/// Validation and handler errors become standard JSON-RPC error objects.
+pub struct RpcRouter {
+    protocol: RpcProtocol,
+    handlers: HashMap<String, Handler>,
+}
+
+impl RpcRouter {
+    /// Create an empty router
+    pub fn new() -> Self {
+        Self::with_protocol(RpcProtocol::new())
+    }
+
+    /// Create a router validating against an existing protocol definition
+    pub fn with_protocol(protocol: RpcProtocol) -> Self {
+        Self {
+            protocol,
+            handlers: HashMap::new(),
+        }
+    }
+
+    /// Register a handler together with the metadata describing it
+    pub fn register<F, Fut>(&mut self, metadata: MethodMetadata, handler: F) -> Result<()>
+    where
+        F: Fn(Option<Value>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Value>> + Send + 'static,
+    {
+        if self.handlers.contains_key(&metadata.name) {
+            return Err(RpcError::InvalidRequest(format!(
+                "Method '{}' already registered",
+                metadata.name
+            )));
+        }
+
+        let handler: Handler = Arc::new(move |params| Box::pin(handler(params)));
+        self.handlers.insert(metadata.name.clone(), handler);
+        self.protocol.register_method(metadata);
+        Ok(())
+    }
+
+    /// Metadata of a routed method
+    pub fn metadata(&self, method: &str) -> Option<&MethodMetadata> {
+        self.handlers
+            .contains_key(method)
+            .then(|| self.protocol.get_method(method))
+            .flatten()
+    }
+
+    /// Names of the routed methods, sorted
+    pub fn methods(&self) -> Vec<String> {
+        let mut methods: Vec<String> = self.handlers.keys().cloned().collect();
+        methods.sort();
+        methods
+    }
+
+    /// The protocol definition calls are validated against
+    pub fn protocol(&self) -> &RpcProtocol {
+        &self.protocol
+    }
+
+    /// Validate and run a parsed request
+    pub async fn dispatch(&self, request: JsonRpcRequest) -> JsonRpcResponse {
+        let id = request.id.clone();
+        match self.call(request).await {
+            Ok(result) => JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                result: Some(result),
+                error: None,
+                id,
+            },
+            Err(error) => JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                result: None,
+                error: Some(error),
+                id,
+            },
+        }
+    }
+
+    /// Parse, validate and run a JSON-RPC request, returning the serialized response
+    pub async fn handle_request(&self, request: &str) -> String {
+        let response = match serde_json::from_str::<JsonRpcRequest>(request) {
+            Ok(request) => self.dispatch(request).await,
+            Err(_) => JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                result: None,
+                error: Some(JsonRpcError {
+                    code: RpcErrorCode::ParseError.code(),
+                    message: "Parse error".to_string(),
+                    data: None,
+                }),
+                id: None,
+            },
+        };
+        serde_json::to_string(&response).unwrap_or_else(|_| {
+            r#"{"jsonrpc": "2.0", "error": {"code": -32603, "message": "Internal error"}, "id": null}"#.to_string()
+        })
+    }
+
+    async fn call(&self, request: JsonRpcRequest) -> std::result::Result<Value, JsonRpcError> {
+        if request.jsonrpc != "2.0" {
+            return Err(JsonRpcError {
+                code: RpcErrorCode::InvalidRequest.code(),
+                message: "Invalid Request".to_string(),
+                data: Some(serde_json::json!({"reason": "JSON-RPC version must be 2.0"})),
+            });
+        }
+
+        let handler = self
+            .handlers
+            .get(&request.method)
+            .ok_or_else(|| {
+                RpcError::MethodNotFound(format!("Method not found: {}", request.method))
+            })?
+            .clone();
+        self.protocol
+            .validate_method_call(&request.method, request.params.as_ref())
+            .map_err(protocol_error)?;
+
+        Ok(handler(request.params).await?)
+    }
+}
+
+impl Default for RpcRouter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Map a protocol validation failure to its JSON-RPC error
+fn protocol_error(error: ProtocolError) -> JsonRpcError {
+    let code = match &error {
+        ProtocolError::UnsupportedMethod(_) => RpcErrorCode::MethodNotFound,
+        ProtocolError::InvalidMessageFormat(_) => RpcErrorCode::InvalidParams,
+        ProtocolError::AuthenticationRequired => RpcErrorCode::Unauthorized,
+        ProtocolError::RateLimitExceeded(_) => RpcErrorCode::RateLimitExceeded,
+        ProtocolError::VersionMismatch { .. } => RpcErrorCode::InvalidRequest,
+        ProtocolError::NegotiationFailed(_) => RpcErrorCode::NegotiationFailed,
+    }
+    .code();
+    JsonRpcError {
+        code,
+        message: error.to_string(),
+        data: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rpc_protocol::{ParameterSpec, ProtocolVersion, ValidationRule};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn transfer_metadata() -> MethodMetadata {
+        MethodMetadata {
+            name: "test_transfer".to_string(),
+            description: "Transfer funds".to_string(),
+            parameters: vec![ParameterSpec {
+                name: "amount".to_string(),
+                parameter_type: "integer".to_string(),
+                required: true,
+                description: "Amount to transfer".to_string(),
+                default_value: None,
+                validation: Some(ValidationRule {
+                    min_value: Some(1.0),
+                    ..ValidationRule::default()
+                }),
+                schema: None,
+            }],
+            returns: None,
+            deprecated: false,
+            since_version: ProtocolVersion::CURRENT,
+            rate_limit: None,
+            auth_required: false,
+            required_permissions: Vec::new(),
+        }
+    }
+
+    fn router(calls: Arc<AtomicUsize>) -> RpcRouter {
+        let mut router = RpcRouter::new();
+        router
+            .register(transfer_metadata(), move |params| {
+                let calls = calls.clone();
+                async move {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    let amount = params
+                        .as_ref()
+                        .and_then(|params| params.get("amount"))
+                        .and_then(Value::as_u64)
+                        .unwrap_or_default();
+                    if amount > 1_000 {
+                        return Err(RpcError::ServiceUnavailable(
+                            "Insufficient liquidity".to_string(),
+                        ));
+                    }
+                    Ok(serde_json::json!({"sent": amount}))
+                }
+            })
+            .unwrap();
+        router
+    }
+
+    #[tokio::test]
+    async fn test_dispatches_valid_calls() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let router = router(calls.clone());
+
+        let response = router
+            .handle_request(
+                r#"{"jsonrpc":"2.0","method":"test_transfer","params":{"amount":5},"id":1}"#,
+            )
+            .await;
+        let response: JsonRpcResponse = serde_json::from_str(&response).unwrap();
+        assert_eq!(response.result.unwrap()["sent"], 5);
+        assert_eq!(response.id, Some(serde_json::json!(1)));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(router.methods(), vec!["test_transfer".to_string()]);
+        assert!(router.metadata("test_transfer").is_some());
+        // Standard protocol metadata without a handler is not routed
+        assert!(router.metadata("cc_getBlockByHeight").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_validation_runs_before_handler() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let router = router(calls.clone());
+
+        for params in [None, Some(serde_json::json!({"amount": 0}))] {
+            let response = router
+                .dispatch(JsonRpcRequest {
+                    jsonrpc: "2.0".to_string(),
+                    method: "test_transfer".to_string(),
+                    params,
+                    id: Some(serde_json::json!(2)),
+                })
+                .await;
+            assert_eq!(response.error.unwrap().code, -32602);
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_errors_become_json_rpc_errors() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut router = router(calls);
+
+        let response = router
+            .handle_request(
+                r#"{"jsonrpc":"2.0","method":"test_transfer","params":{"amount":5000},"id":3}"#,
+            )
+            .await;
+        let error = serde_json::from_str::<JsonRpcResponse>(&response)
+            .unwrap()
+            .error
+            .unwrap();
+        assert_eq!(
+            (error.code, error.message.as_str()),
+            (
+                RpcErrorCode::ServiceUnavailable.code(),
+                "Insufficient liquidity"
+            )
+        );
+
+        let response = router
+            .handle_request(r#"{"jsonrpc":"2.0","method":"missing","id":4}"#)
+            .await;
+        assert!(response.contains("-32601"));
+        let response = router
+            .handle_request(r#"{"jsonrpc":"1.0","method":"test_transfer","id":5}"#)
+            .await;
+        assert!(response.contains("-32600"));
+        assert!(router.handle_request("{").await.contains("-32700"));
+
+        let duplicate = router.register(transfer_metadata(), |_| async { Ok(Value::Null) });
+        assert!(matches!(duplicate, Err(RpcError::InvalidRequest(_))));
+    }
+}
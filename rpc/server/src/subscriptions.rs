@@ -0,0 +1,502 @@
+//! Subscriptions pushed to persistent connections
+//!
+//! Connections register with a `SubscriptionHub` and receive notifications
+//! through a bounded queue. Publishing never blocks: a connection whose
+//! queue is full has fallen too far behind, so it loses its subscriptions
+//! and is flagged for the transport to close, keeping memory bounded.
+
+use crate::filters::{FilterConfig, FilterRegistry};
+use rpc_errors::RpcErrorCode;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+use tokio::sync::mpsc;
+
+/// Method carrying subscription notifications
+pub const SUBSCRIPTION_NOTIFICATION: &str = "cc_subscription";
+
+/// Subscription error types
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum SubscriptionError {
+    #[error("Invalid subscription: {0}")]
+    Invalid(String),
+
+    #[error("Connection already has the maximum of {0} subscriptions")]
+    LimitReached(usize),
+
+    #[error("Connection is closing")]
+    Closed,
+
+    #[error("Filter {0} not found")]
+    UnknownFilter(String),
+
+    #[error("Server already has the maximum of {0} filters")]
+    FilterLimitReached(usize),
+}
+
+impl SubscriptionError {
+    /// JSON-RPC error code for this error
+    pub fn code(&self) -> RpcErrorCode {
+        match self {
+            SubscriptionError::Invalid(_) => RpcErrorCode::InvalidParams,
+            SubscriptionError::LimitReached(_) => RpcErrorCode::SubscriptionLimitReached,
+            SubscriptionError::Closed => RpcErrorCode::ServerError,
+            SubscriptionError::UnknownFilter(_) => RpcErrorCode::FilterNotFound,
+            SubscriptionError::FilterLimitReached(_) => RpcErrorCode::SubscriptionLimitReached,
+        }
+    }
+}
+
+/// Events a connection can subscribe to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubscriptionKind {
+    NewHeads,
+    PendingTransactions,
+    Logs(LogFilter),
+}
+
+impl SubscriptionKind {
+    /// Parse `cc_subscribe` params: `["newHeads"]`, `["pendingTransactions"]`
+    /// or `["logs", filter]`
+    pub fn from_params(params: Option<&Value>) -> Result<Self, SubscriptionError> {
+        let params = params
+            .and_then(Value::as_array)
+            .ok_or_else(|| SubscriptionError::Invalid("Expected [kind, options?]".to_string()))?;
+
+        match (params.first().and_then(Value::as_str), params.get(1)) {
+            (Some("newHeads"), _) => Ok(SubscriptionKind::NewHeads),
+            (Some("pendingTransactions"), _) => Ok(SubscriptionKind::PendingTransactions),
+            (Some("logs"), filter) => Ok(SubscriptionKind::Logs(
+                filter
+                    .map(LogFilter::from_value)
+                    .transpose()?
+                    .unwrap_or_default(),
+            )),
+            (Some(kind), _) => Err(SubscriptionError::Invalid(format!(
+                "Unknown subscription kind '{}'",
+                kind
+            ))),
+            (None, _) => Err(SubscriptionError::Invalid(
+                "Missing subscription kind".to_string(),
+            )),
+        }
+    }
+}
+
+/// Which logs a `logs` subscription receives
+///
+/// Matches logs emitted by any of `addresses` (all when empty) whose topics
+/// match position by position; `None` matches any topic and a list matches
+/// any of its entries. Comparisons ignore case.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogFilter {
+    pub addresses: Vec<String>,
+    pub topics: Vec<Option<Vec<String>>>,
+}
+
+impl LogFilter {
+    /// Parse a filter object such as `{"address": "0x..", "topics": [null, ["0x..", "0x.."]]}`
+    pub fn from_value(value: &Value) -> Result<Self, SubscriptionError> {
+        let object = value.as_object().ok_or_else(|| {
+            SubscriptionError::Invalid("Log filter must be an object".to_string())
+        })?;
+
+        let addresses = match object.get("address") {
+            None | Some(Value::Null) => Vec::new(),
+            Some(value) => strings(value, "address")?,
+        };
+        let topics = match object.get("topics") {
+            None | Some(Value::Null) => Vec::new(),
+            Some(Value::Array(topics)) => topics
+                .iter()
+                .map(|topic| match topic {
+                    Value::Null => Ok(None),
+                    topic => strings(topic, "topic").map(Some),
+                })
+                .collect::<Result<_, _>>()?,
+            Some(_) => {
+                return Err(SubscriptionError::Invalid(
+                    "Log filter topics must be an array".to_string(),
+                ))
+            }
+        };
+        Ok(Self { addresses, topics })
+    }
+
+    /// Whether a log with `address` and `topics` fields matches the filter
+    pub fn matches(&self, log: &Value) -> bool {
+        let address = log
+            .get("address")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if !self.addresses.is_empty() && !self.addresses.contains(&address) {
+            return false;
+        }
+
+        let topics = log.get("topics").and_then(Value::as_array);
+        self.topics.iter().enumerate().all(|(i, wanted)| {
+            let Some(wanted) = wanted else {
+                return true;
+            };
+            topics
+                .and_then(|topics| topics.get(i))
+                .and_then(Value::as_str)
+                .is_some_and(|topic| wanted.contains(&topic.to_ascii_lowercase()))
+        })
+    }
+}
+
+/// A string or list of strings, lowercased
+fn strings(value: &Value, field: &str) -> Result<Vec<String>, SubscriptionError> {
+    let invalid = || {
+        SubscriptionError::Invalid(format!(
+            "Log filter {} must be a string or list of strings",
+            field
+        ))
+    };
+    match value {
+        Value::String(s) => Ok(vec![s.to_ascii_lowercase()]),
+        Value::Array(values) => values
+            .iter()
+            .map(|v| v.as_str().map(str::to_ascii_lowercase).ok_or_else(invalid))
+            .collect(),
+        _ => Err(invalid()),
+    }
+}
+
+/// Subscription limits
+#[derive(Debug, Clone)]
+pub struct SubscriptionConfig {
+    /// Maximum active subscriptions per connection
+    pub max_subscriptions_per_connection: usize,
+
+    /// Notifications queued per connection before it is dropped as too slow
+    pub queue_capacity: usize,
+}
+
+impl Default for SubscriptionConfig {
+    fn default() -> Self {
+        Self {
+            max_subscriptions_per_connection: 32,
+            queue_capacity: 1024,
+        }
+    }
+}
+
+/// A persistent connection's side of its subscriptions
+pub struct Connection {
+    sender: mpsc::Sender<String>,
+    subscriptions: Mutex<HashSet<String>>,
+    overflowed: AtomicBool,
+}
+
+impl Connection {
+    /// Whether the connection fell behind and should be closed
+    pub fn is_overflowed(&self) -> bool {
+        self.overflowed.load(Ordering::Acquire)
+    }
+
+    /// Number of active subscriptions
+    pub fn subscription_count(&self) -> usize {
+        self.subscriptions.lock().unwrap().len()
+    }
+}
+
+struct Subscription {
+    kind: SubscriptionKind,
+    connection: Arc<Connection>,
+}
+
+/// Routes published chain events to subscribed connections and filters
+pub struct SubscriptionHub {
+    config: SubscriptionConfig,
+    filters: FilterRegistry,
+    next_id: AtomicU64,
+    subscriptions: Mutex<HashMap<String, Subscription>>,
+    dropped_connections: AtomicU64,
+    head_height: Mutex<Option<u64>>,
+}
+
+impl SubscriptionHub {
+    /// Create a hub with default limits
+    pub fn new() -> Self {
+        Self::with_config(SubscriptionConfig::default())
+    }
+
+    /// Create a hub with custom limits
+    pub fn with_config(config: SubscriptionConfig) -> Self {
+        Self {
+            config,
+            filters: FilterRegistry::default(),
+            next_id: AtomicU64::new(1),
+            subscriptions: Mutex::new(HashMap::new()),
+            dropped_connections: AtomicU64::new(0),
+            head_height: Mutex::new(None),
+        }
+    }
+
+    /// Replace the limits on polled filters
+    pub fn with_filter_config(mut self, config: FilterConfig) -> Self {
+        self.filters = FilterRegistry::new(config);
+        self
+    }
+
+    /// Filters polled with `cc_getFilterChanges`
+    pub fn filters(&self) -> &FilterRegistry {
+        &self.filters
+    }
+
+    /// Height of the last head published, if it carried one
+    pub fn head_height(&self) -> Option<u64> {
+        *self.head_height.lock().unwrap()
+    }
+
+    /// Register a connection, returning it and the queue its notifications arrive on
+    pub fn connect(&self) -> (Arc<Connection>, mpsc::Receiver<String>) {
+        let (sender, receiver) = mpsc::channel(self.config.queue_capacity.max(1));
+        let connection = Arc::new(Connection {
+            sender,
+            subscriptions: Mutex::new(HashSet::new()),
+            overflowed: AtomicBool::new(false),
+        });
+        (connection, receiver)
+    }
+
+    /// Subscribe a connection, returning the subscription id
+    pub fn subscribe(
+        &self,
+        connection: &Arc<Connection>,
+        kind: SubscriptionKind,
+    ) -> Result<String, SubscriptionError> {
+        if connection.is_overflowed() {
+            return Err(SubscriptionError::Closed);
+        }
+
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        let mut owned = connection.subscriptions.lock().unwrap();
+        if owned.len() >= self.config.max_subscriptions_per_connection {
+            return Err(SubscriptionError::LimitReached(
+                self.config.max_subscriptions_per_connection,
+            ));
+        }
+
+        let id = format!("0x{:x}", self.next_id.fetch_add(1, Ordering::Relaxed));
+        owned.insert(id.clone());
+        subscriptions.insert(
+            id.clone(),
+            Subscription {
+                kind,
+                connection: connection.clone(),
+            },
+        );
+        Ok(id)
+    }
+
+    /// Cancel one of the connection's own subscriptions, returning whether it existed
+    pub fn unsubscribe(&self, connection: &Connection, id: &str) -> bool {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        let removed = connection.subscriptions.lock().unwrap().remove(id);
+        if removed {
+            subscriptions.remove(id);
+        }
+        removed
+    }
+
+    /// Cancel all of a connection's subscriptions
+    pub fn disconnect(&self, connection: &Connection) {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        for id in connection.subscriptions.lock().unwrap().drain() {
+            subscriptions.remove(&id);
+        }
+    }
+
+    /// Notify `newHeads` subscribers, returning how many were notified
+    ///
+    /// Like the other `publish_*` methods, this also buffers the event for
+    /// matching filters, which are not counted. The header's `height` (or
+    /// `number`) becomes the hub's `head_height`.
+    pub fn publish_new_head(&self, header: &Value) -> usize {
+        if let Some(height) = header
+            .get("height")
+            .or_else(|| header.get("number"))
+            .and_then(Value::as_u64)
+        {
+            *self.head_height.lock().unwrap() = Some(height);
+        }
+        self.publish(header, |kind| *kind == SubscriptionKind::NewHeads)
+    }
+
+    /// Notify `pendingTransactions` subscribers, returning how many were notified
+    pub fn publish_pending_transaction(&self, transaction: &Value) -> usize {
+        self.publish(transaction, |kind| {
+            *kind == SubscriptionKind::PendingTransactions
+        })
+    }
+
+    /// Notify `logs` subscribers whose filter matches, returning how many were notified
+    pub fn publish_log(&self, log: &Value) -> usize {
+        self.publish(
+            log,
+            |kind| matches!(kind, SubscriptionKind::Logs(filter) if filter.matches(log)),
+        )
+    }
+
+    /// Number of active subscriptions across all connections
+    pub fn subscription_count(&self) -> usize {
+        self.subscriptions.lock().unwrap().len()
+    }
+
+    /// Number of connections dropped for falling behind
+    pub fn dropped_connections(&self) -> u64 {
+        self.dropped_connections.load(Ordering::Relaxed)
+    }
+
+    fn publish(&self, event: &Value, wanted: impl Fn(&SubscriptionKind) -> bool) -> usize {
+        self.filters.record(event, &wanted);
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        let mut delivered = 0;
+        let mut overflowed = Vec::new();
+
+        for (id, subscription) in subscriptions.iter() {
+            if !wanted(&subscription.kind) || subscription.connection.is_overflowed() {
+                continue;
+            }
+            let notification = json!({
+                "jsonrpc": "2.0",
+                "method": SUBSCRIPTION_NOTIFICATION,
+                "params": {"subscription": id, "result": event},
+            });
+            match subscription
+                .connection
+                .sender
+                .try_send(notification.to_string())
+            {
+                Ok(()) => delivered += 1,
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    subscription
+                        .connection
+                        .overflowed
+                        .store(true, Ordering::Release);
+                    self.dropped_connections.fetch_add(1, Ordering::Relaxed);
+                    overflowed.push(subscription.connection.clone());
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => {
+                    overflowed.push(subscription.connection.clone())
+                }
+            }
+        }
+
+        for connection in overflowed {
+            for id in connection.subscriptions.lock().unwrap().drain() {
+                subscriptions.remove(&id);
+            }
+        }
+        delivered
+    }
+}
+
+impl Default for SubscriptionHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_params_and_log_filters() {
+        let parse = |params: Value| SubscriptionKind::from_params(Some(&params));
+        assert_eq!(
+            parse(json!(["newHeads"])).unwrap(),
+            SubscriptionKind::NewHeads
+        );
+        assert_eq!(
+            parse(json!(["pendingTransactions"])).unwrap(),
+            SubscriptionKind::PendingTransactions
+        );
+        assert!(matches!(
+            parse(json!(["blocks"])),
+            Err(SubscriptionError::Invalid(_))
+        ));
+        assert!(SubscriptionKind::from_params(None).is_err());
+
+        let SubscriptionKind::Logs(filter) = parse(json!([
+            "logs",
+            {"address": "0xABC", "topics": [null, ["0x01", "0x02"]]}
+        ]))
+        .unwrap() else {
+            panic!("expected a logs subscription");
+        };
+        assert!(filter.matches(&json!({"address": "0xabc", "topics": ["0xff", "0x02"]})));
+        assert!(!filter.matches(&json!({"address": "0xabc", "topics": ["0xff", "0x03"]})));
+        assert!(!filter.matches(&json!({"address": "0xdef", "topics": ["0xff", "0x01"]})));
+        assert!(!filter.matches(&json!({"address": "0xabc", "topics": ["0xff"]})));
+        assert!(parse(json!(["logs", {"topics": "0x01"}])).is_err());
+    }
+
+    #[test]
+    fn test_limits_and_ownership() {
+        let hub = SubscriptionHub::with_config(SubscriptionConfig {
+            max_subscriptions_per_connection: 2,
+            queue_capacity: 16,
+        });
+        let (alice, mut alice_rx) = hub.connect();
+        let (bob, _bob_rx) = hub.connect();
+
+        let heads = hub.subscribe(&alice, SubscriptionKind::NewHeads).unwrap();
+        hub.subscribe(&alice, SubscriptionKind::PendingTransactions)
+            .unwrap();
+        assert_eq!(
+            hub.subscribe(&alice, SubscriptionKind::NewHeads),
+            Err(SubscriptionError::LimitReached(2))
+        );
+
+        // Connections can only cancel their own subscriptions
+        assert!(!hub.unsubscribe(&bob, &heads));
+        assert_eq!(hub.publish_new_head(&json!({"number": 1})), 1);
+        let notification: Value = serde_json::from_str(&alice_rx.try_recv().unwrap()).unwrap();
+        assert_eq!(notification["params"]["subscription"], heads.as_str());
+        assert_eq!(notification["params"]["result"]["number"], 1);
+
+        assert!(hub.unsubscribe(&alice, &heads));
+        assert_eq!(hub.publish_new_head(&json!({"number": 2})), 0);
+        assert_eq!(hub.head_height(), Some(2));
+        hub.disconnect(&alice);
+        assert_eq!(hub.subscription_count(), 0);
+        assert_eq!(alice.subscription_count(), 0);
+    }
+
+    #[test]
+    fn test_slow_connection_is_dropped() {
+        let hub = SubscriptionHub::with_config(SubscriptionConfig {
+            max_subscriptions_per_connection: 4,
+            queue_capacity: 2,
+        });
+        let (slow, _slow_rx) = hub.connect();
+        let (fast, mut fast_rx) = hub.connect();
+        hub.subscribe(&slow, SubscriptionKind::PendingTransactions)
+            .unwrap();
+        hub.subscribe(&slow, SubscriptionKind::NewHeads).unwrap();
+        hub.subscribe(&fast, SubscriptionKind::PendingTransactions)
+            .unwrap();
+
+        for nonce in 0..3 {
+            hub.publish_pending_transaction(&json!({"nonce": nonce}));
+            fast_rx.try_recv().unwrap();
+        }
+
+        assert!(slow.is_overflowed());
+        assert!(!fast.is_overflowed());
+        assert_eq!(hub.dropped_connections(), 1);
+        assert_eq!(hub.subscription_count(), 1);
+        assert_eq!(
+            hub.subscribe(&slow, SubscriptionKind::NewHeads),
+            Err(SubscriptionError::Closed)
+        );
+    }
+}
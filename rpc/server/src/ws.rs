@@ -0,0 +1,616 @@
+//! WebSocket transport for the JSON-RPC server
+//!
+//! Implements the RFC 6455 handshake and framing on top of hyper's
+//! connection upgrade. Each text or binary message is a JSON-RPC request
+//! handled like an HTTP body; subscription notifications are interleaved
+//! with responses on the same socket.
+
+use crate::http::HttpRpcServer;
+use crate::rate_limit::ClientId;
+use crate::subscriptions::Connection;
+use axum::body::Body;
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use hyper_util::rt::TokioIo;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+/// GUID appended to the client key when computing `Sec-WebSocket-Accept`
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Responses and control frames queued for the writer; when full the
+/// reader stops reading requests until the client catches up
+const OUTBOUND_CAPACITY: usize = 64;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xa;
+
+const CLOSE_NORMAL: u16 = 1000;
+const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+const CLOSE_POLICY_VIOLATION: u16 = 1008;
+const CLOSE_TOO_BIG: u16 = 1009;
+
+/// A single WebSocket frame
+#[derive(Debug, Clone, PartialEq)]
+struct Frame {
+    fin: bool,
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+impl Frame {
+    fn new(opcode: u8, payload: Vec<u8>) -> Self {
+        Self {
+            fin: true,
+            opcode,
+            payload,
+        }
+    }
+
+    fn close(code: u16, reason: &str) -> Self {
+        let mut payload = code.to_be_bytes().to_vec();
+        payload.extend_from_slice(reason.as_bytes());
+        Self::new(OPCODE_CLOSE, payload)
+    }
+}
+
+/// Why a connection is being closed
+#[derive(Debug)]
+enum FrameError {
+    /// The connection dropped without a close frame
+    Io,
+    Protocol(&'static str),
+    TooBig,
+}
+
+impl From<io::Error> for FrameError {
+    fn from(_: io::Error) -> Self {
+        FrameError::Io
+    }
+}
+
+/// Validate an upgrade request and switch the connection to WebSocket
+pub(crate) async fn upgrade(
+    State(http): State<HttpRpcServer>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    mut request: Request,
+) -> Response {
+    let key = match handshake_key(request.headers()) {
+        Ok(key) => key,
+        Err(status) => return (status, [(header::SEC_WEBSOCKET_VERSION, "13")]).into_response(),
+    };
+
+    let addr = connect_info.map(|ConnectInfo(addr)| addr);
+    let client = ClientId::from_request(request.headers(), addr, &http.server().config);
+    let on_upgrade = hyper::upgrade::on(&mut request);
+    tokio::spawn(async move {
+        if let Ok(upgraded) = on_upgrade.await {
+            run_connection(http, client, TokioIo::new(upgraded)).await;
+        }
+    });
+
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+    let headers = response.headers_mut();
+    headers.insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+    headers.insert(header::CONNECTION, HeaderValue::from_static("upgrade"));
+    if let Ok(accept) = HeaderValue::from_str(&accept_key(&key)) {
+        headers.insert(header::SEC_WEBSOCKET_ACCEPT, accept);
+    }
+    response
+}
+
+/// The client's `Sec-WebSocket-Key`, or the status rejecting the handshake
+fn handshake_key(headers: &HeaderMap) -> Result<String, StatusCode> {
+    let has_token = |name: header::HeaderName, token: &str| {
+        headers
+            .get_all(name)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|value| value.trim().eq_ignore_ascii_case(token))
+    };
+
+    if !has_token(header::CONNECTION, "upgrade") || !has_token(header::UPGRADE, "websocket") {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if headers
+        .get(header::SEC_WEBSOCKET_VERSION)
+        .map(HeaderValue::as_bytes)
+        != Some(b"13")
+    {
+        return Err(StatusCode::UPGRADE_REQUIRED);
+    }
+    headers
+        .get(header::SEC_WEBSOCKET_KEY)
+        .and_then(|key| key.to_str().ok())
+        .map(str::to_string)
+        .ok_or(StatusCode::BAD_REQUEST)
+}
+
+/// Serve JSON-RPC over an upgraded connection until either side closes it
+async fn run_connection<S>(http: HttpRpcServer, client: ClientId, stream: S)
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (mut reader, writer) = tokio::io::split(stream);
+    let (connection, notifications) = http.subscriptions().connect();
+    let (outbound, outbound_rx) = mpsc::channel(OUTBOUND_CAPACITY);
+    let mut writer_task = tokio::spawn(write_loop(
+        writer,
+        outbound_rx,
+        notifications,
+        connection.clone(),
+    ));
+
+    let max_message_size = http.capabilities().max_request_size;
+    let mut writer_finished = false;
+    loop {
+        let message = tokio::select! {
+            message = read_message(&mut reader, max_message_size, &outbound) => message,
+            _ = &mut writer_task => {
+                writer_finished = true;
+                break;
+            }
+        };
+        let close = match message {
+            Ok(Some(payload)) => match http
+                .process(&payload, &client, Some(&connection), None)
+                .await
+            {
+                Some(response) => outbound
+                    .send(Frame::new(OPCODE_TEXT, response.into_bytes()))
+                    .await
+                    .is_err(),
+                None => false,
+            },
+            Ok(None) => {
+                let _ = outbound.send(Frame::close(CLOSE_NORMAL, "")).await;
+                true
+            }
+            Err(FrameError::Io) => true,
+            Err(FrameError::Protocol(reason)) => {
+                let _ = outbound
+                    .send(Frame::close(CLOSE_PROTOCOL_ERROR, reason))
+                    .await;
+                true
+            }
+            Err(FrameError::TooBig) => {
+                let _ = outbound
+                    .send(Frame::close(CLOSE_TOO_BIG, "Message too big"))
+                    .await;
+                true
+            }
+        };
+        if close {
+            break;
+        }
+    }
+
+    http.subscriptions().disconnect(&connection);
+    drop(outbound);
+    if !writer_finished {
+        let _ = writer_task.await;
+    }
+}
+
+/// Write responses and notifications until the reader finishes or the
+/// connection falls too far behind its subscriptions
+async fn write_loop<W: AsyncWrite + Unpin>(
+    mut writer: W,
+    mut outbound: mpsc::Receiver<Frame>,
+    mut notifications: mpsc::Receiver<String>,
+    connection: Arc<Connection>,
+) {
+    loop {
+        let frame = tokio::select! {
+            biased;
+            frame = outbound.recv() => match frame {
+                Some(frame) => frame,
+                None => break,
+            },
+            Some(notification) = notifications.recv() => {
+                if connection.is_overflowed() {
+                    let reason = "Subscription queue full";
+                    let _ = write_frame(&mut writer, &Frame::close(CLOSE_POLICY_VIOLATION, reason), None).await;
+                    break;
+                }
+                Frame::new(OPCODE_TEXT, notification.into_bytes())
+            }
+        };
+        let closing = frame.opcode == OPCODE_CLOSE;
+        if write_frame(&mut writer, &frame, None).await.is_err() || closing {
+            break;
+        }
+    }
+}
+
+/// Read the next complete data message, answering pings along the way;
+/// `None` means the client closed the connection
+async fn read_message<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    max_size: usize,
+    outbound: &mpsc::Sender<Frame>,
+) -> Result<Option<Vec<u8>>, FrameError> {
+    let mut message: Option<Vec<u8>> = None;
+    loop {
+        let frame = read_frame(reader, max_size, true).await?;
+        match frame.opcode {
+            OPCODE_PING => {
+                let _ = outbound.send(Frame::new(OPCODE_PONG, frame.payload)).await;
+            }
+            OPCODE_PONG => {}
+            OPCODE_CLOSE => return Ok(None),
+            OPCODE_TEXT | OPCODE_BINARY if message.is_none() => {
+                if frame.fin {
+                    return Ok(Some(frame.payload));
+                }
+                message = Some(frame.payload);
+            }
+            OPCODE_CONTINUATION => {
+                let Some(partial) = message.as_mut() else {
+                    return Err(FrameError::Protocol("Unexpected continuation frame"));
+                };
+                if partial.len() + frame.payload.len() > max_size {
+                    return Err(FrameError::TooBig);
+                }
+                partial.extend_from_slice(&frame.payload);
+                if frame.fin {
+                    return Ok(message);
+                }
+            }
+            OPCODE_TEXT | OPCODE_BINARY => {
+                return Err(FrameError::Protocol("Expected a continuation frame"))
+            }
+            _ => return Err(FrameError::Protocol("Unknown opcode")),
+        }
+    }
+}
+
+/// Read one frame; clients must mask their frames, servers must not
+async fn read_frame<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    max_size: usize,
+    expect_masked: bool,
+) -> Result<Frame, FrameError> {
+    let mut head = [0u8; 2];
+    reader.read_exact(&mut head).await?;
+    if head[0] & 0x70 != 0 {
+        return Err(FrameError::Protocol("Reserved bits set"));
+    }
+    let fin = head[0] & 0x80 != 0;
+    let opcode = head[0] & 0x0f;
+    let masked = head[1] & 0x80 != 0;
+    let len = match head[1] & 0x7f {
+        126 => u64::from(reader.read_u16().await?),
+        127 => reader.read_u64().await?,
+        len => u64::from(len),
+    };
+
+    if opcode >= OPCODE_CLOSE && (!fin || len > 125) {
+        return Err(FrameError::Protocol("Invalid control frame"));
+    }
+    if masked != expect_masked {
+        return Err(FrameError::Protocol("Unexpected frame masking"));
+    }
+    if len > max_size as u64 {
+        return Err(FrameError::TooBig);
+    }
+
+    let mut mask = [0u8; 4];
+    if masked {
+        reader.read_exact(&mut mask).await?;
+    }
+    let mut payload = vec![0u8; len as usize];
+    reader.read_exact(&mut payload).await?;
+    if masked {
+        apply_mask(&mut payload, mask);
+    }
+    Ok(Frame {
+        fin,
+        opcode,
+        payload,
+    })
+}
+
+/// Write one frame, masked with `mask` if given
+async fn write_frame<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    frame: &Frame,
+    mask: Option<[u8; 4]>,
+) -> io::Result<()> {
+    let mut bytes = Vec::with_capacity(frame.payload.len() + 14);
+    bytes.push(u8::from(frame.fin) << 7 | frame.opcode);
+    let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+    match frame.payload.len() {
+        len if len < 126 => bytes.push(mask_bit | len as u8),
+        len if len <= usize::from(u16::MAX) => {
+            bytes.push(mask_bit | 126);
+            bytes.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            bytes.push(mask_bit | 127);
+            bytes.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    let start = bytes.len();
+    bytes.extend_from_slice(&frame.payload);
+    if let Some(mask) = mask {
+        bytes.splice(start..start, mask);
+        apply_mask(&mut bytes[start + 4..], mask);
+    }
+    writer.write_all(&bytes).await?;
+    writer.flush().await
+}
+
+fn apply_mask(payload: &mut [u8], mask: [u8; 4]) {
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+}
+
+/// `Sec-WebSocket-Accept` value for a client key
+fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{}{}", key, ACCEPT_GUID).as_bytes()))
+}
+
+/// SHA-1, needed only for the handshake
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut words = [0u32; 80];
+        for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        for i in 16..80 {
+            words[i] = (words[i - 3] ^ words[i - 8] ^ words[i - 14] ^ words[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (i, word) in words.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a827999),
+                20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let next = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = next;
+        }
+        for (word, value) in state.iter_mut().zip([a, b, c, d, e]) {
+            *word = word.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (bytes, word) in digest.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+/// Standard padded base64
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &byte)| {
+            bits | u32::from(byte) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subscriptions::SubscriptionConfig;
+    use crate::{BlockchainRpcMethods, RpcServer, RpcServerConfig, SubscriptionHub};
+    use rpc_protocol::ProtocolCapabilities;
+    use serde_json::{json, Value};
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// Minimal client: handshake, then masked frames
+    struct Client {
+        stream: TcpStream,
+    }
+
+    impl Client {
+        async fn connect(http: HttpRpcServer) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(http.serve(listener));
+
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            let request = format!(
+                "GET / HTTP/1.1\r\nHost: {}\r\nConnection: Upgrade\r\nUpgrade: websocket\r\n\
+                 Sec-WebSocket-Version: 13\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+                addr
+            );
+            stream.write_all(request.as_bytes()).await.unwrap();
+
+            let mut head = Vec::new();
+            while !head.ends_with(b"\r\n\r\n") {
+                head.push(stream.read_u8().await.unwrap());
+            }
+            let head = String::from_utf8(head).unwrap().to_ascii_lowercase();
+            assert!(head.starts_with("http/1.1 101"));
+            assert!(head.contains("sec-websocket-accept: s3pplmbitxaq9kygzzhzrbk+xoo="));
+            Self { stream }
+        }
+
+        async fn send(&mut self, opcode: u8, payload: &[u8]) {
+            let frame = Frame::new(opcode, payload.to_vec());
+            write_frame(&mut self.stream, &frame, Some([7, 3, 9, 1]))
+                .await
+                .unwrap();
+        }
+
+        async fn call(&mut self, request: Value) -> Value {
+            self.send(OPCODE_TEXT, request.to_string().as_bytes()).await;
+            self.receive().await
+        }
+
+        async fn receive(&mut self) -> Value {
+            let frame = read_frame(&mut self.stream, usize::MAX, false)
+                .await
+                .unwrap();
+            assert_eq!(frame.opcode, OPCODE_TEXT);
+            serde_json::from_slice(&frame.payload).unwrap()
+        }
+    }
+
+    fn http_server(hub: Arc<SubscriptionHub>) -> HttpRpcServer {
+        let server = RpcServer::new(RpcServerConfig::default());
+        server
+            .register_method("ping", BlockchainRpcMethods::ping_handler())
+            .unwrap();
+        HttpRpcServer::new(server).with_subscriptions(hub)
+    }
+
+    #[test]
+    fn test_accept_key() {
+        // Example from RFC 6455 section 1.3
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        assert_eq!(base64(b"ab"), "YWI=");
+    }
+
+    #[tokio::test]
+    async fn test_calls_and_subscriptions() {
+        let hub = Arc::new(SubscriptionHub::new());
+        let mut client = Client::connect(http_server(hub.clone())).await;
+
+        let response = client
+            .call(json!({"jsonrpc": "2.0", "method": "ping", "id": 1}))
+            .await;
+        assert_eq!(response["result"]["status"], "ok");
+
+        let negotiate = json!({"jsonrpc": "2.0", "method": "cc_negotiate", "params": {
+            "versions": [{"major": 1, "minor": 0, "patch": 0}]
+        }, "id": 9});
+        assert_eq!(
+            client.call(negotiate).await["result"]["encoding"],
+            "identity"
+        );
+
+        let response = client
+            .call(json!({"jsonrpc": "2.0", "method": "cc_subscribe", "params": ["newHeads"], "id": 2}))
+            .await;
+        let subscription = response["result"].as_str().unwrap().to_string();
+        assert_eq!(hub.publish_new_head(&json!({"number": 7})), 1);
+        let notification = client.receive().await;
+        assert_eq!(notification["method"], "cc_subscription");
+        assert_eq!(
+            notification["params"]["subscription"],
+            subscription.as_str()
+        );
+        assert_eq!(notification["params"]["result"]["number"], 7);
+
+        // Fragmented message with a ping in between
+        let request = json!({"jsonrpc": "2.0", "method": "cc_unsubscribe", "params": [subscription], "id": 3})
+            .to_string();
+        let (first, rest) = request.as_bytes().split_at(10);
+        let fragment = Frame {
+            fin: false,
+            opcode: OPCODE_TEXT,
+            payload: first.to_vec(),
+        };
+        write_frame(&mut client.stream, &fragment, Some([1, 2, 3, 4]))
+            .await
+            .unwrap();
+        client.send(OPCODE_PING, b"hi").await;
+        client.send(OPCODE_CONTINUATION, rest).await;
+        let pong = read_frame(&mut client.stream, usize::MAX, false)
+            .await
+            .unwrap();
+        assert_eq!((pong.opcode, pong.payload), (OPCODE_PONG, b"hi".to_vec()));
+        assert_eq!(client.receive().await["result"], true);
+        assert_eq!(hub.subscription_count(), 0);
+
+        client.send(OPCODE_CLOSE, &CLOSE_NORMAL.to_be_bytes()).await;
+        let close = read_frame(&mut client.stream, usize::MAX, false)
+            .await
+            .unwrap();
+        assert_eq!(close.opcode, OPCODE_CLOSE);
+    }
+
+    #[tokio::test]
+    async fn test_limits_close_or_reject() {
+        let hub = Arc::new(SubscriptionHub::with_config(SubscriptionConfig {
+            max_subscriptions_per_connection: 1,
+            queue_capacity: 1,
+        }));
+        let capabilities = ProtocolCapabilities {
+            max_request_size: 256,
+            ..ProtocolCapabilities::default()
+        };
+        let mut client =
+            Client::connect(http_server(hub.clone()).with_capabilities(capabilities.clone())).await;
+
+        let subscribe = json!({"jsonrpc": "2.0", "method": "cc_subscribe", "params": ["pendingTransactions"], "id": 1});
+        assert!(client.call(subscribe.clone()).await["result"].is_string());
+        let response = client.call(subscribe).await;
+        assert_eq!(
+            response["error"]["code"],
+            rpc_errors::RpcErrorCode::SubscriptionLimitReached.code()
+        );
+
+        // The client never reads, so its queue overflows and it is dropped
+        for nonce in 0..8 {
+            hub.publish_pending_transaction(&json!({"nonce": nonce}));
+        }
+        assert_eq!(hub.dropped_connections(), 1);
+        assert_eq!(hub.subscription_count(), 0);
+        loop {
+            let frame = read_frame(&mut client.stream, usize::MAX, false)
+                .await
+                .unwrap();
+            if frame.opcode == OPCODE_CLOSE {
+                assert_eq!(frame.payload[..2], CLOSE_POLICY_VIOLATION.to_be_bytes());
+                break;
+            }
+        }
+
+        // Oversized messages close the connection
+        let mut client = Client::connect(http_server(hub).with_capabilities(capabilities)).await;
+        client.send(OPCODE_TEXT, &[b' '; 512]).await;
+        let close = read_frame(&mut client.stream, usize::MAX, false)
+            .await
+            .unwrap();
+        assert_eq!(close.payload[..2], CLOSE_TOO_BIG.to_be_bytes());
+    }
+}